@@ -0,0 +1,7 @@
+-- Radio station table: stores saved internet radio (Shoutcast/Icecast) streams
+CREATE TABLE IF NOT EXISTS radio_station (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    url TEXT NOT NULL UNIQUE,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
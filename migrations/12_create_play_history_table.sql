@@ -0,0 +1,9 @@
+-- Play_history table: records every time a track is played to the end
+CREATE TABLE IF NOT EXISTS play_history (
+    id INTEGER PRIMARY KEY,
+    track_id INTEGER NOT NULL,
+    played_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (track_id) REFERENCES track(id)
+);
+
+CREATE INDEX IF NOT EXISTS play_history_track_id ON play_history(track_id);
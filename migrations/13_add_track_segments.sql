@@ -0,0 +1,6 @@
+-- Add segment fields to track table for tracks that are part of a larger file, such as the tracks
+-- of a cue sheet. file is the audio file to play, start_ms and end_ms are the bounds of the track
+-- within it (end_ms is NULL when the track plays to the end of the file).
+ALTER TABLE track ADD COLUMN file TEXT;
+ALTER TABLE track ADD COLUMN start_ms INTEGER;
+ALTER TABLE track ADD COLUMN end_ms INTEGER;
@@ -0,0 +1,3 @@
+-- Plays used to be kept when their track was removed from the library, so a track that was later
+-- given the same id would inherit them. Remove the plays that no longer have a track.
+DELETE FROM play_history WHERE track_id NOT IN (SELECT id FROM track);
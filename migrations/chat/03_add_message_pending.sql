@@ -0,0 +1,3 @@
+-- Databases created before the migrations were recorded may already have this column, in which
+-- case the migration is skipped as already applied.
+ALTER TABLE messages ADD COLUMN pending INTEGER NOT NULL DEFAULT 0;
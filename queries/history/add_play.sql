@@ -0,0 +1,2 @@
+INSERT INTO play_history (track_id)
+    VALUES($1);
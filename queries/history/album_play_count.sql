@@ -0,0 +1,4 @@
+SELECT COUNT(*)
+FROM play_history
+JOIN track ON track.id = play_history.track_id
+WHERE track.album_id = $1;
@@ -0,0 +1,5 @@
+SELECT play_history.track_id, COUNT(*)
+FROM play_history
+JOIN track ON track.id = play_history.track_id
+WHERE track.album_id = $1
+GROUP BY play_history.track_id;
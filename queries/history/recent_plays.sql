@@ -0,0 +1,6 @@
+SELECT track.*
+FROM play_history
+JOIN track ON track.id = play_history.track_id
+GROUP BY play_history.track_id
+ORDER BY MAX(play_history.id) DESC
+LIMIT $1;
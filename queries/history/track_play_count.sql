@@ -0,0 +1 @@
+SELECT COUNT(*) FROM play_history WHERE track_id = $1;
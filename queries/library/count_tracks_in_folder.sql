@@ -0,0 +1 @@
+SELECT COUNT(*) FROM track WHERE substr(location, 1, length($1)) = $1;
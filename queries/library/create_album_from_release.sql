@@ -0,0 +1,5 @@
+INSERT INTO album (title, title_sortable, artist_id, release_date, mbid)
+    VALUES ($1, $2, NULLIF($3, 0), NULLIF($4, 0), $5)
+    ON CONFLICT (title, artist_id, mbid) DO UPDATE SET
+        release_date = COALESCE(EXCLUDED.release_date, album.release_date)
+    RETURNING id;
@@ -0,0 +1 @@
+SELECT id FROM album WHERE title = $1 AND artist_id = $2;
@@ -3,4 +3,7 @@ SELECT
     p.title,
     a.name
 FROM
-    album p JOIN artist a ON p.artist_id = a.id;
+    album p JOIN artist a ON p.artist_id = a.id
+ORDER BY
+    a.name_sortable COLLATE NOCASE ASC,
+    p.title_sortable COLLATE NOCASE ASC;
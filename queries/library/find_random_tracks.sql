@@ -0,0 +1,3 @@
+SELECT id, album_id, location, file, start_ms, end_ms FROM track
+ORDER BY RANDOM()
+LIMIT $1;
@@ -1,3 +1,5 @@
 SELECT * FROM track
 WHERE album_id = $1
-ORDER BY disc_number ASC, track_number ASC;
+ORDER BY
+    CASE WHEN disc_number IS NULL OR disc_number < 1 THEN 1 ELSE disc_number END ASC,
+    track_number ASC;
@@ -0,0 +1 @@
+SELECT id, title, title_sortable FROM album;
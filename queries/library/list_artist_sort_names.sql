@@ -0,0 +1 @@
+SELECT id, name, name_sortable FROM artist;
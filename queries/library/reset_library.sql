@@ -0,0 +1,15 @@
+BEGIN;
+DROP TABLE IF EXISTS play_history;
+DROP TABLE IF EXISTS playlist_item;
+DROP TABLE IF EXISTS album_path;
+DROP TABLE IF EXISTS track;
+DROP TABLE IF EXISTS album;
+DROP TABLE IF EXISTS artist;
+-- the migrations that only create the playlists and radio stations stay applied, since those
+-- tables are kept, every other migration is applied again to the new tables
+DELETE FROM mrchat_migrations WHERE filename NOT IN (
+    '08_create_playlist_table.sql',
+    '10_insert_default_playlist.sql',
+    '11_create_radio_station_table.sql'
+);
+COMMIT;
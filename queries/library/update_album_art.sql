@@ -0,0 +1,4 @@
+UPDATE album SET
+    image = CASE WHEN length(?) = 0 THEN NULL ELSE ? END,
+    thumb = CASE WHEN length(?) = 0 THEN NULL ELSE ? END
+    WHERE id = ?;
@@ -0,0 +1,4 @@
+UPDATE album SET
+    mbid = $2,
+    release_date = COALESCE(NULLIF($3, 0), release_date)
+    WHERE id = $1;
@@ -0,0 +1 @@
+UPDATE album SET title_sortable = $2 WHERE id = $1;
@@ -0,0 +1 @@
+UPDATE artist SET name_sortable = $2 WHERE id = $1;
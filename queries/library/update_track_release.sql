@@ -0,0 +1,7 @@
+UPDATE track SET
+    title = $1,
+    title_sortable = $2,
+    artist_names = $3,
+    album_id = $4,
+    track_number = COALESCE(NULLIF($5, 0), track_number)
+    WHERE id = $6;
@@ -0,0 +1,2 @@
+INSERT INTO radio_station (name, url)
+    VALUES($1, $2);
@@ -0,0 +1 @@
+DELETE FROM radio_station WHERE id = $1;
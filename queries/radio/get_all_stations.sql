@@ -0,0 +1,7 @@
+SELECT
+    id,
+    name,
+    url,
+    created_at
+FROM radio_station
+ORDER BY created_at DESC;
@@ -0,0 +1 @@
+SELECT location FROM track WHERE folder = $1 AND file IS NOT NULL;
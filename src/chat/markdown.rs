@@ -0,0 +1,381 @@
+//! A small, forgiving Markdown parser for chat messages.
+//!
+//! Assistant replies are rendered while they are still arriving, so the parser is run on every
+//! prefix of a message. Syntax that hasn't been closed yet never changes how the text before it
+//! is laid out: an unterminated code fence is shown as an open code block, and unbalanced
+//! emphasis or code spans are shown as literal text until they are closed.
+
+/// A run of text inside a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inline {
+    Text(String),
+    Code(String),
+    Emphasis(String),
+    Strong(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    Heading {
+        level: u8,
+        content: Vec<Inline>,
+    },
+    Paragraph(Vec<Inline>),
+    ListItem(Vec<Inline>),
+    CodeBlock {
+        language: Option<String>,
+        code: String,
+        /// False if the closing fence hasn't been received yet.
+        closed: bool,
+    },
+}
+
+/// Returns the language of a code fence line, or `None` if the line isn't a code fence.
+fn code_fence(line: &str) -> Option<&str> {
+    line.trim_start().strip_prefix("```").map(str::trim)
+}
+
+fn heading(line: &str) -> Option<(u8, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+
+    line[level..]
+        .strip_prefix(' ')
+        .map(|content| (level as u8, content.trim()))
+}
+
+fn list_item(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+
+    ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))
+}
+
+/// Returns the text between a delimiter at the start of `text` and its closing delimiter, along
+/// with the number of bytes the whole span takes up. Empty spans and spans that start with
+/// whitespace don't count, so that `a * b * c` stays literal.
+fn delimited<'a>(text: &'a str, delimiter: &str) -> Option<(&'a str, usize)> {
+    let inner = text.strip_prefix(delimiter)?;
+
+    if inner.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let end = inner.find(delimiter)?;
+
+    if end == 0 {
+        return None;
+    }
+
+    Some((&inner[..end], end + delimiter.len() * 2))
+}
+
+/// Parses emphasis, strong emphasis and code spans. Delimiters without a match are kept as text.
+pub fn parse_inline(text: &str) -> Vec<Inline> {
+    let mut inlines = Vec::new();
+    let mut literal = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let (span, len) = match c {
+            '`' => match delimited(rest, "`") {
+                Some((code, len)) => (Some(Inline::Code(code.to_string())), len),
+                None => (None, 1),
+            },
+            '*' if rest.starts_with("**") => match delimited(rest, "**") {
+                Some((strong, len)) => (Some(Inline::Strong(strong.to_string())), len),
+                // skip both asterisks, or the second one would start an emphasis span
+                None => (None, 2),
+            },
+            // underscores inside words (such as snake_case) aren't emphasis
+            '*' | '_' if !(c == '_' && literal.ends_with(char::is_alphanumeric)) => {
+                match delimited(rest, if c == '*' { "*" } else { "_" }) {
+                    Some((emphasis, len)) => (Some(Inline::Emphasis(emphasis.to_string())), len),
+                    None => (None, 1),
+                }
+            }
+            _ => (None, c.len_utf8()),
+        };
+
+        match span {
+            Some(span) => {
+                if !literal.is_empty() {
+                    inlines.push(Inline::Text(std::mem::take(&mut literal)));
+                }
+                inlines.push(span);
+            }
+            None => literal.push_str(&rest[..len]),
+        }
+
+        rest = &rest[len..];
+    }
+
+    if !literal.is_empty() {
+        inlines.push(Inline::Text(literal));
+    }
+
+    inlines
+}
+
+/// Parses a (possibly incomplete) Markdown document into blocks.
+pub fn parse(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut code: Option<(Option<String>, Vec<&str>)> = None;
+
+    let flush_paragraph = |paragraph: &mut Vec<&str>, blocks: &mut Vec<Block>| {
+        if !paragraph.is_empty() {
+            blocks.push(Block::Paragraph(parse_inline(&paragraph.join("\n"))));
+            paragraph.clear();
+        }
+    };
+
+    for line in text.lines() {
+        if let Some((language, lines)) = code.as_mut() {
+            if code_fence(line).is_some_and(str::is_empty) {
+                blocks.push(Block::CodeBlock {
+                    language: language.take(),
+                    code: lines.join("\n"),
+                    closed: true,
+                });
+                code = None;
+            } else {
+                lines.push(line);
+            }
+
+            continue;
+        }
+
+        if let Some(language) = code_fence(line) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            let language = (!language.is_empty()).then(|| language.to_string());
+            code = Some((language, Vec::new()));
+        } else if line.trim().is_empty() {
+            flush_paragraph(&mut paragraph, &mut blocks);
+        } else if let Some((level, content)) = heading(line) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            blocks.push(Block::Heading {
+                level,
+                content: parse_inline(content),
+            });
+        } else if let Some(content) = list_item(line) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            blocks.push(Block::ListItem(parse_inline(content)));
+        } else {
+            paragraph.push(line);
+        }
+    }
+
+    flush_paragraph(&mut paragraph, &mut blocks);
+
+    // the closing fence hasn't arrived yet, show what we have so far as code
+    if let Some((language, mut lines)) = code {
+        // a line of backticks that hasn't been finished is most likely the start of the closing
+        // fence, so don't show it as code in the meantime
+        let partial_fence = !text.ends_with('\n')
+            && lines.last().is_some_and(|line| {
+                let line = line.trim_start();
+                !line.is_empty() && line.chars().all(|c| c == '`')
+            });
+
+        if partial_fence {
+            lines.pop();
+        }
+
+        blocks.push(Block::CodeBlock {
+            language,
+            code: lines.join("\n"),
+            closed: false,
+        });
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = "# Title\n\nSome **bold** and *italic* text with `code`.\n\n\
+        - first item\n- second item\n\n```rust\nfn main() {\n    println!(\"hi\");\n}\n```\n\n\
+        A closing paragraph.\n";
+
+    /// Every prefix of the document, cut at character boundaries.
+    fn prefixes(text: &str) -> impl Iterator<Item = &str> {
+        text.char_indices()
+            .map(|(idx, _)| &text[..idx])
+            .chain(std::iter::once(text))
+    }
+
+    fn text_of(inlines: &[Inline]) -> String {
+        inlines
+            .iter()
+            .map(|inline| match inline {
+                Inline::Text(text) => text.clone(),
+                Inline::Code(text) => format!("`{text}`"),
+                Inline::Emphasis(text) => format!("*{text}*"),
+                Inline::Strong(text) => format!("**{text}**"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn full_document() {
+        let blocks = parse(DOCUMENT);
+
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Heading {
+                    level: 1,
+                    content: vec![Inline::Text("Title".to_string())],
+                },
+                Block::Paragraph(vec![
+                    Inline::Text("Some ".to_string()),
+                    Inline::Strong("bold".to_string()),
+                    Inline::Text(" and ".to_string()),
+                    Inline::Emphasis("italic".to_string()),
+                    Inline::Text(" text with ".to_string()),
+                    Inline::Code("code".to_string()),
+                    Inline::Text(".".to_string()),
+                ]),
+                Block::ListItem(vec![Inline::Text("first item".to_string())]),
+                Block::ListItem(vec![Inline::Text("second item".to_string())]),
+                Block::CodeBlock {
+                    language: Some("rust".to_string()),
+                    code: "fn main() {\n    println!(\"hi\");\n}".to_string(),
+                    closed: true,
+                },
+                Block::Paragraph(vec![Inline::Text("A closing paragraph.".to_string())]),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_fence_is_open_code_block() {
+        let blocks = parse("Intro\n\n```py\nprint(1)\nprint(");
+
+        assert_eq!(
+            blocks.last(),
+            Some(&Block::CodeBlock {
+                language: Some("py".to_string()),
+                code: "print(1)\nprint(".to_string(),
+                closed: false,
+            })
+        );
+    }
+
+    #[test]
+    fn unbalanced_emphasis_is_literal() {
+        assert_eq!(
+            parse_inline("some **bold"),
+            vec![Inline::Text("some **bold".to_string())]
+        );
+        assert_eq!(
+            parse_inline("an *ital"),
+            vec![Inline::Text("an *ital".to_string())]
+        );
+        assert_eq!(
+            parse_inline("run `cargo"),
+            vec![Inline::Text("run `cargo".to_string())]
+        );
+        assert_eq!(
+            parse_inline("snake_case_name"),
+            vec![Inline::Text("snake_case_name".to_string())]
+        );
+    }
+
+    #[test]
+    fn prefixes_never_lose_text() {
+        let letters =
+            |text: &str| -> String { text.chars().filter(|c| c.is_alphanumeric()).collect() };
+
+        for prefix in prefixes(DOCUMENT) {
+            let rendered: String = parse(prefix)
+                .iter()
+                .map(|block| match block {
+                    Block::Heading { content, .. }
+                    | Block::Paragraph(content)
+                    | Block::ListItem(content) => text_of(content),
+                    Block::CodeBlock { language, code, .. } => {
+                        format!("{}{}", language.as_deref().unwrap_or_default(), code)
+                    }
+                })
+                .collect();
+
+            // markup is only ever removed from around text, never in place of it
+            assert_eq!(
+                letters(&rendered),
+                letters(prefix),
+                "text went missing for prefix {prefix:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn partial_closing_fence_is_hidden() {
+        for prefix in [
+            "```
+let a = 1;
+`",
+            "```
+let a = 1;
+``",
+        ] {
+            assert_eq!(
+                parse(prefix),
+                vec![Block::CodeBlock {
+                    language: None,
+                    code: "let a = 1;".to_string(),
+                    closed: false,
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn earlier_blocks_are_stable() {
+        let mut previous: Vec<Block> = Vec::new();
+
+        for prefix in prefixes(DOCUMENT) {
+            let blocks = parse(prefix);
+
+            // blocks that were followed by another block are finished, and must not change as
+            // more text arrives
+            if previous.len() > 1 {
+                let finished = &previous[..previous.len() - 1];
+                assert_eq!(
+                    &blocks[..finished.len()],
+                    finished,
+                    "an earlier block changed at prefix {prefix:?}"
+                );
+            }
+
+            previous = blocks;
+        }
+    }
+
+    #[test]
+    fn open_code_block_only_grows() {
+        let start = DOCUMENT.find("```rust\n").unwrap() + "```rust\n".len();
+        let end = DOCUMENT.rfind("```").unwrap();
+        let mut last_code = String::new();
+
+        for prefix in prefixes(&DOCUMENT[..end]).filter(|prefix| prefix.len() >= start) {
+            let Some(Block::CodeBlock { code, closed, .. }) = parse(prefix).pop() else {
+                panic!("expected an open code block for prefix {prefix:?}");
+            };
+
+            assert!(!closed);
+            assert!(
+                code.starts_with(last_code.trim_end()),
+                "code block content jumped at prefix {prefix:?}"
+            );
+            last_code = code;
+        }
+    }
+}
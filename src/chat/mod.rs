@@ -1,9 +1,13 @@
+pub mod markdown;
 pub mod models;
 pub mod services;
 pub mod storage;
 pub mod ui;
 
-use std::sync::Arc;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
 
 use anyhow::Result;
 use gpui::App;
@@ -17,6 +21,9 @@ use self::{
     storage::ChatDao,
 };
 
+/// Set while pending messages are being retried, so only one retry loop runs at a time.
+static RETRYING_PENDING: AtomicBool = AtomicBool::new(false);
+
 /// High level entry point for chat functionality.
 pub struct ChatFacade {
     db: Arc<TursoDatabase>,
@@ -129,11 +136,58 @@ pub fn bootstrap_state(cx: &mut App, services: ChatServices) {
                 *slot = LlmRequestState::Idle;
                 cx.notify();
             });
+
+            // messages left pending when the app was last closed
+            retry_pending_messages(app, services.clone());
         });
     })
     .detach();
 }
 
+/// Retries messages that couldn't be answered because the endpoint was unreachable, until they
+/// have all been answered.
+pub fn retry_pending_messages(cx: &mut App, services: ChatServices) {
+    if RETRYING_PENDING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let state = cx.global::<ChatState>().clone();
+
+    cx.spawn(async move |app| {
+        services
+            .retry_pending_with_backoff(|replies| {
+                let _ = app.update(|app| {
+                    let current = state.current_conversation.read(app).clone();
+                    let (replies, elsewhere): (Vec<_>, Vec<_>) = replies
+                        .into_iter()
+                        .partition(|reply| Some(&reply.conversation_id) == current.as_ref());
+
+                    for reply in elsewhere {
+                        state.mark_unread(app, &reply.conversation_id);
+                    }
+
+                    if replies.is_empty() {
+                        return;
+                    }
+
+                    state.messages.update(app, |data, cx| {
+                        for reply in replies {
+                            for message in data.iter_mut() {
+                                message.pending = false;
+                            }
+                            data.push(reply);
+                        }
+                        cx.notify();
+                    });
+                });
+            })
+            .await;
+
+        RETRYING_PENDING.store(false, Ordering::SeqCst);
+    })
+    .detach();
+}
+
 pub fn load_messages_for(cx: &mut App, services: ChatServices, conversation_id: ConversationId) {
     let (messages, current) = {
         let state = cx.global::<ChatState>();
@@ -1,4 +1,4 @@
-use std::time::SystemTime;
+use std::{ops::RangeInclusive, time::SystemTime};
 
 use gpui::{App, AppContext, Entity, Global};
 use serde::{Deserialize, Serialize};
@@ -25,6 +25,59 @@ pub struct ConversationSummary {
     pub title: String,
     pub updated_at: SystemTime,
     pub model_id: String,
+    pub parameters: SamplingParameters,
+    /// Whether a reply has arrived since the conversation was last open. This isn't stored, so
+    /// every conversation starts out read.
+    #[serde(default)]
+    pub unread: bool,
+}
+
+/// Sampling parameters sent with completion requests. Parameters that are `None` fall back to the
+/// defaults from the chat configuration, or are left to the server if those aren't set either.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SamplingParameters {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+impl SamplingParameters {
+    pub const TEMPERATURE_RANGE: RangeInclusive<f32> = 0.0..=2.0;
+    pub const TOP_P_RANGE: RangeInclusive<f32> = 0.0..=1.0;
+    pub const MAX_TOKENS_RANGE: RangeInclusive<u32> = 1..=131072;
+
+    /// Fills in the parameters that aren't set from `defaults`.
+    pub fn or(&self, defaults: &SamplingParameters) -> SamplingParameters {
+        SamplingParameters {
+            temperature: self.temperature.or(defaults.temperature),
+            top_p: self.top_p.or(defaults.top_p),
+            max_tokens: self.max_tokens.or(defaults.max_tokens),
+        }
+    }
+
+    /// Clamps every parameter into the range accepted by OpenAI-compatible servers. Values that
+    /// aren't numbers are dropped.
+    pub fn validated(&self) -> SamplingParameters {
+        let clamp = |value: Option<f32>, range: &RangeInclusive<f32>| {
+            value
+                .filter(|v| !v.is_nan())
+                .map(|v| v.clamp(*range.start(), *range.end()))
+        };
+
+        SamplingParameters {
+            temperature: clamp(self.temperature, &Self::TEMPERATURE_RANGE),
+            top_p: clamp(self.top_p, &Self::TOP_P_RANGE),
+            max_tokens: self.max_tokens.map(|v| {
+                v.clamp(
+                    *Self::MAX_TOKENS_RANGE.start(),
+                    *Self::MAX_TOKENS_RANGE.end(),
+                )
+            }),
+        }
+    }
 }
 
 /// Individual chat message.
@@ -36,6 +89,10 @@ pub struct Message {
     pub content: String,
     pub created_at: SystemTime,
     pub token_usage: Option<u32>,
+    /// True if the message couldn't be answered because the endpoint was unreachable, and is
+    /// waiting to be retried.
+    #[serde(default)]
+    pub pending: bool,
 }
 
 impl Message {
@@ -51,6 +108,7 @@ impl Message {
             content: content.into(),
             created_at: SystemTime::now(),
             token_usage: None,
+            pending: false,
         }
     }
 
@@ -88,6 +146,7 @@ impl Default for LlmRequestState {
 }
 
 /// Root chat state registered as a gpui global model.
+#[derive(Clone)]
 pub struct ChatState {
     pub conversations: Entity<Vec<ConversationSummary>>,
     pub current_conversation: Entity<Option<ConversationId>>,
@@ -104,6 +163,22 @@ impl ChatState {
         let messages = cx.new(|_| Vec::<Message>::new());
         let request_state = cx.new(|_| LlmRequestState::Idle);
 
+        // opening a conversation marks its replies as read
+        let conversations_clone = conversations.clone();
+        cx.observe(&current_conversation, move |current, cx| {
+            let Some(id) = current.read(cx).clone() else {
+                return;
+            };
+
+            conversations_clone.update(cx, |list, cx| {
+                if let Some(conversation) = list.iter_mut().find(|c| c.id == id && c.unread) {
+                    conversation.unread = false;
+                    cx.notify();
+                }
+            });
+        })
+        .detach();
+
         cx.set_global(ChatState {
             conversations,
             current_conversation,
@@ -133,6 +208,20 @@ impl ChatState {
         });
     }
 
+    /// Marks a conversation as having an unread reply, unless it's the one that's open.
+    pub fn mark_unread(&self, cx: &mut App, conversation: &ConversationId) {
+        if self.current_conversation.read(cx).as_ref() == Some(conversation) {
+            return;
+        }
+
+        self.conversations.update(cx, |list, cx| {
+            if let Some(summary) = list.iter_mut().find(|c| &c.id == conversation) {
+                summary.unread = true;
+                cx.notify();
+            }
+        });
+    }
+
     pub fn set_request_state(&self, cx: &mut App, state: LlmRequestState) {
         self.request_state.update(cx, |slot, cx| {
             *slot = state;
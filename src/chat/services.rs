@@ -1,17 +1,78 @@
-use std::{sync::Arc, time::SystemTime};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
 use anyhow::{Context, Result, bail};
 use gpui::Global;
-use isahc::AsyncReadResponseExt;
+use isahc::{AsyncReadResponseExt, config::Configurable};
 use serde_json::json;
+use tracing::warn;
 
 use crate::{shared::config::ChatSection, shared::db::TursoDatabase};
 
 use super::{
-    models::{ConversationId, ConversationSummary, Message, MessageRole},
+    models::{ConversationId, ConversationSummary, Message, MessageRole, SamplingParameters},
     storage::ChatDao,
 };
 
+/// Returns the index of the first message sent with a completion request when `limit` drops some
+/// of the earlier messages, or `None` if the whole history is sent. System messages don't count
+/// towards the limit, and the most recent user message is always sent.
+pub fn history_cutoff(history: &[Message], limit: usize) -> Option<usize> {
+    if limit == 0 {
+        return None;
+    }
+
+    let conversation: Vec<usize> = history
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| message.role != MessageRole::System)
+        .map(|(idx, _)| idx)
+        .collect();
+
+    if conversation.len() <= limit {
+        return None;
+    }
+
+    let mut cutoff = conversation[conversation.len() - limit];
+
+    if let Some(last_user) = history
+        .iter()
+        .rposition(|message| message.role == MessageRole::User)
+    {
+        cutoff = cutoff.min(last_user);
+    }
+
+    // nothing is dropped if the latest user message is also the first one
+    if cutoff <= conversation[0] {
+        return None;
+    }
+
+    Some(cutoff)
+}
+
+/// How long to wait before retrying pending messages. Doubled after every attempt that finds the
+/// endpoint still unreachable, up to `RETRY_MAX_DELAY`.
+const RETRY_INITIAL_DELAY: Duration = Duration::from_secs(5);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(300);
+
+/// Returns true if `err` was caused by the endpoint being unreachable, rather than by the endpoint
+/// rejecting the request.
+pub fn is_connectivity_error(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause
+            .downcast_ref::<isahc::Error>()
+            .is_some_and(|err| err.is_network() || err.is_timeout())
+    })
+}
+
+/// Returns true if `endpoint` is Ollama's native chat API rather than an OpenAI-compatible one.
+/// The two take sampling parameters in different places.
+fn is_ollama_native(endpoint: &str) -> bool {
+    endpoint.trim_end_matches('/').ends_with("/api/chat")
+}
+
 /// Container for chat-related service objects.
 #[derive(Clone)]
 pub struct ChatServices {
@@ -50,6 +111,15 @@ impl ChatServices {
         &self.chat_config
     }
 
+    /// The sampling parameters used by conversations that don't override them.
+    pub fn default_parameters(&self) -> SamplingParameters {
+        SamplingParameters {
+            temperature: self.chat_config.temperature,
+            top_p: self.chat_config.top_p,
+            max_tokens: self.chat_config.max_tokens,
+        }
+    }
+
     pub async fn ensure_schema(&self) -> Result<()> {
         self.dao.ensure_schema().await
     }
@@ -82,6 +152,16 @@ impl ChatServices {
             .await
     }
 
+    pub async fn update_conversation_parameters(
+        &self,
+        id: &ConversationId,
+        parameters: &SamplingParameters,
+    ) -> Result<()> {
+        self.dao
+            .update_conversation_parameters(id, &parameters.validated())
+            .await
+    }
+
     pub async fn delete_conversation(&self, id: &ConversationId) -> Result<()> {
         self.dao.delete_conversation(id).await
     }
@@ -105,6 +185,86 @@ impl ChatServices {
         Ok(message)
     }
 
+    pub async fn set_message_pending(&self, id: &str, pending: bool) -> Result<()> {
+        self.dao.set_message_pending(id, pending).await
+    }
+
+    /// Returns true if the completion endpoint can be reached. Any response counts, even an error
+    /// status, since only the connection matters here.
+    pub async fn endpoint_reachable(&self) -> bool {
+        let Ok(request) = isahc::http::Request::get(&self.chat_config.api_endpoint)
+            .timeout(Duration::from_secs(10))
+            .body(())
+        else {
+            return false;
+        };
+
+        isahc::send_async(request).await.is_ok()
+    }
+
+    /// Requests replies for every conversation with pending messages, and returns the replies
+    /// that were received. Conversations stay pending if the endpoint is still unreachable.
+    pub async fn retry_pending(&self) -> Result<Vec<Message>> {
+        let pending = self.dao.list_pending_messages().await?;
+
+        let mut conversations: Vec<ConversationId> = Vec::new();
+        for message in &pending {
+            if !conversations.contains(&message.conversation_id) {
+                conversations.push(message.conversation_id.clone());
+            }
+        }
+
+        let mut replies = Vec::new();
+        for conversation_id in conversations {
+            match self.generate_assistant_reply(&conversation_id).await {
+                Ok(reply) => replies.extend(reply),
+                Err(err) if is_connectivity_error(&err) => break,
+                // retrying won't help if the endpoint rejected the request, so give up on it
+                Err(err) => warn!("failed to answer pending message: {err:?}"),
+            }
+
+            for message in pending
+                .iter()
+                .filter(|message| message.conversation_id == conversation_id)
+            {
+                self.dao.set_message_pending(&message.id, false).await?;
+            }
+        }
+
+        Ok(replies)
+    }
+
+    /// Retries pending messages until there are none left, backing off while the endpoint is
+    /// unreachable. `on_replies` is called with the replies received by each attempt.
+    pub async fn retry_pending_with_backoff(&self, mut on_replies: impl FnMut(Vec<Message>)) {
+        let mut delay = RETRY_INITIAL_DELAY;
+
+        loop {
+            if self.endpoint_reachable().await {
+                match self.retry_pending().await {
+                    Ok(replies) if !replies.is_empty() => on_replies(replies),
+                    Ok(_) => {}
+                    Err(err) => {
+                        warn!("failed to retry pending messages: {err:?}");
+                        return;
+                    }
+                }
+            }
+
+            match self.dao.list_pending_messages().await {
+                Ok(pending) if pending.is_empty() => return,
+                Ok(_) => {}
+                Err(err) => {
+                    warn!("failed to list pending messages: {err:?}");
+                    return;
+                }
+            }
+
+            smol::Timer::after(delay).await;
+            delay = (delay * 2).min(RETRY_MAX_DELAY);
+        }
+    }
+
     pub async fn store_message(&self, message: Message) -> Result<Message> {
         self.dao.append_message(&message).await?;
         Ok(message)
@@ -119,16 +279,60 @@ impl ChatServices {
         }
 
         let history = self.dao.list_messages(conversation_id).await?;
+        let message = self
+            .request_reply(conversation_id, &history, &self.chat_config.default_model)
+            .await?;
+
+        self.dao.append_message(&message).await?;
+
+        Ok(Some(message))
+    }
+
+    /// Replaces the last assistant message of a conversation with a new reply. `model` is only
+    /// used for this request, the conversation's own model is left unchanged.
+    pub async fn regenerate_assistant_reply(
+        &self,
+        conversation_id: &ConversationId,
+        model: Option<&str>,
+    ) -> Result<Option<Message>> {
+        if self.chat_config.api_endpoint.is_empty() {
+            return Ok(None);
+        }
+
+        let mut history = self.dao.list_messages(conversation_id).await?;
+        let previous = match history.last() {
+            Some(message) if message.role == MessageRole::Assistant => history.pop(),
+            _ => None,
+        };
+
+        let model = model.unwrap_or(&self.chat_config.default_model);
+        let message = self.request_reply(conversation_id, &history, model).await?;
+
+        // the old reply is only removed once there's a new one to take its place
+        if let Some(previous) = previous {
+            self.dao.delete_message(&previous.id).await?;
+        }
+        self.dao.append_message(&message).await?;
+
+        Ok(Some(message))
+    }
+
+    /// Requests a reply to `history` from `model`. The reply isn't stored.
+    async fn request_reply(
+        &self,
+        conversation_id: &ConversationId,
+        history: &[Message],
+        model: &str,
+    ) -> Result<Message> {
+        let cutoff = history_cutoff(history, self.chat_config.history_limit);
 
         let mut payload_messages = Vec::new();
-        for message in history
-            .iter()
-            .rev()
-            .take(50)
-            .collect::<Vec<_>>()
-            .into_iter()
-            .rev()
-        {
+        for (idx, message) in history.iter().enumerate() {
+            // the system prompt is always sent, no matter how much of the history is dropped
+            if cutoff.is_some_and(|cutoff| idx < cutoff) && message.role != MessageRole::System {
+                continue;
+            }
+
             let role = match message.role {
                 MessageRole::User => "user",
                 MessageRole::Assistant => "assistant",
@@ -141,11 +345,46 @@ impl ChatServices {
             }));
         }
 
-        let payload = json!({
-            "model": self.chat_config.default_model,
+        let parameters = self
+            .dao
+            .get_conversation(conversation_id)
+            .await?
+            .map(|conversation| conversation.parameters)
+            .unwrap_or_default()
+            .or(&self.default_parameters())
+            .validated();
+
+        let mut payload = json!({
+            "model": model,
             "messages": payload_messages,
         });
 
+        if is_ollama_native(&self.chat_config.api_endpoint) {
+            let mut options = serde_json::Map::new();
+            if let Some(temperature) = parameters.temperature {
+                options.insert("temperature".into(), json!(temperature));
+            }
+            if let Some(top_p) = parameters.top_p {
+                options.insert("top_p".into(), json!(top_p));
+            }
+            if let Some(max_tokens) = parameters.max_tokens {
+                options.insert("num_predict".into(), json!(max_tokens));
+            }
+
+            payload["stream"] = json!(false);
+            payload["options"] = serde_json::Value::Object(options);
+        } else {
+            if let Some(temperature) = parameters.temperature {
+                payload["temperature"] = json!(temperature);
+            }
+            if let Some(top_p) = parameters.top_p {
+                payload["top_p"] = json!(top_p);
+            }
+            if let Some(max_tokens) = parameters.max_tokens {
+                payload["max_tokens"] = json!(max_tokens);
+            }
+        }
+
         let mut request = isahc::http::Request::builder()
             .method(isahc::http::Method::POST)
             .uri(&self.chat_config.api_endpoint)
@@ -188,12 +427,18 @@ impl ChatServices {
                     .pointer("/choices/0/text")
                     .and_then(|v| v.as_str().map(|s| s.to_string()))
             })
+            .or_else(|| {
+                parsed
+                    .pointer("/message/content")
+                    .and_then(|v| v.as_str().map(|s| s.to_string()))
+            })
             .context("assistant response missing content")?;
 
-        let message = Message::new(conversation_id.clone(), MessageRole::Assistant, reply_text);
-        self.dao.append_message(&message).await?;
-
-        Ok(Some(message))
+        Ok(Message::new(
+            conversation_id.clone(),
+            MessageRole::Assistant,
+            reply_text,
+        ))
     }
 
     /// Placeholder for ensuring the pipeline works end-to-end.
@@ -207,3 +452,55 @@ impl ChatServices {
 }
 
 impl Global for ChatServices {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(roles: &[MessageRole]) -> Vec<Message> {
+        let conversation = ConversationId::new("test");
+
+        roles
+            .iter()
+            .map(|role| Message::new(conversation.clone(), role.clone(), "message"))
+            .collect()
+    }
+
+    #[test]
+    fn short_histories_are_sent_whole() {
+        use MessageRole::*;
+        let messages = history(&[System, User, Assistant, User]);
+
+        assert_eq!(history_cutoff(&messages, 0), None);
+        assert_eq!(history_cutoff(&messages, 3), None);
+        assert_eq!(history_cutoff(&messages, 10), None);
+    }
+
+    #[test]
+    fn the_oldest_messages_are_dropped() {
+        use MessageRole::*;
+        let messages = history(&[User, Assistant, User, Assistant, User]);
+
+        assert_eq!(history_cutoff(&messages, 3), Some(2));
+        assert_eq!(history_cutoff(&messages, 1), Some(4));
+    }
+
+    #[test]
+    fn system_messages_dont_count_towards_the_limit() {
+        use MessageRole::*;
+        let messages = history(&[System, User, Assistant, User]);
+
+        assert_eq!(history_cutoff(&messages, 2), Some(2));
+    }
+
+    #[test]
+    fn the_latest_user_message_is_always_sent() {
+        use MessageRole::*;
+        let messages = history(&[User, Assistant, User, Assistant, Assistant, Assistant]);
+        assert_eq!(history_cutoff(&messages, 2), Some(2));
+
+        // nothing can be dropped if the only user message comes first
+        let messages = history(&[User, Assistant, Assistant, Assistant]);
+        assert_eq!(history_cutoff(&messages, 1), None);
+    }
+}
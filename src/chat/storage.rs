@@ -4,32 +4,22 @@ use std::{
 };
 
 use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use turso::Row;
 
-use crate::shared::db::{TursoConnection, TursoDatabase};
-
-use super::models::{ConversationId, ConversationSummary, Message, MessageRole};
-
-const DDL_CONVERSATIONS: &str = r#"
-CREATE TABLE IF NOT EXISTS conversations (
-    id TEXT PRIMARY KEY,
-    title TEXT NOT NULL,
-    model_id TEXT NOT NULL,
-    created_at INTEGER NOT NULL,
-    updated_at INTEGER NOT NULL,
-    metadata TEXT
-)"#;
-
-const DDL_MESSAGES: &str = r#"
-CREATE TABLE IF NOT EXISTS messages (
-    id TEXT PRIMARY KEY,
-    conversation_id TEXT NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
-    role TEXT NOT NULL,
-    content TEXT NOT NULL,
-    created_at INTEGER NOT NULL,
-    token_usage INTEGER,
-    metadata TEXT
-)"#;
+use crate::shared::db::{TursoConnection, TursoDatabase, migrations::CHAT_MIGRATIONS};
+
+use super::models::{
+    ConversationId, ConversationSummary, Message, MessageRole, SamplingParameters,
+};
+
+/// The contents of the `metadata` column of a conversation, stored as JSON.
+#[derive(Default, Serialize, Deserialize)]
+struct ConversationMetadata {
+    #[serde(default)]
+    parameters: SamplingParameters,
+}
 
 /// Data access object for chat domain entities using Turso.
 #[derive(Clone)]
@@ -48,10 +38,7 @@ impl ChatDao {
 
     /// Ensure the minimal schema required by the chat module exists.
     pub async fn ensure_schema(&self) -> Result<()> {
-        let conn = self.connection()?;
-        conn.execute(DDL_CONVERSATIONS, ()).await?;
-        conn.execute(DDL_MESSAGES, ()).await?;
-        Ok(())
+        self.pool.run_migrations(CHAT_MIGRATIONS).await
     }
 
     pub async fn list_conversations(&self) -> Result<Vec<ConversationSummary>> {
@@ -59,7 +46,7 @@ impl ChatDao {
         let mut rows = conn
             .query(
                 r#"
-                SELECT id, title, updated_at, model_id
+                SELECT id, title, updated_at, model_id, metadata
                 FROM conversations
                 ORDER BY updated_at DESC
                 "#,
@@ -83,7 +70,7 @@ impl ChatDao {
         let mut rows = conn
             .query(
                 r#"
-                SELECT id, title, updated_at, model_id
+                SELECT id, title, updated_at, model_id, metadata
                 FROM conversations
                 WHERE id = ?1
                 "#,
@@ -105,6 +92,7 @@ impl ChatDao {
         model_id: &str,
         timestamp: SystemTime,
     ) -> Result<ConversationSummary> {
+        let _guard = self.pool.lock_writes().await;
         let conn = self.connection()?;
         let ts = to_millis(timestamp);
         conn.execute(
@@ -121,6 +109,8 @@ impl ChatDao {
             title: title.to_string(),
             updated_at: timestamp,
             model_id: model_id.to_string(),
+            parameters: SamplingParameters::default(),
+            unread: false,
         })
     }
 
@@ -130,6 +120,7 @@ impl ChatDao {
         title: &str,
         timestamp: SystemTime,
     ) -> Result<()> {
+        let _guard = self.pool.lock_writes().await;
         let conn = self.connection()?;
         conn.execute(
             r#"
@@ -143,7 +134,41 @@ impl ChatDao {
         Ok(())
     }
 
+    pub async fn update_conversation_parameters(
+        &self,
+        id: &ConversationId,
+        parameters: &SamplingParameters,
+    ) -> Result<()> {
+        let _guard = self.pool.lock_writes().await;
+        let conn = self.connection()?;
+
+        // anything else stored in the metadata is kept, only the parameters are replaced
+        let mut metadata = conn
+            .query_scalar_optional::<Option<String>>(
+                r#"SELECT metadata FROM conversations WHERE id = ?1"#,
+                [id.0.as_str()],
+            )
+            .await?
+            .flatten()
+            .and_then(|raw| serde_json::from_str::<serde_json::Map<String, Value>>(&raw).ok())
+            .unwrap_or_default();
+        metadata.insert("parameters".to_string(), serde_json::to_value(parameters)?);
+        let metadata = serde_json::to_string(&metadata)?;
+
+        conn.execute(
+            r#"
+            UPDATE conversations
+            SET metadata = ?2
+            WHERE id = ?1
+            "#,
+            (id.0.as_str(), metadata.as_str()),
+        )
+        .await?;
+        Ok(())
+    }
+
     pub async fn delete_conversation(&self, id: &ConversationId) -> Result<()> {
+        let _guard = self.pool.lock_writes().await;
         let conn = self.connection()?;
         conn.execute(
             r#"DELETE FROM conversations WHERE id = ?1"#,
@@ -153,12 +178,20 @@ impl ChatDao {
         Ok(())
     }
 
+    pub async fn delete_message(&self, id: &str) -> Result<()> {
+        let _guard = self.pool.lock_writes().await;
+        let conn = self.connection()?;
+        conn.execute(r#"DELETE FROM messages WHERE id = ?1"#, [id])
+            .await?;
+        Ok(())
+    }
+
     pub async fn list_messages(&self, conversation_id: &ConversationId) -> Result<Vec<Message>> {
         let conn = self.connection()?;
         let mut rows = conn
             .query(
                 r#"
-                SELECT id, conversation_id, role, content, created_at, token_usage
+                SELECT id, conversation_id, role, content, created_at, token_usage, pending
                 FROM messages
                 WHERE conversation_id = ?1
                 ORDER BY created_at ASC
@@ -175,12 +208,48 @@ impl ChatDao {
         Ok(messages)
     }
 
+    /// Lists the messages still waiting for a reply, oldest first.
+    pub async fn list_pending_messages(&self) -> Result<Vec<Message>> {
+        let conn = self.connection()?;
+        let mut rows = conn
+            .query(
+                r#"
+                SELECT id, conversation_id, role, content, created_at, token_usage, pending
+                FROM messages
+                WHERE pending = 1
+                ORDER BY created_at ASC
+                "#,
+                (),
+            )
+            .await?;
+
+        let mut messages = Vec::new();
+        while let Some(row) = rows.next().await? {
+            messages.push(row_to_message(&row)?);
+        }
+
+        Ok(messages)
+    }
+
+    pub async fn set_message_pending(&self, id: &str, pending: bool) -> Result<()> {
+        let _guard = self.pool.lock_writes().await;
+        let conn = self.connection()?;
+        conn.execute(
+            r#"UPDATE messages SET pending = ?2 WHERE id = ?1"#,
+            (id, pending as i64),
+        )
+        .await?;
+        Ok(())
+    }
+
     pub async fn append_message(&self, message: &Message) -> Result<()> {
+        let _guard = self.pool.lock_writes().await;
         let conn = self.connection()?;
         conn.execute(
             r#"
-            INSERT INTO messages (id, conversation_id, role, content, created_at, token_usage)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            INSERT INTO messages
+                (id, conversation_id, role, content, created_at, token_usage, pending)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
             "#,
             (
                 message.id.as_str(),
@@ -189,6 +258,7 @@ impl ChatDao {
                 message.content.as_str(),
                 to_millis(message.created_at),
                 message.token_usage.map(|v| v as i64),
+                message.pending as i64,
             ),
         )
         .await?;
@@ -218,6 +288,12 @@ fn row_to_conversation_summary(row: &Row) -> Result<ConversationSummary> {
         updated_at: from_millis(row.get::<i64>(2)?)
             .context("invalid updated_at stored for conversation")?,
         model_id: row.get::<String>(3)?,
+        parameters: row
+            .get::<Option<String>>(4)?
+            .and_then(|raw| serde_json::from_str::<ConversationMetadata>(&raw).ok())
+            .unwrap_or_default()
+            .parameters,
+        unread: false,
     })
 }
 
@@ -232,6 +308,7 @@ fn row_to_message(row: &Row) -> Result<Message> {
         created_at: from_millis(row.get::<i64>(4)?)
             .context("invalid created_at stored for message")?,
         token_usage: row.get::<Option<i64>>(5)?.map(|v| v as u32),
+        pending: row.get::<i64>(6)? != 0,
     })
 }
 
@@ -264,3 +341,140 @@ fn str_to_role(src: &str) -> Result<MessageRole> {
         other => Err(anyhow!("unknown message role: {other}")),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use smol::block_on;
+
+    use super::*;
+
+    async fn dao() -> ChatDao {
+        let db = TursoDatabase::open_in_memory().await.unwrap();
+        let dao = ChatDao::new(Arc::new(db));
+        dao.ensure_schema().await.unwrap();
+        dao
+    }
+
+    fn at(millis: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn messages_are_listed_in_order() {
+        block_on(async {
+            let dao = dao().await;
+            let id = ConversationId::generate();
+            dao.create_conversation(id.clone(), "Hello", "model", at(1000))
+                .await
+                .unwrap();
+
+            let reply = Message::new(id.clone(), MessageRole::Assistant, "Hi!")
+                .with_created_at(at(3000))
+                .with_token_usage(Some(12));
+            let question =
+                Message::new(id.clone(), MessageRole::User, "Hello?").with_created_at(at(2000));
+            dao.append_message(&reply).await.unwrap();
+            dao.append_message(&question).await.unwrap();
+
+            let messages = dao.list_messages(&id).await.unwrap();
+            assert_eq!(messages.len(), 2);
+            assert_eq!(messages[0].content, "Hello?");
+            assert_eq!(messages[1].role, MessageRole::Assistant);
+            assert_eq!(messages[1].token_usage, Some(12));
+
+            let conversation = dao.get_conversation(&id).await.unwrap().unwrap();
+            assert_eq!(conversation.updated_at, at(2000));
+        });
+    }
+
+    #[test]
+    fn conversations_are_listed_most_recent_first() {
+        block_on(async {
+            let dao = dao().await;
+            let older = ConversationId::generate();
+            let newer = ConversationId::generate();
+            dao.create_conversation(older.clone(), "Older", "model", at(1000))
+                .await
+                .unwrap();
+            dao.create_conversation(newer.clone(), "Newer", "model", at(2000))
+                .await
+                .unwrap();
+
+            let conversations = dao.list_conversations().await.unwrap();
+            let ids: Vec<_> = conversations.into_iter().map(|v| v.id).collect();
+            assert_eq!(ids, vec![newer, older]);
+        });
+    }
+
+    #[test]
+    fn pending_messages_are_tracked() {
+        block_on(async {
+            let dao = dao().await;
+            let id = ConversationId::generate();
+            dao.create_conversation(id.clone(), "Offline", "model", at(1000))
+                .await
+                .unwrap();
+
+            let message = Message::new(id, MessageRole::User, "Are you there?");
+            dao.append_message(&message).await.unwrap();
+            assert!(dao.list_pending_messages().await.unwrap().is_empty());
+
+            dao.set_message_pending(&message.id, true).await.unwrap();
+            let pending = dao.list_pending_messages().await.unwrap();
+            assert_eq!(pending.len(), 1);
+            assert_eq!(pending[0].id, message.id);
+            assert!(pending[0].pending);
+
+            dao.set_message_pending(&message.id, false).await.unwrap();
+            assert!(dao.list_pending_messages().await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn schema_can_be_ensured_repeatedly() {
+        block_on(async {
+            let dao = dao().await;
+            dao.ensure_schema().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn parameters_are_merged_into_the_metadata() {
+        block_on(async {
+            let dao = dao().await;
+            let id = ConversationId::generate();
+            dao.create_conversation(id.clone(), "Tuned", "model", at(1000))
+                .await
+                .unwrap();
+
+            let conn = dao.connection().unwrap();
+            conn.execute(
+                r#"UPDATE conversations SET metadata = ?2 WHERE id = ?1"#,
+                (id.0.as_str(), r#"{"pinned":true}"#),
+            )
+            .await
+            .unwrap();
+
+            let parameters = SamplingParameters {
+                temperature: Some(0.5),
+                ..Default::default()
+            };
+            dao.update_conversation_parameters(&id, &parameters)
+                .await
+                .unwrap();
+
+            let conversation = dao.get_conversation(&id).await.unwrap().unwrap();
+            assert_eq!(conversation.parameters, parameters);
+
+            let metadata = conn
+                .query_scalar::<String>(
+                    r#"SELECT metadata FROM conversations WHERE id = ?1"#,
+                    [id.0.as_str()],
+                )
+                .await
+                .unwrap();
+            let metadata: Value = serde_json::from_str(&metadata).unwrap();
+            assert_eq!(metadata["pinned"], Value::Bool(true));
+        });
+    }
+}
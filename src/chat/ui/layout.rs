@@ -1,30 +1,100 @@
-use std::time::SystemTime;
+use std::{
+    collections::{HashMap, HashSet},
+    time::SystemTime,
+};
 
 use gpui::{
     App, AppContext, Context, CursorStyle, Entity, FocusHandle, FontWeight, InteractiveElement,
     IntoElement, ParentElement, Render, SharedString, StatefulInteractiveElement, Styled,
-    WeakEntity, Window, div, px, rgba,
+    WeakEntity, Window, div, prelude::FluentBuilder, px, relative,
 };
 use tracing::warn;
 
 use crate::{
     chat::{
         self,
-        models::{ChatState, ConversationId, MessageRole},
-        services::ChatServices,
+        models::{ChatState, ConversationId, LlmRequestState, MessageRole, SamplingParameters},
+        services::{ChatServices, history_cutoff, is_connectivity_error},
+        ui::rich_text::rich_text,
+    },
+    player::ui::{
+        global_actions::{FocusChat, NewChat},
+        models::Models,
+        theme::Theme,
     },
-    shared::ui::components::{
-        button::{ButtonIntent, ButtonSize, ButtonStyle, button},
-        input::{EnrichedInputAction, TextInput},
+    shared::{
+        i18n::{t, t_args},
+        ui::components::{
+            button::{ButtonIntent, ButtonSize, ButtonStyle, button},
+            input::{EnrichedInputAction, TextInput},
+        },
     },
 };
 
-const DEFAULT_CHAT_TITLE: &str = "新会话";
+/// Changes one sampling parameter by a single step, starting from the value currently in effect.
+type ParameterStep = fn(&mut SamplingParameters, &SamplingParameters, bool);
+
+fn step_temperature(parameters: &mut SamplingParameters, defaults: &SamplingParameters, up: bool) {
+    let value = parameters
+        .temperature
+        .or(defaults.temperature)
+        .unwrap_or(1.0);
+    let value = value + if up { 0.1 } else { -0.1 };
+    parameters.temperature = Some((value * 10.0).round() / 10.0);
+}
+
+fn step_top_p(parameters: &mut SamplingParameters, defaults: &SamplingParameters, up: bool) {
+    let value = parameters.top_p.or(defaults.top_p).unwrap_or(1.0);
+    let value = value + if up { 0.05 } else { -0.05 };
+    parameters.top_p = Some((value * 100.0).round() / 100.0);
+}
+
+fn step_max_tokens(parameters: &mut SamplingParameters, defaults: &SamplingParameters, up: bool) {
+    let value = parameters
+        .max_tokens
+        .or(defaults.max_tokens)
+        .unwrap_or(1024);
+    parameters.max_tokens = Some(if up {
+        value.saturating_add(256)
+    } else {
+        value.saturating_sub(256)
+    });
+}
+
+fn role_label(role: &MessageRole) -> &'static str {
+    match role {
+        MessageRole::System => t("chat.role.system"),
+        MessageRole::User => t("chat.role.user"),
+        MessageRole::Assistant => t("chat.role.assistant"),
+        MessageRole::Tool => t("chat.role.tool"),
+    }
+}
+
+/// Describes a parameter, noting where the value comes from when the conversation doesn't set it.
+fn parameter_value<T: std::fmt::Display>(value: Option<T>, default: Option<T>) -> String {
+    match (value, default) {
+        (Some(value), _) => value.to_string(),
+        (None, Some(default)) => t_args("chat.parameter.default", &[&default]),
+        (None, None) => t("chat.parameter.server_default").to_string(),
+    }
+}
 
 pub struct ChatOverview {
     input: Entity<TextInput>,
     buffer: Entity<String>,
+    /// The conversation the text in the composer was written for.
+    draft_conversation: Option<ConversationId>,
+    /// Unsent text for conversations other than the current one, restored when they're selected
+    /// again.
+    drafts: HashMap<ConversationId, String>,
     focus: FocusHandle,
+    /// Whether the composer should take focus the next time it's rendered. Set when the view is
+    /// created and by the FocusChat action, so that other inputs keep focus once they have it.
+    focus_requested: bool,
+    show_parameters: bool,
+    show_model_menu: bool,
+    /// The system messages that have been expanded, by message id.
+    expanded_messages: HashSet<String>,
 }
 
 impl ChatOverview {
@@ -50,10 +120,44 @@ impl ChatOverview {
                 cx,
                 focus.clone(),
                 None,
-                Some(SharedString::from("发送消息…")),
+                Some(SharedString::from(t("chat.composer.placeholder"))),
                 Some(Box::new(handler)),
             );
 
+            App::on_action(cx, {
+                let weak = weak.clone();
+                move |_: &NewChat, cx| {
+                    if let Some(entity) = weak.upgrade() {
+                        entity.update(cx, |this, cx| this.start_new_conversation(cx));
+                    }
+                }
+            });
+
+            App::on_action(cx, {
+                let weak = weak.clone();
+                move |_: &FocusChat, cx| {
+                    // the composer isn't shown in the mini player
+                    let mini_player = cx.global::<Models>().mini_player.clone();
+                    mini_player.write(cx, false);
+
+                    if let Some(entity) = weak.upgrade() {
+                        entity.update(cx, |this, cx| {
+                            this.focus_requested = true;
+                            cx.notify();
+                        });
+                    }
+                }
+            });
+
+            let current_conversation = cx.global::<ChatState>().current_conversation.clone();
+            let draft_conversation = current_conversation.read(cx).clone();
+
+            cx.observe(&current_conversation, |this: &mut Self, current, cx| {
+                let conversation = current.read(cx).clone();
+                this.switch_draft(conversation, cx);
+            })
+            .detach();
+
             cx.subscribe(&input, move |_, _, text: &String, cx| {
                 buffer_clone.update(cx, |buf, _| {
                     *buf = text.clone();
@@ -64,9 +168,220 @@ impl ChatOverview {
             ChatOverview {
                 input,
                 buffer,
+                draft_conversation,
+                drafts: HashMap::new(),
                 focus,
+                focus_requested: true,
+                show_parameters: false,
+                show_model_menu: false,
+                expanded_messages: HashSet::new(),
+            }
+        })
+    }
+
+    /// Replaces the text in the composer.
+    fn set_composer_text(&self, text: String, cx: &mut Context<Self>) {
+        cx.update_entity(&self.input, |input, cx| {
+            input.set_content(text.clone());
+            cx.notify();
+        });
+        cx.update_entity(&self.buffer, |buf, _| *buf = text);
+    }
+
+    /// Keeps the unsent text of the conversation being left, and restores the draft of the one
+    /// being opened.
+    fn switch_draft(&mut self, conversation: Option<ConversationId>, cx: &mut Context<Self>) {
+        if conversation == self.draft_conversation {
+            return;
+        }
+
+        let text = self.buffer.read(cx).clone();
+        let draft = conversation.as_ref().and_then(|id| self.drafts.remove(id));
+
+        match self.draft_conversation.take() {
+            Some(previous) => {
+                if !text.trim().is_empty() {
+                    self.drafts.insert(previous, text);
+                }
+                self.set_composer_text(draft.unwrap_or_default(), cx);
+            }
+            // text written before any conversation was open carries over to the first one opened
+            None => {
+                if let Some(draft) = draft {
+                    self.set_composer_text(draft, cx);
+                }
+            }
+        }
+
+        self.draft_conversation = conversation;
+    }
+
+    /// Puts back the text of a message that couldn't be sent, unless something else has been
+    /// written for the conversation since.
+    fn restore_unsent(
+        &mut self,
+        conversation: Option<ConversationId>,
+        text: String,
+        cx: &mut Context<Self>,
+    ) {
+        if conversation == self.draft_conversation {
+            if self.buffer.read(cx).trim().is_empty() {
+                self.set_composer_text(text, cx);
+            }
+        } else if let Some(id) = conversation {
+            self.drafts.entry(id).or_insert(text);
+        }
+    }
+
+    /// Applies `update` to the current conversation's sampling parameters and saves them.
+    fn update_parameters(
+        &mut self,
+        cx: &mut Context<Self>,
+        update: impl FnOnce(&mut SamplingParameters, &SamplingParameters),
+    ) {
+        let Some(services) = cx.try_global::<ChatServices>().cloned() else {
+            return;
+        };
+        let state = cx.global::<ChatState>();
+        let Some(id) = state.current_conversation.read(cx).clone() else {
+            return;
+        };
+        let conversations = state.conversations.clone();
+        let defaults = services.default_parameters();
+
+        let parameters = conversations.update(cx, |list, cx| {
+            let conversation = list.iter_mut().find(|c| c.id == id)?;
+            update(&mut conversation.parameters, &defaults);
+            conversation.parameters = conversation.parameters.validated();
+            cx.notify();
+            Some(conversation.parameters.clone())
+        });
+
+        let Some(parameters) = parameters else {
+            return;
+        };
+
+        cx.spawn(async move |_weak: WeakEntity<Self>, _| {
+            if let Err(err) = services
+                .update_conversation_parameters(&id, &parameters)
+                .await
+            {
+                warn!("failed to save conversation parameters: {err:?}");
             }
         })
+        .detach();
+    }
+
+    fn parameter_row(
+        cx: &mut Context<Self>,
+        theme: &Theme,
+        id: &'static str,
+        label: &'static str,
+        value: String,
+        step: ParameterStep,
+    ) -> impl IntoElement {
+        div()
+            .flex()
+            .items_center()
+            .gap(px(8.0))
+            .child(
+                div()
+                    .w(px(96.0))
+                    .text_sm()
+                    .text_color(theme.text_secondary)
+                    .child(label),
+            )
+            .child(
+                button()
+                    .style(ButtonStyle::MinimalNoRounding)
+                    .size(ButtonSize::Regular)
+                    .child("−")
+                    .id((id, 0usize))
+                    .on_click(cx.listener(move |this, _, _, cx| {
+                        this.update_parameters(cx, |parameters, defaults| {
+                            step(parameters, defaults, false)
+                        })
+                    })),
+            )
+            .child(div().w(px(120.0)).text_sm().child(value))
+            .child(
+                button()
+                    .style(ButtonStyle::MinimalNoRounding)
+                    .size(ButtonSize::Regular)
+                    .child("+")
+                    .id((id, 1usize))
+                    .on_click(cx.listener(move |this, _, _, cx| {
+                        this.update_parameters(cx, |parameters, defaults| {
+                            step(parameters, defaults, true)
+                        })
+                    })),
+            )
+    }
+
+    /// Replaces the last assistant message of the current conversation with a new reply, from
+    /// `model` if given. The conversation keeps its own model either way.
+    fn regenerate(&mut self, model: Option<String>, cx: &mut Context<Self>) {
+        let Some(services) = cx.try_global::<ChatServices>().cloned() else {
+            return;
+        };
+        let state = cx.global::<ChatState>();
+        let Some(id) = state.current_conversation.read(cx).clone() else {
+            return;
+        };
+
+        if matches!(
+            state.request_state.read(cx),
+            LlmRequestState::InFlight | LlmRequestState::Streaming { .. }
+        ) {
+            return;
+        }
+
+        let current = state.current_conversation.clone();
+        let messages = state.messages.clone();
+        let request_state = state.request_state.clone();
+
+        self.show_model_menu = false;
+        request_state.write(cx, LlmRequestState::InFlight);
+
+        cx.spawn(async move |_weak: WeakEntity<Self>, app| {
+            let result = services
+                .regenerate_assistant_reply(&id, model.as_deref())
+                .await;
+
+            let _ = app.update(|app| {
+                let state = match result {
+                    Ok(Some(message)) => {
+                        // the user may have switched to another conversation in the meantime
+                        if current.read(app).as_ref() == Some(&id) {
+                            messages.update(app, |msgs, cx| {
+                                if msgs
+                                    .last()
+                                    .is_some_and(|last| last.role == MessageRole::Assistant)
+                                {
+                                    msgs.pop();
+                                }
+                                msgs.push(message);
+                                cx.notify();
+                            });
+                        } else {
+                            app.global::<ChatState>().clone().mark_unread(app, &id);
+                        }
+                        LlmRequestState::Idle
+                    }
+                    Ok(None) => {
+                        warn!("LLM endpoint未配置，跳过助手回复");
+                        LlmRequestState::Idle
+                    }
+                    Err(err) => {
+                        warn!("failed to regenerate assistant response: {err:?}");
+                        LlmRequestState::Error(err.to_string())
+                    }
+                };
+
+                request_state.write(app, state);
+            });
+        })
+        .detach();
     }
 
     fn submit_message(&self, cx: &mut Context<Self>) {
@@ -89,7 +404,7 @@ impl ChatOverview {
 
         cx.spawn({
             let services = services.clone();
-            async move |_weak: WeakEntity<Self>, app| {
+            async move |weak: WeakEntity<Self>, app| {
                 let mut conversation_id = app
                     .update(|app| {
                         let state = app.global::<ChatState>();
@@ -100,7 +415,7 @@ impl ChatOverview {
                 if conversation_id.is_none() {
                     let default_model = services.chat_config().default_model.clone();
                     match services
-                        .create_conversation(DEFAULT_CHAT_TITLE, default_model.as_str())
+                        .create_conversation(t("chat.new_conversation"), default_model.as_str())
                         .await
                     {
                         Ok(summary) => {
@@ -131,6 +446,8 @@ impl ChatOverview {
                         }
                         Err(err) => {
                             warn!("failed to create conversation: {err:?}");
+                            let _ =
+                                weak.update(app, |this, cx| this.restore_unsent(None, text, cx));
                             return;
                         }
                     }
@@ -140,7 +457,7 @@ impl ChatOverview {
                     return;
                 };
 
-                match services
+                let message_id = match services
                     .append_message(conv_id.clone(), MessageRole::User, text.clone(), None)
                     .await
                 {
@@ -163,27 +480,57 @@ impl ChatOverview {
                                 }
                             });
                         });
+
+                        message.id
                     }
                     Err(err) => {
                         warn!("failed to append chat message: {err:?}");
+                        let _ = weak
+                            .update(app, |this, cx| this.restore_unsent(Some(conv_id), text, cx));
                         return;
                     }
-                }
+                };
 
                 match services.generate_assistant_reply(&conv_id).await {
                     Ok(Some(assistant_msg)) => {
                         let _ = app.update(|app| {
-                            let state = app.global::<ChatState>();
-                            let messages = state.messages.clone();
-                            messages.update(app, |msgs, cx| {
-                                msgs.push(assistant_msg.clone());
-                                cx.notify();
-                            });
+                            let state = app.global::<ChatState>().clone();
+
+                            // the user may have switched to another conversation in the meantime
+                            if state.current_conversation.read(app).as_ref() == Some(&conv_id) {
+                                state.messages.update(app, |msgs, cx| {
+                                    msgs.push(assistant_msg.clone());
+                                    cx.notify();
+                                });
+                            } else {
+                                state.mark_unread(app, &conv_id);
+                            }
                         });
                     }
                     Ok(None) => {
                         warn!("LLM endpoint未配置，跳过助手回复");
                     }
+                    Err(err) if is_connectivity_error(&err) => {
+                        warn!("assistant endpoint unreachable, queueing message: {err:?}");
+
+                        if let Err(err) = services.set_message_pending(&message_id, true).await {
+                            warn!("failed to mark message as pending: {err:?}");
+                            return;
+                        }
+
+                        let _ = app.update(|app| {
+                            let messages = app.global::<ChatState>().messages.clone();
+                            messages.update(app, |msgs, cx| {
+                                if let Some(message) = msgs.iter_mut().find(|m| m.id == message_id)
+                                {
+                                    message.pending = true;
+                                    cx.notify();
+                                }
+                            });
+
+                            chat::retry_pending_messages(app, services.clone());
+                        });
+                    }
                     Err(err) => warn!("failed to fetch assistant response: {err:?}"),
                 }
             }
@@ -199,7 +546,7 @@ impl ChatOverview {
 
         cx.spawn(async move |_weak: WeakEntity<Self>, app| {
             match services
-                .create_conversation(DEFAULT_CHAT_TITLE, default_model.as_str())
+                .create_conversation(t("chat.new_conversation"), default_model.as_str())
                 .await
             {
                 Ok(summary) => {
@@ -237,14 +584,16 @@ impl Render for ChatOverview {
             return div()
                 .px(px(24.0))
                 .py(px(16.0))
-                .text_color(rgba(0x94a3b8ff))
-                .child("聊天不可用：缺少 Turso 配置或聊天服务");
+                .text_color(cx.global::<Theme>().text_secondary)
+                .child(t("chat.unavailable"));
         }
 
-        if !self.focus.is_focused(window) {
+        if self.focus_requested {
+            self.focus_requested = false;
             self.focus.focus(window);
         }
 
+        let theme = cx.global::<Theme>().clone();
         let state = cx.global::<ChatState>();
         let current_entity = state.current_conversation.clone();
         let current = state.current_conversation.read(cx).clone();
@@ -262,13 +611,17 @@ impl Render for ChatOverview {
                     .flex()
                     .items_center()
                     .gap(px(8.0))
-                    .child(div().font_weight(FontWeight::BOLD).child("会话列表"))
+                    .child(
+                        div()
+                            .font_weight(FontWeight::BOLD)
+                            .child(t("chat.conversations")),
+                    )
                     .child(
                         button()
                             .style(ButtonStyle::MinimalNoRounding)
                             .size(ButtonSize::Regular)
                             .intent(ButtonIntent::Primary)
-                            .child("+ 新建")
+                            .child(t("chat.new"))
                             .id("chat-new-conversation")
                             .on_click(
                                 cx.listener(|this, _, _, cx| this.start_new_conversation(cx)),
@@ -277,8 +630,11 @@ impl Render for ChatOverview {
             );
 
         if conversations.is_empty() {
-            conversation_column =
-                conversation_column.child(div().text_color(rgba(0x94a3b8ff)).child("暂无会话"));
+            conversation_column = conversation_column.child(
+                div()
+                    .text_color(theme.text_secondary)
+                    .child(t("chat.no_conversations")),
+            );
         } else {
             for (idx, conversation) in conversations.iter().enumerate() {
                 let conversation_id: ConversationId = conversation.id.clone();
@@ -297,19 +653,35 @@ impl Render for ChatOverview {
                     .cursor(CursorStyle::PointingHand)
                     .child(
                         div()
-                            .font_weight(FontWeight::SEMIBOLD)
-                            .child(conversation.title.clone()),
+                            .flex()
+                            .items_center()
+                            .gap(px(6.0))
+                            .child(
+                                div()
+                                    .font_weight(FontWeight::SEMIBOLD)
+                                    .child(conversation.title.clone()),
+                            )
+                            // a reply arrived while the conversation wasn't open
+                            .when(conversation.unread, |this| {
+                                this.child(
+                                    div()
+                                        .flex_shrink_0()
+                                        .size(px(8.0))
+                                        .rounded_full()
+                                        .bg(theme.button_primary),
+                                )
+                            }),
                     )
                     .child(
                         div()
                             .text_sm()
-                            .text_color(rgba(0x94a3b8ff))
-                            .child(format!("模型: {}", conversation.model_id)),
+                            .text_color(theme.text_secondary)
+                            .child(t_args("chat.model", &[&conversation.model_id])),
                     )
-                    .hover(|div| div.bg(rgba(0x1b2434ff)));
+                    .hover(|div| div.bg(theme.queue_item_hover));
 
                 if is_selected {
-                    item = item.bg(rgba(0x1f2937ff));
+                    item = item.bg(theme.queue_item_current);
                 }
 
                 let current_handle = current_entity.clone();
@@ -333,57 +705,304 @@ impl Render for ChatOverview {
             }
         }
 
+        let current_parameters = current.as_ref().and_then(|id| {
+            conversations
+                .iter()
+                .find(|c| &c.id == id)
+                .map(|c| c.parameters.clone())
+        });
+
         let mut message_column = div().flex().flex_col().flex_grow().gap(px(12.0)).child(
-            div().font_weight(FontWeight::BOLD).child(
-                current
-                    .as_ref()
-                    .map(|id| format!("当前会话: {}", id.0))
-                    .unwrap_or_else(|| "当前会话: 无".to_string()),
-            ),
+            div()
+                .flex()
+                .items_center()
+                .gap(px(8.0))
+                .child(
+                    div().font_weight(FontWeight::BOLD).child(
+                        current
+                            .as_ref()
+                            .map(|id| t_args("chat.current", &[&id.0]))
+                            .unwrap_or_else(|| t("chat.current.none").to_string()),
+                    ),
+                )
+                .when(current_parameters.is_some(), |this| {
+                    this.child(
+                        button()
+                            .style(ButtonStyle::MinimalNoRounding)
+                            .size(ButtonSize::Regular)
+                            .child(t("chat.parameters"))
+                            .id("chat-toggle-parameters")
+                            .on_click(cx.listener(|this, _, _, cx| {
+                                this.show_parameters = !this.show_parameters;
+                                cx.notify();
+                            })),
+                    )
+                }),
         );
 
+        if let Some(parameters) = current_parameters.filter(|_| self.show_parameters) {
+            let defaults = cx
+                .try_global::<ChatServices>()
+                .map(|services| services.default_parameters())
+                .unwrap_or_default();
+
+            message_column = message_column.child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap(px(4.0))
+                    .rounded(px(6.0))
+                    .bg(theme.background_primary)
+                    .px(px(12.0))
+                    .py(px(10.0))
+                    .child(Self::parameter_row(
+                        cx,
+                        &theme,
+                        "chat-parameter-temperature",
+                        "Temperature",
+                        parameter_value(parameters.temperature, defaults.temperature),
+                        step_temperature,
+                    ))
+                    .child(Self::parameter_row(
+                        cx,
+                        &theme,
+                        "chat-parameter-top-p",
+                        "Top P",
+                        parameter_value(parameters.top_p, defaults.top_p),
+                        step_top_p,
+                    ))
+                    .child(Self::parameter_row(
+                        cx,
+                        &theme,
+                        "chat-parameter-max-tokens",
+                        t("chat.parameter.max_tokens"),
+                        parameter_value(parameters.max_tokens, defaults.max_tokens),
+                        step_max_tokens,
+                    ))
+                    .child(
+                        div().flex().child(
+                            button()
+                                .style(ButtonStyle::MinimalNoRounding)
+                                .size(ButtonSize::Regular)
+                                .child(t("chat.parameters.reset"))
+                                .id("chat-reset-parameters")
+                                .on_click(cx.listener(|this, _, _, cx| {
+                                    this.update_parameters(cx, |parameters, _| {
+                                        *parameters = SamplingParameters::default()
+                                    })
+                                })),
+                        ),
+                    ),
+            );
+        }
+
         if messages.is_empty() {
-            message_column =
-                message_column.child(div().text_color(rgba(0x94a3b8ff)).child("该会话暂无消息"));
+            message_column = message_column.child(
+                div()
+                    .text_color(theme.text_secondary)
+                    .child(t("chat.no_messages")),
+            );
         } else {
-            for message in messages.iter().take(100) {
-                message_column = message_column.child(
-                    div()
-                        .flex()
-                        .flex_col()
-                        .gap(px(4.0))
-                        .rounded(px(6.0))
-                        .bg(rgba(0x111827ff))
-                        .px(px(12.0))
-                        .py(px(10.0))
-                        .child(
-                            div()
-                                .font_weight(FontWeight::MEDIUM)
-                                .text_sm()
-                                .text_color(rgba(0x94a3b8ff))
-                                .child(format!("{:?}", message.role)),
-                        )
-                        .child(
-                            div()
-                                .text_color(rgba(0xe2e8f0ff))
-                                .child(message.content.clone()),
-                        ),
-                );
+            let cutoff = cx.try_global::<ChatServices>().and_then(|services| {
+                history_cutoff(&messages, services.chat_config().history_limit)
+            });
+
+            for (idx, message) in messages.iter().enumerate().take(100) {
+                // messages above this point are no longer sent to the model
+                if cutoff == Some(idx) {
+                    message_column = message_column.child(
+                        div()
+                            .flex()
+                            .justify_center()
+                            .text_sm()
+                            .text_color(theme.text_secondary)
+                            .child(t("chat.history_truncated")),
+                    );
+                }
+
+                message_column = message_column.child(match &message.role {
+                    MessageRole::System => {
+                        let expanded = self.expanded_messages.contains(&message.id);
+                        let message_id = message.id.clone();
+
+                        div()
+                            .id(("chat-system-message", idx))
+                            .flex()
+                            .flex_col()
+                            .gap(px(4.0))
+                            .rounded(px(6.0))
+                            .border_1()
+                            .border_color(theme.chat_system_message_border)
+                            .bg(theme.chat_system_message)
+                            .px(px(12.0))
+                            .py(px(8.0))
+                            .cursor(CursorStyle::PointingHand)
+                            .on_click(cx.listener(move |this, _, _, cx| {
+                                if !this.expanded_messages.remove(&message_id) {
+                                    this.expanded_messages.insert(message_id.clone());
+                                }
+                                cx.notify();
+                            }))
+                            .child(
+                                div()
+                                    .font_weight(FontWeight::MEDIUM)
+                                    .text_sm()
+                                    .text_color(theme.text_secondary)
+                                    .child(if expanded {
+                                        t("chat.system_prompt.expanded")
+                                    } else {
+                                        t("chat.system_prompt.collapsed")
+                                    }),
+                            )
+                            .when(expanded, |this| {
+                                this.child(
+                                    div()
+                                        .text_sm()
+                                        .text_color(theme.text)
+                                        .child(message.content.clone()),
+                                )
+                            })
+                            .into_any_element()
+                    }
+                    role => {
+                        let is_user = *role == MessageRole::User;
+                        let (background, label_color) = if is_user {
+                            (theme.chat_user_message, theme.chat_user_message_text)
+                        } else {
+                            (theme.chat_assistant_message, theme.text_secondary)
+                        };
+
+                        div()
+                            .flex()
+                            .when(is_user, |this| this.justify_end())
+                            .child(
+                                div()
+                                    .flex()
+                                    .flex_col()
+                                    .gap(px(4.0))
+                                    .max_w(relative(0.8))
+                                    .rounded(px(8.0))
+                                    .bg(background)
+                                    .px(px(12.0))
+                                    .py(px(10.0))
+                                    .when(message.pending, |this| this.opacity(0.6))
+                                    .child(
+                                        div()
+                                            .font_weight(FontWeight::MEDIUM)
+                                            .text_xs()
+                                            .text_color(label_color)
+                                            .child(if message.pending {
+                                                t_args(
+                                                    "chat.waiting_for_network",
+                                                    &[&role_label(role)],
+                                                )
+                                            } else {
+                                                role_label(role).to_string()
+                                            }),
+                                    )
+                                    .map(|this| match role {
+                                        MessageRole::Assistant => {
+                                            this.child(rich_text(&message.content, &theme))
+                                        }
+                                        MessageRole::User => this.child(
+                                            div()
+                                                .text_color(theme.chat_user_message_text)
+                                                .child(message.content.clone()),
+                                        ),
+                                        _ => this.child(
+                                            div()
+                                                .text_color(theme.text)
+                                                .child(message.content.clone()),
+                                        ),
+                                    }),
+                            )
+                            .into_any_element()
+                    }
+                });
             }
         }
 
+        let last_is_reply = messages
+            .last()
+            .is_some_and(|message| message.role == MessageRole::Assistant);
+
+        if last_is_reply {
+            let busy = matches!(
+                cx.global::<ChatState>().request_state.read(cx),
+                LlmRequestState::InFlight | LlmRequestState::Streaming { .. }
+            );
+            let models = cx
+                .try_global::<ChatServices>()
+                .map(|services| services.chat_config().available_models.clone())
+                .unwrap_or_default();
+
+            message_column = message_column.child(if busy {
+                div()
+                    .text_sm()
+                    .text_color(theme.text_secondary)
+                    .child(t("chat.generating"))
+            } else {
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap(px(4.0))
+                    .child(
+                        div()
+                            .flex()
+                            .gap(px(8.0))
+                            .child(
+                                button()
+                                    .style(ButtonStyle::MinimalNoRounding)
+                                    .size(ButtonSize::Regular)
+                                    .child(t("chat.regenerate"))
+                                    .id("chat-regenerate")
+                                    .on_click(
+                                        cx.listener(|this, _, _, cx| this.regenerate(None, cx)),
+                                    ),
+                            )
+                            .when(!models.is_empty(), |this| {
+                                this.child(
+                                    button()
+                                        .style(ButtonStyle::MinimalNoRounding)
+                                        .size(ButtonSize::Regular)
+                                        .child(t("chat.other_model"))
+                                        .id("chat-regenerate-with")
+                                        .on_click(cx.listener(|this, _, _, cx| {
+                                            this.show_model_menu = !this.show_model_menu;
+                                            cx.notify();
+                                        })),
+                                )
+                            }),
+                    )
+                    .when(self.show_model_menu, |this| {
+                        this.children(models.into_iter().enumerate().map(|(idx, model)| {
+                            let label = model.clone();
+
+                            button()
+                                .style(ButtonStyle::MinimalNoRounding)
+                                .size(ButtonSize::Regular)
+                                .child(label)
+                                .id(("chat-regenerate-model", idx))
+                                .on_click(cx.listener(move |this, _, _, cx| {
+                                    this.regenerate(Some(model.clone()), cx)
+                                }))
+                        }))
+                    })
+            });
+        }
+
         let composer = div()
             .flex()
             .gap(px(12.0))
             .items_center()
             .pt(px(8.0))
             .border_t_1()
-            .border_color(rgba(0x1f2937ff))
+            .border_color(theme.border_color)
             .child(
                 div()
                     .flex()
                     .flex_grow()
-                    .bg(rgba(0x111827ff))
+                    .bg(theme.background_primary)
                     .rounded(px(8.0))
                     .px(px(10.0))
                     .py(px(8.0))
@@ -393,7 +1012,7 @@ impl Render for ChatOverview {
                 button()
                     .intent(ButtonIntent::Primary)
                     .size(ButtonSize::Regular)
-                    .child("发送")
+                    .child(t("chat.send"))
                     .id("chat-send-message")
                     .on_click(cx.listener(|this, _, _, cx| this.submit_message(cx))),
             );
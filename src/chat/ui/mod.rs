@@ -1,3 +1,4 @@
 //! Placeholder for chat UI components. Concrete views will be added progressively.
 
 pub mod layout;
+pub mod rich_text;
@@ -0,0 +1,89 @@
+use gpui::{
+    FontStyle, FontWeight, HighlightStyle, IntoElement, ParentElement, SharedString, Styled,
+    StyledText, div, px, rems,
+};
+
+use crate::{
+    chat::markdown::{Block, Inline, parse},
+    player::ui::theme::Theme,
+};
+
+/// Flattens inline spans into a single string, highlighting the styled ranges.
+fn inline_text(inlines: &[Inline], theme: &Theme) -> StyledText {
+    let mut text = String::new();
+    let mut highlights = Vec::new();
+
+    for inline in inlines {
+        let start = text.len();
+
+        let style = match inline {
+            Inline::Text(content) => {
+                text.push_str(content);
+                None
+            }
+            Inline::Code(content) => {
+                text.push_str(content);
+                Some(HighlightStyle {
+                    background_color: Some(theme.background_tertiary.into()),
+                    ..Default::default()
+                })
+            }
+            Inline::Emphasis(content) => {
+                text.push_str(content);
+                Some(HighlightStyle {
+                    font_style: Some(FontStyle::Italic),
+                    ..Default::default()
+                })
+            }
+            Inline::Strong(content) => {
+                text.push_str(content);
+                Some(HighlightStyle {
+                    font_weight: Some(FontWeight::BOLD),
+                    ..Default::default()
+                })
+            }
+        };
+
+        if let Some(style) = style {
+            highlights.push((start..text.len(), style));
+        }
+    }
+
+    StyledText::new(SharedString::from(text)).with_highlights(highlights)
+}
+
+/// Renders a chat message as Markdown. The message may still be arriving, see
+/// [`crate::chat::markdown`] for how incomplete syntax is handled.
+pub fn rich_text(text: &str, theme: &Theme) -> impl IntoElement {
+    div()
+        .flex()
+        .flex_col()
+        .gap(px(6.0))
+        .text_color(theme.text)
+        .children(parse(text).into_iter().map(|block| {
+            match block {
+                Block::Heading { level, content } => div()
+                    .font_weight(FontWeight::BOLD)
+                    .text_size(rems(match level {
+                        1 => 1.5,
+                        2 => 1.25,
+                        _ => 1.1,
+                    }))
+                    .child(inline_text(&content, theme)),
+                Block::Paragraph(content) => div().child(inline_text(&content, theme)),
+                Block::ListItem(content) => div()
+                    .flex()
+                    .gap(px(6.0))
+                    .child("•")
+                    .child(inline_text(&content, theme)),
+                Block::CodeBlock { code, .. } => div()
+                    .rounded(px(4.0))
+                    .bg(theme.background_tertiary)
+                    .px(px(10.0))
+                    .py(px(8.0))
+                    .font_family("Roboto Mono")
+                    .text_sm()
+                    .child(code),
+            }
+        }))
+}
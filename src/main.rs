@@ -13,10 +13,7 @@ mod shared;
 
 main! {
     async fn main() {
-        // Configure tracing with local time instead of UTC
-        tracing_subscriber::fmt()
-            .with_timer(tracing_subscriber::fmt::time::LocalTime::rfc_3339())
-            .init();
+        shared::logging::init();
 
         tracing::info!("Starting application");
 
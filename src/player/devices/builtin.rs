@@ -1,4 +1,6 @@
 pub mod cpal;
 pub mod dummy;
+#[cfg(target_os = "macos")]
+pub mod hog_mode;
 #[cfg(target_os = "windows")]
 pub mod win_audiograph;
@@ -19,6 +19,9 @@ use cpal::{
 };
 use rb::{Producer, RB, RbConsumer, RbProducer, SpscRb};
 
+#[cfg(target_os = "macos")]
+use super::hog_mode::{HogMode, HogModeStream};
+
 pub struct CpalProvider {
     host: Host,
 }
@@ -40,7 +43,7 @@ impl DeviceProvider for CpalProvider {
     fn get_devices(&mut self) -> Result<Vec<Box<dyn Device>>, ListError> {
         Ok(self
             .host
-            .devices()?
+            .output_devices()?
             .map(|dev| Box::new(CpalDevice::from(dev)) as Box<dyn Device>)
             .collect())
     }
@@ -54,7 +57,7 @@ impl DeviceProvider for CpalProvider {
 
     fn get_device_by_uid(&mut self, id: &str) -> Result<Box<dyn Device>, FindError> {
         self.host
-            .devices()?
+            .output_devices()?
             .find(|dev| dev.name().unwrap_or("NULL".into()) == *id)
             .ok_or(FindError::DeviceDoesNotExist)
             .map(|dev| Box::new(CpalDevice::from(dev)) as Box<dyn Device>)
@@ -178,6 +181,20 @@ impl Device for CpalDevice {
         }
     }
 
+    /// Takes exclusive access to the device with CoreAudio hog mode before opening the stream.
+    /// cpal sets the device's nominal rate to the stream's, and while the device is hogged no
+    /// other application can change it back.
+    #[cfg(target_os = "macos")]
+    fn open_device_exclusive(
+        &mut self,
+        format: FormatInfo,
+    ) -> Result<Box<dyn OutputStream>, OpenError> {
+        let hog_mode = HogMode::acquire(&self.device.name()?)?;
+        let stream = self.open_device(format)?;
+
+        Ok(Box::new(HogModeStream { stream, hog_mode }))
+    }
+
     fn get_supported_formats(&self) -> Result<Vec<SupportedFormat>, InfoError> {
         Ok(self
             .device
@@ -317,6 +334,7 @@ make_unknown_error!(OpenError, ResetError);
 make_unknown_error!(cpal::PlayStreamError, StateError);
 make_unknown_error!(cpal::PauseStreamError, StateError);
 make_unknown_error!(cpal::DeviceNameError, InfoError);
+make_unknown_error!(cpal::DeviceNameError, OpenError);
 make_unknown_error!(cpal::DefaultStreamConfigError, InfoError);
 make_unknown_error!(cpal::SupportedStreamConfigsError, InfoError);
 make_unknown_error!(cpal::BuildStreamError, OpenError);
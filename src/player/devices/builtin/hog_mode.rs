@@ -0,0 +1,243 @@
+//! CoreAudio hog mode, which gives a single process exclusive access to an output device. While a
+//! device is hogged, no other application can play through it or change its sample rate, so the
+//! rate cpal opens the stream at is the rate the hardware runs at.
+
+use std::{
+    ffi::c_void,
+    mem::size_of,
+    ptr::{NonNull, null},
+};
+
+use objc2_core_audio::{
+    AudioObjectGetPropertyData, AudioObjectGetPropertyDataSize, AudioObjectID,
+    AudioObjectPropertyAddress, AudioObjectSetPropertyData, kAudioDevicePropertyDeviceNameCFString,
+    kAudioDevicePropertyHogMode, kAudioHardwarePropertyDevices, kAudioObjectPropertyElementMain,
+    kAudioObjectPropertyScopeGlobal, kAudioObjectSystemObject,
+};
+use objc2_core_foundation::{CFRetained, CFString};
+use tracing::warn;
+
+use crate::player::{
+    devices::{
+        errors::{CloseError, InfoError, OpenError, ResetError, StateError, SubmissionError},
+        format::FormatInfo,
+        traits::OutputStream,
+    },
+    media::playback::PlaybackFrame,
+};
+
+/// Exclusive access to a CoreAudio device, released when dropped.
+pub struct HogMode {
+    device: AudioObjectID,
+}
+
+impl HogMode {
+    /// Takes exclusive access to the output device with the given name, as cpal names it.
+    pub fn acquire(name: &str) -> Result<HogMode, OpenError> {
+        let device = find_device(name)?;
+        let pid = std::process::id() as i32;
+
+        match get_owner(device)? {
+            -1 => (),
+            owner if owner == pid => return Ok(HogMode { device }),
+            owner => {
+                return Err(OpenError::Unknown(format!(
+                    "the device is in use by process {owner}"
+                )));
+            }
+        }
+
+        // setting the property toggles hog mode: the value is ignored, and replaced with the new
+        // owner
+        let mut owner = pid;
+        set_owner(device, &mut owner)?;
+
+        if owner != pid {
+            return Err(OpenError::Unknown(format!(
+                "the device is in use by process {owner}"
+            )));
+        }
+
+        Ok(HogMode { device })
+    }
+}
+
+impl Drop for HogMode {
+    fn drop(&mut self) {
+        let pid = std::process::id() as i32;
+
+        if get_owner(self.device).is_ok_and(|owner| owner == pid) {
+            let mut owner = pid;
+            if let Err(err) = set_owner(self.device, &mut owner) {
+                warn!(
+                    "Failed to release exclusive access to the device: {:?}",
+                    err
+                );
+            }
+        }
+    }
+}
+
+/// A stream on a hogged device, which keeps exclusive access until it's dropped.
+pub struct HogModeStream {
+    pub stream: Box<dyn OutputStream>,
+    pub hog_mode: HogMode,
+}
+
+impl OutputStream for HogModeStream {
+    fn submit_frame(&mut self, frame: PlaybackFrame) -> Result<(), SubmissionError> {
+        self.stream.submit_frame(frame)
+    }
+
+    fn close_stream(&mut self) -> Result<(), CloseError> {
+        self.stream.close_stream()
+    }
+
+    fn needs_input(&self) -> bool {
+        self.stream.needs_input()
+    }
+
+    fn get_current_format(&self) -> Result<&FormatInfo, InfoError> {
+        self.stream.get_current_format()
+    }
+
+    fn play(&mut self) -> Result<(), StateError> {
+        self.stream.play()
+    }
+
+    fn pause(&mut self) -> Result<(), StateError> {
+        self.stream.pause()
+    }
+
+    fn reset(&mut self) -> Result<(), ResetError> {
+        self.stream.reset()
+    }
+
+    fn set_volume(&mut self, volume: f64) -> Result<(), StateError> {
+        self.stream.set_volume(volume)
+    }
+}
+
+fn address(selector: u32) -> AudioObjectPropertyAddress {
+    AudioObjectPropertyAddress {
+        mSelector: selector,
+        mScope: kAudioObjectPropertyScopeGlobal,
+        mElement: kAudioObjectPropertyElementMain,
+    }
+}
+
+fn check(status: i32, action: &str) -> Result<(), OpenError> {
+    if status == 0 {
+        Ok(())
+    } else {
+        Err(OpenError::Unknown(format!(
+            "couldn't {action}: OSStatus {status}"
+        )))
+    }
+}
+
+/// Finds the CoreAudio device that cpal gives the name `name`.
+fn find_device(name: &str) -> Result<AudioObjectID, OpenError> {
+    let address = address(kAudioHardwarePropertyDevices);
+    let mut size = 0u32;
+
+    // SAFETY: the address and size outlive the call
+    let status = unsafe {
+        AudioObjectGetPropertyDataSize(
+            kAudioObjectSystemObject as AudioObjectID,
+            NonNull::from(&address),
+            0,
+            null(),
+            NonNull::from(&mut size),
+        )
+    };
+    check(status, "list devices")?;
+
+    let mut devices = vec![0 as AudioObjectID; size as usize / size_of::<AudioObjectID>()];
+
+    // SAFETY: `devices` holds `size` bytes
+    let status = unsafe {
+        AudioObjectGetPropertyData(
+            kAudioObjectSystemObject as AudioObjectID,
+            NonNull::from(&address),
+            0,
+            null(),
+            NonNull::from(&mut size),
+            NonNull::new_unchecked(devices.as_mut_ptr() as *mut c_void),
+        )
+    };
+    check(status, "list devices")?;
+
+    devices
+        .into_iter()
+        .find(|device| device_name(*device).is_some_and(|found| found == name))
+        .ok_or(OpenError::Unknown(format!(
+            "no CoreAudio device named {name}"
+        )))
+}
+
+fn device_name(device: AudioObjectID) -> Option<String> {
+    let address = address(kAudioDevicePropertyDeviceNameCFString);
+    let mut name: *const CFString = null();
+    let mut size = size_of::<*const CFString>() as u32;
+
+    // SAFETY: `name` is a CFStringRef, which the property is, and the caller owns the string
+    let status = unsafe {
+        AudioObjectGetPropertyData(
+            device,
+            NonNull::from(&address),
+            0,
+            null(),
+            NonNull::from(&mut size),
+            NonNull::from(&mut name).cast(),
+        )
+    };
+    if status != 0 {
+        return None;
+    }
+
+    let name = NonNull::new(name as *mut CFString)?;
+    // SAFETY: the property follows the create rule, so the string is released when dropped
+    let name = unsafe { CFRetained::from_raw(name) };
+
+    Some(name.to_string())
+}
+
+/// The process with exclusive access to the device, or -1 if no process has it.
+fn get_owner(device: AudioObjectID) -> Result<i32, OpenError> {
+    let address = address(kAudioDevicePropertyHogMode);
+    let mut owner = -1i32;
+    let mut size = size_of::<i32>() as u32;
+
+    // SAFETY: `owner` is a pid_t, which the property is
+    let status = unsafe {
+        AudioObjectGetPropertyData(
+            device,
+            NonNull::from(&address),
+            0,
+            null(),
+            NonNull::from(&mut size),
+            NonNull::from(&mut owner).cast(),
+        )
+    };
+    check(status, "read the device's hog mode")?;
+
+    Ok(owner)
+}
+
+fn set_owner(device: AudioObjectID, owner: &mut i32) -> Result<(), OpenError> {
+    let address = address(kAudioDevicePropertyHogMode);
+
+    // SAFETY: `owner` is a pid_t, which the property is, and CoreAudio writes the new owner back
+    let status = unsafe {
+        AudioObjectSetPropertyData(
+            device,
+            NonNull::from(&address),
+            0,
+            null(),
+            size_of::<i32>() as u32,
+            NonNull::from(owner).cast(),
+        )
+    };
+    check(status, "change the device's hog mode")
+}
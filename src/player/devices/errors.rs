@@ -48,6 +48,8 @@ pub enum OpenError {
     InvalidConfigProvider,
     #[error("The supplied sample format is not supported by the device")]
     InvalidSampleFormat,
+    #[error("The device provider does not support exclusive mode")]
+    ExclusiveModeUnsupported,
     #[error("Unknown device error: `{0}`")]
     Unknown(String),
 }
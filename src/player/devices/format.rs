@@ -22,6 +22,23 @@ pub enum SampleFormat {
     Unsupported,
 }
 
+impl SampleFormat {
+    /// The number of bits in each sample, or `None` for formats without a fixed bit depth.
+    pub fn bit_depth(&self) -> Option<u16> {
+        match self {
+            SampleFormat::Float64 => Some(64),
+            SampleFormat::Float32 | SampleFormat::Signed32 | SampleFormat::Unsigned32 => Some(32),
+            SampleFormat::Signed24
+            | SampleFormat::Unsigned24
+            | SampleFormat::Signed24Packed
+            | SampleFormat::Unsigned24Packed => Some(24),
+            SampleFormat::Signed16 | SampleFormat::Unsigned16 => Some(16),
+            SampleFormat::Signed8 | SampleFormat::Unsigned8 => Some(8),
+            SampleFormat::Dsd | SampleFormat::Unsupported => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ChannelSpec {
     Bitmask(Channels),
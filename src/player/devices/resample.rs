@@ -165,6 +165,46 @@ pub fn match_bit_depth(target_frame: PlaybackFrame, target_depth: SampleFormat)
     PlaybackFrame { samples, rate }
 }
 
+/// Converts decoded frames to the format of the device.
+pub enum FrameConverter {
+    /// The frames already have the device's sample rate and channel count, so only the bit depth
+    /// has to be matched. No resampler is created.
+    Passthrough,
+    Resample(Resampler),
+}
+
+impl FrameConverter {
+    /// Creates a converter for frames shaped like `first_frame`.
+    pub fn new(
+        first_frame: &PlaybackFrame,
+        target_rate: u32,
+        duration: u64,
+        channels: u16,
+    ) -> Self {
+        if first_frame.rate == target_rate && first_frame.samples.channels() == channels as usize {
+            FrameConverter::Passthrough
+        } else {
+            FrameConverter::Resample(Resampler::new(
+                first_frame.rate,
+                target_rate,
+                duration,
+                channels,
+            ))
+        }
+    }
+
+    pub fn convert_formats(
+        &mut self,
+        frame: PlaybackFrame,
+        target_format: &FormatInfo,
+    ) -> PlaybackFrame {
+        match self {
+            FrameConverter::Passthrough => match_bit_depth(frame, target_format.sample_type),
+            FrameConverter::Resample(resampler) => resampler.convert_formats(frame, target_format),
+        }
+    }
+}
+
 pub struct Resampler {
     resampler: FftFixedIn<f32>,
     duration: u64,
@@ -261,3 +301,203 @@ impl Resampler {
         self.eof = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::devices::format::{BufferSize, ChannelSpec};
+
+    const RATE: u32 = 44100;
+    const FRAME_LEN: usize = 1024;
+
+    fn device_format(sample_type: SampleFormat) -> FormatInfo {
+        format_at(RATE, 2, sample_type)
+    }
+
+    fn format_at(sample_rate: u32, channels: u16, sample_type: SampleFormat) -> FormatInfo {
+        FormatInfo {
+            originating_provider: "test",
+            sample_type,
+            sample_rate,
+            buffer_size: BufferSize::Unknown,
+            channels: ChannelSpec::Count(channels),
+            rate_channel_ratio: channels,
+            rate_channel_ratio_fixed: false,
+        }
+    }
+
+    /// A stereo frame of a sine wave, starting `offset` samples in.
+    fn frame(offset: usize) -> PlaybackFrame {
+        sine(offset, FRAME_LEN, 2)
+    }
+
+    /// A frame of `len` samples of a sine wave on every channel, starting `offset` samples in.
+    fn sine(offset: usize, len: usize, channels: usize) -> PlaybackFrame {
+        let channel = (offset..offset + len)
+            .map(|n| (n as f32 * 0.05).sin() * 0.8)
+            .collect::<Vec<f32>>();
+
+        PlaybackFrame {
+            samples: Samples::Float32(vec![channel; channels]),
+            rate: RATE,
+        }
+    }
+
+    fn float_samples(frame: PlaybackFrame) -> Vec<Vec<f32>> {
+        match frame.samples {
+            Samples::Float32(samples) => samples,
+            _ => panic!("expected 32-bit float samples"),
+        }
+    }
+
+    /// Runs the same frames through the pass-through converter and the resampler, and returns the
+    /// output of each.
+    fn convert_both(sample_type: SampleFormat) -> Vec<(PlaybackFrame, PlaybackFrame)> {
+        let format = device_format(sample_type);
+        let mut passthrough = FrameConverter::new(&frame(0), RATE, FRAME_LEN as u64, 2);
+        let mut resampler = Resampler::new(RATE, RATE, FRAME_LEN as u64, 2);
+
+        assert!(matches!(passthrough, FrameConverter::Passthrough));
+
+        (0..8)
+            .map(|idx| {
+                let input = frame(idx * FRAME_LEN);
+                (
+                    passthrough.convert_formats(input.clone(), &format),
+                    resampler.convert_formats(input, &format),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn passthrough_matches_resampler_float() {
+        for (fast, slow) in convert_both(SampleFormat::Float32) {
+            let (Samples::Float32(fast), Samples::Float32(slow)) = (fast.samples, slow.samples)
+            else {
+                panic!("expected 32-bit float samples");
+            };
+
+            let fast_bits: Vec<Vec<u32>> = fast
+                .iter()
+                .map(|channel| channel.iter().map(|v| v.to_bits()).collect())
+                .collect();
+            let slow_bits: Vec<Vec<u32>> = slow
+                .iter()
+                .map(|channel| channel.iter().map(|v| v.to_bits()).collect())
+                .collect();
+
+            assert_eq!(fast_bits, slow_bits);
+        }
+    }
+
+    #[test]
+    fn passthrough_matches_resampler_integer() {
+        for (fast, slow) in convert_both(SampleFormat::Signed16) {
+            let (Samples::Signed16(fast), Samples::Signed16(slow)) = (fast.samples, slow.samples)
+            else {
+                panic!("expected 16-bit samples");
+            };
+
+            assert_eq!(fast, slow);
+            assert_eq!(fast.len(), 2);
+        }
+    }
+
+    #[test]
+    fn resamples_when_rates_differ() {
+        let converter = FrameConverter::new(&frame(0), 48000, FRAME_LEN as u64, 2);
+
+        assert!(matches!(converter, FrameConverter::Resample(_)));
+    }
+
+    #[test]
+    fn same_format_passes_through_unchanged() {
+        let format = device_format(SampleFormat::Float32);
+        let mut converter = FrameConverter::new(&frame(0), RATE, FRAME_LEN as u64, 2);
+
+        let output = converter.convert_formats(frame(0), &format);
+
+        assert_eq!(output.rate, RATE);
+        assert_eq!(float_samples(output), float_samples(frame(0)));
+    }
+
+    #[test]
+    fn bit_depth_is_matched() {
+        let input = PlaybackFrame {
+            samples: Samples::Float32(vec![vec![0.0, 0.5, -0.5, 1.0]]),
+            rate: RATE,
+        };
+
+        let output = match_bit_depth(input, SampleFormat::Signed16);
+
+        let Samples::Signed16(samples) = output.samples else {
+            panic!("expected 16-bit samples");
+        };
+        assert_eq!(samples, vec![vec![0, 16383, -16383, i16::MAX]]);
+    }
+
+    #[test]
+    fn frames_are_buffered_until_a_chunk_is_full() {
+        let format = format_at(48000, 2, SampleFormat::Float32);
+        let mut resampler = Resampler::new(RATE, 48000, FRAME_LEN as u64, 2);
+
+        let first = resampler.convert_formats(sine(0, FRAME_LEN / 2, 2), &format);
+        assert_eq!(first.rate, 48000);
+        assert!(float_samples(first).is_empty());
+
+        let second = resampler.convert_formats(sine(FRAME_LEN / 2, FRAME_LEN / 2, 2), &format);
+        assert_eq!(second.rate, 48000);
+        assert!(!float_samples(second)[0].is_empty());
+    }
+
+    #[test]
+    fn resampling_keeps_the_rate_ratio() {
+        const FRAMES: usize = 64;
+
+        for (from, to) in [
+            (44100, 48000),
+            (44100, 96000),
+            (48000, 44100),
+            (96000, 44100),
+        ] {
+            let format = format_at(to, 2, SampleFormat::Float32);
+            let mut resampler = Resampler::new(from, to, FRAME_LEN as u64, 2);
+
+            let mut produced = 0;
+            for idx in 0..FRAMES {
+                let mut input = sine(idx * FRAME_LEN, FRAME_LEN, 2);
+                input.rate = from;
+
+                let output = resampler.convert_formats(input, &format);
+                assert_eq!(output.rate, to);
+
+                let samples = float_samples(output);
+                if let Some(first) = samples.first() {
+                    assert!(samples.iter().all(|channel| channel.len() == first.len()));
+                    produced += first.len();
+                }
+            }
+
+            // the resampler holds back up to a chunk of output, so allow for that much
+            let expected = (FRAMES * FRAME_LEN) as f64 * f64::from(to) / f64::from(from);
+            let chunk = FRAME_LEN as f64 * f64::from(to) / f64::from(from);
+            assert!(
+                (produced as f64 - expected).abs() <= chunk * 2.0,
+                "{from} -> {to}: produced {produced} samples, expected about {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn every_channel_is_resampled() {
+        for channels in [1, 2, 6] {
+            let format = format_at(48000, channels, SampleFormat::Float32);
+            let mut resampler = Resampler::new(RATE, 48000, FRAME_LEN as u64, channels);
+
+            let output = resampler.convert_formats(sine(0, FRAME_LEN, channels as usize), &format);
+
+            assert_eq!(float_samples(output).len(), channels as usize);
+        }
+    }
+}
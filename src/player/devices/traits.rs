@@ -30,6 +30,17 @@ pub trait Device {
     /// Requests the device open a stream with the given format.
     fn open_device(&mut self, format: FormatInfo) -> Result<Box<dyn OutputStream>, OpenError>;
 
+    /// Requests the device open a stream with the given format, with exclusive access to the
+    /// device. Only cpal on macOS supports this, through CoreAudio hog mode. Other providers
+    /// return `OpenError::ExclusiveModeUnsupported`, and the stream should be opened normally
+    /// instead.
+    fn open_device_exclusive(
+        &mut self,
+        _format: FormatInfo,
+    ) -> Result<Box<dyn OutputStream>, OpenError> {
+        Err(OpenError::ExclusiveModeUnsupported)
+    }
+
     /// Returns the supported formats of the device.
     fn get_supported_formats(&self) -> Result<Vec<SupportedFormat>, InfoError>;
     /// Returns the device's default format.
@@ -0,0 +1,313 @@
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The number of frames in a second of cue sheet time (one frame is a CD sector).
+const FRAMES_PER_SECOND: u64 = 75;
+
+/// A cue sheet, which describes how one or more audio files are split into tracks. Only the
+/// commands needed to list the tracks are read; anything else is ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueSheet {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub genre: Option<String>,
+    pub date: Option<String>,
+    pub catalog: Option<String>,
+    pub files: Vec<CueFile>,
+}
+
+/// An audio file referenced by a cue sheet, and the tracks in it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueFile {
+    /// The file's name, relative to the folder containing the cue sheet.
+    pub name: String,
+    pub tracks: Vec<CueTrack>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub isrc: Option<String>,
+    /// Where the track starts in the file, in milliseconds.
+    pub start_ms: u64,
+}
+
+impl CueSheet {
+    /// The audio file that holds the whole album, if the sheet describes a single file. Sheets
+    /// that list one file per track don't need splitting, so their files are scanned as usual.
+    pub fn single_file(&self) -> Option<&CueFile> {
+        match self.files.as_slice() {
+            [file] if !file.tracks.is_empty() => Some(file),
+            _ => None,
+        }
+    }
+}
+
+impl CueFile {
+    /// Where the track at `index` ends in the file, in milliseconds: the start of the next track,
+    /// or None for the last track, which plays to the end of the file.
+    pub fn end_ms(&self, index: usize) -> Option<u64> {
+        self.tracks.get(index + 1).map(|next| next.start_ms)
+    }
+}
+
+/// Whether the path is a cue sheet, judging by its extension.
+pub fn is_cue_sheet(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("cue"))
+}
+
+/// The location a track from a cue sheet is stored under in the library. Each track needs a
+/// location of its own, so the track number is appended to the path of the cue sheet.
+pub fn cue_track_location(cue: &Path, number: u32) -> PathBuf {
+    let mut location = OsString::from(cue.as_os_str());
+    location.push(format!("#{number:02}"));
+    PathBuf::from(location)
+}
+
+/// Whether `location` is the location of a track from the cue sheet at `cue`.
+pub fn is_cue_track_location(cue: &Path, location: &Path) -> bool {
+    let cue = cue.as_os_str().as_encoded_bytes();
+    let location = location.as_os_str().as_encoded_bytes();
+
+    location.len() > cue.len() && location.starts_with(cue) && location[cue.len()] == b'#'
+}
+
+/// Reads and parses a cue sheet. Cue sheets are often saved in legacy encodings, so text that
+/// isn't valid UTF-8 is read lossily rather than rejected.
+pub fn read_cue_sheet(path: &Path) -> std::io::Result<CueSheet> {
+    let bytes = fs::read(path)?;
+    let text = String::from_utf8_lossy(&bytes);
+
+    Ok(parse_cue_sheet(&text))
+}
+
+/// Parses the text of a cue sheet. Unknown and malformed commands are skipped.
+pub fn parse_cue_sheet(text: &str) -> CueSheet {
+    let mut sheet = CueSheet::default();
+    // non-audio tracks (such as the data track of an enhanced CD) are skipped
+    let mut in_audio_track = false;
+
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let (command, rest) = split_command(line.trim());
+
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => {
+                sheet.files.push(CueFile {
+                    name: file_name(rest),
+                    tracks: Vec::new(),
+                });
+                in_audio_track = false;
+            }
+            "TRACK" => {
+                let mut parts = rest.split_whitespace();
+                let number = parts.next().and_then(|v| v.parse().ok());
+                let is_audio = parts
+                    .next()
+                    .is_some_and(|v| v.eq_ignore_ascii_case("AUDIO"));
+
+                in_audio_track = false;
+
+                if let (Some(number), true, Some(file)) = (number, is_audio, sheet.files.last_mut())
+                {
+                    file.tracks.push(CueTrack {
+                        number,
+                        ..Default::default()
+                    });
+                    in_audio_track = true;
+                }
+            }
+            "INDEX" => {
+                let mut parts = rest.split_whitespace();
+                let index = parts.next().and_then(|v| v.parse::<u32>().ok());
+                let time = parts.next().and_then(parse_time);
+
+                let track = current_track(&mut sheet, in_audio_track);
+
+                if let (Some(track), Some(index), Some(time)) = (track, index, time) {
+                    // index 1 is where the track starts, index 0 is the pregap before it, which
+                    // is only used if the start is missing
+                    if index == 1 || (index == 0 && track.start_ms == 0) {
+                        track.start_ms = time;
+                    }
+                }
+            }
+            "TITLE" => match current_track(&mut sheet, in_audio_track) {
+                Some(track) => track.title = Some(unquote(rest)),
+                None if sheet.files.is_empty() => sheet.title = Some(unquote(rest)),
+                None => (),
+            },
+            "PERFORMER" => match current_track(&mut sheet, in_audio_track) {
+                Some(track) => track.performer = Some(unquote(rest)),
+                None if sheet.files.is_empty() => sheet.performer = Some(unquote(rest)),
+                None => (),
+            },
+            "ISRC" => {
+                if let Some(track) = current_track(&mut sheet, in_audio_track) {
+                    track.isrc = Some(unquote(rest));
+                }
+            }
+            "CATALOG" => sheet.catalog = Some(unquote(rest)),
+            "REM" => {
+                let (field, value) = split_command(rest);
+
+                match field.to_ascii_uppercase().as_str() {
+                    "GENRE" => sheet.genre = Some(unquote(value)),
+                    "DATE" => sheet.date = Some(unquote(value)),
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    sheet
+}
+
+fn current_track(sheet: &mut CueSheet, in_audio_track: bool) -> Option<&mut CueTrack> {
+    if !in_audio_track {
+        return None;
+    }
+
+    sheet.files.last_mut()?.tracks.last_mut()
+}
+
+/// Splits a line into its command and the rest of the line.
+fn split_command(line: &str) -> (&str, &str) {
+    match line.split_once(char::is_whitespace) {
+        Some((command, rest)) => (command, rest.trim()),
+        None => (line, ""),
+    }
+}
+
+/// Removes the quotes around a value, if it has them.
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.rsplit_once('"'))
+        .map_or(value, |(inner, _)| inner)
+        .to_string()
+}
+
+/// Reads the file name from the arguments of a FILE command, which end with the file's type.
+fn file_name(rest: &str) -> String {
+    if rest.starts_with('"') {
+        return unquote(rest);
+    }
+
+    // unquoted names can't contain spaces in the spec, but often do anyway
+    rest.rsplit_once(char::is_whitespace)
+        .map_or(rest, |(name, _)| name.trim())
+        .to_string()
+}
+
+/// Parses a `MM:SS:FF` timestamp into milliseconds.
+fn parse_time(time: &str) -> Option<u64> {
+    let mut parts = time.split(':').map(|v| v.parse::<u64>().ok());
+    let (Some(Some(minutes)), Some(Some(seconds)), Some(Some(frames)), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return None;
+    };
+
+    Some((minutes * 60 + seconds) * 1000 + frames * 1000 / FRAMES_PER_SECOND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = r#"REM GENRE "Progressive Rock"
+REM DATE 1973
+PERFORMER "Pink Floyd"
+TITLE "The Dark Side of the Moon"
+FILE "Pink Floyd - The Dark Side of the Moon.flac" WAVE
+  TRACK 01 AUDIO
+    TITLE "Speak to Me"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Breathe"
+    PERFORMER "David Gilmour"
+    INDEX 00 01:05:20
+    INDEX 01 01:07:00
+  TRACK 03 AUDIO
+    TITLE "On the Run"
+    INDEX 01 03:55:37
+"#;
+
+    #[test]
+    fn album_details_are_read() {
+        let sheet = parse_cue_sheet(SHEET);
+
+        assert_eq!(sheet.title.as_deref(), Some("The Dark Side of the Moon"));
+        assert_eq!(sheet.performer.as_deref(), Some("Pink Floyd"));
+        assert_eq!(sheet.genre.as_deref(), Some("Progressive Rock"));
+        assert_eq!(sheet.date.as_deref(), Some("1973"));
+    }
+
+    #[test]
+    fn tracks_start_at_index_one() {
+        let sheet = parse_cue_sheet(SHEET);
+        let file = sheet.single_file().unwrap();
+
+        assert_eq!(file.name, "Pink Floyd - The Dark Side of the Moon.flac");
+        assert_eq!(file.tracks.len(), 3);
+        assert_eq!(file.tracks[1].title.as_deref(), Some("Breathe"));
+        assert_eq!(file.tracks[1].performer.as_deref(), Some("David Gilmour"));
+        assert_eq!(file.tracks[1].start_ms, 67_000);
+        // 37 frames is 493 ms
+        assert_eq!(file.tracks[2].start_ms, 235_493);
+    }
+
+    #[test]
+    fn tracks_end_where_the_next_one_starts() {
+        let sheet = parse_cue_sheet(SHEET);
+        let file = sheet.single_file().unwrap();
+
+        assert_eq!(file.end_ms(0), Some(67_000));
+        assert_eq!(file.end_ms(1), Some(235_493));
+        assert_eq!(file.end_ms(2), None);
+    }
+
+    #[test]
+    fn sheets_with_a_file_per_track_are_not_split() {
+        let sheet = parse_cue_sheet(
+            "FILE \"01.flac\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n\
+            FILE \"02.flac\" WAVE\nTRACK 02 AUDIO\nINDEX 01 00:00:00\n",
+        );
+
+        assert_eq!(sheet.files.len(), 2);
+        assert!(sheet.single_file().is_none());
+    }
+
+    #[test]
+    fn data_tracks_and_unquoted_names_are_handled() {
+        let sheet = parse_cue_sheet(
+            "\u{feff}FILE album image.wav WAVE\r\nTRACK 01 AUDIO\r\nINDEX 01 00:00:00\r\n\
+            TRACK 02 MODE1/2352\r\nTITLE \"Data\"\r\nINDEX 01 40:00:00\r\n",
+        );
+        let file = sheet.single_file().unwrap();
+
+        assert_eq!(file.name, "album image.wav");
+        assert_eq!(file.tracks.len(), 1);
+        assert_eq!(file.tracks[0].title, None);
+    }
+
+    #[test]
+    fn track_locations_are_matched_to_their_sheet() {
+        let cue = Path::new("/music/album.cue");
+        let location = cue_track_location(cue, 3);
+
+        assert_eq!(location, Path::new("/music/album.cue#03"));
+        assert!(is_cue_track_location(cue, &location));
+        assert!(!is_cue_track_location(Path::new("/music/album"), &location));
+        assert!(!is_cue_track_location(cue, cue));
+    }
+}
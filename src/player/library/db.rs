@@ -1,16 +1,27 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use anyhow::Result;
 use gpui::App;
 use smol::block_on;
 
 use crate::{
-    shared::db::TursoDatabase,
-    player::library::types::{Playlist, PlaylistItem, PlaylistWithCount, TrackStats},
+    player::library::types::{Playlist, PlaylistItem, PlaylistWithCount, RadioStation, TrackStats},
     player::ui::app::Pool,
+    shared::{
+        db::{TursoDatabase, migrations::library_migrations},
+        settings::SettingsGlobal,
+    },
 };
 
-use super::types::{Album, Artist, Track};
+use super::{
+    paths::{decode_path, encode_path},
+    scan::sortable_name,
+    types::{Album, Artist, Track, TrackLocation},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AlbumSortMethod {
@@ -26,6 +37,18 @@ pub enum AlbumSortMethod {
     CatalogDesc,
 }
 
+/// Release information chosen by the user for a track, for example from a MusicBrainz lookup.
+#[derive(Debug, Clone)]
+pub struct ReleaseInfo {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub album_mbid: String,
+    /// The release date, as a Unix timestamp.
+    pub release_date: Option<i64>,
+    pub track_number: Option<i32>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AlbumMethod {
     FullQuality,
@@ -85,6 +108,18 @@ pub async fn list_tracks_in_album(db: &TursoDatabase, album_id: i64) -> Result<A
     Ok(Arc::new(tracks))
 }
 
+/// Picks up to `limit` tracks from anywhere in the library, in a random order.
+pub async fn list_random_tracks(db: &TursoDatabase, limit: i64) -> Result<Arc<Vec<TrackLocation>>> {
+    let query = include_str!("../../../queries/library/find_random_tracks.sql");
+
+    let conn = db.connect()?;
+    let tracks = conn
+        .query_map(query, [limit], TrackLocation::from_row)
+        .await?;
+
+    Ok(Arc::new(tracks))
+}
+
 pub async fn get_album_by_id(
     db: &TursoDatabase,
     album_id: i64,
@@ -134,6 +169,27 @@ pub async fn get_track_by_id(db: &TursoDatabase, track_id: i64) -> Result<Arc<Tr
     Ok(Arc::new(track))
 }
 
+/// Finds the track stored at the given path, if it is in the library.
+pub async fn get_track_by_path(db: &TursoDatabase, path: &Path) -> Result<Option<Arc<Track>>> {
+    let query = include_str!("../../../queries/library/find_track_by_path.sql");
+
+    let conn = db.connect()?;
+    let track = conn
+        .query_optional(query, [encode_path(path).into_owned()], Track::from_row)
+        .await?;
+
+    Ok(track.map(Arc::new))
+}
+
+/// Lists the tracks in the library that are directly inside `folder`.
+pub async fn list_tracks_in_folder(db: &TursoDatabase, folder: &Path) -> Result<Vec<Track>> {
+    let query = include_str!("../../../queries/library/find_tracks_in_folder.sql");
+
+    let conn = db.connect()?;
+    conn.query_map(query, [encode_path(folder).into_owned()], Track::from_row)
+        .await
+}
+
 /// Lists all albums for searching. Returns a vector of tuples containing the id, name, and artist
 /// name.
 pub async fn list_albums_search(db: &TursoDatabase) -> Result<Vec<(u32, String, String)>> {
@@ -153,6 +209,7 @@ pub async fn list_albums_search(db: &TursoDatabase) -> Result<Vec<(u32, String,
 pub async fn add_playlist_item(db: &TursoDatabase, playlist_id: i64, track_id: i64) -> Result<i64> {
     let query = include_str!("../../../queries/playlist/add_track.sql");
 
+    let _guard = db.lock_writes().await;
     let conn = db.connect()?;
     conn.execute_returning_id(query, [playlist_id, track_id])
         .await
@@ -161,6 +218,7 @@ pub async fn add_playlist_item(db: &TursoDatabase, playlist_id: i64, track_id: i
 pub async fn create_playlist(db: &TursoDatabase, name: &str) -> Result<i64> {
     let query = include_str!("../../../queries/playlist/create_playlist.sql");
 
+    let _guard = db.lock_writes().await;
     let conn = db.connect()?;
     conn.execute_returning_id(query, [name]).await
 }
@@ -168,6 +226,7 @@ pub async fn create_playlist(db: &TursoDatabase, name: &str) -> Result<i64> {
 pub async fn delete_playlist(db: &TursoDatabase, playlist_id: i64) -> Result<()> {
     let query = include_str!("../../../queries/playlist/delete_playlist.sql");
 
+    let _guard = db.lock_writes().await;
     let conn = db.connect()?;
     conn.execute(query, [playlist_id]).await?;
 
@@ -199,12 +258,14 @@ pub async fn get_playlist(db: &TursoDatabase, playlist_id: i64) -> Result<Arc<Pl
 pub async fn get_playlist_track_files(
     db: &TursoDatabase,
     playlist_id: i64,
-) -> Result<Arc<Vec<String>>> {
+) -> Result<Arc<Vec<PathBuf>>> {
     let query = include_str!("../../../queries/playlist/get_track_files.sql");
 
     let conn = db.connect()?;
     let track_files = conn
-        .query_map(query, [playlist_id], |row| Ok(row.get::<String>(0)?))
+        .query_map(query, [playlist_id], |row| {
+            Ok(decode_path(&row.get::<String>(0)?))
+        })
         .await?;
 
     Ok(Arc::new(track_files))
@@ -230,6 +291,7 @@ pub async fn get_playlist_tracks(
 pub async fn move_playlist_item(db: &TursoDatabase, item_id: i64, new_position: i64) -> Result<()> {
     // retrieve the current item's position
     let original_item = get_playlist_item(db, item_id).await?;
+    let _guard = db.lock_writes().await;
 
     if original_item.position > new_position {
         let move_query = include_str!("../../../queries/playlist/move_track_down.sql");
@@ -252,6 +314,7 @@ pub async fn remove_playlist_item(db: &TursoDatabase, item_id: i64) -> Result<()
     let query = include_str!("../../../queries/playlist/remove_track.sql");
     let item = get_playlist_item(db, item_id).await?;
 
+    let _guard = db.lock_writes().await;
     let conn = db.connect()?;
     conn.execute(query, [item.position, item_id]).await?;
 
@@ -275,6 +338,166 @@ pub async fn get_track_stats(db: &TursoDatabase) -> Result<Arc<TrackStats>> {
     Ok(Arc::new(stats))
 }
 
+pub async fn count_albums(db: &TursoDatabase) -> Result<i64> {
+    let query = include_str!("../../../queries/library/count_albums.sql");
+
+    let conn = db.connect()?;
+    conn.query_scalar(query, ()).await
+}
+
+/// Counts the tracks anywhere inside the given folder.
+pub async fn count_tracks_in_folder(db: &TursoDatabase, folder: &Path) -> Result<i64> {
+    let query = include_str!("../../../queries/library/count_tracks_in_folder.sql");
+
+    // match on the whole folder name, so /music doesn't also count /music2
+    let mut prefix = encode_path(folder).into_owned();
+    if !prefix.ends_with(std::path::MAIN_SEPARATOR) {
+        prefix.push(std::path::MAIN_SEPARATOR);
+    }
+
+    let conn = db.connect()?;
+    conn.query_scalar(query, (prefix.as_str(),)).await
+}
+
+/// Recomputes the sort names of artists and albums that were derived from their display names,
+/// so that they follow `ignore_articles` when it's changed. Sort names from tags are left alone,
+/// unless the tag only strips the leading article, which can't be told apart from a derived name.
+pub async fn update_sortable_names(db: &TursoDatabase, ignore_articles: bool) -> Result<()> {
+    let _guard = db.lock_writes().await;
+    let conn = db.connect()?;
+
+    for (list, update) in [
+        (
+            include_str!("../../../queries/library/list_artist_sort_names.sql"),
+            include_str!("../../../queries/library/update_artist_sort_name.sql"),
+        ),
+        (
+            include_str!("../../../queries/library/list_album_sort_names.sql"),
+            include_str!("../../../queries/library/update_album_sort_name.sql"),
+        ),
+    ] {
+        let rows = conn
+            .query_map(list, (), |row| {
+                Ok((
+                    row.get::<i64>(0)?,
+                    row.get::<String>(1)?,
+                    row.get::<String>(2)?,
+                ))
+            })
+            .await?;
+
+        for (id, name, sortable) in rows {
+            let derived = sortable == name || sortable == sortable_name(&name, None, true);
+            let wanted = sortable_name(&name, None, ignore_articles);
+
+            if derived && sortable != wanted {
+                conn.execute(update, (id, wanted.as_str())).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Moves a track onto the given release, creating the release's artist and album if they aren't
+/// in the library yet. Only the library is changed; the file's tags are left alone.
+pub async fn apply_release_info(
+    db: &TursoDatabase,
+    track_id: i64,
+    info: &ReleaseInfo,
+    ignore_articles: bool,
+) -> Result<()> {
+    let _guard = db.lock_writes().await;
+    let conn = db.connect()?;
+
+    let artist_sortable = sortable_name(&info.artist, None, ignore_articles);
+    let artist_id = match conn
+        .query_scalar_optional::<i64>(
+            include_str!("../../../queries/scan/create_artist.sql"),
+            (info.artist.as_str(), artist_sortable.as_str()),
+        )
+        .await?
+    {
+        Some(id) => id,
+        None => {
+            conn.query_scalar(
+                include_str!("../../../queries/scan/get_artist_id.sql"),
+                (info.artist.as_str(),),
+            )
+            .await?
+        }
+    };
+
+    // albums are unique per title and artist, so an album the scanner added without an mbid is
+    // given the release's instead of being duplicated
+    let existing_album = conn
+        .query_scalar_optional::<i64>(
+            include_str!("../../../queries/library/find_album_by_title_artist.sql"),
+            (info.album.as_str(), artist_id),
+        )
+        .await?;
+
+    let album_id = match existing_album {
+        Some(id) => {
+            conn.execute(
+                include_str!("../../../queries/library/update_album_release.sql"),
+                (id, info.album_mbid.as_str(), info.release_date.unwrap_or(0)),
+            )
+            .await?;
+
+            id
+        }
+        None => {
+            let album_sortable = sortable_name(&info.album, None, ignore_articles);
+
+            conn.query_scalar(
+                include_str!("../../../queries/library/create_album_from_release.sql"),
+                (
+                    info.album.as_str(),
+                    album_sortable.as_str(),
+                    artist_id,
+                    info.release_date.unwrap_or(0),
+                    info.album_mbid.as_str(),
+                ),
+            )
+            .await?
+        }
+    };
+
+    conn.execute(
+        include_str!("../../../queries/library/update_track_release.sql"),
+        (
+            info.title.as_str(),
+            info.title.as_str(),
+            info.artist.as_str(),
+            album_id,
+            info.track_number.unwrap_or(0) as i64,
+            track_id,
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Stores album art that was found after the album was scanned, for example art downloaded from
+/// the Cover Art Archive.
+pub async fn set_album_art(
+    db: &TursoDatabase,
+    album_id: i64,
+    image: Vec<u8>,
+    thumb: Vec<u8>,
+) -> Result<()> {
+    let query = include_str!("../../../queries/library/update_album_art.sql");
+
+    let _guard = db.lock_writes().await;
+    let conn = db.connect()?;
+    conn.execute(query, (image.clone(), image, thumb.clone(), thumb, album_id))
+        .await?;
+
+    Ok(())
+}
+
 pub async fn playlist_has_track(
     db: &TursoDatabase,
     playlist_id: i64,
@@ -287,26 +510,136 @@ pub async fn playlist_has_track(
         .await
 }
 
+pub async fn add_radio_station(db: &TursoDatabase, name: &str, url: &str) -> Result<i64> {
+    let query = include_str!("../../../queries/radio/add_station.sql");
+
+    let _guard = db.lock_writes().await;
+    let conn = db.connect()?;
+    conn.execute_returning_id(query, [name, url]).await
+}
+
+pub async fn delete_radio_station(db: &TursoDatabase, station_id: i64) -> Result<()> {
+    let query = include_str!("../../../queries/radio/delete_station.sql");
+
+    let _guard = db.lock_writes().await;
+    let conn = db.connect()?;
+    conn.execute(query, [station_id]).await?;
+
+    Ok(())
+}
+
+pub async fn get_all_radio_stations(db: &TursoDatabase) -> Result<Arc<Vec<RadioStation>>> {
+    let query = include_str!("../../../queries/radio/get_all_stations.sql");
+
+    let conn = db.connect()?;
+    let stations = conn.query_map(query, (), RadioStation::from_row).await?;
+
+    Ok(Arc::new(stations))
+}
+
+/// Drops the tables holding the tracks, albums and artists and creates them again by re-running
+/// their migrations, so that the library can be rebuilt by a full scan even if its schema has
+/// drifted. The playlist items and play history that refer to the tracks are dropped with them,
+/// since the rebuilt tracks get new ids. Playlists themselves and radio stations are kept.
+pub async fn reset_library(db: &TursoDatabase) -> Result<()> {
+    let query = include_str!("../../../queries/library/reset_library.sql");
+
+    {
+        let _guard = db.lock_writes().await;
+        let conn = db.connect()?;
+        conn.execute_batch(query).await?;
+    }
+
+    db.run_migrations(library_migrations()).await
+}
+
+/// Records that a track has been played to the end.
+pub async fn add_play(db: &TursoDatabase, track_id: i64) -> Result<i64> {
+    let query = include_str!("../../../queries/history/add_play.sql");
+
+    let _guard = db.lock_writes().await;
+    let conn = db.connect()?;
+    conn.execute_returning_id(query, [track_id]).await
+}
+
+/// Counts how many times a track has been played to the end.
+pub async fn track_play_count(db: &TursoDatabase, track_id: i64) -> Result<i64> {
+    let query = include_str!("../../../queries/history/track_play_count.sql");
+
+    let conn = db.connect()?;
+    conn.query_scalar(query, [track_id]).await
+}
+
+/// Counts how many times tracks from an album have been played to the end, in total.
+pub async fn album_play_count(db: &TursoDatabase, album_id: i64) -> Result<i64> {
+    let query = include_str!("../../../queries/history/album_play_count.sql");
+
+    let conn = db.connect()?;
+    conn.query_scalar(query, [album_id]).await
+}
+
+/// Counts how many times each track from an album has been played to the end. Tracks that
+/// haven't been played are left out.
+pub async fn album_track_play_counts(
+    db: &TursoDatabase,
+    album_id: i64,
+) -> Result<HashMap<i64, i64>> {
+    let query = include_str!("../../../queries/history/album_track_play_counts.sql");
+
+    let conn = db.connect()?;
+    let counts = conn
+        .query_map(query, [album_id], |row| {
+            Ok((row.get::<i64>(0)?, row.get::<i64>(1)?))
+        })
+        .await?;
+
+    Ok(counts.into_iter().collect())
+}
+
+/// Lists the most recently played tracks, most recent first. Tracks that were played more than
+/// once only appear once, at the time they were last played.
+pub async fn recent_plays(db: &TursoDatabase, limit: i64) -> Result<Arc<Vec<Track>>> {
+    let query = include_str!("../../../queries/history/recent_plays.sql");
+
+    let conn = db.connect()?;
+    let tracks = conn.query_map(query, [limit], Track::from_row).await?;
+
+    Ok(Arc::new(tracks))
+}
+
 pub trait LibraryAccess {
     fn list_albums(&self, sort_method: AlbumSortMethod) -> Result<Vec<(u32, String)>>;
     fn list_tracks_in_album(&self, album_id: i64) -> Result<Arc<Vec<Track>>>;
+    fn list_random_tracks(&self, limit: i64) -> Result<Arc<Vec<TrackLocation>>>;
     fn get_album_by_id(&self, album_id: i64, method: AlbumMethod) -> Result<Arc<Album>>;
     fn get_artist_name_by_id(&self, artist_id: i64) -> Result<Arc<String>>;
     fn get_artist_by_id(&self, artist_id: i64) -> Result<Arc<Artist>>;
     fn get_track_by_id(&self, track_id: i64) -> Result<Arc<Track>>;
+    fn get_track_by_path(&self, path: &Path) -> Result<Option<Arc<Track>>>;
     fn list_albums_search(&self) -> Result<Vec<(u32, String, String)>>;
     fn add_playlist_item(&self, playlist_id: i64, track_id: i64) -> Result<i64>;
     fn create_playlist(&self, name: &str) -> Result<i64>;
     fn delete_playlist(&self, playlist_id: i64) -> Result<()>;
     fn get_all_playlists(&self) -> Result<Arc<Vec<PlaylistWithCount>>>;
     fn get_playlist(&self, playlist_id: i64) -> Result<Arc<Playlist>>;
-    fn get_playlist_track_files(&self, playlist_id: i64) -> Result<Arc<Vec<String>>>;
+    fn get_playlist_track_files(&self, playlist_id: i64) -> Result<Arc<Vec<PathBuf>>>;
     fn get_playlist_tracks(&self, playlist_id: i64) -> Result<Arc<Vec<(i64, i64, i64)>>>;
     fn move_playlist_item(&self, item_id: i64, new_position: i64) -> Result<()>;
     fn remove_playlist_item(&self, item_id: i64) -> Result<()>;
     fn get_playlist_item(&self, item_id: i64) -> Result<PlaylistItem>;
     fn get_track_stats(&self) -> Result<Arc<TrackStats>>;
+    fn count_albums(&self) -> Result<i64>;
+    fn count_tracks_in_folder(&self, folder: &Path) -> Result<i64>;
+    fn apply_release_info(&self, track_id: i64, info: &ReleaseInfo) -> Result<()>;
+    fn set_album_art(&self, album_id: i64, image: Vec<u8>, thumb: Vec<u8>) -> Result<()>;
     fn playlist_has_track(&self, playlist_id: i64, track_id: i64) -> Result<Option<i64>>;
+    fn add_radio_station(&self, name: &str, url: &str) -> Result<i64>;
+    fn delete_radio_station(&self, station_id: i64) -> Result<()>;
+    fn get_all_radio_stations(&self) -> Result<Arc<Vec<RadioStation>>>;
+    fn add_play(&self, track_id: i64) -> Result<i64>;
+    fn track_play_count(&self, track_id: i64) -> Result<i64>;
+    fn album_play_count(&self, album_id: i64) -> Result<i64>;
+    fn recent_plays(&self, limit: i64) -> Result<Arc<Vec<Track>>>;
 }
 
 impl LibraryAccess for App {
@@ -320,6 +653,11 @@ impl LibraryAccess for App {
         block_on(list_tracks_in_album(&pool.0, album_id))
     }
 
+    fn list_random_tracks(&self, limit: i64) -> Result<Arc<Vec<TrackLocation>>> {
+        let pool: &Pool = self.global();
+        block_on(list_random_tracks(&pool.0, limit))
+    }
+
     fn get_album_by_id(&self, album_id: i64, method: AlbumMethod) -> Result<Arc<Album>> {
         let pool: &Pool = self.global();
         block_on(get_album_by_id(&pool.0, album_id, method))
@@ -340,6 +678,11 @@ impl LibraryAccess for App {
         block_on(get_track_by_id(&pool.0, track_id))
     }
 
+    fn get_track_by_path(&self, path: &Path) -> Result<Option<Arc<Track>>> {
+        let pool: &Pool = self.global();
+        block_on(get_track_by_path(&pool.0, path))
+    }
+
     /// Lists all albums for searching. Returns a vector of tuples containing the id, name, and artist
     /// name.
     fn list_albums_search(&self) -> Result<Vec<(u32, String, String)>> {
@@ -372,7 +715,7 @@ impl LibraryAccess for App {
         block_on(get_playlist(&pool.0, playlist_id))
     }
 
-    fn get_playlist_track_files(&self, playlist_id: i64) -> Result<Arc<Vec<String>>> {
+    fn get_playlist_track_files(&self, playlist_id: i64) -> Result<Arc<Vec<PathBuf>>> {
         let pool: &Pool = self.global();
         block_on(get_playlist_track_files(&pool.0, playlist_id))
     }
@@ -402,8 +745,476 @@ impl LibraryAccess for App {
         block_on(get_track_stats(&pool.0))
     }
 
+    fn count_albums(&self) -> Result<i64> {
+        let pool: &Pool = self.global();
+        block_on(count_albums(&pool.0))
+    }
+
+    fn count_tracks_in_folder(&self, folder: &Path) -> Result<i64> {
+        let pool: &Pool = self.global();
+        block_on(count_tracks_in_folder(&pool.0, folder))
+    }
+
+    fn apply_release_info(&self, track_id: i64, info: &ReleaseInfo) -> Result<()> {
+        let pool: &Pool = self.global();
+        let ignore_articles = self
+            .global::<SettingsGlobal>()
+            .model
+            .read(self)
+            .scanning
+            .ignore_articles;
+        block_on(apply_release_info(&pool.0, track_id, info, ignore_articles))
+    }
+
+    fn set_album_art(&self, album_id: i64, image: Vec<u8>, thumb: Vec<u8>) -> Result<()> {
+        let pool: &Pool = self.global();
+        block_on(set_album_art(&pool.0, album_id, image, thumb))
+    }
+
     fn playlist_has_track(&self, playlist_id: i64, track_id: i64) -> Result<Option<i64>> {
         let pool: &Pool = self.global();
         block_on(playlist_has_track(&pool.0, playlist_id, track_id))
     }
+
+    fn add_radio_station(&self, name: &str, url: &str) -> Result<i64> {
+        let pool: &Pool = self.global();
+        block_on(add_radio_station(&pool.0, name, url))
+    }
+
+    fn delete_radio_station(&self, station_id: i64) -> Result<()> {
+        let pool: &Pool = self.global();
+        block_on(delete_radio_station(&pool.0, station_id))
+    }
+
+    fn get_all_radio_stations(&self) -> Result<Arc<Vec<RadioStation>>> {
+        let pool: &Pool = self.global();
+        block_on(get_all_radio_stations(&pool.0))
+    }
+
+    fn add_play(&self, track_id: i64) -> Result<i64> {
+        let pool: &Pool = self.global();
+        block_on(add_play(&pool.0, track_id))
+    }
+
+    fn track_play_count(&self, track_id: i64) -> Result<i64> {
+        let pool: &Pool = self.global();
+        block_on(track_play_count(&pool.0, track_id))
+    }
+
+    fn album_play_count(&self, album_id: i64) -> Result<i64> {
+        let pool: &Pool = self.global();
+        block_on(album_play_count(&pool.0, album_id))
+    }
+
+    fn album_track_play_counts(&self, album_id: i64) -> Result<HashMap<i64, i64>> {
+        let pool: &Pool = self.global();
+        block_on(album_track_play_counts(&pool.0, album_id))
+    }
+
+    fn recent_plays(&self, limit: i64) -> Result<Arc<Vec<Track>>> {
+        let pool: &Pool = self.global();
+        block_on(recent_plays(&pool.0, limit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Adds a track without an album, as the scanner would for a file with no album tag.
+    async fn insert_track(db: &TursoDatabase, title: &str, location: &str) -> i64 {
+        db.connect()
+            .unwrap()
+            .execute_returning_id(
+                "INSERT INTO track (title, title_sortable, duration, location)
+                VALUES ($1, $1, 180, $2)",
+                [title, location],
+            )
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn liked_songs_playlist_exists() {
+        block_on(async {
+            let db = TursoDatabase::open_in_memory().await.unwrap();
+            let playlists = get_all_playlists(&db).await.unwrap();
+
+            assert_eq!(playlists.len(), 1);
+            assert_eq!(playlists[0].name, "Liked Songs");
+            assert_eq!(playlists[0].track_count, 0);
+        });
+    }
+
+    #[test]
+    fn playlist_items_can_be_added_and_removed() {
+        block_on(async {
+            let db = TursoDatabase::open_in_memory().await.unwrap();
+            let playlist = create_playlist(&db, "Road trip").await.unwrap();
+            let first = insert_track(&db, "First", "/music/first.flac").await;
+            let second = insert_track(&db, "Second", "/music/second.flac").await;
+
+            let first_item = add_playlist_item(&db, playlist, first).await.unwrap();
+            add_playlist_item(&db, playlist, second).await.unwrap();
+
+            assert_eq!(
+                playlist_has_track(&db, playlist, first).await.unwrap(),
+                Some(first_item)
+            );
+            assert_eq!(
+                *get_playlist_track_files(&db, playlist).await.unwrap(),
+                vec![
+                    PathBuf::from("/music/first.flac"),
+                    PathBuf::from("/music/second.flac")
+                ]
+            );
+
+            remove_playlist_item(&db, first_item).await.unwrap();
+
+            assert_eq!(
+                playlist_has_track(&db, playlist, first).await.unwrap(),
+                None
+            );
+            assert_eq!(
+                *get_playlist_track_files(&db, playlist).await.unwrap(),
+                vec![PathBuf::from("/music/second.flac")]
+            );
+        });
+    }
+
+    #[test]
+    fn random_tracks_are_limited() {
+        block_on(async {
+            let db = TursoDatabase::open_in_memory().await.unwrap();
+            for n in 0..5 {
+                insert_track(&db, "Track", &format!("/music/{n}.flac")).await;
+            }
+
+            assert_eq!(list_random_tracks(&db, 3).await.unwrap().len(), 3);
+
+            let mut all: Vec<_> = list_random_tracks(&db, 100)
+                .await
+                .unwrap()
+                .iter()
+                .map(|track| track.id)
+                .collect();
+            all.sort_unstable();
+            all.dedup();
+            assert_eq!(all.len(), 5);
+        });
+    }
+
+    #[test]
+    fn resetting_keeps_playlists_and_stations() {
+        block_on(async {
+            let db = TursoDatabase::open_in_memory().await.unwrap();
+            let playlist = create_playlist(&db, "Road trip").await.unwrap();
+            let track = insert_track(&db, "Track", "/music/track.flac").await;
+            add_playlist_item(&db, playlist, track).await.unwrap();
+            add_play(&db, track).await.unwrap();
+            add_radio_station(&db, "Radio", "http://radio.example/stream")
+                .await
+                .unwrap();
+
+            reset_library(&db).await.unwrap();
+
+            assert!(
+                get_track_by_path(&db, Path::new("/music/track.flac"))
+                    .await
+                    .unwrap()
+                    .is_none()
+            );
+            assert_eq!(track_play_count(&db, track).await.unwrap(), 0);
+
+            let playlists = get_all_playlists(&db).await.unwrap();
+            assert_eq!(playlists.len(), 2);
+            assert!(playlists.iter().all(|playlist| playlist.track_count == 0));
+            assert_eq!(get_all_radio_stations(&db).await.unwrap().len(), 1);
+        });
+    }
+
+    #[test]
+    fn resetting_recreates_the_library_schema() {
+        block_on(async {
+            let db = TursoDatabase::open_in_memory().await.unwrap();
+            db.connect()
+                .unwrap()
+                .execute("ALTER TABLE track ADD COLUMN stray TEXT", ())
+                .await
+                .unwrap();
+
+            reset_library(&db).await.unwrap();
+
+            let conn = db.connect().unwrap();
+            assert!(
+                conn.execute("UPDATE track SET stray = NULL", ())
+                    .await
+                    .is_err()
+            );
+            assert!(
+                db.migration_status(library_migrations())
+                    .await
+                    .unwrap()
+                    .is_up_to_date()
+            );
+
+            insert_track(&db, "Track", "/music/track.flac").await;
+            assert!(
+                get_track_by_path(&db, Path::new("/music/track.flac"))
+                    .await
+                    .unwrap()
+                    .is_some()
+            );
+        });
+    }
+
+    #[test]
+    fn playlist_names_are_unique() {
+        block_on(async {
+            let db = TursoDatabase::open_in_memory().await.unwrap();
+            create_playlist(&db, "Favorites").await.unwrap();
+
+            assert!(create_playlist(&db, "Favorites").await.is_err());
+        });
+    }
+
+    #[test]
+    fn plays_are_counted_per_track_and_album() {
+        block_on(async {
+            let db = TursoDatabase::open_in_memory().await.unwrap();
+            let conn = db.connect().unwrap();
+            let album = conn
+                .execute_returning_id(
+                    "INSERT INTO album (title, title_sortable) VALUES ('Album', 'Album')",
+                    (),
+                )
+                .await
+                .unwrap();
+            let first = insert_track(&db, "First", "/music/first.flac").await;
+            let second = insert_track(&db, "Second", "/music/second.flac").await;
+            let other = insert_track(&db, "Other", "/music/other.flac").await;
+            conn.execute(
+                "UPDATE track SET album_id = $1 WHERE id IN ($2, $3)",
+                [album, first, second],
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(track_play_count(&db, first).await.unwrap(), 0);
+            assert_eq!(album_play_count(&db, album).await.unwrap(), 0);
+
+            add_play(&db, first).await.unwrap();
+            add_play(&db, first).await.unwrap();
+            add_play(&db, second).await.unwrap();
+            add_play(&db, other).await.unwrap();
+
+            assert_eq!(track_play_count(&db, first).await.unwrap(), 2);
+            assert_eq!(track_play_count(&db, second).await.unwrap(), 1);
+            assert_eq!(album_play_count(&db, album).await.unwrap(), 3);
+
+            let counts = album_track_play_counts(&db, album).await.unwrap();
+            assert_eq!(counts, HashMap::from([(first, 2), (second, 1)]));
+        });
+    }
+
+    #[test]
+    fn folder_tracks_exclude_subfolders() {
+        block_on(async {
+            let db = TursoDatabase::open_in_memory().await.unwrap();
+            let conn = db.connect().unwrap();
+            let track = insert_track(&db, "Track", "/music/mix/track.flac").await;
+            let nested = insert_track(&db, "Nested", "/music/mix/cd2/nested.flac").await;
+            conn.execute(
+                "UPDATE track SET folder = '/music/mix' WHERE id = $1",
+                [track],
+            )
+            .await
+            .unwrap();
+            conn.execute(
+                "UPDATE track SET folder = '/music/mix/cd2' WHERE id = $1",
+                [nested],
+            )
+            .await
+            .unwrap();
+
+            let tracks = list_tracks_in_folder(&db, Path::new("/music/mix"))
+                .await
+                .unwrap();
+
+            assert_eq!(tracks.len(), 1);
+            assert_eq!(tracks[0].location, PathBuf::from("/music/mix/track.flac"));
+        });
+    }
+
+    #[test]
+    fn sortable_names_follow_the_ignore_articles_setting() {
+        block_on(async {
+            let db = TursoDatabase::open_in_memory().await.unwrap();
+            db.connect()
+                .unwrap()
+                .execute_batch(
+                    "INSERT INTO artist (name, name_sortable) VALUES
+                        ('The Beatles', 'The Beatles'),
+                        ('A Tribe Called Quest', 'Tribe Called Quest, A'),
+                        ('The', 'The'),
+                        ('Anathema', 'Anathema');",
+                )
+                .await
+                .unwrap();
+
+            let names = || async {
+                db.connect()
+                    .unwrap()
+                    .query_map("SELECT name_sortable FROM artist ORDER BY id", (), |row| {
+                        Ok(row.get::<String>(0)?)
+                    })
+                    .await
+                    .unwrap()
+            };
+
+            update_sortable_names(&db, true).await.unwrap();
+            assert_eq!(
+                names().await,
+                vec!["Beatles", "Tribe Called Quest, A", "The", "Anathema"]
+            );
+
+            update_sortable_names(&db, false).await.unwrap();
+            assert_eq!(
+                names().await,
+                vec!["The Beatles", "Tribe Called Quest, A", "The", "Anathema"]
+            );
+        });
+    }
+
+    #[test]
+    fn deleting_a_track_deletes_its_plays() {
+        block_on(async {
+            let db = TursoDatabase::open_in_memory().await.unwrap();
+            let other = insert_track(&db, "Other", "/music/other.flac").await;
+            let track = insert_track(&db, "Track", "/music/track.flac").await;
+            add_play(&db, track).await.unwrap();
+            add_play(&db, other).await.unwrap();
+
+            let conn = db.connect().unwrap();
+            for query in [
+                include_str!("../../../queries/scan/delete_track_plays.sql"),
+                include_str!("../../../queries/scan/delete_track.sql"),
+            ] {
+                conn.execute(query, ["/music/track.flac"]).await.unwrap();
+            }
+
+            let tracks: i64 = conn
+                .query_scalar("SELECT COUNT(*) FROM track WHERE id = $1", [track])
+                .await
+                .unwrap();
+            let plays: i64 = conn
+                .query_scalar(
+                    "SELECT COUNT(*) FROM play_history WHERE track_id = $1",
+                    [track],
+                )
+                .await
+                .unwrap();
+            assert_eq!(tracks, 0);
+            assert_eq!(plays, 0);
+
+            // a track added later may be given the same id
+            let replacement = insert_track(&db, "New", "/music/new.flac").await;
+            assert_eq!(replacement, track);
+            assert_eq!(track_play_count(&db, replacement).await.unwrap(), 0);
+            assert_eq!(track_play_count(&db, other).await.unwrap(), 1);
+        });
+    }
+
+    #[test]
+    fn recent_plays_are_distinct_and_newest_first() {
+        block_on(async {
+            let db = TursoDatabase::open_in_memory().await.unwrap();
+            let first = insert_track(&db, "First", "/music/first.flac").await;
+            let second = insert_track(&db, "Second", "/music/second.flac").await;
+            let third = insert_track(&db, "Third", "/music/third.flac").await;
+
+            for track in [first, second, second, first, third, first] {
+                add_play(&db, track).await.unwrap();
+            }
+
+            let ids = |tracks: Arc<Vec<Track>>| tracks.iter().map(|t| t.id).collect::<Vec<_>>();
+
+            assert_eq!(
+                ids(recent_plays(&db, 10).await.unwrap()),
+                vec![first, third, second]
+            );
+            assert_eq!(ids(recent_plays(&db, 2).await.unwrap()), vec![first, third]);
+        });
+    }
+
+    #[test]
+    fn radio_stations_can_be_added_and_deleted() {
+        block_on(async {
+            let db = TursoDatabase::open_in_memory().await.unwrap();
+            let station = add_radio_station(&db, "Jazz", "https://example.com/jazz")
+                .await
+                .unwrap();
+
+            let stations = get_all_radio_stations(&db).await.unwrap();
+            assert_eq!(stations.len(), 1);
+            assert_eq!(stations[0].id, station);
+            assert_eq!(stations[0].url, "https://example.com/jazz");
+
+            delete_radio_station(&db, station).await.unwrap();
+            assert!(get_all_radio_stations(&db).await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn release_info_updates_the_existing_album() {
+        block_on(async {
+            let db = TursoDatabase::open_in_memory().await.unwrap();
+            let conn = db.connect().unwrap();
+            let artist = conn
+                .execute_returning_id(
+                    "INSERT INTO artist (name, name_sortable) VALUES ('Artist', 'Artist')",
+                    (),
+                )
+                .await
+                .unwrap();
+            // as the scanner adds an album without an mbid
+            let album = conn
+                .execute_returning_id(
+                    "INSERT INTO album (title, title_sortable, artist_id)
+                    VALUES ('Album', 'Album', $1)",
+                    [artist],
+                )
+                .await
+                .unwrap();
+            let track = insert_track(&db, "Track", "/music/track.flac").await;
+
+            let info = ReleaseInfo {
+                title: "Track".to_string(),
+                artist: "Artist".to_string(),
+                album: "Album".to_string(),
+                album_mbid: "release-mbid".to_string(),
+                release_date: Some(946684800),
+                track_number: Some(1),
+            };
+            apply_release_info(&db, track, &info, true).await.unwrap();
+
+            let albums = conn
+                .query_scalar::<i64>("SELECT COUNT(*) FROM album", ())
+                .await
+                .unwrap();
+            assert_eq!(albums, 1);
+
+            let mbid = conn
+                .query_scalar::<String>("SELECT mbid FROM album WHERE id = $1", [album])
+                .await
+                .unwrap();
+            assert_eq!(mbid, "release-mbid");
+
+            let track_album = conn
+                .query_scalar::<i64>("SELECT album_id FROM track WHERE id = $1", [track])
+                .await
+                .unwrap();
+            assert_eq!(track_album, album);
+        });
+    }
 }
@@ -1,3 +1,5 @@
+pub mod cue;
 pub mod db;
+pub mod paths;
 pub mod scan;
 pub mod types;
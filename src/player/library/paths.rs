@@ -0,0 +1,76 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+/// Prefix for paths that couldn't be stored as-is because they aren't valid UTF-8. Paths in the
+/// library are always absolute, so a real path can never start with this.
+const RAW_PREFIX: &str = "raw:";
+
+/// Converts a path into the form it's stored in the database. Valid UTF-8 paths are stored
+/// unchanged; other paths (which are legal on Linux) have their bytes percent-encoded after a
+/// `raw:` prefix, so that they can still be indexed and played.
+pub fn encode_path(path: &Path) -> Cow<'_, str> {
+    if let Some(path) = path.to_str() {
+        return Cow::Borrowed(path);
+    }
+
+    let encoded = urlencoding::encode_binary(path.as_os_str().as_encoded_bytes());
+    Cow::Owned(format!("{RAW_PREFIX}{encoded}"))
+}
+
+/// Converts a path read from the database back into a path. See [`encode_path`].
+pub fn decode_path(stored: &str) -> PathBuf {
+    let Some(encoded) = stored.strip_prefix(RAW_PREFIX) else {
+        return PathBuf::from(stored);
+    };
+
+    let bytes = urlencoding::decode_binary(encoded.as_bytes());
+
+    #[cfg(unix)]
+    {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        PathBuf::from(OsStr::from_bytes(&bytes))
+    }
+
+    // non-UTF-8 paths can only be created on unix, so this can only be reached if the database
+    // was moved between platforms
+    #[cfg(not(unix))]
+    {
+        PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf8_paths_are_stored_unchanged() {
+        let path = Path::new("/music/Sigur Rós/Ágætis byrjun/01 Intro.flac");
+
+        assert_eq!(encode_path(path), path.to_str().unwrap());
+        assert_eq!(decode_path(&encode_path(path)), path);
+    }
+
+    #[test]
+    fn percent_signs_in_utf8_paths_are_not_decoded() {
+        let stored = "/music/100% Hits/track.mp3";
+
+        assert_eq!(decode_path(stored), Path::new(stored));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_paths_round_trip() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        // 0xE9 is "é" in Latin-1, which is not valid UTF-8 on its own
+        let path = Path::new(OsStr::from_bytes(b"/music/caf\xe9/01 track.flac"));
+        let stored = encode_path(path);
+
+        assert!(stored.starts_with(RAW_PREFIX));
+        assert_eq!(decode_path(&stored), path);
+    }
+}
@@ -1,31 +1,43 @@
 use std::{
+    borrow::Cow,
     fs::{self, File},
     io::{BufReader, Cursor, Write},
     path::{Path, PathBuf},
-    time::SystemTime,
+    time::{Duration, Instant, SystemTime},
 };
 
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
 use anyhow::Context;
 use async_channel::{Receiver, Sender};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use globwalk::GlobWalkerBuilder;
 use gpui::{App, Global};
 use image::{DynamicImage, EncodableLayout, codecs::jpeg::JpegEncoder, imageops::thumbnail};
 use smol::{Timer, block_on};
 use tracing::{debug, error, info, warn};
 
-use crate::shared::db::{TursoConnection, TursoDatabase};
+use crate::shared::db::{TursoConnection, TursoDatabase, errors::DbError};
 
 use crate::{
+    player::library::{
+        cue::{cue_track_location, is_cue_sheet, is_cue_track_location, read_cue_sheet},
+        db::{reset_library, update_sortable_names},
+        paths::{decode_path, encode_path},
+        types::TrackSegment,
+    },
     player::media::{
         builtin::symphonia::SymphoniaProvider,
         metadata::Metadata,
         traits::{MediaPlugin, MediaProvider},
     },
-    shared::settings::scan::ScanSettings,
-    player::ui::{app::get_dirs, models::Models},
+    shared::{cache::CacheCategory, settings::scan::ScanSettings, util::write_file_atomically},
+    player::ui::{app::data_dir, models::Models},
 };
 
+/// How often the scan record is saved while a scan is running, so that an interrupted scan doesn't
+/// have to start over.
+const SCAN_RECORD_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum ScanEvent {
     Cleaning,
@@ -35,10 +47,14 @@ pub enum ScanEvent {
     ScanCompleteIdle,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone)]
 enum ScanCommand {
     Scan,
     Stop,
+    Refresh(PathBuf),
+    AddPaths(Vec<PathBuf>),
+    RemovePath { path: PathBuf, purge: bool },
+    Rebuild,
 }
 
 pub struct ScanInterface {
@@ -79,6 +95,57 @@ impl ScanInterface {
         .detach();
     }
 
+    /// Re-reads the tags of a single track and updates it in place, without rescanning the rest
+    /// of the library.
+    pub fn refresh_track(&self, path: PathBuf) {
+        let command_tx = self.command_tx.clone();
+        smol::spawn(async move {
+            command_tx
+                .send(ScanCommand::Refresh(path))
+                .await
+                .expect("could not send tx");
+        })
+        .detach();
+    }
+
+    /// Adds folders to the library and scans only those folders.
+    pub fn add_paths(&self, paths: Vec<PathBuf>) {
+        let command_tx = self.command_tx.clone();
+        smol::spawn(async move {
+            command_tx
+                .send(ScanCommand::AddPaths(paths))
+                .await
+                .expect("could not send tx");
+        })
+        .detach();
+    }
+
+    /// Removes a folder from the library. If `purge` is true, the tracks in the folder are
+    /// removed from the database as well; otherwise they're kept until they no longer exist.
+    pub fn remove_path(&self, path: PathBuf, purge: bool) {
+        let command_tx = self.command_tx.clone();
+        smol::spawn(async move {
+            command_tx
+                .send(ScanCommand::RemovePath { path, purge })
+                .await
+                .expect("could not send tx");
+        })
+        .detach();
+    }
+
+    /// Empties the library and scans every folder again from scratch, for when the database has
+    /// been damaged.
+    pub fn rebuild(&self) {
+        let command_tx = self.command_tx.clone();
+        smol::spawn(async move {
+            command_tx
+                .send(ScanCommand::Rebuild)
+                .await
+                .expect("could not send tx");
+        })
+        .detach();
+    }
+
     pub fn start_broadcast(&mut self, cx: &mut App) {
         let mut events_rx = None;
         std::mem::swap(&mut self.events_rx, &mut events_rx);
@@ -119,15 +186,27 @@ pub struct ScanThread {
     command_rx: Receiver<ScanCommand>,
     pool: TursoDatabase,
     scan_settings: ScanSettings,
-    visited: Vec<PathBuf>,
+    /// The compiled `exclude_patterns` from the scan settings.
+    exclude: GlobSet,
+    /// The canonicalized scan paths, used to find hidden folders relative to the library root.
+    scan_roots: Vec<PathBuf>,
+    /// The folders that have been scanned during the current scan. Folders are identified by
+    /// [`DirId`] rather than path, so a folder reachable through more than one symlink is only
+    /// scanned once, and symlink cycles end.
+    visited: AHashSet<DirId>,
     discovered: Vec<PathBuf>,
     to_process: Vec<PathBuf>,
     scan_state: ScanState,
     provider_table: Vec<(&'static [&'static str], Box<dyn MediaProvider>)>,
     scan_record: AHashMap<PathBuf, u64>,
     scan_record_path: Option<PathBuf>,
+    /// When the scan record was last written.
+    last_record_write: Instant,
     scanned: u64,
     discovered_total: u64,
+    /// The number of times a write had to be retried because the database was locked during the
+    /// current scan.
+    lock_retries: u64,
 }
 
 struct TrackCleanupContext {
@@ -156,6 +235,28 @@ fn file_is_scannable_with_provider(path: &Path, exts: &&[&str]) -> bool {
     false
 }
 
+/// Lists the audio files directly inside a folder that can be played, sorted by file name. Files
+/// in subfolders aren't included. At most `limit` files are returned.
+pub fn list_folder_tracks(folder: &Path, limit: usize) -> std::io::Result<Vec<PathBuf>> {
+    let provider_table = build_provider_table();
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(folder)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|v| v.is_file()))
+        .map(|entry| entry.path())
+        .filter(|path| {
+            provider_table
+                .iter()
+                .any(|(exts, _)| file_is_scannable_with_provider(path, exts))
+        })
+        .collect();
+
+    paths.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    paths.truncate(limit);
+
+    Ok(paths)
+}
+
 type FileInformation = (Metadata, u64, Option<Box<[u8]>>);
 
 fn scan_file_with_provider(
@@ -193,9 +294,119 @@ fn scan_path_for_album_art(path: &Path) -> Option<Box<[u8]>> {
     None
 }
 
-fn is_db_locked(err: &anyhow::Error) -> bool {
-    err.chain()
-        .any(|cause| cause.to_string().contains("database is locked"))
+/// Uniquely identifies a folder on disk.
+#[cfg(unix)]
+type DirId = (u64, u64);
+#[cfg(not(unix))]
+type DirId = PathBuf;
+
+/// Returns the identity of the given folder: the device and inode numbers where they're
+/// available, or the canonicalized path elsewhere.
+fn dir_id(path: &Path) -> Option<DirId> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        fs::metadata(path)
+            .ok()
+            .map(|metadata| (metadata.dev(), metadata.ino()))
+    }
+
+    #[cfg(not(unix))]
+    {
+        path.canonicalize().ok()
+    }
+}
+
+fn build_exclude_set(patterns: &[String]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+
+    for pattern in patterns {
+        match Glob::new(pattern) {
+            Ok(glob) => {
+                builder.add(glob);
+            }
+            Err(e) => warn!("Ignoring invalid exclude pattern {:?}: {}", pattern, e),
+        }
+    }
+
+    builder.build().unwrap_or_else(|e| {
+        error!("Failed to build exclude patterns, nothing will be excluded: {}", e);
+        GlobSet::empty()
+    })
+}
+
+/// Returns the name an album or artist should be sorted by. The sort name tag is used if present;
+/// otherwise the display name is used, optionally with a leading article removed.
+pub fn sortable_name(name: &str, sort_tag: Option<&String>, ignore_articles: bool) -> String {
+    if let Some(sort_tag) = sort_tag.filter(|v| !v.trim().is_empty()) {
+        return sort_tag.clone();
+    }
+
+    if ignore_articles {
+        for article in ["the ", "a ", "an "] {
+            let stripped = name
+                .get(..article.len())
+                .filter(|prefix| prefix.eq_ignore_ascii_case(article))
+                .map(|_| name[article.len()..].trim_start());
+
+            if let Some(stripped) = stripped.filter(|v| !v.is_empty()) {
+                return stripped.to_string();
+            }
+        }
+    }
+
+    name.to_string()
+}
+
+/// Prepares album art for storage in the library, returning the full size image and a 70x70
+/// thumbnail. Images larger than 1024x1024 are scaled down and re-encoded as JPEG.
+pub fn process_album_art(image: &[u8]) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    let mut decoded = image::ImageReader::new(Cursor::new(image))
+        .with_guessed_format()?
+        .decode()?
+        .into_rgb8();
+
+    // for some reason, thumbnails don't load properly when saved as rgb8
+    // also, into_rgba8() causes the application to crash on certain images
+    //
+    // no, I don't no why, and no I can't fix it upstream
+    // this will have to do for now
+    let decoded_rgba = DynamicImage::ImageRgb8(decoded.clone()).into_rgba8();
+
+    let thumb = thumbnail(&decoded_rgba, 70, 70);
+
+    let mut buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
+
+    thumb
+        .write_to(&mut buf, image::ImageFormat::Bmp)
+        .expect("i don't know how Cursor could fail");
+    buf.flush().expect("could not flush buffer");
+
+    let resized = if decoded.dimensions().0 <= 1024 || decoded.dimensions().1 <= 1024 {
+        image.to_vec()
+    } else {
+        decoded = image::imageops::resize(
+            &decoded,
+            1024,
+            1024,
+            image::imageops::FilterType::Lanczos3,
+        );
+        let mut buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
+        let mut encoder = JpegEncoder::new_with_quality(&mut buf, 70);
+
+        encoder.encode(
+            decoded.as_bytes(),
+            decoded.width(),
+            decoded.height(),
+            image::ExtendedColorType::Rgb8,
+        )?;
+        buf.flush()?;
+
+        buf.get_mut().clone()
+    };
+
+    Ok((resized, buf.get_mut().clone()))
 }
 
 /// Escape a string for use as a SQL string literal
@@ -212,20 +423,31 @@ impl ScanThread {
         std::thread::Builder::new()
             .name("scanner".to_string())
             .spawn(move || {
+                let exclude = build_exclude_set(&settings.exclude_patterns);
+                let scan_roots = settings
+                    .paths
+                    .iter()
+                    .filter_map(|path| path.canonicalize().ok())
+                    .collect();
+
                 let mut thread = ScanThread {
                     event_tx: events_tx,
                     command_rx: commands_rx,
                     pool,
-                    visited: Vec::new(),
+                    visited: AHashSet::new(),
                     discovered: Vec::new(),
                     to_process: Vec::new(),
                     scan_state: ScanState::Idle,
                     provider_table: build_provider_table(),
                     scan_settings: settings,
+                    exclude,
+                    scan_roots,
                     scan_record: AHashMap::new(),
                     scan_record_path: None,
+                    last_record_write: Instant::now(),
                     scanned: 0,
                     discovered_total: 0,
+                    lock_retries: 0,
                 };
 
                 thread.run();
@@ -236,12 +458,15 @@ impl ScanThread {
     }
 
     fn run(&mut self) {
-        let dirs = get_dirs();
-        let directory = dirs.data_dir();
-        if !directory.exists() {
-            fs::create_dir(directory).expect("couldn't create data directory");
+        let file_path = CacheCategory::Scan.dir().join("scan_record.json");
+
+        // the scan record used to be stored in the data directory, move it if it's still there
+        let legacy_path = data_dir().join("scan_record.json");
+        if legacy_path.exists() && !file_path.exists() {
+            if let Err(e) = fs::rename(&legacy_path, &file_path) {
+                warn!("could not move scan record to cache directory: {:?}", e);
+            }
         }
-        let file_path = directory.join("scan_record.json");
 
         if file_path.exists() {
             let file = File::open(&file_path);
@@ -251,9 +476,14 @@ impl ScanThread {
             };
             let reader = BufReader::new(file);
 
-            match serde_json::from_reader(reader) {
+            // paths are stored the same way as in the database, since JSON can't hold non-UTF-8
+            // paths
+            match serde_json::from_reader::<_, AHashMap<String, u64>>(reader) {
                 Ok(scan_record) => {
-                    self.scan_record = scan_record;
+                    self.scan_record = scan_record
+                        .into_iter()
+                        .map(|(path, timestamp)| (decode_path(&path), timestamp))
+                        .collect();
                 }
                 Err(e) => {
                     error!("could not read scan record: {:?}", e);
@@ -270,7 +500,14 @@ impl ScanThread {
             // TODO: start file watcher to update db automatically when files are added or removed
             match self.scan_state {
                 ScanState::Idle => {
-                    std::thread::sleep(std::time::Duration::from_millis(100));
+                    // there's nothing to do until the next command arrives
+                    match self.command_rx.recv_blocking() {
+                        Ok(command) => self.handle_command(command),
+                        Err(_) => {
+                            info!("Scan interface dropped, stopping scan thread");
+                            return;
+                        }
+                    }
                 }
                 ScanState::Cleanup => {
                     self.cleanup();
@@ -287,39 +524,213 @@ impl ScanThread {
 
     fn read_commands(&mut self) {
         while let Ok(command) = self.command_rx.try_recv() {
-            match command {
-                ScanCommand::Scan => {
-                    if self.scan_state == ScanState::Idle {
-                        self.discovered = self.scan_settings.paths.clone();
-                        self.scan_state = ScanState::Cleanup;
-                        self.scanned = 0;
-                        self.discovered_total = 0;
-
-                        let event_tx = self.event_tx.clone();
-                        smol::spawn(async move {
-                            event_tx
-                                .send(ScanEvent::Cleaning)
-                                .await
-                                .expect("could not send scan started event");
-                        })
-                        .detach();
-                    }
-                }
-                ScanCommand::Stop => {
-                    self.scan_state = ScanState::Idle;
-                    self.visited.clear();
-                    self.discovered.clear();
-                    self.to_process.clear();
-                }
-            }
+            self.handle_command(command);
         }
 
         if self.scan_state == ScanState::Discovering {
             self.discover();
         } else if self.scan_state == ScanState::Scanning {
             self.scan();
+        }
+    }
+
+    fn handle_command(&mut self, command: ScanCommand) {
+        match command {
+            ScanCommand::Scan => {
+                if self.scan_state == ScanState::Idle {
+                    self.visited.clear();
+                    self.discovered = self.scan_settings.paths.clone();
+                    self.scan_state = ScanState::Cleanup;
+                    self.scanned = 0;
+                    self.discovered_total = 0;
+                    self.last_record_write = Instant::now();
+
+                    let event_tx = self.event_tx.clone();
+                    smol::spawn(async move {
+                        event_tx
+                            .send(ScanEvent::Cleaning)
+                            .await
+                            .expect("could not send scan started event");
+                    })
+                    .detach();
+                }
+            }
+            ScanCommand::Stop => {
+                if self.scan_state == ScanState::Idle {
+                    return;
+                }
+
+                info!("Scan stopped with {} files left to process", self.to_process.len());
+
+                // discovered files are added to the scan record before they're processed, so
+                // remove the ones that weren't, otherwise they'd be skipped by the next scan
+                for path in self.to_process.drain(..) {
+                    self.scan_record.remove(&path);
+                }
+
+                self.scan_state = ScanState::Idle;
+                self.visited.clear();
+                self.discovered.clear();
+                self.write_scan_record();
+
+                let event_tx = self.event_tx.clone();
+                smol::spawn(async move {
+                    event_tx
+                        .send(ScanEvent::ScanCompleteIdle)
+                        .await
+                        .expect("could not send scan stopped event");
+                })
+                .detach();
+            }
+            ScanCommand::Refresh(path) => {
+                self.refresh_track(&path);
+            }
+            ScanCommand::AddPaths(paths) => {
+                self.add_paths(paths);
+            }
+            ScanCommand::RemovePath { path, purge } => {
+                self.remove_path(&path, purge);
+            }
+            ScanCommand::Rebuild => {
+                self.rebuild();
+            }
+        }
+    }
+
+    fn rebuild(&mut self) {
+        info!("Rebuilding the library from scratch");
+
+        // anything in progress is abandoned, the full scan below covers it
+        self.scan_state = ScanState::Idle;
+        self.visited.clear();
+        self.discovered.clear();
+        self.to_process.clear();
+
+        if let Err(err) = block_on(reset_library(&self.pool)) {
+            error!("Could not reset the library: {:?}", err);
+
+            let event_tx = self.event_tx.clone();
+            smol::spawn(async move {
+                event_tx
+                    .send(ScanEvent::ScanCompleteIdle)
+                    .await
+                    .expect("could not send scan stopped event");
+            })
+            .detach();
+            return;
+        }
+
+        self.scan_record.clear();
+        self.write_scan_record();
+
+        self.handle_command(ScanCommand::Scan);
+    }
+
+    fn add_paths(&mut self, paths: Vec<PathBuf>) {
+        for path in &paths {
+            if !self.scan_settings.paths.contains(path) {
+                self.scan_settings.paths.push(path.clone());
+            }
+
+            if let Ok(root) = path.canonicalize() {
+                if !self.scan_roots.contains(&root) {
+                    self.scan_roots.push(root);
+                }
+            }
+        }
+
+        info!("Scanning newly added folders {:?}", paths);
+
+        // skip cleanup, nothing in the new folders can have been removed yet
+        if self.scan_state == ScanState::Idle {
+            self.visited.clear();
+            self.scanned = 0;
+            self.discovered_total = 0;
+            self.discovered = paths;
+            self.scan_state = ScanState::Discovering;
+
+            let event_tx = self.event_tx.clone();
+            smol::spawn(async move {
+                event_tx
+                    .send(ScanEvent::DiscoverProgress(0))
+                    .await
+                    .expect("could not send scan started event");
+            })
+            .detach();
         } else {
-            std::thread::sleep(std::time::Duration::from_millis(100));
+            // a scan is already running, so fold the new folders into it
+            self.discovered.extend(paths);
+
+            if self.scan_state == ScanState::Scanning {
+                self.scan_state = ScanState::Discovering;
+            }
+        }
+    }
+
+    fn remove_path(&mut self, path: &PathBuf, purge: bool) {
+        self.scan_settings.paths.retain(|v| v != path);
+
+        let root = path.canonicalize().unwrap_or_else(|_| path.clone());
+        self.scan_roots.retain(|v| *v != root);
+
+        // files that are also inside another library folder stay
+        let scan_roots = &self.scan_roots;
+        let is_removed = |file: &PathBuf| {
+            file.starts_with(&root) && !scan_roots.iter().any(|other| file.starts_with(other))
+        };
+
+        self.discovered.retain(|v| !is_removed(v));
+
+        let mut unprocessed = Vec::new();
+        self.to_process.retain(|v| {
+            let removed = is_removed(v);
+            if removed {
+                unprocessed.push(v.clone());
+            }
+            !removed
+        });
+
+        // the unprocessed files were added to the scan record when they were discovered
+        for file in &unprocessed {
+            self.scan_record.remove(file);
+        }
+
+        if !purge {
+            info!(
+                "Removed folder {:?} from the library, keeping its tracks",
+                path
+            );
+            return;
+        }
+
+        let removed: Vec<PathBuf> = self
+            .scan_record
+            .keys()
+            .filter(|v| is_removed(v))
+            .cloned()
+            .collect();
+
+        info!(
+            "Removing folder {:?} and its {} tracks from the library",
+            path,
+            removed.len()
+        );
+
+        for file in removed {
+            block_on(self.delete_track(&file));
+        }
+
+        self.write_scan_record();
+
+        if self.scan_state == ScanState::Idle {
+            let event_tx = self.event_tx.clone();
+            smol::spawn(async move {
+                event_tx
+                    .send(ScanEvent::ScanCompleteIdle)
+                    .await
+                    .expect("could not send scan complete event");
+            })
+            .detach();
         }
     }
 
@@ -334,23 +745,56 @@ impl ScanThread {
             Err(_) => return false,
         };
 
-        for (exts, _) in self.provider_table.iter() {
-            let x = file_is_scannable_with_provider(path, exts);
+        let scannable = is_cue_sheet(path)
+            || self
+                .provider_table
+                .iter()
+                .any(|(exts, _)| file_is_scannable_with_provider(path, exts));
 
-            if !x {
-                continue;
-            }
-            if let Some(last_scan) = self.scan_record.get(path) {
-                if *last_scan == timestamp {
-                    return false;
-                }
+        if !scannable {
+            return false;
+        }
+
+        if let Some(last_scan) = self.scan_record.get(path) {
+            if *last_scan == timestamp {
+                return false;
             }
+        }
+
+        self.scan_record.insert(path.clone(), timestamp);
+        true
+    }
 
-            self.scan_record.insert(path.clone(), timestamp);
+    /// Returns true if the given (canonicalized) path matches one of the exclude patterns, or is
+    /// in a hidden folder when hidden folders are skipped.
+    fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        if self.exclude.is_match(path) {
             return true;
         }
 
-        false
+        if !self.scan_settings.skip_hidden_dirs {
+            return false;
+        }
+
+        // only folders inside the library count, the library itself may be in a hidden folder
+        let Some(relative) = self
+            .scan_roots
+            .iter()
+            .find_map(|root| path.strip_prefix(root).ok())
+        else {
+            return false;
+        };
+
+        let dirs = if is_dir {
+            Some(relative)
+        } else {
+            relative.parent()
+        };
+
+        dirs.is_some_and(|dirs| {
+            dirs.components()
+                .any(|component| component.as_os_str().to_string_lossy().starts_with('.'))
+        })
     }
 
     fn discover(&mut self) {
@@ -361,18 +805,74 @@ impl ScanThread {
 
         let path = self.discovered.pop().unwrap();
 
-        if self.visited.contains(&path) {
+        let Some(id) = dir_id(&path) else {
+            warn!("Could not read folder {:?}, skipping", path);
+            return;
+        };
+
+        if !self.visited.insert(id) {
             return;
         }
 
-        let paths = fs::read_dir(&path).unwrap();
+        let entries: Vec<_> = match fs::read_dir(&path) {
+            Ok(entries) => entries.flatten().collect(),
+            Err(e) => {
+                warn!("Could not read folder {:?}, skipping: {}", path, e);
+                return;
+            }
+        };
+
+        // files that a cue sheet splits into tracks are added through the sheet instead
+        let split_files: AHashSet<PathBuf> = entries
+            .iter()
+            .map(|entry| entry.path())
+            .filter(|entry_path| is_cue_sheet(entry_path))
+            .filter_map(|cue| {
+                let sheet = read_cue_sheet(&cue).ok()?;
+                let file = sheet.single_file()?;
+                path.join(&file.name).canonicalize().ok()
+            })
+            .collect();
+
+        for entry in entries {
+            let entry_path = entry.path();
+            let is_symlink = entry.file_type().is_ok_and(|v| v.is_symlink());
+
+            if is_symlink && !self.scan_settings.follow_symlinks && entry_path.is_dir() {
+                debug!("Skipping symlinked folder {:?}", entry_path);
+                continue;
+            }
 
-        for paths in paths {
-            // TODO: handle errors
             // this might be slower than just reading the path directly but this prevents loops
-            let path = paths.unwrap().path().canonicalize().unwrap();
-            if path.is_dir() {
+            let path = match entry_path.canonicalize() {
+                Ok(path) => path,
+                Err(e) => {
+                    warn!("Could not resolve {:?}, skipping: {}", entry_path, e);
+                    continue;
+                }
+            };
+            let is_dir = path.is_dir();
+
+            if self.is_excluded(&path, is_dir) {
+                debug!("Skipping excluded path {:?}", path);
+                continue;
+            }
+
+            if is_dir {
+                if is_symlink && dir_id(&path).is_some_and(|id| self.visited.contains(&id)) {
+                    info!(
+                        "Symlink {:?} points to already scanned folder {:?}, skipping",
+                        entry_path, path
+                    );
+                    continue;
+                }
+
                 self.discovered.push(path);
+            } else if split_files.contains(&path) {
+                // the whole file may have been added before its cue sheet was
+                if self.scan_record.contains_key(&path) {
+                    block_on(self.delete_track(&path));
+                }
             } else if self.file_is_scannable(&path) {
                 self.to_process.push(path);
 
@@ -391,8 +891,6 @@ impl ScanThread {
                 }
             }
         }
-
-        self.visited.push(path.clone());
     }
 
     async fn insert_artist(&self, conn: &TursoConnection, metadata: &Metadata) -> anyhow::Result<Option<i64>> {
@@ -408,7 +906,12 @@ impl ScanThread {
                 include_str!("../../../queries/scan/create_artist.sql"),
                 (
                     artist.as_str(),
-                    metadata.artist_sort.as_ref().unwrap_or(&artist).as_str(),
+                    sortable_name(
+                        &artist,
+                        metadata.artist_sort.as_ref(),
+                        self.scan_settings.ignore_articles,
+                    )
+                    .as_str(),
                 ),
                 |row| Ok(row.get::<i64>(0)?),
             )
@@ -461,53 +964,10 @@ impl ScanThread {
 
         // Album doesn't exist, create it
         let (resized_image, thumb) = match image {
+            // if there is a decode error, just ignore it and pretend there is no image
             Some(image) => {
-                // if there is a decode error, just ignore it and pretend there is no image
-                let mut decoded = image::ImageReader::new(Cursor::new(&image))
-                    .with_guessed_format()?
-                    .decode()?
-                    .into_rgb8();
-
-                // for some reason, thumbnails don't load properly when saved as rgb8
-                // also, into_rgba8() causes the application to crash on certain images
-                //
-                // no, I don't no why, and no I can't fix it upstream
-                // this will have to do for now
-                let decoded_rgba = DynamicImage::ImageRgb8(decoded.clone()).into_rgba8();
-
-                let thumb = thumbnail(&decoded_rgba, 70, 70);
-
-                let mut buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
-
-                thumb
-                    .write_to(&mut buf, image::ImageFormat::Bmp)
-                    .expect("i don't know how Cursor could fail");
-                buf.flush().expect("could not flush buffer");
-
-                let resized = if decoded.dimensions().0 <= 1024 || decoded.dimensions().1 <= 1024 {
-                    image.clone().to_vec()
-                } else {
-                    decoded = image::imageops::resize(
-                        &decoded,
-                        1024,
-                        1024,
-                        image::imageops::FilterType::Lanczos3,
-                    );
-                    let mut buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
-                    let mut encoder = JpegEncoder::new_with_quality(&mut buf, 70);
-
-                    encoder.encode(
-                        decoded.as_bytes(),
-                        decoded.width(),
-                        decoded.height(),
-                        image::ExtendedColorType::Rgb8,
-                    )?;
-                    buf.flush()?;
-
-                    buf.get_mut().clone()
-                };
-
-                (Some(resized), Some(buf.get_mut().clone()))
+                let (resized, thumb) = process_album_art(image)?;
+                (Some(resized), Some(thumb))
             }
             None => (None, None),
         };
@@ -536,7 +996,12 @@ impl ScanThread {
             insert_sql,
             (
                 album.as_str(),
-                metadata.sort_album.as_ref().unwrap_or(album).as_str(),
+                sortable_name(
+                    album,
+                    metadata.sort_album.as_ref(),
+                    self.scan_settings.ignore_articles,
+                )
+                .as_str(),
                 artist_id_val,
                 date_val,
                 label_val,
@@ -595,6 +1060,7 @@ impl ScanThread {
         album_id: Option<i64>,
         path: &Path,
         length: u64,
+        segment: Option<&TrackSegment>,
     ) -> anyhow::Result<()> {
         if album_id.is_none() {
             return Ok(());
@@ -613,20 +1079,18 @@ impl ScanThread {
             )
             .await?;
 
-        let parent_str = parent
-            .to_str()
-            .ok_or_else(|| anyhow::anyhow!("parent path contains invalid UTF-8: {:?}", parent))?;
+        let parent_str = encode_path(parent);
 
         match existing_path {
             Some(path) => {
-                if path.as_str() != parent.as_os_str() {
+                if path != parent_str {
                     return Ok(());
                 }
             }
             None => {
                 conn.execute(
                     include_str!("../../../queries/scan/create_album_path.sql"),
-                    (album_id_unwrapped, parent_str, disc_num),
+                    (album_id_unwrapped, parent_str.as_ref(), disc_num),
                 )
                 .await?;
             }
@@ -637,15 +1101,11 @@ impl ScanThread {
             .clone()
             .or_else(|| {
                 path.file_name()
-                    .and_then(|x| x.to_str())
-                    .map(|x| x.to_string())
+                    .map(|x| x.to_string_lossy().to_string())
             })
             .ok_or_else(|| anyhow::anyhow!("failed to retrieve filename"))?;
 
-        let path_str = path
-            .to_str()
-            .ok_or_else(|| anyhow::anyhow!("path contains invalid UTF-8: {:?}", path))?
-            .to_string();
+        let path_str = encode_path(path);
 
         let genre = metadata.genre.as_deref().unwrap_or("");
         let artist = metadata.artist.as_deref().unwrap_or("");
@@ -658,14 +1118,24 @@ impl ScanThread {
         // Escape all string values for SQL injection safety
         let name_escaped = sql_escape(&name);
         let path_escaped = sql_escape(&path_str);
-        let parent_escaped = sql_escape(parent_str);
+        let parent_escaped = sql_escape(&parent_str);
         let genre_escaped = sql_escape(genre);
         let artist_escaped = sql_escape(artist);
+        let (file, start_ms, end_ms) = match segment {
+            Some(segment) => (
+                format!("'{}'", sql_escape(&encode_path(&segment.file))),
+                segment.start_ms.to_string(),
+                segment
+                    .end_ms
+                    .map_or_else(|| "NULL".to_string(), |v| v.to_string()),
+            ),
+            None => ("NULL".to_string(), "NULL".to_string(), "NULL".to_string()),
+        };
 
         // Single INSERT with all values as SQL literals
         let insert_sql = format!(
-            "INSERT INTO track (title, title_sortable, album_id, track_number, disc_number, duration, location, genres, artist_names, folder)
-                VALUES ('{}', '{}', {}, {}, {}, {}, '{}', '{}', '{}', '{}')
+            "INSERT INTO track (title, title_sortable, album_id, track_number, disc_number, duration, location, genres, artist_names, folder, file, start_ms, end_ms)
+                VALUES ('{}', '{}', {}, {}, {}, {}, '{}', '{}', '{}', '{}', {}, {}, {})
                 ON CONFLICT (location) DO UPDATE SET
                     title = EXCLUDED.title,
                     title_sortable = EXCLUDED.title_sortable,
@@ -675,7 +1145,10 @@ impl ScanThread {
                     duration = EXCLUDED.duration,
                     genres = EXCLUDED.genres,
                     artist_names = EXCLUDED.artist_names,
-                    folder = EXCLUDED.folder",
+                    folder = EXCLUDED.folder,
+                    file = EXCLUDED.file,
+                    start_ms = EXCLUDED.start_ms,
+                    end_ms = EXCLUDED.end_ms",
             name_escaped,           // title
             name_escaped,           // title_sortable
             album_id_unwrapped,     // album_id
@@ -685,7 +1158,10 @@ impl ScanThread {
             path_escaped,           // location
             genre_escaped,          // genres
             artist_escaped,         // artist_names
-            parent_escaped          // folder
+            parent_escaped,         // folder
+            file,                   // file
+            start_ms,               // start_ms
+            end_ms                  // end_ms
         );
 
         conn.execute(&insert_sql, ())
@@ -701,6 +1177,7 @@ impl ScanThread {
         &mut self,
         metadata: &FileInformation,
         path: &Path,
+        segment: Option<&TrackSegment>,
     ) -> anyhow::Result<()> {
         let (meta, length, image) = metadata;
 
@@ -709,26 +1186,115 @@ impl ScanThread {
             meta.artist, meta.name
         );
 
-        // Use a single connection for the entire metadata update
+        // Use a single connection for the entire metadata update, and hold the write lock so that
+        // other writers in this process don't collide with it
+        let _guard = self.pool.lock_writes().await;
         let conn = self.pool.connect()?;
 
         let artist_id = self.insert_artist(&conn, meta).await?;
         let album_id = self.insert_album(&conn, meta, artist_id, image).await?;
-        self.insert_track(&conn, meta, album_id, path, *length).await?;
+        self.insert_track(&conn, meta, album_id, path, *length, segment)
+            .await?;
 
         Ok(())
     }
 
+    async fn refresh_track_once(
+        &mut self,
+        metadata: &FileInformation,
+        path: &Path,
+    ) -> anyhow::Result<()> {
+        let (meta, length, image) = metadata;
+
+        let _guard = self.pool.lock_writes().await;
+        let conn = self.pool.connect()?;
+
+        let path_str = encode_path(path);
+
+        // read where the track was before updating it, so the old album and artist can be
+        // removed if the edit moved the track somewhere else
+        let previous = conn
+            .query_optional(
+                include_str!("../../../queries/scan/get_track_cleanup_context.sql"),
+                (path_str.as_ref(),),
+                |row| {
+                    Ok(TrackCleanupContext {
+                        album_id: row.get::<Option<i64>>(0)?,
+                        disc_key: row.get::<i64>(1)?,
+                        folder: row.get::<Option<String>>(2)?,
+                    })
+                },
+            )
+            .await?;
+
+        let artist_id = self.insert_artist(&conn, meta).await?;
+        let album_id = self.insert_album(&conn, meta, artist_id, image).await?;
+        self.insert_track(&conn, meta, album_id, path, *length, None)
+            .await?;
+
+        if let Some(previous) = previous {
+            self.cleanup_track_removal(&conn, &previous).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Refreshes a single track's metadata in place. If the track's album or artist changed, the
+    /// track is moved to the new album and the old album and artist are removed if they are now
+    /// empty.
+    fn refresh_track(&mut self, path: &PathBuf) {
+        info!("Refreshing metadata for {:?}", path);
+
+        let Some(metadata) = self.read_metadata_for_path(path) else {
+            warn!("Could not read metadata for file: {:?}", path);
+            return;
+        };
+
+        const MAX_RETRY: usize = 5;
+        for attempt in 0..=MAX_RETRY {
+            match block_on(self.refresh_track_once(&metadata, path)) {
+                Ok(()) => break,
+                Err(err) if DbError::classify(&err).is_retryable() && attempt < MAX_RETRY => {
+                    self.lock_retries += 1;
+                    let delay_ms = 50 * (attempt as u64 + 1);
+                    std::thread::sleep(std::time::Duration::from_millis(delay_ms));
+                }
+                Err(err) => {
+                    error!("Failed to refresh metadata for {:?}: {err:#?}", path);
+                    return;
+                }
+            }
+        }
+
+        if let Ok(modified) = fs::metadata(path).and_then(|m| m.modified()) {
+            if let Ok(timestamp) = modified.duration_since(SystemTime::UNIX_EPOCH) {
+                self.scan_record.insert(path.clone(), timestamp.as_secs());
+                self.write_scan_record();
+            }
+        }
+
+        // a running scan will tell the UI to reload once it's finished
+        if self.scan_state == ScanState::Idle {
+            let event_tx = self.event_tx.clone();
+            smol::spawn(async move {
+                event_tx.send(ScanEvent::ScanCompleteIdle).await.unwrap();
+            })
+            .detach();
+        }
+    }
+
     async fn update_metadata(
         &mut self,
         metadata: FileInformation,
         path: &Path,
+        segment: Option<&TrackSegment>,
     ) -> anyhow::Result<()> {
         const MAX_RETRY: usize = 5;
         for attempt in 0..=MAX_RETRY {
-            match self.update_metadata_once(&metadata, path).await {
+            match self.update_metadata_once(&metadata, path, segment).await {
                 Ok(()) => return Ok(()),
-                Err(err) if is_db_locked(&err) && attempt < MAX_RETRY => {
+                Err(err) if DbError::classify(&err).is_retryable() && attempt < MAX_RETRY => {
+                    self.lock_retries += 1;
                     let delay_ms = 50 * (attempt as u64 + 1);
                     Timer::after(std::time::Duration::from_millis(delay_ms)).await;
                 }
@@ -756,24 +1322,43 @@ impl ScanThread {
         None
     }
 
-    fn write_scan_record(&self) {
-        if let Some(path) = self.scan_record_path.as_ref() {
-            let mut file = File::create(path).unwrap();
-            let data = serde_json::to_string(&self.scan_record).unwrap();
-            if let Err(err) = file.write_all(data.as_bytes()) {
-                error!("Could not write scan record: {:?}", err);
-                error!("Scan record will not be saved, this may cause rescans on restart");
-            } else {
-                info!("Scan record written to {:?}", path);
-            }
-        } else {
+    /// Writes the scan record. Files waiting to be processed are left out, since they're added to
+    /// the record when they're discovered: if the scan were interrupted, they'd otherwise be
+    /// skipped by the next one.
+    ///
+    /// The record is written to a temporary file that then replaces the old one, so it isn't lost
+    /// if MrChat is closed partway through writing it.
+    fn write_scan_record(&mut self) {
+        self.last_record_write = Instant::now();
+
+        let Some(path) = self.scan_record_path.as_ref() else {
             error!("No scan record path set, scan record will not be saved");
+            return;
+        };
+
+        let pending: AHashSet<&PathBuf> = self.to_process.iter().collect();
+        let record: AHashMap<Cow<str>, u64> = self
+            .scan_record
+            .iter()
+            .filter(|(path, _)| !pending.contains(path))
+            .map(|(path, timestamp)| (encode_path(path), *timestamp))
+            .collect();
+
+        if let Err(err) = write_file_atomically(path, serde_json::to_string(&record).unwrap()) {
+            error!("Could not write scan record: {:?}", err);
+            error!("Scan record will not be saved, this may cause rescans on restart");
+        } else {
+            info!("Scan record written to {:?}", path);
         }
     }
 
     fn scan(&mut self) {
         if self.to_process.is_empty() {
-            info!("Scan complete, writing scan record and stopping");
+            info!(
+                "Scan complete ({} database lock retries), writing scan record and stopping",
+                self.lock_retries
+            );
+            self.lock_retries = 0;
             self.write_scan_record();
             self.scan_state = ScanState::Idle;
             let event_tx = self.event_tx.clone();
@@ -785,10 +1370,11 @@ impl ScanThread {
         }
 
         let path = self.to_process.pop().unwrap();
-        let metadata = self.read_metadata_for_path(&path);
 
-        if let Some(metadata) = metadata {
-            let result = block_on(self.update_metadata(metadata, &path));
+        if is_cue_sheet(&path) {
+            self.scan_cue_sheet(&path);
+        } else if let Some(metadata) = self.read_metadata_for_path(&path) {
+            let result = block_on(self.update_metadata(metadata, &path, None));
 
             if let Err(err) = result {
                 error!(
@@ -797,26 +1383,172 @@ impl ScanThread {
                 );
             }
 
-            self.scanned += 1;
-
-            if self.scanned % 5 == 0 {
-                let event_tx = self.event_tx.clone();
-                let scan_progress = ScanEvent::ScanProgress {
-                    current: self.scanned,
-                    total: self.discovered_total,
-                };
-                smol::spawn(async move {
-                    event_tx.send(scan_progress).await.unwrap();
-                })
-                .detach();
-            }
+            self.report_scanned();
         } else {
             warn!("Could not read metadata for file: {:?}", path);
         }
+
+        if self.last_record_write.elapsed() >= SCAN_RECORD_SAVE_INTERVAL {
+            self.write_scan_record();
+        }
+    }
+
+    fn report_scanned(&mut self) {
+        self.scanned += 1;
+
+        if self.scanned % 5 == 0 {
+            let event_tx = self.event_tx.clone();
+            let scan_progress = ScanEvent::ScanProgress {
+                current: self.scanned,
+                total: self.discovered_total,
+            };
+            smol::spawn(async move {
+                event_tx.send(scan_progress).await.unwrap();
+            })
+            .detach();
+        }
+    }
+
+    /// Adds the tracks of a cue sheet to the library. Each track gets a location of its own (see
+    /// [`cue_track_location`]) and plays a segment of the sheet's audio file. Tracks that are no
+    /// longer in the sheet are removed.
+    fn scan_cue_sheet(&mut self, cue: &PathBuf) {
+        let sheet = match read_cue_sheet(cue) {
+            Ok(sheet) => sheet,
+            Err(e) => {
+                warn!("Could not read cue sheet {:?}: {}", cue, e);
+                return;
+            }
+        };
+
+        let Some(file) = sheet.single_file() else {
+            debug!("Cue sheet {:?} doesn't split a single file, skipping", cue);
+            return;
+        };
+
+        let audio = cue.with_file_name(&file.name);
+        let Some((metadata, length, image)) = self.read_metadata_for_path(&audio) else {
+            warn!(
+                "Could not read metadata for {:?}, the audio file of cue sheet {:?}",
+                audio, cue
+            );
+            return;
+        };
+
+        let mut locations = Vec::new();
+
+        for (index, track) in file.tracks.iter().enumerate() {
+            let segment = TrackSegment {
+                file: audio.clone(),
+                start_ms: track.start_ms,
+                end_ms: file.end_ms(index),
+            };
+            let end_ms = segment.end_ms.unwrap_or(length * 1000);
+            let track_length = end_ms.saturating_sub(segment.start_ms).div_ceil(1000);
+
+            let mut metadata = metadata.clone();
+            metadata.name = track
+                .title
+                .clone()
+                .or_else(|| Some(format!("Track {:02}", track.number)));
+            metadata.artist = track
+                .performer
+                .clone()
+                .or_else(|| sheet.performer.clone())
+                .or(metadata.artist);
+            metadata.album_artist = sheet.performer.clone().or(metadata.album_artist);
+            metadata.album = sheet.title.clone().or(metadata.album);
+            metadata.genre = sheet.genre.clone().or(metadata.genre);
+            metadata.catalog = sheet.catalog.clone().or(metadata.catalog);
+            if metadata.date.is_none() {
+                metadata.date = sheet
+                    .date
+                    .as_deref()
+                    .and_then(|date| dateparser::parse(date).ok());
+            }
+            metadata.isrc = track.isrc.clone();
+            metadata.track_current = Some(track.number as u64);
+            metadata.track_max = Some(file.tracks.len() as u64);
+
+            let location = cue_track_location(cue, track.number);
+            let result = block_on(self.update_metadata(
+                (metadata, track_length, image.clone()),
+                &location,
+                Some(&segment),
+            ));
+
+            if let Err(err) = result {
+                error!(
+                    "Failed to update metadata for track {} of cue sheet {:?}, error: {err:#?}",
+                    track.number, cue
+                );
+            }
+
+            locations.push(location);
+        }
+
+        block_on(self.delete_stale_cue_tracks(cue, &locations));
+
+        self.report_scanned();
+    }
+
+    /// Removes the tracks of a cue sheet that aren't in `current`, because the sheet was edited
+    /// since it was last scanned.
+    async fn delete_stale_cue_tracks(&mut self, cue: &Path, current: &[PathBuf]) {
+        let _guard = self.pool.lock_writes().await;
+        let conn = match self.pool.connect() {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("Failed to connect to database: {:?}", e);
+                return;
+            }
+        };
+
+        let locations = match self.cue_track_locations(&conn, cue).await {
+            Ok(locations) => locations,
+            Err(e) => {
+                error!(
+                    "Database error while finding the tracks of cue sheet {:?}: {:?}",
+                    cue, e
+                );
+                return;
+            }
+        };
+
+        for location in locations {
+            if !current.contains(&location) {
+                debug!("track removed from cue sheet: {:?}", location);
+                self.delete_location(&conn, &location).await;
+            }
+        }
+    }
+
+    /// The locations of the tracks in the library that came from the cue sheet at `cue`.
+    async fn cue_track_locations(
+        &self,
+        conn: &TursoConnection,
+        cue: &Path,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        let folder = cue.parent().map(encode_path).unwrap_or_default();
+
+        let locations = conn
+            .query_map(
+                include_str!("../../../queries/scan/get_segment_locations.sql"),
+                (folder.as_ref(),),
+                |row| Ok(row.get::<String>(0)?),
+            )
+            .await?;
+
+        Ok(locations
+            .iter()
+            .map(|location| decode_path(location))
+            .filter(|location| is_cue_track_location(cue, location))
+            .collect())
     }
 
     async fn delete_track(&mut self, path: &PathBuf) {
         debug!("track deleted or moved: {:?}", path);
+        let _guard = self.pool.lock_writes().await;
         let conn = match self.pool.connect() {
             Ok(conn) => conn,
             Err(e) => {
@@ -825,18 +1557,41 @@ impl ScanThread {
             }
         };
 
-        let Some(path_str) = path.to_str() else {
-            error!(
-                "Failed to delete track: path is not valid UTF-8: {:?}",
-                path
-            );
-            return;
+        // the tracks of a cue sheet are stored under locations of their own
+        let locations = if is_cue_sheet(path) {
+            match self.cue_track_locations(&conn, path).await {
+                Ok(locations) => locations,
+                Err(e) => {
+                    error!(
+                        "Database error while finding the tracks of cue sheet {:?}: {:?}",
+                        path, e
+                    );
+                    return;
+                }
+            }
+        } else {
+            vec![path.clone()]
         };
 
+        let mut deleted = true;
+        for location in &locations {
+            deleted &= self.delete_location(&conn, location).await;
+        }
+
+        if deleted {
+            self.scan_record.remove(path);
+        }
+    }
+
+    /// Removes a single track from the library, along with its album and artist if nothing else
+    /// is left in them. Returns false if the track couldn't be removed.
+    async fn delete_location(&self, conn: &TursoConnection, path: &Path) -> bool {
+        let path_str = encode_path(path);
+
         let track_context = match conn
             .query_optional(
                 include_str!("../../../queries/scan/get_track_cleanup_context.sql"),
-                (path_str,),
+                (path_str.as_ref(),),
                 |row| {
                     Ok(TrackCleanupContext {
                         album_id: row.get::<Option<i64>>(0)?,
@@ -857,26 +1612,39 @@ impl ScanThread {
             }
         };
 
-        let result = conn
+        // the plays are found through the track, so they have to go first
+        let result = match conn
             .execute(
-                include_str!("../../../queries/scan/delete_track.sql"),
-                (path_str,),
+                include_str!("../../../queries/scan/delete_track_plays.sql"),
+                (path_str.as_ref(),),
             )
-            .await;
+            .await
+        {
+            Ok(_) => {
+                conn.execute(
+                    include_str!("../../../queries/scan/delete_track.sql"),
+                    (path_str.as_ref(),),
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
 
         if let Err(e) = result {
             error!("Database error while deleting track: {:?}", e);
-        } else {
-            self.scan_record.remove(path);
-            if let Some(ctx) = track_context {
-                if let Err(e) = self.cleanup_track_removal(&conn, &ctx).await {
-                    error!(
-                        "Database error while cleaning up after track deletion {:?}: {:?}",
-                        path, e
-                    );
-                }
+            return false;
+        }
+
+        if let Some(ctx) = track_context {
+            if let Err(e) = self.cleanup_track_removal(conn, &ctx).await {
+                error!(
+                    "Database error while cleaning up after track deletion {:?}: {:?}",
+                    path, e
+                );
             }
         }
+
+        true
     }
 
     async fn cleanup_track_removal(
@@ -952,14 +1720,24 @@ impl ScanThread {
     // This is done in one shot because it's required for data integrity
     // Cleanup cannot be cancelled
     fn cleanup(&mut self) {
-        self.scan_record
-            .clone()
-            .iter()
-            .filter(|v| !v.0.exists())
-            .map(|v| v.0)
-            .for_each(|v| {
-                block_on(self.delete_track(v));
-            });
+        // tracks that now match an exclude are removed just like ones that no longer exist
+        let removed: Vec<PathBuf> = self
+            .scan_record
+            .keys()
+            .filter(|path| !path.exists() || self.is_excluded(path, false))
+            .cloned()
+            .collect();
+
+        for path in removed {
+            block_on(self.delete_track(&path));
+        }
+
+        // unchanged files aren't scanned again, so sort names are brought in line with the
+        // setting here
+        let ignore_articles = self.scan_settings.ignore_articles;
+        if let Err(e) = block_on(update_sortable_names(&self.pool, ignore_articles)) {
+            error!("Failed to update sort names: {:?}", e);
+        }
 
         self.scan_state = ScanState::Discovering;
     }
@@ -7,10 +7,13 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 use gpui::{IntoElement, RenderImage, SharedString};
 use image::{Frame, RgbaImage};
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 
 use crate::shared::util::rgb_to_bgr;
 
+use super::paths::decode_path;
+
 fn parse_timestamp(raw: &str) -> Result<DateTime<Utc>> {
     let trimmed = raw.trim();
     if trimmed.is_empty() {
@@ -268,6 +271,18 @@ pub struct Track {
     pub tags: Option<Vec<DBString>>,
     pub location: PathBuf,
     pub artist_names: Option<DBString>,
+    /// The part of a larger file that this track plays, if it isn't a file of its own.
+    pub segment: Option<TrackSegment>,
+}
+
+/// A track stored inside a larger file, such as one of the tracks of a cue sheet. The track's
+/// location identifies it, while the audio is read from `file`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrackSegment {
+    pub file: PathBuf,
+    pub start_ms: u64,
+    /// Where the track ends, or None if it plays to the end of the file.
+    pub end_ms: Option<u64>,
 }
 
 impl Track {
@@ -289,12 +304,62 @@ impl Track {
             },
             genres: None,  // Column 8: genres (not parsed yet)
             tags: None,    // Column 9: tags (not parsed yet)
-            location: PathBuf::from(row.get::<String>(10).context("failed to get location")?),
+            location: decode_path(&row.get::<String>(10).context("failed to get location")?),
             artist_names: row
                 .get::<Option<String>>(11)
                 .context("failed to get artist_names")?
                 .map(DBString::from),
             // Column 12: folder (not used in struct)
+            segment: read_segment(row, 13)?,
+        })
+    }
+
+    /// Returns the disc this track is on. Tracks without a disc number (stored as NULL or -1) are
+    /// treated as being on disc 1.
+    pub fn disc(&self) -> i32 {
+        self.disc_number.filter(|v| *v > 0).unwrap_or(1)
+    }
+}
+
+/// Reads the segment stored in the file, start and end columns beginning at `index`, if the track
+/// has one.
+fn read_segment(row: &turso::Row, index: usize) -> Result<Option<TrackSegment>> {
+    let Some(file) = row
+        .get::<Option<String>>(index)
+        .context("failed to get file")?
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(TrackSegment {
+        file: decode_path(&file),
+        start_ms: row
+            .get::<Option<i64>>(index + 1)
+            .context("failed to get start_ms")?
+            .unwrap_or(0) as u64,
+        end_ms: row
+            .get::<Option<i64>>(index + 2)
+            .context("failed to get end_ms")?
+            .map(|v| v as u64),
+    }))
+}
+
+/// Just enough of a track to queue it. The rest of its details are read when it's played.
+#[derive(Clone, Debug)]
+pub struct TrackLocation {
+    pub id: i64,
+    pub album_id: Option<i64>,
+    pub location: PathBuf,
+    pub segment: Option<TrackSegment>,
+}
+
+impl TrackLocation {
+    pub fn from_row(row: &turso::Row) -> Result<Self> {
+        Ok(Self {
+            id: row.get(0).context("failed to get id")?,
+            album_id: row.get(1).context("failed to get album_id")?,
+            location: decode_path(&row.get::<String>(2).context("failed to get location")?),
+            segment: read_segment(row, 3)?,
         })
     }
 }
@@ -403,3 +468,25 @@ impl TrackStats {
         })
     }
 }
+
+#[derive(Clone)]
+pub struct RadioStation {
+    pub id: i64,
+    pub name: DBString,
+    pub url: DBString,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RadioStation {
+    pub fn from_row(row: &turso::Row) -> Result<Self> {
+        Ok(Self {
+            id: row.get(0).context("failed to get id")?,
+            name: DBString::from(row.get::<String>(1).context("failed to get name")?),
+            url: DBString::from(row.get::<String>(2).context("failed to get url")?),
+            created_at: {
+                let raw = row.get::<String>(3).context("failed to get created_at")?;
+                parse_timestamp(&raw).context("failed to parse created_at")?
+            },
+        })
+    }
+}
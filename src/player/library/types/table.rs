@@ -1,13 +1,28 @@
 use std::sync::Arc;
 
-use gpui::{App, SharedString};
+use gpui::{AnyElement, App, AppContext, IntoElement, SharedString};
 use indexmap::IndexMap;
 use rustc_hash::FxBuildHasher;
 
 use super::Album;
 use crate::{
     player::library::db::{AlbumMethod, AlbumSortMethod, LibraryAccess},
-    shared::ui::components::table::table_data::{Column, TableData, TableSort},
+    player::playback::{
+        interface::{GPUIPlaybackInterface, replace_queue},
+        queue::QueueItemData,
+    },
+    player::ui::{
+        components::icons::{DISC, PLAY, PLUS},
+        drag::{DragSource, DraggedTracks},
+        library::ViewSwitchMessage,
+        models::Models,
+    },
+    shared::format,
+    shared::i18n::t,
+    shared::ui::components::{
+        menu::{menu, menu_item},
+        table::table_data::{Column, TableData, TableSort},
+    },
 };
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
@@ -102,7 +117,7 @@ impl TableData<AlbumColumn> for Album {
                 .map(|v| (*v).clone().into()),
             AlbumColumn::Date => self
                 .release_date
-                .map(|date| date.format("%x").to_string().into()),
+                .map(|date| format::short_date(&date, format::locale(cx)).into()),
             AlbumColumn::Label => self.label.as_ref().map(|v| v.0.clone()),
             AlbumColumn::CatalogNumber => self.catalog_number.as_ref().map(|v| v.0.clone()),
         }
@@ -128,6 +143,61 @@ impl TableData<AlbumColumn> for Album {
         (self.id as u32, self.title.0.clone().into())
     }
 
+    fn get_context_menu(&self, _cx: &mut App) -> Option<AnyElement> {
+        let album_id = self.id;
+
+        Some(
+            menu()
+                .item(menu_item(
+                    "album_play",
+                    Some(PLAY),
+                    t("library.play"),
+                    move |_, _, cx| {
+                        let queue_items = album_queue_items(cx, album_id);
+                        replace_queue(queue_items, cx);
+                    },
+                ))
+                .item(menu_item(
+                    "album_play_next",
+                    None::<&str>,
+                    t("library.play_next"),
+                    move |_, _, cx| {
+                        let queue_items = album_queue_items(cx, album_id);
+                        cx.global::<GPUIPlaybackInterface>().play_next(queue_items);
+                    },
+                ))
+                .item(menu_item(
+                    "album_add_to_queue",
+                    Some(PLUS),
+                    "Add to queue",
+                    move |_, _, cx| {
+                        let queue_items = album_queue_items(cx, album_id);
+                        cx.global::<GPUIPlaybackInterface>().queue_list(queue_items);
+                    },
+                ))
+                .item(menu_item(
+                    "album_go_to",
+                    Some(DISC),
+                    "Go to album",
+                    move |_, _, cx| {
+                        let switcher = cx.global::<Models>().switcher_model.clone();
+                        switcher.update(cx, |_, cx| {
+                            cx.emit(ViewSwitchMessage::Release(album_id));
+                        });
+                    },
+                ))
+                .into_any_element(),
+        )
+    }
+
+    fn get_drag_data(&self, _: &mut App) -> Option<DraggedTracks> {
+        Some(DraggedTracks {
+            source: DragSource::Album(self.id),
+            title: self.title.0.clone(),
+            image: self.get_image_path(),
+        })
+    }
+
     fn default_columns() -> IndexMap<AlbumColumn, f32, FxBuildHasher> {
         let s = FxBuildHasher;
         let mut columns: IndexMap<AlbumColumn, f32, FxBuildHasher> = IndexMap::with_hasher(s);
@@ -139,3 +209,15 @@ impl TableData<AlbumColumn> for Album {
         columns
     }
 }
+
+/// Returns every track in the album, in order, ready to be queued.
+fn album_queue_items(cx: &mut App, album_id: i64) -> Vec<QueueItemData> {
+    let Ok(tracks) = cx.list_tracks_in_album(album_id) else {
+        return Vec::new();
+    };
+
+    tracks
+        .iter()
+        .map(|track| QueueItemData::from_track(cx, track))
+        .collect()
+}
@@ -1,4 +1,4 @@
-use std::{ffi::OsStr, fs::File};
+use std::{ffi::OsStr, fs::File, io::Read};
 
 use intx::{I24, U24};
 use regex::Regex;
@@ -8,7 +8,7 @@ use symphonia::{
         codecs::{CODEC_TYPE_NULL, Decoder, DecoderOptions},
         errors::Error,
         formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
-        io::MediaSourceStream,
+        io::{MediaSourceStream, ReadOnlySource},
         meta::{MetadataOptions, StandardTagKey, Tag, Value, Visual},
         probe::{Hint, ProbeResult},
         units::{Time, TimeBase},
@@ -24,7 +24,7 @@ use crate::{
             PlaybackReadError, PlaybackStartError, PlaybackStopError, SeekError,
             TrackDurationError,
         },
-        metadata::Metadata,
+        metadata::{Metadata, SourceFormat},
         playback::{PlaybackFrame, Samples},
         traits::{MediaPlugin, MediaProvider},
     },
@@ -42,6 +42,8 @@ pub struct SymphoniaProvider {
     decoder: Option<Box<dyn Decoder>>,
     pending_metadata_update: bool,
     last_image: Option<Visual>,
+    /// Whether the current source is a live network stream rather than a file.
+    live: bool,
 }
 
 impl SymphoniaProvider {
@@ -185,11 +187,41 @@ impl SymphoniaProvider {
 
         self.pending_metadata_update = true;
     }
-}
 
-impl MediaProvider for SymphoniaProvider {
-    fn open(&mut self, file: File, ext: Option<&OsStr>) -> Result<(), OpenError> {
-        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    /// Applies metadata revisions found in the stream since the last call, such as chapter or
+    /// stream title changes. Tags in a revision replace the current ones, and tags it doesn't
+    /// have are kept.
+    fn read_metadata_updates(&mut self) {
+        let Some(format) = &mut self.format else {
+            return;
+        };
+
+        let mut revisions = Vec::new();
+        while !format.metadata().is_latest() {
+            format.metadata().pop();
+
+            if let Some(revision) = format.metadata().current() {
+                revisions.push((
+                    revision.tags().to_vec(),
+                    revision.visuals().first().cloned(),
+                ));
+            }
+        }
+
+        for (tags, visual) in revisions {
+            self.break_metadata(&tags);
+            if visual.is_some() {
+                self.last_image = visual;
+            }
+            self.pending_metadata_update = true;
+        }
+    }
+
+    fn open_source(
+        &mut self,
+        mss: MediaSourceStream,
+        ext: Option<&OsStr>,
+    ) -> Result<(), OpenError> {
         let meta_opts: MetadataOptions = Default::default();
         let fmt_opts: FormatOptions = Default::default();
 
@@ -217,6 +249,27 @@ impl MediaProvider for SymphoniaProvider {
 
         Ok(())
     }
+}
+
+impl MediaProvider for SymphoniaProvider {
+    fn open(&mut self, file: File, ext: Option<&OsStr>) -> Result<(), OpenError> {
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+        self.live = false;
+        self.open_source(mss, ext)
+    }
+
+    fn open_stream(
+        &mut self,
+        stream: Box<dyn Read + Send + Sync>,
+        ext: Option<&OsStr>,
+    ) -> Result<(), OpenError> {
+        let mss = MediaSourceStream::new(
+            Box::new(ReadOnlySource::new(stream)),
+            Default::default(),
+        );
+        self.live = true;
+        self.open_source(mss, ext)
+    }
 
     fn close(&mut self) -> Result<(), CloseError> {
         self.stop_playback().expect("invalid outcome");
@@ -240,6 +293,9 @@ impl MediaProvider for SymphoniaProvider {
                 self.current_length = Some(tb.calc_time(frame_count).seconds);
                 self.current_timebase = Some(tb);
             }
+        } else if self.live {
+            // live streams never have a frame count, but the position can still be tracked
+            self.current_timebase = track.codec_params.time_base;
         }
 
         self.current_track = track.id;
@@ -262,6 +318,10 @@ impl MediaProvider for SymphoniaProvider {
     }
 
     fn read_samples(&mut self) -> Result<PlaybackFrame, PlaybackReadError> {
+        // revisions found while reading the previous packet are picked up here, since the format
+        // reader is borrowed for the rest of this function
+        self.read_metadata_updates();
+
         let Some(format) = &mut self.format else {
             return Err(PlaybackReadError::NothingOpen);
         };
@@ -276,11 +336,6 @@ impl MediaProvider for SymphoniaProvider {
                 }
             };
 
-            while !format.metadata().is_latest() {
-                // TODO: handle metadata updates
-                format.metadata().pop();
-            }
-
             if packet.track_id() != self.current_track {
                 continue;
             }
@@ -504,13 +559,9 @@ impl MediaProvider for SymphoniaProvider {
 
     fn read_image(&mut self) -> Result<Option<Box<[u8]>>, MetadataError> {
         if self.format.is_some() {
-            if let Some(visual) = &self.last_image {
-                let data = Ok(Some(visual.data.clone()));
-                self.last_image = None;
-                data
-            } else {
-                Ok(None)
-            }
+            // the image is kept, so metadata updates later in the stream that don't come with a
+            // new image don't clear it
+            Ok(self.last_image.as_ref().map(|visual| visual.data.clone()))
         } else {
             Err(MetadataError::NothingOpen)
         }
@@ -529,7 +580,7 @@ impl MediaProvider for SymphoniaProvider {
     fn position_secs(&self) -> Result<u64, TrackDurationError> {
         if self.decoder.is_none() {
             Err(TrackDurationError::NothingOpen)
-        } else if self.current_length.is_none() {
+        } else if self.current_length.is_none() && !self.live {
             Err(TrackDurationError::NeverStarted)
         } else {
             Ok(self.current_position)
@@ -537,6 +588,10 @@ impl MediaProvider for SymphoniaProvider {
     }
 
     fn seek(&mut self, time: f64) -> Result<(), SeekError> {
+        if self.live {
+            return Err(SeekError::Unknown("live streams cannot be seeked".to_string()));
+        }
+
         let timebase = self.current_timebase;
         let Some(format) = &mut self.format else {
             return Err(SeekError::NothingOpen);
@@ -584,6 +639,26 @@ impl MediaProvider for SymphoniaProvider {
                 .unwrap_or(2) as u16,
         ))
     }
+
+    fn source_format(&self) -> Result<SourceFormat, ChannelRetrievalError> {
+        let Some(format) = &self.format else {
+            return Err(ChannelRetrievalError::NothingOpen);
+        };
+
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or(ChannelRetrievalError::NothingToPlay)?;
+
+        Ok(SourceFormat {
+            codec: get_codecs()
+                .get_codec(track.codec_params.codec)
+                .map(|descriptor| descriptor.short_name),
+            sample_rate: track.codec_params.sample_rate,
+            bit_depth: track.codec_params.bits_per_sample,
+        })
+    }
 }
 
 impl MediaPlugin for SymphoniaProvider {
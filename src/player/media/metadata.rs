@@ -27,3 +27,13 @@ pub struct Metadata {
 
     pub mbid_album: Option<String>,
 }
+
+/// The format a track's audio is stored in.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct SourceFormat {
+    /// The short name of the codec, such as `flac` or `mp3`.
+    pub codec: Option<&'static str>,
+    pub sample_rate: Option<u32>,
+    /// The number of bits in each sample. Lossy codecs don't have a bit depth.
+    pub bit_depth: Option<u32>,
+}
@@ -2,4 +2,5 @@ pub mod builtin;
 pub mod errors;
 pub mod metadata;
 pub mod playback;
+pub mod stream;
 pub mod traits;
@@ -20,6 +20,57 @@ pub enum Samples {
 }
 
 impl Samples {
+    /// The number of channels in the samples.
+    pub fn channels(&self) -> usize {
+        match self {
+            Samples::Float64(v) => v.len(),
+            Samples::Float32(v) => v.len(),
+            Samples::Signed32(v) => v.len(),
+            Samples::Unsigned32(v) => v.len(),
+            Samples::Signed24(v) => v.len(),
+            Samples::Unsigned24(v) => v.len(),
+            Samples::Signed16(v) => v.len(),
+            Samples::Unsigned16(v) => v.len(),
+            Samples::Signed8(v) => v.len(),
+            Samples::Unsigned8(v) => v.len(),
+            Samples::Dsd(v) => v.len(),
+        }
+    }
+
+    /// The number of samples in each channel.
+    pub fn samples_per_channel(&self) -> usize {
+        match self {
+            Samples::Float64(v) => v.first().map_or(0, Vec::len),
+            Samples::Float32(v) => v.first().map_or(0, Vec::len),
+            Samples::Signed32(v) => v.first().map_or(0, Vec::len),
+            Samples::Unsigned32(v) => v.first().map_or(0, Vec::len),
+            Samples::Signed24(v) => v.first().map_or(0, Vec::len),
+            Samples::Unsigned24(v) => v.first().map_or(0, Vec::len),
+            Samples::Signed16(v) => v.first().map_or(0, Vec::len),
+            Samples::Unsigned16(v) => v.first().map_or(0, Vec::len),
+            Samples::Signed8(v) => v.first().map_or(0, Vec::len),
+            Samples::Unsigned8(v) => v.first().map_or(0, Vec::len),
+            Samples::Dsd(v) => v.first().map_or(0, Vec::len),
+        }
+    }
+
+    /// Shortens every channel to at most `len` samples.
+    pub fn truncate(&mut self, len: usize) {
+        match self {
+            Samples::Float64(v) => v.iter_mut().for_each(|channel| channel.truncate(len)),
+            Samples::Float32(v) => v.iter_mut().for_each(|channel| channel.truncate(len)),
+            Samples::Signed32(v) => v.iter_mut().for_each(|channel| channel.truncate(len)),
+            Samples::Unsigned32(v) => v.iter_mut().for_each(|channel| channel.truncate(len)),
+            Samples::Signed24(v) => v.iter_mut().for_each(|channel| channel.truncate(len)),
+            Samples::Unsigned24(v) => v.iter_mut().for_each(|channel| channel.truncate(len)),
+            Samples::Signed16(v) => v.iter_mut().for_each(|channel| channel.truncate(len)),
+            Samples::Unsigned16(v) => v.iter_mut().for_each(|channel| channel.truncate(len)),
+            Samples::Signed8(v) => v.iter_mut().for_each(|channel| channel.truncate(len)),
+            Samples::Unsigned8(v) => v.iter_mut().for_each(|channel| channel.truncate(len)),
+            Samples::Dsd(v) => v.iter_mut().for_each(|channel| channel.truncate(len)),
+        }
+    }
+
     pub fn is_format(&self, format: SampleFormat) -> bool {
         match self {
             Samples::Float64(_) => format == SampleFormat::Float64,
@@ -0,0 +1,406 @@
+use std::{
+    io::{self, Read},
+    path::Path,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{Receiver, SyncSender, sync_channel},
+    },
+    thread::{self, sleep},
+    time::{Duration, Instant},
+};
+
+use isahc::{Body, Response, http::Request};
+use tracing::{info, warn};
+
+use super::metadata::Metadata;
+
+/// The maximum number of times a dropped stream will be reconnected before giving up.
+const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+/// The delay before the first reconnection attempt. Doubled after every failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// The upper bound for the delay between reconnection attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+/// How often a reconnecting stream checks whether it has been dropped while backing off.
+const CLOSE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+/// The largest chunk of data read from the server at once.
+const CHUNK_SIZE: usize = 16 * 1024;
+/// The number of chunks buffered ahead of the decoder before the reader waits for it.
+const MAX_BUFFERED_CHUNKS: usize = 64;
+/// While reconnecting, the amount of buffered data below which the decoder is no longer fed, since
+/// a packet that runs past the end of the buffer would block until the reconnection finishes.
+const MIN_READ_AHEAD: usize = 32 * 1024;
+
+/// Returns true if the given path is actually the URL of a network stream (such as an internet
+/// radio station) rather than a local file.
+pub fn is_stream_url(path: &Path) -> bool {
+    path.to_str()
+        .map(|v| v.starts_with("http://") || v.starts_with("https://"))
+        .unwrap_or(false)
+}
+
+/// Information provided by the server of a Shoutcast/Icecast stream. This is shared between the
+/// stream (which is read by the media provider) and the playback thread, which broadcasts changes
+/// to the user interface.
+#[derive(Debug, Default, Clone)]
+pub struct IcyInfo {
+    /// The name of the station, from the `icy-name` header.
+    pub station_name: Option<String>,
+    /// The current `StreamTitle`, usually in the form of "Artist - Title".
+    pub stream_title: Option<String>,
+    /// Whether or not the information has changed since it was last read.
+    pub updated: bool,
+}
+
+impl IcyInfo {
+    /// Splits the current stream title into the artist and track name. If the title does not
+    /// contain a separator, the entire title is treated as the track name.
+    pub fn split_title(&self) -> (Option<String>, Option<String>) {
+        match self.stream_title.as_deref() {
+            Some(title) => match title.split_once(" - ") {
+                Some((artist, name)) => (
+                    Some(artist.trim().to_string()),
+                    Some(name.trim().to_string()),
+                ),
+                None => (None, Some(title.trim().to_string())),
+            },
+            None => (None, None),
+        }
+    }
+
+    /// Overlays the stream information onto metadata read from the decoder. The station name is
+    /// used as the album, and as the track name until the server sends a `StreamTitle`.
+    pub fn apply_to(&self, metadata: &mut Metadata) {
+        let (artist, name) = self.split_title();
+
+        if let Some(station_name) = &self.station_name {
+            metadata.album = Some(station_name.clone());
+        }
+
+        if let Some(name) = name.or_else(|| self.station_name.clone()) {
+            metadata.name = Some(name);
+        }
+
+        if artist.is_some() {
+            metadata.artist = artist;
+        }
+    }
+}
+
+/// A readable HTTP audio stream. If the server supports ICY metadata, the metadata blocks are
+/// stripped from the audio data and the `StreamTitle` is stored in the shared [`IcyInfo`].
+///
+/// The connection is read on a background thread, which buffers the audio data ahead of the
+/// decoder. If the connection drops, that thread reconnects with exponential backoff while the
+/// playback thread keeps handling commands (see [`StreamStatus::is_stalled`]). Once all
+/// reconnection attempts have been exhausted, the stream reports EOF.
+pub struct IcyStream {
+    chunks: Receiver<Vec<u8>>,
+    /// The chunk currently being read, and how much of it has been read already.
+    chunk: Vec<u8>,
+    offset: usize,
+    info: Arc<Mutex<IcyInfo>>,
+    status: Arc<StreamStatus>,
+    content_type: Option<String>,
+}
+
+impl IcyStream {
+    /// Connects to the given URL, requesting ICY metadata from the server, and starts reading the
+    /// stream in the background.
+    pub fn connect(url: &str) -> io::Result<Self> {
+        let response = Connection::request(url)?;
+        let info = Arc::new(Mutex::new(IcyInfo::default()));
+        let status = Arc::new(StreamStatus::default());
+
+        let mut connection = Connection {
+            url: url.to_string(),
+            body: Body::empty(),
+            metaint: None,
+            until_meta: 0,
+            info: info.clone(),
+        };
+        let content_type = connection.accept_response(response);
+
+        let (tx, chunks) = sync_channel(MAX_BUFFERED_CHUNKS);
+        let reader_status = status.clone();
+
+        thread::Builder::new()
+            .name("stream reader".to_string())
+            .spawn(move || connection.run(tx, &reader_status))?;
+
+        Ok(IcyStream {
+            chunks,
+            chunk: Vec::new(),
+            offset: 0,
+            info,
+            status,
+            content_type,
+        })
+    }
+
+    /// Returns a handle to the stream's ICY information.
+    pub fn info(&self) -> Arc<Mutex<IcyInfo>> {
+        self.info.clone()
+    }
+
+    /// Returns a handle to the state of the stream's background reader.
+    pub fn status(&self) -> Arc<StreamStatus> {
+        self.status.clone()
+    }
+
+    /// Returns the file extension that best matches the stream's content type, to be used as a
+    /// hint for the decoder.
+    pub fn extension_hint(&self) -> Option<&'static str> {
+        let content_type = self.content_type.as_deref()?.to_ascii_lowercase();
+
+        if content_type.contains("mpeg") {
+            Some("mp3")
+        } else if content_type.contains("aac") {
+            Some("aac")
+        } else if content_type.contains("ogg") || content_type.contains("opus") {
+            Some("ogg")
+        } else if content_type.contains("flac") {
+            Some("flac")
+        } else {
+            None
+        }
+    }
+}
+
+impl Read for IcyStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.offset == self.chunk.len() {
+            match self.chunks.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.offset = 0;
+                }
+                // the reader has given up on the stream
+                Err(_) => return Ok(0),
+            }
+        }
+
+        let read = buf.len().min(self.chunk.len() - self.offset);
+        buf[..read].copy_from_slice(&self.chunk[self.offset..self.offset + read]);
+        self.offset += read;
+        self.status.buffered.fetch_sub(read, Ordering::Relaxed);
+
+        Ok(read)
+    }
+}
+
+impl Drop for IcyStream {
+    fn drop(&mut self) {
+        self.status.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+/// The state of a stream's background reader, shared with the playback thread.
+#[derive(Debug, Default)]
+pub struct StreamStatus {
+    /// The number of bytes read from the server that the decoder hasn't consumed yet.
+    buffered: AtomicUsize,
+    /// Whether the reader is currently reconnecting to the server.
+    reconnecting: AtomicBool,
+    /// Whether the stream has been dropped, which stops the reader.
+    closed: AtomicBool,
+}
+
+impl StreamStatus {
+    /// Returns true if the stream is reconnecting and too little data is buffered to decode from.
+    /// Reading from the stream would block until the reconnection finishes, so the playback thread
+    /// should handle commands instead.
+    pub fn is_stalled(&self) -> bool {
+        self.reconnecting.load(Ordering::Relaxed)
+            && self.buffered.load(Ordering::Relaxed) < MIN_READ_AHEAD
+    }
+}
+
+/// The connection to the server, owned by the stream's background reader.
+struct Connection {
+    url: String,
+    body: Body,
+    /// The number of audio bytes between metadata blocks, if the server sends metadata.
+    metaint: Option<usize>,
+    /// The number of audio bytes remaining before the next metadata block.
+    until_meta: usize,
+    info: Arc<Mutex<IcyInfo>>,
+}
+
+impl Connection {
+    fn request(url: &str) -> io::Result<Response<Body>> {
+        let request = Request::get(url)
+            .header("Icy-MetaData", "1")
+            .body(())
+            .map_err(io::Error::other)?;
+
+        let response = isahc::send(request).map_err(io::Error::other)?;
+
+        if !response.status().is_success() {
+            return Err(io::Error::other(format!(
+                "stream server responded with {}",
+                response.status()
+            )));
+        }
+
+        Ok(response)
+    }
+
+    /// Starts reading from a new response, returning its content type.
+    fn accept_response(&mut self, response: Response<Body>) -> Option<String> {
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+
+        self.metaint = header("icy-metaint").and_then(|v| v.trim().parse().ok());
+        self.until_meta = self.metaint.unwrap_or(0);
+        let content_type = header("content-type");
+
+        let station_name = header("icy-name").filter(|v| !v.trim().is_empty());
+
+        {
+            let mut info = self.info.lock().expect("icy info poisoned");
+            if station_name.is_some() && info.station_name != station_name {
+                info.station_name = station_name;
+                info.updated = true;
+            }
+        }
+
+        self.body = response.into_body();
+        content_type
+    }
+
+    /// Reads the stream into the channel until the stream is dropped or every reconnection
+    /// attempt has failed.
+    fn run(mut self, tx: SyncSender<Vec<u8>>, status: &StreamStatus) {
+        let mut buf = vec![0u8; CHUNK_SIZE];
+
+        while !status.closed.load(Ordering::Relaxed) {
+            match self.read_inner(&mut buf) {
+                Ok(0) | Err(_) => {
+                    if !self.reconnect(status) {
+                        warn!("Giving up on stream {}", self.url);
+                        return;
+                    }
+                }
+                Ok(read) => {
+                    status.buffered.fetch_add(read, Ordering::Relaxed);
+
+                    // the stream has been dropped
+                    if tx.send(buf[..read].to_vec()).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Attempts to reconnect to the stream, backing off exponentially between attempts. Returns
+    /// false if every attempt failed, or the stream was dropped in the meantime.
+    fn reconnect(&mut self, status: &StreamStatus) -> bool {
+        status.reconnecting.store(true, Ordering::Relaxed);
+        let reconnected = self.try_reconnect(status);
+        status.reconnecting.store(false, Ordering::Relaxed);
+
+        reconnected
+    }
+
+    fn try_reconnect(&mut self, status: &StreamStatus) -> bool {
+        let mut backoff = INITIAL_BACKOFF;
+
+        for attempt in 1..=MAX_RECONNECT_ATTEMPTS {
+            warn!(
+                "Stream {} dropped, reconnecting in {:?} (attempt {}/{})",
+                self.url, backoff, attempt, MAX_RECONNECT_ATTEMPTS
+            );
+
+            let deadline = Instant::now() + backoff;
+            while Instant::now() < deadline {
+                if status.closed.load(Ordering::Relaxed) {
+                    return false;
+                }
+                sleep(CLOSE_CHECK_INTERVAL);
+            }
+
+            match Self::request(&self.url) {
+                Ok(response) => {
+                    info!("Reconnected to stream {}", self.url);
+                    self.accept_response(response);
+                    return true;
+                }
+                Err(e) => warn!("Failed to reconnect to stream: {}", e),
+            }
+
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+
+        false
+    }
+
+    /// Reads and parses a metadata block. The first byte is the length of the block divided by
+    /// 16, followed by the block itself.
+    fn read_metadata_block(&mut self) -> io::Result<()> {
+        let mut length = [0u8; 1];
+        self.body.read_exact(&mut length)?;
+
+        let length = length[0] as usize * 16;
+        if length == 0 {
+            return Ok(());
+        }
+
+        let mut block = vec![0u8; length];
+        self.body.read_exact(&mut block)?;
+
+        let block = String::from_utf8_lossy(&block);
+        if let Some(title) = parse_stream_title(&block) {
+            let mut info = self.info.lock().expect("icy info poisoned");
+            if info.stream_title.as_deref() != Some(title.as_str()) {
+                info!("Stream title changed: {}", title);
+                info.stream_title = Some(title);
+                info.updated = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_inner(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(metaint) = self.metaint else {
+            return self.body.read(buf);
+        };
+
+        if self.until_meta == 0 {
+            self.read_metadata_block()?;
+            self.until_meta = metaint;
+        }
+
+        let max = buf.len().min(self.until_meta);
+        let read = self.body.read(&mut buf[..max])?;
+        self.until_meta -= read;
+
+        Ok(read)
+    }
+}
+
+/// Extracts the `StreamTitle` value from an ICY metadata block, which is formatted as
+/// `StreamTitle='Artist - Title';StreamUrl='...';`.
+fn parse_stream_title(block: &str) -> Option<String> {
+    let start = block.find("StreamTitle='")? + "StreamTitle='".len();
+    let rest = &block[start..];
+    let end = rest.find("';").unwrap_or(rest.len());
+    let title = rest[..end].trim_end_matches(['\0', '\'', ';']).trim();
+
+    if title.is_empty() {
+        None
+    } else {
+        Some(title.to_string())
+    }
+}
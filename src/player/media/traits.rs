@@ -1,4 +1,4 @@
-use std::{ffi::OsStr, fs::File};
+use std::{ffi::OsStr, fs::File, io::Read};
 
 use crate::player::devices::format::ChannelSpec;
 
@@ -7,7 +7,7 @@ use super::{
         ChannelRetrievalError, CloseError, FrameDurationError, MetadataError, OpenError,
         PlaybackReadError, PlaybackStartError, PlaybackStopError, SeekError, TrackDurationError,
     },
-    metadata::Metadata,
+    metadata::{Metadata, SourceFormat},
     playback::PlaybackFrame,
 };
 
@@ -59,6 +59,15 @@ pub trait MediaProvider {
     /// Provider attempts to determine the extension based off of the file's contents.
     fn open(&mut self, file: File, ext: Option<&OsStr>) -> Result<(), OpenError>;
 
+    /// Requests the Provider open the specified network stream, such as an internet radio
+    /// station. Streams cannot be seeked, and usually do not have a known duration. The extension
+    /// is a hint derived from the stream's content type, if it could be determined.
+    fn open_stream(
+        &mut self,
+        stream: Box<dyn Read + Send + Sync>,
+        ext: Option<&OsStr>,
+    ) -> Result<(), OpenError>;
+
     /// Informs the Provider that the currently opened file is no longer needed. This function is
     /// not guaranteed to be called before open if a file is already opened.
     fn close(&mut self) -> Result<(), CloseError>;
@@ -113,4 +122,9 @@ pub trait MediaProvider {
     /// This function is used by the playback thread to determine whether or not the track's
     /// channel count can be handled by the current device, and if it is, change the channel count.
     fn channels(&self) -> Result<ChannelSpec, ChannelRetrievalError>;
+
+    /// Returns the codec, sample rate and bit depth of the track being decoded, as far as they're
+    /// known. This function should be available immediately after playback has started, and
+    /// should not require reading any samples.
+    fn source_format(&self) -> Result<SourceFormat, ChannelRetrievalError>;
 }
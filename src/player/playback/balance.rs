@@ -0,0 +1,138 @@
+use crate::player::{
+    devices::resample::{SampleFrom, SampleInto},
+    media::playback::Samples,
+};
+
+fn attenuate<T>(channel: &mut [T], gain: f64)
+where
+    T: Copy + SampleInto<f64> + SampleFrom<f64>,
+{
+    for sample in channel {
+        *sample = T::sample_from((*sample).sample_into() * gain);
+    }
+}
+
+fn balance_channels<T>(channels: &mut [Vec<T>], balance: f64)
+where
+    T: Copy + SampleInto<f64> + SampleFrom<f64>,
+{
+    let [left, right] = channels else {
+        return;
+    };
+
+    if balance < 0.0 {
+        attenuate(right, 1.0 + balance);
+    } else {
+        attenuate(left, 1.0 - balance);
+    }
+}
+
+/// Shifts stereo audio towards the left (-1.0) or right (1.0) channel, by attenuating the
+/// opposite channel. At either end, the opposite channel is silent.
+///
+/// Only stereo samples are changed: balance has no clear meaning for mono or surround layouts, so
+/// they're passed through untouched. Mono tracks played on a stereo device are converted to stereo
+/// before this is applied, so they can still be balanced.
+pub fn apply_balance(samples: &mut Samples, balance: f64) {
+    let balance = balance.clamp(-1.0, 1.0);
+
+    if balance == 0.0 {
+        return;
+    }
+
+    match samples {
+        Samples::Float64(v) => {
+            if let [left, right] = v.as_mut_slice() {
+                let (channel, gain) = if balance < 0.0 {
+                    (right, 1.0 + balance)
+                } else {
+                    (left, 1.0 - balance)
+                };
+                channel.iter_mut().for_each(|sample| *sample *= gain);
+            }
+        }
+        Samples::Float32(v) => balance_channels(v, balance),
+        Samples::Signed32(v) => balance_channels(v, balance),
+        Samples::Unsigned32(v) => balance_channels(v, balance),
+        Samples::Signed24(v) => balance_channels(v, balance),
+        Samples::Unsigned24(v) => balance_channels(v, balance),
+        Samples::Signed16(v) => balance_channels(v, balance),
+        Samples::Unsigned16(v) => balance_channels(v, balance),
+        Samples::Signed8(v) => balance_channels(v, balance),
+        Samples::Unsigned8(v) => balance_channels(v, balance),
+        Samples::Dsd(_) => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stereo() -> Samples {
+        Samples::Float64(vec![vec![0.5, -0.25, 1.0], vec![0.75, -0.5, -1.0]])
+    }
+
+    fn channels(samples: Samples) -> Vec<Vec<f64>> {
+        let Samples::Float64(channels) = samples else {
+            unreachable!();
+        };
+        channels
+    }
+
+    #[test]
+    fn centered_balance_is_identity() {
+        let mut samples = stereo();
+
+        apply_balance(&mut samples, 0.0);
+
+        assert_eq!(channels(samples), channels(stereo()));
+    }
+
+    #[test]
+    fn full_left_silences_the_right_channel() {
+        let mut samples = stereo();
+
+        apply_balance(&mut samples, -1.0);
+
+        let channels = channels(samples);
+        assert_eq!(channels[0], vec![0.5, -0.25, 1.0]);
+        assert!(channels[1].iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn full_right_silences_the_left_channel() {
+        let mut samples = stereo();
+
+        apply_balance(&mut samples, 1.0);
+
+        let channels = channels(samples);
+        assert!(channels[0].iter().all(|sample| *sample == 0.0));
+        assert_eq!(channels[1], vec![0.75, -0.5, -1.0]);
+    }
+
+    #[test]
+    fn partial_balance_attenuates_the_opposite_channel() {
+        let mut samples = Samples::Signed16(vec![vec![1000; 4], vec![1000; 4]]);
+
+        apply_balance(&mut samples, -0.5);
+
+        let Samples::Signed16(channels) = samples else {
+            unreachable!();
+        };
+        assert_eq!(channels[0], vec![1000; 4]);
+        assert!(
+            channels[1]
+                .iter()
+                .all(|sample| (499..=501).contains(sample))
+        );
+    }
+
+    #[test]
+    fn mono_is_untouched() {
+        let mut samples = Samples::Float64(vec![vec![0.5; 4]]);
+
+        apply_balance(&mut samples, 1.0);
+
+        assert_eq!(channels(samples), vec![vec![0.5; 4]]);
+    }
+}
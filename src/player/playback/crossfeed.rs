@@ -0,0 +1,246 @@
+use std::f64::consts::PI;
+
+use crate::{
+    player::devices::resample::{SampleFrom, SampleInto},
+    player::media::playback::{PlaybackFrame, Samples},
+    shared::settings::playback::CrossfeedIntensity,
+};
+
+/// The filter coefficients for a sample rate, following the Bauer stereophonic-to-binaural (bs2b)
+/// design: each channel is fed into the other through a low-pass filter, and the direct signal
+/// goes through a matching high-boost filter so that the overall tone stays flat.
+#[derive(Debug, Clone, Copy)]
+struct Coefficients {
+    a0_lo: f64,
+    b1_lo: f64,
+    a0_hi: f64,
+    a1_hi: f64,
+    b1_hi: f64,
+    gain: f64,
+}
+
+impl Coefficients {
+    fn new(cutoff: f64, feed_db: f64, rate: u32) -> Self {
+        let lo_db = feed_db * -5.0 / 6.0 - 3.0;
+        let hi_db = feed_db / 6.0 - 3.0;
+
+        let lo_gain = 10_f64.powf(lo_db / 20.0);
+        let hi_gain = 1.0 - 10_f64.powf(hi_db / 20.0);
+        let hi_cutoff = cutoff * 2_f64.powf((lo_db - 20.0 * hi_gain.log10()) / 12.0);
+
+        let lo_x = (-2.0 * PI * cutoff / rate as f64).exp();
+        let hi_x = (-2.0 * PI * hi_cutoff / rate as f64).exp();
+
+        Coefficients {
+            a0_lo: lo_gain * (1.0 - lo_x),
+            b1_lo: lo_x,
+            a0_hi: 1.0 - hi_gain * (1.0 - hi_x),
+            a1_hi: -hi_x,
+            b1_hi: hi_x,
+            // the low-pass and high-boost filters add up to more than unity at low frequencies
+            gain: 1.0 / (1.0 - hi_gain + lo_gain),
+        }
+    }
+}
+
+/// Mixes a delayed, low-passed amount of each stereo channel into the other, as happens naturally
+/// when listening to speakers, so that hard-panned audio sounds less fatiguing on headphones.
+///
+/// The filter state is carried from one frame to the next, so a single instance should be used
+/// for the whole stream. Only stereo samples are changed; mono and surround layouts, and DSD, are
+/// passed through untouched.
+pub struct Crossfeed {
+    intensity: CrossfeedIntensity,
+    /// The sample rate the coefficients were calculated for.
+    rate: u32,
+    coefficients: Coefficients,
+    /// The previous output of the low-pass filter, for each channel.
+    lo: [f64; 2],
+    /// The previous output of the high-boost filter, for each channel.
+    hi: [f64; 2],
+    /// The previous input sample, for each channel.
+    last: [f64; 2],
+}
+
+impl Crossfeed {
+    pub fn new(intensity: CrossfeedIntensity) -> Self {
+        Crossfeed {
+            intensity,
+            rate: 0,
+            coefficients: Coefficients::new(intensity.cutoff(), intensity.feed_db(), 44100),
+            lo: [0.0; 2],
+            hi: [0.0; 2],
+            last: [0.0; 2],
+        }
+    }
+
+    fn set_rate(&mut self, rate: u32) {
+        let intensity = self.intensity;
+
+        self.rate = rate;
+        self.coefficients = Coefficients::new(intensity.cutoff(), intensity.feed_db(), rate);
+        self.lo = [0.0; 2];
+        self.hi = [0.0; 2];
+        self.last = [0.0; 2];
+    }
+
+    fn process(&mut self, left: f64, right: f64) -> (f64, f64) {
+        let c = self.coefficients;
+        let input = [left, right];
+
+        for channel in 0..2 {
+            self.lo[channel] = c.a0_lo * input[channel] + c.b1_lo * self.lo[channel];
+            self.hi[channel] = c.a0_hi * input[channel]
+                + c.a1_hi * self.last[channel]
+                + c.b1_hi * self.hi[channel];
+        }
+
+        self.last = input;
+
+        (
+            (self.hi[0] + self.lo[1]) * c.gain,
+            (self.hi[1] + self.lo[0]) * c.gain,
+        )
+    }
+
+    fn process_channels<T>(&mut self, channels: &mut [Vec<T>])
+    where
+        T: Copy + SampleInto<f64> + SampleFrom<f64>,
+    {
+        let [left, right] = channels else {
+            return;
+        };
+
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let (new_l, new_r) = self.process((*l).sample_into(), (*r).sample_into());
+            *l = T::sample_from(new_l);
+            *r = T::sample_from(new_r);
+        }
+    }
+
+    /// Applies the crossfeed to a frame that's about to be played.
+    pub fn apply(&mut self, frame: &mut PlaybackFrame) {
+        if frame.rate == 0 {
+            return;
+        }
+
+        if frame.rate != self.rate {
+            self.set_rate(frame.rate);
+        }
+
+        match &mut frame.samples {
+            Samples::Float64(v) => {
+                if let [left, right] = v.as_mut_slice() {
+                    for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+                        (*l, *r) = self.process(*l, *r);
+                    }
+                }
+            }
+            Samples::Float32(v) => self.process_channels(v),
+            Samples::Signed32(v) => self.process_channels(v),
+            Samples::Unsigned32(v) => self.process_channels(v),
+            Samples::Signed24(v) => self.process_channels(v),
+            Samples::Unsigned24(v) => self.process_channels(v),
+            Samples::Signed16(v) => self.process_channels(v),
+            Samples::Unsigned16(v) => self.process_channels(v),
+            Samples::Signed8(v) => self.process_channels(v),
+            Samples::Unsigned8(v) => self.process_channels(v),
+            Samples::Dsd(_) => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(channels: Vec<Vec<f64>>) -> PlaybackFrame {
+        PlaybackFrame {
+            samples: Samples::Float64(channels),
+            rate: 44100,
+        }
+    }
+
+    fn channels(frame: PlaybackFrame) -> Vec<Vec<f64>> {
+        let Samples::Float64(channels) = frame.samples else {
+            unreachable!();
+        };
+        channels
+    }
+
+    #[test]
+    fn mono_is_untouched() {
+        let original = vec![vec![0.5; 256]];
+        let mut frame = frame(original.clone());
+
+        Crossfeed::new(CrossfeedIntensity::High).apply(&mut frame);
+
+        assert_eq!(channels(frame), original);
+    }
+
+    #[test]
+    fn centered_audio_keeps_its_level() {
+        let mut crossfeed = Crossfeed::new(CrossfeedIntensity::Medium);
+        let mut frame = frame(vec![vec![0.5; 44100], vec![0.5; 44100]]);
+
+        crossfeed.apply(&mut frame);
+
+        let channels = channels(frame);
+        assert!((channels[0][44099] - 0.5).abs() < 1e-6);
+        assert!((channels[1][44099] - 0.5).abs() < 1e-6);
+    }
+
+    /// How much of one-sided audio ends up in the other channel, once the filter has settled.
+    fn feed_level(intensity: CrossfeedIntensity) -> f64 {
+        let mut crossfeed = Crossfeed::new(intensity);
+        let mut frame = frame(vec![vec![0.5; 4410], vec![0.0; 4410]]);
+
+        crossfeed.apply(&mut frame);
+
+        channels(frame)[1][4409]
+    }
+
+    #[test]
+    fn low_intensity_feeds_less_than_high() {
+        let low = feed_level(CrossfeedIntensity::Low);
+        let medium = feed_level(CrossfeedIntensity::Medium);
+        let high = feed_level(CrossfeedIntensity::High);
+
+        assert!(low < medium, "low {low}, medium {medium}");
+        assert!(medium < high, "medium {medium}, high {high}");
+    }
+
+    #[test]
+    fn one_sided_audio_is_fed_to_the_other_channel() {
+        let mut crossfeed = Crossfeed::new(CrossfeedIntensity::Low);
+        let mut frame = frame(vec![vec![0.5; 4410], vec![0.0; 4410]]);
+
+        crossfeed.apply(&mut frame);
+
+        let channels = channels(frame);
+        assert!(channels[1][4409] > 0.0);
+        assert!(channels[1][4409] < channels[0][4409]);
+    }
+
+    #[test]
+    fn state_is_carried_between_frames() {
+        let mut whole = Crossfeed::new(CrossfeedIntensity::Medium);
+        let mut split = Crossfeed::new(CrossfeedIntensity::Medium);
+        let left: Vec<f64> = (0..512).map(|n| (n as f64 * 0.05).sin() * 0.8).collect();
+        let right = vec![0.0; 512];
+
+        let mut all = frame(vec![left.clone(), right.clone()]);
+        whole.apply(&mut all);
+
+        let mut first = frame(vec![left[..256].to_vec(), right[..256].to_vec()]);
+        let mut second = frame(vec![left[256..].to_vec(), right[256..].to_vec()]);
+        split.apply(&mut first);
+        split.apply(&mut second);
+
+        let all = channels(all);
+        let second = channels(second);
+        for (a, b) in all[1][256..].iter().zip(&second[1]) {
+            assert!((a - b).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use async_channel::Receiver;
+use tracing::{info, warn};
+
+use crate::player::devices::traits::DeviceProvider;
+
+use super::events::OutputDevice;
+
+/// How often the devices are listed, to notice when a device is removed or the default device
+/// changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The output devices that are available, and which of them is the default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceList {
+    pub devices: Vec<OutputDevice>,
+    /// The UID of the default device, if there is one.
+    pub default: Option<String>,
+}
+
+impl DeviceList {
+    /// Lists the devices available from the provider. Returns None if they couldn't be listed.
+    pub fn read(provider: &mut dyn DeviceProvider) -> Option<DeviceList> {
+        let devices = match provider.get_devices() {
+            Ok(devices) => devices
+                .iter()
+                .filter_map(|device| {
+                    Some(OutputDevice {
+                        uid: device.get_uid().ok()?,
+                        name: device.get_name().ok()?,
+                    })
+                })
+                .collect(),
+            Err(err) => {
+                warn!("Failed to list devices: {:?}", err);
+                return None;
+            }
+        };
+
+        let default = provider
+            .get_default_device()
+            .ok()
+            .and_then(|device| device.get_uid().ok());
+
+        Some(DeviceList { devices, default })
+    }
+
+    /// Whether the device with the given UID can be opened. The default device always can, even
+    /// if the provider doesn't list it.
+    pub fn contains(&self, uid: &str) -> bool {
+        self.default.as_deref() == Some(uid) || self.devices.iter().any(|device| device.uid == uid)
+    }
+}
+
+/// Starts a thread that lists the output devices every couple of seconds, and sends each list to
+/// the returned channel. Listing devices can take a while, so it's kept off the playback thread.
+///
+/// The device provider is created on the new thread by `create_provider`, since providers can't
+/// be moved between threads. The thread stops once the receiver is dropped.
+pub fn watch_devices(
+    create_provider: impl FnOnce() -> Box<dyn DeviceProvider> + Send + 'static,
+) -> Receiver<DeviceList> {
+    let (tx, rx) = async_channel::bounded(1);
+
+    std::thread::Builder::new()
+        .name("device watcher".to_string())
+        .spawn(move || {
+            let mut provider = create_provider();
+
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+
+                let Some(list) = DeviceList::read(provider.as_mut()) else {
+                    continue;
+                };
+
+                if tx.send_blocking(list).is_err() {
+                    info!("Playback thread stopped, no longer watching devices");
+                    return;
+                }
+            }
+        })
+        .expect("could not start device watcher thread");
+
+    rx
+}
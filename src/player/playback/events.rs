@@ -1,6 +1,12 @@
 #![allow(dead_code)]
 
-use crate::player::media::metadata::Metadata;
+use crate::{
+    player::{
+        devices::format::FormatInfo,
+        media::metadata::{Metadata, SourceFormat},
+    },
+    shared::settings::playback::PlaybackSettings,
+};
 
 use super::{queue::QueueItemData, thread::PlaybackState};
 use std::path::PathBuf;
@@ -12,10 +18,40 @@ pub enum RepeatState {
     RepeatingOne,
 }
 
+/// An output device that playback can be switched to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputDevice {
+    /// The device's unique ID, which is used to select it.
+    pub uid: String,
+    pub name: String,
+}
+
+/// Details about the audio pipeline, for bug reports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaybackDiagnostics {
+    /// The device provider the stream was opened with, if one is open.
+    pub device_provider: Option<&'static str>,
+    pub media_provider: &'static str,
+    /// The output device being played to, if one is open.
+    pub device: Option<OutputDevice>,
+    /// The format the output stream was opened with, if one is open.
+    pub format: Option<FormatInfo>,
+}
+
+/// The format of the track being played, and of the output stream it's played to.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct PlaybackFormat {
+    /// The format of the current track, if one is open.
+    pub source: Option<SourceFormat>,
+    /// The format the output stream was opened with, if one is open.
+    pub output: Option<FormatInfo>,
+}
+
 /// A command to the playback thread. This is used to control the playback thread from other
 /// threads. The playback thread recieves these commands from an MPSC channel, and processes them
-/// in the order they are recieved. They are processed every 10ms when playback is stopped, or
-/// every time additional decoding is required to fill the ring buffer during playback.
+/// in the order they are recieved. They are processed as soon as they arrive when playback is
+/// stopped or paused, or every time additional decoding is required to fill the ring buffer during
+/// playback.
 #[derive(Debug, PartialEq, Clone)]
 pub enum PlaybackCommand {
     /// Requests that the playback thread begin playback.
@@ -24,21 +60,36 @@ pub enum PlaybackCommand {
     Pause,
     /// Requests that, if the playback thread is playing, it pauses, and vise/versa.
     TogglePlayPause,
-    /// Requests that the playback thread open the specified file for immediate playback.
-    Open(PathBuf),
+    /// Requests that the playback thread open the specified track for immediate playback, without
+    /// adding it to the queue.
+    Open(QueueItemData),
     /// Requests that the playback thread queue the specified file for playback after the current
     /// file. If there is no current file, the specified file will be played immediately.
     Queue(QueueItemData),
     /// Requests that the playback thread queue a list of files for playback after the current
     /// file. If there is no current file, the first file in the list will be played immediately.
     QueueList(Vec<QueueItemData>),
+    /// Requests that the playback thread insert a list of files into the queue directly after the
+    /// current file. If there is no current file, this behaves like QueueList.
+    PlayNext(Vec<QueueItemData>),
+    /// Requests that the playback thread add a list of files to the end of the queue, and play
+    /// the first of them that was added.
+    QueueAndPlay(Vec<QueueItemData>),
     /// Requests that the playback thread skip to the next file in the queue.
     Next,
     /// Requests that the playback thread skip to the previous file in the queue.
-    /// If the current file is more than 5 seconds in, it will be restarted.
+    /// If restarting tracks is enabled and the current file has played for longer than the
+    /// restart threshold, it will be restarted instead.
     Previous,
-    /// Requests that the playback thread clear the queue.
+    /// Requests that the playback thread play the current file again from its start, resuming
+    /// playback if it's paused.
+    Restart,
+    /// Requests that the playback thread clear the queue and stop playback.
     ClearQueue,
+    /// Requests that the playback thread put back the queue from before it was last cleared, and
+    /// reopen the track that was current where it was stopped. Does nothing if anything has been
+    /// queued since.
+    UndoClearQueue,
     /// Jumps to the specified position in the queue.
     Jump(usize),
     /// Jumps to the specified position in the queue. This will use the position of the track
@@ -48,6 +99,16 @@ pub enum PlaybackCommand {
     Seek(f64),
     /// Requests that the playback thread set the volume to the specified level.
     SetVolume(f64),
+    /// Requests that the playback thread set the stereo balance, from -1.0 (left only) to 1.0
+    /// (right only).
+    SetBalance(f64),
+    /// Requests that the playback thread switch to the output device with the specified UID, or
+    /// to the default device if None. The device is kept until another one is requested: if it's
+    /// removed, playback moves to the default device until it's connected again.
+    SetDevice(Option<String>),
+    /// Requests that the playback thread use the specified playback settings. Changes to the
+    /// output format reopen the stream, other changes apply from the next frame or track.
+    UpdateSettings(PlaybackSettings),
     /// Requests that the playback thread replace the current queue with the specified queue.
     /// This will set the current playing track to the first item in the queue.
     ReplaceQueue(Vec<QueueItemData>),
@@ -58,6 +119,20 @@ pub enum PlaybackCommand {
     ToggleShuffle,
     /// Requests that the repeating setting should be set to the specified RepeatState.
     SetRepeat(RepeatState),
+    /// Requests that the playback thread mute playback, remembering the exact current volume, or
+    /// restore that volume if it is already muted. Setting the volume while muted also unmutes.
+    ToggleMute,
+    /// Requests that the playback thread re-send its current state (playback state, position,
+    /// duration, volume, mute, balance, repeat, shuffle and output device), so that newly
+    /// attached listeners don't have to wait for the next change to know what's going on.
+    RequestState,
+    /// Requests that the playback thread send a Diagnostics event describing the output device,
+    /// its format, and the providers in use.
+    RequestDiagnostics,
+    /// Requests that the playback thread stop playback, close the stream and device, and exit.
+    /// The playback thread will send a ShutdownComplete event once this is done, and will not
+    /// process any further commands.
+    Shutdown,
 }
 
 /// An event from the playback thread. This is used to communicate information from the playback
@@ -69,6 +144,9 @@ pub enum PlaybackEvent {
     StateChanged(PlaybackState),
     /// Indicates that the current file has changed providing the path to the new file.
     SongChanged(PathBuf),
+    /// Indicates that the file at the given path was played to the end. Not sent when a track is
+    /// skipped or playback is stopped.
+    TrackFinished(PathBuf),
     /// Indicates that the duration of the current file has changed. The f64 is the new duration,
     /// in seconds.
     DurationChanged(u64),
@@ -91,4 +169,24 @@ pub enum PlaybackEvent {
     RepeatChanged(RepeatState),
     /// Indicates that the volume has changed. The f64 is the new volume, from 0.0 to 1.0.
     VolumeChanged(f64),
+    /// Indicates that the stereo balance has changed. The f64 is the new balance, from -1.0 (left
+    /// only) to 1.0 (right only).
+    BalanceChanged(f64),
+    /// Indicates that the output device has changed. None if no device could be opened.
+    DeviceChanged(Option<OutputDevice>),
+    /// Indicates that an output device has been connected or removed. Contains every device that
+    /// is currently available.
+    DevicesChanged(Vec<OutputDevice>),
+    /// Indicates that playback was requested while stopped, but there was nothing in the queue to
+    /// play.
+    QueueEmpty,
+    /// Indicates that playback has been muted (true) or unmuted (false).
+    MuteChanged(bool),
+    /// Indicates that the format of the current track or of the output stream has changed.
+    FormatChanged(Box<PlaybackFormat>),
+    /// Describes the audio pipeline, in response to a RequestDiagnostics command.
+    Diagnostics(Box<PlaybackDiagnostics>),
+    /// Indicates that the playback thread has closed the stream and device and has exited. No
+    /// further events will be sent.
+    ShutdownComplete,
 }
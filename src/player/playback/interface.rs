@@ -1,21 +1,51 @@
 #![allow(dead_code)]
 
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use async_channel::{Receiver, Sender};
-use gpui::App;
+use gpui::{App, PromptLevel};
+use smol::Timer;
+use tracing::{info, warn};
 
 use crate::{
+    player::library::{
+        db::{LibraryAccess, list_tracks_in_folder},
+        scan::list_folder_tracks,
+        types::Track,
+    },
+    player::media::metadata::Metadata,
     player::playback::events::RepeatState,
-    player::ui::models::{CurrentTrack, ImageEvent, MMBSEvent, Models, PlaybackInfo},
+    player::ui::{
+        app::Pool,
+        models::{CurrentTrack, ImageEvent, MMBSEvent, Models, PlaybackInfo},
+    },
+    shared::{
+        db::TursoDatabase,
+        settings::{SettingsGlobal, playback::PlaybackSettings},
+    },
 };
 
 use super::{
-    events::{PlaybackCommand, PlaybackEvent},
+    events::{OutputDevice, PlaybackCommand, PlaybackEvent},
     queue::QueueItemData,
     thread::PlaybackState,
 };
 
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(5);
+const SHUTDOWN_POLL_ATTEMPTS: usize = 20;
+
+/// The most tracks queued at once from a folder, so that pointing at a huge folder doesn't flood
+/// the queue.
+const MAX_FOLDER_TRACKS: usize = 500;
+
+/// How long the UI says that the queue is empty after play is pressed with nothing queued.
+const QUEUE_EMPTY_NOTICE_DURATION: Duration = Duration::from_secs(3);
+
 /// The PlaybackInterface trait defines the method used to create the struct that will be used to
 /// communicate between the playback thread and the main thread.
 pub trait PlaybackInterface {
@@ -72,11 +102,11 @@ impl GPUIPlaybackInterface {
         .detach();
     }
 
-    pub fn open(&self, path: PathBuf) {
+    pub fn open(&self, item: QueueItemData) {
         let commands_tx = self.commands_tx.clone();
         smol::spawn(async move {
             commands_tx
-                .send(PlaybackCommand::Open(path))
+                .send(PlaybackCommand::Open(item))
                 .await
                 .expect("could not send tx");
         })
@@ -105,6 +135,28 @@ impl GPUIPlaybackInterface {
         .detach();
     }
 
+    pub fn queue_and_play(&self, items: Vec<QueueItemData>) {
+        let commands_tx = self.commands_tx.clone();
+        smol::spawn(async move {
+            commands_tx
+                .send(PlaybackCommand::QueueAndPlay(items))
+                .await
+                .expect("could not send tx");
+        })
+        .detach();
+    }
+
+    pub fn play_next(&self, items: Vec<QueueItemData>) {
+        let commands_tx = self.commands_tx.clone();
+        smol::spawn(async move {
+            commands_tx
+                .send(PlaybackCommand::PlayNext(items))
+                .await
+                .expect("could not send tx");
+        })
+        .detach();
+    }
+
     pub fn next(&self) {
         let commands_tx = self.commands_tx.clone();
         smol::spawn(async move {
@@ -127,6 +179,17 @@ impl GPUIPlaybackInterface {
         .detach();
     }
 
+    pub fn restart(&self) {
+        let commands_tx = self.commands_tx.clone();
+        smol::spawn(async move {
+            commands_tx
+                .send(PlaybackCommand::Restart)
+                .await
+                .expect("could not send tx");
+        })
+        .detach();
+    }
+
     pub fn clear_queue(&self) {
         let commands_tx = self.commands_tx.clone();
         smol::spawn(async move {
@@ -138,6 +201,17 @@ impl GPUIPlaybackInterface {
         .detach();
     }
 
+    pub fn undo_clear_queue(&self) {
+        let commands_tx = self.commands_tx.clone();
+        smol::spawn(async move {
+            commands_tx
+                .send(PlaybackCommand::UndoClearQueue)
+                .await
+                .expect("could not send tx");
+        })
+        .detach();
+    }
+
     pub fn jump(&self, index: usize) {
         let commands_tx = self.commands_tx.clone();
         smol::spawn(async move {
@@ -182,6 +256,65 @@ impl GPUIPlaybackInterface {
         .detach();
     }
 
+    pub fn set_balance(&self, balance: f64) {
+        let commands_tx = self.commands_tx.clone();
+        smol::spawn(async move {
+            commands_tx
+                .send(PlaybackCommand::SetBalance(balance))
+                .await
+                .expect("could not send tx");
+        })
+        .detach();
+    }
+
+    /// Sends the current playback settings to the playback thread.
+    pub fn update_settings(&self, settings: PlaybackSettings) {
+        let commands_tx = self.commands_tx.clone();
+        smol::spawn(async move {
+            commands_tx
+                .send(PlaybackCommand::UpdateSettings(settings))
+                .await
+                .expect("could not send tx");
+        })
+        .detach();
+    }
+
+    /// Switches to the output device with the given UID, or to the default device if None.
+    pub fn set_device(&self, uid: Option<String>) {
+        let commands_tx = self.commands_tx.clone();
+        smol::spawn(async move {
+            commands_tx
+                .send(PlaybackCommand::SetDevice(uid))
+                .await
+                .expect("could not send tx");
+        })
+        .detach();
+    }
+
+    /// Asks the playback thread to describe the audio pipeline. The answer is stored in
+    /// `PlaybackInfo::diagnostics`.
+    pub fn request_diagnostics(&self) {
+        let commands_tx = self.commands_tx.clone();
+        smol::spawn(async move {
+            commands_tx
+                .send(PlaybackCommand::RequestDiagnostics)
+                .await
+                .expect("could not send tx");
+        })
+        .detach();
+    }
+
+    pub fn toggle_mute(&self) {
+        let commands_tx = self.commands_tx.clone();
+        smol::spawn(async move {
+            commands_tx
+                .send(PlaybackCommand::ToggleMute)
+                .await
+                .expect("could not send tx");
+        })
+        .detach();
+    }
+
     pub fn replace_queue(&self, items: Vec<QueueItemData>) {
         let commands_tx = self.commands_tx.clone();
         smol::spawn(async move {
@@ -226,6 +359,30 @@ impl GPUIPlaybackInterface {
         .detach();
     }
 
+    /// Requests that the playback thread shut down. The returned future resolves once the playback
+    /// thread has closed the stream and exited, or after a short timeout if it doesn't respond.
+    pub fn shutdown(&self) -> impl Future<Output = ()> + 'static {
+        let commands_tx = self.commands_tx.clone();
+
+        async move {
+            if commands_tx.send(PlaybackCommand::Shutdown).await.is_err() {
+                // the playback thread has already exited
+                return;
+            }
+
+            // the command channel closes when the playback thread drops its receiver on exit
+            for _ in 0..SHUTDOWN_POLL_ATTEMPTS {
+                if commands_tx.is_closed() {
+                    return;
+                }
+
+                Timer::after(SHUTDOWN_POLL_INTERVAL).await;
+            }
+
+            warn!("Playback thread did not shut down in time");
+        }
+    }
+
     pub fn get_sender(&self) -> Sender<PlaybackCommand> {
         self.commands_tx.clone()
     }
@@ -243,6 +400,7 @@ impl GPUIPlaybackInterface {
         let albumart_model = app.global::<Models>().albumart.clone();
         let queue_model = app.global::<Models>().queue.clone();
         let mmbs_model = app.global::<Models>().mmbs.clone();
+        let play_history_model = app.global::<Models>().play_history.clone();
 
         let playback_info = app.global::<PlaybackInfo>().clone();
 
@@ -251,10 +409,26 @@ impl GPUIPlaybackInterface {
         };
 
         app.spawn(async move |cx| {
+            let mut segment_track = None;
+
             loop {
                 while let Ok(event) = events_rx.recv().await {
                     match event {
-                        PlaybackEvent::MetadataUpdate(v) => {
+                        PlaybackEvent::ShutdownComplete => {
+                            info!("Playback thread shut down");
+                            return;
+                        }
+                        PlaybackEvent::MetadataUpdate(mut v) => {
+                            cx.update(|cx| {
+                                apply_segment_details(
+                                    cx,
+                                    &playback_info,
+                                    &mut segment_track,
+                                    &mut v,
+                                )
+                            })
+                            .expect("failed to read current track");
+
                             let metadata = Arc::new(*v.clone());
 
                             metadata_model
@@ -321,6 +495,9 @@ impl GPUIPlaybackInterface {
                                 })
                                 .expect("failed to broadcast MMBS event PositionChanged");
                         }
+                        PlaybackEvent::TrackFinished(path) => play_history_model
+                            .update(cx, |m, cx| m.record_play(&path, cx))
+                            .expect("failed to record play"),
                         PlaybackEvent::DurationChanged(v) => {
                             playback_info
                                 .duration
@@ -364,6 +541,9 @@ impl GPUIPlaybackInterface {
                                 .expect("failed to update shuffle state");
                         }
                         PlaybackEvent::VolumeChanged(v) => {
+                            cx.update(|cx| remember_device_volume(cx, &playback_info, v))
+                                .expect("failed to remember device volume");
+
                             playback_info
                                 .volume
                                 .update(cx, |m, cx| {
@@ -386,6 +566,80 @@ impl GPUIPlaybackInterface {
                                     .expect("failed to update volume model");
                             }
                         }
+                        PlaybackEvent::BalanceChanged(v) => playback_info
+                            .balance
+                            .update(cx, |m, cx| {
+                                *m = v;
+                                cx.notify();
+                            })
+                            .expect("failed to update balance model"),
+                        PlaybackEvent::DeviceChanged(v) => {
+                            cx.update(|cx| restore_device_volume(cx, &playback_info, v.as_ref()))
+                                .expect("failed to restore device volume");
+
+                            playback_info
+                                .device
+                                .update(cx, |m, cx| {
+                                    *m = v;
+                                    cx.notify();
+                                })
+                                .expect("failed to update device model");
+                        }
+                        PlaybackEvent::DevicesChanged(v) => playback_info
+                            .devices
+                            .update(cx, |m, cx| {
+                                *m = v;
+                                cx.notify();
+                            })
+                            .expect("failed to update devices model"),
+                        PlaybackEvent::Diagnostics(v) => playback_info
+                            .diagnostics
+                            .update(cx, |m, cx| {
+                                *m = Some(*v);
+                                cx.notify();
+                            })
+                            .expect("failed to update diagnostics model"),
+                        PlaybackEvent::FormatChanged(v) => playback_info
+                            .format
+                            .update(cx, |m, cx| {
+                                *m = *v;
+                                cx.notify();
+                            })
+                            .expect("failed to update format model"),
+                        PlaybackEvent::QueueEmpty => {
+                            let shown_at = Instant::now();
+                            let notice = playback_info.queue_empty_notice.clone();
+
+                            notice
+                                .update(cx, |m, cx| {
+                                    *m = Some(shown_at);
+                                    cx.notify();
+                                })
+                                .expect("failed to update queue empty notice model");
+
+                            // pressing play again restarts the countdown, so only the latest
+                            // notice is hidden
+                            cx.spawn(async move |cx| {
+                                Timer::after(QUEUE_EMPTY_NOTICE_DURATION).await;
+
+                                notice
+                                    .update(cx, |m, cx| {
+                                        if *m == Some(shown_at) {
+                                            *m = None;
+                                            cx.notify();
+                                        }
+                                    })
+                                    .ok();
+                            })
+                            .detach();
+                        }
+                        PlaybackEvent::MuteChanged(v) => playback_info
+                            .muted
+                            .update(cx, |m, cx| {
+                                *m = v;
+                                cx.notify();
+                            })
+                            .expect("failed to update mute model"),
                         PlaybackEvent::QueuePositionChanged(v) => queue_model
                             .update(cx, |m, cx| {
                                 m.position = v;
@@ -417,3 +671,347 @@ pub fn replace_queue(items: Vec<QueueItemData>, app: &mut App) {
 
     // data_interface.evict_cache();
 }
+
+/// Adds the given items to the end of the queue and jumps to the first of them, keeping the
+/// tracks that were already queued.
+pub fn append_and_play(items: Vec<QueueItemData>, app: &mut App) {
+    app.global::<GPUIPlaybackInterface>().queue_and_play(items);
+}
+
+/// Adds every other playable file in the same folder as `path` to the end of the queue, for music
+/// that isn't organized into albums. Files that are in the library are queued with their library
+/// information. The folder is read in the background, and the user is told if it had too many
+/// files to queue them all.
+pub fn queue_other_folder_tracks(path: &Path, app: &mut App) {
+    let Some(folder) = path.parent().map(Path::to_path_buf) else {
+        return;
+    };
+    let path = path.to_path_buf();
+    let db = app.global::<Pool>().0.clone();
+
+    app.spawn(async move |cx| {
+        let found = {
+            let folder = folder.clone();
+            cx.background_spawn(async move { other_folder_tracks(&db, &folder, &path).await })
+                .await
+        };
+
+        let (tracks, truncated) = match found {
+            Ok(found) => found,
+            Err(err) => {
+                warn!("Couldn't read folder {:?}: {}", folder, err);
+                return;
+            }
+        };
+
+        if tracks.is_empty() {
+            info!("No other tracks in {:?} to queue", folder);
+            return;
+        }
+
+        cx.update(|cx| {
+            let items = tracks
+                .into_iter()
+                .map(|(path, track)| match track {
+                    Some(track) => QueueItemData::from_track(cx, &track),
+                    None => QueueItemData::new(cx, path, None, None),
+                })
+                .collect();
+            cx.global::<GPUIPlaybackInterface>().queue_list(items);
+
+            if truncated {
+                show_folder_truncated(cx, &folder);
+            }
+        })
+        .ok();
+    })
+    .detach();
+}
+
+/// Lists the playable files in `folder` other than `path`, each with its track if it's in the
+/// library. At most `MAX_FOLDER_TRACKS` are listed; the flag says whether there were more.
+async fn other_folder_tracks(
+    db: &TursoDatabase,
+    folder: &Path,
+    path: &Path,
+) -> std::io::Result<(Vec<(PathBuf, Option<Track>)>, bool)> {
+    // one more than the limit besides `path`, to tell whether any were left out
+    let mut paths = list_folder_tracks(folder, MAX_FOLDER_TRACKS + 2)?;
+    paths.retain(|other| other != path);
+
+    let truncated = paths.len() > MAX_FOLDER_TRACKS;
+    paths.truncate(MAX_FOLDER_TRACKS);
+
+    let mut library: HashMap<PathBuf, Track> = match list_tracks_in_folder(db, folder).await {
+        Ok(tracks) => tracks
+            .into_iter()
+            .map(|track| (track.location.clone(), track))
+            .collect(),
+        Err(err) => {
+            warn!(
+                "Couldn't look up the library tracks in {:?}: {:?}",
+                folder, err
+            );
+            HashMap::new()
+        }
+    };
+
+    let tracks = paths
+        .into_iter()
+        .map(|path| {
+            let track = library.remove(&path);
+            (path, track)
+        })
+        .collect();
+
+    Ok((tracks, truncated))
+}
+
+fn show_folder_truncated(cx: &mut App, folder: &Path) {
+    warn!(
+        "{:?} has more than {} tracks, only queued the first {}",
+        folder, MAX_FOLDER_TRACKS, MAX_FOLDER_TRACKS
+    );
+
+    let Some(window) = cx.active_window() else {
+        return;
+    };
+    let message = format!("Only the first {MAX_FOLDER_TRACKS} tracks were queued");
+    let detail = format!(
+        "{} has more tracks than can be queued at once.",
+        folder.display()
+    );
+
+    window
+        .update(cx, |_, window, cx| {
+            // nothing needs to happen once the prompt is dismissed
+            drop(window.prompt(PromptLevel::Info, &message, Some(&detail), &["OK"], cx));
+        })
+        .ok();
+}
+
+/// Replaces the queue with tracks from across the whole library, shuffled, and starts playing them.
+/// Libraries with more tracks than the user's limit are sampled at random.
+pub fn shuffle_library(app: &mut App) {
+    let limit = app
+        .global::<SettingsGlobal>()
+        .model
+        .read(app)
+        .playback
+        .shuffle_library_limit;
+
+    let tracks = match app.list_random_tracks(limit.max(1).into()) {
+        Ok(tracks) => tracks,
+        Err(err) => {
+            warn!("Couldn't pick tracks to shuffle: {:?}", err);
+            return;
+        }
+    };
+
+    if tracks.is_empty() {
+        info!("No tracks in the library to shuffle");
+        return;
+    }
+
+    let items = tracks
+        .iter()
+        .map(|track| {
+            QueueItemData::new(app, track.location.clone(), Some(track.id), track.album_id)
+                .with_segment(track.segment.clone())
+        })
+        .collect();
+
+    if !*app.global::<PlaybackInfo>().shuffling.read(app) {
+        app.global::<GPUIPlaybackInterface>().toggle_shuffle();
+    }
+
+    replace_queue(items, app);
+}
+
+/// Remembers the volume for the output device being played to. Muting doesn't count, so that
+/// switching back to a device doesn't leave it silent.
+fn remember_device_volume(cx: &mut App, playback_info: &PlaybackInfo, volume: f64) {
+    if volume <= 0.0 {
+        return;
+    }
+
+    let Some(uid) = playback_info
+        .device
+        .read(cx)
+        .as_ref()
+        .map(|device| device.uid.clone())
+    else {
+        return;
+    };
+
+    playback_info.device_volumes.update(cx, |m, _| {
+        m.insert(uid, volume);
+    });
+}
+
+/// Switches to the volume last used with the device, when playback moves to a different device
+/// that has been played to before.
+fn restore_device_volume(
+    cx: &mut App,
+    playback_info: &PlaybackInfo,
+    device: Option<&OutputDevice>,
+) {
+    if let Some(volume) = device_volume(cx, playback_info, device) {
+        cx.global::<GPUIPlaybackInterface>().set_volume(volume);
+    }
+}
+
+/// The volume to switch to when playback moves to the device, if it's a different device that
+/// was last played to at a different volume. Nothing is changed while muted, since setting the
+/// volume would unmute.
+fn device_volume(
+    cx: &App,
+    playback_info: &PlaybackInfo,
+    device: Option<&OutputDevice>,
+) -> Option<f64> {
+    let device = device?;
+
+    let unchanged = playback_info
+        .device
+        .read(cx)
+        .as_ref()
+        .is_some_and(|current| current.uid == device.uid);
+
+    if unchanged || *playback_info.muted.read(cx) {
+        return None;
+    }
+
+    let volume = playback_info
+        .device_volumes
+        .read(cx)
+        .get(&device.uid)
+        .copied()?;
+
+    (volume != *playback_info.volume.read(cx)).then_some(volume)
+}
+
+/// Replaces the details read from the file with the ones in the library when the current track is
+/// a segment of a larger file, such as a track from a cue sheet. The file's own tags describe the
+/// whole file, not the track.
+fn apply_segment_details(
+    cx: &App,
+    playback_info: &PlaybackInfo,
+    cache: &mut SegmentTrackCache,
+    metadata: &mut Metadata,
+) {
+    let Some(current) = playback_info.current_track.read(cx) else {
+        return;
+    };
+    let path = current.get_path();
+
+    // streams send new metadata for every song, so the track is only looked up once per path
+    if cache.as_ref().is_none_or(|(cached, _)| cached != path) {
+        match cx.get_track_by_path(path) {
+            Ok(track) => *cache = Some((path.clone(), track)),
+            Err(err) => {
+                warn!("Couldn't look up {:?} in the library: {:?}", path, err);
+                return;
+            }
+        }
+    }
+
+    let Some((_, Some(track))) = cache else {
+        return;
+    };
+
+    if track.segment.is_none() {
+        return;
+    }
+
+    metadata.name = Some(track.title.0.to_string());
+    if let Some(artist) = &track.artist_names {
+        metadata.artist = Some(artist.0.to_string());
+    }
+    metadata.track_current = track.track_number.map(|v| v as u64);
+}
+
+/// The library track last looked up by `apply_segment_details`, with the path it was looked up
+/// by. None inside means the path isn't in the library.
+type SegmentTrackCache = Option<(PathBuf, Option<Arc<Track>>)>;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use gpui::{AppContext, TestAppContext};
+
+    use crate::player::playback::events::PlaybackFormat;
+
+    use super::*;
+
+    fn playback_info(cx: &mut TestAppContext) -> PlaybackInfo {
+        cx.update(|cx| PlaybackInfo {
+            position: cx.new(|_| 0),
+            duration: cx.new(|_| 0),
+            playback_state: cx.new(|_| PlaybackState::Stopped),
+            current_track: cx.new(|_| None),
+            shuffling: cx.new(|_| false),
+            repeating: cx.new(|_| RepeatState::NotRepeating),
+            volume: cx.new(|_| 0.5),
+            prev_volume: cx.new(|_| 0.5),
+            muted: cx.new(|_| false),
+            balance: cx.new(|_| 0.0),
+            device: cx.new(|_| None),
+            devices: cx.new(|_| Vec::new()),
+            preferred_device: cx.new(|_| None),
+            device_volumes: cx.new(|_| BTreeMap::new()),
+            queue_empty_notice: cx.new(|_| None),
+            diagnostics: cx.new(|_| None),
+            format: cx.new(|_| PlaybackFormat::default()),
+        })
+    }
+
+    fn device(uid: &str) -> OutputDevice {
+        OutputDevice {
+            uid: uid.to_string(),
+            name: uid.to_string(),
+        }
+    }
+
+    fn play_to(cx: &mut TestAppContext, info: &PlaybackInfo, uid: &str, volume: f64) {
+        cx.update(|cx| {
+            info.device.update(cx, |m, _| *m = Some(device(uid)));
+            info.volume.update(cx, |m, _| *m = volume);
+            remember_device_volume(cx, info, volume);
+        });
+    }
+
+    #[gpui::test]
+    fn volumes_are_remembered_per_device(cx: &mut TestAppContext) {
+        let info = playback_info(cx);
+        play_to(cx, &info, "speakers", 0.3);
+        play_to(cx, &info, "headphones", 0.8);
+
+        cx.update(|cx| {
+            assert_eq!(
+                device_volume(cx, &info, Some(&device("speakers"))),
+                Some(0.3)
+            );
+            assert_eq!(device_volume(cx, &info, Some(&device("headphones"))), None);
+            assert_eq!(device_volume(cx, &info, Some(&device("hdmi"))), None);
+        });
+    }
+
+    #[gpui::test]
+    fn muting_is_not_remembered(cx: &mut TestAppContext) {
+        let info = playback_info(cx);
+        play_to(cx, &info, "speakers", 0.3);
+        play_to(cx, &info, "speakers", 0.0);
+        play_to(cx, &info, "headphones", 0.8);
+
+        cx.update(|cx| {
+            assert_eq!(
+                device_volume(cx, &info, Some(&device("speakers"))),
+                Some(0.3)
+            );
+
+            info.muted.update(cx, |m, _| *m = true);
+            assert_eq!(device_volume(cx, &info, Some(&device("speakers"))), None);
+        });
+    }
+}
@@ -0,0 +1,118 @@
+use crate::player::{
+    devices::resample::{SampleFrom, SampleInto},
+    media::playback::Samples,
+};
+
+/// The level above which samples start being compressed, as a fraction of full scale (about
+/// -0.9 dBFS). Anything quieter passes through untouched.
+const KNEE: f64 = 0.9;
+
+/// Compresses a sample above the knee so that it approaches, but never exceeds, full scale. The
+/// curve has the same slope as the input at the knee, so there's no audible corner.
+fn soft_clip(value: f64) -> f64 {
+    let magnitude = value.abs();
+
+    if magnitude <= KNEE {
+        return value;
+    }
+
+    let headroom = 1.0 - KNEE;
+    let limited = KNEE + headroom * ((magnitude - KNEE) / headroom).tanh();
+
+    limited.copysign(value)
+}
+
+fn limit_channels<T>(channels: &mut [Vec<T>])
+where
+    T: Copy + SampleInto<f64> + SampleFrom<f64>,
+{
+    for sample in channels.iter_mut().flatten() {
+        let value = (*sample).sample_into();
+
+        if value.abs() > KNEE {
+            *sample = T::sample_from(soft_clip(value));
+        }
+    }
+}
+
+/// Keeps samples within full scale after their level has been raised, so that peaks are rounded
+/// off instead of clipping harshly. Integer samples can't exceed full scale, but peaks right at
+/// the edge of the range are still softened the same way.
+pub fn limit(samples: &mut Samples) {
+    match samples {
+        Samples::Float64(v) => v
+            .iter_mut()
+            .flatten()
+            .for_each(|sample| *sample = soft_clip(*sample)),
+        Samples::Float32(v) => limit_channels(v),
+        Samples::Signed32(v) => limit_channels(v),
+        Samples::Unsigned32(v) => limit_channels(v),
+        Samples::Signed24(v) => limit_channels(v),
+        Samples::Unsigned24(v) => limit_channels(v),
+        Samples::Signed16(v) => limit_channels(v),
+        Samples::Unsigned16(v) => limit_channels(v),
+        Samples::Signed8(v) => limit_channels(v),
+        Samples::Unsigned8(v) => limit_channels(v),
+        Samples::Dsd(_) => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quiet_samples_are_untouched() {
+        let original: Vec<f32> = (0..100).map(|n| (n as f32 * 0.1).sin() * 0.8).collect();
+        let mut samples = Samples::Float32(vec![original.clone()]);
+
+        limit(&mut samples);
+
+        let Samples::Float32(limited) = samples else {
+            unreachable!();
+        };
+        assert_eq!(limited[0], original);
+    }
+
+    #[test]
+    fn over_range_float_samples_stay_within_full_scale() {
+        let mut samples = Samples::Float64(vec![vec![1.5, -2.0, 4.0, -1.01, 0.95]]);
+
+        limit(&mut samples);
+
+        let Samples::Float64(limited) = samples else {
+            unreachable!();
+        };
+        for sample in &limited[0] {
+            assert!(sample.abs() < 1.0, "{sample} exceeds full scale");
+        }
+        assert!(limited[0][1] < 0.0 && limited[0][3] < 0.0);
+    }
+
+    #[test]
+    fn curve_is_continuous_and_monotonic() {
+        let mut last = 0.0;
+
+        for n in 0..=4000 {
+            let value = soft_clip(n as f64 / 1000.0);
+            assert!(value >= last, "curve decreased at {}", n as f64 / 1000.0);
+            last = value;
+        }
+
+        assert!((soft_clip(KNEE + 1e-9) - KNEE).abs() < 1e-6);
+    }
+
+    #[test]
+    fn integer_samples_stay_in_range() {
+        let mut samples = Samples::Signed16(vec![vec![i16::MAX, i16::MIN + 1, 1000]]);
+
+        limit(&mut samples);
+
+        let Samples::Signed16(limited) = samples else {
+            unreachable!();
+        };
+        assert!(limited[0][0] < i16::MAX);
+        assert!(limited[0][1] > i16::MIN + 1);
+        assert_eq!(limited[0][2], 1000);
+    }
+}
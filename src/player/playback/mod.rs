@@ -1,4 +1,11 @@
+pub mod balance;
+pub mod crossfeed;
+pub mod device_watcher;
 pub mod events;
 pub mod interface;
+pub mod limiter;
 pub mod queue;
+pub mod segment;
+pub mod silence;
 pub mod thread;
+pub mod volume;
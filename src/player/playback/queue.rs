@@ -3,23 +3,37 @@ use std::{fmt::Display, sync::Arc};
 use gpui::{App, AppContext, Entity, RenderImage, SharedString};
 use std::path::PathBuf;
 
-use crate::{player::library::db::LibraryAccess, player::ui::data::Decode};
+use crate::{
+    player::library::db::LibraryAccess,
+    player::library::types::{Track, TrackSegment},
+    player::media::stream::is_stream_url,
+    player::ui::data::Decode,
+};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct QueueItemData {
     /// The UI data associated with the queue item.
     data: Entity<Option<QueueItemUIData>>,
+    /// The title, artist and duration of the track, once they're known.
+    details: Entity<Option<QueueItemDetails>>,
     /// The database ID of track the item is from, if it exists.
     db_id: Option<i64>,
     /// The database ID of album the item is from, if it exists.
     db_album_id: Option<i64>,
+    /// The disc and track number of the track on its album, if it's from the library and they're
+    /// known.
+    disc_number: Option<i32>,
+    track_number: Option<i32>,
     /// The path to the track file.
     path: PathBuf,
+    /// The part of a larger file that's played, if the track isn't a file of its own. The path
+    /// still identifies the track, while the audio is read from the segment's file.
+    segment: Option<TrackSegment>,
 }
 
 impl Display for QueueItemData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(self.path.to_str().unwrap_or("invalid path"))
+        write!(f, "{}", self.path.display())
     }
 }
 
@@ -35,6 +49,28 @@ pub struct QueueItemUIData {
     pub source: DataSource,
 }
 
+/// The title, artist and duration of a queued track. Unlike the UI data, these are kept for as
+/// long as the item is queued, so they're only ever looked up once.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueueItemDetails {
+    /// The name of the track, if it is known.
+    pub title: Option<SharedString>,
+    /// The name of the artist, if it is known.
+    pub artist: Option<SharedString>,
+    /// The duration of the track in seconds, if it is known.
+    pub duration: Option<u64>,
+}
+
+impl From<&Track> for QueueItemDetails {
+    fn from(track: &Track) -> Self {
+        QueueItemDetails {
+            title: Some(track.title.0.clone()),
+            artist: track.artist_names.as_ref().map(|v| v.0.clone()),
+            duration: Some(track.duration.max(0) as u64),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Copy)]
 pub enum DataSource {
     /// The metadata was read directly from the file.
@@ -50,10 +86,51 @@ impl QueueItemData {
             path,
             db_id,
             db_album_id,
+            disc_number: None,
+            track_number: None,
+            segment: None,
             data: cx.new(|_| None),
+            details: cx.new(|_| None),
         }
     }
 
+    /// Creates a new `QueueItemData` instance for a track from the library. The track's details
+    /// are filled in right away, so they don't have to be looked up again.
+    pub fn from_track(cx: &mut App, track: &Track) -> Self {
+        let details = QueueItemDetails::from(track);
+
+        QueueItemData {
+            path: track.location.clone(),
+            db_id: Some(track.id),
+            db_album_id: track.album_id,
+            disc_number: track.disc_number,
+            track_number: track.track_number,
+            segment: track.segment.clone(),
+            data: cx.new(|_| None),
+            details: cx.new(|_| Some(details)),
+        }
+    }
+
+    /// Plays only part of the item's file, from `start` to `end` (in seconds). Without an end, the
+    /// file is played to its end.
+    pub fn with_offsets(mut self, start: f64, end: Option<f64>) -> Self {
+        let to_ms = |secs: f64| (secs.max(0.0) * 1000.0).round() as u64;
+
+        self.segment = Some(TrackSegment {
+            file: self.path.clone(),
+            start_ms: to_ms(start),
+            end_ms: end.map(to_ms),
+        });
+        self
+    }
+
+    /// Plays the given part of a larger file, as for a track from a cue sheet, or the whole file if
+    /// there's no segment.
+    pub fn with_segment(mut self, segment: Option<TrackSegment>) -> Self {
+        self.segment = segment;
+        self
+    }
+
     /// Returns a copy of the UI data after ensuring that the metadata is loaded (or going to be
     /// loaded).
     pub fn get_data(&self, cx: &mut App) -> Entity<Option<QueueItemUIData>> {
@@ -95,17 +172,73 @@ impl QueueItemData {
                 return;
             }
 
+            // streams have no metadata on disk, the track info arrives once playback starts
+            if is_stream_url(&path) {
+                m.as_mut().unwrap().name = Some(path.to_string_lossy().to_string().into());
+                cx.notify();
+                return;
+            }
+
             // vital information left blank, try retriving the metadata from disk
             // much slower, especially on windows
-            let path_clone = path.clone();
+            cx.read_metadata(path.clone(), cx.entity()).detach();
+        });
+
+        model
+    }
+
+    /// Returns the details of the track after ensuring that they're loaded (or going to be
+    /// loaded). They're loaded once per item, in the background: library tracks are looked up in
+    /// the database, other files are read from disk.
+    pub fn get_details(&self, cx: &mut App) -> Entity<Option<QueueItemDetails>> {
+        let model = self.details.clone();
+        let track_id = self.db_id;
+        let path = self.path.clone();
+
+        model.update(cx, move |m, cx| {
+            if m.is_some() {
+                return;
+            }
+
+            // the title is shown until the stream's own information arrives
+            if is_stream_url(&path) {
+                *m = Some(QueueItemDetails {
+                    title: Some(path.to_string_lossy().to_string().into()),
+                    ..Default::default()
+                });
+                cx.notify();
+                return;
+            }
 
-            cx.read_metadata(path_clone.to_string_lossy().to_string(), cx.entity())
-                .detach();
+            // marks the details as loading, so that they're only read once
+            *m = Some(QueueItemDetails::default());
+            cx.read_details(path, track_id, cx.entity()).detach();
         });
 
         model
     }
 
+    /// Returns the duration of the track in seconds, if it is known. The details aren't loaded
+    /// if they haven't been already.
+    pub fn duration(&self, cx: &App) -> Option<u64> {
+        self.details.read(cx).as_ref().and_then(|v| v.duration)
+    }
+
+    /// Returns the title of the track, if it is known. The details aren't loaded if they haven't
+    /// been already.
+    pub fn title(&self, cx: &App) -> Option<SharedString> {
+        self.details.read(cx).as_ref().and_then(|v| v.title.clone())
+    }
+
+    /// Returns the artist of the track, if it is known. The details aren't loaded if they haven't
+    /// been already.
+    pub fn artist(&self, cx: &App) -> Option<SharedString> {
+        self.details
+            .read(cx)
+            .as_ref()
+            .and_then(|v| v.artist.clone())
+    }
+
     /// Drop the UI data from the queue item. This means the data must be retrieved again from disk
     /// if the item is used with get_data again.
     pub fn drop_data(&self, cx: &mut App) {
@@ -119,4 +252,95 @@ impl QueueItemData {
     pub fn get_path(&self) -> &PathBuf {
         &self.path
     }
+
+    /// Returns the part of a larger file that's played, if the track isn't a file of its own.
+    pub fn get_segment(&self) -> Option<&TrackSegment> {
+        self.segment.as_ref()
+    }
+
+    /// Returns the database ID of the track the item is from, if it exists.
+    pub fn get_track_id(&self) -> Option<i64> {
+        self.db_id
+    }
+
+    /// Whether this item comes straight after `previous` on an album: both are library tracks
+    /// from the same album, and this is the next track on the same disc or the first track on the
+    /// next disc.
+    pub fn follows_on_album(&self, previous: &QueueItemData) -> bool {
+        let (Some(album_id), Some(track), Some(previous_track)) =
+            (self.db_album_id, self.track_number, previous.track_number)
+        else {
+            return false;
+        };
+
+        if previous.db_album_id != Some(album_id) {
+            return false;
+        }
+
+        let disc = self.disc_number.unwrap_or(1);
+        let previous_disc = previous.disc_number.unwrap_or(1);
+
+        (disc == previous_disc && track == previous_track + 1)
+            || (disc == previous_disc + 1 && track == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gpui::TestAppContext;
+
+    use super::*;
+
+    fn item(
+        cx: &mut TestAppContext,
+        album_id: Option<i64>,
+        disc: Option<i32>,
+        track: Option<i32>,
+    ) -> QueueItemData {
+        let mut item =
+            cx.update(|cx| QueueItemData::new(cx, PathBuf::from("track.flac"), None, None));
+        item.db_album_id = album_id;
+        item.disc_number = disc;
+        item.track_number = track;
+        item
+    }
+
+    #[gpui::test]
+    fn the_next_track_on_a_disc_follows(cx: &mut TestAppContext) {
+        let first = item(cx, Some(1), Some(1), Some(3));
+
+        assert!(item(cx, Some(1), Some(1), Some(4)).follows_on_album(&first));
+        assert!(!item(cx, Some(1), Some(1), Some(5)).follows_on_album(&first));
+        assert!(!item(cx, Some(1), Some(1), Some(3)).follows_on_album(&first));
+        assert!(!item(cx, Some(1), Some(1), Some(2)).follows_on_album(&first));
+    }
+
+    #[gpui::test]
+    fn the_first_track_of_the_next_disc_follows(cx: &mut TestAppContext) {
+        let last = item(cx, Some(1), Some(1), Some(12));
+
+        assert!(item(cx, Some(1), Some(2), Some(1)).follows_on_album(&last));
+        assert!(!item(cx, Some(1), Some(2), Some(2)).follows_on_album(&last));
+        assert!(!item(cx, Some(1), Some(3), Some(1)).follows_on_album(&last));
+    }
+
+    #[gpui::test]
+    fn a_missing_disc_number_is_the_first_disc(cx: &mut TestAppContext) {
+        let first = item(cx, Some(1), None, Some(1));
+
+        assert!(item(cx, Some(1), Some(1), Some(2)).follows_on_album(&first));
+        assert!(item(cx, Some(1), None, Some(2)).follows_on_album(&first));
+    }
+
+    #[gpui::test]
+    fn tracks_from_other_albums_dont_follow(cx: &mut TestAppContext) {
+        let first = item(cx, Some(1), Some(1), Some(1));
+
+        assert!(!item(cx, Some(2), Some(1), Some(2)).follows_on_album(&first));
+        assert!(!item(cx, None, Some(1), Some(2)).follows_on_album(&first));
+        assert!(!item(cx, Some(1), Some(1), None).follows_on_album(&first));
+
+        let outside_library = item(cx, None, Some(1), Some(1));
+        assert!(!item(cx, Some(1), Some(1), Some(2)).follows_on_album(&outside_library));
+    }
 }
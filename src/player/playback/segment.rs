@@ -0,0 +1,171 @@
+use crate::player::{library::types::TrackSegment, media::playback::PlaybackFrame};
+
+/// Where a track starts and ends in its file, in seconds. Tracks that are files of their own start
+/// at zero and have no end.
+///
+/// Positions and durations are shown relative to the track, so they're converted with this when
+/// they're passed between the UI and the media provider.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Offsets {
+    pub start: f64,
+    pub end: Option<f64>,
+}
+
+impl Offsets {
+    pub fn from_segment(segment: Option<&TrackSegment>) -> Self {
+        segment.map_or_else(Offsets::default, |segment| Offsets {
+            start: segment.start_ms as f64 / 1000.0,
+            end: segment.end_ms.map(|end_ms| end_ms as f64 / 1000.0),
+        })
+    }
+
+    /// The length of the track in seconds, given the length of its file.
+    pub fn duration(&self, file_duration: u64) -> u64 {
+        let file_duration = file_duration as f64;
+        let end = self.end.map_or(file_duration, |end| end.min(file_duration));
+
+        (end - self.start).max(0.0).round() as u64
+    }
+
+    /// Converts a position in the file to a position in the track.
+    pub fn to_track(&self, file_position: u64) -> u64 {
+        (file_position as f64 - self.start).max(0.0).round() as u64
+    }
+
+    /// Converts a position in the track to a position in the file, never past the track's end.
+    pub fn to_file(&self, track_position: f64) -> f64 {
+        let position = self.start + track_position.max(0.0);
+
+        self.end.map_or(position, |end| position.min(end))
+    }
+}
+
+/// Ends playback part way through a file, at the end of a track that's stored inside a larger
+/// file (such as a track from a cue sheet).
+///
+/// Frames are counted as they're read, so the track ends on the exact sample it should, rather
+/// than at the next whole second reported by the media provider.
+pub struct SegmentEnd {
+    /// Where the segment ends, in seconds from the start of the file.
+    end: f64,
+    /// The position of the next frame, in seconds from the start of the file.
+    position: f64,
+}
+
+impl SegmentEnd {
+    pub fn new(start: f64, end: f64) -> Self {
+        SegmentEnd {
+            end,
+            position: start,
+        }
+    }
+
+    /// Called after seeking to `position`, in seconds from the start of the file.
+    pub fn seeked(&mut self, position: f64) {
+        self.position = position;
+    }
+
+    /// Whether the end of the segment has been reached, so nothing more should be played.
+    pub fn finished(&self) -> bool {
+        self.position >= self.end
+    }
+
+    /// Counts a frame that's about to be played, cutting it short if the segment ends part way
+    /// through it.
+    pub fn limit(&mut self, frame: &mut PlaybackFrame) {
+        let rate = frame.rate.max(1) as f64;
+        let len = frame.samples.samples_per_channel();
+        let remaining = ((self.end - self.position).max(0.0) * rate).round() as usize;
+
+        if remaining < len {
+            frame.samples.truncate(remaining);
+        }
+
+        self.position += len as f64 / rate;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::player::media::playback::Samples;
+
+    use super::*;
+
+    fn frame(len: usize) -> PlaybackFrame {
+        PlaybackFrame {
+            samples: Samples::Float64(vec![vec![0.5; len], vec![0.5; len]]),
+            rate: 1000,
+        }
+    }
+
+    #[test]
+    fn whole_files_are_unchanged() {
+        let offsets = Offsets::from_segment(None);
+
+        assert_eq!(offsets.duration(300), 300);
+        assert_eq!(offsets.to_track(42), 42);
+        assert_eq!(offsets.to_file(42.0), 42.0);
+    }
+
+    #[test]
+    fn positions_are_relative_to_the_track() {
+        let offsets = Offsets::from_segment(Some(&TrackSegment {
+            file: "/music/album.flac".into(),
+            start_ms: 67_000,
+            end_ms: Some(235_493),
+        }));
+
+        assert_eq!(offsets.duration(2580), 168);
+        assert_eq!(offsets.to_track(60), 0);
+        assert_eq!(offsets.to_track(100), 33);
+        assert_eq!(offsets.to_file(33.0), 100.0);
+        assert_eq!(offsets.to_file(500.0), 235.493);
+    }
+
+    #[test]
+    fn the_last_track_ends_with_the_file() {
+        let offsets = Offsets {
+            start: 2400.0,
+            end: None,
+        };
+
+        assert_eq!(offsets.duration(2580), 180);
+        assert_eq!(offsets.to_file(200.0), 2600.0);
+    }
+
+    #[test]
+    fn frames_before_the_end_are_untouched() {
+        let mut end = SegmentEnd::new(1.0, 2.0);
+        let mut first = frame(500);
+
+        end.limit(&mut first);
+
+        assert_eq!(first.samples.samples_per_channel(), 500);
+        assert!(!end.finished());
+    }
+
+    #[test]
+    fn the_last_frame_is_cut_at_the_end() {
+        let mut end = SegmentEnd::new(1.0, 2.0);
+        let mut first = frame(800);
+        let mut second = frame(800);
+
+        end.limit(&mut first);
+        end.limit(&mut second);
+
+        assert_eq!(second.samples.samples_per_channel(), 200);
+        assert!(end.finished());
+    }
+
+    #[test]
+    fn seeking_moves_the_position() {
+        let mut end = SegmentEnd::new(0.0, 2.0);
+        let mut frame = frame(800);
+
+        end.seeked(1.5);
+        end.limit(&mut frame);
+
+        assert_eq!(frame.samples.samples_per_channel(), 500);
+        assert!(end.finished());
+    }
+}
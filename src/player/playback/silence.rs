@@ -0,0 +1,263 @@
+use std::collections::VecDeque;
+
+use tracing::debug;
+
+use crate::{
+    player::devices::resample::SampleInto,
+    player::media::playback::{PlaybackFrame, Samples},
+    shared::settings::playback::PlaybackSettings,
+};
+
+fn peak_of<T>(channels: &[Vec<T>]) -> f64
+where
+    T: Copy + SampleInto<f64>,
+{
+    channels
+        .iter()
+        .flatten()
+        .map(|v| v.sample_into().abs())
+        .fold(0.0, f64::max)
+}
+
+/// Returns the highest absolute amplitude in the samples, from 0.0 to 1.0.
+pub fn peak(samples: &Samples) -> f64 {
+    match samples {
+        Samples::Float64(v) => v.iter().flatten().map(|v| v.abs()).fold(0.0, f64::max),
+        Samples::Float32(v) => peak_of(v),
+        Samples::Signed32(v) => peak_of(v),
+        Samples::Unsigned32(v) => peak_of(v),
+        Samples::Signed24(v) => peak_of(v),
+        Samples::Unsigned24(v) => peak_of(v),
+        Samples::Signed16(v) => peak_of(v),
+        Samples::Unsigned16(v) => peak_of(v),
+        Samples::Signed8(v) => peak_of(v),
+        Samples::Unsigned8(v) => peak_of(v),
+        // DSD can't be measured this way, so it's never treated as silence
+        Samples::Dsd(_) => 1.0,
+    }
+}
+
+/// The length of a frame in seconds.
+fn frame_secs(frame: &PlaybackFrame) -> f64 {
+    let len = match &frame.samples {
+        Samples::Float64(v) => v.first().map(Vec::len),
+        Samples::Float32(v) => v.first().map(Vec::len),
+        Samples::Signed32(v) => v.first().map(Vec::len),
+        Samples::Unsigned32(v) => v.first().map(Vec::len),
+        Samples::Signed24(v) => v.first().map(Vec::len),
+        Samples::Unsigned24(v) => v.first().map(Vec::len),
+        Samples::Signed16(v) => v.first().map(Vec::len),
+        Samples::Unsigned16(v) => v.first().map(Vec::len),
+        Samples::Signed8(v) => v.first().map(Vec::len),
+        Samples::Unsigned8(v) => v.first().map(Vec::len),
+        Samples::Dsd(v) => v.first().map(Vec::len),
+    };
+
+    len.unwrap_or_default() as f64 / frame.rate.max(1) as f64
+}
+
+/// Skips silence at the start and end of a track.
+///
+/// Decoded frames are pushed in, and the frames that should be played are popped out. A frame is
+/// only silent if every sample in it is below the threshold, and at most `max_trim` seconds are
+/// removed from either end, so quiet intros and outros are left alone.
+///
+/// Silence at the end of a track can't be recognized until the track ends, so silent frames are
+/// held back until either a louder frame arrives (and they are played after all) or the track
+/// ends (and they are dropped). Only up to `max_trim` seconds are ever held.
+pub struct SilenceTrimmer {
+    threshold: f64,
+    max_trim: f64,
+    /// Whether no sound has been heard in the track yet.
+    at_start: bool,
+    leading_trimmed: f64,
+    held: VecDeque<PlaybackFrame>,
+    held_secs: f64,
+    ready: VecDeque<PlaybackFrame>,
+}
+
+impl SilenceTrimmer {
+    pub fn new(settings: &PlaybackSettings) -> Self {
+        SilenceTrimmer {
+            threshold: 10_f64.powf(settings.silence_threshold_db / 20.0),
+            max_trim: settings.silence_max_trim_ms as f64 / 1000.0,
+            at_start: true,
+            leading_trimmed: 0.0,
+            held: VecDeque::new(),
+            held_secs: 0.0,
+            ready: VecDeque::new(),
+        }
+    }
+
+    /// Adds a decoded frame.
+    pub fn push(&mut self, frame: PlaybackFrame) {
+        let secs = frame_secs(&frame);
+        let silent = peak(&frame.samples) < self.threshold;
+
+        if self.at_start {
+            if silent && self.leading_trimmed + secs <= self.max_trim {
+                self.leading_trimmed += secs;
+                return;
+            }
+
+            if self.leading_trimmed > 0.0 {
+                debug!("Skipped {:.2}s of leading silence", self.leading_trimmed);
+            }
+            self.at_start = false;
+        }
+
+        if !silent {
+            self.ready.extend(self.held.drain(..));
+            self.held_secs = 0.0;
+            self.ready.push_back(frame);
+            return;
+        }
+
+        self.held.push_back(frame);
+        self.held_secs += secs;
+
+        // this much silence is too long to be trimmed, so the oldest of it is played
+        while self.held_secs > self.max_trim {
+            let Some(frame) = self.held.pop_front() else {
+                break;
+            };
+            self.held_secs -= frame_secs(&frame);
+            self.ready.push_back(frame);
+        }
+    }
+
+    /// Takes the next frame to be played, if there is one.
+    pub fn pop(&mut self) -> Option<PlaybackFrame> {
+        self.ready.pop_front()
+    }
+
+    /// Called when the track has ended. Any silence still being held is dropped.
+    pub fn finish(&mut self) {
+        if !self.held.is_empty() {
+            debug!("Skipped {:.2}s of trailing silence", self.held_secs);
+        }
+
+        self.held.clear();
+        self.held_secs = 0.0;
+    }
+
+    /// How much silence, in seconds, is being held back at the end of the track.
+    pub fn trailing_silence(&self) -> f64 {
+        self.held_secs
+    }
+
+    /// Called instead of `finish` when the track has ended and the next track follows on from it
+    /// without a gap. Silence that was being held back is played after all, and the start of the
+    /// next track isn't trimmed.
+    pub fn join_next_track(&mut self) {
+        self.ready.extend(self.held.drain(..));
+        self.held_secs = 0.0;
+        self.at_start = false;
+    }
+
+    /// Called after seeking. Frames from before the seek are dropped, and the start of the track
+    /// is no longer trimmed.
+    pub fn seeked(&mut self) {
+        self.at_start = false;
+        self.held.clear();
+        self.held_secs = 0.0;
+        self.ready.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SILENT: f64 = 0.0;
+    const QUIET: f64 = 0.01;
+    const LOUD: f64 = 0.5;
+
+    /// A tenth of a second of mono audio at a constant level.
+    fn frame(level: f64) -> PlaybackFrame {
+        PlaybackFrame {
+            samples: Samples::Float64(vec![vec![level; 4410]]),
+            rate: 44100,
+        }
+    }
+
+    /// Trims a whole track, returning the level of each frame that would be played.
+    fn trim(settings: &PlaybackSettings, levels: &[f64]) -> Vec<f64> {
+        let mut trimmer = SilenceTrimmer::new(settings);
+        let mut played = Vec::new();
+
+        for level in levels {
+            trimmer.push(frame(*level));
+            while let Some(frame) = trimmer.pop() {
+                played.push(peak(&frame.samples));
+            }
+        }
+
+        trimmer.finish();
+        while let Some(frame) = trimmer.pop() {
+            played.push(peak(&frame.samples));
+        }
+
+        played
+    }
+
+    #[test]
+    fn leading_silence_is_trimmed() {
+        let settings = PlaybackSettings::default();
+
+        let played = trim(&settings, &[SILENT, SILENT, SILENT, LOUD, LOUD]);
+
+        assert_eq!(played, vec![LOUD, LOUD]);
+    }
+
+    #[test]
+    fn audio_above_the_threshold_is_not_trimmed() {
+        let settings = PlaybackSettings::default();
+
+        let played = trim(&settings, &[QUIET, QUIET, LOUD, QUIET]);
+
+        assert_eq!(played, vec![QUIET, QUIET, LOUD, QUIET]);
+    }
+
+    #[test]
+    fn trailing_silence_is_trimmed() {
+        let settings = PlaybackSettings::default();
+
+        let played = trim(&settings, &[LOUD, LOUD, SILENT, SILENT, SILENT]);
+
+        assert_eq!(played, vec![LOUD, LOUD]);
+    }
+
+    #[test]
+    fn no_more_than_the_max_trim_is_skipped() {
+        let settings = PlaybackSettings {
+            silence_max_trim_ms: 250,
+            ..Default::default()
+        };
+
+        let played = trim(
+            &settings,
+            &[SILENT, SILENT, SILENT, SILENT, LOUD, SILENT, SILENT, SILENT],
+        );
+
+        assert_eq!(played, vec![SILENT, SILENT, LOUD, SILENT]);
+    }
+
+    #[test]
+    fn all_silent_tracks_play_nothing() {
+        let settings = PlaybackSettings::default();
+
+        let played = trim(&settings, &[SILENT; 10]);
+
+        assert!(played.is_empty());
+    }
+
+    #[test]
+    fn quiet_passages_mid_track_are_kept() {
+        let settings = PlaybackSettings::default();
+
+        let played = trim(&settings, &[LOUD, SILENT, SILENT, SILENT, LOUD]);
+
+        assert_eq!(played, vec![LOUD, SILENT, SILENT, SILENT, LOUD]);
+    }
+}
@@ -1,17 +1,24 @@
 use std::{
     env::consts::OS,
-    mem::swap,
+    ffi::OsStr,
+    mem::{swap, take},
     path::PathBuf,
-    sync::{Arc, RwLock},
+    sync::{Arc, Mutex, RwLock},
     thread::sleep,
+    time::Duration,
 };
 
+use ahash::AHashSet;
 use async_channel::{Receiver, Sender};
 use rand::{rng, seq::SliceRandom};
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, trace, warn};
 
+use crate::shared::logging::AUDIO_FRAMES_TARGET;
 use crate::{player::devices::builtin::cpal::CpalProvider, player::playback::events::RepeatState};
-use crate::{player::devices::builtin::dummy::DummyDeviceProvider, shared::settings::playback::PlaybackSettings};
+use crate::{
+    player::devices::builtin::dummy::DummyDeviceProvider,
+    shared::settings::playback::{DuplicateQueueItems, EndOfQueueAction, PlaybackSettings},
+};
 // #[cfg(target_os = "linux")]
 // use crate::player::devices::builtin::pulse::PulseProvider;
 #[cfg(target_os = "windows")]
@@ -20,18 +27,30 @@ use crate::player::devices::builtin::win_audiograph::AudioGraphProvider;
 use crate::player::{
     devices::{
         format::{ChannelSpec, FormatInfo},
-        resample::Resampler,
+        resample::{FrameConverter, convert_samples},
         traits::{Device, DeviceProvider, OutputStream},
     },
+    library::types::TrackSegment,
     media::{
-        builtin::symphonia::SymphoniaProvider, errors::PlaybackReadError, traits::MediaProvider,
+        builtin::symphonia::SymphoniaProvider,
+        errors::PlaybackReadError,
+        playback::{PlaybackFrame, Samples},
+        stream::{IcyInfo, IcyStream, StreamStatus, is_stream_url},
+        traits::MediaProvider,
     },
 };
 
 use super::{
-    events::{PlaybackCommand, PlaybackEvent},
+    balance::apply_balance,
+    crossfeed::Crossfeed,
+    device_watcher::{DeviceList, watch_devices},
+    events::{OutputDevice, PlaybackCommand, PlaybackDiagnostics, PlaybackEvent, PlaybackFormat},
     interface::PlaybackInterface,
+    limiter::limit,
     queue::QueueItemData,
+    segment::{Offsets, SegmentEnd},
+    silence::{SilenceTrimmer, peak},
+    volume::scale_volume,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -41,6 +60,118 @@ pub enum PlaybackState {
     Paused,
 }
 
+/// The queue as it was before it was last cleared, kept so that clearing can be undone.
+struct ClearedQueue {
+    queue: Vec<QueueItemData>,
+    original_queue: Vec<QueueItemData>,
+    shuffle: bool,
+    queue_next: usize,
+    /// The position in the current track, in seconds.
+    position: u64,
+    state: PlaybackState,
+}
+
+/// Sends an event to the playback interface. The events channel is unbounded, so this never blocks,
+/// and events are received in the order they were sent.
+fn send_event(events_tx: &Sender<PlaybackEvent>, event: PlaybackEvent) {
+    if let Err(err) = events_tx.try_send(event) {
+        error!("Unable to send playback event: {}", err);
+    }
+}
+
+/// Reads the next frame to play from the provider. If silence is being trimmed, frames that are
+/// trimmed are never returned, and `Ok(None)` is returned when there's no frame to play yet.
+fn read_frame(
+    provider: &mut dyn MediaProvider,
+    trimmer: Option<&mut SilenceTrimmer>,
+    segment_end: Option<&mut SegmentEnd>,
+) -> Result<Option<PlaybackFrame>, PlaybackReadError> {
+    let Some(trimmer) = trimmer else {
+        return read_samples(provider, segment_end).map(Some);
+    };
+
+    if let Some(frame) = trimmer.pop() {
+        return Ok(Some(frame));
+    }
+
+    match read_samples(provider, segment_end) {
+        Ok(frame) => {
+            trimmer.push(frame);
+            Ok(trimmer.pop())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Reads the next frame from the provider. When playing a segment of a file, reaching the end of
+/// the segment is treated as reaching the end of the file.
+fn read_samples(
+    provider: &mut dyn MediaProvider,
+    segment_end: Option<&mut SegmentEnd>,
+) -> Result<PlaybackFrame, PlaybackReadError> {
+    let Some(segment_end) = segment_end else {
+        return provider.read_samples();
+    };
+
+    if segment_end.finished() {
+        return Err(PlaybackReadError::Eof);
+    }
+
+    let mut frame = provider.read_samples()?;
+    segment_end.limit(&mut frame);
+
+    Ok(frame)
+}
+
+/// Creates the device provider with the given name, which has to be supported on this platform.
+fn create_device_provider(name: &str) -> Box<dyn DeviceProvider> {
+    match name {
+        #[cfg(target_os = "windows")]
+        "win_audiograph" => Box::new(AudioGraphProvider::default()),
+        "dummy" => Box::new(DummyDeviceProvider::new()),
+        _ => Box::new(CpalProvider::default()),
+    }
+}
+
+/// Applies crossfeed and balance to a decoded frame, then limits it if its level may have been
+/// raised. The frame is processed as 64-bit floats before it's converted to the device's format,
+/// so that peaks pushed over full scale are limited instead of clipping in the conversion.
+fn process_frame(
+    frame: PlaybackFrame,
+    crossfeed: Option<&mut Crossfeed>,
+    balance: f64,
+    limit_gain: bool,
+) -> PlaybackFrame {
+    let is_dsd = matches!(frame.samples, Samples::Dsd(_));
+    if is_dsd || (crossfeed.is_none() && balance == 0.0 && !limit_gain) {
+        return frame;
+    }
+
+    let mut frame = PlaybackFrame {
+        samples: Samples::Float64(convert_samples(frame.samples)),
+        rate: frame.rate,
+    };
+
+    if let Some(crossfeed) = crossfeed {
+        crossfeed.apply(&mut frame);
+    }
+
+    apply_balance(&mut frame.samples, balance);
+
+    if limit_gain {
+        limit(&mut frame.samples);
+    }
+
+    frame
+}
+
+/// How long the thread waits for a command while nothing is playing.
+const IDLE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The most silence, in seconds, that a track can end with and still be played without a gap into
+/// the next track on its album.
+const MAX_GAPLESS_TRAILING_SILENCE: f64 = 0.25;
+
 pub struct PlaybackThread {
     /// The playback settings. Recieved on thread startup.
     playback_settings: PlaybackSettings,
@@ -63,6 +194,17 @@ pub struct PlaybackThread {
     /// The current device.
     device: Option<Box<dyn Device>>,
 
+    /// The UID of the device picked by the user, if any. It's used whenever it's connected, and
+    /// the default device is used otherwise.
+    requested_device: Option<String>,
+
+    /// The available devices, as last sent to the UI.
+    devices: DeviceList,
+
+    /// Receives the available devices from the device watcher, which lists them every couple of
+    /// seconds. Set once the device provider has been picked.
+    device_lists: Option<Receiver<DeviceList>>,
+
     /// The current stream.
     ///
     /// Note: This stream may become invalid (depending on the device provider). It is the
@@ -73,15 +215,41 @@ pub struct PlaybackThread {
     /// The current playback state (playing, paused, stopped).
     state: PlaybackState,
 
-    /// The current resampler, if one exists. This is used to convert the audio format of the media
-    /// to the format supported by the device. Note that the resampler should always be called
-    /// before writing to the device, even if the device uses the same format as the media, as the
-    /// resampler will not perform any operations if the formats are the same.
-    resampler: Option<Resampler>,
+    /// The current frame converter, if one exists. This is used to convert the audio format of the
+    /// media to the format supported by the device. Note that the converter should always be
+    /// called before writing to the device, even if the device uses the same sample rate as the
+    /// media, as the bit depth may still differ. When the rates match no resampler is created.
+    resampler: Option<FrameConverter>,
+
+    /// The sample rate and channel count of the frames the frame converter was created for.
+    converter_input: Option<(u32, usize)>,
 
     /// The current format of the media.
     format: Option<FormatInfo>,
 
+    /// The sample rate of the track being played, once its first samples have been decoded. In
+    /// exclusive mode the device is opened at this rate, so that nothing has to be resampled.
+    source_rate: Option<u32>,
+
+    /// Trims silence from the start and end of the current track, if silence skipping is enabled.
+    silence_trimmer: Option<SilenceTrimmer>,
+
+    /// The peak level of the last decoded frame, used to tell whether a track ends in silence.
+    /// Only measured if automatic gapless playback is enabled.
+    last_peak: f64,
+
+    /// Whether the track that's about to be opened follows on from the previous one without a
+    /// gap. If so, the frame converter and silence trimmer are kept for the new track.
+    gapless_next: bool,
+
+    /// Where the current track starts and ends in its file. Only tracks stored inside a larger
+    /// file (such as the tracks of a cue sheet) start anywhere but the beginning. The position and
+    /// duration sent to the UI are relative to the track.
+    offsets: Offsets,
+
+    /// Ends the current track before the end of its file, if it's stored inside a larger file.
+    segment_end: Option<SegmentEnd>,
+
     /// The current queue. Do not hold an indefinite lock on this queue - it is read by the
     /// UI thread.
     queue: Arc<RwLock<Vec<QueueItemData>>>,
@@ -92,6 +260,9 @@ pub struct PlaybackThread {
     /// Whether or not the queue is shuffled.
     shuffle: bool,
 
+    /// The queue from before it was last cleared, if it hasn't been restored or replaced since.
+    cleared_queue: Option<ClearedQueue>,
+
     /// The index after the current item in the queue. This can be out of bounds if the current
     /// track is the last track in the queue.
     queue_next: usize,
@@ -108,10 +279,33 @@ pub struct PlaybackThread {
 
     /// Count of consecutive failures to open files. Used to prevent infinite recursion.
     consecutive_failures: usize,
-}
 
-pub const LN_50: f64 = 3.91202300543_f64;
-pub const LINEAR_SCALING_COEFFICIENT: f64 = 0.295751527165_f64;
+    /// The ICY information of the current network stream, if a stream (rather than a file) is
+    /// being played.
+    radio_info: Option<Arc<Mutex<IcyInfo>>>,
+
+    /// The state of the current network stream's background reader, used to keep handling
+    /// commands while the stream reconnects.
+    radio_status: Option<Arc<StreamStatus>>,
+
+    /// The current volume, as set by the user (before scaling). Zero while muted.
+    volume: f64,
+
+    /// The exact volume from before the thread was muted, if it is currently muted. Restored when
+    /// the thread is unmuted.
+    muted_volume: Option<f64>,
+
+    /// The stereo balance, from -1.0 (left only) to 1.0 (right only).
+    balance: f64,
+
+    /// The crossfeed filter, if crossfeed is enabled. Kept for the life of the thread, so the
+    /// filter state carries over between frames and tracks.
+    crossfeed: Option<Crossfeed>,
+
+    /// Whether or not the main loop should keep running. Set to false when a shutdown is
+    /// requested.
+    running: bool,
+}
 
 impl PlaybackThread {
     /// Starts the playback thread and returns the created interface.
@@ -126,30 +320,7 @@ impl PlaybackThread {
         std::thread::Builder::new()
             .name("playback".to_string())
             .spawn(move || {
-                let mut thread = PlaybackThread {
-                    commands_rx,
-                    events_tx,
-                    media_provider: None,
-                    device_provider: None,
-                    device: None,
-                    stream: None,
-                    state: PlaybackState::Stopped,
-                    resampler: None,
-                    format: None,
-                    queue,
-                    original_queue: Vec::new(),
-                    shuffle: false,
-                    queue_next: 0,
-                    last_timestamp: u64::MAX,
-                    pending_reset: false,
-                    repeat: if settings.always_repeat {
-                        RepeatState::Repeating
-                    } else {
-                        RepeatState::NotRepeating
-                    },
-                    playback_settings: settings,
-                    consecutive_failures: 0,
-                };
+                let mut thread = PlaybackThread::new(queue, settings, commands_rx, events_tx);
 
                 thread.run();
             })
@@ -158,10 +329,62 @@ impl PlaybackThread {
         T::new(commands_tx, events_rx)
     }
 
+    /// Creates the thread's state. The device and media providers are picked once it's run.
+    fn new(
+        queue: Arc<RwLock<Vec<QueueItemData>>>,
+        settings: PlaybackSettings,
+        commands_rx: Receiver<PlaybackCommand>,
+        events_tx: Sender<PlaybackEvent>,
+    ) -> Self {
+        PlaybackThread {
+            commands_rx,
+            events_tx,
+            media_provider: None,
+            device_provider: None,
+            device: None,
+            requested_device: None,
+            devices: DeviceList::default(),
+            device_lists: None,
+            stream: None,
+            state: PlaybackState::Stopped,
+            resampler: None,
+            converter_input: None,
+            format: None,
+            source_rate: None,
+            silence_trimmer: None,
+            last_peak: 0.0,
+            gapless_next: false,
+            offsets: Offsets::default(),
+            segment_end: None,
+            queue,
+            original_queue: Vec::new(),
+            shuffle: false,
+            cleared_queue: None,
+            queue_next: 0,
+            last_timestamp: u64::MAX,
+            pending_reset: false,
+            repeat: if settings.always_repeat {
+                RepeatState::Repeating
+            } else {
+                RepeatState::NotRepeating
+            },
+            balance: settings.balance.clamp(-1.0, 1.0),
+            crossfeed: settings
+                .crossfeed
+                .then(|| Crossfeed::new(settings.crossfeed_intensity)),
+            playback_settings: settings,
+            consecutive_failures: 0,
+            radio_info: None,
+            radio_status: None,
+            volume: 1.0,
+            muted_volume: None,
+            running: true,
+        }
+    }
+
     /// Creates the initial stream and starts the main loop.
     pub fn run(&mut self) {
-        // for now just throw in the default Providers and pick the default Device
-        // TODO: Add a way to select the output device
+        // for now just throw in the default Providers
         // #[cfg(target_os = "linux")]
         // {
         //     self.device_provider = Some(Box::new(PulseProvider::default()));
@@ -188,131 +411,187 @@ impl PlaybackThread {
         let requested_device_provider = std::env::var("DEVICE_PROVIDER")
             .unwrap_or_else(|_| default_device_provider.to_string());
 
-        match requested_device_provider.as_str() {
+        let device_provider = match requested_device_provider.as_str() {
             "pulse" => {
                 // #[cfg(target_os = "linux")]
                 // {
-                //     self.device_provider = Some(Box::new(PulseProvider::default()));
+                //     "pulse"
                 // }
                 // #[cfg(not(target_os = "linux"))]
                 // {
                 //     warn!("pulse is not supported on this platform");
                 //     warn!("Falling back to CPAL");
-                //     self.device_provider = Some(Box::new(CpalProvider::default()));
+                //     "cpal"
                 // }
                 warn!("pulseaudio support was removed");
                 warn!("Falling back to CPAL");
-                self.device_provider = Some(Box::new(CpalProvider::default()));
+                "cpal"
             }
             "win_audiograph" => {
                 #[cfg(target_os = "windows")]
                 {
-                    self.device_provider = Some(Box::new(AudioGraphProvider::default()));
+                    "win_audiograph"
                 }
                 #[cfg(not(target_os = "windows"))]
                 {
                     warn!("win_audiograph is not supported on this platform");
                     warn!("Falling back to CPAL");
-                    self.device_provider = Some(Box::new(CpalProvider::default()));
+                    "cpal"
                 }
             }
-            "cpal" => {
-                self.device_provider = Some(Box::new(CpalProvider::default()));
-            }
-            "dummy" => {
-                self.device_provider = Some(Box::new(DummyDeviceProvider::new()));
-            }
+            "cpal" => "cpal",
+            "dummy" => "dummy",
             _ => {
                 warn!("Unknown device provider: {}", requested_device_provider);
                 warn!("Falling back to CPAL");
-                self.device_provider = Some(Box::new(CpalProvider::default()));
+                "cpal"
             }
-        }
+        };
 
+        self.device_provider = Some(create_device_provider(device_provider));
+        self.device_lists = Some(watch_devices(move || {
+            create_device_provider(device_provider)
+        }));
         self.media_provider = Some(Box::new(SymphoniaProvider::default()));
 
         // TODO: allow the user to pick a format on supported platforms
+        if let Some(list) = self
+            .device_provider
+            .as_deref_mut()
+            .and_then(DeviceList::read)
+        {
+            self.update_devices(list);
+        }
         self.recreate_stream(true, None);
 
-        loop {
+        while self.running {
             self.main_loop();
         }
+
+        info!("Playback thread stopped");
     }
 
     /// Start command intake and audio playback loop.
     pub fn main_loop(&mut self) {
         self.command_intake();
+        self.check_device();
 
-        if self.state == PlaybackState::Playing {
+        // without a stream there's nothing to play to, so wait for a device to become available,
+        // and while a network stream reconnects, keep handling commands until it has data again
+        if self.state == PlaybackState::Playing && self.stream.is_some() && !self.stream_stalled() {
             self.play_audio();
         } else {
-            sleep(std::time::Duration::from_millis(10));
+            self.wait_for_command();
         }
 
         self.broadcast_events();
     }
 
+    /// Returns true if the current network stream is reconnecting and has nothing left to decode.
+    /// Decoding would block until the reconnection finishes, so commands are handled meanwhile.
+    fn stream_stalled(&self) -> bool {
+        self.radio_status
+            .as_ref()
+            .is_some_and(|status| status.is_stalled())
+    }
+
     /// Check for updated metadata and album art, and broadcast it to the UI.
     pub fn broadcast_events(&mut self) {
+        let radio_updated = self
+            .radio_info
+            .as_ref()
+            .is_some_and(|info| take(&mut info.lock().expect("icy info poisoned").updated));
+
         let Some(provider) = &mut self.media_provider else {
             return;
         };
-        if !provider.metadata_updated() {
+        if !provider.metadata_updated() && !radio_updated {
             return;
         }
         // TODO: proper error handling
-        let metadata = Box::new(
+        let mut metadata = Box::new(
             provider
                 .read_metadata()
                 .expect("failed to get metadata")
                 .clone(),
         );
-        let events_tx = self.events_tx.clone();
-        smol::spawn(async move {
-            events_tx
-                .send(PlaybackEvent::MetadataUpdate(metadata))
-                .await
-                .expect("unable to send event");
-        })
-        .detach();
+
+        if let Some(info) = &self.radio_info {
+            info.lock()
+                .expect("icy info poisoned")
+                .apply_to(&mut metadata);
+        }
+        send_event(&self.events_tx, PlaybackEvent::MetadataUpdate(metadata));
 
         let image = provider.read_image().expect("failed to decode image");
-        let events_tx = self.events_tx.clone();
-        smol::spawn(async move {
-            events_tx
-                .send(PlaybackEvent::AlbumArtUpdate(image))
-                .await
-                .expect("unable to send event");
-        })
-        .detach();
+        send_event(&self.events_tx, PlaybackEvent::AlbumArtUpdate(image));
     }
 
     /// Read incoming commands from the command channel, and process them.
     pub fn command_intake(&mut self) {
         while let Ok(command) = self.commands_rx.try_recv() {
-            info!("Received command: {:?}", command);
-            match command {
-                PlaybackCommand::Play => self.play(),
-                PlaybackCommand::Pause => self.pause(),
-                PlaybackCommand::TogglePlayPause => self.toggle_play_pause(),
-                PlaybackCommand::Open(path) => self.open(&path),
-                PlaybackCommand::Queue(v) => self.queue(v),
-                PlaybackCommand::QueueList(v) => self.queue_list(v),
-                PlaybackCommand::Next => self.next(true),
-                PlaybackCommand::Previous => self.previous(),
-                PlaybackCommand::ClearQueue => self.clear_queue(),
-                PlaybackCommand::Jump(v) => self.jump(v),
-                PlaybackCommand::JumpUnshuffled(v) => self.jump_unshuffled(v),
-                PlaybackCommand::Seek(v) => self.seek(v),
-                PlaybackCommand::SetVolume(v) => self.set_volume(v),
-                PlaybackCommand::ReplaceQueue(v) => self.replace_queue(v),
-                PlaybackCommand::Stop => self.stop(),
-                PlaybackCommand::ToggleShuffle => self.toggle_shuffle(),
-                PlaybackCommand::SetRepeat(v) => self.set_repeat(v),
+            self.handle_command(command);
+
+            // any remaining commands are irrelevant once the stream is closed
+            if !self.running {
+                return;
             }
         }
     }
 
+    /// Blocks until a command is received or `IDLE_TIMEOUT` passes, and processes the command.
+    /// Used instead of polling while nothing is playing.
+    fn wait_for_command(&mut self) {
+        let command = smol::block_on(smol::future::or(
+            async { self.commands_rx.recv().await.ok() },
+            async {
+                smol::Timer::after(IDLE_TIMEOUT).await;
+                None
+            },
+        ));
+
+        match command {
+            Some(command) => self.handle_command(command),
+            // the interface is gone, so there's nothing left to wait for
+            None if self.commands_rx.is_closed() => sleep(IDLE_TIMEOUT),
+            None => (),
+        }
+    }
+
+    fn handle_command(&mut self, command: PlaybackCommand) {
+        info!("Received command: {:?}", command);
+        match command {
+            PlaybackCommand::Play => self.play(),
+            PlaybackCommand::Pause => self.pause(),
+            PlaybackCommand::TogglePlayPause => self.toggle_play_pause(),
+            PlaybackCommand::Open(item) => self.open_item(&item),
+            PlaybackCommand::Queue(v) => self.queue(v),
+            PlaybackCommand::QueueList(v) => self.queue_list(v),
+            PlaybackCommand::PlayNext(v) => self.play_next(v),
+            PlaybackCommand::QueueAndPlay(v) => self.queue_and_play(v),
+            PlaybackCommand::Next => self.next(true),
+            PlaybackCommand::Previous => self.previous(),
+            PlaybackCommand::Restart => self.restart(),
+            PlaybackCommand::ClearQueue => self.clear_queue(),
+            PlaybackCommand::UndoClearQueue => self.undo_clear_queue(),
+            PlaybackCommand::Jump(v) => self.jump(v),
+            PlaybackCommand::JumpUnshuffled(v) => self.jump_unshuffled(v),
+            PlaybackCommand::Seek(v) => self.seek(v),
+            PlaybackCommand::SetVolume(v) => self.set_volume(v),
+            PlaybackCommand::ToggleMute => self.toggle_mute(),
+            PlaybackCommand::SetBalance(v) => self.set_balance(v),
+            PlaybackCommand::SetDevice(v) => self.set_requested_device(v),
+            PlaybackCommand::UpdateSettings(v) => self.update_settings(v),
+            PlaybackCommand::ReplaceQueue(v) => self.replace_queue(v),
+            PlaybackCommand::Stop => self.stop(),
+            PlaybackCommand::ToggleShuffle => self.toggle_shuffle(),
+            PlaybackCommand::SetRepeat(v) => self.set_repeat(v),
+            PlaybackCommand::RequestState => self.send_state(),
+            PlaybackCommand::RequestDiagnostics => self.send_diagnostics(),
+            PlaybackCommand::Shutdown => self.shutdown(),
+        }
+    }
+
     /// Pause playback.
     pub fn pause(&mut self) {
         info!("pause() called, current state: {:?}", self.state);
@@ -323,22 +602,17 @@ impl PlaybackThread {
 
         if self.state == PlaybackState::Playing {
             info!("Pausing stream...");
-            if let Some(stream) = &mut self.stream {
-                // stream is being played right now which means it has to be valid
-                // this is fine
-                stream.pause().expect("unable to pause stream");
+            if let Some(Err(err)) = self.stream.as_mut().map(|stream| stream.pause()) {
+                // the device may have been removed, in which case there's nothing left to pause
+                warn!("Failed to pause stream: {:?}", err);
             }
 
             self.state = PlaybackState::Paused;
 
-            let events_tx = self.events_tx.clone();
-            smol::spawn(async move {
-                events_tx
-                    .send(PlaybackEvent::StateChanged(PlaybackState::Paused))
-                    .await
-                    .expect("unable to send event");
-            })
-            .detach();
+            send_event(
+                &self.events_tx,
+                PlaybackEvent::StateChanged(PlaybackState::Paused),
+            );
         }
     }
 
@@ -374,104 +648,104 @@ impl PlaybackThread {
                         err
                     );
                     self.recreate_stream(true, format.map(|v| v.channels));
-                    let final_result = self.stream.as_mut().unwrap().play();
+                    let final_result = self.stream.as_mut().map(|stream| stream.play());
 
-                    if final_result.is_err() {
+                    if !matches!(final_result, Some(Ok(()))) {
                         error!("Failed to start playback after recreation");
-                        error!("This likely indicates a problem with the audio device or driver");
-                        error!("(or an underlying issue in the used DeviceProvider)");
-                        error!("Please check your audio setup and try again.");
-                        panic!("Failed to submit frame after recreation");
+                        self.stream_failed();
+                        return;
                     }
                 }
             }
 
             self.state = PlaybackState::Playing;
 
-            let events_tx = self.events_tx.clone();
-            smol::spawn(async move {
-                events_tx
-                    .send(PlaybackEvent::StateChanged(PlaybackState::Playing))
-                    .await
-                    .expect("unable to send event");
-            })
-            .detach();
+            send_event(
+                &self.events_tx,
+                PlaybackEvent::StateChanged(PlaybackState::Playing),
+            );
+        }
+
+        if self.state != PlaybackState::Stopped {
+            return;
         }
 
         let queue = self.queue.read().expect("couldn't get the queue");
 
-        if self.state == PlaybackState::Stopped && !queue.is_empty() {
-            let path = queue[0].get_path().clone();
-            drop(queue);
-            self.open(&path);
-            let events_tx = self.events_tx.clone();
-            smol::spawn(async move {
-                events_tx
-                    .send(PlaybackEvent::QueuePositionChanged(0))
-                    .await
-                    .expect("unable to send event");
-            })
-            .detach();
-            self.queue_next = 1;
+        if queue.is_empty() {
+            // nothing to play, so let the UI say so instead of silently doing nothing
+            info!("Play requested, but the queue is empty");
+            send_event(&self.events_tx, PlaybackEvent::QueueEmpty);
+            return;
         }
 
-        // nothing to play, womp womp
+        let item = queue[0].clone();
+        drop(queue);
+        self.open_item(&item);
+        send_event(&self.events_tx, PlaybackEvent::QueuePositionChanged(0));
+        self.queue_next = 1;
+    }
+
+    /// Opens a track from the queue.
+    fn open_item(&mut self, item: &QueueItemData) {
+        self.open(item.get_path(), item.get_segment());
     }
 
-    /// Open a new track by given path.
-    fn open(&mut self, path: &PathBuf) {
+    /// Open a new track by given path. If the track is a segment of a larger file, the segment's
+    /// file is opened instead, and only the segment is played.
+    fn open(&mut self, path: &PathBuf, segment: Option<&TrackSegment>) {
         info!("Opening: {:?}", path);
 
+        // only the track directly after a gapless transition is joined to the previous one
+        let gapless = std::mem::take(&mut self.gapless_next);
+
         // Check if path is empty or invalid
         if path.as_os_str().is_empty() {
             error!("Cannot open file: path is empty");
-            self.consecutive_failures += 1;
-
-            // Prevent infinite recursion by limiting consecutive failures
-            if self.consecutive_failures > 10 {
-                error!("Too many consecutive failures ({}), stopping playback", self.consecutive_failures);
-                self.stop();
-                return;
-            }
-
-            self.next(false);
+            self.skip_failed_open();
             return;
         }
 
-        if !path.exists() {
-            error!("Cannot open file: path does not exist: {:?}", path);
-            self.consecutive_failures += 1;
-
-            // Prevent infinite recursion by limiting consecutive failures
-            if self.consecutive_failures > 10 {
-                error!("Too many consecutive failures ({}), stopping playback", self.consecutive_failures);
-                self.stop();
-                return;
-            }
+        let is_stream = is_stream_url(path);
+        let file = segment.map_or(path, |segment| &segment.file);
 
-            self.next(false);
+        if !is_stream && !file.exists() {
+            error!("Cannot open file: path does not exist: {:?}", file);
+            self.skip_failed_open();
             return;
         }
 
+        let icy_stream = if is_stream {
+            match IcyStream::connect(&path.to_string_lossy()) {
+                Ok(stream) => Some(stream),
+                Err(err) => {
+                    error!("Cannot open stream {:?}: {}", path, err);
+                    self.skip_failed_open();
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+
         let mut recreation_required = false;
 
         if self.state == PlaybackState::Paused {
-            let result = self.stream.as_mut().unwrap().reset();
+            let result = self.stream.as_mut().map(|stream| stream.reset());
 
-            if let Err(err) = result {
+            if let Some(Err(err)) = result {
                 warn!("Failed to reset device, forcing recreation: {:?}", err);
                 recreation_required = true;
             }
         }
 
-        let play_result = self.stream.as_mut().unwrap().play();
-
-        if play_result.is_err() {
-            warn!(
-                "Failed to start playback, forcing recreation: {:?}",
-                play_result.err().unwrap()
-            );
-            recreation_required = true;
+        match self.stream.as_mut().map(|stream| stream.play()) {
+            Some(Ok(())) => (),
+            Some(Err(err)) => {
+                warn!("Failed to start playback, forcing recreation: {:?}", err);
+                recreation_required = true;
+            }
+            None => recreation_required = true,
         }
 
         // TODO: handle multiple media providers
@@ -479,70 +753,88 @@ impl PlaybackThread {
             return;
         };
         // TODO: proper error handling
-        self.resampler = None;
-        let src = std::fs::File::open(path).expect("failed to open media");
-        provider.open(src, None).expect("unable to open file");
+        if !gapless {
+            self.resampler = None;
+            self.silence_trimmer = self
+                .playback_settings
+                .skip_silence
+                .then(|| SilenceTrimmer::new(&self.playback_settings));
+        }
+        if let Some(icy_stream) = icy_stream {
+            let info = icy_stream.info();
+            let status = icy_stream.status();
+            // always broadcast the station information once the stream is open
+            info.lock().expect("icy info poisoned").updated = true;
+
+            let ext = icy_stream.extension_hint().map(OsStr::new);
+            if let Err(err) = provider.open_stream(Box::new(icy_stream), ext) {
+                error!("Unable to decode stream {:?}: {}", path, err);
+                self.radio_info = None;
+                self.radio_status = None;
+                self.skip_failed_open();
+                return;
+            }
+
+            self.radio_info = Some(info);
+            self.radio_status = Some(status);
+        } else {
+            let src = std::fs::File::open(file).expect("failed to open media");
+            provider.open(src, None).expect("unable to open file");
+            self.radio_info = None;
+            self.radio_status = None;
+        }
         provider.start_playback().expect("unable to start playback");
 
+        self.offsets = Offsets::from_segment(segment);
+        self.segment_end = self
+            .offsets
+            .end
+            .map(|end| SegmentEnd::new(self.offsets.start, end));
+
+        if self.offsets.start > 0.0 {
+            if let Err(err) = provider.seek(self.offsets.start) {
+                error!("Unable to seek to the start of {:?}: {:?}", path, err);
+            }
+        }
+
         let channels = provider.channels().expect("unable to get channels");
         let stream_channels = self
             .stream
             .as_ref()
-            .unwrap()
-            .get_current_format()
-            .unwrap()
-            .channels
-            .clone();
+            .and_then(|stream| stream.get_current_format().ok())
+            .map(|format| format.channels.count());
 
-        if channels.count() != stream_channels.count() {
+        if let Some(stream_channels) = stream_channels.filter(|count| *count != channels.count()) {
             info!(
                 "Channel count mismatch, re-opening with the correct channel count (if supported)"
             );
             info!(
                 "Decoder wanted {}, stream had {}",
                 channels.count(),
-                stream_channels.count()
+                stream_channels
             );
             recreation_required = true;
         }
 
-        let events_tx = self.events_tx.clone();
-        let path = path.clone();
-        smol::spawn(async move {
-            events_tx
-                .send(PlaybackEvent::SongChanged(path))
-                .await
-                .expect("unable to send event");
-        })
-        .detach();
+        send_event(&self.events_tx, PlaybackEvent::SongChanged(path.clone()));
 
         if let Ok(duration) = provider.duration_secs() {
-            let events_tx = self.events_tx.clone();
-            smol::spawn(async move {
-                events_tx
-                    .send(PlaybackEvent::DurationChanged(duration))
-                    .await
-                    .expect("unable to send event");
-            })
-            .detach();
+            let duration = self.offsets.duration(duration);
+            send_event(&self.events_tx, PlaybackEvent::DurationChanged(duration));
         } else {
-            let events_tx = self.events_tx.clone();
-            smol::spawn(async move {
-                events_tx
-                    .send(PlaybackEvent::DurationChanged(0))
-                    .await
-                    .expect("unable to send event");
-            })
-            .detach();
+            send_event(&self.events_tx, PlaybackEvent::DurationChanged(0));
         }
 
         if recreation_required {
             self.recreate_stream(true, Some(channels));
-            let play_result = self.stream.as_mut().unwrap().play();
+            let play_result = self.stream.as_mut().map(|stream| stream.play());
 
-            if play_result.is_err() {
+            if !matches!(play_result, Some(Ok(()))) {
                 error!("Device was recreated and we still can't play");
-                panic!("couldn't play device")
+                self.consecutive_failures = 0;
+                self.update_ts();
+                self.stream_failed();
+                return;
             }
         }
 
@@ -553,14 +845,28 @@ impl PlaybackThread {
 
         self.update_ts();
 
-        let events_tx = self.events_tx.clone();
-        smol::spawn(async move {
-            events_tx
-                .send(PlaybackEvent::StateChanged(PlaybackState::Playing))
-                .await
-                .expect("unable to send event");
-        })
-        .detach();
+        send_event(
+            &self.events_tx,
+            PlaybackEvent::StateChanged(PlaybackState::Playing),
+        );
+    }
+
+    /// Records a failure to open a track and skips to the next one, stopping playback entirely if
+    /// too many tracks have failed in a row.
+    fn skip_failed_open(&mut self) {
+        self.consecutive_failures += 1;
+
+        // Prevent infinite recursion by limiting consecutive failures
+        if self.consecutive_failures > 10 {
+            error!(
+                "Too many consecutive failures ({}), stopping playback",
+                self.consecutive_failures
+            );
+            self.stop();
+            return;
+        }
+
+        self.next(false);
     }
 
     /// Skip to the next track in the queue.
@@ -569,26 +875,21 @@ impl PlaybackThread {
 
         if self.repeat == RepeatState::RepeatingOne {
             info!("Repeating current track");
-            let path = queue[self.queue_next - 1].get_path().clone();
+            let item = queue[self.queue_next - 1].clone();
             drop(queue);
-            self.open(&path);
+            self.open_item(&item);
             return;
         }
 
         if self.queue_next < queue.len() {
             info!("Opening next file in queue");
-            let path = queue[self.queue_next].get_path().clone();
+            let item = queue[self.queue_next].clone();
             drop(queue);
-            self.open(&path);
-            let events_tx = self.events_tx.clone();
-            let queue_next = self.queue_next;
-            smol::spawn(async move {
-                events_tx
-                    .send(PlaybackEvent::QueuePositionChanged(queue_next))
-                    .await
-                    .expect("unable to send event");
-            })
-            .detach();
+            self.open_item(&item);
+            send_event(
+                &self.events_tx,
+                PlaybackEvent::QueuePositionChanged(self.queue_next),
+            );
             self.queue_next += 1;
         } else if !user_initiated {
             if self.repeat == RepeatState::Repeating {
@@ -597,18 +898,18 @@ impl PlaybackThread {
                 if self.shuffle {
                     queue.shuffle(&mut rng());
 
-                    let events_tx = self.events_tx.clone();
-                    smol::spawn(async move {
-                        events_tx
-                            .send(PlaybackEvent::QueueUpdated)
-                            .await
-                            .expect("unable to send event");
-                    })
-                    .detach();
+                    send_event(&self.events_tx, PlaybackEvent::QueueUpdated);
                 }
 
                 drop(queue);
                 self.jump(0);
+            } else if self.playback_settings.end_of_queue_action == EndOfQueueAction::Pause
+                && self.radio_info.is_none()
+            {
+                info!("End of queue reached, pausing at the start of the last track");
+                drop(queue);
+                self.seek(0.0);
+                self.pause();
             } else {
                 info!("Playback queue is empty, stopping playback");
                 drop(queue);
@@ -617,11 +918,76 @@ impl PlaybackThread {
         }
     }
 
+    /// Called when the current track has been played to the end. Moves on to the next track.
+    fn track_finished(&mut self) {
+        let path = self
+            .queue
+            .read()
+            .expect("couldn't get the queue")
+            .get(self.queue_next.wrapping_sub(1))
+            .map(|item| item.get_path().clone());
+
+        if let Some(path) = path {
+            send_event(&self.events_tx, PlaybackEvent::TrackFinished(path));
+        }
+
+        let gapless = self.joins_next_track();
+
+        if let Some(trimmer) = &mut self.silence_trimmer {
+            if gapless {
+                trimmer.join_next_track();
+            } else {
+                trimmer.finish();
+            }
+        }
+
+        if gapless {
+            info!("Next track follows on from this one, playing without a gap");
+        }
+
+        self.gapless_next = gapless;
+        self.next(false);
+    }
+
+    /// Whether the track that just ended runs straight into the next track in the queue, so that
+    /// they should be played without a gap. See `PlaybackSettings::auto_gapless_albums`.
+    fn joins_next_track(&self) -> bool {
+        // in exclusive mode the device may be reopened for the next track anyway
+        if !self.playback_settings.auto_gapless_albums
+            || self.playback_settings.exclusive_mode
+            || self.repeat == RepeatState::RepeatingOne
+        {
+            return false;
+        }
+
+        let queue = self.queue.read().expect("couldn't get the queue");
+        let (Some(current), Some(next)) = (
+            queue.get(self.queue_next.wrapping_sub(1)),
+            queue.get(self.queue_next),
+        ) else {
+            return false;
+        };
+
+        if !next.follows_on_album(current) {
+            return false;
+        }
+
+        let threshold = 10_f64.powf(self.playback_settings.silence_threshold_db / 20.0);
+        let trailing_silence = self
+            .silence_trimmer
+            .as_ref()
+            .map(SilenceTrimmer::trailing_silence)
+            .unwrap_or_default();
+
+        self.last_peak >= threshold && trailing_silence <= MAX_GAPLESS_TRAILING_SILENCE
+    }
+
     /// Skip to the previous track in the queue.
     fn previous(&mut self) {
         if self.state == PlaybackState::Playing
-            && self.playback_settings.prev_track_jump_first
-            && self.last_timestamp > 5
+            && self
+                .playback_settings
+                .restarts_on_previous(self.last_timestamp)
         {
             self.seek(0_f64);
             return;
@@ -630,35 +996,25 @@ impl PlaybackThread {
         let queue = self.queue.read().expect("couldn't get the queue");
 
         if self.state == PlaybackState::Stopped && !queue.is_empty() {
-            let path = queue.last().unwrap().get_path().clone();
+            let item = queue.last().unwrap().clone();
             self.queue_next = queue.len();
             drop(queue);
-            self.open(&path);
-            let events_tx = self.events_tx.clone();
-            let new_position = self.queue_next - 1;
-            smol::spawn(async move {
-                events_tx
-                    .send(PlaybackEvent::QueuePositionChanged(new_position))
-                    .await
-                    .expect("unable to send event");
-            })
-            .detach();
+            self.open_item(&item);
+            send_event(
+                &self.events_tx,
+                PlaybackEvent::QueuePositionChanged(self.queue_next - 1),
+            );
         } else if self.queue_next > 1 {
             info!("Opening previous file in queue");
-            let path = queue[self.queue_next - 2].get_path().clone();
+            let item = queue[self.queue_next - 2].clone();
             drop(queue);
-            let events_tx = self.events_tx.clone();
-            let new_position = self.queue_next - 2;
-            smol::spawn(async move {
-                events_tx
-                    .send(PlaybackEvent::QueuePositionChanged(new_position))
-                    .await
-                    .expect("unable to send event");
-            })
-            .detach();
+            send_event(
+                &self.events_tx,
+                PlaybackEvent::QueuePositionChanged(self.queue_next - 2),
+            );
             self.queue_next -= 1;
             debug!("queue_next: {}", self.queue_next);
-            self.open(&path);
+            self.open_item(&item);
         }
     }
 
@@ -666,6 +1022,10 @@ impl PlaybackThread {
     fn queue(&mut self, item: QueueItemData) {
         info!("Adding file to queue: {}", item);
 
+        let Some(item) = self.handle_duplicates(vec![item]).pop() else {
+            return;
+        };
+
         let mut queue = self.queue.write().expect("couldn't get the queue");
 
         let pre_len = queue.len();
@@ -678,34 +1038,27 @@ impl PlaybackThread {
         }
 
         if self.state == PlaybackState::Stopped {
-            let path = item.get_path();
-            self.open(path);
+            self.open_item(&item);
             self.queue_next = pre_len + 1;
-            let events_tx = self.events_tx.clone();
-            smol::spawn(async move {
-                events_tx
-                    .send(PlaybackEvent::QueuePositionChanged(pre_len))
-                    .await
-                    .expect("unable to send event");
-            })
-            .detach();
+            send_event(
+                &self.events_tx,
+                PlaybackEvent::QueuePositionChanged(pre_len),
+            );
         }
 
-        let events_tx = self.events_tx.clone();
-        smol::spawn(async move {
-            events_tx
-                .send(PlaybackEvent::QueueUpdated)
-                .await
-                .expect("unable to send event");
-        })
-        .detach();
+        send_event(&self.events_tx, PlaybackEvent::QueueUpdated);
     }
 
     /// Add a list of QueueItemData to the queue. If nothing is playing, start playing the first
     /// track.
-    fn queue_list(&mut self, mut paths: Vec<QueueItemData>) {
+    fn queue_list(&mut self, paths: Vec<QueueItemData>) {
         info!("Adding files to queue: {:?}", paths);
 
+        let mut paths = self.handle_duplicates(paths);
+        if paths.is_empty() {
+            return;
+        }
+
         let mut queue = self.queue.write().expect("couldn't get the queue");
 
         let pre_len = queue.len();
@@ -726,150 +1079,380 @@ impl PlaybackThread {
 
         if self.state == PlaybackState::Stopped {
             if let Some(first) = first {
-                let path = first.get_path();
-                self.open(path);
+                self.open_item(&first);
                 self.queue_next = pre_len + 1;
-                let events_tx = self.events_tx.clone();
-                smol::spawn(async move {
-                    events_tx
-                        .send(PlaybackEvent::QueuePositionChanged(pre_len))
-                        .await
-                        .expect("unable to send event");
-                })
-                .detach();
+                send_event(
+                    &self.events_tx,
+                    PlaybackEvent::QueuePositionChanged(pre_len),
+                );
             }
         }
 
-        let events_tx = self.events_tx.clone();
-        smol::spawn(async move {
-            events_tx
-                .send(PlaybackEvent::QueueUpdated)
-                .await
-                .expect("unable to send event");
-        })
-        .detach();
+        send_event(&self.events_tx, PlaybackEvent::QueueUpdated);
     }
 
-    /// Emit a PositionChanged event if the timestamp has changed.
-    fn update_ts(&mut self) {
-        if let Some(provider) = &self.media_provider {
-            if let Ok(timestamp) = provider.position_secs() {
-                if timestamp == self.last_timestamp {
-                    return;
-                }
+    /// Add a list of QueueItemData to the end of the queue, and play the first of them in the
+    /// queue. If every item was already queued and is skipped, the first item is played where it
+    /// already is.
+    fn queue_and_play(&mut self, items: Vec<QueueItemData>) {
+        info!("Adding files to queue and playing them: {:?}", items);
 
-                let events_tx = self.events_tx.clone();
-                smol::spawn(async move {
-                    events_tx
-                        .send(PlaybackEvent::PositionChanged(timestamp))
-                        .await
-                        .expect("unable to send event");
-                })
-                .detach();
+        let first = items.first().map(|item| item.get_path().clone());
+        let mut items = self.handle_duplicates(items);
 
-                self.last_timestamp = timestamp;
+        if items.is_empty() {
+            let queue = self.queue.read().expect("couldn't get the queue");
+            let index = first.and_then(|path| queue.iter().position(|v| *v.get_path() == path));
+            drop(queue);
+
+            if let Some(index) = index {
+                self.jump(index);
             }
+            return;
         }
-    }
 
-    /// Seek to the specified timestamp (in seconds).
-    fn seek(&mut self, timestamp: f64) {
-        if let Some(provider) = &mut self.media_provider {
-            provider.seek(timestamp).expect("unable to seek");
-            self.pending_reset = true;
-            self.update_ts();
-        }
-    }
+        let mut queue = self.queue.write().expect("couldn't get the queue");
+        let start = queue.len();
 
-    /// Jump to the specified index in the queue.
-    fn jump(&mut self, index: usize) {
-        let queue = self.queue.read().expect("couldn't get the queue");
+        if self.shuffle {
+            let mut shuffled = items.clone();
+            shuffled.shuffle(&mut rng());
 
-        if index < queue.len() {
-            let path = queue[index].get_path().clone();
+            queue.append(&mut shuffled);
+            drop(queue);
+
+            self.original_queue.append(&mut items);
+        } else {
+            queue.append(&mut items);
             drop(queue);
-            self.open(&path);
-            self.queue_next = index + 1;
-            let events_tx = self.events_tx.clone();
-            smol::spawn(async move {
-                events_tx
-                    .send(PlaybackEvent::QueuePositionChanged(index))
-                    .await
-                    .expect("unable to send event");
-            })
-            .detach();
         }
+
+        send_event(&self.events_tx, PlaybackEvent::QueueUpdated);
+        self.jump(start);
     }
 
-    /// Jump to the specified index in the queue, disregarding shuffling. This means that the
-    /// original queue item at the specified index will be played, rather than the shuffled item.
-    fn jump_unshuffled(&mut self, index: usize) {
-        if !self.shuffle {
-            self.jump(index);
+    /// Insert a list of QueueItemData directly after the current track. If nothing is playing,
+    /// this is the same as queue_list.
+    fn play_next(&mut self, items: Vec<QueueItemData>) {
+        if self.state == PlaybackState::Stopped {
+            self.queue_list(items);
             return;
         }
 
-        let queue = self.queue.read().expect("couldn't get the queue");
-        let path = self.original_queue[index].get_path();
-        let pos = queue.iter().position(|a| a.get_path() == path);
-        drop(queue);
+        info!("Adding files to play next: {:?}", items);
 
-        if let Some(pos) = pos {
-            self.jump(pos);
+        let items = self.handle_duplicates(items);
+        if items.is_empty() {
+            return;
         }
-    }
-
-    /// Replace the current queue with the given paths.
-    fn replace_queue(&mut self, paths: Vec<QueueItemData>) {
-        info!("Replacing queue with: {:?}", paths);
 
         let mut queue = self.queue.write().expect("couldn't get the queue");
 
-        if self.shuffle {
-            let mut shuffled_paths = paths.clone();
-            shuffled_paths.shuffle(&mut rng());
+        let index = self.queue_next.min(queue.len());
+        let current = index
+            .checked_sub(1)
+            .map(|current| queue[current].get_path().clone());
+        queue.splice(index..index, items.iter().cloned());
 
-            *queue = shuffled_paths;
+        drop(queue);
 
-            drop(queue);
-            self.original_queue = paths;
-        } else {
-            *queue = paths;
-            drop(queue);
-        }
+        // keep the files after the current track in the unshuffled queue too, so they still play
+        // next if shuffle is turned off
+        if self.shuffle {
+            let original_index = current
+                .and_then(|path| {
+                    self.original_queue
+                        .iter()
+                        .position(|item| *item.get_path() == path)
+                })
+                .map(|current| current + 1)
+                .unwrap_or(self.original_queue.len());
 
-        self.queue_next = 0;
-        self.jump(0);
+            self.original_queue
+                .splice(original_index..original_index, items);
+        }
 
-        let events_tx = self.events_tx.clone();
-        smol::spawn(async move {
-            events_tx
-                .send(PlaybackEvent::QueueUpdated)
-                .await
-                .expect("unable to send event");
-        })
-        .detach();
+        send_event(&self.events_tx, PlaybackEvent::QueueUpdated);
     }
 
-    /// Clear the current queue.
+    /// Applies the duplicate queue item setting to items that are about to be queued, either
+    /// dropping the ones already in the queue, or removing them from the queue so they're only
+    /// queued at their new position.
+    fn handle_duplicates(&mut self, items: Vec<QueueItemData>) -> Vec<QueueItemData> {
+        match self.playback_settings.duplicate_queue_items {
+            DuplicateQueueItems::Allow => items,
+            DuplicateQueueItems::Skip => {
+                let queue = self.queue.read().expect("couldn't get the queue");
+                let mut queued: AHashSet<PathBuf> =
+                    queue.iter().map(|item| item.get_path().clone()).collect();
+                drop(queue);
+
+                let count = items.len();
+                let items: Vec<QueueItemData> = items
+                    .into_iter()
+                    .filter(|item| queued.insert(item.get_path().clone()))
+                    .collect();
+
+                if items.len() < count {
+                    info!("Skipped {} files already in the queue", count - items.len());
+                }
+
+                items
+            }
+            DuplicateQueueItems::Move => {
+                self.remove_queued(&items);
+                items
+            }
+        }
+    }
+
+    /// Removes every queued copy of the items, apart from the current track.
+    fn remove_queued(&mut self, items: &[QueueItemData]) {
+        let paths: AHashSet<&PathBuf> = items.iter().map(|item| item.get_path()).collect();
+        let current = self.queue_next.checked_sub(1);
+
+        let mut queue = self.queue.write().expect("couldn't get the queue");
+        let current_path =
+            current.and_then(|index| queue.get(index).map(|item| item.get_path().clone()));
+        let count = queue.len();
+
+        let mut index = 0;
+        let mut removed_before_current = 0;
+        queue.retain(|item| {
+            let keep = Some(index) == current || !paths.contains(item.get_path());
+            if !keep && current.is_some_and(|current| index < current) {
+                removed_before_current += 1;
+            }
+            index += 1;
+            keep
+        });
+
+        let removed = count - queue.len();
+        drop(queue);
+
+        if removed == 0 {
+            return;
+        }
+
+        info!("Moving {} files already in the queue", removed);
+
+        if self.shuffle {
+            let mut kept_current = false;
+            self.original_queue.retain(|item| {
+                // keep one copy of the current track, since it's still in the queue
+                if !kept_current && current_path.as_ref() == Some(item.get_path()) {
+                    kept_current = true;
+                    return true;
+                }
+                !paths.contains(item.get_path())
+            });
+        }
+
+        if removed_before_current > 0 {
+            self.queue_next -= removed_before_current;
+            send_event(
+                &self.events_tx,
+                PlaybackEvent::QueuePositionChanged(self.queue_next - 1),
+            );
+        }
+
+        send_event(&self.events_tx, PlaybackEvent::QueueUpdated);
+    }
+
+    /// Emit a PositionChanged event if the timestamp has changed.
+    fn update_ts(&mut self) {
+        if let Some(provider) = &self.media_provider {
+            if let Ok(timestamp) = provider.position_secs() {
+                let timestamp = self.offsets.to_track(timestamp);
+
+                if timestamp == self.last_timestamp {
+                    return;
+                }
+
+                send_event(&self.events_tx, PlaybackEvent::PositionChanged(timestamp));
+
+                self.last_timestamp = timestamp;
+            }
+        }
+    }
+
+    /// Plays the current track again from its start. Unlike `previous`, this never moves to
+    /// another track.
+    fn restart(&mut self) {
+        if self.state == PlaybackState::Stopped {
+            return;
+        }
+
+        self.seek(0.0);
+        self.play();
+    }
+
+    /// Seek to the specified timestamp (in seconds), relative to the start of the current track.
+    fn seek(&mut self, timestamp: f64) {
+        if self.radio_info.is_some() {
+            debug!("Ignoring seek, live streams cannot be seeked");
+            return;
+        }
+
+        let position = self.offsets.to_file(timestamp);
+
+        if let Some(provider) = &mut self.media_provider {
+            provider.seek(position).expect("unable to seek");
+            if let Some(trimmer) = &mut self.silence_trimmer {
+                trimmer.seeked();
+            }
+            if let Some(segment_end) = &mut self.segment_end {
+                segment_end.seeked(position);
+            }
+            self.pending_reset = true;
+            self.update_ts();
+        }
+    }
+
+    /// Jump to the specified index in the queue.
+    fn jump(&mut self, index: usize) {
+        let queue = self.queue.read().expect("couldn't get the queue");
+
+        if index < queue.len() {
+            let item = queue[index].clone();
+            drop(queue);
+            self.open_item(&item);
+            self.queue_next = index + 1;
+            send_event(&self.events_tx, PlaybackEvent::QueuePositionChanged(index));
+        }
+    }
+
+    /// Jump to the specified index in the queue, disregarding shuffling. This means that the
+    /// original queue item at the specified index will be played, rather than the shuffled item.
+    fn jump_unshuffled(&mut self, index: usize) {
+        if !self.shuffle {
+            self.jump(index);
+            return;
+        }
+
+        let queue = self.queue.read().expect("couldn't get the queue");
+        let path = self.original_queue[index].get_path();
+        let pos = queue.iter().position(|a| a.get_path() == path);
+        drop(queue);
+
+        if let Some(pos) = pos {
+            self.jump(pos);
+        }
+    }
+
+    /// Replace the current queue with the given paths.
+    fn replace_queue(&mut self, paths: Vec<QueueItemData>) {
+        info!("Replacing queue with: {:?}", paths);
+
+        let mut queue = self.queue.write().expect("couldn't get the queue");
+
+        if self.shuffle {
+            let mut shuffled_paths = paths.clone();
+            shuffled_paths.shuffle(&mut rng());
+
+            *queue = shuffled_paths;
+
+            drop(queue);
+            self.original_queue = paths;
+        } else {
+            *queue = paths;
+            drop(queue);
+        }
+
+        self.queue_next = 0;
+        self.jump(0);
+
+        send_event(&self.events_tx, PlaybackEvent::QueueUpdated);
+    }
+
+    /// Clear the current queue. The cleared queue is kept, so that clearing can be undone.
     fn clear_queue(&mut self) {
         let mut queue = self.queue.write().expect("couldn't get the queue");
-        *queue = Vec::new();
-        self.original_queue = Vec::new();
+        let cleared = take(&mut *queue);
+        drop(queue);
+        let original_queue = take(&mut self.original_queue);
+
+        if !cleared.is_empty() {
+            self.cleared_queue = Some(ClearedQueue {
+                queue: cleared,
+                original_queue,
+                shuffle: self.shuffle,
+                queue_next: self.queue_next,
+                position: if self.last_timestamp == u64::MAX {
+                    0
+                } else {
+                    self.last_timestamp
+                },
+                state: self.state,
+            });
+        }
+
         self.queue_next = 0;
+        self.stop();
 
-        let events_tx = self.events_tx.clone();
-        smol::spawn(async move {
-            events_tx
-                .send(PlaybackEvent::QueuePositionChanged(0))
-                .await
-                .expect("unable to send event");
-            events_tx
-                .send(PlaybackEvent::QueueUpdated)
-                .await
-                .expect("unable to send event");
-        })
-        .detach();
+        send_event(&self.events_tx, PlaybackEvent::QueuePositionChanged(0));
+        send_event(&self.events_tx, PlaybackEvent::QueueUpdated);
+    }
+
+    /// Puts back the queue from before it was last cleared, in the same order, and reopens the
+    /// track that was current at the same position, paused or playing as it was. Nothing is
+    /// restored if anything has been queued since.
+    fn undo_clear_queue(&mut self) {
+        let Some(cleared) = self.cleared_queue.take() else {
+            return;
+        };
+
+        let mut queue = self.queue.write().expect("couldn't get the queue");
+        if !queue.is_empty() {
+            return;
+        }
+
+        let mut queue_next = cleared.queue_next;
+
+        if cleared.shuffle == self.shuffle {
+            *queue = cleared.queue;
+            self.original_queue = cleared.original_queue;
+        } else if self.shuffle {
+            // shuffle was turned on while the queue was empty: shuffle what hadn't been played yet
+            self.original_queue = cleared.queue.clone();
+            *queue = cleared.queue;
+            let length = queue.len();
+            queue[queue_next..length].shuffle(&mut rng());
+        } else {
+            // shuffle was turned off: go back to the unshuffled order, keeping the current track
+            if queue_next > 0 {
+                let path = cleared.queue[queue_next - 1].get_path();
+                queue_next = cleared
+                    .original_queue
+                    .iter()
+                    .position(|item| item.get_path() == path)
+                    .map_or(0, |index| index + 1);
+            }
+            *queue = cleared.original_queue;
+            self.original_queue = Vec::new();
+        }
+
+        drop(queue);
+
+        send_event(&self.events_tx, PlaybackEvent::QueueUpdated);
+
+        if queue_next == 0 || cleared.state == PlaybackState::Stopped {
+            self.queue_next = queue_next;
+            send_event(
+                &self.events_tx,
+                PlaybackEvent::QueuePositionChanged(queue_next.saturating_sub(1)),
+            );
+            return;
+        }
+
+        self.jump(queue_next - 1);
+
+        if cleared.position > 0 {
+            self.seek(cleared.position as f64);
+        }
+        if cleared.state == PlaybackState::Paused {
+            self.pause();
+        }
     }
 
     /// Stop the current playback.
@@ -879,17 +1462,50 @@ impl PlaybackThread {
             provider.stop_playback().expect("unable to stop playback");
             provider.close().expect("unable to close media");
         }
+        self.radio_info = None;
+        self.radio_status = None;
         self.state = PlaybackState::Stopped;
         info!("Playback stopped");
 
-        let events_tx = self.events_tx.clone();
-        smol::spawn(async move {
-            events_tx
-                .send(PlaybackEvent::StateChanged(PlaybackState::Stopped))
-                .await
-                .expect("unable to send event");
-        })
-        .detach();
+        send_event(
+            &self.events_tx,
+            PlaybackEvent::StateChanged(PlaybackState::Stopped),
+        );
+        self.send_format();
+    }
+
+    /// Stops playback, closes the stream and releases the device, then stops the main loop.
+    fn shutdown(&mut self) {
+        info!("Shutting down playback thread");
+
+        if let Some(provider) = &mut self.media_provider {
+            if self.state != PlaybackState::Stopped {
+                if let Err(e) = provider.stop_playback() {
+                    warn!("Failed to stop playback during shutdown: {:?}", e);
+                }
+            }
+
+            if let Err(e) = provider.close() {
+                warn!("Failed to close media during shutdown: {:?}", e);
+            }
+        }
+
+        if let Some(mut stream) = self.stream.take() {
+            if let Err(e) = stream.close_stream() {
+                warn!("Failed to close stream during shutdown: {:?}", e);
+            }
+        }
+
+        self.device = None;
+        self.radio_info = None;
+        self.radio_status = None;
+        self.state = PlaybackState::Stopped;
+        self.running = false;
+
+        // this is sent synchronously, since the thread is about to exit
+        if let Err(e) = self.events_tx.send_blocking(PlaybackEvent::ShutdownComplete) {
+            warn!("Failed to confirm playback thread shutdown: {:?}", e);
+        }
     }
 
     /// Toggle shuffle mode. This will result in the queue being duplicated and shuffled.
@@ -915,69 +1531,100 @@ impl PlaybackThread {
             self.original_queue = Vec::new();
             self.shuffle = false;
 
-            let events_tx = self.events_tx.clone();
-            smol::spawn(async move {
-                events_tx
-                    .send(PlaybackEvent::ShuffleToggled(false, index))
-                    .await
-                    .expect("unable to send event");
-                events_tx
-                    .send(PlaybackEvent::QueueUpdated)
-                    .await
-                    .expect("unable to send event");
-                if index != 0 {
-                    events_tx
-                        .send(PlaybackEvent::QueuePositionChanged(index))
-                        .await
-                        .expect("unable to send event");
-                }
-            })
-            .detach();
+            send_event(&self.events_tx, PlaybackEvent::ShuffleToggled(false, index));
+            send_event(&self.events_tx, PlaybackEvent::QueueUpdated);
+            if index != 0 {
+                send_event(&self.events_tx, PlaybackEvent::QueuePositionChanged(index));
+            }
         } else {
             self.original_queue = queue.clone();
             let length = queue.len();
             queue[self.queue_next..length].shuffle(&mut rng());
             self.shuffle = true;
 
-            let events_tx = self.events_tx.clone();
-            let queue_next = self.queue_next;
-            smol::spawn(async move {
-                events_tx
-                    .send(PlaybackEvent::ShuffleToggled(true, queue_next))
-                    .await
-                    .expect("unable to send event");
-                events_tx
-                    .send(PlaybackEvent::QueueUpdated)
-                    .await
-                    .expect("unable to send event");
-            })
-            .detach();
+            send_event(
+                &self.events_tx,
+                PlaybackEvent::ShuffleToggled(true, self.queue_next),
+            );
+            send_event(&self.events_tx, PlaybackEvent::QueueUpdated);
         }
     }
 
     /// Sets the volume of the playback stream.
     fn set_volume(&mut self, volume: f64) {
+        // setting the volume directly always unmutes
+        if self.muted_volume.take().is_some() {
+            self.send_mute_changed(false);
+        }
+
+        self.apply_volume(volume);
+    }
+
+    /// Mutes the stream, remembering the exact current volume, or restores it if already muted.
+    fn toggle_mute(&mut self) {
+        if let Some(volume) = self.muted_volume.take() {
+            self.apply_volume(volume);
+            self.send_mute_changed(false);
+        } else {
+            self.muted_volume = Some(self.volume);
+            self.apply_volume(0.0);
+            self.send_mute_changed(true);
+        }
+    }
+
+    fn send_mute_changed(&self, muted: bool) {
+        send_event(&self.events_tx, PlaybackEvent::MuteChanged(muted));
+    }
+
+    fn apply_volume(&mut self, volume: f64) {
+        self.volume = volume;
+
         if let Some(stream) = self.stream.as_mut() {
-            let volume_scaled = if volume >= 0.99_f64 {
-                1_f64
-            } else if volume > 0.1 {
-                f64::exp(LN_50 * volume) / 50_f64
-            } else {
-                volume * LINEAR_SCALING_COEFFICIENT
-            };
+            if let Err(err) = stream.set_volume(scale_volume(volume)) {
+                warn!("Failed to set volume: {:?}", err);
+            }
+
+            send_event(&self.events_tx, PlaybackEvent::VolumeChanged(volume));
+        }
+    }
 
-            stream
-                .set_volume(volume_scaled)
-                .expect("failed to set volume");
+    /// Sets the stereo balance, which is applied to every frame as it's submitted.
+    fn set_balance(&mut self, balance: f64) {
+        self.balance = balance.clamp(-1.0, 1.0);
 
-            let events_tx = self.events_tx.clone();
-            smol::spawn(async move {
-                events_tx
-                    .send(PlaybackEvent::VolumeChanged(volume))
-                    .await
-                    .expect("unable to send event");
-            })
-            .detach();
+        send_event(&self.events_tx, PlaybackEvent::BalanceChanged(self.balance));
+    }
+
+    /// Replaces the playback settings. The stream is reopened if the output format settings
+    /// changed, and the crossfeed filter is rebuilt if its settings changed. Skipping silence
+    /// applies from the next track. The balance isn't taken from the settings, since it's set
+    /// directly with SetBalance.
+    fn update_settings(&mut self, settings: PlaybackSettings) {
+        if settings == self.playback_settings {
+            return;
+        }
+
+        let old = std::mem::replace(&mut self.playback_settings, settings);
+        let new = &self.playback_settings;
+
+        if new.crossfeed != old.crossfeed || new.crossfeed_intensity != old.crossfeed_intensity {
+            self.crossfeed = new
+                .crossfeed
+                .then(|| Crossfeed::new(new.crossfeed_intensity));
+        }
+
+        if new.always_repeat && self.repeat == RepeatState::NotRepeating {
+            self.repeat = RepeatState::Repeating;
+            send_event(&self.events_tx, PlaybackEvent::RepeatChanged(self.repeat));
+        }
+
+        let format_changed = new.preferred_sample_rate != old.preferred_sample_rate
+            || new.preferred_bit_depth != old.preferred_bit_depth
+            || new.exclusive_mode != old.exclusive_mode;
+
+        // the new format is sent once the frame converter is set up again
+        if format_changed && self.stream.is_some() {
+            self.switch_device();
         }
     }
 
@@ -991,14 +1638,77 @@ impl PlaybackThread {
             state
         };
 
-        let events_tx = self.events_tx.clone();
-        smol::spawn(async move {
-            events_tx
-                .send(PlaybackEvent::RepeatChanged(state))
-                .await
-                .expect("unable to send event");
-        })
-        .detach();
+        send_event(&self.events_tx, PlaybackEvent::RepeatChanged(state));
+    }
+
+    /// Re-sends the current state of the thread as events.
+    fn send_state(&self) {
+        let duration = self
+            .media_provider
+            .as_ref()
+            .and_then(|provider| provider.duration_secs().ok())
+            .map(|duration| self.offsets.duration(duration));
+
+        // the last timestamp is stale once the track is closed, and unset before anything plays
+        let position = if self.state == PlaybackState::Stopped || self.last_timestamp == u64::MAX {
+            0
+        } else {
+            self.last_timestamp
+        };
+
+        let events = [
+            Some(PlaybackEvent::StateChanged(self.state)),
+            Some(PlaybackEvent::PositionChanged(position)),
+            duration.map(PlaybackEvent::DurationChanged),
+            Some(PlaybackEvent::VolumeChanged(self.volume)),
+            Some(PlaybackEvent::MuteChanged(self.muted_volume.is_some())),
+            Some(PlaybackEvent::BalanceChanged(self.balance)),
+            Some(PlaybackEvent::DeviceChanged(self.active_device())),
+            Some(PlaybackEvent::DevicesChanged(self.devices.devices.clone())),
+            Some(PlaybackEvent::RepeatChanged(self.repeat)),
+            Some(PlaybackEvent::ShuffleToggled(self.shuffle, self.queue_next)),
+        ];
+
+        for event in events.into_iter().flatten() {
+            send_event(&self.events_tx, event);
+        }
+
+        self.send_format();
+    }
+
+    /// Tells the UI the format of the current track and of the output stream.
+    fn send_format(&self) {
+        let format = PlaybackFormat {
+            source: self
+                .media_provider
+                .as_ref()
+                .and_then(|provider| provider.source_format().ok()),
+            output: self.stream.as_ref().and(self.format.clone()),
+        };
+
+        send_event(
+            &self.events_tx,
+            PlaybackEvent::FormatChanged(Box::new(format)),
+        );
+    }
+
+    fn send_diagnostics(&self) {
+        let diagnostics = PlaybackDiagnostics {
+            device_provider: self
+                .stream
+                .as_ref()
+                .and(self.format.as_ref())
+                .map(|format| format.originating_provider),
+            // symphonia is the only media provider
+            media_provider: "symphonia",
+            device: self.active_device(),
+            format: self.stream.as_ref().and(self.format.clone()),
+        };
+
+        send_event(
+            &self.events_tx,
+            PlaybackEvent::Diagnostics(Box::new(diagnostics)),
+        );
     }
 
     /// Toggles between play/pause.
@@ -1012,17 +1722,31 @@ impl PlaybackThread {
 
     /// Recreates the playback stream with the given channels if any are provided, otherwise uses
     /// the device's default channel layout.
+    ///
+    /// If the stream can't be opened, the thread is left without a stream, and the device is
+    /// opened again the next time the devices are checked. If the previous device was removed,
+    /// playback moves to another device, and is paused there if the user asked for that.
     fn recreate_stream(&mut self, force: bool, channels: Option<ChannelSpec>) {
+        let previous = self
+            .device
+            .as_ref()
+            .and_then(|device| device.get_uid().ok());
+        let disconnected = previous.as_deref().is_some_and(|uid| {
+            self.device_provider
+                .as_mut()
+                .is_some_and(|provider| provider.get_device_by_uid(uid).is_err())
+        });
+
         if let Some(mut stream) = self.stream.take() {
-            stream.close_stream().expect("failed to close stream");
+            // closing fails if the device has already been removed, which is fine
+            if let Err(err) = stream.close_stream() {
+                warn!("Failed to close stream: {:?}", err);
+            }
         }
 
-        let Some(device_provider) = self.device_provider.as_mut() else {
-            panic!("playback thread incorrectly initialized")
-        };
-
-        let Ok(mut device) = device_provider.get_default_device() else {
+        let Some(mut device) = self.find_device() else {
             error!("No playback device found, audio will not play");
+            self.set_device(None);
             return;
         };
 
@@ -1030,11 +1754,22 @@ impl PlaybackThread {
             return;
         }
 
-        let stream = if let Some(channels) = channels {
-            let mut format = device
-                .get_default_format()
-                .expect("failed to get device format");
+        let default_format = match device.get_default_format() {
+            Ok(format) => format,
+            Err(err) => {
+                error!(
+                    "Failed to get device format, audio will not play: {:?}",
+                    err
+                );
+                self.set_device(None);
+                return;
+            }
+        };
+        let mut format = default_format.clone();
 
+        self.apply_preferred_format(device.as_ref(), &mut format);
+
+        if let Some(channels) = channels {
             if !format.rate_channel_ratio_fixed {
                 let old_channels = format.channels.count();
                 format.sample_rate =
@@ -1043,48 +1778,322 @@ impl PlaybackThread {
             }
 
             format.channels = channels;
+        }
 
-            let result = device.open_device(format.clone());
-            match result {
-                Ok(stream) => stream,
+        let exclusive = if self.playback_settings.exclusive_mode {
+            match device.open_device_exclusive(format.clone()) {
+                Ok(stream) => Some(stream),
                 Err(err) => {
-                    warn!(
-                        "Failed to open device with requested format {:?}, error: {:?}",
-                        format, err
-                    );
-                    warn!("Falling back to default format");
-                    let format = device
-                        .get_default_format()
-                        .expect("failed to get device format");
-                    device
-                        .open_device(format)
-                        .expect("failed to open device with default format")
+                    info!("Exclusive mode unavailable, using shared mode: {:?}", err);
+                    None
                 }
             }
         } else {
-            let format = device
-                .get_default_format()
-                .expect("failed to get device format");
+            None
+        };
 
-            device
-                .open_device(format)
-                .expect("failed to open device with default format")
+        let stream = if let Some(stream) = exclusive {
+            Ok(stream)
+        } else if format == default_format {
+            device.open_device(format)
+        } else {
+            device.open_device(format.clone()).or_else(|err| {
+                warn!(
+                    "Failed to open device with requested format {:?}, error: {:?}",
+                    format, err
+                );
+                warn!("Falling back to default format");
+                device.open_device(default_format)
+            })
         };
 
-        self.device = Some(device);
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                error!("Failed to open device, audio will not play: {:?}", err);
+                self.set_device(None);
+                return;
+            }
+        };
+
+        // the new stream starts at its own default volume
+        if let Err(err) = stream.set_volume(scale_volume(self.volume)) {
+            warn!("Failed to set the volume of the new stream: {:?}", err);
+        }
+
+        if let Ok(format) = stream.get_current_format() {
+            info!(
+                "Opened device: {:?}, format: {:?}, bit depth: {:?}, rate: {}, channel_count: {}",
+                device.get_name(),
+                format.sample_type,
+                format.sample_type.bit_depth(),
+                format.sample_rate,
+                format.channels.count()
+            );
+        }
+
+        let moved = device.get_uid().ok() != previous;
+
         self.stream = Some(stream);
+        self.set_device(Some(device));
+
+        // a stream usually fails because its device was unplugged, which `check_device` may not
+        // have noticed yet
+        if disconnected && moved && self.playback_settings.pause_on_output_disconnect {
+            info!("Output device was removed, pausing playback");
+            self.pause();
+        }
+    }
+
+    /// Picks the device to open. The device requested by the user is used if it's connected.
+    /// Otherwise, the current device is kept if it's still present, unless the default device is
+    /// being followed, in which case the default device is used.
+    fn find_device(&mut self) -> Option<Box<dyn Device>> {
+        let current = self
+            .device
+            .as_ref()
+            .and_then(|device| device.get_uid().ok());
+        let follow_default = self.playback_settings.follow_default_device;
+
+        let Some(device_provider) = self.device_provider.as_mut() else {
+            panic!("playback thread incorrectly initialized")
+        };
+
+        let requested = self
+            .requested_device
+            .as_deref()
+            .and_then(|uid| device_provider.get_device_by_uid(uid).ok());
+
+        if requested.is_some() {
+            return requested;
+        }
+
+        if !follow_default {
+            let current = current.and_then(|uid| device_provider.get_device_by_uid(&uid).ok());
+
+            if current.is_some() {
+                return current;
+            }
+        }
+
+        device_provider.get_default_device().ok()
+    }
 
-        let format = self.stream.as_mut().unwrap().get_current_format().unwrap();
+    /// Replaces the current device, and tells the UI about it.
+    fn set_device(&mut self, device: Option<Box<dyn Device>>) {
+        self.device = device;
 
-        info!(
-            "Opened device: {:?}, format: {:?}, rate: {}, channel_count: {}",
-            self.device.as_ref().unwrap().get_name(),
-            format.sample_type,
-            format.sample_rate,
-            format.channels.count()
+        send_event(
+            &self.events_tx,
+            PlaybackEvent::DeviceChanged(self.active_device()),
         );
     }
 
+    fn active_device(&self) -> Option<OutputDevice> {
+        let device = self.device.as_ref()?;
+
+        Some(OutputDevice {
+            uid: device.get_uid().unwrap_or_default(),
+            name: device
+                .get_name()
+                .unwrap_or_else(|_| "Unknown device".to_string()),
+        })
+    }
+
+    /// Switches to the device with the given UID, or to the default device if None.
+    fn set_requested_device(&mut self, uid: Option<String>) {
+        let connected = uid.as_ref().is_none_or(|uid| self.devices.contains(uid));
+
+        if !connected {
+            warn!(
+                "Requested device {:?} is not connected, using the default device",
+                uid
+            );
+        }
+
+        self.requested_device = uid;
+        self.switch_device();
+    }
+
+    /// Stores the available devices, and tells the UI if they've changed.
+    fn update_devices(&mut self, list: DeviceList) {
+        if list.devices != self.devices.devices {
+            send_event(
+                &self.events_tx,
+                PlaybackEvent::DevicesChanged(list.devices.clone()),
+            );
+        }
+
+        self.devices = list;
+    }
+
+    /// Returns the UID of the device that playback should be on, following the same rules as
+    /// `find_device` but using the device watcher's list, so nothing has to be opened to check.
+    fn wanted_device(&self, current: Option<&str>) -> Option<String> {
+        let requested = self.requested_device.as_deref();
+
+        if let Some(requested) = requested.filter(|uid| self.devices.contains(uid)) {
+            return Some(requested.to_string());
+        }
+
+        if !self.playback_settings.follow_default_device {
+            if let Some(current) = current.filter(|uid| self.devices.contains(uid)) {
+                return Some(current.to_string());
+            }
+        }
+
+        self.devices.default.clone()
+    }
+
+    /// Checks the latest device list from the device watcher, and moves playback to another
+    /// device if needed: because the current device was removed, because the default device
+    /// changed while it's being followed, because the requested device was connected again, or
+    /// because no device was available before. Devices are compared by UID, so the stream is only
+    /// reopened when playback actually moves.
+    fn check_device(&mut self) {
+        let Some(device_lists) = &self.device_lists else {
+            return;
+        };
+
+        let mut latest = None;
+        while let Ok(list) = device_lists.try_recv() {
+            latest = Some(list);
+        }
+
+        let Some(list) = latest else {
+            return;
+        };
+
+        self.update_devices(list);
+
+        // if there's nothing to open, wait until a device is connected
+        let current = self
+            .device
+            .as_ref()
+            .and_then(|device| device.get_uid().ok());
+        let Some(wanted) = self.wanted_device(current.as_deref()) else {
+            return;
+        };
+
+        if self.stream.is_some() && current.as_ref() == Some(&wanted) {
+            return;
+        }
+
+        let removed = current
+            .as_ref()
+            .is_some_and(|uid| !self.devices.contains(uid));
+
+        // the stream is still moved to the new device, so that playback can be resumed there
+        if removed && self.playback_settings.pause_on_output_disconnect {
+            info!("Output device was removed, pausing playback");
+            self.pause();
+        }
+
+        self.switch_device();
+    }
+
+    /// Moves playback to the device picked by `find_device`, continuing from the same position.
+    fn switch_device(&mut self) {
+        info!("Switching output device, reopening the stream");
+
+        let channels = self
+            .format
+            .as_ref()
+            .map(|format| format.channels.clone())
+            .or_else(|| {
+                self.media_provider
+                    .as_ref()
+                    .and_then(|provider| provider.channels().ok())
+            });
+
+        self.recreate_stream(true, channels);
+
+        // the frame converter was set up for the old device's format
+        self.resampler = None;
+        self.pending_reset = false;
+
+        if self.state == PlaybackState::Playing {
+            let result = self.stream.as_mut().map(|stream| stream.play());
+
+            if let Some(Err(err)) = result {
+                error!("Failed to start playback on the new device: {:?}", err);
+                self.stream_failed();
+            }
+        }
+    }
+
+    /// Called when the stream can't be used even after it was recreated, which usually means the
+    /// device was removed. The stream is dropped and playback is paused; a device is opened again
+    /// when the devices are next checked.
+    fn stream_failed(&mut self) {
+        error!("This likely indicates a problem with the audio device or driver");
+        error!("(or an underlying issue in the used DeviceProvider)");
+
+        self.stream = None;
+        self.resampler = None;
+        self.send_format();
+
+        if self.state == PlaybackState::Playing {
+            self.state = PlaybackState::Paused;
+
+            send_event(
+                &self.events_tx,
+                PlaybackEvent::StateChanged(PlaybackState::Paused),
+            );
+        }
+    }
+
+    /// Applies the preferred sample rate and bit depth from the playback settings to `format`, if
+    /// the device supports them. Otherwise `format` is left as it is.
+    fn apply_preferred_format(&self, device: &dyn Device, format: &mut FormatInfo) {
+        // in exclusive mode the track's own rate takes precedence, so it isn't resampled
+        let preferred_rate = if self.playback_settings.exclusive_mode {
+            self.source_rate
+                .or(self.playback_settings.preferred_sample_rate)
+        } else {
+            self.playback_settings.preferred_sample_rate
+        };
+        let preferred_depth = self.playback_settings.preferred_bit_depth;
+
+        if preferred_rate.is_none() && preferred_depth.is_none() {
+            return;
+        }
+
+        let supported = match device.get_supported_formats() {
+            Ok(supported) => supported,
+            Err(err) => {
+                warn!(
+                    "Failed to get supported formats, using default format: {:?}",
+                    err
+                );
+                return;
+            }
+        };
+
+        let rate = preferred_rate.unwrap_or(format.sample_rate);
+        let candidate = supported.iter().find(|candidate| {
+            let depth_matches = match preferred_depth {
+                Some(depth) => candidate.sample_type.bit_depth() == Some(depth),
+                None => candidate.sample_type == format.sample_type,
+            };
+
+            depth_matches
+                && candidate.sample_rates.start <= rate
+                && rate <= candidate.sample_rates.end
+        });
+
+        match candidate {
+            Some(candidate) => {
+                format.sample_type = candidate.sample_type;
+                format.sample_rate = rate;
+            }
+            None => warn!(
+                "Device does not support {} Hz at {:?} bits, using default format",
+                rate, preferred_depth
+            ),
+        }
+    }
+
     /// Uses the current media provider to decode audio samples and sends them to the current
     /// playback stream.
     fn play_audio(&mut self) {
@@ -1097,8 +2106,11 @@ impl PlaybackThread {
         if self.resampler.is_none() {
             // TODO: proper error handling
             // Read the first samples ahead of time to determine the format.
-            let first_samples = match provider.read_samples() {
-                Ok(samples) => samples,
+            let trimmer = self.silence_trimmer.as_mut();
+            let segment_end = self.segment_end.as_mut();
+            let first_samples = match read_frame(provider.as_mut(), trimmer, segment_end) {
+                Ok(Some(samples)) => samples,
+                Ok(None) => return,
                 Err(e) => match e {
                     PlaybackReadError::NothingOpen => {
                         panic!("thread state is invalid: no file open")
@@ -1108,7 +2120,7 @@ impl PlaybackThread {
                     }
                     PlaybackReadError::Eof => {
                         info!("EOF, moving to next song");
-                        self.next(false);
+                        self.track_finished();
                         return;
                     }
                     PlaybackReadError::Unknown(s) => {
@@ -1124,6 +2136,30 @@ impl PlaybackThread {
                 },
             };
 
+            // In exclusive mode, reopen the device at the track's rate if it differs, so the
+            // resampler only has to match the bit depth
+            if self.playback_settings.exclusive_mode && self.source_rate != Some(first_samples.rate)
+            {
+                self.source_rate = Some(first_samples.rate);
+
+                if stream
+                    .get_current_format()
+                    .is_ok_and(|format| format.sample_rate != first_samples.rate)
+                {
+                    let channels = stream.get_current_format().ok().map(|v| v.channels.clone());
+                    self.recreate_stream(true, channels);
+
+                    if let Some(Err(err)) = self.stream.as_mut().map(|stream| stream.play()) {
+                        warn!("Failed to start playback after reopening device: {:?}", err);
+                    }
+                }
+            }
+
+            let (Some(stream), Some(provider)) = (&mut self.stream, &mut self.media_provider)
+            else {
+                return;
+            };
+
             // Set up the resampler
             let duration = provider.frame_duration().expect("can't get duration");
             let device_format = stream.get_current_format().unwrap();
@@ -1131,14 +2167,35 @@ impl PlaybackThread {
             let resampler_sample_rate =
                 (device_format.sample_rate / device_format.rate_channel_ratio as u32) * 2;
 
-            self.resampler = Some(Resampler::new(
-                first_samples.rate,
+            self.resampler = Some(FrameConverter::new(
+                &first_samples,
                 resampler_sample_rate,
                 duration,
                 device_format.channels.count(),
             ));
+            self.converter_input = Some((first_samples.rate, first_samples.samples.channels()));
             self.format = Some(device_format.clone());
 
+            let format = PlaybackFormat {
+                source: provider.source_format().ok(),
+                output: Some(device_format.clone()),
+            };
+            send_event(
+                &self.events_tx,
+                PlaybackEvent::FormatChanged(Box::new(format)),
+            );
+
+            if self.playback_settings.auto_gapless_albums {
+                self.last_peak = peak(&first_samples.samples);
+            }
+
+            let first_samples = process_frame(
+                first_samples,
+                self.crossfeed.as_mut(),
+                self.balance,
+                self.playback_settings.modifies_gain(),
+            );
+
             // Convert the first samples to the device format
             let converted = self
                 .resampler
@@ -1157,22 +2214,26 @@ impl PlaybackThread {
                     submit_frame.err().unwrap()
                 );
                 self.recreate_stream(true, format.map(|v| v.channels));
-                let final_result = self.stream.as_mut().unwrap().submit_frame(converted);
+                let final_result = self
+                    .stream
+                    .as_mut()
+                    .map(|stream| stream.submit_frame(converted));
 
-                if final_result.is_err() {
+                if !matches!(final_result, Some(Ok(_))) {
                     error!("Failed to submit frame after recreation");
-                    error!("This likely indicates a problem with the audio device or driver");
-                    error!("(or an underlying issue in the used DeviceProvider)");
-                    error!("Please check your audio setup and try again.");
-                    panic!("Failed to submit frame after recreation");
+                    self.stream_failed();
+                    return;
                 }
             }
 
             self.update_ts();
         } else {
             // Ditto above but without creating the resampler
-            let samples = match provider.read_samples() {
-                Ok(samples) => samples,
+            let trimmer = self.silence_trimmer.as_mut();
+            let segment_end = self.segment_end.as_mut();
+            let samples = match read_frame(provider.as_mut(), trimmer, segment_end) {
+                Ok(Some(samples)) => samples,
+                Ok(None) => return,
                 Err(e) => match e {
                     PlaybackReadError::NothingOpen => {
                         panic!("thread state is invalid: no file open")
@@ -1182,7 +2243,7 @@ impl PlaybackThread {
                     }
                     PlaybackReadError::Eof => {
                         info!("EOF, moving to next song");
-                        self.next(false);
+                        self.track_finished();
                         return;
                     }
                     PlaybackReadError::Unknown(s) => {
@@ -1197,15 +2258,45 @@ impl PlaybackThread {
                     }
                 },
             };
+            if self.playback_settings.auto_gapless_albums {
+                self.last_peak = peak(&samples.samples);
+            }
+
+            // the converter is kept when the next track follows on without a gap, but it can
+            // only be used if the new track has the same sample rate and channels
+            let input = (samples.rate, samples.samples.channels());
+
+            if self.converter_input != Some(input) {
+                let duration = provider.frame_duration().expect("can't get duration");
+                let format = self.format.as_ref().unwrap();
+                let resampler_sample_rate =
+                    (format.sample_rate / format.rate_channel_ratio as u32) * 2;
+
+                self.resampler = Some(FrameConverter::new(
+                    &samples,
+                    resampler_sample_rate,
+                    duration,
+                    format.channels.count(),
+                ));
+                self.converter_input = Some(input);
+            }
+
+            let samples = process_frame(
+                samples,
+                self.crossfeed.as_mut(),
+                self.balance,
+                self.playback_settings.modifies_gain(),
+            );
+
             let converted = self
                 .resampler
                 .as_mut()
                 .unwrap()
                 .convert_formats(samples, self.format.as_ref().unwrap());
 
-            debug!("Submitting frame");
+            trace!(target: AUDIO_FRAMES_TARGET, "Submitting frame");
             let submit_frame = stream.submit_frame(converted.clone());
-            debug!("Finished submitting frame");
+            trace!(target: AUDIO_FRAMES_TARGET, "Finished submitting frame");
 
             // If we get an error, recreate the stream and retry
             if submit_frame.is_err() {
@@ -1216,14 +2307,15 @@ impl PlaybackThread {
                     submit_frame.err().unwrap()
                 );
                 self.recreate_stream(true, format.map(|v| v.channels));
-                let final_result = self.stream.as_mut().unwrap().submit_frame(converted);
+                let final_result = self
+                    .stream
+                    .as_mut()
+                    .map(|stream| stream.submit_frame(converted));
 
-                if final_result.is_err() {
+                if !matches!(final_result, Some(Ok(_))) {
                     error!("Failed to submit frame after recreation");
-                    error!("This likely indicates a problem with the audio device or driver");
-                    error!("(or an underlying issue in the used DeviceProvider)");
-                    error!("Please check your audio setup and try again.");
-                    panic!("Failed to submit frame after recreation");
+                    self.stream_failed();
+                    return;
                 }
             }
 
@@ -1231,3 +2323,545 @@ impl PlaybackThread {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use chrono::Utc;
+    use gpui::TestAppContext;
+
+    use super::*;
+    use crate::player::library::types::{DBString, Track};
+
+    /// Gives each harness its own directory, since tests run in parallel.
+    static NEXT_HARNESS: AtomicUsize = AtomicUsize::new(0);
+
+    /// A playback thread that plays to the dummy device. Commands are handled directly on the
+    /// test's thread, instead of by a running playback thread.
+    struct Harness {
+        thread: PlaybackThread,
+        events: Receiver<PlaybackEvent>,
+        _commands: Sender<PlaybackCommand>,
+        dir: PathBuf,
+    }
+
+    impl Harness {
+        fn new(settings: PlaybackSettings) -> Self {
+            let (commands_tx, commands_rx) = async_channel::unbounded();
+            let (events_tx, events_rx) = async_channel::unbounded();
+
+            let mut thread = PlaybackThread::new(
+                Arc::new(RwLock::new(Vec::new())),
+                settings,
+                commands_rx,
+                events_tx,
+            );
+            thread.device_provider = Some(Box::new(DummyDeviceProvider::new()));
+            thread.media_provider = Some(Box::new(SymphoniaProvider::default()));
+            thread.recreate_stream(true, None);
+
+            let dir = std::env::temp_dir().join(format!(
+                "mrchat-playback-{}-{}",
+                std::process::id(),
+                NEXT_HARNESS.fetch_add(1, Ordering::Relaxed)
+            ));
+            fs::create_dir_all(&dir).unwrap();
+
+            Harness {
+                thread,
+                events: events_rx,
+                _commands: commands_tx,
+                dir,
+            }
+        }
+
+        /// Writes a second of silence to a WAV file for each name, and returns them as queue
+        /// items.
+        fn items(&self, cx: &mut TestAppContext, names: &[&str]) -> Vec<QueueItemData> {
+            names
+                .iter()
+                .map(|name| {
+                    let path = self.dir.join(name);
+                    if !path.exists() {
+                        fs::write(&path, silent_wav(44100, 2, 1)).unwrap();
+                    }
+
+                    cx.update(|cx| QueueItemData::new(cx, path, None, None))
+                })
+                .collect()
+        }
+
+        /// Like `items`, but as consecutive tracks on the first disc of an album in the library.
+        fn album_items(
+            &self,
+            cx: &mut TestAppContext,
+            album_id: i64,
+            names: &[&str],
+        ) -> Vec<QueueItemData> {
+            self.items(cx, names)
+                .iter()
+                .zip(1..)
+                .map(|(item, number)| {
+                    let track = Track {
+                        id: album_id * 1000 + number as i64,
+                        title: DBString::from(item.to_string()),
+                        title_sortable: DBString::from(item.to_string()),
+                        album_id: Some(album_id),
+                        track_number: Some(number),
+                        disc_number: Some(1),
+                        duration: 1,
+                        created_at: Utc::now(),
+                        genres: None,
+                        tags: None,
+                        location: item.get_path().clone(),
+                        artist_names: None,
+                        segment: None,
+                    };
+
+                    cx.update(|cx| QueueItemData::from_track(cx, &track))
+                })
+                .collect()
+        }
+
+        fn send(&mut self, command: PlaybackCommand) {
+            self.thread.handle_command(command);
+        }
+
+        /// The file names of the queued tracks, in order.
+        fn queued(&self) -> Vec<String> {
+            names(&self.thread.queue.read().unwrap())
+        }
+
+        /// The position of the current track in the queue.
+        fn position(&self) -> Option<usize> {
+            self.thread.queue_next.checked_sub(1)
+        }
+
+        /// Takes the events the thread has sent so far.
+        fn events(&self) -> Vec<PlaybackEvent> {
+            std::iter::from_fn(|| self.events.try_recv().ok()).collect()
+        }
+    }
+
+    impl Drop for Harness {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn names(items: &[QueueItemData]) -> Vec<String> {
+        items
+            .iter()
+            .map(|item| {
+                let name = item.get_path().file_name().unwrap();
+                name.to_string_lossy().into_owned()
+            })
+            .collect()
+    }
+
+    fn duplicates(duplicate_queue_items: DuplicateQueueItems) -> Harness {
+        Harness::new(PlaybackSettings {
+            duplicate_queue_items,
+            ..Default::default()
+        })
+    }
+
+    /// A second of 16-bit silence as a WAV file.
+    fn silent_wav(rate: u32, channels: u16, seconds: u32) -> Vec<u8> {
+        let block_align = channels * 2;
+        let data_len = rate * block_align as u32 * seconds;
+
+        let mut wav = Vec::with_capacity(44 + data_len as usize);
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16_u32.to_le_bytes());
+        wav.extend_from_slice(&1_u16.to_le_bytes());
+        wav.extend_from_slice(&channels.to_le_bytes());
+        wav.extend_from_slice(&rate.to_le_bytes());
+        wav.extend_from_slice(&(rate * block_align as u32).to_le_bytes());
+        wav.extend_from_slice(&block_align.to_le_bytes());
+        wav.extend_from_slice(&16_u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        wav.resize(44 + data_len as usize, 0);
+        wav
+    }
+
+    #[gpui::test]
+    fn previous_restarts_only_after_the_threshold(cx: &mut TestAppContext) {
+        let mut harness = Harness::new(PlaybackSettings {
+            prev_track_jump_first: true,
+            prev_restart_threshold_secs: 5,
+            ..Default::default()
+        });
+        let items = harness.items(cx, &["one.wav", "two.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+        harness.send(PlaybackCommand::Jump(1));
+        harness.events();
+
+        // just past the threshold, the current track is restarted
+        harness.thread.last_timestamp = 6;
+        harness.send(PlaybackCommand::Previous);
+
+        assert_eq!(harness.position(), Some(1));
+        assert!(
+            !harness
+                .events()
+                .iter()
+                .any(|event| matches!(event, PlaybackEvent::SongChanged(_)))
+        );
+
+        // at the threshold, the previous track is played
+        harness.thread.last_timestamp = 5;
+        harness.send(PlaybackCommand::Previous);
+
+        assert_eq!(harness.position(), Some(0));
+        assert!(
+            harness
+                .events()
+                .contains(&PlaybackEvent::QueuePositionChanged(0))
+        );
+    }
+
+    #[gpui::test]
+    fn the_end_of_the_queue_stops_or_pauses(cx: &mut TestAppContext) {
+        for (action, state) in [
+            (EndOfQueueAction::Stop, PlaybackState::Stopped),
+            (EndOfQueueAction::Pause, PlaybackState::Paused),
+        ] {
+            let mut harness = Harness::new(PlaybackSettings {
+                end_of_queue_action: action,
+                ..Default::default()
+            });
+            let items = harness.items(cx, &["one.wav", "two.wav"]);
+            harness.send(PlaybackCommand::QueueList(items));
+            harness.send(PlaybackCommand::Jump(1));
+
+            harness.thread.next(false);
+
+            assert_eq!(harness.thread.state, state);
+            assert_eq!(harness.position(), Some(1));
+        }
+    }
+
+    #[gpui::test]
+    fn queued_tracks_are_skipped(cx: &mut TestAppContext) {
+        let mut harness = duplicates(DuplicateQueueItems::Skip);
+        let items = harness.items(cx, &["a.wav", "b.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+
+        let items = harness.items(cx, &["b.wav", "c.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+        let item = harness.items(cx, &["a.wav"]).remove(0);
+        harness.send(PlaybackCommand::Queue(item));
+
+        assert_eq!(harness.queued(), ["a.wav", "b.wav", "c.wav"]);
+    }
+
+    #[gpui::test]
+    fn moving_earlier_tracks_keeps_the_current_track(cx: &mut TestAppContext) {
+        let mut harness = duplicates(DuplicateQueueItems::Move);
+        let items = harness.items(cx, &["a.wav", "b.wav", "c.wav", "d.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+        harness.send(PlaybackCommand::Jump(2));
+        harness.events();
+
+        let items = harness.items(cx, &["a.wav", "b.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+
+        assert_eq!(harness.queued(), ["c.wav", "d.wav", "a.wav", "b.wav"]);
+        assert_eq!(harness.position(), Some(0));
+        assert!(
+            harness
+                .events()
+                .contains(&PlaybackEvent::QueuePositionChanged(0))
+        );
+    }
+
+    #[gpui::test]
+    fn the_current_track_is_never_moved(cx: &mut TestAppContext) {
+        let mut harness = duplicates(DuplicateQueueItems::Move);
+        let items = harness.items(cx, &["a.wav", "b.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+
+        let item = harness.items(cx, &["a.wav"]).remove(0);
+        harness.send(PlaybackCommand::Queue(item));
+
+        assert_eq!(harness.queued(), ["a.wav", "b.wav", "a.wav"]);
+        assert_eq!(harness.position(), Some(0));
+    }
+
+    #[gpui::test]
+    fn tracks_are_moved_to_play_next(cx: &mut TestAppContext) {
+        let mut harness = duplicates(DuplicateQueueItems::Move);
+        let items = harness.items(cx, &["a.wav", "b.wav", "c.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+
+        let items = harness.items(cx, &["c.wav"]);
+        harness.send(PlaybackCommand::PlayNext(items));
+
+        assert_eq!(harness.queued(), ["a.wav", "c.wav", "b.wav"]);
+        assert_eq!(harness.position(), Some(0));
+    }
+
+    #[gpui::test]
+    fn moved_tracks_leave_the_unshuffled_queue(cx: &mut TestAppContext) {
+        let mut harness = duplicates(DuplicateQueueItems::Move);
+        let items = harness.items(cx, &["a.wav", "b.wav", "c.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+        harness.send(PlaybackCommand::ToggleShuffle);
+
+        let items = harness.items(cx, &["b.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+
+        assert_eq!(
+            names(&harness.thread.original_queue),
+            ["a.wav", "c.wav", "b.wav"]
+        );
+
+        harness.send(PlaybackCommand::ToggleShuffle);
+        assert_eq!(harness.queued(), ["a.wav", "c.wav", "b.wav"]);
+        assert_eq!(harness.position(), Some(0));
+    }
+
+    #[gpui::test]
+    fn added_tracks_are_played_from_the_first_one_added(cx: &mut TestAppContext) {
+        let mut harness = duplicates(DuplicateQueueItems::Move);
+        let items = harness.items(cx, &["a.wav", "b.wav", "c.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+        harness.send(PlaybackCommand::Jump(1));
+
+        // moving a.wav shifts the queue, so it's played from its new position
+        let items = harness.items(cx, &["a.wav", "d.wav"]);
+        harness.send(PlaybackCommand::QueueAndPlay(items));
+
+        assert_eq!(harness.queued(), ["b.wav", "c.wav", "a.wav", "d.wav"]);
+        assert_eq!(harness.position(), Some(2));
+    }
+
+    #[gpui::test]
+    fn skipped_tracks_are_played_where_they_are(cx: &mut TestAppContext) {
+        let mut harness = duplicates(DuplicateQueueItems::Skip);
+        let items = harness.items(cx, &["a.wav", "b.wav", "c.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+
+        let items = harness.items(cx, &["b.wav", "c.wav"]);
+        harness.send(PlaybackCommand::QueueAndPlay(items));
+
+        assert_eq!(harness.queued(), ["a.wav", "b.wav", "c.wav"]);
+        assert_eq!(harness.position(), Some(1));
+    }
+
+    #[gpui::test]
+    fn shuffled_tracks_are_played_from_the_first_one_added(cx: &mut TestAppContext) {
+        let mut harness = Harness::new(PlaybackSettings::default());
+        let items = harness.items(cx, &["a.wav", "b.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+        harness.send(PlaybackCommand::ToggleShuffle);
+
+        let items = harness.items(cx, &["c.wav", "d.wav", "e.wav"]);
+        harness.send(PlaybackCommand::QueueAndPlay(items));
+
+        assert_eq!(harness.position(), Some(2));
+        assert!(["c.wav", "d.wav", "e.wav"].contains(&harness.queued()[2].as_str()));
+        assert_eq!(
+            names(&harness.thread.original_queue),
+            ["a.wav", "b.wav", "c.wav", "d.wav", "e.wav"]
+        );
+    }
+
+    #[gpui::test]
+    fn undoing_a_clear_restores_the_shuffled_queue(cx: &mut TestAppContext) {
+        let mut harness = Harness::new(PlaybackSettings::default());
+        let items = harness.items(cx, &["a.wav", "b.wav", "c.wav", "d.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+        harness.send(PlaybackCommand::ToggleShuffle);
+        harness.send(PlaybackCommand::Jump(2));
+        let queued = harness.queued();
+
+        harness.send(PlaybackCommand::ClearQueue);
+        assert!(harness.queued().is_empty());
+
+        harness.send(PlaybackCommand::UndoClearQueue);
+        assert_eq!(harness.queued(), queued);
+        assert_eq!(harness.position(), Some(2));
+        assert_eq!(
+            names(&harness.thread.original_queue),
+            ["a.wav", "b.wav", "c.wav", "d.wav"]
+        );
+    }
+
+    #[gpui::test]
+    fn undoing_a_clear_keeps_the_current_track_when_unshuffled(cx: &mut TestAppContext) {
+        let mut harness = Harness::new(PlaybackSettings::default());
+        let items = harness.items(cx, &["a.wav", "b.wav", "c.wav", "d.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+        harness.send(PlaybackCommand::ToggleShuffle);
+        harness.send(PlaybackCommand::Jump(2));
+        let current = harness.queued()[2].clone();
+
+        harness.send(PlaybackCommand::ClearQueue);
+        harness.send(PlaybackCommand::ToggleShuffle);
+        harness.send(PlaybackCommand::UndoClearQueue);
+
+        let queued = harness.queued();
+        assert_eq!(queued, ["a.wav", "b.wav", "c.wav", "d.wav"]);
+        assert_eq!(queued[harness.position().unwrap()], current);
+        assert!(harness.thread.original_queue.is_empty());
+    }
+
+    #[gpui::test]
+    fn undoing_a_clear_keeps_the_position_and_pause(cx: &mut TestAppContext) {
+        let mut harness = Harness::new(PlaybackSettings::default());
+        fs::write(harness.dir.join("long.wav"), silent_wav(44100, 2, 10)).unwrap();
+        let items = harness.items(cx, &["a.wav", "long.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+        harness.send(PlaybackCommand::Jump(1));
+        harness.send(PlaybackCommand::Seek(4.0));
+        harness.send(PlaybackCommand::Pause);
+
+        harness.send(PlaybackCommand::ClearQueue);
+        assert_eq!(harness.thread.state, PlaybackState::Stopped);
+
+        harness.send(PlaybackCommand::UndoClearQueue);
+        assert_eq!(harness.position(), Some(1));
+        assert_eq!(harness.thread.state, PlaybackState::Paused);
+        assert_eq!(harness.thread.last_timestamp, 4);
+    }
+
+    #[gpui::test]
+    fn undoing_a_clear_while_stopped_doesnt_play(cx: &mut TestAppContext) {
+        let mut harness = Harness::new(PlaybackSettings::default());
+        let items = harness.items(cx, &["a.wav", "b.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+        harness.send(PlaybackCommand::Jump(1));
+        harness.send(PlaybackCommand::Stop);
+
+        harness.send(PlaybackCommand::ClearQueue);
+        harness.send(PlaybackCommand::UndoClearQueue);
+
+        assert_eq!(harness.queued(), ["a.wav", "b.wav"]);
+        assert_eq!(harness.position(), Some(1));
+        assert_eq!(harness.thread.state, PlaybackState::Stopped);
+    }
+
+    #[gpui::test]
+    fn clearing_isnt_undone_once_something_is_queued(cx: &mut TestAppContext) {
+        let mut harness = Harness::new(PlaybackSettings::default());
+        let items = harness.items(cx, &["a.wav", "b.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+        harness.send(PlaybackCommand::ClearQueue);
+
+        let items = harness.items(cx, &["c.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+        harness.send(PlaybackCommand::UndoClearQueue);
+
+        assert_eq!(harness.queued(), ["c.wav"]);
+    }
+
+    #[gpui::test]
+    fn album_tracks_are_joined_unless_they_end_in_silence(cx: &mut TestAppContext) {
+        let mut harness = Harness::new(PlaybackSettings {
+            auto_gapless_albums: true,
+            ..Default::default()
+        });
+        let items = harness.album_items(cx, 1, &["one.wav", "two.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+        harness.send(PlaybackCommand::Jump(0));
+
+        harness.thread.last_peak = 0.5;
+        assert!(harness.thread.joins_next_track());
+
+        // silence is below the default threshold of -60 dB
+        harness.thread.last_peak = 0.0005;
+        assert!(!harness.thread.joins_next_track());
+    }
+
+    #[gpui::test]
+    fn album_tracks_arent_joined_when_turned_off(cx: &mut TestAppContext) {
+        let mut harness = Harness::new(PlaybackSettings::default());
+        let items = harness.album_items(cx, 1, &["one.wav", "two.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+        harness.send(PlaybackCommand::Jump(0));
+        harness.thread.last_peak = 0.5;
+
+        assert!(!harness.thread.joins_next_track());
+    }
+
+    #[gpui::test]
+    fn only_tracks_that_follow_on_an_album_are_joined(cx: &mut TestAppContext) {
+        let mut harness = Harness::new(PlaybackSettings {
+            auto_gapless_albums: true,
+            ..Default::default()
+        });
+        let mut items = harness.album_items(cx, 1, &["one.wav"]);
+        items.extend(harness.album_items(cx, 2, &["two.wav"]));
+        items.extend(harness.items(cx, &["three.wav"]));
+        harness.send(PlaybackCommand::QueueList(items));
+        harness.thread.last_peak = 0.5;
+
+        harness.send(PlaybackCommand::Jump(0));
+        assert!(!harness.thread.joins_next_track());
+
+        harness.send(PlaybackCommand::Jump(1));
+        assert!(!harness.thread.joins_next_track());
+
+        // the last track has nothing to join
+        harness.send(PlaybackCommand::Jump(2));
+        assert!(!harness.thread.joins_next_track());
+    }
+
+    #[gpui::test]
+    fn repeating_one_track_isnt_joined(cx: &mut TestAppContext) {
+        let mut harness = Harness::new(PlaybackSettings {
+            auto_gapless_albums: true,
+            ..Default::default()
+        });
+        let items = harness.album_items(cx, 1, &["one.wav", "two.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+        harness.send(PlaybackCommand::Jump(0));
+        harness.send(PlaybackCommand::SetRepeat(RepeatState::RepeatingOne));
+        harness.thread.last_peak = 0.5;
+
+        assert!(!harness.thread.joins_next_track());
+    }
+
+    #[gpui::test]
+    fn the_state_has_no_position_until_a_track_plays(cx: &mut TestAppContext) {
+        let mut harness = Harness::new(PlaybackSettings::default());
+        harness.send(PlaybackCommand::RequestState);
+
+        assert!(
+            harness
+                .events()
+                .contains(&PlaybackEvent::PositionChanged(0))
+        );
+
+        let items = harness.items(cx, &["one.wav"]);
+        harness.send(PlaybackCommand::QueueList(items));
+        harness.send(PlaybackCommand::Jump(0));
+        harness.thread.last_timestamp = 3;
+        harness.events();
+        harness.send(PlaybackCommand::RequestState);
+
+        assert!(
+            harness
+                .events()
+                .contains(&PlaybackEvent::PositionChanged(3))
+        );
+
+        harness.send(PlaybackCommand::Stop);
+        harness.send(PlaybackCommand::RequestState);
+
+        assert!(
+            harness
+                .events()
+                .contains(&PlaybackEvent::PositionChanged(0))
+        );
+    }
+}
@@ -0,0 +1,58 @@
+pub const LN_50: f64 = 3.91202300543_f64;
+pub const LINEAR_SCALING_COEFFICIENT: f64 = 0.295751527165_f64;
+
+/// Converts a user-facing volume (from 0.0 to 1.0, as shown on the volume slider) into the
+/// amplitude multiplier applied to the output stream.
+///
+/// Loudness is perceived logarithmically, so most of the range follows an exponential curve. Below
+/// 0.1 the curve becomes linear so that 0.0 is actually silent, and at 0.99 and above the output is
+/// set to full volume.
+pub fn scale_volume(user: f64) -> f64 {
+    if user >= 0.99_f64 {
+        1_f64
+    } else if user > 0.1 {
+        f64::exp(LN_50 * user) / 50_f64
+    } else {
+        user * LINEAR_SCALING_COEFFICIENT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints() {
+        assert_eq!(scale_volume(0.0), 0.0);
+        assert_eq!(scale_volume(1.0), 1.0);
+    }
+
+    #[test]
+    fn monotonic() {
+        let mut last = scale_volume(0.0);
+
+        for i in 1..=1000 {
+            let current = scale_volume(i as f64 / 1000.0);
+            assert!(current >= last, "curve decreased at {}", i as f64 / 1000.0);
+            last = current;
+        }
+    }
+
+    #[test]
+    fn continuous_at_linear_breakpoint() {
+        let below = scale_volume(0.1);
+        let above = scale_volume(0.1 + f64::EPSILON);
+
+        assert!((above - below).abs() < 1e-9);
+    }
+
+    #[test]
+    fn continuous_at_full_volume_breakpoint() {
+        let below = scale_volume(0.99 - f64::EPSILON);
+        let above = scale_volume(0.99);
+
+        // the curve snaps to full volume here, so allow for a small step
+        assert!(above >= below);
+        assert!(above - below < 0.05);
+    }
+}
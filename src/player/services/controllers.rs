@@ -2,6 +2,9 @@
 mod macos;
 #[cfg(target_os = "linux")]
 mod mpris;
+mod notifications;
+pub mod now_playing;
+pub mod remote;
 #[cfg(target_os = "windows")]
 mod windows;
 
@@ -20,9 +23,11 @@ use crate::{
     player::playback::{
         events::{PlaybackCommand, RepeatState},
         interface::GPUIPlaybackInterface,
+        queue::QueueItemData,
         thread::PlaybackState,
     },
     player::ui::models::{Models, PlaybackInfo},
+    shared::settings::SettingsGlobal,
 };
 
 /// The InitPlaybackController trait allows you to initialize a new PlaybackController. All
@@ -170,6 +175,17 @@ impl ControllerBridge {
         .detach();
     }
 
+    pub fn restart(&self) {
+        let playback_thread = self.playback_thread.clone();
+        smol::spawn(async move {
+            playback_thread
+                .send(PlaybackCommand::Restart)
+                .await
+                .expect("could not send tx (from ControllerBridge)");
+        })
+        .detach();
+    }
+
     pub fn jump(&self, index: usize) {
         let playback_thread = self.playback_thread.clone();
         smol::spawn(async move {
@@ -203,6 +219,17 @@ impl ControllerBridge {
         .detach();
     }
 
+    pub fn queue(&self, item: QueueItemData) {
+        let playback_thread = self.playback_thread.clone();
+        smol::spawn(async move {
+            playback_thread
+                .send(PlaybackCommand::Queue(item))
+                .await
+                .expect("could not send tx (from ControllerBridge)");
+        })
+        .detach();
+    }
+
     pub fn toggle_shuffle(&self) {
         let playback_thread = self.playback_thread.clone();
         smol::spawn(async move {
@@ -224,6 +251,20 @@ impl ControllerBridge {
         })
         .detach();
     }
+
+    /// Requests that the playback thread re-send its current state. Controllers receive the
+    /// resulting events through the usual PlaybackController methods, so this can be used to
+    /// populate a controller that was attached (or a client that reconnected) mid-playback.
+    pub fn request_state(&self) {
+        let playback_thread = self.playback_thread.clone();
+        smol::spawn(async move {
+            playback_thread
+                .send(PlaybackCommand::RequestState)
+                .await
+                .expect("could not send tx (from ControllerBridge)");
+        })
+        .detach();
+    }
 }
 
 pub type ControllerList = AHashMap<String, Arc<Mutex<dyn PlaybackController>>>;
@@ -404,6 +445,7 @@ pub fn make_cl(cx: &mut App, window: &mut Window) {
 
         let sender = cx.global::<GPUIPlaybackInterface>().get_sender();
         let bridge = ControllerBridge::new(sender);
+        let state_bridge = bridge.clone();
 
         #[cfg(target_os = "macos")]
         {
@@ -435,6 +477,62 @@ pub fn make_cl(cx: &mut App, window: &mut Window) {
             };
         }
 
+        let remote_settings = cx
+            .global::<SettingsGlobal>()
+            .model
+            .read(cx)
+            .remote_control
+            .clone();
+
+        if remote_settings.enabled {
+            match remote::RemoteController::start(state_bridge.clone(), &remote_settings, cx) {
+                Ok(remote_pc) => {
+                    list.insert("remote".to_string(), remote_pc);
+                }
+                Err(err) => {
+                    error!("Failed to start the control server: {:#}", err);
+                    warn!("Remote control will be unavailable.");
+                }
+            }
+        }
+
+        if remote_settings.write_now_playing_file {
+            let path = now_playing::now_playing_path(&remote_settings);
+            let file_pc: Arc<Mutex<dyn PlaybackController>> =
+                Arc::new(Mutex::new(now_playing::NowPlayingFile::new(path)));
+
+            list.insert("now_playing_file".to_string(), file_pc);
+        }
+
+        // notifications can be turned on and off without restarting, and are only shown while the
+        // window is in the background, so the controller is always attached
+        let settings = cx.global::<SettingsGlobal>().model.clone();
+        let gate = Arc::new(notifications::NotificationGate::new(
+            settings.read(cx).interface.notify_on_track_change,
+            window.is_window_active(),
+        ));
+
+        cx.observe(&settings, {
+            let gate = gate.clone();
+            move |_, settings, cx| {
+                gate.set_enabled(settings.read(cx).interface.notify_on_track_change);
+            }
+        })
+        .detach();
+
+        cx.observe_window_activation(window, {
+            let gate = gate.clone();
+            move |_, window, _| gate.set_window_active(window.is_window_active())
+        })
+        .detach();
+
+        let notifications_pc: Arc<Mutex<dyn PlaybackController>> =
+            Arc::new(Mutex::new(notifications::TrackNotifications::new(gate)));
+        list.insert("notifications".to_string(), notifications_pc);
+
+        // the controllers were attached after playback started, so they need a full state dump
+        state_bridge.request_state();
+
         list
     });
 
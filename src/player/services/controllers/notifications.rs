@@ -0,0 +1,276 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use notify_rust::Notification;
+use tracing::warn;
+
+use crate::{
+    player::media::metadata::Metadata,
+    player::playback::{events::RepeatState, thread::PlaybackState},
+    player::ui::app::cache_dir,
+    shared::i18n::t,
+};
+
+use super::PlaybackController;
+
+/// How long after a track starts its notification is shown. Tracks skipped within this time don't
+/// get a notification, so skipping through the queue doesn't flood the screen, and the album art
+/// has time to arrive.
+const NOTIFICATION_DELAY: Duration = Duration::from_millis(750);
+
+/// Whether notifications are shown, kept in step with the settings and the main window.
+pub struct NotificationGate {
+    enabled: AtomicBool,
+    window_active: AtomicBool,
+}
+
+impl NotificationGate {
+    pub fn new(enabled: bool, window_active: bool) -> Self {
+        NotificationGate {
+            enabled: AtomicBool::new(enabled),
+            window_active: AtomicBool::new(window_active),
+        }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn set_window_active(&self, active: bool) {
+        self.window_active.store(active, Ordering::Relaxed);
+    }
+
+    fn is_open(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed) && !self.window_active.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Default)]
+struct Track {
+    /// Counts track changes, so a delayed notification can tell whether its track is still the
+    /// current one.
+    generation: u64,
+    path: Option<PathBuf>,
+    metadata: Option<Metadata>,
+    art: Option<Vec<u8>>,
+    state: Option<PlaybackState>,
+    /// The last notification shown, replaced by the next one where the platform allows it.
+    notification_id: Option<u32>,
+}
+
+/// Shows a desktop notification with the title, artist and album art of each new track, while the
+/// main window is in the background.
+pub struct TrackNotifications {
+    gate: Arc<NotificationGate>,
+    track: Arc<Mutex<Track>>,
+}
+
+impl TrackNotifications {
+    pub fn new(gate: Arc<NotificationGate>) -> Self {
+        TrackNotifications {
+            gate,
+            track: Arc::new(Mutex::new(Track::default())),
+        }
+    }
+
+    fn track(&self) -> MutexGuard<'_, Track> {
+        self.track.lock().expect("notification state poisoned")
+    }
+}
+
+fn notify(track: &Mutex<Track>, generation: u64) {
+    let mut notification = Notification::new();
+
+    // the lock isn't held while the art is written and the notification is shown, since either can
+    // block for a while and the other controller methods need the lock
+    let (art, previous) = {
+        let track = track.lock().expect("notification state poisoned");
+
+        if track.generation != generation || track.state != Some(PlaybackState::Playing) {
+            return;
+        }
+
+        let metadata = track.metadata.as_ref();
+        let title = metadata
+            .and_then(|metadata| metadata.name.clone())
+            .or_else(|| {
+                let path = track.path.as_ref()?;
+                Some(path.file_stem()?.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| t("player.unknown_track").to_string());
+        let artist = metadata
+            .and_then(|metadata| metadata.artist.as_deref())
+            .unwrap_or(t("player.unknown_artist"));
+        let body = match metadata.and_then(|metadata| metadata.album.as_deref()) {
+            Some(album) => format!("{artist} — {album}"),
+            None => artist.to_string(),
+        };
+
+        notification.appname("MrChat").summary(&title).body(&body);
+
+        (track.art.clone(), track.notification_id)
+    };
+
+    if let Some(path) = art.as_deref().and_then(|art| write_art(art, generation)) {
+        notification.image_path(&path.to_string_lossy());
+    }
+
+    let id = show(&mut notification, previous);
+    track
+        .lock()
+        .expect("notification state poisoned")
+        .notification_id = id;
+}
+
+/// Writes the album art where the notification service can read it, returning its path. Each track
+/// gets its own file, since notification services may cache images by path or read them after the
+/// next track's art has been written. The art of earlier tracks is removed.
+fn write_art(art: &[u8], generation: u64) -> Option<PathBuf> {
+    let format = image::guess_format(art).ok()?;
+    let directory = cache_dir().join("notification_art");
+    let path = directory
+        .join(generation.to_string())
+        .with_extension(format.extensions_str().first()?);
+
+    match fs::remove_dir_all(&directory) {
+        Err(err) if err.kind() != ErrorKind::NotFound => {
+            warn!("Couldn't remove old notification album art: {}", err);
+        }
+        _ => {}
+    }
+
+    if let Err(err) = fs::create_dir_all(&directory).and_then(|_| fs::write(&path, art)) {
+        warn!("Couldn't write album art for the notification: {}", err);
+        return None;
+    }
+
+    Some(path)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn show(notification: &mut Notification, previous: Option<u32>) -> Option<u32> {
+    if let Some(id) = previous {
+        notification.id(id);
+    }
+
+    match notification.show() {
+        Ok(handle) => Some(handle.id()),
+        Err(err) => {
+            warn!("Couldn't show the track notification: {}", err);
+            None
+        }
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn show(notification: &mut Notification, _: Option<u32>) -> Option<u32> {
+    if let Err(err) = notification.show() {
+        warn!("Couldn't show the track notification: {}", err);
+    }
+
+    None
+}
+
+#[async_trait]
+impl PlaybackController for TrackNotifications {
+    async fn position_changed(&mut self, _: u64) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn duration_changed(&mut self, _: u64) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn volume_changed(&mut self, _: f64) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn metadata_changed(&mut self, metadata: &Metadata) -> anyhow::Result<()> {
+        self.track().metadata = Some(metadata.clone());
+        Ok(())
+    }
+
+    async fn album_art_changed(&mut self, album_art: &[u8]) -> anyhow::Result<()> {
+        self.track().art = Some(album_art.to_vec());
+        Ok(())
+    }
+
+    async fn repeat_state_changed(&mut self, _: RepeatState) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn playback_state_changed(
+        &mut self,
+        playback_state: PlaybackState,
+    ) -> anyhow::Result<()> {
+        self.track().state = Some(playback_state);
+        Ok(())
+    }
+
+    async fn shuffle_state_changed(&mut self, _: bool) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn new_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        let generation = {
+            let mut track = self.track();
+            track.generation += 1;
+            track.path = Some(path.to_path_buf());
+            track.metadata = None;
+            track.art = None;
+            track.generation
+        };
+
+        let gate = self.gate.clone();
+        let track = self.track.clone();
+
+        smol::spawn(async move {
+            smol::Timer::after(NOTIFICATION_DELAY).await;
+
+            if gate.is_open() {
+                smol::unblock(move || notify(&track, generation)).await;
+            }
+        })
+        .detach();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notifications_are_shown_while_the_window_is_in_the_background() {
+        let gate = NotificationGate::new(true, false);
+        assert!(gate.is_open());
+
+        gate.set_window_active(true);
+        assert!(!gate.is_open());
+
+        gate.set_window_active(false);
+        assert!(gate.is_open());
+    }
+
+    #[test]
+    fn notifications_can_be_turned_off() {
+        let gate = NotificationGate::new(false, false);
+        assert!(!gate.is_open());
+
+        gate.set_enabled(true);
+        assert!(gate.is_open());
+
+        gate.set_enabled(false);
+        assert!(!gate.is_open());
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use tracing::{info, warn};
+
+use crate::{
+    player::media::metadata::Metadata,
+    player::playback::{events::RepeatState, thread::PlaybackState},
+    player::ui::app::data_dir,
+    shared::settings::remote::RemoteControlSettings,
+};
+
+use super::PlaybackController;
+
+/// The shortest time between writes of the now playing file caused by the position changing.
+const MIN_POSITION_WRITE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where the now playing file is written: the path set in the settings, or `now_playing.json` in
+/// the data directory.
+pub fn now_playing_path(settings: &RemoteControlSettings) -> PathBuf {
+    settings
+        .now_playing_file
+        .clone()
+        .unwrap_or_else(|| data_dir().join("now_playing.json"))
+}
+
+/// The playback state, as reported to external tools.
+#[derive(Default)]
+pub struct NowPlaying {
+    pub path: Option<PathBuf>,
+    pub metadata: Option<Metadata>,
+    pub state: Option<PlaybackState>,
+    pub repeat: Option<RepeatState>,
+    pub position: u64,
+    pub duration: u64,
+    pub volume: Option<f64>,
+    pub shuffle: bool,
+}
+
+impl NowPlaying {
+    pub fn new_file(&mut self, path: &Path) {
+        self.path = Some(path.to_path_buf());
+        self.metadata = None;
+        self.position = 0;
+        self.duration = 0;
+    }
+
+    pub fn set_state(&mut self, state: PlaybackState) {
+        self.state = Some(state);
+
+        if state == PlaybackState::Stopped {
+            self.path = None;
+            self.metadata = None;
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let metadata = self.metadata.as_ref();
+
+        json!({
+            "state": match self.state {
+                Some(PlaybackState::Playing) => "playing",
+                Some(PlaybackState::Paused) => "paused",
+                Some(PlaybackState::Stopped) | None => "stopped",
+            },
+            "path": self.path.as_ref().map(|path| path.to_string_lossy()),
+            "title": metadata.and_then(|v| v.name.as_deref()),
+            "artist": metadata.and_then(|v| v.artist.as_deref()),
+            "album": metadata.and_then(|v| v.album.as_deref()),
+            "position": self.position,
+            "duration": self.duration,
+            "volume": self.volume,
+            "shuffle": self.shuffle,
+            "repeat": match self.repeat {
+                Some(RepeatState::Repeating) => "repeating",
+                Some(RepeatState::RepeatingOne) => "repeating_one",
+                Some(RepeatState::NotRepeating) | None => "not_repeating",
+            },
+        })
+    }
+}
+
+/// Writes the playback state to a JSON file, for stream overlays (such as OBS text sources) and
+/// other tools that can't use the control server.
+///
+/// The file is rewritten whenever the track or playback state changes, and at most once a second
+/// as the position changes. It's written to a temporary file that then replaces the old one, so
+/// readers never see a partially written file.
+pub struct NowPlayingFile {
+    path: PathBuf,
+    now_playing: NowPlaying,
+    last_position_write: Option<Instant>,
+}
+
+impl NowPlayingFile {
+    pub fn new(path: PathBuf) -> Self {
+        info!("Writing now playing information to {:?}", path);
+
+        NowPlayingFile {
+            path,
+            now_playing: NowPlaying::default(),
+            last_position_write: None,
+        }
+    }
+
+    fn write(&self) -> anyhow::Result<()> {
+        let temp_path = self.path.with_extension("json.tmp");
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&temp_path, self.now_playing.to_json().to_string())?;
+        fs::rename(&temp_path, &self.path)?;
+
+        Ok(())
+    }
+
+    fn write_or_warn(&self) {
+        if let Err(err) = self.write() {
+            warn!("Couldn't write now playing file {:?}: {}", self.path, err);
+        }
+    }
+}
+
+#[async_trait]
+impl PlaybackController for NowPlayingFile {
+    async fn position_changed(&mut self, new_position: u64) -> anyhow::Result<()> {
+        self.now_playing.position = new_position;
+
+        if self
+            .last_position_write
+            .is_none_or(|last| last.elapsed() >= MIN_POSITION_WRITE_INTERVAL)
+        {
+            self.last_position_write = Some(Instant::now());
+            self.write_or_warn();
+        }
+
+        Ok(())
+    }
+
+    async fn duration_changed(&mut self, new_duration: u64) -> anyhow::Result<()> {
+        self.now_playing.duration = new_duration;
+        self.write_or_warn();
+        Ok(())
+    }
+
+    async fn volume_changed(&mut self, new_volume: f64) -> anyhow::Result<()> {
+        self.now_playing.volume = Some(new_volume);
+        Ok(())
+    }
+
+    async fn metadata_changed(&mut self, metadata: &Metadata) -> anyhow::Result<()> {
+        self.now_playing.metadata = Some(metadata.clone());
+        self.write_or_warn();
+        Ok(())
+    }
+
+    async fn album_art_changed(&mut self, _: &[u8]) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn repeat_state_changed(&mut self, repeat_state: RepeatState) -> anyhow::Result<()> {
+        self.now_playing.repeat = Some(repeat_state);
+        Ok(())
+    }
+
+    async fn playback_state_changed(
+        &mut self,
+        playback_state: PlaybackState,
+    ) -> anyhow::Result<()> {
+        self.now_playing.set_state(playback_state);
+        self.write_or_warn();
+        Ok(())
+    }
+
+    async fn shuffle_state_changed(&mut self, shuffling: bool) -> anyhow::Result<()> {
+        self.now_playing.shuffle = shuffling;
+        Ok(())
+    }
+
+    async fn new_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.now_playing.new_file(path);
+        self.write_or_warn();
+        Ok(())
+    }
+}
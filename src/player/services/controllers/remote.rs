@@ -0,0 +1,670 @@
+//! A local HTTP server that allows playback to be controlled by scripts and other programs, such
+//! as Stream Deck plugins or home automation.
+//!
+//! The server is only started if it's enabled in the settings, and listens on 127.0.0.1:47630 by
+//! default. Every request has to include the token stored in `remote_token` in the data directory
+//! as an `X-MrChat-Token` header. Since browsers can't add that header to requests for other sites,
+//! web pages can't send commands, and requests whose `Host` or `Origin` isn't this computer (or the
+//! configured address) are rejected as well.
+//!
+//! Every endpoint responds with JSON:
+//!
+//! - `GET /now-playing`: the current track, position, duration (both in seconds), volume, and the
+//!   playback, shuffle and repeat states.
+//! - `POST /play`, `POST /pause`, `POST /toggle`, `POST /stop`, `POST /next`, `POST /previous`
+//! - `POST /restart`: plays the current track again from the start.
+//! - `POST /seek?position=<seconds>`
+//! - `POST /volume?level=<0.0 to 1.0>`
+//! - `POST /enqueue?path=<file path or stream URL>`: adds the track to the end of the queue.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{ErrorKind, Write},
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Context;
+use async_lock::{Mutex, RwLock};
+use async_trait::async_trait;
+use gpui::{App, AsyncApp};
+use serde_json::{Value, json};
+use smol::{
+    Timer,
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    stream::StreamExt,
+};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    player::media::metadata::Metadata,
+    player::playback::{events::RepeatState, queue::QueueItemData, thread::PlaybackState},
+    player::ui::app::data_dir,
+    shared::settings::remote::RemoteControlSettings,
+};
+
+use super::{ControllerBridge, PlaybackController, now_playing::NowPlaying};
+
+/// The largest request accepted. Requests don't have bodies, so this only has to fit the headers.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// How long a client has to send its request before the connection is closed.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The file in the data directory that the token is kept in.
+const TOKEN_FILE: &str = "remote_token";
+
+/// The header clients send the token in, in lowercase.
+const TOKEN_HEADER: &str = "x-mrchat-token";
+
+/// Keeps track of the playback state for the control server.
+pub struct RemoteController {
+    now_playing: Arc<RwLock<NowPlaying>>,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    /// The request's headers, with lowercase names.
+    headers: HashMap<String, String>,
+}
+
+/// Who is allowed to use the control server.
+struct Access {
+    token: String,
+    /// The host names that requests may be addressed to, and sent from.
+    hosts: Vec<String>,
+}
+
+impl Access {
+    fn new(token: String, address: IpAddr) -> Self {
+        let address = match address {
+            IpAddr::V4(ip) => ip.to_string(),
+            IpAddr::V6(ip) => format!("[{ip}]"),
+        };
+
+        Access {
+            token,
+            hosts: vec![
+                "localhost".to_string(),
+                "127.0.0.1".to_string(),
+                "[::1]".to_string(),
+                address,
+            ],
+        }
+    }
+
+    /// Checks that the request is addressed to this computer, wasn't sent by a web page on
+    /// another site, and has the right token. Otherwise, the error response is returned.
+    fn check(&self, request: &Request) -> Result<(), String> {
+        // a web page on another site can reach the server through DNS rebinding, which leaves the
+        // site's name in the Host header
+        let host = request.headers.get("host").map(|v| host_name(v));
+        if !host.is_some_and(|host| self.allows(host)) {
+            return Err(error(
+                "403 Forbidden",
+                "requests must be sent to this computer",
+            ));
+        }
+
+        if let Some(origin) = request.headers.get("origin") {
+            let origin = origin.split_once("://").map_or(origin.as_str(), |v| v.1);
+            if !self.allows(host_name(origin)) {
+                return Err(error(
+                    "403 Forbidden",
+                    "requests from web pages aren't allowed",
+                ));
+            }
+        }
+
+        if request.headers.get(TOKEN_HEADER) != Some(&self.token) {
+            return Err(error(
+                "401 Unauthorized",
+                "the token from remote_token in the data folder must be sent as X-MrChat-Token",
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn allows(&self, host: &str) -> bool {
+        self.hosts.iter().any(|v| v.eq_ignore_ascii_case(host))
+    }
+}
+
+/// Returns the host name from a Host header or an origin without its scheme, without the port.
+fn host_name(value: &str) -> &str {
+    let value = value.split('/').next().unwrap_or_default();
+
+    if value.starts_with('[') {
+        // IPv6 addresses are bracketed, and contain colons
+        value.find(']').map_or(value, |end| &value[..=end])
+    } else {
+        value.split(':').next().unwrap_or_default()
+    }
+}
+
+/// Reads the control server's token, creating one if this is the first time the server has been
+/// started.
+fn load_token(path: &Path) -> anyhow::Result<String> {
+    match std::fs::read_to_string(path) {
+        Ok(token) if !token.trim().is_empty() => return Ok(token.trim().to_string()),
+        Ok(_) => (),
+        Err(err) if err.kind() == ErrorKind::NotFound => (),
+        Err(err) => return Err(err).context("couldn't read the control server token"),
+    }
+
+    let token = Uuid::new_v4().simple().to_string();
+    File::create(path)
+        .and_then(|mut file| {
+            // the token allows playback to be controlled, so keep it private
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+            }
+
+            file.write_all(token.as_bytes())
+        })
+        .context("couldn't save the control server token")?;
+
+    Ok(token)
+}
+
+/// Reads the request line and headers of an HTTP request. Anything after the headers is ignored.
+async fn read_request(stream: &mut (impl AsyncRead + Unpin)) -> anyhow::Result<Request> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 1024];
+
+    while !buffer.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut chunk).await?;
+
+        if read == 0 {
+            anyhow::bail!("connection closed before the request was complete");
+        }
+
+        buffer.extend_from_slice(&chunk[..read]);
+
+        if buffer.len() > MAX_REQUEST_SIZE {
+            anyhow::bail!("request is too large");
+        }
+    }
+
+    let head = String::from_utf8_lossy(&buffer);
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        anyhow::bail!("malformed request line");
+    };
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let headers = lines
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+
+    Ok(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect(),
+        headers,
+    })
+}
+
+fn response(status: &str, body: Value) -> String {
+    let body = body.to_string();
+
+    format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+        Connection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+fn error(status: &str, message: &str) -> String {
+    response(status, json!({ "error": message }))
+}
+
+/// Something a request asks for.
+#[derive(Debug, PartialEq)]
+enum Command {
+    NowPlaying,
+    Play,
+    Pause,
+    Toggle,
+    Stop,
+    Next,
+    Previous,
+    Restart,
+    Seek(f64),
+    Volume(f64),
+    Enqueue(PathBuf),
+}
+
+/// Works out what the request asks for. Requests that can't be run are turned into the error
+/// response to send back.
+fn route(request: &Request) -> Result<Command, String> {
+    let number = |name: &str| request.query.get(name).and_then(|v| v.parse::<f64>().ok());
+
+    // commands change playback, so they aren't accepted as GET requests, which browsers send
+    // without asking
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/now-playing") => Ok(Command::NowPlaying),
+        ("POST", "/play") => Ok(Command::Play),
+        ("POST", "/pause") => Ok(Command::Pause),
+        ("POST", "/toggle") => Ok(Command::Toggle),
+        ("POST", "/stop") => Ok(Command::Stop),
+        ("POST", "/next") => Ok(Command::Next),
+        ("POST", "/previous") => Ok(Command::Previous),
+        ("POST", "/restart") => Ok(Command::Restart),
+        ("POST", "/seek") => match number("position") {
+            Some(position) if position >= 0.0 => Ok(Command::Seek(position)),
+            _ => Err(error(
+                "400 Bad Request",
+                "position must be a number of seconds",
+            )),
+        },
+        ("POST", "/volume") => match number("level") {
+            Some(level) if (0.0..=1.0).contains(&level) => Ok(Command::Volume(level)),
+            _ => Err(error(
+                "400 Bad Request",
+                "level must be between 0.0 and 1.0",
+            )),
+        },
+        ("POST", "/enqueue") => match request.query.get("path").filter(|v| !v.is_empty()) {
+            Some(path) => Ok(Command::Enqueue(PathBuf::from(path))),
+            None => Err(error("400 Bad Request", "path is required")),
+        },
+        _ => Err(error(
+            "404 Not Found",
+            "unknown endpoint (commands must be sent as POST requests)",
+        )),
+    }
+}
+
+/// Runs the request, and returns the response to send back.
+async fn respond(
+    request: Request,
+    access: &Access,
+    bridge: &ControllerBridge,
+    now_playing: &RwLock<NowPlaying>,
+    cx: &mut AsyncApp,
+) -> String {
+    if let Err(response) = access.check(&request) {
+        return response;
+    }
+
+    let command = match route(&request) {
+        Ok(command) => command,
+        Err(response) => return response,
+    };
+
+    match command {
+        Command::NowPlaying => return response("200 OK", now_playing.read().await.to_json()),
+        Command::Play => bridge.play(),
+        Command::Pause => bridge.pause(),
+        Command::Toggle => bridge.toggle_play_pause(),
+        Command::Stop => bridge.stop(),
+        Command::Next => bridge.next(),
+        Command::Previous => bridge.previous(),
+        Command::Restart => bridge.restart(),
+        Command::Seek(position) => bridge.seek(position),
+        Command::Volume(level) => bridge.set_volume(level),
+        Command::Enqueue(path) => match cx.update(|cx| QueueItemData::new(cx, path, None, None)) {
+            Ok(item) => bridge.queue(item),
+            Err(_) => return error("503 Service Unavailable", "MrChat is shutting down"),
+        },
+    }
+
+    response("200 OK", json!({ "ok": true }))
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    access: Arc<Access>,
+    bridge: ControllerBridge,
+    now_playing: Arc<RwLock<NowPlaying>>,
+    cx: &mut AsyncApp,
+) -> anyhow::Result<()> {
+    let request = smol::future::or(read_request(&mut stream), async {
+        Timer::after(REQUEST_TIMEOUT).await;
+        Err(anyhow::anyhow!("timed out waiting for the request"))
+    })
+    .await;
+
+    let response = match request {
+        Ok(request) => {
+            debug!(
+                "Control server request: {} {}",
+                request.method, request.path
+            );
+            respond(request, &access, &bridge, &now_playing, cx).await
+        }
+        Err(err) => {
+            debug!("Invalid control server request: {err}");
+            error("400 Bad Request", "invalid request")
+        }
+    };
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await?;
+
+    Ok(())
+}
+
+impl RemoteController {
+    /// Starts the control server. Fails if the address can't be listened on, for instance because
+    /// another program is already using the port, or if the token can't be read or created.
+    pub fn start(
+        bridge: ControllerBridge,
+        settings: &RemoteControlSettings,
+        cx: &mut App,
+    ) -> anyhow::Result<Arc<Mutex<dyn PlaybackController>>> {
+        let ip: IpAddr = settings
+            .address
+            .parse()
+            .with_context(|| format!("{:?} is not an IP address", settings.address))?;
+        let address = SocketAddr::new(ip, settings.port);
+        let access = Arc::new(Access::new(load_token(&data_dir().join(TOKEN_FILE))?, ip));
+        let listener = std::net::TcpListener::bind(address)
+            .with_context(|| format!("couldn't listen on {address}"))?;
+        let listener = TcpListener::try_from(listener)?;
+
+        info!("Control server listening on http://{address}");
+
+        let now_playing = Arc::new(RwLock::new(NowPlaying::default()));
+        let server_state = now_playing.clone();
+
+        cx.spawn(async move |cx| {
+            let mut incoming = listener.incoming();
+
+            while let Some(stream) = incoming.next().await {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        warn!("Control server couldn't accept a connection: {err}");
+                        continue;
+                    }
+                };
+
+                let access = access.clone();
+                let bridge = bridge.clone();
+                let now_playing = server_state.clone();
+
+                cx.spawn(async move |cx| {
+                    if let Err(err) =
+                        handle_connection(stream, access, bridge, now_playing, cx).await
+                    {
+                        debug!("Control server connection failed: {err}");
+                    }
+                })
+                .detach();
+            }
+        })
+        .detach();
+
+        Ok(Arc::new(Mutex::new(RemoteController { now_playing })))
+    }
+}
+
+#[async_trait]
+impl PlaybackController for RemoteController {
+    async fn position_changed(&mut self, new_position: u64) -> anyhow::Result<()> {
+        self.now_playing.write().await.position = new_position;
+        Ok(())
+    }
+
+    async fn duration_changed(&mut self, new_duration: u64) -> anyhow::Result<()> {
+        self.now_playing.write().await.duration = new_duration;
+        Ok(())
+    }
+
+    async fn volume_changed(&mut self, new_volume: f64) -> anyhow::Result<()> {
+        self.now_playing.write().await.volume = Some(new_volume);
+        Ok(())
+    }
+
+    async fn metadata_changed(&mut self, metadata: &Metadata) -> anyhow::Result<()> {
+        self.now_playing.write().await.metadata = Some(metadata.clone());
+        Ok(())
+    }
+
+    async fn album_art_changed(&mut self, _: &[u8]) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn repeat_state_changed(&mut self, repeat_state: RepeatState) -> anyhow::Result<()> {
+        self.now_playing.write().await.repeat = Some(repeat_state);
+        Ok(())
+    }
+
+    async fn playback_state_changed(
+        &mut self,
+        playback_state: PlaybackState,
+    ) -> anyhow::Result<()> {
+        self.now_playing.write().await.set_state(playback_state);
+        Ok(())
+    }
+
+    async fn shuffle_state_changed(&mut self, shuffling: bool) -> anyhow::Result<()> {
+        self.now_playing.write().await.shuffle = shuffling;
+        Ok(())
+    }
+
+    async fn new_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.now_playing.write().await.new_file(path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    fn parse(raw: &str) -> anyhow::Result<Request> {
+        smol::block_on(read_request(&mut raw.as_bytes()))
+    }
+
+    fn request(method: &str, target: &str, headers: &[(&str, &str)]) -> Request {
+        let headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}\r\n"))
+            .collect();
+
+        parse(&format!("{method} {target} HTTP/1.1\r\n{headers}\r\n")).unwrap()
+    }
+
+    fn local(method: &str, target: &str) -> Request {
+        request(
+            method,
+            target,
+            &[("Host", "127.0.0.1:47630"), ("X-MrChat-Token", TOKEN)],
+        )
+    }
+
+    fn status(response: String) -> String {
+        response.lines().next().unwrap_or_default().to_string()
+    }
+
+    fn access() -> Access {
+        Access::new(TOKEN.to_string(), "127.0.0.1".parse().unwrap())
+    }
+
+    #[test]
+    fn requests_are_parsed() {
+        let request = parse(
+            "POST /seek?position=12.5 HTTP/1.1\r\nHost: localhost:47630\r\n\
+            X-MrChat-Token:  abc \r\n\r\nignored body",
+        )
+        .unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/seek");
+        assert_eq!(request.query.get("position").unwrap(), "12.5");
+        assert_eq!(request.headers.get("host").unwrap(), "localhost:47630");
+        assert_eq!(request.headers.get("x-mrchat-token").unwrap(), "abc");
+    }
+
+    #[test]
+    fn incomplete_requests_are_rejected() {
+        assert!(parse("GET /now-playing HTTP/1.1\r\nHost: localhost\r\n").is_err());
+        assert!(parse("\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn oversized_requests_are_rejected() {
+        let raw = format!(
+            "GET / HTTP/1.1\r\nX-Padding: {}\r\n\r\n",
+            "a".repeat(10_000)
+        );
+
+        assert!(parse(&raw).is_err());
+    }
+
+    #[test]
+    fn commands_are_routed() {
+        assert_eq!(
+            route(&local("GET", "/now-playing")),
+            Ok(Command::NowPlaying)
+        );
+        assert_eq!(route(&local("POST", "/toggle")), Ok(Command::Toggle));
+        assert_eq!(route(&local("POST", "/restart")), Ok(Command::Restart));
+        assert_eq!(
+            route(&local("POST", "/seek?position=30")),
+            Ok(Command::Seek(30.0))
+        );
+        assert_eq!(
+            route(&local("POST", "/volume?level=0.5")),
+            Ok(Command::Volume(0.5))
+        );
+        assert_eq!(
+            route(&local("POST", "/enqueue?path=%2Fmusic%2Ftrack.flac")),
+            Ok(Command::Enqueue(PathBuf::from("/music/track.flac")))
+        );
+    }
+
+    #[test]
+    fn commands_must_be_posted() {
+        let response = route(&local("GET", "/play")).unwrap_err();
+
+        assert_eq!(status(response), "HTTP/1.1 404 Not Found");
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        for target in [
+            "/seek",
+            "/seek?position=-1",
+            "/volume?level=1.5",
+            "/volume?level=loud",
+            "/enqueue?path=",
+        ] {
+            let response = route(&local("POST", target)).unwrap_err();
+
+            assert_eq!(status(response), "HTTP/1.1 400 Bad Request", "{target}");
+        }
+    }
+
+    #[test]
+    fn local_requests_with_the_token_are_allowed() {
+        let access = access();
+
+        assert!(access.check(&local("POST", "/play")).is_ok());
+        assert!(
+            access
+                .check(&request(
+                    "POST",
+                    "/play",
+                    &[
+                        ("Host", "[::1]:47630"),
+                        ("Origin", "http://localhost:3000"),
+                        ("X-MrChat-Token", TOKEN),
+                    ],
+                ))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn requests_without_the_token_are_rejected() {
+        let access = access();
+
+        for headers in [
+            &[("Host", "127.0.0.1:47630")][..],
+            &[("Host", "127.0.0.1:47630"), ("X-MrChat-Token", "wrong")][..],
+        ] {
+            let response = access
+                .check(&request("POST", "/play", headers))
+                .unwrap_err();
+
+            assert_eq!(status(response), "HTTP/1.1 401 Unauthorized");
+        }
+    }
+
+    #[test]
+    fn requests_for_other_hosts_are_rejected() {
+        let access = access();
+
+        for headers in [
+            &[("X-MrChat-Token", TOKEN)][..],
+            &[("Host", "evil.example:47630"), ("X-MrChat-Token", TOKEN)][..],
+            &[
+                ("Host", "127.0.0.1:47630"),
+                ("Origin", "https://evil.example"),
+                ("X-MrChat-Token", TOKEN),
+            ][..],
+        ] {
+            let response = access
+                .check(&request("POST", "/play", headers))
+                .unwrap_err();
+
+            assert_eq!(status(response), "HTTP/1.1 403 Forbidden");
+        }
+    }
+
+    #[test]
+    fn the_configured_address_is_allowed() {
+        let access = Access::new(TOKEN.to_string(), "192.168.1.20".parse().unwrap());
+        let request = request(
+            "POST",
+            "/play",
+            &[("Host", "192.168.1.20:47630"), ("X-MrChat-Token", TOKEN)],
+        );
+
+        assert!(access.check(&request).is_ok());
+    }
+
+    #[test]
+    fn the_token_is_created_once_and_kept_private() {
+        let path = std::env::temp_dir().join(format!("mrchat-remote-token-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let token = load_token(&path).unwrap();
+        let reloaded = load_token(&path).unwrap();
+
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::metadata(&path).unwrap().permissions().mode()
+        };
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(token.len(), 32);
+        assert_eq!(reloaded, token);
+        #[cfg(unix)]
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
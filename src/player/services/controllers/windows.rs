@@ -38,6 +38,7 @@ impl WindowsController {
         self.controls.SetIsPreviousEnabled(true)?;
         self.controls.SetIsPlayEnabled(true)?;
         self.controls.SetIsPauseEnabled(true)?;
+        self.controls.SetIsRewindEnabled(true)?;
 
         let bridge = self.bridge.clone();
         self.controls.ButtonPressed(&TypedEventHandler::<
@@ -51,6 +52,7 @@ impl WindowsController {
                 SystemMediaTransportControlsButton::Pause => bridge.pause(),
                 SystemMediaTransportControlsButton::Next => bridge.next(),
                 SystemMediaTransportControlsButton::Previous => bridge.previous(),
+                SystemMediaTransportControlsButton::Rewind => bridge.restart(),
                 _ => (),
             }
 
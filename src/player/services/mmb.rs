@@ -1,9 +1,12 @@
 pub mod lastfm;
+pub mod listenbrainz;
+pub mod scrobble;
 
 use std::{path::PathBuf, sync::Arc};
 
 use crate::{player::media::metadata::Metadata, player::playback::thread::PlaybackState};
 use async_trait::async_trait;
+use scrobble::ScrobbleThreshold;
 
 /// MediaMetadataBroadcastService is a trait that can be implemented by services that wish to
 /// display information about the currently playing track. When the currently playing track
@@ -32,4 +35,8 @@ pub trait MediaMetadataBroadcastService {
     /// Called when the duration of the currently playing track changes, or when a new track is
     /// played. Time is in seconds.
     async fn duration_changed(&mut self, duration: u64);
+    /// Called when the user adds a track to or removes a track from their favorites.
+    async fn favorite_changed(&mut self, _artist: String, _track: String, _favorite: bool) {}
+    /// Called when the user changes how much of a track has to be played before it's scrobbled.
+    async fn scrobble_threshold_changed(&mut self, _threshold: ScrobbleThreshold) {}
 }
@@ -1,4 +1,4 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{collections::VecDeque, path::PathBuf, sync::Arc};
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
@@ -8,7 +8,10 @@ use tracing::{debug, warn};
 
 use crate::{player::media::metadata::Metadata, player::playback::thread::PlaybackState};
 
-use super::MediaMetadataBroadcastService;
+use super::{
+    MediaMetadataBroadcastService,
+    scrobble::{ListenTracker, ScrobbleThreshold},
+};
 
 pub mod client;
 mod requests;
@@ -18,26 +21,41 @@ mod util;
 pub const LASTFM_API_KEY: Option<&'static str> = option_env!("LASTFM_API_KEY");
 pub const LASTFM_API_SECRET: Option<&'static str> = option_env!("LASTFM_API_SECRET");
 
+/// The most requests that are kept around to be retried while Last.fm can't be reached. Once
+/// full, the oldest requests are dropped.
+const MAX_PENDING_REQUESTS: usize = 1000;
+
+/// A request that should be retried if it couldn't be sent.
+enum PendingRequest {
+    Scrobble {
+        artist: String,
+        track: String,
+        timestamp: DateTime<Utc>,
+        album: Option<String>,
+    },
+    Love {
+        artist: String,
+        track: String,
+        love: bool,
+    },
+}
+
 pub struct LastFM {
     client: LastFMClient,
-    start_timestamp: Option<DateTime<Utc>>,
-    accumulated_time: u64,
-    duration: u64,
+    tracker: ListenTracker,
     metadata: Option<Arc<Metadata>>,
-    last_postion: u64,
     should_scrobble: bool,
+    pending: VecDeque<PendingRequest>,
 }
 
 impl LastFM {
-    pub fn new(client: LastFMClient) -> Self {
+    pub fn new(client: LastFMClient, threshold: ScrobbleThreshold) -> Self {
         LastFM {
             client,
-            start_timestamp: None,
-            accumulated_time: 0,
+            tracker: ListenTracker::new(threshold),
             metadata: None,
-            duration: 0,
-            last_postion: 0,
             should_scrobble: false,
+            pending: VecDeque::new(),
         }
     }
 
@@ -48,18 +66,69 @@ impl LastFM {
         let (Some(artist), Some(track)) = (info.artist.clone(), info.name.clone()) else {
             return;
         };
-        if let Err(e) = self
-            .client
-            .scrobble(
+        let Some(timestamp) = self.tracker.start_timestamp() else {
+            return;
+        };
+
+        self.submit(PendingRequest::Scrobble {
+            artist,
+            track,
+            timestamp,
+            album: info.album.clone(),
+        })
+        .await;
+    }
+
+    async fn send(&mut self, request: &PendingRequest) -> anyhow::Result<()> {
+        match request {
+            PendingRequest::Scrobble {
                 artist,
                 track,
-                self.start_timestamp.unwrap(),
-                info.album.clone(),
-                None,
-            )
-            .await
-        {
-            warn!("Could not scrobble: {}", e)
+                timestamp,
+                album,
+            } => {
+                self.client
+                    .scrobble(
+                        artist.clone(),
+                        track.clone(),
+                        *timestamp,
+                        album.clone(),
+                        None,
+                    )
+                    .await
+            }
+            PendingRequest::Love {
+                artist,
+                track,
+                love,
+            } => self.client.love(artist.clone(), track.clone(), *love).await,
+        }
+    }
+
+    /// Sends a request after any requests that previously failed. If Last.fm can't be reached,
+    /// the request is queued and retried the next time a request is made.
+    async fn submit(&mut self, request: PendingRequest) {
+        if self.pending.len() >= MAX_PENDING_REQUESTS {
+            warn!("Too many Last.fm requests are waiting to be sent, dropping the oldest");
+            self.pending.pop_front();
+        }
+
+        self.pending.push_back(request);
+
+        while let Some(request) = self.pending.pop_front() {
+            if let Err(e) = self.send(&request).await {
+                // only network errors are worth retrying, anything else (such as Last.fm
+                // rejecting the request) will fail again
+                if e.downcast_ref::<isahc::Error>().is_some()
+                    || e.downcast_ref::<std::io::Error>().is_some()
+                {
+                    warn!("Could not reach Last.fm, will retry later: {}", e);
+                    self.pending.push_front(request);
+                    break;
+                }
+
+                warn!("Last.fm request failed: {}", e);
+            }
         }
     }
 }
@@ -72,9 +141,7 @@ impl MediaMetadataBroadcastService for LastFM {
             self.scrobble().await;
         }
 
-        self.start_timestamp = Some(chrono::offset::Utc::now());
-        self.accumulated_time = 0;
-        self.last_postion = 0;
+        self.tracker.reset();
         self.should_scrobble = false;
     }
 
@@ -102,23 +169,26 @@ impl MediaMetadataBroadcastService for LastFM {
     }
 
     async fn position_changed(&mut self, position: u64) {
-        if position < self.last_postion + 2 && position > self.last_postion {
-            self.accumulated_time += position - self.last_postion;
-        }
-
-        self.last_postion = position;
-
-        if self.duration >= 30
-            && (self.accumulated_time > self.duration / 2 || self.accumulated_time > 240)
-            && !self.should_scrobble
-            && self.metadata.is_some()
-        {
+        if self.tracker.position_changed(position) && self.metadata.is_some() {
             self.should_scrobble = true;
         }
     }
 
     async fn duration_changed(&mut self, duration: u64) {
-        self.duration = duration;
+        self.tracker.set_duration(duration);
+    }
+
+    async fn favorite_changed(&mut self, artist: String, track: String, favorite: bool) {
+        self.submit(PendingRequest::Love {
+            artist,
+            track,
+            love: favorite,
+        })
+        .await;
+    }
+
+    async fn scrobble_threshold_changed(&mut self, threshold: ScrobbleThreshold) {
+        self.tracker.set_threshold(threshold);
     }
 }
 
@@ -47,6 +47,17 @@ impl LastFMClient {
         Ok(session.session)
     }
 
+    /// Starts a signed request for a method that requires the user to be signed in.
+    fn session_request(&self, method: &str) -> anyhow::Result<LFMRequestBuilder> {
+        let Some(session) = self.auth_session.clone() else {
+            return Err(anyhow::Error::msg("not logged in"));
+        };
+
+        Ok(LFMRequestBuilder::new(self.api_key.clone())
+            .add_param("method", method.to_string())
+            .add_param("sk", session))
+    }
+
     pub async fn scrobble(
         &mut self,
         artist: String,
@@ -55,17 +66,12 @@ impl LastFMClient {
         album: Option<String>,
         duration: Option<u64>,
     ) -> anyhow::Result<()> {
-        let Some(session) = self.auth_session.clone() else {
-            return Err(anyhow::Error::msg("not logged in"));
-        };
-        LFMRequestBuilder::new(self.api_key.clone())
-            .add_param("method", "track.scrobble".to_string())
+        self.session_request("track.scrobble")?
             .add_param("artist[0]", artist)
             .add_param("track[0]", track)
             .add_param("timestamp[0]", timestamp.timestamp().to_string())
             .add_optional_param("album[0]", album)
             .add_optional_param("duration[0]", duration.map(|a| u64::to_string(&a)))
-            .add_param("sk", session)
             .write()
             .sign(self.api_secret)
             .send_write_request_ns()
@@ -81,16 +87,24 @@ impl LastFMClient {
         album: Option<String>,
         duration: Option<u64>,
     ) -> anyhow::Result<()> {
-        let Some(session) = self.auth_session.clone() else {
-            return Err(anyhow::Error::msg("not logged in"));
-        };
-        LFMRequestBuilder::new(self.api_key.clone())
-            .add_param("method", "track.updateNowPlaying".to_string())
+        self.session_request("track.updateNowPlaying")?
             .add_param("artist", artist)
             .add_param("track", track)
             .add_optional_param("album", album)
             .add_optional_param("duration", duration.map(|a| u64::to_string(&a)))
-            .add_param("sk", session)
+            .write()
+            .sign(self.api_secret)
+            .send_write_request_ns()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Loves (or, if `love` is false, unloves) a track.
+    pub async fn love(&mut self, artist: String, track: String, love: bool) -> anyhow::Result<()> {
+        self.session_request(if love { "track.love" } else { "track.unlove" })?
+            .add_param("artist", artist)
+            .add_param("track", track)
             .write()
             .sign(self.api_secret)
             .send_write_request_ns()
@@ -13,6 +13,8 @@ pub struct GetSession {
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Session {
     pub name: String,
+    /// Empty when read from `lastfm.json`, if the key is kept in the credential store instead.
+    #[serde(default)]
     pub key: String,
     pub subscriber: i8,
 }
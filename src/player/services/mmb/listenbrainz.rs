@@ -0,0 +1,218 @@
+use std::{path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use isahc::{AsyncReadResponseExt, http::StatusCode};
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use crate::{player::media::metadata::Metadata, player::playback::thread::PlaybackState};
+
+use super::{
+    MediaMetadataBroadcastService,
+    scrobble::{ListenTracker, ScrobbleThreshold},
+};
+
+const SUBMIT_LISTENS_URL: &str = "https://api.listenbrainz.org/1/submit-listens";
+
+/// The most listens that are kept around to be retried while ListenBrainz can't be reached. This
+/// is also the most listens ListenBrainz accepts in a single request.
+const MAX_PENDING_LISTENS: usize = 1000;
+
+#[derive(Serialize, Clone)]
+struct TrackMetadata {
+    artist_name: String,
+    track_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    release_name: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct Listen {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    listened_at: Option<i64>,
+    track_metadata: TrackMetadata,
+}
+
+#[derive(Serialize)]
+struct Submission<'a> {
+    listen_type: &'static str,
+    payload: &'a [Listen],
+}
+
+/// Why a submission failed.
+enum SubmitError {
+    /// ListenBrainz couldn't be reached, or asked us to try again later.
+    Unavailable(anyhow::Error),
+    /// ListenBrainz rejected the submission. Sending it again won't help.
+    Rejected(anyhow::Error),
+}
+
+/// Submits listens to ListenBrainz, authenticated by the user's token.
+pub struct ListenBrainz {
+    token: String,
+    tracker: ListenTracker,
+    metadata: Option<Arc<Metadata>>,
+    should_submit: bool,
+    pending: Vec<Listen>,
+}
+
+impl ListenBrainz {
+    pub fn new(token: String, threshold: ScrobbleThreshold) -> Self {
+        ListenBrainz {
+            token,
+            tracker: ListenTracker::new(threshold),
+            metadata: None,
+            should_submit: false,
+            pending: Vec::new(),
+        }
+    }
+
+    fn track_metadata(&self) -> Option<TrackMetadata> {
+        let info = self.metadata.as_ref()?;
+
+        Some(TrackMetadata {
+            artist_name: info.artist.clone()?,
+            track_name: info.name.clone()?,
+            release_name: info.album.clone(),
+        })
+    }
+
+    async fn send(&self, listen_type: &'static str, listens: &[Listen]) -> Result<(), SubmitError> {
+        let body = serde_json::to_vec(&Submission {
+            listen_type,
+            payload: listens,
+        })
+        .map_err(|e| SubmitError::Rejected(e.into()))?;
+
+        let request = isahc::http::Request::builder()
+            .method(isahc::http::Method::POST)
+            .uri(SUBMIT_LISTENS_URL)
+            .header("content-type", "application/json")
+            .header("authorization", format!("Token {}", self.token))
+            .body(body)
+            .map_err(|e| SubmitError::Rejected(e.into()))?;
+
+        let mut response = isahc::send_async(request)
+            .await
+            .map_err(|e| SubmitError::Unavailable(e.into()))?;
+        let status = response.status();
+
+        if status.is_success() {
+            return Ok(());
+        }
+
+        let error = anyhow::anyhow!(
+            "ListenBrainz returned {}: {}",
+            status,
+            response.text().await.unwrap_or_default()
+        );
+
+        if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            Err(SubmitError::Unavailable(error))
+        } else {
+            Err(SubmitError::Rejected(error))
+        }
+    }
+
+    /// Submits the current track as a listen, along with any listens that couldn't be submitted
+    /// earlier. If ListenBrainz can't be reached, the listens are kept and submitted once it can
+    /// be reached again.
+    async fn submit(&mut self) {
+        let (Some(track_metadata), Some(timestamp)) =
+            (self.track_metadata(), self.tracker.start_timestamp())
+        else {
+            return;
+        };
+
+        if self.pending.len() >= MAX_PENDING_LISTENS {
+            warn!("Too many listens are waiting to be submitted, dropping the oldest");
+            self.pending.remove(0);
+        }
+
+        self.pending.push(Listen {
+            listened_at: Some(timestamp.timestamp()),
+            track_metadata,
+        });
+
+        self.flush().await;
+    }
+
+    /// Submits every listen that hasn't been submitted yet.
+    async fn flush(&mut self) {
+        let listen_type = if self.pending.len() == 1 {
+            "single"
+        } else {
+            "import"
+        };
+
+        match self.send(listen_type, &self.pending).await {
+            Ok(()) => self.pending.clear(),
+            Err(SubmitError::Unavailable(e)) => {
+                warn!("Could not reach ListenBrainz, will retry later: {}", e);
+            }
+            Err(SubmitError::Rejected(e)) => {
+                warn!("ListenBrainz rejected listens: {}", e);
+                self.pending.clear();
+            }
+        }
+    }
+
+    async fn playing_now(&mut self) {
+        let Some(track_metadata) = self.track_metadata() else {
+            return;
+        };
+        let listen = Listen {
+            listened_at: None,
+            track_metadata,
+        };
+
+        match self.send("playing_now", &[listen]).await {
+            // ListenBrainz can be reached again, so submit anything that failed earlier
+            Ok(()) if !self.pending.is_empty() => self.flush().await,
+            Ok(()) => (),
+            Err(SubmitError::Unavailable(e) | SubmitError::Rejected(e)) => {
+                warn!("Could not set playing now: {}", e);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl MediaMetadataBroadcastService for ListenBrainz {
+    async fn new_track(&mut self, _: PathBuf) {
+        if self.should_submit {
+            debug!("attempting listen submission");
+            self.submit().await;
+        }
+
+        self.tracker.reset();
+        self.should_submit = false;
+    }
+
+    async fn metadata_recieved(&mut self, info: Arc<Metadata>) {
+        self.metadata = Some(info);
+        self.playing_now().await;
+    }
+
+    async fn state_changed(&mut self, state: PlaybackState) {
+        if self.should_submit && state != PlaybackState::Playing {
+            debug!("attempting listen submission");
+            self.submit().await;
+            self.should_submit = false;
+        }
+    }
+
+    async fn position_changed(&mut self, position: u64) {
+        if self.tracker.position_changed(position) && self.metadata.is_some() {
+            self.should_submit = true;
+        }
+    }
+
+    async fn duration_changed(&mut self, duration: u64) {
+        self.tracker.set_duration(duration);
+    }
+
+    async fn scrobble_threshold_changed(&mut self, threshold: ScrobbleThreshold) {
+        self.tracker.set_threshold(threshold);
+    }
+}
@@ -0,0 +1,99 @@
+use chrono::{DateTime, Utc};
+
+use crate::shared::settings::scrobbling::ScrobblingSettings;
+
+/// Tracks shorter than this are never scrobbled.
+const MIN_SCROBBLE_DURATION: u64 = 30;
+
+/// How much of a track has to be played before it counts as listened to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScrobbleThreshold {
+    pub percent: u8,
+    pub seconds: u64,
+}
+
+impl From<&ScrobblingSettings> for ScrobbleThreshold {
+    fn from(settings: &ScrobblingSettings) -> Self {
+        ScrobbleThreshold {
+            percent: settings.threshold_percent.min(100),
+            seconds: settings.threshold_seconds,
+        }
+    }
+}
+
+impl Default for ScrobbleThreshold {
+    fn default() -> Self {
+        (&ScrobblingSettings::default()).into()
+    }
+}
+
+/// Keeps track of how long the current track has actually been listened to, so that scrobbling
+/// services agree on when a track should be scrobbled. Seeking doesn't count towards the time
+/// listened.
+pub struct ListenTracker {
+    threshold: ScrobbleThreshold,
+    start_timestamp: Option<DateTime<Utc>>,
+    accumulated_time: u64,
+    duration: u64,
+    last_position: u64,
+    reached: bool,
+}
+
+impl ListenTracker {
+    pub fn new(threshold: ScrobbleThreshold) -> Self {
+        ListenTracker {
+            threshold,
+            start_timestamp: None,
+            accumulated_time: 0,
+            duration: 0,
+            last_position: 0,
+            reached: false,
+        }
+    }
+
+    /// Starts tracking a new track.
+    pub fn reset(&mut self) {
+        self.start_timestamp = Some(Utc::now());
+        self.accumulated_time = 0;
+        self.last_position = 0;
+        self.reached = false;
+    }
+
+    /// The time the current track started playing.
+    pub fn start_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.start_timestamp
+    }
+
+    pub fn set_duration(&mut self, duration: u64) {
+        self.duration = duration;
+    }
+
+    pub fn set_threshold(&mut self, threshold: ScrobbleThreshold) {
+        self.threshold = threshold;
+    }
+
+    /// Records a position update. Returns true the first time the current track passes the
+    /// scrobble threshold.
+    pub fn position_changed(&mut self, position: u64) -> bool {
+        if position < self.last_position + 2 && position > self.last_position {
+            self.accumulated_time += position - self.last_position;
+        }
+
+        self.last_position = position;
+
+        if self.reached || self.duration < MIN_SCROBBLE_DURATION {
+            return false;
+        }
+
+        let percent_played = self.accumulated_time * 100 / self.duration;
+
+        if percent_played >= self.threshold.percent as u64
+            || self.accumulated_time >= self.threshold.seconds
+        {
+            self.reached = true;
+            return true;
+        }
+
+        false
+    }
+}
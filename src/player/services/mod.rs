@@ -1,2 +1,4 @@
 pub mod controllers;
 pub mod mmb;
+pub mod musicbrainz;
+pub mod tray;
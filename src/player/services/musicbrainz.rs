@@ -0,0 +1,265 @@
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use async_lock::Mutex;
+use chrono::{DateTime, NaiveDate, Utc};
+use isahc::{
+    AsyncReadResponseExt,
+    config::{Configurable, RedirectPolicy},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::shared::cache::CacheCategory;
+
+const SEARCH_RECORDINGS_URL: &str = "https://musicbrainz.org/ws/2/recording";
+const COVER_ART_URL: &str = "https://coverartarchive.org/release";
+
+/// MusicBrainz asks that clients identify themselves, and rejects requests that don't.
+const USER_AGENT: &str = concat!(
+    "MrChat/",
+    env!("CARGO_PKG_VERSION"),
+    " ( https://github.com/v1cc0/MrChat )"
+);
+
+/// MusicBrainz allows an average of one request per second.
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(1100);
+
+/// The most candidates returned from a single lookup.
+const MAX_CANDIDATES: usize = 25;
+
+/// The time the last request was sent, shared by every lookup so they stay under the rate limit.
+static LAST_REQUEST: Mutex<Option<Instant>> = Mutex::new(None);
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    recordings: Vec<Recording>,
+}
+
+#[derive(Deserialize)]
+struct Recording {
+    title: String,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+    #[serde(default)]
+    releases: Vec<Release>,
+}
+
+#[derive(Deserialize)]
+struct ArtistCredit {
+    name: String,
+    #[serde(default)]
+    joinphrase: String,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    id: String,
+    title: String,
+    date: Option<String>,
+    #[serde(default)]
+    media: Vec<Medium>,
+}
+
+#[derive(Deserialize)]
+struct Medium {
+    #[serde(default)]
+    track: Vec<MediumTrack>,
+}
+
+#[derive(Deserialize)]
+struct MediumTrack {
+    number: String,
+}
+
+/// A release a recording appears on, as suggested by MusicBrainz.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReleaseCandidate {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub album_mbid: String,
+    pub date: Option<DateTime<Utc>>,
+    pub track_number: Option<i32>,
+}
+
+/// Parses a MusicBrainz date, which may be just a year or a year and month.
+fn parse_date(date: &str) -> Option<DateTime<Utc>> {
+    let mut parts = date.split('-').map(|v| v.parse::<u32>().ok());
+    let year = parts.next()??;
+    let month = parts.next().flatten().unwrap_or(1);
+    let day = parts.next().flatten().unwrap_or(1);
+
+    NaiveDate::from_ymd_opt(year as i32, month, day)?
+        .and_hms_opt(0, 0, 0)
+        .map(|v| v.and_utc())
+}
+
+/// Quotes a value for use in a search query.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Waits until another request can be sent without exceeding the rate limit.
+async fn wait_for_rate_limit() {
+    let mut last = LAST_REQUEST.lock().await;
+
+    if let Some(elapsed) = last.map(|v| v.elapsed()) {
+        if elapsed < MIN_REQUEST_INTERVAL {
+            smol::Timer::after(MIN_REQUEST_INTERVAL - elapsed).await;
+        }
+    }
+
+    *last = Some(Instant::now());
+}
+
+/// Searches MusicBrainz for releases containing a recording with the given title, optionally
+/// narrowed down by artist and album.
+pub async fn lookup_recording(
+    title: &str,
+    artist: Option<&str>,
+    album: Option<&str>,
+) -> anyhow::Result<Vec<ReleaseCandidate>> {
+    let mut query = format!("recording:{}", quote(title));
+
+    if let Some(artist) = artist.filter(|v| !v.is_empty()) {
+        query.push_str(&format!(" AND artist:{}", quote(artist)));
+    }
+
+    if let Some(album) = album.filter(|v| !v.is_empty()) {
+        query.push_str(&format!(" AND release:{}", quote(album)));
+    }
+
+    let url = format!(
+        "{}?fmt=json&limit=10&query={}",
+        SEARCH_RECORDINGS_URL,
+        urlencoding::encode(&query)
+    );
+
+    wait_for_rate_limit().await;
+
+    let request = isahc::http::Request::get(url)
+        .header("user-agent", USER_AGENT)
+        .header("accept", "application/json")
+        .timeout(Duration::from_secs(20))
+        .body(())
+        .context("failed to build MusicBrainz request")?;
+
+    let mut response = isahc::send_async(request)
+        .await
+        .context("failed to reach MusicBrainz")?;
+
+    if !response.status().is_success() {
+        anyhow::bail!("MusicBrainz returned {}", response.status());
+    }
+
+    let body = response
+        .text()
+        .await
+        .context("failed to read MusicBrainz response")?;
+    let parsed: SearchResponse =
+        serde_json::from_str(&body).context("failed to parse MusicBrainz response")?;
+
+    let mut candidates: Vec<ReleaseCandidate> = Vec::new();
+
+    for recording in parsed.recordings {
+        let artist: String = recording
+            .artist_credit
+            .iter()
+            .map(|v| format!("{}{}", v.name, v.joinphrase))
+            .collect();
+
+        for release in recording.releases {
+            let candidate = ReleaseCandidate {
+                title: recording.title.clone(),
+                artist: artist.clone(),
+                album: release.title,
+                album_mbid: release.id,
+                date: release.date.as_deref().and_then(parse_date),
+                track_number: release
+                    .media
+                    .first()
+                    .and_then(|v| v.track.first())
+                    .and_then(|v| v.number.parse().ok()),
+            };
+
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+    }
+
+    candidates.truncate(MAX_CANDIDATES);
+
+    Ok(candidates)
+}
+
+/// Returns true if `mbid` looks like a MusicBrainz identifier. Albums without one are stored with
+/// the placeholder "none".
+pub fn is_valid_mbid(mbid: &str) -> bool {
+    mbid.len() == 36 && mbid.chars().all(|c| c.is_ascii_hexdigit() || c == '-')
+}
+
+/// The file that records that a release has no usable cover art.
+fn unavailable_marker(release_mbid: &str) -> PathBuf {
+    CacheCategory::Artwork
+        .dir()
+        .join(format!("{release_mbid}.unavailable"))
+}
+
+/// Returns true if an earlier attempt to fetch cover art for the release found nothing usable.
+/// Clearing the cache allows these releases to be tried again.
+pub fn cover_art_unavailable(release_mbid: &str) -> bool {
+    unavailable_marker(release_mbid).exists()
+}
+
+/// Remembers that the release has no usable cover art, so it isn't fetched again.
+pub fn mark_cover_art_unavailable(release_mbid: &str) {
+    let path = unavailable_marker(release_mbid);
+
+    if let Err(e) = fs::write(&path, []) {
+        warn!("couldn't record missing cover art at {:?}: {:?}", path, e);
+    }
+}
+
+/// Downloads the front cover of a release from the Cover Art Archive. Returns `None` if the
+/// release has no front cover.
+pub async fn fetch_cover_art(release_mbid: &str) -> anyhow::Result<Option<Vec<u8>>> {
+    if !is_valid_mbid(release_mbid) {
+        anyhow::bail!("{:?} is not a MusicBrainz identifier", release_mbid);
+    }
+
+    let url = format!("{}/{}/front-1200", COVER_ART_URL, release_mbid);
+
+    // the Cover Art Archive is run alongside MusicBrainz, so share its rate limit
+    wait_for_rate_limit().await;
+
+    let request = isahc::http::Request::get(url)
+        .header("user-agent", USER_AGENT)
+        .redirect_policy(RedirectPolicy::Follow)
+        .timeout(Duration::from_secs(30))
+        .body(())
+        .context("failed to build Cover Art Archive request")?;
+
+    let mut response = isahc::send_async(request)
+        .await
+        .context("failed to reach the Cover Art Archive")?;
+
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    if !response.status().is_success() {
+        anyhow::bail!("the Cover Art Archive returned {}", response.status());
+    }
+
+    let bytes = response.bytes().await.context("failed to read cover art")?;
+
+    Ok(Some(bytes))
+}
@@ -0,0 +1,477 @@
+//! The tray icon shown while closing the window keeps MrChat playing. Its menu controls playback,
+//! brings the window back and quits.
+//!
+//! Windows and macOS use tray-icon, which hooks into the event loop GPUI already runs on the main
+//! thread. Linux uses ksni, which talks to the desktop's StatusNotifierItem host over D-Bus from
+//! its own thread, since tray-icon needs GTK there.
+
+use async_channel::Sender;
+use gpui::{App, AsyncApp, Global, Window};
+use tracing::{error, warn};
+
+use crate::{
+    player::media::metadata::Metadata,
+    player::playback::{interface::GPUIPlaybackInterface, thread::PlaybackState},
+    player::services::controllers::ControllerBridge,
+    player::ui::models::{Models, PlaybackInfo},
+    shared::settings::{SettingsGlobal, interface::CloseAction},
+};
+
+/// Something picked from the tray icon or its menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TrayCommand {
+    PlayPause,
+    Previous,
+    Next,
+    Show,
+    Quit,
+}
+
+/// What the tray icon shows about playback.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct TrayStatus {
+    playing: bool,
+    /// The title and artist of the current track, shown as the icon's tooltip.
+    track: Option<String>,
+}
+
+/// The tray icon, while closing the window keeps MrChat playing and the icon could be created.
+pub struct TrayHolder(Option<platform::Tray>);
+
+impl Global for TrayHolder {}
+
+/// Shows the tray icon whenever closing the window is set to keep MrChat playing, and keeps its
+/// menu in step with playback.
+pub fn setup_tray(cx: &mut App, window: &mut Window) {
+    let (commands_tx, commands_rx) = async_channel::unbounded();
+    let window_handle = window.window_handle();
+    let bridge = ControllerBridge::new(cx.global::<GPUIPlaybackInterface>().get_sender());
+
+    cx.spawn(async move |cx: &mut AsyncApp| {
+        while let Ok(command) = commands_rx.recv().await {
+            let handled = cx.update(|cx| match command {
+                TrayCommand::PlayPause => bridge.toggle_play_pause(),
+                TrayCommand::Previous => bridge.previous(),
+                TrayCommand::Next => bridge.next(),
+                TrayCommand::Show => {
+                    window_handle
+                        .update(cx, |_, window, cx| show_window(window, cx))
+                        .ok();
+                }
+                TrayCommand::Quit => cx.quit(),
+            });
+
+            if handled.is_err() {
+                break;
+            }
+        }
+    })
+    .detach();
+
+    cx.set_global(TrayHolder(None));
+    update_tray(cx, &commands_tx);
+
+    let settings = cx.global::<SettingsGlobal>().model.clone();
+    cx.observe(&settings, move |_, cx| update_tray(cx, &commands_tx))
+        .detach();
+
+    let state = cx.global::<PlaybackInfo>().playback_state.clone();
+    cx.observe(&state, |_, cx| update_status(cx)).detach();
+
+    let metadata = cx.global::<Models>().metadata.clone();
+    cx.observe(&metadata, |_, cx| update_status(cx)).detach();
+}
+
+/// Adds or removes the tray icon to match the close action setting.
+fn update_tray(cx: &mut App, commands: &Sender<TrayCommand>) {
+    let settings = cx.global::<SettingsGlobal>().model.read(cx);
+    let keep_playing = settings.interface.close_action == CloseAction::KeepPlaying;
+    let shown = cx.global::<TrayHolder>().0.is_some();
+
+    if keep_playing == shown {
+        return;
+    }
+
+    if !keep_playing {
+        cx.global_mut::<TrayHolder>().0 = None;
+        return;
+    }
+
+    let icon = match image::load_from_memory(include_bytes!("../../../res/icon/32x32@2x.png")) {
+        Ok(icon) => icon.to_rgba8(),
+        Err(err) => {
+            error!("Couldn't read the tray icon: {}", err);
+            return;
+        }
+    };
+
+    match platform::Tray::new(commands.clone(), icon) {
+        Ok(tray) => {
+            cx.global_mut::<TrayHolder>().0 = Some(tray);
+            update_status(cx);
+        }
+        Err(err) => {
+            warn!("Couldn't show the tray icon, closing the window will minimize it: {err:#}");
+        }
+    }
+}
+
+fn update_status(cx: &mut App) {
+    if cx.global::<TrayHolder>().0.is_none() {
+        return;
+    }
+
+    let playing = *cx.global::<PlaybackInfo>().playback_state.read(cx) == PlaybackState::Playing;
+    let track = describe(cx.global::<Models>().metadata.read(cx));
+
+    if let Some(tray) = &mut cx.global_mut::<TrayHolder>().0 {
+        tray.set_status(TrayStatus { playing, track });
+    }
+}
+
+/// The track's title and artist, or None if its title isn't known.
+fn describe(metadata: &Metadata) -> Option<String> {
+    match (&metadata.name, &metadata.artist) {
+        (Some(name), Some(artist)) => Some(format!("{name} — {artist}")),
+        (Some(name), None) => Some(name.clone()),
+        (None, _) => None,
+    }
+}
+
+/// Hides the window, leaving MrChat running. The tray icon brings it back. Without a tray icon,
+/// the window is minimized instead, so it can still be found in the taskbar. macOS hides the
+/// whole app, which comes back when its Dock icon is clicked.
+pub fn hide_window(window: &mut Window, cx: &mut App) {
+    let has_tray = cx
+        .try_global::<TrayHolder>()
+        .is_some_and(|tray| tray.0.is_some());
+
+    if cfg!(target_os = "macos") {
+        cx.hide();
+    } else if !has_tray || !platform::hide_window(window) {
+        window.minimize_window();
+    }
+}
+
+/// Brings back a window hidden by `hide_window`.
+fn show_window(window: &mut Window, cx: &mut App) {
+    if cfg!(target_os = "macos") {
+        cx.activate(true);
+    } else {
+        platform::show_window(window);
+        window.activate_window();
+    }
+}
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+mod platform {
+    use async_channel::Sender;
+    use gpui::Window;
+    use image::RgbaImage;
+    use tracing::warn;
+    use tray_icon::{
+        Icon, MouseButton, TrayIcon, TrayIconBuilder, TrayIconEvent,
+        menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem},
+    };
+
+    use crate::shared::i18n::t;
+
+    use super::{TrayCommand, TrayStatus};
+
+    pub struct Tray {
+        icon: TrayIcon,
+        play_pause: MenuItem,
+    }
+
+    impl Tray {
+        pub fn new(commands: Sender<TrayCommand>, icon: RgbaImage) -> anyhow::Result<Tray> {
+            let play_pause = MenuItem::new(t("tray.play"), true, None);
+            let previous = MenuItem::new(t("tray.previous"), true, None);
+            let next = MenuItem::new(t("tray.next"), true, None);
+            let show = MenuItem::new(t("tray.show"), true, None);
+            let quit = MenuItem::new(t("tray.quit"), true, None);
+
+            let menu = Menu::new();
+            menu.append_items(&[
+                &play_pause,
+                &previous,
+                &next,
+                &PredefinedMenuItem::separator(),
+                &show,
+                &quit,
+            ])?;
+
+            let items = [
+                (play_pause.id().clone(), TrayCommand::PlayPause),
+                (previous.id().clone(), TrayCommand::Previous),
+                (next.id().clone(), TrayCommand::Next),
+                (show.id().clone(), TrayCommand::Show),
+                (quit.id().clone(), TrayCommand::Quit),
+            ];
+
+            let menu_commands = commands.clone();
+            MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+                if let Some((_, command)) = items.iter().find(|(id, _)| *id == event.id) {
+                    menu_commands.try_send(*command).ok();
+                }
+            }));
+
+            TrayIconEvent::set_event_handler(Some(move |event: TrayIconEvent| {
+                if let TrayIconEvent::DoubleClick {
+                    button: MouseButton::Left,
+                    ..
+                } = event
+                {
+                    commands.try_send(TrayCommand::Show).ok();
+                }
+            }));
+
+            let (width, height) = icon.dimensions();
+            let icon = TrayIconBuilder::new()
+                .with_menu(Box::new(menu))
+                .with_tooltip("MrChat")
+                .with_icon(Icon::from_rgba(icon.into_raw(), width, height)?)
+                .build()?;
+
+            Ok(Tray { icon, play_pause })
+        }
+
+        pub fn set_status(&mut self, status: TrayStatus) {
+            self.play_pause.set_text(if status.playing {
+                t("tray.pause")
+            } else {
+                t("tray.play")
+            });
+
+            let tooltip = status.track.as_deref().unwrap_or("MrChat");
+            if let Err(err) = self.icon.set_tooltip(Some(tooltip)) {
+                warn!("Couldn't update the tray icon's tooltip: {}", err);
+            }
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    fn set_visible(window: &Window, visible: bool) -> bool {
+        use std::ffi::c_void;
+
+        use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+        use windows::Win32::{
+            Foundation::HWND,
+            UI::WindowsAndMessaging::{SW_HIDE, SW_SHOW, ShowWindow},
+        };
+
+        let Ok(handle) = HasWindowHandle::window_handle(window) else {
+            return false;
+        };
+        let RawWindowHandle::Win32(handle) = handle.as_raw() else {
+            return false;
+        };
+
+        let hwnd = HWND(handle.hwnd.get() as *mut c_void);
+        // SAFETY: the handle belongs to the open window. The result is whether the window was
+        // visible before, not whether this worked
+        let _ = unsafe { ShowWindow(hwnd, if visible { SW_SHOW } else { SW_HIDE }) };
+
+        true
+    }
+
+    /// Returns false if the window couldn't be hidden.
+    #[cfg(target_os = "windows")]
+    pub fn hide_window(window: &Window) -> bool {
+        set_visible(window, false)
+    }
+
+    #[cfg(target_os = "windows")]
+    pub fn show_window(window: &Window) {
+        set_visible(window, true);
+    }
+
+    // macOS hides the whole app instead
+    #[cfg(target_os = "macos")]
+    pub fn hide_window(_: &Window) -> bool {
+        false
+    }
+
+    #[cfg(target_os = "macos")]
+    pub fn show_window(_: &Window) {}
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use async_channel::Sender;
+    use gpui::Window;
+    use image::RgbaImage;
+    use ksni::{
+        Icon, MenuItem, ToolTip,
+        blocking::{Handle, TrayMethods},
+        menu::StandardItem,
+    };
+
+    use crate::shared::i18n::t;
+
+    use super::{TrayCommand, TrayStatus};
+
+    struct StatusNotifierItem {
+        commands: Sender<TrayCommand>,
+        icon: Icon,
+        status: TrayStatus,
+    }
+
+    impl StatusNotifierItem {
+        fn item(label: &str, command: TrayCommand) -> MenuItem<Self> {
+            StandardItem {
+                label: label.to_string(),
+                activate: Box::new(move |this: &mut Self| {
+                    this.commands.try_send(command).ok();
+                }),
+                ..Default::default()
+            }
+            .into()
+        }
+    }
+
+    impl ksni::Tray for StatusNotifierItem {
+        fn id(&self) -> String {
+            "org.v1cc0.mrchat".to_string()
+        }
+
+        fn title(&self) -> String {
+            "MrChat".to_string()
+        }
+
+        fn icon_pixmap(&self) -> Vec<Icon> {
+            vec![self.icon.clone()]
+        }
+
+        fn tool_tip(&self) -> ToolTip {
+            ToolTip {
+                title: "MrChat".to_string(),
+                description: self.status.track.clone().unwrap_or_default(),
+                ..Default::default()
+            }
+        }
+
+        fn activate(&mut self, _: i32, _: i32) {
+            self.commands.try_send(TrayCommand::Show).ok();
+        }
+
+        fn menu(&self) -> Vec<MenuItem<Self>> {
+            let play_pause = if self.status.playing {
+                t("tray.pause")
+            } else {
+                t("tray.play")
+            };
+
+            vec![
+                Self::item(play_pause, TrayCommand::PlayPause),
+                Self::item(t("tray.previous"), TrayCommand::Previous),
+                Self::item(t("tray.next"), TrayCommand::Next),
+                MenuItem::Separator,
+                Self::item(t("tray.show"), TrayCommand::Show),
+                Self::item(t("tray.quit"), TrayCommand::Quit),
+            ]
+        }
+    }
+
+    pub struct Tray {
+        handle: Handle<StatusNotifierItem>,
+    }
+
+    impl Tray {
+        pub fn new(commands: Sender<TrayCommand>, icon: RgbaImage) -> anyhow::Result<Tray> {
+            let (width, height) = icon.dimensions();
+            // StatusNotifierItem icons are ARGB, with each pixel in network byte order
+            let data = icon
+                .pixels()
+                .flat_map(|pixel| {
+                    let [r, g, b, a] = pixel.0;
+                    [a, r, g, b]
+                })
+                .collect();
+
+            let item = StatusNotifierItem {
+                commands,
+                icon: Icon {
+                    width: width as i32,
+                    height: height as i32,
+                    data,
+                },
+                status: TrayStatus::default(),
+            };
+
+            Ok(Tray {
+                handle: item.spawn()?,
+            })
+        }
+
+        pub fn set_status(&mut self, status: TrayStatus) {
+            self.handle.update(|item| item.status = status);
+        }
+    }
+
+    impl Drop for Tray {
+        fn drop(&mut self) {
+            let _ = self.handle.shutdown();
+        }
+    }
+
+    /// GPUI can't hide windows on Linux, so they're minimized instead.
+    pub fn hide_window(_: &Window) -> bool {
+        false
+    }
+
+    pub fn show_window(_: &Window) {}
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+mod platform {
+    use async_channel::Sender;
+    use gpui::Window;
+    use image::RgbaImage;
+
+    use super::{TrayCommand, TrayStatus};
+
+    pub struct Tray;
+
+    impl Tray {
+        pub fn new(_: Sender<TrayCommand>, _: RgbaImage) -> anyhow::Result<Tray> {
+            anyhow::bail!("tray icons aren't supported on this platform")
+        }
+
+        pub fn set_status(&mut self, _: TrayStatus) {}
+    }
+
+    pub fn hide_window(_: &Window) -> bool {
+        false
+    }
+
+    pub fn show_window(_: &Window) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(name: Option<&str>, artist: Option<&str>) -> Metadata {
+        Metadata {
+            name: name.map(str::to_string),
+            artist: artist.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tracks_are_described_by_title_and_artist() {
+        let track = describe(&metadata(Some("Aja"), Some("Steely Dan")));
+        assert_eq!(track.as_deref(), Some("Aja — Steely Dan"));
+
+        let track = describe(&metadata(Some("Aja"), None));
+        assert_eq!(track.as_deref(), Some("Aja"));
+    }
+
+    #[test]
+    fn tracks_without_a_title_arent_described() {
+        assert_eq!(describe(&metadata(None, Some("Steely Dan"))), None);
+        assert_eq!(describe(&Metadata::default()), None);
+    }
+}
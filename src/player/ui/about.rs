@@ -5,6 +5,9 @@ use gpui::{
 
 use super::{
     components::modal::{OnExitHandler, modal},
+    diagnostics::{copy_diagnostics, diagnostics_lines},
+    global_actions::ClearCache,
+    models::Models,
     theme::Theme,
 };
 
@@ -22,6 +25,7 @@ impl RenderOnce for AboutDialog {
         let theme = cx.global::<Theme>();
         let version = env!("CARGO_PKG_VERSION");
         let hash = option_env!("VERGEN_GIT_SHA").unwrap_or("unknown");
+        let cache_size = *cx.global::<Models>().cache_size.read(cx);
 
         modal().on_exit(self.on_exit).child(
             div()
@@ -119,6 +123,51 @@ impl RenderOnce for AboutDialog {
                                                     })
                                                     .child("Learn more about your rights."),
                                             ),
+                                    )
+                                    .child(
+                                        div()
+                                            .mt(px(10.0))
+                                            .flex()
+                                            .child(format!(
+                                                "Cache: {:.1} MB. ",
+                                                cache_size as f64 / (1024.0 * 1024.0)
+                                            ))
+                                            .child(
+                                                div()
+                                                    .id("about-clear-cache-link")
+                                                    .cursor_pointer()
+                                                    .text_color(theme.text_link)
+                                                    .hover(|this| {
+                                                        this.border_b_1()
+                                                            .border_color(theme.text_link)
+                                                    })
+                                                    .on_click(|_, window, cx| {
+                                                        window.dispatch_action(
+                                                            Box::new(ClearCache),
+                                                            cx,
+                                                        );
+                                                    })
+                                                    .child("Clear cache"),
+                                            ),
+                                    )
+                                    .child(
+                                        div()
+                                            .mt(px(10.0))
+                                            .flex()
+                                            .flex_col()
+                                            .text_size(px(12.0))
+                                            .children(diagnostics_lines(cx).into_iter().skip(1)),
+                                    )
+                                    .child(
+                                        div()
+                                            .id("about-copy-diagnostics-link")
+                                            .cursor_pointer()
+                                            .text_color(theme.text_link)
+                                            .hover(|this| {
+                                                this.border_b_1().border_color(theme.text_link)
+                                            })
+                                            .on_click(|_, _, cx| copy_diagnostics(cx))
+                                            .child("Copy diagnostics"),
                                     ),
                             ),
                         ),
@@ -2,7 +2,7 @@ use core::panic;
 use std::{
     env, fs,
     path::{Path, PathBuf},
-    sync::{Arc, RwLock},
+    sync::{Arc, OnceLock, RwLock},
 };
 
 use directories::ProjectDirs;
@@ -12,34 +12,44 @@ use tracing::{debug, info, warn};
 
 use crate::{
     shared::config::{AppConfig, AppConfigGlobal},
-    shared::db::TursoDatabase,
-    player::library::scan::{ScanInterface, ScanThread},
+    shared::db::{
+        TursoDatabase,
+        migrations::{CHAT_MIGRATIONS, library_migrations},
+    },
+    shared::i18n::setup_language,
+    player::library::scan::{ScanEvent, ScanInterface, ScanThread},
     chat::{self, services::ChatServices, ui::layout::ChatOverview},
     player::playback::{interface::GPUIPlaybackInterface, queue::QueueItemData, thread::PlaybackThread},
-    player::services::controllers::make_cl,
+    player::services::{controllers::make_cl, tray::setup_tray},
     shared::settings::{
         SettingsGlobal, setup_settings,
-        storage::{Storage, StorageData},
+        storage::{Storage, StorageData, WindowState},
     },
     player::ui::{assets::HummingbirdAssetSource, constants::APP_SHADOW_SIZE},
 };
 
 use super::{
     about::about_dialog,
-    arguments::parse_args_and_prepare,
+    arguments::{self, Args, parse_args, prepare_args, print_migration_plan},
+    command_palette::CommandPalette,
     components::{input, modal},
     config::config_dialog,
     constants::APP_ROUNDING,
     controls::Controls,
     data::create_album_cache,
+    diagnostics::refresh_diagnostics,
     global_actions::register_actions,
-    header::Header,
-    library::Library,
+    header::{Header, close_window},
+    library::{Library, track_listing},
+    metadata_lookup::MetadataLookup,
+    mini_player::{MINI_PLAYER_SIZE, MiniPlayer},
     models::{self, Models, PlaybackInfo, build_models},
     queue::Queue,
     search::SearchView,
-    theme::{Theme, setup_theme},
+    settings::{refresh_folder_track_counts, settings_dialog},
+    theme::{Theme, apply_theme, setup_theme},
     util::drop_image_from_app,
+    window_level::set_always_on_top,
 };
 
 struct WindowShadow {
@@ -48,11 +58,20 @@ struct WindowShadow {
     pub library: Entity<Library>,
     pub header: Entity<Header>,
     pub search: Entity<SearchView>,
+    pub command_palette: Entity<CommandPalette>,
     pub chat_overview: Entity<ChatOverview>,
+    pub mini_player: Entity<MiniPlayer>,
     pub show_queue: Entity<bool>,
     pub show_about: Entity<bool>,
     pub show_config: Entity<bool>,
+    pub show_settings: Entity<bool>,
+    pub metadata_lookup: Entity<Option<Entity<MetadataLookup>>>,
     pub config_path: Arc<PathBuf>,
+    pub mini_mode: Entity<bool>,
+    /// The window size before the mini player was shown, restored when it's hidden again.
+    pub normal_size: Option<Size<Pixels>>,
+    /// The most recent bounds of the window outside of the mini player, saved when quitting.
+    pub window_state: Option<WindowState>,
 }
 
 impl Render for WindowShadow {
@@ -69,8 +88,11 @@ impl Render for WindowShadow {
         let show_queue_flag = *self.show_queue.read(cx);
         let show_about = *self.show_about.clone().read(cx);
         let show_config = *self.show_config.clone().read(cx);
+        let show_settings = *self.show_settings.clone().read(cx);
+        let metadata_lookup = self.metadata_lookup.read(cx).clone();
         let config_path = self.config_path.clone();
         let chat_overview = self.chat_overview.clone();
+        let mini_mode = *self.mini_mode.read(cx);
 
         let mut element = div()
             .id("window-backdrop")
@@ -153,7 +175,7 @@ impl Render for WindowShadow {
                         Decorations::Server => div,
                         Decorations::Client { tiling } => div
                             .when(cfg!(not(target_os = "macos")), |div| {
-                                div.border_color(rgba(0x64748b33))
+                                div.border_color(theme.window_border)
                             })
                             .when(!(tiling.top || tiling.right), |div| {
                                 div.rounded_tr(rounding)
@@ -203,44 +225,48 @@ impl Render for WindowShadow {
                     .flex_col()
                     .max_w_full()
                     .max_h_full()
-                    .child(self.header.clone())
-                    .child({
-                        let mut library_column = div()
-                            .flex()
-                            .flex_col()
-                            .flex_grow()
-                            .max_w_full()
-                            .max_h_full()
-                            .overflow_hidden()
-                            .child(self.library.clone());
-
-                        if show_queue_flag {
-                            library_column = library_column.child(queue);
-                        }
-
-                        let chat_column = div()
-                            .flex()
-                            .flex_col()
-                            .flex_basis(px(340.0))
-                            .flex_shrink_0()
-                            .max_h_full()
-                            .bg(theme.background_secondary)
-                            .rounded(px(12.0))
-                            .p(px(16.0))
-                            .child(chat_overview);
-
-                        div()
-                            .flex()
-                            .gap(px(20.0))
-                            .w_full()
-                            .h_full()
-                            .max_w_full()
-                            .max_h_full()
-                            .child(library_column)
-                            .child(chat_column)
+                    .when(mini_mode, |this| this.child(self.mini_player.clone()))
+                    .when(!mini_mode, |this| {
+                        this.child(self.header.clone())
+                            .child({
+                                let mut library_column = div()
+                                    .flex()
+                                    .flex_col()
+                                    .flex_grow()
+                                    .max_w_full()
+                                    .max_h_full()
+                                    .overflow_hidden()
+                                    .child(self.library.clone());
+
+                                if show_queue_flag {
+                                    library_column = library_column.child(queue);
+                                }
+
+                                let chat_column = div()
+                                    .flex()
+                                    .flex_col()
+                                    .flex_basis(px(340.0))
+                                    .flex_shrink_0()
+                                    .max_h_full()
+                                    .bg(theme.background_secondary)
+                                    .rounded(px(12.0))
+                                    .p(px(16.0))
+                                    .child(chat_overview);
+
+                                div()
+                                    .flex()
+                                    .gap(px(20.0))
+                                    .w_full()
+                                    .h_full()
+                                    .max_w_full()
+                                    .max_h_full()
+                                    .child(library_column)
+                                    .child(chat_column)
+                            })
+                            .child(self.controls.clone())
+                            .child(self.search.clone())
+                            .child(self.command_palette.clone())
                     })
-                    .child(self.controls.clone())
-                    .child(self.search.clone())
                     .when(show_config, move |this| {
                         let path = config_path.clone();
                         this.child(config_dialog(path, &|_, cx| {
@@ -248,6 +274,13 @@ impl Render for WindowShadow {
                             show_config.write(cx, false);
                         }))
                     })
+                    .when(show_settings, |this| {
+                        this.child(settings_dialog(&|_, cx| {
+                            let show_settings = cx.global::<Models>().show_settings.clone();
+                            show_settings.write(cx, false);
+                        }))
+                    })
+                    .when_some(metadata_lookup, |this, lookup| this.child(lookup))
                     .when(show_about, |this| {
                         this.child(about_dialog(&|_, cx| {
                             let show_about = cx.global::<Models>().show_about.clone();
@@ -323,6 +356,20 @@ pub fn find_fonts(cx: &mut App) -> gpui::Result<()> {
     results
 }
 
+/// The name of the library database file, in the data directory.
+pub const MUSIC_DB_FILE: &str = "music.db";
+
+/// The name of the chat database file, in the data directory.
+pub const CHAT_DB_FILE: &str = "mrchat.db";
+
+/// The smallest size the window can be resized to outside of the mini player. The platform's
+/// minimum is the mini player's size, since it's the same window, so this one is kept by
+/// [`WindowShadow`] instead.
+const MIN_WINDOW_SIZE: Size<Pixels> = Size {
+    width: px(800.0),
+    height: px(600.0),
+};
+
 pub struct Pool(pub TursoDatabase);
 
 impl Global for Pool {}
@@ -332,6 +379,37 @@ pub fn get_dirs() -> ProjectDirs {
         .expect("couldn't generate project dirs")
 }
 
+/// The directory requested with `--data-dir` or `MRCHAT_DATA_DIR`, made absolute so that it
+/// doesn't depend on the working directory. Resolved once, so every caller agrees on it.
+static DATA_DIR_OVERRIDE: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+fn resolve_data_dir_override(args: Option<&Args>) -> Option<PathBuf> {
+    arguments::data_dir_override(args).map(|dir| std::path::absolute(&dir).unwrap_or(dir))
+}
+
+fn data_dir_override() -> Option<&'static Path> {
+    DATA_DIR_OVERRIDE
+        .get_or_init(|| resolve_data_dir_override(None))
+        .as_deref()
+}
+
+/// The directory the library database, settings and other data are kept in.
+pub fn data_dir() -> PathBuf {
+    match data_dir_override() {
+        Some(dir) => dir.to_path_buf(),
+        None => get_dirs().data_dir().to_path_buf(),
+    }
+}
+
+/// The directory cached files are kept in. When the data directory is overridden, the cache is
+/// kept inside it, so that nothing is written to the default locations.
+pub fn cache_dir() -> PathBuf {
+    match data_dir_override() {
+        Some(dir) => dir.join("cache"),
+        None => get_dirs().cache_dir().to_path_buf(),
+    }
+}
+
 enum ConfigLoadSource {
     DataDir,
     CopiedFromWorkingDir,
@@ -373,15 +451,42 @@ pub struct DropImageDummyModel;
 impl EventEmitter<Vec<Arc<RenderImage>>> for DropImageDummyModel {}
 
 pub async fn run() {
-    let dirs = get_dirs();
-    let directory = dirs.data_dir().to_path_buf();
+    let args = parse_args();
+    // this has to happen before anything asks for the data directory
+    DATA_DIR_OVERRIDE
+        .set(resolve_data_dir_override(Some(&args)))
+        .expect("data directory resolved before the arguments were parsed");
+
+    let directory = data_dir();
+
+    // checked before anything is created, so a dry run on a fresh install leaves nothing behind
+    if args.migrate_dry_run() {
+        for (name, file, migrations) in [
+            ("Music", MUSIC_DB_FILE, library_migrations()),
+            ("Chat", CHAT_DB_FILE, CHAT_MIGRATIONS),
+        ] {
+            match TursoDatabase::migration_status_at(&directory.join(file), migrations).await {
+                Ok(status) => print_migration_plan(name, &status),
+                Err(err) => {
+                    eprintln!("failed to read {name} migration status: {:?}", err);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        std::process::exit(0);
+    }
+
+    if directory.exists() && !directory.is_dir() {
+        panic!("fatal: data directory {:?} is not a directory", directory);
+    }
     if !directory.exists() {
         fs::create_dir_all(&directory)
             .unwrap_or_else(|e| panic!("couldn't create data directory, {:?}, {:?}", directory, e));
     }
 
     // Create separate databases for music and chat functionality
-    let music_db_path = directory.join("music.db");
+    let music_db_path = directory.join(MUSIC_DB_FILE);
     tracing::info!("Opening music database at: {:?}", music_db_path);
     let music_db = match TursoDatabase::open_local(&music_db_path).await {
         Ok(db) => db,
@@ -391,7 +496,7 @@ pub async fn run() {
         ),
     };
 
-    let chat_db_path = directory.join("mrchat.db");
+    let chat_db_path = directory.join(CHAT_DB_FILE);
     tracing::info!("Opening chat database at: {:?}", chat_db_path);
     let chat_db = match TursoDatabase::open_local(&chat_db_path).await {
         Ok(db) => db,
@@ -401,23 +506,8 @@ pub async fn run() {
         ),
     };
 
-    // Run migrations for library schema
-    // Migrations are embedded in the binary, so they work regardless of working directory
-    const MIGRATIONS: &[(&str, &str)] = &[
-        ("01_create_artist_table.sql", include_str!("../../../migrations/01_create_artist_table.sql")),
-        ("02_create_album_table.sql", include_str!("../../../migrations/02_create_album_table.sql")),
-        ("03_create_track_table.sql", include_str!("../../../migrations/03_create_track_table.sql")),
-        ("04_add_album_release_info.sql", include_str!("../../../migrations/04_add_album_release_info.sql")),
-        ("05_add_track_artist.sql", include_str!("../../../migrations/05_add_track_artist.sql")),
-        ("06_add_album_mbid_and_path.sql", include_str!("../../../migrations/06_add_album_mbid_and_path.sql")),
-        ("07_add_track_folder.sql", include_str!("../../../migrations/07_add_track_folder.sql")),
-        ("08_create_playlist_table.sql", include_str!("../../../migrations/08_create_playlist_table.sql")),
-        ("09_create_playlist_item_table.sql", include_str!("../../../migrations/09_create_playlist_item_table.sql")),
-        ("10_insert_default_playlist.sql", include_str!("../../../migrations/10_insert_default_playlist.sql")),
-    ];
-
     music_db
-        .run_migrations(MIGRATIONS)
+        .run_migrations(library_migrations())
         .await
         .expect("failed to run library migrations - this is fatal");
 
@@ -467,7 +557,6 @@ pub async fn run() {
             cx.set_global(services.clone());
             chat::bootstrap_state(cx, services);
 
-            let bounds = Bounds::centered(None, size(px(1024.0), px(700.0)), cx);
             find_fonts(cx).expect("unable to load fonts");
             register_actions(cx);
 
@@ -475,8 +564,16 @@ pub async fn run() {
             let storage = Storage::new(directory.clone().join("app_data.json"));
             let storage_data = storage.load_or_default();
 
-            setup_theme(cx, directory.join("theme.json"));
+            let window_bounds = storage_data
+                .window
+                .map(|state| state.to_window_bounds(MIN_WINDOW_SIZE, cx))
+                .unwrap_or_else(|| {
+                    WindowBounds::Windowed(Bounds::centered(None, size(px(1024.0), px(700.0)), cx))
+                });
+
             setup_settings(cx, directory.join("settings.json"));
+            setup_language(cx);
+            setup_theme(cx, directory.join("theme.json"));
 
             build_models(
                 cx,
@@ -489,12 +586,14 @@ pub async fn run() {
             );
 
             input::bind_actions(cx);
+            track_listing::bind_actions(cx);
             modal::bind_actions(cx);
 
             create_album_cache(cx);
 
             let settings = cx.global::<SettingsGlobal>().model.read(cx);
             let playback_settings = settings.playback.clone();
+            let always_on_top = settings.interface.always_on_top;
 
             // Use scan directories from config.toml if provided, otherwise fall back to settings.json
             let scan_settings = if !app_config_for_closure.player.scan_directories.is_empty() {
@@ -518,14 +617,22 @@ pub async fn run() {
                     .collect();
 
                 info!("使用 config.toml 中的扫描路径: {:?}", paths);
-                ScanSettings { paths }
+                ScanSettings {
+                    paths,
+                    ..settings.scanning.clone()
+                }
             } else {
                 settings.scanning.clone()
             };
 
+            let scan_on_startup = scan_settings.scan_on_startup;
             let mut scan_interface: ScanInterface =
                 ScanThread::start(music_db.clone(), scan_settings);
-            scan_interface.scan();
+            if scan_on_startup {
+                scan_interface.scan();
+            } else {
+                info!("Scanning on startup is disabled, skipping library scan");
+            }
             scan_interface.start_broadcast(cx);
 
             cx.set_global(scan_interface);
@@ -540,28 +647,45 @@ pub async fn run() {
             })
             .detach();
 
+            let initial_volume = playback_settings.initial_volume();
             let mut playback_interface: GPUIPlaybackInterface =
                 PlaybackThread::start(queue, playback_settings);
             playback_interface.start_broadcast(cx);
+            playback_interface.set_volume(initial_volume);
 
-            if !parse_args_and_prepare(cx, &playback_interface) {
+            if storage_data.output_device.is_some() {
+                playback_interface.set_device(storage_data.output_device.clone());
+            }
+
+            if !prepare_args(cx, &playback_interface, &args) {
                 if let Some(track) = storage_data.current_track {
-                    // open current track,
-                    playback_interface.open(track.get_path().clone());
+                    // open current track, from its part of the file if it's from a cue sheet,
+                    let item = QueueItemData::new(cx, track.get_path().clone(), None, None)
+                        .with_segment(storage_data.current_segment);
+                    playback_interface.open(item);
                     // but stop it immediately
                     playback_interface.pause();
                 }
             }
             cx.set_global(playback_interface);
 
+            // playback settings changed in the settings dialog apply without a restart
+            let settings_model = cx.global::<SettingsGlobal>().model.clone();
+            cx.observe(&settings_model, |settings, cx| {
+                let playback = settings.read(cx).playback.clone();
+                cx.global::<GPUIPlaybackInterface>().update_settings(playback);
+            })
+            .detach();
+
             cx.activate(true);
 
             cx.open_window(
                 WindowOptions {
-                    window_bounds: Some(WindowBounds::Windowed(bounds)),
+                    window_bounds: Some(window_bounds),
                     window_background: WindowBackgroundAppearance::Opaque,
                     window_decorations: Some(WindowDecorations::Client),
-                    window_min_size: Some(size(px(800.0), px(600.0))),
+                    // outside of the mini player, MIN_WINDOW_SIZE is enforced instead
+                    window_min_size: Some(MINI_PLAYER_SIZE),
                     titlebar: Some(TitlebarOptions {
                         title: Some(SharedString::from("MrChat")),
                         appears_transparent: true,
@@ -571,29 +695,81 @@ pub async fn run() {
                         }),
                     }),
                     app_id: Some("org.v1cc0.mrchat".to_string()),
-                    kind: WindowKind::Normal,
+                    kind: if always_on_top {
+                        WindowKind::Floating
+                    } else {
+                        WindowKind::Normal
+                    },
                     ..Default::default()
                 },
                 |window, cx| {
                     window.set_window_title("MrChat");
 
                     make_cl(cx, window);
+                    setup_tray(cx, window);
+
+                    // closing from the title bar or the window manager behaves like the close
+                    // button, which quits or hides the window depending on the settings
+                    window.on_window_should_close(cx, |window, cx| {
+                        close_window(window, cx);
+                        false
+                    });
 
                     cx.new(|cx| {
                         cx.observe_window_appearance(window, |_, _, cx| {
-                            cx.refresh_windows();
+                            apply_theme(cx);
+                        })
+                        .detach();
+
+                        // the mini player's bounds aren't kept, so that the app reopens at the size
+                        // it was before the mini player was shown
+                        cx.observe_window_bounds(window, |this: &mut WindowShadow, window, cx| {
+                            if *this.mini_mode.read(cx) {
+                                return;
+                            }
+
+                            let current = window.bounds().size;
+                            if current.width < MIN_WINDOW_SIZE.width
+                                || current.height < MIN_WINDOW_SIZE.height
+                            {
+                                window.resize(current.max(&MIN_WINDOW_SIZE));
+                            }
+
+                            this.window_state = Some(WindowState::new(window.window_bounds()));
                         })
                         .detach();
 
                         // Update `StorageData` and save it to file system while quitting the app
                         cx.on_app_quit({
                             let current_track = cx.global::<PlaybackInfo>().current_track.clone();
-                            move |_, cx| {
+                            let queue = cx.global::<Models>().queue.clone();
+                            let output_device =
+                                cx.global::<PlaybackInfo>().preferred_device.clone();
+                            let device_volumes = cx.global::<PlaybackInfo>().device_volumes.clone();
+                            move |this: &mut WindowShadow, cx| {
                                 let current_track = current_track.read(cx).clone();
+                                let current_segment = current_track
+                                    .as_ref()
+                                    .and_then(|track| queue.read(cx).current_segment(track));
+                                let output_device = output_device.read(cx).clone();
+                                let device_volumes = device_volumes.read(cx).clone();
+                                let window = this.window_state;
                                 let storage = storage.clone();
-                                cx.background_executor().spawn(async move {
-                                    storage.save(&StorageData { current_track });
-                                })
+                                let shutdown = cx.global::<GPUIPlaybackInterface>().shutdown();
+                                let save = cx.background_executor().spawn(async move {
+                                    storage.save(&StorageData {
+                                        current_track,
+                                        current_segment,
+                                        window,
+                                        output_device,
+                                        device_volumes,
+                                    });
+                                });
+
+                                async move {
+                                    shutdown.await;
+                                    save.await;
+                                }
                             }
                         })
                         .detach();
@@ -601,8 +777,26 @@ pub async fn run() {
                         let show_queue = cx.new(|_| true);
                         let show_about = cx.global::<Models>().show_about.clone();
                         let show_config = cx.global::<Models>().show_config.clone();
+                        let show_settings = cx.global::<Models>().show_settings.clone();
+                        let settings = cx.global::<SettingsGlobal>().model.clone();
+
+                        cx.observe(&show_about, |_, m, cx| {
+                            if *m.read(cx) {
+                                refresh_diagnostics(cx);
+                            }
+                            cx.notify();
+                        })
+                        .detach();
 
-                        cx.observe(&show_about, |_, _, cx| {
+                        let playback_diagnostics = cx.global::<PlaybackInfo>().diagnostics.clone();
+                        let library_diagnostics = cx.global::<Models>().library_diagnostics.clone();
+
+                        cx.observe(&playback_diagnostics, |_, _, cx| {
+                            cx.notify();
+                        })
+                        .detach();
+
+                        cx.observe(&library_diagnostics, |_, _, cx| {
                             cx.notify();
                         })
                         .detach();
@@ -612,17 +806,128 @@ pub async fn run() {
                         })
                         .detach();
 
+                        cx.observe(&show_settings, |_, m, cx| {
+                            if *m.read(cx) {
+                                refresh_folder_track_counts(cx);
+                            }
+                            cx.notify();
+                        })
+                        .detach();
+
+                        let scan_state = cx.global::<Models>().scan_state.clone();
+                        let folder_track_counts = cx.global::<Models>().folder_track_counts.clone();
+
+                        // the counts are only shown in the settings dialog
+                        cx.observe(&scan_state, |this: &mut WindowShadow, m, cx| {
+                            let complete = matches!(
+                                m.read(cx),
+                                ScanEvent::ScanCompleteIdle | ScanEvent::ScanCompleteWatching
+                            );
+
+                            if complete && *this.show_settings.read(cx) {
+                                refresh_folder_track_counts(cx);
+                            }
+                        })
+                        .detach();
+
+                        cx.observe(&folder_track_counts, |_, _, cx| {
+                            cx.notify();
+                        })
+                        .detach();
+
+                        // keeps the settings dialog up to date
+                        cx.observe(&settings, |_, _, cx| {
+                            cx.notify();
+                        })
+                        .detach();
+
+                        let balance = cx.global::<PlaybackInfo>().balance.clone();
+
+                        cx.observe(&balance, |_, _, cx| {
+                            cx.notify();
+                        })
+                        .detach();
+
+                        let device = cx.global::<PlaybackInfo>().device.clone();
+
+                        cx.observe(&device, |_, _, cx| {
+                            cx.notify();
+                        })
+                        .detach();
+
+                        let metadata_lookup = cx.global::<Models>().metadata_lookup.clone();
+
+                        cx.observe(&metadata_lookup, |_, _, cx| {
+                            cx.notify();
+                        })
+                        .detach();
+
+                        let listenbrainz_token =
+                            cx.global::<Models>().listenbrainz_token.clone();
+
+                        cx.observe(&listenbrainz_token, |_, _, cx| {
+                            cx.notify();
+                        })
+                        .detach();
+
+                        let cache_size = cx.global::<Models>().cache_size.clone();
+
+                        cx.observe(&cache_size, |_, _, cx| {
+                            cx.notify();
+                        })
+                        .detach();
+
+                        let mini_mode = cx.global::<Models>().mini_player.clone();
+
+                        cx.observe_in(
+                            &mini_mode,
+                            window,
+                            |this: &mut WindowShadow, m, window, cx| {
+                                if *m.read(cx) {
+                                    this.normal_size = Some(window.bounds().size);
+                                    window.resize(MINI_PLAYER_SIZE);
+                                } else if let Some(size) = this.normal_size.take() {
+                                    window.resize(size);
+                                }
+
+                                cx.notify();
+                            },
+                        )
+                        .detach();
+
+                        let mut last_on_top = always_on_top;
+
+                        cx.observe_in(
+                            &settings,
+                            window,
+                            move |_: &mut WindowShadow, settings, window, cx| {
+                                let on_top = settings.read(cx).interface.always_on_top;
+                                if on_top != last_on_top {
+                                    last_on_top = on_top;
+                                    set_always_on_top(window, on_top);
+                                }
+                            },
+                        )
+                        .detach();
+
                         WindowShadow {
                             controls: Controls::new(cx, show_queue.clone()),
                             queue: Queue::new(cx, show_queue.clone()),
                             library: Library::new(cx),
                             header: Header::new(cx),
                             search: SearchView::new(cx),
+                            command_palette: CommandPalette::new(cx),
                             chat_overview: ChatOverview::create(cx),
+                            mini_player: MiniPlayer::new(cx),
                             show_queue,
                             show_about,
                             show_config,
+                            show_settings,
+                            metadata_lookup,
                             config_path: config_path_arc.clone(),
+                            mini_mode,
+                            normal_size: None,
+                            window_state: Some(WindowState::new(window.window_bounds())),
                         }
                     })
                 },
@@ -4,27 +4,106 @@ use clap::Parser;
 use gpui::App;
 use tracing::info;
 
-use crate::player::playback::{interface::GPUIPlaybackInterface, queue::QueueItemData};
+use crate::{
+    player::playback::{interface::GPUIPlaybackInterface, queue::QueueItemData},
+    shared::db::MigrationStatus,
+};
 
 #[derive(Parser, Debug)]
 #[command(version, about)]
-struct Args {
+pub struct Args {
     #[arg()]
     files: Option<Vec<PathBuf>>,
+    /// Start playing each of the files this many seconds in.
+    #[arg(long, value_name = "SECONDS")]
+    start: Option<f64>,
+    /// Stop playing each of the files this many seconds in.
+    #[arg(long, value_name = "SECONDS")]
+    end: Option<f64>,
+    /// Print which database migrations are applied and pending, then exit without applying them.
+    #[arg(long)]
+    migrate_dry_run: bool,
+    /// Keep the library, settings and cache in this directory instead of the default location.
+    /// Can also be set with the MRCHAT_DATA_DIR environment variable.
+    #[arg(long, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
 }
 
-/// Parses the arguments provided by the user and handles them. Returns true if files were provided
-/// for playback as command line arguments.
-pub fn parse_args_and_prepare(cx: &mut App, interface: &GPUIPlaybackInterface) -> bool {
-    let args = Args::parse();
+/// The environment variable that the data directory can be overridden with, if `--data-dir`
+/// isn't given.
+const DATA_DIR_VAR: &str = "MRCHAT_DATA_DIR";
 
+/// Parses the arguments provided by the user. This should only happen once, at startup; the
+/// result is passed to everything that needs it.
+pub fn parse_args() -> Args {
+    Args::parse()
+}
+
+impl Args {
+    /// Returns true if the user asked for a migration dry run instead of starting the application.
+    pub fn migrate_dry_run(&self) -> bool {
+        self.migrate_dry_run
+    }
+}
+
+/// Returns the data directory requested with `--data-dir` or `MRCHAT_DATA_DIR`, if any. Without
+/// arguments (as in tests, which never parse them), only the environment variable is checked.
+pub fn data_dir_override(args: Option<&Args>) -> Option<PathBuf> {
+    args.and_then(|args| args.data_dir.clone())
+        .or_else(|| std::env::var_os(DATA_DIR_VAR).map(PathBuf::from))
+        .filter(|dir| !dir.as_os_str().is_empty())
+}
+
+/// Prints the migration plan for the `--migrate-dry-run` flag.
+pub fn print_migration_plan(name: &str, status: &MigrationStatus) {
+    println!("{} database:", name);
+
+    for filename in &status.applied {
+        println!("  applied  {}", filename);
+    }
+
+    for filename in &status.pending {
+        println!("  pending  {}", filename);
+    }
+
+    for filename in &status.missing {
+        println!(
+            "  missing  {} (recorded as applied, but unknown to this build)",
+            filename
+        );
+    }
+
+    if status.is_up_to_date() {
+        println!("  up to date");
+    } else {
+        println!(
+            "  {} pending, {} missing",
+            status.pending.len(),
+            status.missing.len()
+        );
+    }
+}
+
+/// Handles the arguments provided by the user. Returns true if files were provided for playback
+/// as command line arguments.
+pub fn prepare_args(cx: &mut App, interface: &GPUIPlaybackInterface, args: &Args) -> bool {
     if let Some(files) = &args.files {
         info!("Queueing files found in arguments: {:?}", files);
 
+        let has_offsets = args.start.is_some() || args.end.is_some();
+
         interface.queue_list(
             files
                 .iter()
-                .map(|path| QueueItemData::new(cx, path.clone(), None, None))
+                .map(|path| {
+                    let item = QueueItemData::new(cx, path.clone(), None, None);
+
+                    if has_offsets {
+                        item.with_offsets(args.start.unwrap_or_default(), args.end)
+                    } else {
+                        item
+                    }
+                })
                 .collect(),
         );
     }
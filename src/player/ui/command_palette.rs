@@ -0,0 +1,339 @@
+use std::collections::VecDeque;
+
+use gpui::*;
+use nucleo::{
+    Config, Matcher,
+    pattern::{CaseMatching, Normalization, Pattern},
+};
+use prelude::FluentBuilder;
+use tracing::error;
+
+use crate::player::library::db::LibraryAccess;
+
+use super::{
+    components::{
+        input::{EnrichedInputAction, TextInput},
+        modal::modal,
+    },
+    global_actions::{
+        About, ClearCache, CopyNowPlaying, ExportSettings, FocusChat, ImportSettings, NewChat,
+        Next, OpenCommandPalette, OpenSettings, PlayPause, Previous, RebuildLibrary, RestartTrack,
+        ScanLibrary, Search, ShuffleLibrary, ToggleAlwaysOnTop, ToggleMiniPlayer, ToggleRepeat,
+        ToggleShuffle,
+    },
+    library::ViewSwitchMessage,
+    models::Models,
+    theme::Theme,
+};
+
+/// The most results shown at once.
+const MAX_RESULTS: usize = 50;
+
+enum PaletteTarget {
+    Action(Box<dyn Action>),
+    Album(i64),
+}
+
+struct PaletteEntry {
+    label: SharedString,
+    detail: Option<SharedString>,
+    /// The text the query is matched against.
+    haystack: String,
+    target: PaletteTarget,
+}
+
+/// An entry being matched against the query, along with its index in the list of entries.
+struct Candidate<'a>(usize, &'a str);
+
+impl AsRef<str> for Candidate<'_> {
+    fn as_ref(&self) -> &str {
+        self.1
+    }
+}
+
+fn command(label: &'static str, action: impl Action) -> PaletteEntry {
+    PaletteEntry {
+        label: SharedString::from(label),
+        detail: None,
+        haystack: label.to_string(),
+        target: PaletteTarget::Action(Box::new(action)),
+    }
+}
+
+fn commands() -> Vec<PaletteEntry> {
+    vec![
+        command("Play/Pause", PlayPause),
+        command("Next Track", Next),
+        command("Previous Track", Previous),
+        command("Restart Track", RestartTrack),
+        command("Toggle Shuffle", ToggleShuffle),
+        command("Toggle Repeat", ToggleRepeat),
+        command("Shuffle Library", ShuffleLibrary),
+        command("Scan Library", ScanLibrary),
+        command("Rebuild Library", RebuildLibrary),
+        command("Open Settings", OpenSettings),
+        command("Export Settings", ExportSettings),
+        command("Import Settings", ImportSettings),
+        command("New Chat", NewChat),
+        command("Focus Chat", FocusChat),
+        command("Search Albums", Search),
+        command("Toggle Mini Player", ToggleMiniPlayer),
+        command("Toggle Always on Top", ToggleAlwaysOnTop),
+        command("Copy Now Playing", CopyNowPlaying),
+        command("Clear Cache", ClearCache),
+        command("About MrChat", About),
+    ]
+}
+
+/// A searchable list of actions and albums, opened with a shortcut.
+pub struct CommandPalette {
+    show: Entity<bool>,
+    input: Entity<TextInput>,
+    handle: FocusHandle,
+    matcher: Matcher,
+    entries: Vec<PaletteEntry>,
+    /// Indices into `entries` of the entries matching the current query, best match first.
+    results: Vec<usize>,
+    selection: usize,
+}
+
+impl CommandPalette {
+    pub fn new(cx: &mut App) -> Entity<Self> {
+        cx.new(|cx| {
+            let show = cx.new(|_| false);
+            let handle = cx.focus_handle();
+            let weak = cx.weak_entity();
+
+            let handler = {
+                let weak = weak.clone();
+                move |action: EnrichedInputAction, _: &mut Window, cx: &mut App| {
+                    weak.update(cx, |this: &mut CommandPalette, cx| match action {
+                        EnrichedInputAction::Previous => {
+                            this.selection = this.selection.saturating_sub(1);
+                            cx.notify();
+                        }
+                        EnrichedInputAction::Next => {
+                            if this.selection + 1 < this.results.len() {
+                                this.selection += 1;
+                            }
+                            cx.notify();
+                        }
+                        EnrichedInputAction::Accept => this.run(this.selection, cx),
+                    })
+                    .ok();
+                }
+            };
+
+            let input = TextInput::new(
+                cx,
+                handle.clone(),
+                None,
+                Some(SharedString::from("Type a command or album…")),
+                Some(Box::new(handler)),
+            );
+
+            cx.subscribe(
+                &input,
+                |this: &mut CommandPalette, _, query: &String, cx| {
+                    this.set_query(query, cx);
+                },
+            )
+            .detach();
+
+            App::on_action(cx, move |_: &OpenCommandPalette, cx| {
+                weak.update(cx, |this, cx| this.open(cx)).ok();
+            });
+
+            cx.observe(&show, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            CommandPalette {
+                show,
+                input,
+                handle,
+                matcher: Matcher::new(Config::DEFAULT),
+                entries: Vec::new(),
+                results: Vec::new(),
+                selection: 0,
+            }
+        })
+    }
+
+    fn open(&mut self, cx: &mut Context<Self>) {
+        let mut entries = commands();
+
+        // albums are reloaded every time, so the palette is never out of date after a scan
+        match cx.list_albums_search() {
+            Ok(albums) => {
+                entries.extend(albums.into_iter().map(|(id, title, artist)| PaletteEntry {
+                    haystack: format!("{title} {artist}"),
+                    label: SharedString::from(title),
+                    detail: Some(SharedString::from(artist)),
+                    target: PaletteTarget::Album(id as i64),
+                }))
+            }
+            Err(e) => error!("Could not list albums for the command palette: {:?}", e),
+        }
+
+        self.entries = entries;
+        self.set_query("", cx);
+        self.show.write(cx, true);
+    }
+
+    fn set_query(&mut self, query: &str, cx: &mut Context<Self>) {
+        self.results = if query.trim().is_empty() {
+            // only show commands until something is typed
+            self.entries
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| matches!(entry.target, PaletteTarget::Action(_)))
+                .map(|(idx, _)| idx)
+                .collect()
+        } else {
+            let pattern = Pattern::parse(query, CaseMatching::Smart, Normalization::Smart);
+            let candidates = self
+                .entries
+                .iter()
+                .enumerate()
+                .map(|(idx, entry)| Candidate(idx, &entry.haystack));
+
+            pattern
+                .match_list(candidates, &mut self.matcher)
+                .into_iter()
+                .take(MAX_RESULTS)
+                .map(|(candidate, _)| candidate.0)
+                .collect()
+        };
+
+        self.selection = 0;
+        cx.notify();
+    }
+
+    fn reset(&mut self, cx: &mut Context<Self>) {
+        self.input.update(cx, |input, cx| {
+            input.reset();
+            cx.notify();
+        });
+        self.entries.clear();
+        self.results.clear();
+        self.show.write(cx, false);
+    }
+
+    /// Runs the result at `idx` and closes the palette.
+    fn run(&mut self, idx: usize, cx: &mut Context<Self>) {
+        let Some(entry) = self.results.get(idx).and_then(|idx| self.entries.get(*idx)) else {
+            return;
+        };
+
+        let target = match &entry.target {
+            PaletteTarget::Action(action) => PaletteTarget::Action(action.boxed_clone()),
+            PaletteTarget::Album(id) => PaletteTarget::Album(*id),
+        };
+
+        self.reset(cx);
+
+        match target {
+            PaletteTarget::Action(action) => cx.dispatch_action(action.as_ref()),
+            PaletteTarget::Album(id) => {
+                let switcher = cx.global::<Models>().switcher_model.clone();
+                switcher.update(cx, |_: &mut VecDeque<ViewSwitchMessage>, cx| {
+                    cx.emit(ViewSwitchMessage::Release(id));
+                });
+            }
+        }
+    }
+}
+
+impl Render for CommandPalette {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if !*self.show.read(cx) {
+            return div().into_any_element();
+        }
+
+        if !self.handle.is_focused(window) {
+            self.handle.focus(window);
+        }
+
+        let theme = cx.global::<Theme>();
+        let weak = cx.weak_entity();
+
+        modal()
+            .on_exit(move |_, cx| {
+                weak.update(cx, |this, cx| this.reset(cx)).ok();
+            })
+            .child(
+                div()
+                    .w(px(550.0))
+                    .h(px(350.0))
+                    .overflow_hidden()
+                    .flex()
+                    .flex_col()
+                    .child(
+                        div()
+                            .w_full()
+                            .p(px(12.0))
+                            .line_height(px(14.0))
+                            .h(px(40.0))
+                            .flex_shrink_0()
+                            .text_sm()
+                            .border_b(px(1.0))
+                            .border_color(theme.border_color)
+                            .child(self.input.clone()),
+                    )
+                    .child(
+                        div()
+                            .id("command-palette-results")
+                            .flex()
+                            .flex_col()
+                            .w_full()
+                            .flex_grow()
+                            .overflow_y_scroll()
+                            .p(px(4.0))
+                            .children(self.results.iter().enumerate().filter_map(|(row, idx)| {
+                                let entry = self.entries.get(*idx)?;
+
+                                Some(
+                                    div()
+                                        .id(("command-palette-item", row))
+                                        .flex()
+                                        .px(px(8.0))
+                                        .py(px(8.0))
+                                        .rounded(px(4.0))
+                                        .cursor_pointer()
+                                        .text_sm()
+                                        .line_height(px(14.0))
+                                        .hover(|this| this.bg(theme.palette_item_hover))
+                                        .active(|this| this.bg(theme.palette_item_active))
+                                        .when(row == self.selection, |this| {
+                                            this.bg(theme.palette_item_hover)
+                                        })
+                                        .on_click(
+                                            cx.listener(move |this, _, _, cx| this.run(row, cx)),
+                                        )
+                                        .child(
+                                            div()
+                                                .font_weight(FontWeight::BOLD)
+                                                .overflow_hidden()
+                                                .text_ellipsis()
+                                                .child(entry.label.clone()),
+                                        )
+                                        .when_some(entry.detail.clone(), |this, detail| {
+                                            this.child(
+                                                div()
+                                                    .ml_auto()
+                                                    .pl(px(8.0))
+                                                    .overflow_hidden()
+                                                    .text_ellipsis()
+                                                    .text_color(theme.text_secondary)
+                                                    .child(detail),
+                                            )
+                                        }),
+                                )
+                            })),
+                    ),
+            )
+            .into_any_element()
+    }
+}
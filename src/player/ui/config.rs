@@ -10,13 +10,16 @@ use gpui::{
 };
 use tracing::{info, warn};
 
-use crate::player::ui::{
-    components::{
-        button::{ButtonIntent, button},
-        modal::{OnExitHandler, modal},
+use crate::{
+    player::ui::{
+        components::{
+            button::{ButtonIntent, button},
+            modal::{OnExitHandler, modal},
+        },
+        models::Models,
+        theme::Theme,
     },
-    models::Models,
-    theme::Theme,
+    shared::i18n::{t, t_args},
 };
 
 const SAMPLE_CONFIG: &str = include_str!("../../../config.sample.toml");
@@ -32,92 +35,73 @@ impl RenderOnce for ConfigDialog {
         let theme = cx.global::<Theme>();
         let path_string = self.config_path.display().to_string();
 
-        modal()
-            .on_exit(self.on_exit)
-            .child(
-                div()
-                    .id("config-setup-dialog")
-                    .flex()
-                    .flex_col()
-                    .w(px(480.0))
-                    .p(px(24.0))
-                    .gap(px(18.0))
-                    .child(
-                        div()
-                            .text_size(px(20.0))
-                            .font_weight(FontWeight::BOLD)
-                            .child("需要配置 MrChat"),
-                    )
-                    .child(
-                        div()
-                            .text_sm()
-                            .text_color(theme.text_secondary)
-                            .child(
-                                div().child(format!(
-                                    "未在数据目录找到 config.toml。请在下列路径创建配置文件：{}",
-                                    path_string
-                                )),
-                            )
-                            .child(
-                                div().mt(px(8.0)).child(
-                                    "可以点击“生成默认配置”按钮获取一份模版文件，再根据实际环境填写 API 等参数。",
-                                ),
-                            )
-                            .child(
-                                div()
-                                    .mt(px(8.0))
-                                    .child("配置保存后请重新启动或刷新以启用聊天功能。"),
-                            ),
-                    )
-                    .child(
-                        div()
-                            .flex()
-                            .gap(px(12.0))
-                            .flex_wrap()
-                            .child({
-                                let path = self.config_path.clone();
-                                button()
-                                    .intent(ButtonIntent::Primary)
-                                    .id("config-generate-default")
-                                    .child(SharedString::from("生成默认配置"))
-                                    .on_click(move |_, _, cx| {
-                                        let show_config = cx.global::<Models>().show_config.clone();
-                                        let path = path.clone();
-                                        match write_default_config(path.as_ref()) {
-                                            Ok(status) => {
-                                                match status {
-                                                    ConfigWriteStatus::Created => info!(
-                                                        "已生成默认配置：{}",
-                                                        path.display()
-                                                    ),
-                                                    ConfigWriteStatus::AlreadyExists => info!(
-                                                        "配置文件已存在：{}",
-                                                        path.display()
-                                                    ),
-                                                }
+        modal().on_exit(self.on_exit).child(
+            div()
+                .id("config-setup-dialog")
+                .flex()
+                .flex_col()
+                .w(px(480.0))
+                .p(px(24.0))
+                .gap(px(18.0))
+                .child(
+                    div()
+                        .text_size(px(20.0))
+                        .font_weight(FontWeight::BOLD)
+                        .child(t("config.title")),
+                )
+                .child(
+                    div()
+                        .text_sm()
+                        .text_color(theme.text_secondary)
+                        .child(div().child(t_args("config.missing", &[&path_string])))
+                        .child(div().mt(px(8.0)).child(t("config.hint")))
+                        .child(div().mt(px(8.0)).child(t("config.restart"))),
+                )
+                .child(
+                    div()
+                        .flex()
+                        .gap(px(12.0))
+                        .flex_wrap()
+                        .child({
+                            let path = self.config_path.clone();
+                            button()
+                                .intent(ButtonIntent::Primary)
+                                .id("config-generate-default")
+                                .child(SharedString::from(t("config.create")))
+                                .on_click(move |_, _, cx| {
+                                    let show_config = cx.global::<Models>().show_config.clone();
+                                    let path = path.clone();
+                                    match write_default_config(path.as_ref()) {
+                                        Ok(status) => match status {
+                                            ConfigWriteStatus::Created => {
+                                                info!("已生成默认配置：{}", path.display())
                                             }
-                                            Err(err) => {
-                                                warn!(
-                                                    "写入默认配置失败（{}）：{err:?}",
-                                                    path.display()
-                                                );
+                                            ConfigWriteStatus::AlreadyExists => {
+                                                info!("配置文件已存在：{}", path.display())
                                             }
+                                        },
+                                        Err(err) => {
+                                            warn!(
+                                                "写入默认配置失败（{}）：{err:?}",
+                                                path.display()
+                                            );
                                         }
-                                        show_config.write(cx, false);
-                                    })
-                            })
-                            .child(
-                                button()
-                                    .intent(ButtonIntent::Secondary)
-                                    .id("config-dismiss")
-                                    .child(SharedString::from("稍后配置"))
-                                    .on_click(|_, _, cx| {
-                                        let show_config = cx.global::<Models>().show_config.clone();
-                                        show_config.write(cx, false);
-                                    }),
-                            ),
-                    ),
-            )
+                                    }
+                                    show_config.write(cx, false);
+                                })
+                        })
+                        .child(
+                            button()
+                                .intent(ButtonIntent::Secondary)
+                                .id("config-dismiss")
+                                .child(SharedString::from(t("config.later")))
+                                .on_click(|_, _, cx| {
+                                    let show_config = cx.global::<Models>().show_config.clone();
+                                    show_config.write(cx, false);
+                                }),
+                        ),
+                ),
+        )
     }
 }
 
@@ -1,13 +1,20 @@
 use crate::{
-    player::playback::{events::RepeatState, interface::GPUIPlaybackInterface, thread::PlaybackState},
+    player::library::db::LibraryAccess,
+    player::media::stream::is_stream_url,
+    player::playback::{
+        events::{PlaybackFormat, RepeatState},
+        interface::{GPUIPlaybackInterface, queue_other_folder_tracks},
+        thread::PlaybackState,
+    },
+    shared::i18n::t,
     shared::settings::SettingsGlobal,
     player::ui::components::{
         context::context,
         icons::{
-            MENU, NEXT_TRACK, PAUSE, PLAY, PREV_TRACK, REPEAT, REPEAT_OFF, REPEAT_ONCE, SHUFFLE,
-            VOLUME, VOLUME_OFF, icon,
+            CHECK, MENU, NEXT_TRACK, PAUSE, PLAY, PREV_TRACK, REPEAT, REPEAT_OFF, REPEAT_ONCE,
+            SHUFFLE, SPEAKER, VOLUME, VOLUME_OFF, icon,
         },
-        menu::{menu, menu_item},
+        menu::{CMenuItem, menu, menu_item},
     },
 };
 use gpui::*;
@@ -16,8 +23,9 @@ use prelude::FluentBuilder;
 use super::{
     components::slider::slider,
     constants::APP_ROUNDING,
-    global_actions::{Next, PlayPause, Previous},
-    models::{Models, PlaybackInfo},
+    global_actions::{Next, PlayPause, Previous, copy_now_playing},
+    library::ViewSwitchMessage,
+    models::{CurrentTrack, Models, PlaybackInfo},
     theme::Theme,
 };
 
@@ -69,6 +77,37 @@ pub struct InfoSection {
     artist_name: Option<SharedString>,
     albumart_actual: Option<ImageSource>,
     playback_info: PlaybackInfo,
+    /// The album containing the current track, if the track is in the library.
+    album_id: Option<i64>,
+}
+
+/// Looks up the album containing the given track. Returns None if the track isn't in the library
+/// (for example, if it was dropped onto the window) or has no album.
+fn find_album_id(cx: &App, current_track: Option<&CurrentTrack>) -> Option<i64> {
+    let track = cx
+        .get_track_by_path(current_track?.get_path())
+        .ok()
+        .flatten()?;
+
+    track.album_id
+}
+
+/// Queues the other tracks in the folder of the track that's playing. Streams have no folder, so
+/// nothing is queued for them.
+fn queue_now_playing_folder(cx: &mut App) {
+    let Some(path) = cx
+        .global::<PlaybackInfo>()
+        .current_track
+        .read(cx)
+        .as_ref()
+        .map(|track| track.get_path().clone())
+    else {
+        return;
+    };
+
+    if !is_stream_url(&path) {
+        queue_other_folder_tracks(&path, cx);
+    }
 }
 
 impl InfoSection {
@@ -83,6 +122,16 @@ impl InfoSection {
             })
             .detach();
 
+            cx.observe(&playback_info.queue_empty_notice, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            cx.observe(&playback_info.format, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
             cx.observe(&metadata_model, |this: &mut Self, m, cx| {
                 let metadata = m.read(cx);
 
@@ -101,10 +150,21 @@ impl InfoSection {
             })
             .detach();
 
+            cx.observe(&playback_info.current_track, |this: &mut Self, m, cx| {
+                let current_track = m.read(cx).clone();
+
+                this.album_id = find_album_id(cx, current_track.as_ref());
+                cx.notify()
+            })
+            .detach();
+
+            let current_track = playback_info.current_track.read(cx).clone();
+
             Self {
                 artist_name: None,
                 track_name: None,
                 albumart_actual: None,
+                album_id: find_album_id(cx, current_track.as_ref()),
                 playback_info,
             }
         })
@@ -115,88 +175,162 @@ impl Render for InfoSection {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
         let state = self.playback_info.playback_state.read(cx);
+        let queue_empty = self.playback_info.queue_empty_notice.read(cx).is_some();
+        let format = format_label(self.playback_info.format.read(cx));
 
-        div()
-            .id("info-section")
-            .flex()
-            .w(px(275.0))
-            .min_w(px(275.0))
-            .max_w(px(275.0))
-            .overflow_x_hidden()
-            .flex_shrink_0()
-            .child(
+        context("info-context")
+            .with(
                 div()
-                    .mx(px(12.0))
-                    .mt(px(12.0))
-                    .mb(px(6.0))
-                    .gap(px(10.0))
+                    .id("info-section")
                     .flex()
+                    .w(px(275.0))
+                    .min_w(px(275.0))
+                    .max_w(px(275.0))
                     .overflow_x_hidden()
+                    .flex_shrink_0()
                     .child(
                         div()
-                            .id("album-art")
-                            .rounded(px(4.0))
-                            .bg(theme.album_art_background)
-                            .shadow_sm()
-                            .w(px(36.0))
-                            .h(px(36.0))
+                            .mx(px(12.0))
+                            .mt(px(12.0))
                             .mb(px(6.0))
-                            .when(self.albumart_actual.is_some(), |div| {
-                                div.child(
-                                    img(self.albumart_actual.clone().unwrap())
-                                        .w(px(36.0))
-                                        .h(px(36.0))
-                                        .rounded(px(4.0)),
-                                )
-                            }),
-                    )
-                    .when(*state == PlaybackState::Stopped, |e| {
-                        e.child(
-                            div()
-                                .line_height(rems(1.0))
-                                .font_weight(FontWeight::EXTRA_BOLD)
-                                .text_size(px(15.0))
-                                .flex()
-                                .h_full()
-                                .items_center()
-                                .pb(px(6.0))
-                                .child("MrChat"),
-                        )
-                    })
-                    .when(*state != PlaybackState::Stopped, |e| {
-                        e.child(
-                            div()
-                                .flex()
-                                .flex_col()
-                                .line_height(rems(1.0))
-                                .text_size(px(15.0))
-                                .gap_1()
-                                .overflow_x_hidden()
-                                .child(
+                            .gap(px(10.0))
+                            .flex()
+                            .overflow_x_hidden()
+                            .child(
+                                div()
+                                    .id("album-art")
+                                    .rounded(px(4.0))
+                                    .bg(theme.album_art_background)
+                                    .shadow_sm()
+                                    .w(px(36.0))
+                                    .h(px(36.0))
+                                    .mb(px(6.0))
+                                    .when(self.albumart_actual.is_some(), |div| {
+                                        div.child(
+                                            img(self.albumart_actual.clone().unwrap())
+                                                .w(px(36.0))
+                                                .h(px(36.0))
+                                                .rounded(px(4.0)),
+                                        )
+                                    }),
+                            )
+                            .when(*state == PlaybackState::Stopped, |e| {
+                                e.child(
                                     div()
-                                        .overflow_x_hidden()
-                                        .font_weight(FontWeight::EXTRA_BOLD)
-                                        .text_ellipsis()
+                                        .line_height(rems(1.0))
+                                        .text_size(px(15.0))
+                                        .flex()
+                                        .flex_col()
+                                        .h_full()
+                                        .justify_center()
+                                        .gap_1()
+                                        .pb(px(6.0))
                                         .child(
-                                            self.track_name
-                                                .clone()
-                                                .unwrap_or("Unknown Track".into()),
-                                        ),
+                                            div()
+                                                .font_weight(FontWeight::EXTRA_BOLD)
+                                                .child("MrChat"),
+                                        )
+                                        .when(queue_empty, |this| {
+                                            this.child(
+                                                div()
+                                                    .text_color(theme.text_secondary)
+                                                    .child(t("player.queue_empty")),
+                                            )
+                                        }),
                                 )
-                                .child(
+                            })
+                            .when(*state != PlaybackState::Stopped, |e| {
+                                e.child(
                                     div()
-                                        .overflow_x_hidden()
-                                        .pb(px(6.0))
-                                        .text_ellipsis()
+                                        .flex()
+                                        .flex_col()
+                                        .line_height(rems(1.0))
+                                        .text_size(px(15.0))
+                                        .gap_1()
                                         .overflow_x_hidden()
                                         .child(
-                                            self.artist_name
-                                                .clone()
-                                                .unwrap_or("Unknown Artist".into()),
-                                        ),
-                                ),
-                        )
-                    }),
+                                            div()
+                                                .id("info-track-name")
+                                                .overflow_x_hidden()
+                                                .font_weight(FontWeight::EXTRA_BOLD)
+                                                .text_ellipsis()
+                                                .when_some(self.album_id, |this, album_id| {
+                                                    this.cursor_pointer()
+                                                        .hover(|this| {
+                                                            this.text_color(theme.text_link)
+                                                        })
+                                                        .on_click(move |_, _, cx| {
+                                                            let switcher = cx
+                                                                .global::<Models>()
+                                                                .switcher_model
+                                                                .clone();
+
+                                                            switcher.update(cx, |_, cx| {
+                                                                cx.emit(
+                                                                    ViewSwitchMessage::Release(
+                                                                        album_id,
+                                                                    ),
+                                                                );
+                                                            });
+                                                        })
+                                                })
+                                                .child(
+                                                    self.track_name.clone().unwrap_or(
+                                                        t("player.unknown_track").into(),
+                                                    ),
+                                                ),
+                                        )
+                                        // TODO: link the artist name once there is an artist view
+                                        .child(
+                                            div()
+                                                .overflow_x_hidden()
+                                                .when(format.is_none(), |this| this.pb(px(6.0)))
+                                                .text_ellipsis()
+                                                .overflow_x_hidden()
+                                                .child(
+                                                    self.artist_name.clone().unwrap_or(
+                                                        t("player.unknown_artist").into(),
+                                                    ),
+                                                ),
+                                        )
+                                        .when_some(format, |this, format| {
+                                            this.child(
+                                                div()
+                                                    .id("info-format")
+                                                    .overflow_x_hidden()
+                                                    .pb(px(6.0))
+                                                    .text_size(px(11.0))
+                                                    .text_color(theme.text_secondary)
+                                                    .text_ellipsis()
+                                                    .child(format),
+                                            )
+                                        }),
+                                )
+                            }),
+                    ),
+            )
+            .child(
+                div().bg(theme.elevated_background).child(
+                    menu()
+                        .item(menu_item(
+                            "info-copy-now-playing",
+                            None::<&str>,
+                            t("player.copy_now_playing"),
+                            |_, _, cx| copy_now_playing(cx, false),
+                        ))
+                        .item(menu_item(
+                            "info-copy-now-playing-time",
+                            None::<&str>,
+                            t("player.copy_now_playing_with_time"),
+                            |_, _, cx| copy_now_playing(cx, true),
+                        ))
+                        .item(menu_item(
+                            "info-queue-folder",
+                            None::<&str>,
+                            t("player.queue_folder"),
+                            |_, _, cx| queue_now_playing_folder(cx),
+                        )),
+                ),
             )
     }
 }
@@ -418,7 +552,7 @@ impl Render for PlaybackSection {
                                         menu.item(menu_item(
                                             "repeat-not-repeat",
                                             Some(REPEAT_OFF),
-                                            "Off",
+                                            t("player.repeat_off"),
                                             move |_, _, cx| {
                                                 cx.global::<GPUIPlaybackInterface>()
                                                     .set_repeat(RepeatState::NotRepeating);
@@ -428,7 +562,7 @@ impl Render for PlaybackSection {
                                     .item(menu_item(
                                         "repeat-repeat",
                                         Some(REPEAT),
-                                        "Repeat",
+                                        t("player.repeat"),
                                         move |_, _, cx| {
                                             cx.global::<GPUIPlaybackInterface>()
                                                 .set_repeat(RepeatState::Repeating);
@@ -437,7 +571,7 @@ impl Render for PlaybackSection {
                                     .item(menu_item(
                                         "repeat-repeat-one",
                                         Some(REPEAT_ONCE),
-                                        "Repeat One",
+                                        t("player.repeat_one"),
                                         move |_, _, cx| {
                                             cx.global::<GPUIPlaybackInterface>()
                                                 .set_repeat(RepeatState::RepeatingOne);
@@ -450,10 +584,72 @@ impl Render for PlaybackSection {
     }
 }
 
+/// How far the arrow keys move the scrubber, in seconds.
+const SCRUBBER_STEP_SECS: u64 = 5;
+/// How far Page Up and Page Down move the scrubber, in seconds.
+const SCRUBBER_PAGE_STEP_SECS: u64 = 30;
+
+/// Converts a number of seconds into a fraction of the track's duration, for use as a slider
+/// step.
+fn seconds_to_fraction(seconds: u64, duration: u64) -> f32 {
+    if duration == 0 {
+        return 0.0;
+    }
+
+    (seconds as f32 / duration as f32).min(1.0)
+}
+
+/// Describes the current track's format and the format it's played at, e.g.
+/// `FLAC 96 kHz/24-bit → 48 kHz/32-bit`. The output format is left out when nothing is playing.
+fn format_label(format: &PlaybackFormat) -> Option<String> {
+    fn rate(rate: u32) -> String {
+        let khz = rate as f64 / 1000.0;
+        if khz.fract() == 0.0 {
+            format!("{khz:.0} kHz")
+        } else {
+            format!("{khz:.1} kHz")
+        }
+    }
+
+    let source = format.source.as_ref()?;
+    let mut label = [
+        source.codec.map(str::to_uppercase),
+        source.sample_rate.map(rate),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" ");
+
+    if let Some(bit_depth) = source.bit_depth {
+        label += &format!("/{bit_depth}-bit");
+    }
+
+    if let Some(output) = &format.output {
+        // matches the rate the resampler converts to
+        let output_rate = output.sample_rate / output.rate_channel_ratio as u32 * 2;
+        label += &format!(" → {}", rate(output_rate));
+
+        if let Some(bit_depth) = output.sample_type.bit_depth() {
+            label += &format!("/{bit_depth}-bit");
+        }
+    }
+
+    (!label.is_empty()).then_some(label)
+}
+
+/// Formats a number of seconds as minutes and seconds, e.g. `03:07`.
+pub fn format_duration(seconds: u64) -> String {
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
 pub struct Scrubber {
     position: Entity<u64>,
     duration: Entity<u64>,
+    current_track: Entity<Option<CurrentTrack>>,
     playback_section: Entity<PlaybackSection>,
+    focus_handle: FocusHandle,
+    hover_position: Option<f32>,
 }
 
 impl Scrubber {
@@ -461,6 +657,7 @@ impl Scrubber {
         cx.new(|cx| {
             let position_model = cx.global::<PlaybackInfo>().position.clone();
             let duration_model = cx.global::<PlaybackInfo>().duration.clone();
+            let current_track_model = cx.global::<PlaybackInfo>().current_track.clone();
 
             cx.observe(&position_model, |_, _, cx| {
                 cx.notify();
@@ -472,10 +669,18 @@ impl Scrubber {
             })
             .detach();
 
+            cx.observe(&current_track_model, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
             Self {
                 position: position_model,
                 duration: duration_model,
+                current_track: current_track_model,
                 playback_section: PlaybackSection::new(cx),
+                focus_handle: cx.focus_handle(),
+                hover_position: None,
             }
         })
     }
@@ -486,9 +691,21 @@ impl Render for Scrubber {
         let theme = cx.global::<Theme>();
         let position = *self.position.read(cx);
         let duration = *self.duration.read(cx);
-        let remaining = duration - position;
+        let remaining = duration.saturating_sub(position);
+        let is_live = self
+            .current_track
+            .read(cx)
+            .as_ref()
+            .is_some_and(|track| is_stream_url(track.get_path()));
 
         let window_width = window.viewport_size().width;
+        let scrubber = cx.entity();
+
+        // the time under the cursor, hidden when the duration isn't known
+        let hover_time = self
+            .hover_position
+            .filter(|_| duration > 0 && !is_live)
+            .map(|v| (v as f64 * duration as f64).round() as u64);
 
         div()
             .pl(px(13.0))
@@ -509,44 +726,98 @@ impl Render for Scrubber {
                     .items_end()
                     .mt(px(6.0))
                     .mb(px(6.0))
-                    .child(div().mr(px(6.0)).line_height(rems(1.0)).child(format!(
-                        "{:02}:{:02}",
-                        position / 60,
-                        position % 60
-                    )))
-                    .when(window_width > px(900.0), |this| {
+                    .child(
+                        div()
+                            .mr(px(6.0))
+                            .line_height(rems(1.0))
+                            .child(format_duration(position)),
+                    )
+                    .when(window_width > px(900.0) && !is_live, |this| {
                         this.child(
                             div()
                                 .line_height(rems(1.0))
-                                .border_color(rgb(0x4b5563))
+                                .border_color(theme.border_color)
                                 .border_l(px(2.0))
                                 .pl(px(6.0))
-                                .text_color(rgb(0xcbd5e1))
-                                .child(format!("{:02}:{:02}", duration / 60, duration % 60)),
+                                .text_color(theme.text_secondary)
+                                .child(format_duration(duration)),
                         )
                     })
                     .child(self.playback_section.clone())
                     .child(div().h(px(30.0)))
-                    .child(div().ml(auto()).line_height(rems(1.0)).child(format!(
-                        "-{:02}:{:02}",
-                        remaining / 60,
-                        remaining % 60
-                    ))),
+                    .when(!is_live, |this| {
+                        this.child(
+                            div()
+                                .ml(auto())
+                                .line_height(rems(1.0))
+                                .child(format!("-{}", format_duration(remaining))),
+                        )
+                    })
+                    .when(is_live, |this| {
+                        this.child(
+                            div()
+                                .ml(auto())
+                                .line_height(rems(1.0))
+                                .text_color(theme.slider_foreground)
+                                .child(t("player.live")),
+                        )
+                    }),
             )
             .child(
-                slider()
+                div()
+                    .relative()
                     .w_full()
-                    .h(px(6.0))
-                    .rounded(px(3.0))
-                    .id("scrubber-back")
-                    .value(position as f32 / duration as f32)
-                    .on_change(move |v, _, cx| {
-                        let info = cx.global::<PlaybackInfo>().clone();
-
-                        if duration > 0 && *info.playback_state.read(cx) != PlaybackState::Stopped {
-                            cx.global::<GPUIPlaybackInterface>()
-                                .seek(v as f64 * duration as f64);
-                        }
+                    .child(
+                        slider()
+                            .w_full()
+                            .h(px(6.0))
+                            .rounded(px(3.0))
+                            .id("scrubber-back")
+                            .track_focus(&self.focus_handle)
+                            .step(seconds_to_fraction(SCRUBBER_STEP_SECS, duration))
+                            .page_step(seconds_to_fraction(SCRUBBER_PAGE_STEP_SECS, duration))
+                            .value(if is_live {
+                                1.0
+                            } else {
+                                position as f32 / duration as f32
+                            })
+                            .on_change(move |v, _, cx| {
+                                let info = cx.global::<PlaybackInfo>().clone();
+
+                                if duration > 0
+                                    && !is_live
+                                    && *info.playback_state.read(cx) != PlaybackState::Stopped
+                                {
+                                    cx.global::<GPUIPlaybackInterface>()
+                                        .seek(v as f64 * duration as f64);
+                                }
+                            })
+                            .on_hover(move |v, _, cx| {
+                                scrubber.update(cx, |this, cx| {
+                                    if this.hover_position != v {
+                                        this.hover_position = v;
+                                        cx.notify();
+                                    }
+                                });
+                            }),
+                    )
+                    .when_some(hover_time, |this, time| {
+                        this.child(
+                            div()
+                                .absolute()
+                                .bottom(px(12.0))
+                                .left(relative(self.hover_position.unwrap_or_default()))
+                                .ml(px(-28.0))
+                                .w(px(56.0))
+                                .py(px(2.0))
+                                .rounded(px(3.0))
+                                .border_1()
+                                .border_color(theme.border_color)
+                                .bg(theme.elevated_background)
+                                .text_size(px(12.0))
+                                .text_center()
+                                .child(format_duration(time)),
+                        )
                     }),
             )
     }
@@ -555,6 +826,8 @@ impl Render for Scrubber {
 pub struct SecondaryControls {
     info: PlaybackInfo,
     show_queue: Entity<bool>,
+    volume_focus: FocusHandle,
+    show_devices: bool,
 }
 
 impl SecondaryControls {
@@ -562,22 +835,105 @@ impl SecondaryControls {
         cx.new(|cx| {
             let info = cx.global::<PlaybackInfo>().clone();
             let volume = info.volume.clone();
+            let muted = info.muted.clone();
 
             cx.observe(&volume, |_, _, cx| {
                 cx.notify();
             })
             .detach();
 
-            Self { info, show_queue }
+            cx.observe(&muted, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            cx.observe(&info.device, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            cx.observe(&info.devices, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            Self {
+                info,
+                show_queue,
+                volume_focus: cx.focus_handle(),
+                show_devices: false,
+            }
         })
     }
 }
 
+impl SecondaryControls {
+    /// Lists the output devices, with the default device first. The device being played to is
+    /// checked, as is the default device if no other device was picked.
+    fn device_menu(&self, cx: &Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let active = self.info.device.read(cx).as_ref().map(|v| v.uid.clone());
+        let preferred = self.info.preferred_device.clone();
+        let using_default = preferred.read(cx).is_none();
+
+        let select = move |uid: Option<String>| {
+            let preferred = preferred.clone();
+
+            move |_: &ClickEvent, _: &mut Window, cx: &mut App| {
+                preferred.write(cx, uid.clone());
+                cx.global::<GPUIPlaybackInterface>().set_device(uid.clone());
+            }
+        };
+
+        let mut devices = menu()
+            .item(menu_item(
+                "device-default",
+                using_default.then_some(CHECK),
+                t("player.default_device"),
+                select(None),
+            ))
+            .item(CMenuItem::Seperator);
+
+        for (idx, device) in self.info.devices.read(cx).iter().enumerate() {
+            devices = devices.item(menu_item(
+                ("device", idx),
+                (active.as_ref() == Some(&device.uid)).then_some(CHECK),
+                device.name.clone(),
+                select(Some(device.uid.clone())),
+            ));
+        }
+
+        anchored()
+            .anchor(Corner::BottomRight)
+            .snap_to_window_with_margin(px(8.0))
+            .child(deferred(
+                div()
+                    .occlude()
+                    .border_1()
+                    .shadow_sm()
+                    .rounded(px(6.0))
+                    .border_color(theme.elevated_border_color)
+                    .bg(theme.elevated_background)
+                    .id("device-menu")
+                    .on_click(cx.listener(|this, _, _, cx| {
+                        this.show_devices = false;
+                        cx.notify();
+                    }))
+                    .on_mouse_down_out(cx.listener(|this, _, _, cx| {
+                        this.show_devices = false;
+                        cx.notify();
+                    }))
+                    .child(devices),
+            ))
+    }
+}
+
 impl Render for SecondaryControls {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
         let volume = *self.info.volume.read(cx);
         let prev_volume = *self.info.prev_volume.read(cx);
+        let muted = *self.info.muted.read(cx);
         let show_queue = self.show_queue.clone();
 
         div().px(px(18.0)).flex().child(
@@ -601,16 +957,24 @@ impl Render for SecondaryControls {
                         .bg(theme.playback_button)
                         .hover(|this| this.bg(theme.playback_button_hover))
                         .active(|this| this.bg(theme.playback_button_active))
-                        .when(volume <= 0.0, |div| {
+                        .when(muted, |div| {
+                            div.child(icon(VOLUME_OFF).size(px(14.0)))
+                                .on_click(move |_, _, cx| {
+                                    cx.global::<GPUIPlaybackInterface>().toggle_mute();
+                                })
+                        })
+                        .when(!muted && volume <= 0.0, |div| {
+                            // the volume was dragged to zero rather than muted, so there's no
+                            // exact volume to restore
                             div.child(icon(VOLUME_OFF).size(px(14.0)))
                                 .on_click(move |_, _, cx| {
                                     cx.global::<GPUIPlaybackInterface>().set_volume(prev_volume);
                                 })
                         })
-                        .when(volume > 0.0, |div| {
+                        .when(!muted && volume > 0.0, |div| {
                             div.child(icon(VOLUME).size(px(14.0)))
                                 .on_click(move |_, _, cx| {
-                                    cx.global::<GPUIPlaybackInterface>().set_volume(0 as f64);
+                                    cx.global::<GPUIPlaybackInterface>().toggle_mute();
                                 })
                         }),
                 )
@@ -623,6 +987,7 @@ impl Render for SecondaryControls {
                                 .mt(px(11.0))
                                 .rounded(px(3.0))
                                 .id("volume")
+                                .track_focus(&self.volume_focus)
                                 .value((volume) as f32)
                                 .on_change(move |v, _, cx| {
                                     cx.global::<GPUIPlaybackInterface>().set_volume(v as f64);
@@ -637,6 +1002,33 @@ impl Render for SecondaryControls {
                             ));
                         }),
                 )
+                .child(
+                    div()
+                        .relative()
+                        .child(
+                            div()
+                                .rounded(px(3.0))
+                                .w(px(28.0))
+                                .h(px(25.0))
+                                .mt(px(2.0))
+                                .flex()
+                                .items_center()
+                                .justify_center()
+                                .border_color(theme.playback_button_border)
+                                .id("device-button")
+                                .cursor_pointer()
+                                .bg(theme.playback_button)
+                                .hover(|this| this.bg(theme.playback_button_hover))
+                                .active(|this| this.bg(theme.playback_button_active))
+                                .child(icon(SPEAKER).size(px(14.0)))
+                                // the menu closes itself on any click outside of it, so this only has to open it
+                                .on_click(cx.listener(|this, _, _, cx| {
+                                    this.show_devices = true;
+                                    cx.notify();
+                                })),
+                        )
+                        .when(self.show_devices, |this| this.child(self.device_menu(cx))),
+                )
                 .child(
                     div()
                         .rounded(px(3.0))
@@ -671,3 +1063,76 @@ impl Render for EmptyView {
         div()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::{
+        devices::format::{BufferSize, ChannelSpec, FormatInfo, SampleFormat},
+        media::metadata::SourceFormat,
+    };
+
+    fn source(codec: &'static str, sample_rate: u32, bit_depth: Option<u32>) -> SourceFormat {
+        SourceFormat {
+            codec: Some(codec),
+            sample_rate: Some(sample_rate),
+            bit_depth,
+        }
+    }
+
+    /// A stereo output stream.
+    fn output(sample_rate: u32, sample_type: SampleFormat) -> FormatInfo {
+        FormatInfo {
+            originating_provider: "test",
+            sample_type,
+            sample_rate,
+            buffer_size: BufferSize::Unknown,
+            channels: ChannelSpec::Count(2),
+            rate_channel_ratio: 2,
+            rate_channel_ratio_fixed: false,
+        }
+    }
+
+    #[test]
+    fn both_formats_are_described() {
+        let format = PlaybackFormat {
+            source: Some(source("flac", 96000, Some(24))),
+            output: Some(output(48000, SampleFormat::Float32)),
+        };
+
+        let label = format_label(&format);
+        assert_eq!(label.as_deref(), Some("FLAC 96 kHz/24-bit → 48 kHz/32-bit"));
+    }
+
+    #[test]
+    fn lossy_tracks_have_no_bit_depth() {
+        let format = PlaybackFormat {
+            source: Some(source("mp3", 44100, None)),
+            output: Some(output(44100, SampleFormat::Signed16)),
+        };
+
+        let label = format_label(&format);
+        assert_eq!(label.as_deref(), Some("MP3 44.1 kHz → 44.1 kHz/16-bit"));
+    }
+
+    #[test]
+    fn the_output_is_left_out_without_a_stream() {
+        let format = PlaybackFormat {
+            source: Some(source("flac", 44100, Some(16))),
+            output: None,
+        };
+
+        let label = format_label(&format);
+        assert_eq!(label.as_deref(), Some("FLAC 44.1 kHz/16-bit"));
+    }
+
+    #[test]
+    fn nothing_is_described_without_a_track() {
+        let format = PlaybackFormat {
+            source: None,
+            output: Some(output(48000, SampleFormat::Float32)),
+        };
+
+        assert_eq!(format_label(&format), None);
+    }
+}
@@ -1,15 +1,23 @@
-use std::{fs::File, hash::Hasher, io::Cursor, sync::Arc};
+use std::{
+    fs::File,
+    hash::Hasher,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use ahash::AHasher;
 use async_lock::Mutex;
 use gpui::{App, AppContext, Entity, Global, RenderImage, SharedString, Task};
 use image::{Frame, ImageReader, imageops::thumbnail};
 use smallvec::smallvec;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 use crate::{
+    player::library::db::get_track_by_id,
     player::media::{builtin::symphonia::SymphoniaProvider, traits::MediaProvider},
-    player::playback::queue::{DataSource, QueueItemUIData},
+    player::playback::queue::{DataSource, QueueItemDetails, QueueItemUIData},
+    player::ui::app::Pool,
     shared::util::rgb_to_bgr,
 };
 
@@ -49,7 +57,7 @@ fn decode_image(data: Box<[u8]>, thumb: bool) -> anyhow::Result<Arc<RenderImage>
     Ok(Arc::new(RenderImage::new(smallvec![frame])))
 }
 
-async fn read_metadata(path: String, cache: &mut AlbumCache) -> anyhow::Result<QueueItemUIData> {
+async fn read_metadata(path: PathBuf, cache: &mut AlbumCache) -> anyhow::Result<QueueItemUIData> {
     let file = File::open(&path)?;
 
     // TODO: Switch to a different media provider based on the file
@@ -97,6 +105,23 @@ async fn read_metadata(path: String, cache: &mut AlbumCache) -> anyhow::Result<Q
     })
 }
 
+/// Reads the title, artist and duration of a file that isn't in the library.
+fn read_details(path: &Path) -> anyhow::Result<QueueItemDetails> {
+    let file = File::open(path)?;
+
+    let mut media_provider = SymphoniaProvider::default();
+    media_provider.open(file, None)?;
+    media_provider.start_playback()?;
+
+    let metadata = media_provider.read_metadata()?;
+
+    Ok(QueueItemDetails {
+        title: metadata.name.as_ref().map(SharedString::from),
+        artist: metadata.artist.as_ref().map(SharedString::from),
+        duration: media_provider.duration_secs().ok(),
+    })
+}
+
 pub trait Decode {
     fn decode_image(
         &self,
@@ -104,7 +129,13 @@ pub trait Decode {
         thumb: bool,
         entity: Entity<Option<Arc<RenderImage>>>,
     ) -> Task<()>;
-    fn read_metadata(&self, path: String, entity: Entity<Option<QueueItemUIData>>) -> Task<()>;
+    fn read_metadata(&self, path: PathBuf, entity: Entity<Option<QueueItemUIData>>) -> Task<()>;
+    fn read_details(
+        &self,
+        path: PathBuf,
+        track_id: Option<i64>,
+        entity: Entity<Option<QueueItemDetails>>,
+    ) -> Task<()>;
 }
 
 impl Decode for App {
@@ -139,7 +170,7 @@ impl Decode for App {
         })
     }
 
-    fn read_metadata(&self, path: String, entity: Entity<Option<QueueItemUIData>>) -> Task<()> {
+    fn read_metadata(&self, path: PathBuf, entity: Entity<Option<QueueItemUIData>>) -> Task<()> {
         let mut cache = self.global::<AlbumCache>().clone();
 
         self.spawn(async move |cx| {
@@ -160,4 +191,42 @@ impl Decode for App {
                 .expect("Failed to update entity");
         })
     }
+
+    fn read_details(
+        &self,
+        path: PathBuf,
+        track_id: Option<i64>,
+        entity: Entity<Option<QueueItemDetails>>,
+    ) -> Task<()> {
+        let db = self.global::<Pool>().0.clone();
+
+        self.spawn(async move |cx| {
+            let read_task = cx
+                .background_spawn(async move {
+                    // library tracks are looked up in the database, other files are read from disk
+                    if let Some(id) = track_id {
+                        match get_track_by_id(&db, id).await {
+                            Ok(track) => return Ok(QueueItemDetails::from(track.as_ref())),
+                            Err(e) => warn!("Could not look up track {}: {:?}", id, e),
+                        }
+                    }
+
+                    read_details(&path)
+                        .inspect_err(|e| warn!("Could not read the details of {:?}: {:?}", path, e))
+                })
+                .await;
+
+            // the item is left with empty details, so the file isn't read again
+            let Ok(details) = read_task else {
+                return;
+            };
+
+            entity
+                .update(cx, |m, cx| {
+                    *m = Some(details);
+                    cx.notify();
+                })
+                .expect("Failed to update entity");
+        })
+    }
 }
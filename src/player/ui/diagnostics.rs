@@ -0,0 +1,112 @@
+use std::{fs, path::PathBuf};
+
+use gpui::{App, ClipboardItem};
+use tracing::error;
+
+use crate::{
+    player::library::db::LibraryAccess, player::playback::interface::GPUIPlaybackInterface,
+    shared::logging::effective_filter,
+};
+
+use super::{
+    app::{MUSIC_DB_FILE, data_dir},
+    models::{Models, PlaybackInfo},
+};
+
+/// Details about the library, shown alongside the playback diagnostics.
+#[derive(Debug, Clone)]
+pub struct LibraryDiagnostics {
+    pub track_count: i64,
+    pub album_count: i64,
+    pub db_path: PathBuf,
+    /// The size of the database file in bytes, if it could be read.
+    pub db_size: Option<u64>,
+}
+
+/// Gathers the library details and asks the playback thread to describe the audio pipeline. The
+/// results arrive in `Models::library_diagnostics` and `PlaybackInfo::diagnostics`.
+pub fn refresh_diagnostics(cx: &mut App) {
+    cx.global::<GPUIPlaybackInterface>().request_diagnostics();
+
+    let db_path = data_dir().join(MUSIC_DB_FILE);
+    let library = LibraryDiagnostics {
+        track_count: cx
+            .get_track_stats()
+            .inspect_err(|e| error!("Could not count tracks: {:?}", e))
+            .map_or(0, |stats| stats.track_count),
+        album_count: cx
+            .count_albums()
+            .inspect_err(|e| error!("Could not count albums: {:?}", e))
+            .unwrap_or_default(),
+        db_size: fs::metadata(&db_path).ok().map(|metadata| metadata.len()),
+        db_path,
+    };
+
+    let library_diagnostics = cx.global::<Models>().library_diagnostics.clone();
+    library_diagnostics.write(cx, Some(library));
+}
+
+/// Describes the app, the audio pipeline and the library, one detail per line.
+pub fn diagnostics_lines(cx: &App) -> Vec<String> {
+    let version = env!("CARGO_PKG_VERSION");
+    let hash = option_env!("VERGEN_GIT_SHA").unwrap_or("unknown");
+    let playback = cx.global::<PlaybackInfo>().diagnostics.read(cx);
+    let library = cx.global::<Models>().library_diagnostics.read(cx);
+
+    let mut lines = vec![
+        format!("Version: {version} ({hash})"),
+        format!("OS: {} ({})", std::env::consts::OS, std::env::consts::ARCH),
+    ];
+
+    match playback {
+        Some(playback) => {
+            let device = playback
+                .device
+                .as_ref()
+                .map_or("None", |device| device.name.as_str());
+
+            lines.push(format!(
+                "Device provider: {}",
+                playback.device_provider.unwrap_or("None")
+            ));
+            lines.push(format!("Media provider: {}", playback.media_provider));
+            lines.push(format!("Output device: {device}"));
+
+            lines.push(match &playback.format {
+                Some(format) => format!(
+                    "Output format: {} Hz, {}, {} channels",
+                    format.sample_rate,
+                    format.sample_type.bit_depth().map_or_else(
+                        || format!("{:?}", format.sample_type),
+                        |depth| format!("{depth}-bit {:?}", format.sample_type)
+                    ),
+                    format.channels.count()
+                ),
+                None => "Output format: None".to_string(),
+            });
+        }
+        None => lines.push("Playback: waiting for the playback thread".to_string()),
+    }
+
+    if let Some(library) = library {
+        lines.push(format!(
+            "Library: {} tracks, {} albums",
+            library.track_count, library.album_count
+        ));
+        lines.push(format!("Database: {}", library.db_path.display()));
+        lines.push(match library.db_size {
+            Some(size) => format!("Database size: {:.1} MB", size as f64 / (1024.0 * 1024.0)),
+            None => "Database size: unknown".to_string(),
+        });
+    }
+
+    lines.push(format!("Log filter: {}", effective_filter()));
+
+    lines
+}
+
+/// Copies the diagnostics to the clipboard, to be pasted into a bug report.
+pub fn copy_diagnostics(cx: &mut App) {
+    let text = diagnostics_lines(cx).join("\n");
+    cx.write_to_clipboard(ClipboardItem::new_string(text));
+}
@@ -0,0 +1,86 @@
+use gpui::{prelude::FluentBuilder, *};
+
+use crate::player::{library::db::LibraryAccess, playback::queue::QueueItemData, ui::theme::Theme};
+
+/// What is being dragged. Only ids are carried, and they're resolved into queue items when
+/// dropped, so that starting a drag doesn't have to query the library.
+#[derive(Clone)]
+pub enum DragSource {
+    Tracks(Vec<i64>),
+    /// Every track in the album.
+    Album(i64),
+}
+
+/// Tracks from the library being dragged onto the queue.
+#[derive(Clone)]
+pub struct DraggedTracks {
+    pub source: DragSource,
+    pub title: SharedString,
+    pub image: Option<SharedString>,
+}
+
+impl DraggedTracks {
+    /// Looks up every dragged track, skipping any that have since been removed from the library.
+    pub fn queue_items(&self, cx: &mut App) -> Vec<QueueItemData> {
+        match &self.source {
+            DragSource::Tracks(track_ids) => {
+                let tracks: Vec<_> = track_ids
+                    .iter()
+                    .filter_map(|id| cx.get_track_by_id(*id).ok())
+                    .collect();
+
+                tracks
+                    .iter()
+                    .map(|track| QueueItemData::from_track(cx, track))
+                    .collect()
+            }
+            DragSource::Album(album_id) => {
+                let Ok(tracks) = cx.list_tracks_in_album(*album_id) else {
+                    return Vec::new();
+                };
+
+                tracks
+                    .iter()
+                    .map(|track| QueueItemData::from_track(cx, track))
+                    .collect()
+            }
+        }
+    }
+}
+
+impl Render for DraggedTracks {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+
+        div()
+            .flex()
+            .gap(px(8.0))
+            .items_center()
+            .max_w(px(250.0))
+            .px(px(8.0))
+            .py(px(6.0))
+            .rounded(px(4.0))
+            .border_1()
+            .border_color(theme.border_color)
+            .bg(theme.elevated_background)
+            .text_sm()
+            .child(
+                div()
+                    .w(px(22.0))
+                    .h(px(22.0))
+                    .flex_shrink_0()
+                    .rounded(px(3.0))
+                    .bg(theme.album_art_background)
+                    .when_some(self.image.clone(), |div, image| {
+                        div.child(img(image).w(px(22.0)).h(px(22.0)).rounded(px(3.0)))
+                    }),
+            )
+            .child(
+                div()
+                    .overflow_hidden()
+                    .text_ellipsis()
+                    .whitespace_nowrap()
+                    .child(self.title.clone()),
+            )
+    }
+}
@@ -1,13 +1,62 @@
-use gpui::{App, KeyBinding, Menu, MenuItem, SharedString, actions};
-use tracing::{debug, info};
+use gpui::{App, ClipboardItem, KeyBinding, Menu, MenuItem, SharedString, actions};
+use tracing::{debug, error, info};
 
-use crate::player::playback::{interface::GPUIPlaybackInterface, thread::PlaybackState};
+use crate::{
+    player::library::scan::ScanInterface,
+    player::playback::{
+        events::RepeatState,
+        interface::{GPUIPlaybackInterface, shuffle_library},
+        thread::PlaybackState,
+    },
+    shared::{
+        cache,
+        settings::{
+            interface::{AlbumArtSize, ThemeMode},
+            update_settings,
+        },
+    },
+};
 
-use super::models::{Models, PlaybackInfo};
+use super::{
+    controls::format_duration,
+    header::close_window,
+    models::{Models, PlaybackInfo},
+    settings::{confirm_rebuild_library, prompt_to_export_settings, prompt_to_import_settings},
+};
 
 actions!(
     hummingbird,
-    [About, Quit, PlayPause, Next, Previous, Search]
+    [
+        About,
+        OpenSettings,
+        ExportSettings,
+        ImportSettings,
+        Quit,
+        CloseWindow,
+        PlayPause,
+        Next,
+        Previous,
+        RestartTrack,
+        Search,
+        ClearCache,
+        ScanLibrary,
+        RebuildLibrary,
+        ToggleMiniPlayer,
+        ToggleAlwaysOnTop,
+        CopyNowPlaying,
+        CopyNowPlayingWithTime,
+        UseDarkTheme,
+        UseLightTheme,
+        UseSystemTheme,
+        LargerAlbumArt,
+        SmallerAlbumArt,
+        ToggleShuffle,
+        ToggleRepeat,
+        ShuffleLibrary,
+        NewChat,
+        FocusChat,
+        OpenCommandPalette
+    ]
 );
 
 actions!(hummingbird, [HideSelf, HideOthers, ShowAll]);
@@ -15,27 +64,64 @@ actions!(hummingbird, [HideSelf, HideOthers, ShowAll]);
 pub fn register_actions(cx: &mut App) {
     debug!("registering actions");
     cx.on_action(quit);
+    cx.on_action(close_active_window);
     cx.on_action(play_pause);
     cx.on_action(next);
     cx.on_action(previous);
+    cx.on_action(restart_track);
     cx.on_action(hide_self);
     cx.on_action(hide_others);
     cx.on_action(show_all);
     cx.on_action(about);
+    cx.on_action(open_settings);
+    cx.on_action(|_: &ExportSettings, cx| prompt_to_export_settings(cx));
+    cx.on_action(|_: &ImportSettings, cx| prompt_to_import_settings(cx));
+    cx.on_action(clear_cache);
+    cx.on_action(scan_library);
+    cx.on_action(rebuild_library);
+    cx.on_action(toggle_mini_player);
+    cx.on_action(toggle_always_on_top);
+    cx.on_action(toggle_shuffle);
+    cx.on_action(toggle_repeat);
+    cx.on_action(|_: &ShuffleLibrary, cx| shuffle_library(cx));
+    cx.on_action(|_: &CopyNowPlaying, cx| copy_now_playing(cx, false));
+    cx.on_action(|_: &CopyNowPlayingWithTime, cx| copy_now_playing(cx, true));
+    cx.on_action(|_: &UseDarkTheme, cx| set_theme_mode(cx, ThemeMode::Dark));
+    cx.on_action(|_: &UseLightTheme, cx| set_theme_mode(cx, ThemeMode::Light));
+    cx.on_action(|_: &UseSystemTheme, cx| set_theme_mode(cx, ThemeMode::System));
+    cx.on_action(|_: &LargerAlbumArt, cx| resize_album_art(cx, AlbumArtSize::larger));
+    cx.on_action(|_: &SmallerAlbumArt, cx| resize_album_art(cx, AlbumArtSize::smaller));
     debug!("actions: {:?}", cx.all_action_names());
     debug!("action available: {:?}", cx.is_action_available(&Quit));
     if cfg!(target_os = "macos") {
         cx.bind_keys([KeyBinding::new("cmd-q", Quit, None)]);
+        cx.bind_keys([KeyBinding::new("cmd-w", CloseWindow, None)]);
         cx.bind_keys([KeyBinding::new("cmd-right", Next, None)]);
         cx.bind_keys([KeyBinding::new("cmd-left", Previous, None)]);
+        cx.bind_keys([KeyBinding::new("cmd-shift-left", RestartTrack, None)]);
         cx.bind_keys([KeyBinding::new("cmd-f", Search, None)]);
+        cx.bind_keys([KeyBinding::new("cmd-l", FocusChat, None)]);
         cx.bind_keys([KeyBinding::new("cmd-h", HideSelf, None)]);
         cx.bind_keys([KeyBinding::new("cmd-alt-h", HideOthers, None)]);
+        cx.bind_keys([KeyBinding::new("cmd-shift-m", ToggleMiniPlayer, None)]);
+        cx.bind_keys([KeyBinding::new("cmd-,", OpenSettings, None)]);
+        cx.bind_keys([KeyBinding::new("cmd-shift-c", CopyNowPlaying, None)]);
+        cx.bind_keys([KeyBinding::new("cmd-shift-p", OpenCommandPalette, None)]);
+        cx.bind_keys([KeyBinding::new("cmd-=", LargerAlbumArt, None)]);
+        cx.bind_keys([KeyBinding::new("cmd--", SmallerAlbumArt, None)]);
     } else {
-        cx.bind_keys([KeyBinding::new("ctrl-w", Quit, None)]);
+        cx.bind_keys([KeyBinding::new("ctrl-w", CloseWindow, None)]);
         cx.bind_keys([KeyBinding::new("ctrl-right", Next, None)]);
         cx.bind_keys([KeyBinding::new("ctrl-left", Previous, None)]);
+        cx.bind_keys([KeyBinding::new("ctrl-shift-left", RestartTrack, None)]);
         cx.bind_keys([KeyBinding::new("ctrl-f", Search, None)]);
+        cx.bind_keys([KeyBinding::new("ctrl-l", FocusChat, None)]);
+        cx.bind_keys([KeyBinding::new("ctrl-shift-m", ToggleMiniPlayer, None)]);
+        cx.bind_keys([KeyBinding::new("ctrl-,", OpenSettings, None)]);
+        cx.bind_keys([KeyBinding::new("ctrl-shift-c", CopyNowPlaying, None)]);
+        cx.bind_keys([KeyBinding::new("ctrl-shift-p", OpenCommandPalette, None)]);
+        cx.bind_keys([KeyBinding::new("ctrl-=", LargerAlbumArt, None)]);
+        cx.bind_keys([KeyBinding::new("ctrl--", SmallerAlbumArt, None)]);
     }
     cx.bind_keys([KeyBinding::new("space", PlayPause, None)]);
     cx.set_menus(vec![
@@ -44,6 +130,10 @@ pub fn register_actions(cx: &mut App) {
             items: vec![
                 MenuItem::action("About MrChat", About),
                 MenuItem::separator(),
+                MenuItem::action("Settings…", OpenSettings),
+                MenuItem::action("Export Settings…", ExportSettings),
+                MenuItem::action("Import Settings…", ImportSettings),
+                MenuItem::separator(),
                 MenuItem::submenu(Menu {
                     name: SharedString::from("Services"),
                     items: vec![],
@@ -56,13 +146,40 @@ pub fn register_actions(cx: &mut App) {
                 MenuItem::action("Quit MrChat", Quit),
             ],
         },
+        Menu {
+            name: SharedString::from("Edit"),
+            items: vec![
+                MenuItem::action("Copy Now Playing", CopyNowPlaying),
+                MenuItem::action("Copy Now Playing with Time", CopyNowPlayingWithTime),
+            ],
+        },
         Menu {
             name: SharedString::from("View"),
-            items: vec![],
+            items: vec![
+                MenuItem::action("Command Palette…", OpenCommandPalette),
+                MenuItem::action("Mini Player", ToggleMiniPlayer),
+                MenuItem::action("Always on Top", ToggleAlwaysOnTop),
+                MenuItem::submenu(Menu {
+                    name: SharedString::from("Theme"),
+                    items: vec![
+                        MenuItem::action("Dark", UseDarkTheme),
+                        MenuItem::action("Light", UseLightTheme),
+                        MenuItem::action("Match System", UseSystemTheme),
+                    ],
+                }),
+                MenuItem::action("Larger Album Art", LargerAlbumArt),
+                MenuItem::action("Smaller Album Art", SmallerAlbumArt),
+                MenuItem::separator(),
+                MenuItem::action("Shuffle Library", ShuffleLibrary),
+                MenuItem::action("Scan Library", ScanLibrary),
+                MenuItem::action("Rebuild Library…", RebuildLibrary),
+                MenuItem::separator(),
+                MenuItem::action("Clear Cache", ClearCache),
+            ],
         },
         Menu {
             name: SharedString::from("Window"),
-            items: vec![],
+            items: vec![MenuItem::action("Close Window", CloseWindow)],
         },
     ]);
 }
@@ -72,6 +189,15 @@ fn quit(_: &Quit, cx: &mut App) {
     cx.quit();
 }
 
+/// Closes the window the way its close button does, so it follows the close action setting.
+fn close_active_window(_: &CloseWindow, cx: &mut App) {
+    if let Some(window) = cx.active_window() {
+        window
+            .update(cx, |_, window, cx| close_window(window, cx))
+            .ok();
+    }
+}
+
 fn play_pause(_: &PlayPause, cx: &mut App) {
     let state = cx.global::<PlaybackInfo>().playback_state.read(cx);
     let interface = cx.global::<GPUIPlaybackInterface>();
@@ -102,6 +228,11 @@ fn previous(_: &Previous, cx: &mut App) {
     interface.previous();
 }
 
+fn restart_track(_: &RestartTrack, cx: &mut App) {
+    let interface = cx.global::<GPUIPlaybackInterface>();
+    interface.restart();
+}
+
 fn hide_self(_: &HideSelf, cx: &mut App) {
     cx.hide();
 }
@@ -118,3 +249,104 @@ fn about(_: &About, cx: &mut App) {
     let show_about = cx.global::<Models>().show_about.clone();
     show_about.write(cx, true);
 }
+
+fn open_settings(_: &OpenSettings, cx: &mut App) {
+    let show_settings = cx.global::<Models>().show_settings.clone();
+    show_settings.write(cx, true);
+}
+
+fn clear_cache(_: &ClearCache, cx: &mut App) {
+    if let Err(e) = cache::clear_cache() {
+        error!("Failed to clear cache: {:?}", e);
+    }
+
+    let cache_size = cx.global::<Models>().cache_size.clone();
+    cache_size.write(cx, cache::cache_size());
+}
+
+fn scan_library(_: &ScanLibrary, cx: &mut App) {
+    cx.global::<ScanInterface>().scan();
+}
+
+fn rebuild_library(_: &RebuildLibrary, cx: &mut App) {
+    let Some(window) = cx.active_window() else {
+        return;
+    };
+
+    window
+        .update(cx, |_, window, cx| confirm_rebuild_library(window, cx))
+        .ok();
+}
+
+fn toggle_mini_player(_: &ToggleMiniPlayer, cx: &mut App) {
+    let mini_player = cx.global::<Models>().mini_player.clone();
+    mini_player.update(cx, |v, cx| {
+        *v = !*v;
+        cx.notify();
+    });
+}
+
+fn toggle_always_on_top(_: &ToggleAlwaysOnTop, cx: &mut App) {
+    update_settings(cx, |settings| {
+        settings.interface.always_on_top = !settings.interface.always_on_top
+    });
+}
+
+fn toggle_shuffle(_: &ToggleShuffle, cx: &mut App) {
+    cx.global::<GPUIPlaybackInterface>().toggle_shuffle();
+}
+
+fn toggle_repeat(_: &ToggleRepeat, cx: &mut App) {
+    let next = match cx.global::<PlaybackInfo>().repeating.read(cx) {
+        RepeatState::NotRepeating => RepeatState::Repeating,
+        RepeatState::Repeating => RepeatState::RepeatingOne,
+        RepeatState::RepeatingOne => RepeatState::NotRepeating,
+    };
+
+    cx.global::<GPUIPlaybackInterface>().set_repeat(next);
+}
+
+/// Formats the current track as "Artist - Title (Album)", optionally followed by the playback
+/// position. Returns None if nothing is playing.
+fn now_playing_text(cx: &App, with_time: bool) -> Option<String> {
+    let info = cx.global::<PlaybackInfo>();
+
+    if *info.playback_state.read(cx) == PlaybackState::Stopped {
+        return None;
+    }
+
+    let metadata = cx.global::<Models>().metadata.read(cx);
+    let artist = metadata.artist.as_deref().unwrap_or("Unknown Artist");
+    let title = metadata.name.as_deref().unwrap_or("Unknown Track");
+
+    let mut text = format!("{artist} - {title}");
+
+    if let Some(album) = metadata.album.as_deref() {
+        text.push_str(&format!(" ({album})"));
+    }
+
+    if with_time {
+        text.push_str(&format!(" [{}]", format_duration(*info.position.read(cx))));
+    }
+
+    Some(text)
+}
+
+pub fn copy_now_playing(cx: &mut App, with_time: bool) {
+    let Some(text) = now_playing_text(cx, with_time) else {
+        return;
+    };
+
+    cx.write_to_clipboard(ClipboardItem::new_string(text));
+}
+
+fn set_theme_mode(cx: &mut App, mode: ThemeMode) {
+    // the theme is reloaded by the settings observer in `setup_theme`
+    update_settings(cx, |settings| settings.interface.theme = mode);
+}
+
+fn resize_album_art(cx: &mut App, resize: fn(AlbumArtSize) -> AlbumArtSize) {
+    update_settings(cx, |settings| {
+        settings.interface.album_art_size = resize(settings.interface.album_art_size)
+    });
+}
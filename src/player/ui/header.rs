@@ -4,9 +4,11 @@ use gpui::*;
 use prelude::FluentBuilder;
 
 use crate::{
-    player::library::scan::ScanEvent,
+    player::library::scan::{ScanEvent, ScanInterface},
     player::services::mmb::lastfm::{LASTFM_API_KEY, LASTFM_API_SECRET},
+    player::services::tray::hide_window,
     player::ui::components::icons::{CROSS, FOLDER_CHECK, FOLDER_SEARCH, MAXIMIZE, MINUS, icon},
+    shared::settings::{SettingsGlobal, interface::CloseAction},
 };
 
 use super::{constants::APP_ROUNDING, models::Models, theme::Theme};
@@ -162,12 +164,52 @@ impl Render for ScanStatus {
                 ScanEvent::DiscoverProgress(progress) => {
                     format!("Discovering files ({progress})")
                 }
-                ScanEvent::Cleaning => "".to_string(),
+                ScanEvent::Cleaning => "Cleaning up".to_string(),
                 ScanEvent::ScanCompleteWatching => "Watching for updates".to_string(),
             })
+            .child(scan_control(status, theme))
     }
 }
 
+/// The button next to the scan status that starts a scan when the scanner is idle, or stops the
+/// current scan. Cleanup can't be interrupted, so no button is shown while it's running.
+fn scan_control(status: &ScanEvent, theme: &Theme) -> impl IntoElement {
+    let label = match status {
+        ScanEvent::ScanCompleteIdle | ScanEvent::ScanCompleteWatching => Some("Scan now"),
+        ScanEvent::DiscoverProgress(_) | ScanEvent::ScanProgress { .. } => Some("Stop"),
+        ScanEvent::Cleaning => None,
+    };
+    let scanning = matches!(
+        status,
+        ScanEvent::DiscoverProgress(_) | ScanEvent::ScanProgress { .. }
+    );
+
+    div().when_some(label, |this, label| {
+        this.child(
+            div()
+                .id("scan-control")
+                .ml(px(8.0))
+                .cursor_pointer()
+                .text_color(theme.text_link)
+                .hover(|this| this.text_color(theme.text))
+                .on_mouse_down(MouseButton::Left, |_, window, cx| {
+                    window.prevent_default();
+                    cx.stop_propagation();
+                })
+                .on_click(move |_, _, cx| {
+                    let scan_interface = cx.global::<ScanInterface>();
+
+                    if scanning {
+                        scan_interface.stop();
+                    } else {
+                        scan_interface.scan();
+                    }
+                })
+                .child(label),
+        )
+    })
+}
+
 #[derive(PartialEq, Clone, Copy, IntoElement)]
 pub enum WindowButton {
     Close,
@@ -229,9 +271,21 @@ impl RenderOnce for WindowButton {
             )
             .when(self == WindowButton::Close, |this| this.rounded_tr(px(4.0)))
             .on_click(move |_, window, cx| match self {
-                WindowButton::Close => cx.quit(),
+                WindowButton::Close => close_window(window, cx),
                 WindowButton::Minimize => window.minimize_window(),
                 WindowButton::Maximize => window.zoom_window(),
             })
     }
 }
+
+/// Closes the main window as the settings ask: by quitting, or by hiding the window behind the tray
+/// icon and leaving playback running in the background.
+pub fn close_window(window: &mut Window, cx: &mut App) {
+    let settings = cx.global::<SettingsGlobal>().model.read(cx);
+    let action = settings.interface.close_action;
+
+    match action {
+        CloseAction::Quit => cx.quit(),
+        CloseAction::KeepPlaying => hide_window(window, cx),
+    }
+}
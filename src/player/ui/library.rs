@@ -3,6 +3,7 @@ use std::collections::VecDeque;
 use album_view::AlbumView;
 use gpui::*;
 use navigation::NavigationView;
+use radio_view::RadioView;
 use release_view::ReleaseView;
 use tracing::debug;
 
@@ -13,15 +14,17 @@ use super::models::Models;
 mod album_view;
 mod navigation;
 mod playlist_view;
+mod radio_view;
 mod release_view;
 mod sidebar;
-mod track_listing;
+pub mod track_listing;
 
 #[derive(Clone)]
 enum LibraryView {
     Album(Entity<AlbumView>),
     Release(Entity<ReleaseView>),
     Playlist(Entity<PlaylistView>),
+    Radio(Entity<RadioView>),
 }
 
 pub struct Library {
@@ -35,6 +38,7 @@ pub enum ViewSwitchMessage {
     Albums,
     Release(i64),
     Playlist(i64),
+    Radio,
     Back,
 }
 
@@ -49,6 +53,7 @@ fn make_view(
         ViewSwitchMessage::Albums => LibraryView::Album(AlbumView::new(cx, model.clone())),
         ViewSwitchMessage::Release(id) => LibraryView::Release(ReleaseView::new(cx, *id)),
         ViewSwitchMessage::Playlist(id) => LibraryView::Playlist(PlaylistView::new(cx, *id)),
+        ViewSwitchMessage::Radio => LibraryView::Radio(RadioView::new(cx)),
         ViewSwitchMessage::Back => panic!("improper use of make_view (cannot make Back)"),
     }
 }
@@ -146,6 +151,7 @@ impl Render for Library {
                         LibraryView::Playlist(playlist_view) => {
                             playlist_view.clone().into_any_element()
                         }
+                        LibraryView::Radio(radio_view) => radio_view.clone().into_any_element(),
                     }),
             )
     }
@@ -28,6 +28,7 @@ use crate::{
         theme::Theme,
         util::{create_or_retrieve_view, prune_views},
     },
+    shared::i18n::t,
 };
 
 use super::track_listing::track_item::TrackPlaylistInfo;
@@ -142,7 +143,7 @@ impl Render for PlaylistView {
                                             .font_weight(FontWeight::SEMIBOLD)
                                             .intent(ButtonIntent::Primary)
                                             .child(icon(PLAY).size(px(16.0)).my_auto())
-                                            .child("Play")
+                                            .child(t("library.play"))
                                             .on_click(cx.listener(|this, _, _, cx| {
                                                 let tracks = cx
                                                     .get_playlist_track_files(this.playlist.id)
@@ -266,6 +267,7 @@ impl Render for PlaylistView {
                                             id: pl_id,
                                             item_id: item.0,
                                         }),
+                                        None,
                                     )
                                 },
                                 cx,
@@ -0,0 +1,260 @@
+use std::{path::PathBuf, sync::Arc};
+
+use gpui::{prelude::FluentBuilder, *};
+use isahc::http::Uri;
+use tracing::error;
+
+use crate::{
+    player::library::{db::LibraryAccess, types::RadioStation},
+    player::media::stream::is_stream_url,
+    player::playback::{interface::replace_queue, queue::QueueItemData},
+    player::ui::{
+        components::{
+            button::{ButtonIntent, ButtonSize, button},
+            context::context,
+            icons::{CIRCLE_PLUS, PLAY, RADIO, TRASH, icon},
+            input::TextInput,
+            menu::{menu, menu_item},
+        },
+        models::PlaybackInfo,
+        theme::Theme,
+    },
+    shared::i18n::t,
+};
+
+pub struct RadioView {
+    stations: Arc<Vec<RadioStation>>,
+    input: Entity<TextInput>,
+    url: Entity<String>,
+}
+
+impl RadioView {
+    pub(super) fn new(cx: &mut App) -> Entity<Self> {
+        cx.new(|cx| {
+            let focus = cx.focus_handle();
+            let url = cx.new(|_| String::new());
+
+            let input = TextInput::new(
+                cx,
+                focus,
+                None,
+                Some(SharedString::from("Stream URL (http://...)")),
+                None,
+            );
+
+            let url_clone = url.clone();
+            cx.subscribe(&input, move |_, _, text: &String, cx| {
+                url_clone.update(cx, |buf, _| {
+                    *buf = text.clone();
+                });
+            })
+            .detach();
+
+            Self {
+                stations: cx.get_all_radio_stations().unwrap_or_default(),
+                input,
+                url,
+            }
+        })
+    }
+
+    fn entered_url(&self, cx: &App) -> Option<String> {
+        let url = self.url.read(cx).trim().to_string();
+
+        if is_stream_url(&PathBuf::from(&url)) {
+            Some(url)
+        } else {
+            None
+        }
+    }
+
+    fn save_station(&mut self, cx: &mut Context<Self>) {
+        let Some(url) = self.entered_url(cx) else {
+            return;
+        };
+
+        // the station name can't be known until the stream is opened, so use the host for now
+        let name = url
+            .parse::<Uri>()
+            .ok()
+            .and_then(|uri| uri.host().map(|host| host.to_string()))
+            .unwrap_or_else(|| url.clone());
+
+        if let Err(e) = cx.add_radio_station(&name, &url) {
+            error!("Failed to save radio station: {:?}", e);
+            return;
+        }
+
+        self.refresh(cx);
+    }
+
+    fn refresh(&mut self, cx: &mut Context<Self>) {
+        self.stations = cx.get_all_radio_stations().unwrap_or_default();
+        cx.notify();
+    }
+}
+
+fn play_station(cx: &mut App, url: &str) {
+    replace_queue(
+        vec![QueueItemData::new(cx, PathBuf::from(url), None, None)],
+        cx,
+    );
+}
+
+impl Render for RadioView {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let current_track = cx.global::<PlaybackInfo>().current_track.read(cx).clone();
+
+        div()
+            .id("radio-view")
+            .overflow_y_scroll()
+            .pt(px(10.0))
+            .w_full()
+            .h_full()
+            .flex()
+            .flex_col()
+            .flex_shrink()
+            .overflow_x_hidden()
+            .max_w(px(1000.0))
+            .child(
+                div()
+                    .px(px(18.0))
+                    .pb(px(10.0))
+                    .font_weight(FontWeight::EXTRA_BOLD)
+                    .text_size(px(26.0))
+                    .child(t("library.radio")),
+            )
+            .child(
+                div()
+                    .flex()
+                    .gap(px(10.0))
+                    .px(px(18.0))
+                    .pb(px(18.0))
+                    .child(
+                        div()
+                            .flex_grow()
+                            .my_auto()
+                            .px(px(10.0))
+                            .py(px(8.0))
+                            .text_sm()
+                            .line_height(px(14.0))
+                            .rounded(px(4.0))
+                            .border_1()
+                            .border_color(theme.border_color)
+                            .overflow_hidden()
+                            .child(self.input.clone()),
+                    )
+                    .child(
+                        button()
+                            .id("radio-play-button")
+                            .size(ButtonSize::Large)
+                            .font_weight(FontWeight::SEMIBOLD)
+                            .intent(ButtonIntent::Primary)
+                            .flex_none()
+                            .child(icon(PLAY).size(px(16.0)).my_auto())
+                            .child(t("library.play"))
+                            .on_click(cx.listener(|this: &mut RadioView, _, _, cx| {
+                                if let Some(url) = this.entered_url(cx) {
+                                    play_station(cx, &url);
+                                }
+                            })),
+                    )
+                    .child(
+                        button()
+                            .id("radio-save-button")
+                            .size(ButtonSize::Large)
+                            .flex_none()
+                            .child(icon(CIRCLE_PLUS).size(px(16.0)).my_auto())
+                            .child(t("radio.save"))
+                            .on_click(cx.listener(|this: &mut RadioView, _, _, cx| {
+                                this.save_station(cx);
+                            })),
+                    ),
+            )
+            .when(self.stations.is_empty(), |this| {
+                this.child(
+                    div()
+                        .px(px(18.0))
+                        .text_sm()
+                        .text_color(theme.text_secondary)
+                        .child(t("radio.no_stations")),
+                )
+            })
+            .children(self.stations.iter().map(|station| {
+                let station_id = station.id;
+                let url = station.url.to_string();
+                let url_2 = url.clone();
+                let is_current = current_track
+                    .as_ref()
+                    .is_some_and(|track| *track == PathBuf::from(&url));
+
+                context(("radio-station-context", station_id as usize))
+                    .with(
+                        div()
+                            .id(("radio-station", station_id as u64))
+                            .flex()
+                            .w_full()
+                            .px(px(18.0))
+                            .py(px(6.0))
+                            .border_b_1()
+                            .border_color(theme.border_color)
+                            .cursor_pointer()
+                            .hover(|this| this.bg(theme.nav_button_hover))
+                            .active(|this| this.bg(theme.nav_button_active))
+                            .when(is_current, |this| this.bg(theme.queue_item_current))
+                            .child(
+                                icon(RADIO)
+                                    .size(px(16.0))
+                                    .my_auto()
+                                    .mr(px(12.0))
+                                    .text_color(theme.text_secondary),
+                            )
+                            .child(
+                                div()
+                                    .font_weight(FontWeight::SEMIBOLD)
+                                    .overflow_x_hidden()
+                                    .text_ellipsis()
+                                    .flex_shrink_0()
+                                    .child(station.name.clone()),
+                            )
+                            .child(
+                                div()
+                                    .ml(px(12.0))
+                                    .my_auto()
+                                    .text_sm()
+                                    .font_weight(FontWeight::LIGHT)
+                                    .text_color(theme.text_secondary)
+                                    .overflow_x_hidden()
+                                    .text_ellipsis()
+                                    .flex_shrink()
+                                    .child(station.url.clone()),
+                            )
+                            .on_click(move |_, _, cx| play_station(cx, &url)),
+                    )
+                    .child(
+                        div().bg(theme.elevated_background).child(
+                            menu()
+                                .item(menu_item(
+                                    "radio_station_play",
+                                    Some(PLAY),
+                                    t("library.play"),
+                                    move |_, _, cx| play_station(cx, &url_2),
+                                ))
+                                .item(menu_item(
+                                    "radio_station_remove",
+                                    Some(TRASH),
+                                    t("radio.remove"),
+                                    cx.listener(move |this: &mut RadioView, _, _, cx| {
+                                        if let Err(e) = cx.delete_radio_station(station_id) {
+                                            error!("Failed to remove radio station: {:?}", e);
+                                        }
+                                        this.refresh(cx);
+                                    }),
+                                )),
+                        ),
+                    )
+                    .into_any_element()
+            }))
+    }
+}
@@ -1,28 +1,49 @@
-use std::{f32, sync::Arc};
+use std::{f32, io::Cursor, sync::Arc};
 
 use gpui::*;
 use prelude::FluentBuilder;
+use rand::{rng, seq::SliceRandom};
+use tracing::{error, info, warn};
 
 use crate::{
     player::library::{
         db::{AlbumMethod, LibraryAccess},
+        scan::process_album_art,
         types::{Album, Artist, Track},
     },
     player::playback::{
-        interface::{GPUIPlaybackInterface, replace_queue},
+        interface::{GPUIPlaybackInterface, append_and_play, replace_queue},
         queue::QueueItemData,
         thread::PlaybackState,
     },
+    player::services::musicbrainz::{
+        cover_art_unavailable, fetch_cover_art, is_valid_mbid, mark_cover_art_unavailable,
+    },
     player::ui::{
         components::{
+            album_art::album_art,
             button::{ButtonIntent, ButtonSize, button},
+            context::context,
             icons::{CIRCLE_PLUS, PAUSE, PLAY, SHUFFLE, icon},
+            input::TextInput,
+            menu::{menu, menu_item},
         },
         global_actions::PlayPause,
-        library::track_listing::{ArtistNameVisibility, TrackListing},
-        models::PlaybackInfo,
+        library::track_listing::{
+            ArtistNameVisibility, SelectAll, SelectNext, SelectPrevious, TRACK_LISTING_CONTEXT,
+            TrackListing, track_item::play_count_label,
+        },
+        models::{Models, PlayHistoryEvent, PlaybackInfo},
         theme::Theme,
     },
+    shared::{
+        format,
+        i18n::{t, t_args},
+        settings::{
+            SettingsGlobal,
+            playback::{AlbumPlayAction, AlbumShuffleAction},
+        },
+    },
 };
 
 pub struct ReleaseView {
@@ -30,9 +51,25 @@ pub struct ReleaseView {
     artist: Option<Arc<Artist>>,
     tracks: Arc<Vec<Track>>,
     track_listing: TrackListing,
+    /// Filters the track listing by title.
+    filter_input: Entity<TextInput>,
+    /// Focused when a track is clicked, so the selection can be changed with the keyboard.
+    track_list_focus: FocusHandle,
     release_info: Option<SharedString>,
     img_path: SharedString,
     image_cache: Entity<RetainAllImageCache>,
+    art_fetch: ArtFetch,
+    /// How many times tracks from the album have been played to the end, in total.
+    play_count: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArtFetch {
+    /// The album has art, or there's no way to find any.
+    Unavailable,
+    /// The album has no art, but the Cover Art Archive might.
+    Available,
+    Fetching,
 }
 
 impl ReleaseView {
@@ -59,6 +96,39 @@ impl ReleaseView {
                 px(f32::INFINITY), // render the whole thing
                 ArtistNameVisibility::OnlyIfDifferent(artist.as_ref().and_then(|v| v.name.clone())),
             );
+            track_listing.refresh_play_counts(cx, album_id);
+            let play_count = cx.album_play_count(album_id).unwrap_or_default();
+
+            let filter_input = TextInput::new(
+                cx,
+                cx.focus_handle(),
+                None,
+                Some(SharedString::from(t("library.filter_tracks"))),
+                None,
+            );
+
+            cx.subscribe(&filter_input, |this: &mut Self, _, query: &String, cx| {
+                this.track_listing.set_filter(cx, query);
+                cx.notify();
+            })
+            .detach();
+
+            let play_history = cx.global::<Models>().play_history.clone();
+
+            cx.subscribe(&play_history, |this: &mut Self, _, ev, cx| {
+                let PlayHistoryEvent::PlayRecorded { track_id, album_id } = *ev;
+
+                if album_id != Some(this.album.id) {
+                    return;
+                }
+
+                if let Ok(count) = cx.album_play_count(this.album.id) {
+                    this.play_count = count;
+                }
+                this.track_listing.refresh_play_count(cx, track_id);
+                cx.notify();
+            })
+            .detach();
 
             let release_info = {
                 let mut info = String::default();
@@ -82,22 +152,291 @@ impl ReleaseView {
                 }
             };
 
+            let art_fetch = match &album.mbid {
+                Some(mbid)
+                    if album.image.is_none()
+                        && is_valid_mbid(&mbid.0)
+                        && !cover_art_unavailable(&mbid.0) =>
+                {
+                    ArtFetch::Available
+                }
+                _ => ArtFetch::Unavailable,
+            };
+
             ReleaseView {
                 album,
                 artist,
                 tracks,
                 track_listing,
+                filter_input,
+                track_list_focus: cx.focus_handle(),
                 release_info,
                 img_path: SharedString::from(format!("!db://album/{album_id}/full")),
                 image_cache,
+                art_fetch,
+                play_count,
+            }
+        })
+    }
+
+    /// Downloads the album's front cover from the Cover Art Archive and stores it in the library.
+    fn fetch_art(&mut self, cx: &mut Context<Self>) {
+        let Some(mbid) = self.album.mbid.as_ref().map(|v| v.0.to_string()) else {
+            return;
+        };
+
+        self.art_fetch = ArtFetch::Fetching;
+        cx.notify();
+
+        cx.spawn(async move |this, cx| {
+            let result = cx
+                .background_spawn(async move {
+                    let processed = match fetch_cover_art(&mbid).await? {
+                        Some(bytes) => process_album_art(&bytes)
+                            .inspect_err(|e| warn!("Could not decode cover art: {:?}", e))
+                            .ok(),
+                        None => None,
+                    };
+
+                    // network errors are worth retrying, but missing or broken art isn't
+                    if processed.is_none() {
+                        mark_cover_art_unavailable(&mbid);
+                    }
+
+                    anyhow::Ok(processed)
+                })
+                .await;
+
+            this.update(cx, |this, cx| {
+                this.art_fetch = ArtFetch::Unavailable;
+
+                match result {
+                    Ok(Some((image, thumb))) => this.art_fetched(image, thumb, cx),
+                    Ok(None) => (),
+                    Err(e) => {
+                        error!("Could not fetch cover art: {:?}", e);
+                        this.art_fetch = ArtFetch::Available;
+                    }
+                }
+
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn art_fetched(&mut self, image: Vec<u8>, thumb: Vec<u8>, cx: &mut Context<Self>) {
+        let album_id = self.album.id;
+
+        if let Err(e) = cx.set_album_art(album_id, image, thumb) {
+            error!("Could not store cover art: {:?}", e);
+            return;
+        }
+
+        if let Ok(album) = cx.get_album_by_id(album_id, AlbumMethod::FullQuality) {
+            self.album = album;
+        }
+
+        // drop the empty images that were loaded before the art was stored
+        for path in [
+            self.img_path.clone(),
+            SharedString::from(format!("!db://album/{album_id}/thumb")),
+        ] {
+            ImageSource::Resource(Resource::Embedded(path)).remove_asset(cx);
+        }
+
+        self.image_cache = RetainAllImageCache::new(cx);
+    }
+
+    /// Copies the album's full size art to the clipboard. The art is re-encoded in the
+    /// background, since large art can take a while.
+    fn copy_art(&self, cx: &mut App) {
+        if self.album.image.is_none() {
+            return;
+        }
+
+        let album = self.album.clone();
+
+        cx.spawn(async move |cx: &mut AsyncApp| {
+            let png = cx
+                .background_spawn(async move {
+                    let art = album.image.as_deref().expect("album art was checked");
+                    art_as_png(art)
+                })
+                .await;
+
+            match png {
+                Ok(png) => {
+                    cx.update(|cx| {
+                        cx.write_to_clipboard(ClipboardItem::new_image(&Image::from_bytes(
+                            ImageFormat::Png,
+                            png,
+                        )))
+                    })
+                    .ok();
+                }
+                Err(e) => error!("Could not copy album art: {:?}", e),
             }
         })
+        .detach();
+    }
+
+    /// Asks where to save the album's full size art, and writes it there as it's stored.
+    fn save_art(&self, cx: &mut App) {
+        let Some(art) = self.album.image.clone() else {
+            return;
+        };
+
+        let extension = image::guess_format(&art)
+            .ok()
+            .and_then(|format| format.extensions_str().first().copied())
+            .unwrap_or("jpg");
+        let name = file_name(&self.album.title.0);
+        let directory = directories::UserDirs::new()
+            .map(|dirs| dirs.picture_dir().unwrap_or(dirs.home_dir()).to_path_buf())
+            .unwrap_or_default();
+        let path = cx.prompt_for_new_path(&directory, Some(&format!("{name}.{extension}")));
+
+        cx.spawn(async move |_: &mut AsyncApp| {
+            let Ok(Ok(Some(path))) = path.await else {
+                return;
+            };
+
+            match std::fs::write(&path, &art) {
+                Ok(()) => info!("Saved album art to {:?}", path),
+                Err(e) => error!("Could not save album art: {:?}", e),
+            }
+        })
+        .detach();
     }
 }
 
+/// Plays an album's tracks, replacing the queue or adding them to it as set in the playback
+/// settings.
+fn play_album(items: Vec<QueueItemData>, window: &mut Window, cx: &mut App) {
+    let action = cx
+        .global::<SettingsGlobal>()
+        .model
+        .read(cx)
+        .playback
+        .album_play_action;
+    let queue_empty = cx
+        .global::<Models>()
+        .queue
+        .read(cx)
+        .data
+        .read()
+        .expect("couldn't get queue")
+        .is_empty();
+
+    match action {
+        AlbumPlayAction::Replace => replace_queue(items, cx),
+        AlbumPlayAction::Append => append_and_play(items, cx),
+        AlbumPlayAction::Ask if queue_empty => replace_queue(items, cx),
+        AlbumPlayAction::Ask => {
+            let answer = window.prompt(
+                PromptLevel::Info,
+                t("library.replace_queue.title"),
+                Some(t("library.replace_queue.detail")),
+                &[
+                    t("library.replace_queue"),
+                    t("library.add_to_queue"),
+                    t("common.cancel"),
+                ],
+                cx,
+            );
+
+            cx.spawn(async move |cx: &mut AsyncApp| {
+                let answer = answer.await;
+                cx.update(|cx| match answer {
+                    Ok(0) => replace_queue(items, cx),
+                    Ok(1) => append_and_play(items, cx),
+                    _ => {}
+                })
+                .ok();
+            })
+            .detach();
+        }
+    }
+}
+
+/// Plays an album's tracks in a random order, either by shuffling them before they're queued or
+/// by turning on shuffle, as set in the playback settings.
+fn shuffle_album(mut items: Vec<QueueItemData>, cx: &mut App) {
+    let action = cx
+        .global::<SettingsGlobal>()
+        .model
+        .read(cx)
+        .playback
+        .album_shuffle_action;
+
+    if shuffle_tracks(&mut items, action) && !(*cx.global::<PlaybackInfo>().shuffling.read(cx)) {
+        cx.global::<GPUIPlaybackInterface>().toggle_shuffle();
+    }
+
+    replace_queue(items, cx)
+}
+
+/// Puts an album's tracks in a random order if the action shuffles them itself. Returns whether
+/// shuffle has to be turned on instead.
+fn shuffle_tracks(items: &mut [QueueItemData], action: AlbumShuffleAction) -> bool {
+    match action {
+        AlbumShuffleAction::ShuffleTracks => {
+            items.shuffle(&mut rng());
+            false
+        }
+        AlbumShuffleAction::TurnOnShuffle => true,
+    }
+}
+
+/// Turns an album title into a name that can be used for a file on every platform. Characters
+/// that aren't allowed in file names are replaced, trailing dots and spaces are removed, and names
+/// reserved for devices on Windows, such as "CON", are prefixed with an underscore.
+fn file_name(title: &str) -> String {
+    const RESERVED: &[&str] = &[
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+
+    let name: String = title
+        .chars()
+        .map(|c| {
+            if c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let name = name.trim_end_matches(['.', ' ']);
+
+    // Windows reserves the device names even when they're followed by an extension
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    let reserved = RESERVED.iter().any(|v| stem.eq_ignore_ascii_case(v));
+
+    if name.is_empty() {
+        "Album Art".to_string()
+    } else if reserved {
+        format!("_{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+/// Re-encodes stored album art as a PNG, which every platform's clipboard accepts.
+fn art_as_png(art: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut png = Cursor::new(Vec::new());
+    image::load_from_memory(art)?.write_to(&mut png, image::ImageFormat::Png)?;
+
+    Ok(png.into_inner())
+}
+
 impl Render for ReleaseView {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
+        let locale = format::locale(cx);
+        let has_art = self.album.image.is_some();
 
         let is_playing =
             cx.global::<PlaybackInfo>().playback_state.read(cx) == &PlaybackState::Playing;
@@ -129,29 +468,57 @@ impl Render for ReleaseView {
                     .px(px(18.0))
                     .w_full()
                     .child(
-                        div()
-                            .rounded(px(4.0))
-                            .bg(theme.album_art_background)
-                            .shadow_sm()
-                            .w(px(160.0))
-                            .h(px(160.0))
-                            .flex_shrink_0()
-                            .overflow_hidden()
-                            .child(
-                                img(self.img_path.clone())
-                                    .image_cache(&self.image_cache)
-                                    .min_w(px(160.0))
-                                    .min_h(px(160.0))
-                                    .max_w(px(160.0))
-                                    .max_h(px(160.0))
+                        context("release-art-context")
+                            .with(
+                                div()
+                                    .rounded(px(4.0))
+                                    .bg(theme.album_art_background)
+                                    .shadow_sm()
+                                    .w(px(160.0))
+                                    .h(px(160.0))
+                                    .flex_shrink_0()
                                     .overflow_hidden()
-                                    .flex()
-                                    // TODO: Ideally this should be ObjectFit::Cover, but this
-                                    // breaks rounding
-                                    // FIXME: This is a GPUI bug
-                                    .object_fit(ObjectFit::Fill)
-                                    .rounded(px(4.0)),
-                            ),
+                                    .child(
+                                        album_art(self.img_path.clone(), px(160.0), cx)
+                                            .image_cache(&self.image_cache)
+                                            .min_w(px(160.0))
+                                            .min_h(px(160.0))
+                                            .max_w(px(160.0))
+                                            .max_h(px(160.0))
+                                            .overflow_hidden()
+                                            .flex()
+                                            // TODO: Ideally this should be ObjectFit::Cover, but
+                                            // this breaks rounding
+                                            // FIXME: This is a GPUI bug
+                                            .object_fit(ObjectFit::Fill)
+                                            .rounded(px(4.0)),
+                                    ),
+                            )
+                            // there's nothing to copy or save without art, so the menu is left
+                            // empty and never opens
+                            .when(has_art, |this| {
+                                this.child(
+                                    div().bg(theme.elevated_background).child(
+                                        menu()
+                                            .item(menu_item(
+                                                "release_copy_art",
+                                                None::<&str>,
+                                                t("library.copy_album_art"),
+                                                cx.listener(|this: &mut ReleaseView, _, _, cx| {
+                                                    this.copy_art(cx)
+                                                }),
+                                            ))
+                                            .item(menu_item(
+                                                "release_save_art",
+                                                None::<&str>,
+                                                t("library.save_album_art"),
+                                                cx.listener(|this: &mut ReleaseView, _, _, cx| {
+                                                    this.save_art(cx)
+                                                }),
+                                            )),
+                                    ),
+                                )
+                            }),
                     )
                     .child(
                         div()
@@ -190,22 +557,17 @@ impl Render for ReleaseView {
                                             .intent(ButtonIntent::Primary)
                                             .when(!current_track_in_album, |this| {
                                                 this.on_click(cx.listener(
-                                                    |this: &mut ReleaseView, _, _, cx| {
+                                                    |this: &mut ReleaseView, _, window, cx| {
                                                         let queue_items = this
                                                             .track_listing
-                                                            .tracks()
+                                                            .visible_tracks()
                                                             .iter()
                                                             .map(|track| {
-                                                                QueueItemData::new(
-                                                                    cx,
-                                                                    track.location.clone(),
-                                                                    Some(track.id),
-                                                                    track.album_id,
-                                                                )
+                                                                QueueItemData::from_track(cx, track)
                                                             })
                                                             .collect();
 
-                                                        replace_queue(queue_items, cx)
+                                                        play_album(queue_items, window, cx)
                                                     },
                                                 ))
                                             })
@@ -225,9 +587,9 @@ impl Render for ReleaseView {
                                             )
                                             .child(div().child(
                                                 if current_track_in_album && is_playing {
-                                                    "Pause"
+                                                    t("library.pause")
                                                 } else {
-                                                    "Play"
+                                                    t("library.play")
                                                 },
                                             )),
                                     )
@@ -240,15 +602,10 @@ impl Render for ReleaseView {
                                                 |this: &mut ReleaseView, _, _, cx| {
                                                     let queue_items = this
                                                         .track_listing
-                                                        .tracks()
+                                                        .visible_tracks()
                                                         .iter()
                                                         .map(|track| {
-                                                            QueueItemData::new(
-                                                                cx,
-                                                                track.location.clone(),
-                                                                Some(track.id),
-                                                                track.album_id,
-                                                            )
+                                                            QueueItemData::from_track(cx, track)
                                                         })
                                                         .collect();
 
@@ -267,52 +624,112 @@ impl Render for ReleaseView {
                                                 |this: &mut ReleaseView, _, _, cx| {
                                                     let queue_items = this
                                                         .track_listing
-                                                        .tracks()
+                                                        .visible_tracks()
                                                         .iter()
                                                         .map(|track| {
-                                                            QueueItemData::new(
-                                                                cx,
-                                                                track.location.clone(),
-                                                                Some(track.id),
-                                                                track.album_id,
-                                                            )
+                                                            QueueItemData::from_track(cx, track)
                                                         })
                                                         .collect();
 
-                                                    if !(*cx
-                                                        .global::<PlaybackInfo>()
-                                                        .shuffling
-                                                        .read(cx))
-                                                    {
-                                                        cx.global::<GPUIPlaybackInterface>()
-                                                            .toggle_shuffle();
-                                                    }
-
-                                                    replace_queue(queue_items, cx)
+                                                    shuffle_album(queue_items, cx)
                                                 },
                                             ))
                                             .child(icon(SHUFFLE).size(px(16.0)).my_auto()),
-                                    ),
+                                    )
+                                    .when(self.art_fetch != ArtFetch::Unavailable, |this| {
+                                        let fetching = self.art_fetch == ArtFetch::Fetching;
+
+                                        this.child(
+                                            button()
+                                                .id("release-fetch-art-button")
+                                                .size(ButtonSize::Large)
+                                                .flex_none()
+                                                .when(!fetching, |this| {
+                                                    this.on_click(cx.listener(
+                                                        |this: &mut ReleaseView, _, _, cx| {
+                                                            this.fetch_art(cx)
+                                                        },
+                                                    ))
+                                                })
+                                                .child(div().child(if fetching {
+                                                    t("library.fetching_cover_art")
+                                                } else {
+                                                    t("library.fetch_cover_art")
+                                                })),
+                                        )
+                                    }),
                             ),
                     ),
             )
+            .child(
+                div().flex().px(px(18.0)).pt(px(12.0)).child(
+                    div()
+                        .ml_auto()
+                        .w(px(200.0))
+                        .px(px(8.0))
+                        .py(px(5.0))
+                        .text_sm()
+                        .line_height(px(14.0))
+                        .rounded(px(4.0))
+                        .border_1()
+                        .border_color(theme.border_color)
+                        .overflow_hidden()
+                        .child(self.filter_input.clone()),
+                ),
+            )
+            .when(self.track_listing.visible_count() == 0, |this| {
+                this.child(
+                    div()
+                        .px(px(18.0))
+                        .py(px(12.0))
+                        .text_sm()
+                        .text_color(theme.text_secondary)
+                        .child(t("library.no_matching_tracks")),
+                )
+            })
             .child({
                 let track_listing = self.track_listing.clone();
                 let render_fn = track_listing.make_render_fn();
                 let track_list_state = track_listing.track_list_state().clone();
 
-                list(track_list_state, render_fn)
-                    .w_full()
-                    .flex()
-                    .flex_col()
-                    .mx_auto()
-                    .max_h_full()
-                    .with_sizing_behavior(ListSizingBehavior::Infer)
+                div()
+                    .id("release-track-list")
+                    .track_focus(&self.track_list_focus)
+                    .key_context(TRACK_LISTING_CONTEXT)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this: &mut ReleaseView, _, window, _| {
+                            window.focus(&this.track_list_focus);
+                        }),
+                    )
+                    .on_action(
+                        cx.listener(|this: &mut ReleaseView, _: &SelectNext, _, cx| {
+                            this.track_listing.extend_selection(cx, 1);
+                        }),
+                    )
+                    .on_action(
+                        cx.listener(|this: &mut ReleaseView, _: &SelectPrevious, _, cx| {
+                            this.track_listing.extend_selection(cx, -1);
+                        }),
+                    )
+                    .on_action(cx.listener(|this: &mut ReleaseView, _: &SelectAll, _, cx| {
+                        this.track_listing.select_all(cx);
+                    }))
+                    .child(
+                        list(track_list_state, render_fn)
+                            .w_full()
+                            .flex()
+                            .flex_col()
+                            .mx_auto()
+                            .max_h_full()
+                            .with_sizing_behavior(ListSizingBehavior::Infer),
+                    )
             })
             .when(
                 self.release_info.is_some()
                     || self.album.release_date.is_some()
-                    || self.album.isrc.is_some(),
+                    || self.album.isrc.is_some()
+                    || self.play_count > 0,
                 |this| {
                     this.child(
                         div()
@@ -328,15 +745,75 @@ impl Render for ReleaseView {
                                 this.child(div().child(release_info))
                             })
                             .when_some(self.album.release_date, |this, date| {
-                                this.child(
-                                    div().child(format!("Released {}", date.format("%B %-e, %Y"))),
-                                )
+                                this.child(div().child(t_args(
+                                    "library.released",
+                                    &[&format::long_date(&date, locale)],
+                                )))
                             })
                             .when_some(self.album.isrc.as_ref(), |this, isrc| {
                                 this.child(div().child(isrc.clone()))
+                            })
+                            .when(self.play_count > 0, |this| {
+                                this.child(div().child(play_count_label(self.play_count, locale)))
                             }),
                     )
                 },
             )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn album(cx: &mut TestAppContext) -> Vec<QueueItemData> {
+        cx.update(|cx| {
+            (1..=20)
+                .map(|n| QueueItemData::new(cx, PathBuf::from(format!("{n}.flac")), None, None))
+                .collect()
+        })
+    }
+
+    fn paths(items: &[QueueItemData]) -> Vec<PathBuf> {
+        items.iter().map(|item| item.get_path().clone()).collect()
+    }
+
+    #[gpui::test]
+    fn album_tracks_are_shuffled_without_turning_on_shuffle(cx: &mut TestAppContext) {
+        let mut items = album(cx);
+        let original = paths(&items);
+
+        let turns_on_shuffle = shuffle_tracks(&mut items, AlbumShuffleAction::ShuffleTracks);
+        assert!(!turns_on_shuffle);
+
+        let mut shuffled = paths(&items);
+        assert_ne!(shuffled, original);
+        shuffled.sort();
+        let mut sorted = original;
+        sorted.sort();
+        assert_eq!(shuffled, sorted);
+    }
+
+    #[gpui::test]
+    fn turning_on_shuffle_leaves_the_album_in_order(cx: &mut TestAppContext) {
+        let mut items = album(cx);
+        let original = paths(&items);
+
+        let turns_on_shuffle = shuffle_tracks(&mut items, AlbumShuffleAction::TurnOnShuffle);
+        assert!(turns_on_shuffle);
+        assert_eq!(paths(&items), original);
+    }
+
+    #[test]
+    fn file_names_are_safe_on_every_platform() {
+        assert_eq!(file_name("AC/DC: Live"), "AC_DC_ Live");
+        assert_eq!(file_name("What?"), "What_");
+        assert_eq!(file_name("Et cetera..."), "Et cetera");
+        assert_eq!(file_name("con"), "_con");
+        assert_eq!(file_name("Nul.Live"), "_Nul.Live");
+        assert_eq!(file_name("Console"), "Console");
+        assert_eq!(file_name("..."), "Album Art");
+    }
+}
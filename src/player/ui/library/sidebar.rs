@@ -9,20 +9,26 @@ use crate::{
     player::library::{db::LibraryAccess, types::TrackStats},
     player::ui::{
         components::{
-            icons::{DISC, SEARCH, SIDEBAR_INACTIVE},
+            icons::{DISC, RADIO, SEARCH, SHUFFLE, SIDEBAR_INACTIVE},
             nav_button::nav_button,
             sidebar::{sidebar, sidebar_item, sidebar_separator},
         },
-        global_actions::Search,
-        library::{ViewSwitchMessage, sidebar::playlists::PlaylistList},
+        global_actions::{Search, ShuffleLibrary},
+        library::{
+            ViewSwitchMessage,
+            sidebar::{playlists::PlaylistList, recent::RecentlyPlayed},
+        },
         theme::Theme,
     },
+    shared::{format, i18n::t},
 };
 
 mod playlists;
+mod recent;
 
 pub struct Sidebar {
     playlists: Entity<PlaylistList>,
+    recently_played: Entity<RecentlyPlayed>,
     track_stats: Arc<TrackStats>,
     nav_model: Entity<VecDeque<ViewSwitchMessage>>,
 }
@@ -33,6 +39,7 @@ impl Sidebar {
             cx.observe(&nav_model, |_, _, cx| cx.notify()).detach();
             Self {
                 playlists: PlaylistList::new(cx, nav_model.clone()),
+                recently_played: RecentlyPlayed::new(cx),
                 track_stats: cx.get_track_stats().unwrap(),
                 nav_model,
             }
@@ -43,6 +50,7 @@ impl Sidebar {
 impl Render for Sidebar {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
+        let locale = format::locale(cx);
         let stats_minutes = self.track_stats.total_duration / 60;
         let stats_hours = stats_minutes / 60;
         let current_view = self.nav_model.read(cx);
@@ -69,7 +77,7 @@ impl Render for Sidebar {
             .child(
                 sidebar_item("albums")
                     .icon(DISC)
-                    .child("Albums")
+                    .child(t("library.albums"))
                     .on_click(cx.listener(|this, _, _, cx| {
                         this.nav_model.update(cx, |_, cx| {
                             cx.emit(ViewSwitchMessage::Albums);
@@ -84,8 +92,31 @@ impl Render for Sidebar {
                         |this| this.active(),
                     ),
             )
+            .child(
+                sidebar_item("radio")
+                    .icon(RADIO)
+                    .child(t("library.radio"))
+                    .on_click(cx.listener(|this, _, _, cx| {
+                        this.nav_model.update(cx, |_, cx| {
+                            cx.emit(ViewSwitchMessage::Radio);
+                        });
+                    }))
+                    .when(
+                        matches!(current_view.iter().last(), Some(ViewSwitchMessage::Radio)),
+                        |this| this.active(),
+                    ),
+            )
+            .child(
+                sidebar_item("shuffle-library")
+                    .icon(SHUFFLE)
+                    .child(t("library.shuffle_all"))
+                    .on_click(|_, window, cx| {
+                        window.dispatch_action(Box::new(ShuffleLibrary), cx);
+                    }),
+            )
             .child(sidebar_separator())
             .child(self.playlists.clone())
+            .child(self.recently_played.clone())
             .child(
                 div()
                     .flex()
@@ -94,7 +125,10 @@ impl Render for Sidebar {
                     .text_xs()
                     .text_color(theme.text_secondary)
                     .child(if self.track_stats.track_count != 1 {
-                        format!("{} tracks", self.track_stats.track_count)
+                        format!(
+                            "{} tracks",
+                            format::number(self.track_stats.track_count, locale)
+                        )
                     } else {
                         format!("{} track", self.track_stats.track_count)
                     })
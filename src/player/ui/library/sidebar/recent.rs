@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use gpui::{
+    App, AppContext, Context, Entity, FontWeight, InteractiveElement, ParentElement, Render,
+    StatefulInteractiveElement, Styled, Window, div, img, prelude::FluentBuilder, px,
+};
+use tracing::error;
+
+use crate::{
+    player::library::{db::LibraryAccess, types::Track},
+    player::playback::{interface::GPUIPlaybackInterface, queue::QueueItemData},
+    player::ui::{
+        models::{Models, PlayHistoryEvent},
+        theme::Theme,
+    },
+    shared::i18n::t,
+};
+
+/// How many recently played tracks are shown.
+const RECENT_PLAYS_SHOWN: i64 = 5;
+
+/// The most recently played tracks, which can be clicked to play them again. Nothing is shown
+/// until something has been played.
+pub struct RecentlyPlayed {
+    tracks: Arc<Vec<Track>>,
+}
+
+impl RecentlyPlayed {
+    pub fn new(cx: &mut App) -> Entity<Self> {
+        cx.new(|cx| {
+            let play_history = cx.global::<Models>().play_history.clone();
+
+            cx.subscribe(
+                &play_history,
+                |this: &mut Self, _, _: &PlayHistoryEvent, cx| {
+                    this.tracks = recent_plays(cx);
+                    cx.notify();
+                },
+            )
+            .detach();
+
+            Self {
+                tracks: recent_plays(cx),
+            }
+        })
+    }
+}
+
+fn recent_plays(cx: &App) -> Arc<Vec<Track>> {
+    cx.recent_plays(RECENT_PLAYS_SHOWN)
+        .inspect_err(|e| error!("Could not get recently played tracks: {:?}", e))
+        .unwrap_or_default()
+}
+
+/// Adds the track to the end of the queue and plays it.
+fn play_track(cx: &mut App, track: &Track) {
+    let data = QueueItemData::from_track(cx, track);
+    let queue_length = cx
+        .global::<Models>()
+        .queue
+        .read(cx)
+        .data
+        .read()
+        .expect("couldn't get queue")
+        .len();
+
+    let playback_interface = cx.global::<GPUIPlaybackInterface>();
+    playback_interface.queue(data);
+    playback_interface.jump(queue_length);
+}
+
+impl Render for RecentlyPlayed {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl gpui::IntoElement {
+        let theme = cx.global::<Theme>();
+
+        div()
+            .flex()
+            .flex_col()
+            .when(!self.tracks.is_empty(), |this| {
+                this.child(
+                    div()
+                        .text_xs()
+                        .font_weight(FontWeight::SEMIBOLD)
+                        .text_color(theme.text_secondary)
+                        .px(px(9.0))
+                        .pt(px(4.0))
+                        .pb(px(2.0))
+                        .child(t("library.recently_played")),
+                )
+            })
+            .children(self.tracks.iter().map(|track| {
+                let clicked = track.clone();
+
+                // laid out like a sidebar item, but with the album art in place of the icon
+                div()
+                    .id(("main-sidebar-recent", track.id as u64))
+                    .flex()
+                    .gap(px(6.0))
+                    .rounded(px(4.0))
+                    .px(px(9.0))
+                    .py(px(5.0))
+                    .text_sm()
+                    .line_height(px(18.0))
+                    .font_weight(FontWeight::SEMIBOLD)
+                    .hover(|this| this.bg(theme.nav_button_hover))
+                    .active(|this| this.bg(theme.nav_button_active))
+                    .child(
+                        div()
+                            .size(px(18.0))
+                            .mt(px(1.0))
+                            .flex_shrink_0()
+                            .rounded(px(3.0))
+                            .bg(theme.album_art_background)
+                            .when_some(track.album_id, |this, album_id| {
+                                this.child(
+                                    img(format!("!db://album/{album_id}/thumb"))
+                                        .size(px(18.0))
+                                        .rounded(px(3.0)),
+                                )
+                            }),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .flex_col()
+                            .overflow_x_hidden()
+                            .child(
+                                div()
+                                    .overflow_x_hidden()
+                                    .text_ellipsis()
+                                    .child(track.title.clone()),
+                            )
+                            .when_some(track.artist_names.clone(), |this, artists| {
+                                this.child(
+                                    div()
+                                        .font_weight(FontWeight::NORMAL)
+                                        .text_color(theme.text_secondary)
+                                        .text_xs()
+                                        .overflow_x_hidden()
+                                        .text_ellipsis()
+                                        .child(artists.0),
+                                )
+                            }),
+                    )
+                    .on_click(move |_, _, cx| play_track(cx, &clicked))
+            }))
+    }
+}
@@ -1,14 +1,47 @@
+pub mod selection;
 pub mod track_item;
 
 use std::sync::Arc;
 
-use gpui::{App, Entity, IntoElement, ListAlignment, ListState, Pixels, Window};
+use gpui::{
+    App, AppContext, Entity, IntoElement, KeyBinding, ListAlignment, ListState, Pixels, Window,
+    actions,
+};
+use nucleo::{
+    Config, Matcher,
+    pattern::{CaseMatching, Normalization, Pattern},
+};
+use tracing::warn;
 
 use crate::{
-    player::library::types::{DBString, Track},
+    player::library::{
+        db::LibraryAccess,
+        types::{DBString, Track},
+    },
     player::ui::library::track_listing::track_item::TrackItemLeftField,
 };
-use track_item::TrackItem;
+use selection::TrackSelection;
+use track_item::{ItemSelection, SelectionMenu, TrackItem};
+
+/// The key context of a focused track listing.
+pub const TRACK_LISTING_CONTEXT: &str = "TrackListing";
+
+actions!(track_listing, [SelectNext, SelectPrevious, SelectAll]);
+
+pub fn bind_actions(cx: &mut App) {
+    let context = Some(TRACK_LISTING_CONTEXT);
+
+    cx.bind_keys([
+        KeyBinding::new("shift-down", SelectNext, context),
+        KeyBinding::new("shift-up", SelectPrevious, context),
+    ]);
+
+    if cfg!(target_os = "macos") {
+        cx.bind_keys([KeyBinding::new("cmd-a", SelectAll, context)]);
+    } else {
+        cx.bind_keys([KeyBinding::new("ctrl-a", SelectAll, context)]);
+    }
+}
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum ArtistNameVisibility {
@@ -17,11 +50,25 @@ pub enum ArtistNameVisibility {
     OnlyIfDifferent(Option<DBString>),
 }
 
+/// A track being matched against the filter, along with its index in the listing.
+struct Candidate<'a>(usize, &'a str);
+
+impl AsRef<str> for Candidate<'_> {
+    fn as_ref(&self) -> &str {
+        self.1
+    }
+}
+
 #[derive(Clone)]
 pub struct TrackListing {
     // TODO: replace this with Arc<Vec<i64>>, memoize TrackItem, fetch on load instead of before
     tracks: Arc<Vec<Entity<TrackItem>>>,
     original_tracks: Arc<Vec<Track>>,
+    /// Whether each track starts a new disc, for when the listing isn't filtered.
+    disc_starts: Arc<Vec<bool>>,
+    /// Indices of the tracks that are shown, in listing order.
+    visible: Arc<Vec<usize>>,
+    selection: Entity<TrackSelection>,
     track_list_state: ListState,
 }
 
@@ -33,27 +80,45 @@ impl TrackListing {
         artist_name_visibility: ArtistNameVisibility,
     ) -> Self {
         let state = ListState::new(tracks.len(), ListAlignment::Top, overdraw);
+        // only show disc headers if there's more than one disc
+        let multi_disc = tracks.iter().any(|track| track.disc() != tracks[0].disc());
+        let disc_starts: Vec<bool> = tracks
+            .iter()
+            .enumerate()
+            .map(|(index, track)| {
+                multi_disc && (index == 0 || tracks[index - 1].disc() != track.disc())
+            })
+            .collect();
+        let selection =
+            cx.new(|_| TrackSelection::new(tracks.iter().map(|track| track.id).collect()));
+        let selection_menu = SelectionMenu::observe(cx, &selection);
 
         Self {
             tracks: Arc::new(
                 tracks
                     .iter()
                     .enumerate()
-                    .map(move |(index, track)| {
+                    .map(|(index, track)| {
                         TrackItem::new(
                             cx,
                             track.clone(),
-                            (index == 0 || track.track_number == Some(1))
-                                && track.disc_number != Some(0)
-                                && track.disc_number.is_some(),
+                            disc_starts[index],
                             artist_name_visibility.clone(),
                             TrackItemLeftField::TrackNum,
                             None,
+                            Some(ItemSelection {
+                                selection: selection.clone(),
+                                menu: selection_menu.clone(),
+                                index,
+                            }),
                         )
                     })
                     .collect(),
             ),
+            visible: Arc::new((0..tracks.len()).collect()),
             original_tracks: tracks,
+            disc_starts: Arc::new(disc_starts),
+            selection,
             track_list_state: state,
         }
     }
@@ -62,6 +127,114 @@ impl TrackListing {
         &self.original_tracks
     }
 
+    /// The tracks that are shown, which is all of them unless the listing is filtered.
+    pub fn visible_tracks(&self) -> Vec<Track> {
+        self.visible
+            .iter()
+            .map(|index| self.original_tracks[*index].clone())
+            .collect()
+    }
+
+    /// How many tracks are shown.
+    pub fn visible_count(&self) -> usize {
+        self.visible.len()
+    }
+
+    /// Shows only the tracks whose titles fuzzily match `query`, keeping them in listing order.
+    /// An empty query shows every track again.
+    pub fn set_filter(&mut self, cx: &mut App, query: &str) {
+        let filtering = !query.trim().is_empty();
+
+        let mut visible: Vec<usize> = if filtering {
+            let pattern = Pattern::parse(query, CaseMatching::Smart, Normalization::Smart);
+            let candidates = self
+                .original_tracks
+                .iter()
+                .enumerate()
+                .map(|(index, track)| Candidate(index, track.title.0.as_ref()));
+
+            pattern
+                .match_list(candidates, &mut Matcher::new(Config::DEFAULT))
+                .into_iter()
+                .map(|(candidate, _)| candidate.0)
+                .collect()
+        } else {
+            (0..self.tracks.len()).collect()
+        };
+        visible.sort_unstable();
+
+        // disc headers would be misleading with tracks missing from the discs
+        for (index, item) in self.tracks.iter().enumerate() {
+            let is_start = !filtering && self.disc_starts[index];
+
+            item.update(cx, |item, cx| {
+                if item.is_start != is_start {
+                    item.is_start = is_start;
+                    cx.notify();
+                }
+            });
+        }
+
+        self.track_list_state.reset(visible.len());
+        self.visible = Arc::new(visible);
+
+        let visible = self.visible.clone();
+        self.selection.update(cx, |selection, cx| {
+            selection.set_visible(visible);
+            cx.notify();
+        });
+    }
+
+    /// Moves the end of the selected range by `offset` tracks, and scrolls it into view.
+    pub fn extend_selection(&self, cx: &mut App, offset: isize) {
+        let position = self.selection.update(cx, |selection, cx| {
+            selection.extend_by(offset);
+            cx.notify();
+            selection.cursor_position()
+        });
+
+        if let Some(position) = position {
+            self.track_list_state.scroll_to_reveal_item(position);
+        }
+    }
+
+    pub fn select_all(&self, cx: &mut App) {
+        self.selection.update(cx, |selection, cx| {
+            selection.select_all();
+            cx.notify();
+        });
+    }
+
+    /// Looks up how many times each track of the album has been played, and shows the counts.
+    pub fn refresh_play_counts(&self, cx: &mut App, album_id: i64) {
+        let counts = match cx.album_track_play_counts(album_id) {
+            Ok(counts) => counts,
+            Err(err) => {
+                warn!("Failed to count plays for album {}: {:?}", album_id, err);
+                return;
+            }
+        };
+
+        for item in self.tracks.iter() {
+            let count = counts
+                .get(&item.read(cx).track.id)
+                .copied()
+                .unwrap_or_default();
+            set_play_count(cx, item, count);
+        }
+    }
+
+    /// Looks up the play count of a single track again, after a play of it has been recorded.
+    pub fn refresh_play_count(&self, cx: &mut App, track_id: i64) {
+        if let Some(item) = self
+            .tracks
+            .iter()
+            .find(|item| item.read(cx).track.id == track_id)
+        {
+            update_play_count(cx, item);
+        }
+    }
+
     pub fn track_list_state(&self) -> &ListState {
         &self.track_list_state
     }
@@ -70,6 +243,22 @@ impl TrackListing {
         &self,
     ) -> impl Fn(usize, &mut Window, &mut App) -> gpui::AnyElement + Clone + 'static {
         let tracks = self.tracks.clone();
-        move |idx, _, _| tracks[idx].clone().into_any_element()
+        let visible = self.visible.clone();
+        move |idx, _, _| tracks[visible[idx]].clone().into_any_element()
     }
 }
+
+fn update_play_count(cx: &mut App, item: &Entity<TrackItem>) {
+    let track_id = item.read(cx).track.id;
+    let count = cx.track_play_count(track_id).unwrap_or_default();
+    set_play_count(cx, item, count);
+}
+
+fn set_play_count(cx: &mut App, item: &Entity<TrackItem>, count: i64) {
+    item.update(cx, |item, cx| {
+        if item.play_count != count {
+            item.play_count = count;
+            cx.notify();
+        }
+    });
+}
@@ -0,0 +1,239 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+/// The tracks selected in a track listing, for acting on several tracks at once.
+///
+/// Tracks are referred to by their index in the listing. Only tracks that are shown can be
+/// selected, so the selection is narrowed whenever the listing is filtered.
+pub struct TrackSelection {
+    track_ids: Arc<Vec<i64>>,
+    /// Indices of the tracks that are shown, in listing order.
+    visible: Arc<Vec<usize>>,
+    selected: BTreeSet<usize>,
+    /// The track that ranges are selected from, which is the last one clicked without shift.
+    anchor: Option<usize>,
+    /// The other end of the range, moved with shift and the arrow keys.
+    cursor: Option<usize>,
+}
+
+impl TrackSelection {
+    pub fn new(track_ids: Vec<i64>) -> Self {
+        TrackSelection {
+            visible: Arc::new((0..track_ids.len()).collect()),
+            track_ids: Arc::new(track_ids),
+            selected: BTreeSet::new(),
+            anchor: None,
+            cursor: None,
+        }
+    }
+
+    /// Called when the listing is filtered, deselecting the tracks that are no longer shown.
+    pub fn set_visible(&mut self, visible: Arc<Vec<usize>>) {
+        self.selected.retain(|index| visible.contains(index));
+
+        if self.anchor.is_some_and(|index| !visible.contains(&index)) {
+            self.anchor = None;
+            self.cursor = None;
+        }
+
+        self.visible = visible;
+    }
+
+    pub fn is_selected(&self, index: usize) -> bool {
+        self.selected.contains(&index)
+    }
+
+    /// The ids of the selected tracks, in listing order.
+    pub fn track_ids(&self) -> Vec<i64> {
+        self.selected
+            .iter()
+            .map(|index| self.track_ids[*index])
+            .collect()
+    }
+
+    /// The ids of the tracks that are shown, in listing order, or None if every track is shown.
+    pub fn filtered_track_ids(&self) -> Option<Vec<i64>> {
+        if self.visible.len() == self.track_ids.len() {
+            return None;
+        }
+
+        Some(
+            self.visible
+                .iter()
+                .map(|index| self.track_ids[*index])
+                .collect(),
+        )
+    }
+
+    /// The position of the track that was last selected, for scrolling it into view.
+    pub fn cursor_position(&self) -> Option<usize> {
+        self.cursor.and_then(|index| self.position(index))
+    }
+
+    /// Selects only the given track.
+    pub fn select(&mut self, index: usize) {
+        self.selected.clear();
+        self.selected.insert(index);
+        self.anchor = Some(index);
+        self.cursor = Some(index);
+    }
+
+    /// Adds the track to the selection, or removes it if it's already selected.
+    pub fn toggle(&mut self, index: usize) {
+        if !self.selected.remove(&index) {
+            self.selected.insert(index);
+        }
+
+        self.anchor = Some(index);
+        self.cursor = Some(index);
+    }
+
+    /// Selects every shown track between the anchor and the given track.
+    pub fn extend_to(&mut self, index: usize) {
+        let Some(anchor) = self.anchor else {
+            self.select(index);
+            return;
+        };
+
+        let (Some(from), Some(to)) = (self.position(anchor), self.position(index)) else {
+            self.select(index);
+            return;
+        };
+
+        self.selected = self.visible[from.min(to)..=from.max(to)]
+            .iter()
+            .copied()
+            .collect();
+        self.cursor = Some(index);
+    }
+
+    /// Moves the end of the selected range by `offset` shown tracks. Without a selection, the
+    /// first or last track is selected, depending on the direction.
+    pub fn extend_by(&mut self, offset: isize) {
+        let Some(last) = self.visible.len().checked_sub(1) else {
+            return;
+        };
+
+        match self.cursor.and_then(|index| self.position(index)) {
+            Some(position) => {
+                let target = position.saturating_add_signed(offset).min(last);
+                self.extend_to(self.visible[target]);
+            }
+            None if offset < 0 => self.select(self.visible[last]),
+            None => self.select(self.visible[0]),
+        }
+    }
+
+    pub fn select_all(&mut self) {
+        self.selected = self.visible.iter().copied().collect();
+        self.anchor = self.visible.first().copied();
+        self.cursor = self.visible.last().copied();
+    }
+
+    pub fn clear(&mut self) {
+        self.selected.clear();
+        self.anchor = None;
+        self.cursor = None;
+    }
+
+    /// The position of the track among the shown tracks.
+    fn position(&self, index: usize) -> Option<usize> {
+        self.visible.binary_search(&index).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selection() -> TrackSelection {
+        TrackSelection::new(vec![10, 11, 12, 13, 14, 15])
+    }
+
+    #[test]
+    fn clicking_selects_one_track() {
+        let mut selection = selection();
+
+        selection.select(2);
+        selection.select(4);
+
+        assert_eq!(selection.track_ids(), vec![14]);
+    }
+
+    #[test]
+    fn toggling_adds_and_removes_tracks() {
+        let mut selection = selection();
+
+        selection.toggle(1);
+        selection.toggle(4);
+        selection.toggle(2);
+        selection.toggle(1);
+
+        assert_eq!(selection.track_ids(), vec![12, 14]);
+    }
+
+    #[test]
+    fn ranges_extend_from_the_anchor() {
+        let mut selection = selection();
+
+        selection.select(3);
+        selection.extend_to(1);
+        assert_eq!(selection.track_ids(), vec![11, 12, 13]);
+
+        // the anchor stays put, so the range can be moved to the other side
+        selection.extend_to(5);
+        assert_eq!(selection.track_ids(), vec![13, 14, 15]);
+    }
+
+    #[test]
+    fn ranges_skip_hidden_tracks() {
+        let mut selection = selection();
+
+        selection.set_visible(Arc::new(vec![0, 2, 5]));
+        selection.select(0);
+        selection.extend_to(5);
+
+        assert_eq!(selection.track_ids(), vec![10, 12, 15]);
+    }
+
+    #[test]
+    fn arrow_keys_move_the_end_of_the_range() {
+        let mut selection = selection();
+
+        selection.extend_by(1);
+        assert_eq!(selection.track_ids(), vec![10]);
+
+        selection.extend_by(1);
+        selection.extend_by(1);
+        assert_eq!(selection.track_ids(), vec![10, 11, 12]);
+        assert_eq!(selection.cursor_position(), Some(2));
+
+        selection.extend_by(-1);
+        assert_eq!(selection.track_ids(), vec![10, 11]);
+
+        selection.extend_by(-5);
+        assert_eq!(selection.track_ids(), vec![10]);
+    }
+
+    #[test]
+    fn filtering_deselects_hidden_tracks() {
+        let mut selection = selection();
+
+        selection.select_all();
+        selection.set_visible(Arc::new(vec![1, 3]));
+
+        assert_eq!(selection.track_ids(), vec![11, 13]);
+        assert!(!selection.is_selected(0));
+    }
+
+    #[test]
+    fn filtered_track_ids_are_the_shown_tracks() {
+        let mut selection = selection();
+        assert_eq!(selection.filtered_track_ids(), None);
+
+        selection.set_visible(Arc::new(vec![1, 3, 4]));
+        assert_eq!(selection.filtered_track_ids(), Some(vec![11, 13, 14]));
+
+        selection.set_visible(Arc::new((0..6).collect()));
+        assert_eq!(selection.filtered_track_ids(), None);
+    }
+}
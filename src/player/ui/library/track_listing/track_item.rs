@@ -1,25 +1,38 @@
+use std::sync::Arc;
+
 use gpui::prelude::{FluentBuilder, *};
 use gpui::{App, Entity, FontWeight, IntoElement, SharedString, Window, div, img, px};
 
 use crate::player::ui::components::icons::{PLAY, PLUS, STAR, STAR_FILLED, icon};
-use crate::player::ui::models::PlaylistEvent;
+use crate::player::ui::models::{MMBSEvent, PlaylistEvent};
 use crate::{
-    player::library::{db::LibraryAccess, types::Track},
+    player::library::{
+        db::LibraryAccess,
+        scan::ScanInterface,
+        types::{PlaylistType, Track},
+    },
     player::playback::{
-        interface::{GPUIPlaybackInterface, replace_queue},
+        interface::{GPUIPlaybackInterface, queue_other_folder_tracks, replace_queue},
         queue::QueueItemData,
     },
     player::ui::{
         components::{
             context::context,
-            menu::{menu, menu_item},
+            menu::{CMenuItem, Menu, menu, menu_item},
         },
+        drag::{DragSource, DraggedTracks},
+        metadata_lookup::open_metadata_lookup,
         models::{Models, PlaybackInfo},
         theme::Theme,
     },
+    shared::{
+        format,
+        i18n::{t, t_args},
+        settings::{SettingsGlobal, interface::FormatLocale, playback::TrackDoubleClickAction},
+    },
 };
 
-use super::ArtistNameVisibility;
+use super::{ArtistNameVisibility, selection::TrackSelection};
 
 pub struct TrackPlaylistInfo {
     pub id: i64,
@@ -32,9 +45,95 @@ pub struct TrackItem {
     pub artist_name_visibility: ArtistNameVisibility,
     pub is_liked: Option<i64>,
     pub hover_group: SharedString,
+    /// How many times the track has been played to the end. Only shown where it's been looked up,
+    /// otherwise it's left at 0 and nothing is shown.
+    pub play_count: i64,
     left_field: TrackItemLeftField,
     album_art: Option<SharedString>,
     pl_info: Option<TrackPlaylistInfo>,
+    selection: Option<ItemSelection>,
+}
+
+/// The selection of the listing a track is in, and the track's index in the listing.
+#[derive(Clone)]
+pub struct ItemSelection {
+    pub selection: Entity<TrackSelection>,
+    pub menu: Entity<Option<Arc<SelectionMenu>>>,
+    pub index: usize,
+}
+
+/// What the menu for several selected tracks shows. It's looked up only when the selection or a
+/// playlist changes, rather than every time one of the selected tracks is rendered.
+pub struct SelectionMenu {
+    track_ids: Arc<Vec<i64>>,
+    all_liked: bool,
+    playlists: Vec<(i64, SharedString)>,
+}
+
+impl SelectionMenu {
+    /// Creates the menu for the selection, and keeps it up to date. It's None unless several
+    /// tracks are selected.
+    pub fn observe(
+        cx: &mut App,
+        selection: &Entity<TrackSelection>,
+    ) -> Entity<Option<Arc<SelectionMenu>>> {
+        cx.new(|cx| {
+            cx.observe(
+                selection,
+                |this: &mut Option<Arc<SelectionMenu>>, selection, cx| {
+                    let track_ids = selection.read(cx).track_ids();
+
+                    if this
+                        .as_ref()
+                        .is_some_and(|menu| *menu.track_ids == track_ids)
+                    {
+                        return;
+                    }
+
+                    *this = SelectionMenu::read(cx, track_ids);
+                    cx.notify();
+                },
+            )
+            .detach();
+
+            let playlist_tracker = cx.global::<Models>().playlist_tracker.clone();
+            cx.subscribe(
+                &playlist_tracker,
+                |this: &mut Option<Arc<SelectionMenu>>, _, _: &PlaylistEvent, cx| {
+                    if let Some(menu) = this.take() {
+                        *this = SelectionMenu::read(cx, menu.track_ids.to_vec());
+                        cx.notify();
+                    }
+                },
+            )
+            .detach();
+
+            None
+        })
+    }
+
+    fn read(cx: &App, track_ids: Vec<i64>) -> Option<Arc<SelectionMenu>> {
+        if track_ids.len() < 2 {
+            return None;
+        }
+
+        let all_liked = track_ids
+            .iter()
+            .all(|id| cx.playlist_has_track(1, *id).ok().flatten().is_some());
+        let playlists = cx
+            .get_all_playlists()
+            .unwrap_or_default()
+            .iter()
+            .filter(|playlist| playlist.playlist_type == PlaylistType::User)
+            .map(|playlist| (playlist.id, playlist.name.0.clone()))
+            .collect();
+
+        Some(Arc::new(SelectionMenu {
+            track_ids: Arc::new(track_ids),
+            all_liked,
+            playlists,
+        }))
+    }
 }
 
 #[derive(Eq, PartialEq)]
@@ -51,18 +150,47 @@ impl TrackItem {
         anv: ArtistNameVisibility,
         left_field: TrackItemLeftField,
         pl_info: Option<TrackPlaylistInfo>,
+        selection: Option<ItemSelection>,
     ) -> Entity<Self> {
-        cx.new(|cx| Self {
-            hover_group: format!("track-{}", track.id).into(),
-            is_liked: cx.playlist_has_track(1, track.id).unwrap_or_default(),
-            album_art: track
-                .album_id
-                .map(|v| format!("!db://album/{v}/thumb").into()),
-            track,
-            is_start,
-            artist_name_visibility: anv,
-            left_field,
-            pl_info,
+        cx.new(|cx| {
+            if let Some(selection) = &selection {
+                cx.observe(&selection.selection, |_, _, cx| cx.notify())
+                    .detach();
+                cx.observe(&selection.menu, |_, _, cx| cx.notify()).detach();
+            }
+
+            // liked songs can be changed for several tracks at once, from any of them
+            let playlist_tracker = cx.global::<Models>().playlist_tracker.clone();
+            cx.subscribe(
+                &playlist_tracker,
+                |this: &mut Self, _, ev: &PlaylistEvent, cx| match ev {
+                    PlaylistEvent::PlaylistUpdated(1) => {
+                        let is_liked = cx.playlist_has_track(1, this.track.id).unwrap_or_default();
+
+                        if this.is_liked != is_liked {
+                            this.is_liked = is_liked;
+                            cx.notify();
+                        }
+                    }
+                    PlaylistEvent::PlaylistUpdated(_) => (),
+                },
+            )
+            .detach();
+
+            Self {
+                hover_group: format!("track-{}", track.id).into(),
+                is_liked: cx.playlist_has_track(1, track.id).unwrap_or_default(),
+                play_count: 0,
+                album_art: track
+                    .album_id
+                    .map(|v| format!("!db://album/{v}/thumb").into()),
+                track,
+                is_start,
+                artist_name_visibility: anv,
+                left_field,
+                pl_info,
+                selection,
+            }
         })
     }
 }
@@ -71,9 +199,12 @@ impl Render for TrackItem {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
         let current_track = cx.global::<PlaybackInfo>().current_track.read(cx).clone();
+        let locale = format::locale(cx);
 
         let track_location = self.track.location.clone();
         let track_location_2 = self.track.location.clone();
+        let track_location_3 = self.track.location.clone();
+        let track_location_4 = self.track.location.clone();
         let track_id = self.track.id;
         let album_id = self.track.album_id;
 
@@ -81,7 +212,33 @@ impl Render for TrackItem {
             && self.artist_name_visibility
                 != ArtistNameVisibility::OnlyIfDifferent(self.track.artist_names.clone());
 
+        let selected = self
+            .selection
+            .as_ref()
+            .is_some_and(|item| item.selection.read(cx).is_selected(item.index));
+        // right-clicking or dragging one of several selected tracks acts on all of them
+        let batch = self
+            .selection
+            .as_ref()
+            .filter(|_| selected)
+            .and_then(|item| Some((item.selection.clone(), item.menu.read(cx).clone()?)));
+
         let track = self.track.clone();
+        let drag_data = match &batch {
+            Some((_, menu)) => DraggedTracks {
+                source: DragSource::Tracks(menu.track_ids.to_vec()),
+                title: format!("{} tracks", menu.track_ids.len()).into(),
+                image: self.album_art.clone(),
+            },
+            None => DraggedTracks {
+                source: DragSource::Tracks(vec![self.track.id]),
+                title: self.track.title.0.clone(),
+                image: self.album_art.clone(),
+            },
+        };
+        let batch_items = batch
+            .map(|(selection, menu)| selection_menu_items(selection, &menu))
+            .unwrap_or_default();
 
         context(("context", self.track.id as usize))
             .with(
@@ -93,7 +250,31 @@ impl Render for TrackItem {
                     .on_click({
                         let track = self.track.clone();
                         let plid = self.pl_info.as_ref().map(|pl| pl.id);
-                        move |_, _, cx| play_from_track(cx, &track, plid)
+                        let selection = self.selection.clone();
+                        move |ev, _, cx| {
+                            if let Some(ItemSelection {
+                                selection, index, ..
+                            }) = &selection
+                            {
+                                let modifiers = ev.modifiers();
+
+                                selection.update(cx, |selection, cx| {
+                                    if modifiers.shift {
+                                        selection.extend_to(*index);
+                                    } else if modifiers.secondary() {
+                                        selection.toggle(*index);
+                                    } else {
+                                        selection.select(*index);
+                                    }
+                                    cx.notify();
+                                });
+                            }
+
+                            if ev.click_count() == 2 {
+                                let shown = shown_track_ids(selection.as_ref(), cx);
+                                on_double_click(cx, &track, plid, shown.as_deref())
+                            }
+                        }
                     })
                     .when(self.is_start, |this| {
                         this.child(
@@ -107,9 +288,7 @@ impl Render for TrackItem {
                                 .border_color(theme.border_color)
                                 .mt(px(24.0))
                                 .pb(px(6.0))
-                                .when_some(self.track.disc_number, |this, num| {
-                                    this.child(format!("DISC {num}"))
-                                }),
+                                .child(t_args("library.disc", &[&self.track.disc()])),
                         )
                     })
                     .child(
@@ -118,6 +297,7 @@ impl Render for TrackItem {
                             .flex_row()
                             .border_b_1()
                             .id(("track", self.track.id as u64))
+                            .on_drag(drag_data, |data, _, _, cx| cx.new(|_| data.clone()))
                             .w_full()
                             .border_color(theme.border_color)
                             .cursor_pointer()
@@ -133,6 +313,7 @@ impl Render for TrackItem {
                                     theme.background_primary
                                 })
                             })
+                            .when(selected, |this| this.bg(theme.track_item_selected))
                             .max_w_full()
                             .when(self.left_field == TrackItemLeftField::TrackNum, |this| {
                                 this.child(div().w(px(62.0)).flex_shrink_0().child(format!(
@@ -207,6 +388,17 @@ impl Render for TrackItem {
                                             cx.emit(PlaylistEvent::PlaylistUpdated(1));
                                         });
 
+                                        if let Some(artist) = this.track.artist_names.clone() {
+                                            let mmbs = cx.global::<Models>().mmbs.clone();
+                                            let event = MMBSEvent::FavoriteChanged {
+                                                artist: artist.0.to_string(),
+                                                track: this.track.title.0.to_string(),
+                                                favorite: this.is_liked.is_some(),
+                                            };
+
+                                            mmbs.update(cx, |_, cx| cx.emit(event));
+                                        }
+
                                         cx.notify();
                                     })),
                             )
@@ -227,6 +419,17 @@ impl Render for TrackItem {
                                         )
                                     }),
                             )
+                            .when(self.play_count > 0, |this| {
+                                this.child(
+                                    div()
+                                        .text_sm()
+                                        .my_auto()
+                                        .ml(px(12.0))
+                                        .flex_shrink_0()
+                                        .text_color(theme.text_secondary)
+                                        .child(play_count_label(self.play_count, locale)),
+                                )
+                            })
                             .child(div().ml(px(12.0)).flex_shrink_0().child(format!(
                                 "{}:{:02}",
                                 self.track.duration / 60,
@@ -236,11 +439,13 @@ impl Render for TrackItem {
             )
             .child(
                 div().bg(theme.elevated_background).child(
-                    menu()
+                    batch_items
+                        .into_iter()
+                        .fold(menu(), Menu::item)
                         .item(menu_item(
                             "track_play",
                             Some(PLAY),
-                            "Play",
+                            t("library.play"),
                             move |_, _, cx| {
                                 let data = QueueItemData::new(
                                     cx,
@@ -264,16 +469,20 @@ impl Render for TrackItem {
                         .item(menu_item(
                             "track_play_from_here",
                             None::<&str>,
-                            "Play from here",
+                            t("library.play_from_here"),
                             {
                                 let plid = self.pl_info.as_ref().map(|pl| pl.id);
-                                move |_, _, cx| play_from_track(cx, &track, plid)
+                                let selection = self.selection.clone();
+                                move |_, _, cx| {
+                                    let shown = shown_track_ids(selection.as_ref(), cx);
+                                    play_from_track(cx, &track, plid, shown.as_deref())
+                                }
                             },
                         ))
                         .item(menu_item(
                             "track_add_to_queue",
                             Some(PLUS),
-                            "Add to queue",
+                            t("library.add_to_queue"),
                             move |_, _, cx| {
                                 let data = QueueItemData::new(
                                     cx,
@@ -284,13 +493,275 @@ impl Render for TrackItem {
                                 let playback_interface = cx.global::<GPUIPlaybackInterface>();
                                 playback_interface.queue(data);
                             },
+                        ))
+                        .when_some(album_id, |menu, album_id| {
+                            let track_2 = self.track.clone();
+                            let track_3 = self.track.clone();
+                            let selection_2 = self.selection.clone();
+                            let selection_3 = self.selection.clone();
+
+                            menu.item(menu_item(
+                                "track_play_album_from_here",
+                                None::<&str>,
+                                t("library.play_to_end_of_album"),
+                                move |_, _, cx| {
+                                    let shown = shown_track_ids(selection_2.as_ref(), cx);
+                                    play_album_from_track(cx, &track_2, album_id, shown.as_deref())
+                                },
+                            ))
+                            .item(menu_item(
+                                "track_queue_album_from_here",
+                                None::<&str>,
+                                t("library.queue_to_end_of_album"),
+                                move |_, _, cx| {
+                                    let shown = shown_track_ids(selection_3.as_ref(), cx);
+                                    queue_album_from_track(cx, &track_3, album_id, shown.as_deref())
+                                },
+                            ))
+                        })
+                        .item(menu_item(
+                            "track_queue_folder",
+                            None::<&str>,
+                            t("library.queue_folder"),
+                            move |_, _, cx| queue_other_folder_tracks(&track_location_4, cx),
+                        ))
+                        .item(menu_item(
+                            "track_refresh_metadata",
+                            None::<&str>,
+                            t("library.refresh_metadata"),
+                            move |_, _, cx| {
+                                cx.global::<ScanInterface>()
+                                    .refresh_track(track_location_3.clone());
+                            },
+                        ))
+                        .item(menu_item(
+                            "track_lookup_metadata",
+                            None::<&str>,
+                            t("library.look_up"),
+                            {
+                                let track = self.track.clone();
+                                move |_, _, cx| open_metadata_lookup(cx, track.clone())
+                            },
                         )),
                 ),
             )
     }
 }
 
-pub fn play_from_track(cx: &mut App, track: &Track, pl_id: Option<i64>) {
+/// The menu items that act on every selected track, followed by a separator.
+fn selection_menu_items(selection: Entity<TrackSelection>, menu: &SelectionMenu) -> Vec<CMenuItem> {
+    let count = menu.track_ids.len();
+    let track_ids = menu.track_ids.clone();
+    let all_liked = menu.all_liked;
+
+    let mut items = vec![
+        menu_item(
+            "selection_play",
+            Some(PLAY),
+            t_args("library.play_tracks", &[&count]),
+            {
+                let track_ids = track_ids.clone();
+                move |_, _, cx| {
+                    let queue_items = selected_queue_items(cx, &track_ids);
+                    replace_queue(queue_items, cx);
+                }
+            },
+        ),
+        menu_item(
+            "selection_add_to_queue",
+            Some(PLUS),
+            t_args("library.queue_tracks", &[&count]),
+            {
+                let track_ids = track_ids.clone();
+                move |_, _, cx| {
+                    let queue_items = selected_queue_items(cx, &track_ids);
+                    cx.global::<GPUIPlaybackInterface>().queue_list(queue_items);
+                }
+            },
+        ),
+        menu_item(
+            "selection_like",
+            Some(if all_liked { STAR } else { STAR_FILLED }),
+            if all_liked {
+                t("library.unlike")
+            } else {
+                t("library.like")
+            },
+            {
+                let track_ids = track_ids.clone();
+                move |_, _, cx| set_liked(cx, &track_ids, !all_liked)
+            },
+        ),
+    ];
+
+    for (playlist_id, name) in &menu.playlists {
+        let playlist_id = *playlist_id;
+        let track_ids = track_ids.clone();
+
+        items.push(menu_item(
+            ("selection_add_to_playlist", playlist_id as u64),
+            None::<&str>,
+            t_args("library.add_to_playlist", &[name]),
+            move |_, _, cx| add_to_playlist(cx, playlist_id, &track_ids),
+        ));
+    }
+
+    items.push(menu_item(
+        "selection_clear",
+        None::<&str>,
+        t("library.clear_selection"),
+        move |_, _, cx| {
+            selection.update(cx, |selection, cx| {
+                selection.clear();
+                cx.notify();
+            })
+        },
+    ));
+    items.push(CMenuItem::Seperator);
+
+    items
+}
+
+fn selected_queue_items(cx: &mut App, track_ids: &[i64]) -> Vec<QueueItemData> {
+    DraggedTracks {
+        source: DragSource::Tracks(track_ids.to_vec()),
+        title: SharedString::default(),
+        image: None,
+    }
+    .queue_items(cx)
+}
+
+/// Adds the tracks to, or removes them from, the liked songs playlist.
+fn set_liked(cx: &mut App, track_ids: &[i64], liked: bool) {
+    for track_id in track_ids {
+        let item_id = cx.playlist_has_track(1, *track_id).unwrap_or_default();
+
+        match item_id {
+            Some(item_id) if !liked => {
+                cx.remove_playlist_item(item_id)
+                    .expect("could not unlike song");
+            }
+            None if liked => {
+                cx.add_playlist_item(1, *track_id)
+                    .expect("could not like song");
+            }
+            _ => continue,
+        }
+
+        let track = cx.get_track_by_id(*track_id).ok();
+
+        if let Some((track, artist)) =
+            track.and_then(|track| track.artist_names.clone().map(|artist| (track, artist)))
+        {
+            let mmbs = cx.global::<Models>().mmbs.clone();
+            let event = MMBSEvent::FavoriteChanged {
+                artist: artist.0.to_string(),
+                track: track.title.0.to_string(),
+                favorite: liked,
+            };
+
+            mmbs.update(cx, |_, cx| cx.emit(event));
+        }
+    }
+
+    let playlist_tracker = cx.global::<Models>().playlist_tracker.clone();
+    playlist_tracker.update(cx, |_, cx| {
+        cx.emit(PlaylistEvent::PlaylistUpdated(1));
+    });
+}
+
+/// Adds the tracks that aren't already in the playlist to the end of it.
+fn add_to_playlist(cx: &mut App, playlist_id: i64, track_ids: &[i64]) {
+    for track_id in track_ids {
+        if cx
+            .playlist_has_track(playlist_id, *track_id)
+            .unwrap_or_default()
+            .is_none()
+        {
+            cx.add_playlist_item(playlist_id, *track_id)
+                .expect("could not add track to playlist");
+        }
+    }
+
+    let playlist_tracker = cx.global::<Models>().playlist_tracker.clone();
+    playlist_tracker.update(cx, |_, cx| {
+        cx.emit(PlaylistEvent::PlaylistUpdated(playlist_id));
+    });
+}
+
+/// The ids of the tracks shown in a listing, if it's filtered. Playing from a filtered listing
+/// only plays the tracks that match the filter.
+fn shown_track_ids(selection: Option<&ItemSelection>, cx: &App) -> Option<Vec<i64>> {
+    selection.and_then(|item| item.selection.read(cx).filtered_track_ids())
+}
+
+/// Whether the track is shown, when only the tracks with the given ids are.
+fn is_shown(track_id: i64, shown: Option<&[i64]>) -> bool {
+    shown.is_none_or(|ids| ids.contains(&track_id))
+}
+
+fn on_double_click(cx: &mut App, track: &Track, pl_id: Option<i64>, shown: Option<&[i64]>) {
+    let action = cx
+        .global::<SettingsGlobal>()
+        .model
+        .read(cx)
+        .playback
+        .double_click_action;
+
+    match action {
+        TrackDoubleClickAction::PlayTrack => {
+            let data = QueueItemData::from_track(cx, track);
+            replace_queue(vec![data], cx);
+        }
+        TrackDoubleClickAction::PlayAlbumFromHere => match (pl_id, track.album_id) {
+            (None, Some(album_id)) => play_album_from_track(cx, track, album_id, shown),
+            _ => play_from_track(cx, track, pl_id, shown),
+        },
+        TrackDoubleClickAction::AddToQueue => {
+            let data = QueueItemData::from_track(cx, track);
+            cx.global::<GPUIPlaybackInterface>().queue(data);
+        }
+    }
+}
+
+/// Builds queue items for the given track and every shown track after it in the album, in disc
+/// and track order.
+fn album_items_from_track(
+    cx: &mut App,
+    track: &Track,
+    album_id: i64,
+    shown: Option<&[i64]>,
+) -> Vec<QueueItemData> {
+    let tracks = cx
+        .list_tracks_in_album(album_id)
+        .expect("Failed to retrieve tracks");
+    let start = tracks
+        .iter()
+        .position(|t| t.id == track.id)
+        .unwrap_or_default();
+
+    tracks[start..]
+        .iter()
+        .filter(|other| other.id == track.id || is_shown(other.id, shown))
+        .map(|track| QueueItemData::from_track(cx, track))
+        .collect()
+}
+
+/// Replaces the queue with the given track and every shown track after it in the album.
+fn play_album_from_track(cx: &mut App, track: &Track, album_id: i64, shown: Option<&[i64]>) {
+    let queue_items = album_items_from_track(cx, track, album_id, shown);
+    replace_queue(queue_items, cx);
+}
+
+/// Appends the given track and every shown track after it in the album to the queue.
+fn queue_album_from_track(cx: &mut App, track: &Track, album_id: i64, shown: Option<&[i64]>) {
+    let queue_items = album_items_from_track(cx, track, album_id, shown);
+    cx.global::<GPUIPlaybackInterface>().queue_list(queue_items);
+}
+
+/// Replaces the queue with the playlist or album the track is from, and plays it from the track.
+/// Only the shown tracks are queued, along with the track itself.
+pub fn play_from_track(cx: &mut App, track: &Track, pl_id: Option<i64>, shown: Option<&[i64]>) {
     let queue_items = if let Some(pl_id) = pl_id {
         let ids = cx
             .get_playlist_tracks(pl_id)
@@ -301,6 +772,7 @@ pub fn play_from_track(cx: &mut App, track: &Track, pl_id: Option<i64>) {
 
         ids.iter()
             .zip(paths.iter())
+            .filter(|((_, id, _), _)| *id == track.id || is_shown(*id, shown))
             .map(|((_, track, album), path)| {
                 QueueItemData::new(cx, path.into(), Some(*track), Some(*album))
             })
@@ -309,17 +781,11 @@ pub fn play_from_track(cx: &mut App, track: &Track, pl_id: Option<i64>) {
         cx.list_tracks_in_album(album_id)
             .expect("Failed to retrieve tracks")
             .iter()
-            .map(|track| {
-                QueueItemData::new(cx, track.location.clone(), Some(track.id), track.album_id)
-            })
+            .filter(|other| other.id == track.id || is_shown(other.id, shown))
+            .map(|track| QueueItemData::from_track(cx, track))
             .collect()
     } else {
-        Vec::from([QueueItemData::new(
-            cx,
-            track.location.clone(),
-            Some(track.id),
-            track.album_id,
-        )])
+        Vec::from([QueueItemData::from_track(cx, track)])
     };
 
     replace_queue(queue_items.clone(), cx);
@@ -332,3 +798,12 @@ pub fn play_from_track(cx: &mut App, track: &Track, pl_id: Option<i64>) {
             .unwrap(),
     )
 }
+
+/// Describes a play count, such as "1 play" or "1,234 plays".
+pub fn play_count_label(count: i64, locale: FormatLocale) -> String {
+    if count == 1 {
+        "1 play".to_string()
+    } else {
+        format!("{} plays", format::number(count, locale))
+    }
+}
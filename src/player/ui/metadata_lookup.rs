@@ -0,0 +1,227 @@
+use std::path::Path;
+
+use gpui::*;
+use prelude::FluentBuilder;
+use tracing::error;
+
+use crate::player::{
+    library::{
+        db::{LibraryAccess, ReleaseInfo},
+        scan::ScanEvent,
+        types::Track,
+    },
+    services::musicbrainz::{ReleaseCandidate, lookup_recording},
+};
+
+use super::{
+    components::{
+        button::{ButtonIntent, ButtonSize, button},
+        modal::modal,
+    },
+    models::Models,
+    theme::Theme,
+};
+
+enum LookupState {
+    Searching,
+    Failed(SharedString),
+    Found(Vec<ReleaseCandidate>),
+}
+
+/// Looks up a track on MusicBrainz and lets the user pick which release it belongs to.
+pub struct MetadataLookup {
+    track: Track,
+    state: LookupState,
+}
+
+impl MetadataLookup {
+    pub fn new(cx: &mut App, track: Track) -> Entity<Self> {
+        cx.new(|cx| {
+            // tracks without a title tag are named after their file, which won't match anything
+            let title = if track.location.file_name().and_then(|v| v.to_str())
+                == Some(track.title.0.as_ref())
+            {
+                Path::new(track.title.0.as_ref())
+                    .file_stem()
+                    .map(|v| v.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            } else {
+                track.title.0.to_string()
+            };
+            let artist = track.artist_names.as_ref().map(|v| v.0.to_string());
+
+            cx.spawn(async move |this, cx| {
+                let result = cx
+                    .background_spawn(async move {
+                        lookup_recording(&title, artist.as_deref(), None).await
+                    })
+                    .await;
+
+                this.update(cx, |this, cx| {
+                    this.state = match result {
+                        Ok(candidates) => LookupState::Found(candidates),
+                        Err(e) => {
+                            error!("MusicBrainz lookup failed: {:?}", e);
+                            LookupState::Failed(format!("{e}").into())
+                        }
+                    };
+                    cx.notify();
+                })
+                .ok();
+            })
+            .detach();
+
+            Self {
+                track,
+                state: LookupState::Searching,
+            }
+        })
+    }
+}
+
+/// Opens the lookup dialog for a track.
+pub fn open_metadata_lookup(cx: &mut App, track: Track) {
+    let lookup = MetadataLookup::new(cx, track);
+    let model = cx.global::<Models>().metadata_lookup.clone();
+    model.write(cx, Some(lookup));
+}
+
+fn close_metadata_lookup(cx: &mut App) {
+    let model = cx.global::<Models>().metadata_lookup.clone();
+    model.write(cx, None);
+}
+
+fn apply_candidate(cx: &mut App, track_id: i64, candidate: &ReleaseCandidate) {
+    let info = ReleaseInfo {
+        title: candidate.title.clone(),
+        artist: candidate.artist.clone(),
+        album: candidate.album.clone(),
+        album_mbid: candidate.album_mbid.clone(),
+        release_date: candidate.date.map(|v| v.timestamp()),
+        track_number: candidate.track_number,
+    };
+
+    if let Err(e) = cx.apply_release_info(track_id, &info) {
+        error!("Could not apply release information: {:?}", e);
+        return;
+    }
+
+    // reload the library views
+    let scan_state = cx.global::<Models>().scan_state.clone();
+    scan_state.update(cx, |m, cx| {
+        *m = ScanEvent::ScanCompleteIdle;
+        cx.notify();
+    });
+
+    close_metadata_lookup(cx);
+}
+
+fn candidate_row(
+    idx: usize,
+    track_id: i64,
+    candidate: &ReleaseCandidate,
+    theme: &Theme,
+) -> impl IntoElement {
+    let details = match candidate.date {
+        Some(date) => format!("{} · {}", candidate.artist, date.format("%Y")),
+        None => candidate.artist.clone(),
+    };
+    let candidate = candidate.clone();
+
+    div()
+        .flex()
+        .items_center()
+        .gap(px(8.0))
+        .px(px(10.0))
+        .py(px(6.0))
+        .rounded(px(4.0))
+        .bg(theme.background_tertiary)
+        .child(
+            div()
+                .flex()
+                .flex_col()
+                .flex_grow()
+                .overflow_hidden()
+                .child(
+                    div()
+                        .text_sm()
+                        .overflow_hidden()
+                        .text_ellipsis()
+                        .child(SharedString::from(candidate.album.clone())),
+                )
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(theme.text_secondary)
+                        .overflow_hidden()
+                        .text_ellipsis()
+                        .child(SharedString::from(details)),
+                ),
+        )
+        .child(
+            button()
+                .size(ButtonSize::Regular)
+                .intent(ButtonIntent::Primary)
+                .child("Apply")
+                .id(("lookup-apply", idx))
+                .on_click(move |_, _, cx| apply_candidate(cx, track_id, &candidate)),
+        )
+}
+
+impl Render for MetadataLookup {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let track_id = self.track.id;
+        let subtitle = match &self.track.artist_names {
+            Some(artist) => format!("{} by {}", self.track.title.0, artist.0),
+            None => self.track.title.0.to_string(),
+        };
+
+        modal().on_exit(|_, cx| close_metadata_lookup(cx)).child(
+            div()
+                .id("metadata-lookup")
+                .flex()
+                .flex_col()
+                .w(px(520.0))
+                .max_h(px(520.0))
+                .overflow_y_scroll()
+                .p(px(24.0))
+                .gap(px(12.0))
+                .child(
+                    div()
+                        .text_size(px(20.0))
+                        .font_weight(FontWeight::BOLD)
+                        .child("Look Up on MusicBrainz"),
+                )
+                .child(
+                    div()
+                        .text_sm()
+                        .text_color(theme.text_secondary)
+                        .child(subtitle),
+                )
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(theme.text_secondary)
+                        .child("Applying a release only changes your library, not the file."),
+                )
+                .map(|this| match &self.state {
+                    LookupState::Searching => this.child(div().text_sm().child("Searching…")),
+                    LookupState::Failed(message) => this.child(
+                        div()
+                            .text_sm()
+                            .child(format!("The lookup failed: {message}")),
+                    ),
+                    LookupState::Found(candidates) if candidates.is_empty() => {
+                        this.child(div().text_sm().child("No matches found."))
+                    }
+                    LookupState::Found(candidates) => this.children(
+                        candidates
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, candidate)| candidate_row(idx, track_id, candidate, theme)),
+                    ),
+                }),
+        )
+    }
+}
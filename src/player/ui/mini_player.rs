@@ -0,0 +1,91 @@
+use gpui::*;
+use prelude::FluentBuilder;
+
+use crate::player::ui::components::icons::{MAXIMIZE, icon};
+
+use super::{
+    controls::{InfoSection, PlaybackSection},
+    models::Models,
+    theme::Theme,
+};
+
+/// The size of the window while the mini player is shown.
+pub const MINI_PLAYER_SIZE: Size<Pixels> = Size {
+    width: px(420.0),
+    height: px(110.0),
+};
+
+/// A compact view of the current track and the transport controls, shown in place of the rest of
+/// the interface while mini player mode is enabled.
+pub struct MiniPlayer {
+    info_section: Entity<InfoSection>,
+    playback_section: Entity<PlaybackSection>,
+}
+
+impl MiniPlayer {
+    pub fn new(cx: &mut App) -> Entity<Self> {
+        cx.new(|cx| Self {
+            info_section: InfoSection::new(cx),
+            playback_section: PlaybackSection::new(cx),
+        })
+    }
+}
+
+impl Render for MiniPlayer {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+
+        div()
+            .id("mini-player")
+            .size_full()
+            .flex()
+            .flex_col()
+            .bg(theme.background_secondary)
+            .window_control_area(WindowControlArea::Drag)
+            .when(cfg!(not(target_os = "windows")), |this| {
+                this.on_mouse_down(MouseButton::Left, |_, window, _| {
+                    window.start_window_move();
+                })
+            })
+            .child(
+                div()
+                    .flex()
+                    .w_full()
+                    .child(self.info_section.clone())
+                    .child(
+                        div()
+                            .ml_auto()
+                            .mt(px(12.0))
+                            .mr(px(12.0))
+                            .rounded(px(3.0))
+                            .w(px(28.0))
+                            .h(px(25.0))
+                            .flex()
+                            .items_center()
+                            .justify_center()
+                            .id("mini-player-restore")
+                            .cursor_pointer()
+                            .bg(theme.playback_button)
+                            .hover(|this| this.bg(theme.playback_button_hover))
+                            .active(|this| this.bg(theme.playback_button_active))
+                            .on_mouse_down(MouseButton::Left, |_, window, cx| {
+                                cx.stop_propagation();
+                                window.prevent_default();
+                            })
+                            .on_click(|_, _, cx| {
+                                let mini_player = cx.global::<Models>().mini_player.clone();
+                                mini_player.write(cx, false);
+                            })
+                            .child(icon(MAXIMIZE).size(px(14.0))),
+                    ),
+            )
+            .child(
+                div()
+                    .relative()
+                    .w_full()
+                    .h(px(40.0))
+                    .px(px(12.0))
+                    .child(self.playback_section.clone()),
+            )
+    }
+}
@@ -3,16 +3,23 @@ pub mod app;
 mod arguments;
 mod assets;
 pub mod caching;
+mod command_palette;
 pub mod components;
 mod config;
 pub mod constants;
 mod controls;
+mod diagnostics;
 pub mod data;
+pub mod drag;
 pub mod global_actions;
 mod header;
 mod library;
+mod metadata_lookup;
+mod mini_player;
 pub mod models;
 mod queue;
 mod search;
+mod settings;
 pub mod theme;
 pub mod util;
+mod window_level;
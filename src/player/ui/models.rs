@@ -1,32 +1,45 @@
 use std::{
-    collections::VecDeque,
+    collections::{BTreeMap, VecDeque},
     fs::{File, OpenOptions},
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{Arc, RwLock},
+    time::Instant,
 };
 
 use ahash::AHashMap;
 use async_lock::Mutex;
-use gpui::{App, AppContext, Entity, EventEmitter, Global, RenderImage};
+use gpui::{App, AppContext, Context, Entity, EventEmitter, Global, RenderImage};
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, warn};
 
 use crate::{
-    player::library::scan::ScanEvent,
+    player::library::{db::LibraryAccess, scan::ScanEvent, types::TrackSegment},
     player::media::metadata::Metadata,
     player::playback::{
-        events::RepeatState,
+        events::{OutputDevice, PlaybackDiagnostics, PlaybackFormat, RepeatState},
         queue::{QueueItemData, QueueItemUIData},
         thread::PlaybackState,
     },
     player::services::mmb::{
         MediaMetadataBroadcastService,
         lastfm::{LASTFM_API_KEY, LASTFM_API_SECRET, LastFM, client::LastFMClient, types::Session},
+        listenbrainz::ListenBrainz,
+        scrobble::ScrobbleThreshold,
+    },
+    shared::{
+        cache::enforce_size_limit,
+        credentials::{load_secret, store_secret},
+        settings::{SettingsGlobal, storage::StorageData},
+    },
+    player::ui::{
+        app::data_dir, data::Decode, diagnostics::LibraryDiagnostics, library::ViewSwitchMessage,
+        metadata_lookup::MetadataLookup,
     },
-    shared::settings::{SettingsGlobal, storage::StorageData},
-    player::ui::{app::get_dirs, data::Decode, library::ViewSwitchMessage},
 };
 
+/// The name the last.fm session key is stored under in the credential store.
+const LASTFM_SESSION_SECRET: &str = "lastfm-session";
+
 // yes this looks a little silly
 impl EventEmitter<Metadata> for Metadata {}
 
@@ -44,6 +57,11 @@ pub enum LastFMState {
 
 impl EventEmitter<Session> for LastFMState {}
 
+#[derive(Serialize, Deserialize)]
+struct ListenBrainzToken {
+    token: String,
+}
+
 pub struct Models {
     pub metadata: Entity<Metadata>,
     pub albumart: Entity<Option<Arc<RenderImage>>>,
@@ -51,10 +69,25 @@ pub struct Models {
     pub scan_state: Entity<ScanEvent>,
     pub mmbs: Entity<MMBSList>,
     pub lastfm: Entity<LastFMState>,
+    /// The user's ListenBrainz token, if they've provided one.
+    pub listenbrainz_token: Entity<Option<String>>,
     pub switcher_model: Entity<VecDeque<ViewSwitchMessage>>,
     pub show_about: Entity<bool>,
     pub show_config: Entity<bool>,
+    pub show_settings: Entity<bool>,
     pub playlist_tracker: Entity<PlaylistInfoTransfer>,
+    pub play_history: Entity<PlayHistory>,
+    /// The total size of the cache directory, in bytes.
+    pub cache_size: Entity<u64>,
+    /// Whether the window is showing the compact mini player instead of the full interface.
+    pub mini_player: Entity<bool>,
+    /// The MusicBrainz lookup dialog, if it's open.
+    pub metadata_lookup: Entity<Option<Entity<MetadataLookup>>>,
+    /// Details about the library for the about dialog, gathered when it's opened.
+    pub library_diagnostics: Entity<Option<LibraryDiagnostics>>,
+    /// The number of library tracks in each music folder, counted when the settings dialog is
+    /// opened and after each scan while it's open.
+    pub folder_track_counts: Entity<AHashMap<PathBuf, i64>>,
 }
 
 impl Global for Models {}
@@ -88,6 +121,23 @@ pub struct PlaybackInfo {
     pub repeating: Entity<RepeatState>,
     pub volume: Entity<f64>,
     pub prev_volume: Entity<f64>,
+    pub muted: Entity<bool>,
+    pub balance: Entity<f64>,
+    /// The output device being played to, if one is open.
+    pub device: Entity<Option<OutputDevice>>,
+    /// Every output device that can be played to.
+    pub devices: Entity<Vec<OutputDevice>>,
+    /// The UID of the output device picked by the user, or None to use the default device.
+    pub preferred_device: Entity<Option<String>>,
+    /// The volume last used with each output device, by UID, restored when switching to it.
+    pub device_volumes: Entity<BTreeMap<String, f64>>,
+    /// When play was last pressed with nothing in the queue, while the UI is still saying so.
+    pub queue_empty_notice: Entity<Option<Instant>>,
+    /// The audio pipeline, as last described by the playback thread. Only requested when it's
+    /// shown.
+    pub diagnostics: Entity<Option<PlaybackDiagnostics>>,
+    /// The format of the current track and of the output stream.
+    pub format: Entity<PlaybackFormat>,
 }
 
 impl Global for PlaybackInfo {}
@@ -103,6 +153,19 @@ pub struct Queue {
     pub position: usize,
 }
 
+impl Queue {
+    /// The part of a larger file played by the item at the current position, if that item is the
+    /// current track and it's a track from a cue sheet.
+    pub fn current_segment(&self, current_track: &CurrentTrack) -> Option<TrackSegment> {
+        let items = self.data.read().expect("couldn't get queue");
+
+        items
+            .get(self.position)
+            .filter(|item| *current_track == *item.get_path())
+            .and_then(|item| item.get_segment().cloned())
+    }
+}
+
 impl EventEmitter<(PathBuf, QueueItemUIData)> for Queue {}
 
 #[derive(Clone)]
@@ -115,6 +178,12 @@ pub enum MMBSEvent {
     StateChanged(PlaybackState),
     PositionChanged(u64),
     DurationChanged(u64),
+    FavoriteChanged {
+        artist: String,
+        track: String,
+        favorite: bool,
+    },
+    ScrobbleThresholdChanged(ScrobbleThreshold),
 }
 
 impl EventEmitter<MMBSEvent> for MMBSList {}
@@ -127,6 +196,46 @@ pub enum PlaylistEvent {
 
 impl EventEmitter<PlaylistEvent> for PlaylistInfoTransfer {}
 
+/// Records plays in the library's play history, and lets views know when one has been recorded.
+pub struct PlayHistory;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlayHistoryEvent {
+    /// A play of the track was recorded. The track's album is included so that views can tell
+    /// whether they're affected without looking the track up again.
+    PlayRecorded {
+        track_id: i64,
+        album_id: Option<i64>,
+    },
+}
+
+impl EventEmitter<PlayHistoryEvent> for PlayHistory {}
+
+impl PlayHistory {
+    /// Records a play of the track at `path`. Files that aren't in the library, such as radio
+    /// streams, aren't recorded.
+    pub fn record_play(&mut self, path: &Path, cx: &mut Context<Self>) {
+        let track = match cx.get_track_by_path(path) {
+            Ok(Some(track)) => track,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("Could not look up played track {:?}: {:?}", path, e);
+                return;
+            }
+        };
+
+        if let Err(e) = cx.add_play(track.id) {
+            error!("Could not record play of {:?}: {:?}", path, e);
+            return;
+        }
+
+        cx.emit(PlayHistoryEvent::PlayRecorded {
+            track_id: track.id,
+            album_id: track.album_id,
+        });
+    }
+}
+
 pub fn build_models(
     cx: &mut App,
     queue: Queue,
@@ -141,17 +250,27 @@ pub fn build_models(
     let mmbs: Entity<MMBSList> = cx.new(|_| MMBSList(AHashMap::new()));
     let show_about: Entity<bool> = cx.new(|_| false);
     let show_config: Entity<bool> = cx.new(|_| show_config_initial);
+    let show_settings: Entity<bool> = cx.new(|_| false);
     let lastfm: Entity<LastFMState> = cx.new(|cx| {
-        let dirs = get_dirs();
-        let directory = dirs.data_dir().to_path_buf();
+        let directory = data_dir();
         let path = directory.join("lastfm.json");
 
         if let Ok(file) = File::open(path) {
             let reader = std::io::BufReader::new(file);
 
-            if let Ok(session) = serde_json::from_reader::<std::io::BufReader<File>, Session>(reader) {
-                create_last_fm_mmbs(cx, &mmbs, session.key.clone());
-                LastFMState::Connected(session)
+            if let Ok(mut session) = serde_json::from_reader::<std::io::BufReader<File>, Session>(reader) {
+                // the key is only in the file if there was no credential store to keep it in
+                if session.key.is_empty() {
+                    session.key = load_secret(LASTFM_SESSION_SECRET).unwrap_or_default();
+                }
+
+                if session.key.is_empty() {
+                    warn!("The last.fm session key is missing from the credential store, you will not be logged in to last.fm.");
+                    LastFMState::Disconnected
+                } else {
+                    create_last_fm_mmbs(cx, &mmbs, session.key.clone());
+                    LastFMState::Connected(session)
+                }
             } else {
                 error!("The last.fm session information is stored on disk but the file could not be opened.");
                 warn!("You will not be logged in to last.fm.");
@@ -162,7 +281,68 @@ pub fn build_models(
         }
     });
 
+    let listenbrainz_token: Entity<Option<String>> = cx.new(|_| {
+        let path = data_dir().join("listenbrainz.json");
+        let file = File::open(path).ok()?;
+        let reader = std::io::BufReader::new(file);
+
+        match serde_json::from_reader::<_, ListenBrainzToken>(reader) {
+            Ok(stored) => Some(stored.token),
+            Err(e) => {
+                error!("The ListenBrainz token is stored on disk but could not be read: {e}");
+                None
+            }
+        }
+    });
+
+    update_listenbrainz_mmbs(cx, &mmbs, &listenbrainz_token);
+
+    let mmbs_clone = mmbs.clone();
+    let token_clone = listenbrainz_token.clone();
+
+    cx.observe(&listenbrainz_token, move |_, cx| {
+        update_listenbrainz_mmbs(cx, &mmbs_clone, &token_clone);
+    })
+    .detach();
+
     let playlist_tracker: Entity<PlaylistInfoTransfer> = cx.new(|_| PlaylistInfoTransfer);
+    let play_history: Entity<PlayHistory> = cx.new(|_| PlayHistory);
+    let mini_player: Entity<bool> = cx.new(|_| false);
+    let metadata_lookup: Entity<Option<Entity<MetadataLookup>>> = cx.new(|_| None);
+    let library_diagnostics: Entity<Option<LibraryDiagnostics>> = cx.new(|_| None);
+    let folder_track_counts: Entity<AHashMap<PathBuf, i64>> = cx.new(|_| AHashMap::new());
+
+    let cache_size: Entity<u64> = cx.new(|cx| {
+        let settings = cx.global::<SettingsGlobal>().model.read(cx);
+        enforce_size_limit(settings.cache.max_size_bytes())
+    });
+
+    let settings_model = cx.global::<SettingsGlobal>().model.clone();
+    let cache_size_clone = cache_size.clone();
+    let mmbs_clone = mmbs.clone();
+    let token_clone = listenbrainz_token.clone();
+    let mut threshold = ScrobbleThreshold::from(&settings_model.read(cx).scrobbling);
+
+    cx.observe(&settings_model, move |settings, cx| {
+        let max_size = settings.read(cx).cache.max_size_bytes();
+        let size = enforce_size_limit(max_size);
+        cache_size_clone.update(cx, |m, cx| {
+            *m = size;
+            cx.notify();
+        });
+
+        let new_threshold = ScrobbleThreshold::from(&settings.read(cx).scrobbling);
+
+        if new_threshold != threshold {
+            threshold = new_threshold;
+            mmbs_clone.update(cx, |_, cx| {
+                cx.emit(MMBSEvent::ScrobbleThresholdChanged(threshold));
+            });
+        }
+
+        update_listenbrainz_mmbs(cx, &mmbs_clone, &token_clone);
+    })
+    .detach();
 
     cx.subscribe(&albumart, |e, ev, cx| {
         let img = ev.0.clone();
@@ -180,8 +360,7 @@ pub fn build_models(
             cx.notify();
         });
 
-        let dirs = get_dirs();
-        let directory = dirs.data_dir().to_path_buf();
+        let directory = data_dir();
         let path = directory.join("lastfm.json");
         let file = OpenOptions::new()
             .write(true)
@@ -189,9 +368,28 @@ pub fn build_models(
             .create(true)
             .open(path);
 
+        // the session key grants full access to the user's account, so it's kept in the credential
+        // store, and only written to the file if there isn't one
+        let mut stored = ev.clone();
+        if store_secret(LASTFM_SESSION_SECRET, &ev.key) {
+            stored.key.clear();
+        }
+
         if let Ok(file) = file {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+
+                if let Err(e) = file.set_permissions(std::fs::Permissions::from_mode(0o600)) {
+                    warn!(
+                        "Could not restrict access to the last.fm session file: {:?}",
+                        e
+                    );
+                }
+            }
+
             let writer = std::io::BufWriter::new(file);
-            if serde_json::to_writer_pretty(writer, ev).is_err() {
+            if serde_json::to_writer_pretty(writer, &stored).is_err() {
                 error!("Tried to write lastfm settings but could not write to file!");
                 error!("You will have to sign in again when the application is next started.");
             }
@@ -217,6 +415,14 @@ pub fn build_models(
                     MMBSEvent::StateChanged(state) => borrow.state_changed(state),
                     MMBSEvent::PositionChanged(position) => borrow.position_changed(position),
                     MMBSEvent::DurationChanged(duration) => borrow.duration_changed(duration),
+                    MMBSEvent::FavoriteChanged {
+                        artist,
+                        track,
+                        favorite,
+                    } => borrow.favorite_changed(artist, track, favorite),
+                    MMBSEvent::ScrobbleThresholdChanged(threshold) => {
+                        borrow.scrobble_threshold_changed(threshold)
+                    }
                 }
                 .await;
             })
@@ -238,14 +444,20 @@ pub fn build_models(
         scan_state,
         mmbs,
         lastfm,
+        listenbrainz_token,
         switcher_model,
         show_about,
         show_config,
+        show_settings,
         playlist_tracker,
+        play_history,
+        cache_size,
+        mini_player,
+        metadata_lookup,
+        library_diagnostics,
+        folder_track_counts,
     });
 
-    const DEFAULT_VOLUME: f64 = 1.0;
-
     let position: Entity<u64> = cx.new(|_| 0);
     let duration: Entity<u64> = cx.new(|_| 0);
     let playback_state: Entity<PlaybackState> = cx.new(|_| PlaybackState::Stopped);
@@ -261,8 +473,27 @@ pub fn build_models(
             RepeatState::NotRepeating
         }
     });
-    let volume: Entity<f64> = cx.new(|_| DEFAULT_VOLUME);
-    let prev_volume: Entity<f64> = cx.new(|_| DEFAULT_VOLUME);
+    let initial_volume = cx
+        .global::<SettingsGlobal>()
+        .model
+        .read(cx)
+        .playback
+        .initial_volume();
+    let volume: Entity<f64> = cx.new(|_| initial_volume);
+    let prev_volume: Entity<f64> = cx.new(|_| initial_volume);
+    let muted: Entity<bool> = cx.new(|_| false);
+    let balance: Entity<f64> = cx.new(|cx| {
+        let settings = cx.global::<SettingsGlobal>().model.read(cx);
+        settings.playback.balance
+    });
+    let device: Entity<Option<OutputDevice>> = cx.new(|_| None);
+    let devices: Entity<Vec<OutputDevice>> = cx.new(|_| Vec::new());
+    let preferred_device: Entity<Option<String>> = cx.new(|_| storage_data.output_device.clone());
+    let device_volumes: Entity<BTreeMap<String, f64>> =
+        cx.new(|_| storage_data.device_volumes.clone());
+    let queue_empty_notice: Entity<Option<Instant>> = cx.new(|_| None);
+    let diagnostics: Entity<Option<PlaybackDiagnostics>> = cx.new(|_| None);
+    let format: Entity<PlaybackFormat> = cx.new(|_| PlaybackFormat::default());
 
     cx.set_global(PlaybackInfo {
         position,
@@ -273,6 +504,15 @@ pub fn build_models(
         repeating,
         volume,
         prev_volume,
+        muted,
+        balance,
+        device,
+        devices,
+        preferred_device,
+        device_volumes,
+        queue_empty_notice,
+        diagnostics,
+        format,
     });
 }
 
@@ -280,9 +520,75 @@ pub fn create_last_fm_mmbs(cx: &mut App, mmbs_list: &Entity<MMBSList>, session:
     if let (Some(key), Some(secret)) = (LASTFM_API_KEY, LASTFM_API_SECRET) {
         let mut client = LastFMClient::new(key.to_string(), secret);
         client.set_session(session);
-        let mmbs = LastFM::new(client);
+        let settings = cx.global::<SettingsGlobal>().model.read(cx);
+        let mmbs = LastFM::new(client, (&settings.scrobbling).into());
         mmbs_list.update(cx, |m, _| {
             m.0.insert("lastfm".to_string(), Arc::new(Mutex::new(mmbs)));
         })
     }
 }
+
+/// Starts submitting listens to ListenBrainz if it's enabled and a token has been provided, or
+/// stops if it isn't.
+fn update_listenbrainz_mmbs(
+    cx: &mut App,
+    mmbs_list: &Entity<MMBSList>,
+    token: &Entity<Option<String>>,
+) {
+    let enabled = cx
+        .global::<SettingsGlobal>()
+        .model
+        .read(cx)
+        .scrobbling
+        .listenbrainz_enabled;
+    let token = token.read(cx).clone().filter(|_| enabled);
+    let running = mmbs_list.read(cx).0.contains_key("listenbrainz");
+
+    match token {
+        Some(token) if !running => {
+            let settings = cx.global::<SettingsGlobal>().model.read(cx);
+            let mmbs = ListenBrainz::new(token, (&settings.scrobbling).into());
+            mmbs_list.update(cx, |m, _| {
+                m.0.insert("listenbrainz".to_string(), Arc::new(Mutex::new(mmbs)));
+            })
+        }
+        None if running => mmbs_list.update(cx, |m, _| {
+            m.0.remove("listenbrainz");
+        }),
+        _ => (),
+    }
+}
+
+/// Stores (or, if `token` is None, forgets) the user's ListenBrainz token.
+pub fn set_listenbrainz_token(cx: &mut App, token: Option<String>) {
+    let path = data_dir().join("listenbrainz.json");
+
+    if let Some(token) = token.clone() {
+        let result = File::create(&path).and_then(|file| {
+            // the token grants access to the user's account, so keep it private
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+            }
+
+            let writer = std::io::BufWriter::new(file);
+            serde_json::to_writer_pretty(writer, &ListenBrainzToken { token })
+                .map_err(std::io::Error::other)
+        });
+
+        if let Err(e) = result {
+            error!("Could not save the ListenBrainz token: {e}");
+        }
+    } else {
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                error!("Could not remove the ListenBrainz token: {e}");
+            }
+            _ => (),
+        }
+    }
+
+    let model = cx.global::<Models>().listenbrainz_token.clone();
+    model.write(cx, token);
+}
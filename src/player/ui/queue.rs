@@ -1,4 +1,10 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
 use crate::{
+    player::media::stream::is_stream_url,
     player::playback::{
         interface::GPUIPlaybackInterface,
         queue::{DataSource, QueueItemData},
@@ -7,13 +13,17 @@ use crate::{
         icons::{CROSS, SHUFFLE, TRASH, icon},
         nav_button::nav_button,
     },
+    shared::i18n::t,
 };
 use ahash::AHashMap;
 use gpui::*;
 use prelude::FluentBuilder;
+use smol::Timer;
 
 use super::{
     components::button::{ButtonSize, ButtonStyle, button},
+    controls::format_duration,
+    drag::DraggedTracks,
     models::{Models, PlaybackInfo},
     theme::Theme,
     util::{create_or_retrieve_view, drop_image_from_app, prune_views},
@@ -55,6 +65,13 @@ impl QueueItem {
             })
             .detach();
 
+            let details = item.as_ref().unwrap().get_details(cx);
+
+            cx.observe(&details, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
             Self {
                 item,
                 idx,
@@ -70,6 +87,7 @@ impl Render for QueueItem {
             .item
             .as_ref()
             .and_then(|item| item.get_data(cx).read(cx).clone());
+        let duration = self.item.as_ref().and_then(|item| item.duration(cx));
         let theme = cx.global::<Theme>().clone();
 
         if let Some(item) = data.as_ref() {
@@ -144,6 +162,16 @@ impl Render for QueueItem {
                                 }),
                         ),
                 )
+                .when_some(duration, |this, duration| {
+                    this.child(
+                        div()
+                            .ml_auto()
+                            .flex_shrink_0()
+                            .text_sm()
+                            .text_color(theme.text_secondary)
+                            .child(format_duration(duration)),
+                    )
+                })
         } else {
             // TODO: Skeleton for this
             div()
@@ -156,11 +184,105 @@ impl Render for QueueItem {
     }
 }
 
+/// Shows how long the queue is, and how long is left until it ends.
+pub struct QueueDuration {
+    /// Keeps the view up to date as the details of queued tracks are loaded.
+    details_subscriptions: Vec<Subscription>,
+    position: Entity<u64>,
+}
+
+impl QueueDuration {
+    pub fn new(cx: &mut App) -> Entity<Self> {
+        cx.new(|cx| {
+            let queue = cx.global::<Models>().queue.clone();
+
+            cx.observe(&queue, |this: &mut QueueDuration, _, cx| {
+                this.load_durations(cx);
+                cx.notify();
+            })
+            .detach();
+
+            let position = cx.global::<PlaybackInfo>().position.clone();
+
+            cx.observe(&position, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            let mut this = Self {
+                details_subscriptions: Vec::new(),
+                position,
+            };
+
+            this.load_durations(cx);
+
+            this
+        })
+    }
+
+    /// Makes sure the duration of every queued file is known or being loaded.
+    fn load_durations(&mut self, cx: &mut Context<Self>) {
+        let items: Vec<QueueItemData> = cx
+            .global::<Models>()
+            .queue
+            .read(cx)
+            .data
+            .read()
+            .expect("could not read queue")
+            .clone();
+
+        self.details_subscriptions = items
+            .iter()
+            .filter(|item| !is_stream_url(item.get_path()))
+            .map(|item| {
+                let details = item.get_details(cx);
+                cx.observe(&details, |_, _, cx| cx.notify())
+            })
+            .collect();
+    }
+}
+
+impl Render for QueueDuration {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let queue = cx.global::<Models>().queue.read(cx);
+        let current = queue.position;
+        let items = queue.data.read().expect("could not read queue");
+
+        let duration_of = |item: &QueueItemData| item.duration(cx).unwrap_or_default();
+
+        let total: u64 = items.iter().map(duration_of).sum();
+        let remaining: u64 = items
+            .iter()
+            .skip(current)
+            .map(duration_of)
+            .sum::<u64>()
+            .saturating_sub(*self.position.read(cx));
+
+        div()
+            .text_sm()
+            .text_color(theme.text_secondary)
+            .when(!items.is_empty(), |this| {
+                this.child(format!(
+                    "{} total · {} remaining",
+                    format_duration(total),
+                    format_duration(remaining)
+                ))
+            })
+    }
+}
+
+/// How long clearing the queue can be undone for.
+const CLEAR_UNDO_DURATION: Duration = Duration::from_secs(6);
+
 pub struct Queue {
     views_model: Entity<AHashMap<usize, Entity<QueueItem>>>,
     render_counter: Entity<usize>,
     shuffling: Entity<bool>,
     show_queue: Entity<bool>,
+    duration: Entity<QueueDuration>,
+    /// When the queue was last cleared, while clearing can still be undone.
+    cleared: Option<Instant>,
 }
 
 impl Queue {
@@ -170,10 +292,21 @@ impl Queue {
             let render_counter = cx.new(|_| 0);
             let items = cx.global::<Models>().queue.clone();
 
-            cx.observe(&items, move |this: &mut Queue, _, cx| {
+            cx.observe(&items, move |this: &mut Queue, items, cx| {
                 this.views_model = cx.new(|_| AHashMap::new());
                 this.render_counter = cx.new(|_| 0);
 
+                // once something new is queued, undoing would throw it away
+                let queue_empty = items
+                    .read(cx)
+                    .data
+                    .read()
+                    .expect("could not read queue")
+                    .is_empty();
+                if !queue_empty {
+                    this.cleared = None;
+                }
+
                 cx.notify();
             })
             .detach();
@@ -190,9 +323,57 @@ impl Queue {
                 render_counter,
                 shuffling,
                 show_queue,
+                duration: QueueDuration::new(cx),
+                cleared: None,
             }
         })
     }
+
+    /// Clears the queue, which stops playback. The playback thread keeps what was in it, and where
+    /// the current track was, so that clearing can be undone for a short while.
+    fn clear(&mut self, cx: &mut Context<Self>) {
+        let queue_empty = cx
+            .global::<Models>()
+            .queue
+            .read(cx)
+            .data
+            .read()
+            .expect("could not read queue")
+            .is_empty();
+
+        cx.global::<GPUIPlaybackInterface>().clear_queue();
+
+        if queue_empty {
+            return;
+        }
+
+        let cleared_at = Instant::now();
+        self.cleared = Some(cleared_at);
+        cx.notify();
+
+        cx.spawn(async move |this, cx| {
+            Timer::after(CLEAR_UNDO_DURATION).await;
+
+            this.update(cx, |this, cx| {
+                if this.cleared == Some(cleared_at) {
+                    this.cleared = None;
+                    cx.notify();
+                }
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    /// Puts back the queue that was last cleared, starting from the track that was playing.
+    fn undo_clear(&mut self, cx: &mut Context<Self>) {
+        if self.cleared.take().is_none() {
+            return;
+        }
+
+        cx.global::<GPUIPlaybackInterface>().undo_clear_queue();
+        cx.notify();
+    }
 }
 
 impl Render for Queue {
@@ -209,6 +390,7 @@ impl Render for Queue {
         let shuffling = self.shuffling.read(cx);
         let views_model = self.views_model.clone();
         let render_counter = self.render_counter.clone();
+        let drop_highlight = theme.nav_button_hover;
 
         div()
             // .absolute()
@@ -224,6 +406,11 @@ impl Render for Queue {
             .pb(px(0.0))
             .flex()
             .flex_col()
+            .drag_over::<DraggedTracks>(move |style, _, _, _| style.bg(drop_highlight))
+            .on_drop(|dragged: &DraggedTracks, _, cx| {
+                let items = dragged.queue_items(cx);
+                cx.global::<GPUIPlaybackInterface>().queue_list(items);
+            })
             .child(
                 div().flex().child(
                     div().flex().w_full().child(
@@ -244,13 +431,16 @@ impl Render for Queue {
                     .pb(px(12.0))
                     .px(px(12.0))
                     .flex()
+                    .flex_col()
+                    .gap(px(4.0))
                     .child(
                         div()
                             .line_height(px(26.0))
                             .font_weight(FontWeight::BOLD)
                             .text_size(px(26.0))
-                            .child("Queue"),
-                    ),
+                            .child(t("queue.title")),
+                    )
+                    .child(self.duration.clone()),
             )
             .child(
                 div()
@@ -264,21 +454,18 @@ impl Render for Queue {
                             .style(ButtonStyle::MinimalNoRounding)
                             .size(ButtonSize::Large)
                             .child(icon(TRASH).size(px(14.0)).my_auto())
-                            .child("Clear")
+                            .child(t("queue.clear"))
                             .w_full()
                             .id("clear-queue")
-                            .on_click(|_, _, cx| {
-                                cx.global::<GPUIPlaybackInterface>().clear_queue();
-                                cx.global::<GPUIPlaybackInterface>().stop();
-                            }),
+                            .on_click(cx.listener(|this: &mut Self, _, _, cx| this.clear(cx))),
                     )
                     .child(
                         button()
                             .style(ButtonStyle::MinimalNoRounding)
                             .size(ButtonSize::Large)
                             .child(icon(SHUFFLE).size(px(14.0)).my_auto())
-                            .when(*shuffling, |this| this.child("Shuffling"))
-                            .when(!shuffling, |this| this.child("Shuffle"))
+                            .when(*shuffling, |this| this.child(t("queue.shuffling")))
+                            .when(!shuffling, |this| this.child(t("queue.shuffle")))
                             .w_full()
                             .id("queue-shuffle")
                             .on_click(|_, _, cx| {
@@ -332,5 +519,30 @@ impl Render for Queue {
                 .flex()
                 .flex_col(),
             )
+            .when(self.cleared.is_some(), |this| {
+                this.child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .px(px(12.0))
+                        .py(px(6.0))
+                        .border_t_1()
+                        .border_color(theme.border_color)
+                        .bg(theme.elevated_background)
+                        .text_sm()
+                        .child(t("queue.cleared"))
+                        .child(
+                            button()
+                                .style(ButtonStyle::MinimalNoRounding)
+                                .size(ButtonSize::Regular)
+                                .ml_auto()
+                                .child(t("queue.undo"))
+                                .id("undo-clear-queue")
+                                .on_click(
+                                    cx.listener(|this: &mut Self, _, _, cx| this.undo_clear(cx)),
+                                ),
+                        ),
+                )
+            })
     }
 }
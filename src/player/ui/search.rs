@@ -38,6 +38,10 @@ impl SearchView {
             let input = TextInput::new(cx, handle.clone(), None, None, Some(Box::new(handler)));
 
             App::on_action(cx, move |_: &Search, cx| {
+                // the search view isn't shown in the mini player
+                let mini_player = cx.global::<Models>().mini_player.clone();
+                mini_player.write(cx, false);
+
                 show_clone.update(cx, |m, cx| {
                     *m = true;
                     cx.notify();
@@ -0,0 +1,1137 @@
+use std::{fs, path::PathBuf};
+
+use ahash::AHashMap;
+use gpui::{
+    App, AsyncApp, Context, Div, Entity, FontWeight, InteractiveElement, IntoElement,
+    ParentElement, PathPromptOptions, PromptLevel, Render, RenderOnce, SharedString,
+    StatefulInteractiveElement, Styled, Window, div, prelude::FluentBuilder, px, rgb,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    player::{
+        library::{db::LibraryAccess, scan::ScanInterface},
+        playback::interface::GPUIPlaybackInterface,
+        services::controllers::now_playing::now_playing_path,
+    },
+    shared::settings::{
+        Settings, SettingsGlobal,
+        interface::{
+            AlbumArtSize, CloseAction, FormatLocale, Language, ThemeMode, parse_hex_color,
+        },
+        playback::{
+            AlbumPlayAction, AlbumShuffleAction, CrossfeedIntensity, DuplicateQueueItems,
+            EndOfQueueAction, TrackDoubleClickAction,
+        },
+        transfer::{EXPORT_FILE_NAME, export_settings, import_settings},
+        update_settings,
+    },
+};
+
+use super::{
+    components::{
+        button::{ButtonIntent, ButtonSize, button},
+        input::{EnrichedInputAction, TextInput},
+        modal::{OnExitHandler, modal},
+    },
+    models::{Models, PlaybackInfo, set_listenbrainz_token},
+    theme::Theme,
+};
+
+#[derive(IntoElement)]
+pub struct SettingsDialog {
+    on_exit: &'static OnExitHandler,
+}
+
+impl RenderOnce for SettingsDialog {
+    fn render(self, window: &mut Window, cx: &mut App) -> impl IntoElement {
+        // kept only while the dialog is open, so the field starts out empty each time
+        let accent_input = window.use_keyed_state("settings-accent-color-input", cx, |_, cx| {
+            AccentColorInput::new(cx)
+        });
+        let theme = cx.global::<Theme>();
+        let settings = cx.global::<SettingsGlobal>().model.read(cx).clone();
+
+        modal().on_exit(self.on_exit).child(
+            div()
+                .id("settings-dialog")
+                .flex()
+                .flex_col()
+                .w(px(560.0))
+                .max_h(px(600.0))
+                .overflow_y_scroll()
+                .p(px(24.0))
+                .gap(px(18.0))
+                .child(
+                    div()
+                        .text_size(px(20.0))
+                        .font_weight(FontWeight::BOLD)
+                        .child("Settings"),
+                )
+                .child(
+                    section("Library", theme)
+                        .child(scan_paths(&settings.scanning.paths, theme, cx))
+                        .child(toggle(
+                            "settings-scan-on-startup",
+                            "Scan on startup",
+                            "Look for new and changed files every time MrChat starts.",
+                            settings.scanning.scan_on_startup,
+                            |s| &mut s.scanning.scan_on_startup,
+                            theme,
+                        ))
+                        .child(toggle(
+                            "settings-ignore-articles",
+                            "Ignore leading articles when sorting",
+                            "Sort \"The Beatles\" under B. Applies to the whole library on the \
+                            next scan.",
+                            settings.scanning.ignore_articles,
+                            |s| &mut s.scanning.ignore_articles,
+                            theme,
+                        ))
+                        .child(toggle(
+                            "settings-skip-hidden",
+                            "Skip hidden folders",
+                            "Don't scan folders whose names start with a dot.",
+                            settings.scanning.skip_hidden_dirs,
+                            |s| &mut s.scanning.skip_hidden_dirs,
+                            theme,
+                        ))
+                        .child(toggle(
+                            "settings-follow-symlinks",
+                            "Follow symbolic links",
+                            "Scan folders that are linked to from inside a music folder.",
+                            settings.scanning.follow_symlinks,
+                            |s| &mut s.scanning.follow_symlinks,
+                            theme,
+                        ))
+                        .child(
+                            div().flex().child(
+                                button()
+                                    .size(ButtonSize::Regular)
+                                    .intent(ButtonIntent::Danger)
+                                    .child("Rebuild library…")
+                                    .id("settings-rebuild-library")
+                                    .on_click(|_, window, cx| confirm_rebuild_library(window, cx)),
+                            ),
+                        ),
+                )
+                .child(
+                    section("Playback", theme)
+                        .child(toggle(
+                            "settings-always-repeat",
+                            "Always repeat",
+                            "Never stop at the end of the queue.",
+                            settings.playback.always_repeat,
+                            |s| &mut s.playback.always_repeat,
+                            theme,
+                        ))
+                        .child(toggle(
+                            "settings-prev-jump-first",
+                            "Restart track before going back",
+                            "Previous restarts the current track if it has played for longer than \
+                            the threshold below.",
+                            settings.playback.prev_track_jump_first,
+                            |s| &mut s.playback.prev_track_jump_first,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-prev-restart-threshold",
+                            "Restart after",
+                            &[(2, "2 s"), (5, "5 s"), (10, "10 s")],
+                            settings.playback.prev_restart_threshold_secs,
+                            |s, v| s.playback.prev_restart_threshold_secs = v,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-double-click",
+                            "Double-clicking a track",
+                            &[
+                                (TrackDoubleClickAction::PlayTrack, "Plays it"),
+                                (TrackDoubleClickAction::PlayAlbumFromHere, "Plays from it"),
+                                (TrackDoubleClickAction::AddToQueue, "Queues it"),
+                            ],
+                            settings.playback.double_click_action,
+                            |s, v| s.playback.double_click_action = v,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-album-play-action",
+                            "Playing an album",
+                            &[
+                                (AlbumPlayAction::Replace, "Replaces the queue"),
+                                (AlbumPlayAction::Append, "Adds it to the queue"),
+                                (AlbumPlayAction::Ask, "Asks"),
+                            ],
+                            settings.playback.album_play_action,
+                            |s, v| s.playback.album_play_action = v,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-album-shuffle-action",
+                            "Shuffling an album",
+                            &[
+                                (AlbumShuffleAction::ShuffleTracks, "Shuffles its tracks"),
+                                (AlbumShuffleAction::TurnOnShuffle, "Turns on shuffle"),
+                            ],
+                            settings.playback.album_shuffle_action,
+                            |s, v| s.playback.album_shuffle_action = v,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-duplicate-queue-items",
+                            "Queueing a track that's already queued",
+                            &[
+                                (DuplicateQueueItems::Allow, "Queues it again"),
+                                (DuplicateQueueItems::Skip, "Skips it"),
+                                (DuplicateQueueItems::Move, "Moves it"),
+                            ],
+                            settings.playback.duplicate_queue_items,
+                            |s, v| s.playback.duplicate_queue_items = v,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-end-of-queue",
+                            "At the end of the queue",
+                            &[
+                                (EndOfQueueAction::Stop, "Stop"),
+                                (EndOfQueueAction::Pause, "Pause on the last track"),
+                            ],
+                            settings.playback.end_of_queue_action,
+                            |s, v| s.playback.end_of_queue_action = v,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-sample-rate",
+                            "Output sample rate",
+                            &[
+                                (None, "Default"),
+                                (Some(44100), "44.1 kHz"),
+                                (Some(48000), "48 kHz"),
+                                (Some(96000), "96 kHz"),
+                                (Some(192000), "192 kHz"),
+                            ],
+                            settings.playback.preferred_sample_rate,
+                            |s, v| s.playback.preferred_sample_rate = v,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-bit-depth",
+                            "Output bit depth",
+                            &[
+                                (None, "Default"),
+                                (Some(16), "16-bit"),
+                                (Some(24), "24-bit"),
+                                (Some(32), "32-bit"),
+                            ],
+                            settings.playback.preferred_bit_depth,
+                            |s, v| s.playback.preferred_bit_depth = v,
+                            theme,
+                        ))
+                        .child(toggle(
+                            "settings-exclusive-mode",
+                            "Exclusive mode",
+                            "Play each track at its own sample rate, and take exclusive access to \
+                            the output device on macOS.",
+                            settings.playback.exclusive_mode,
+                            |s| &mut s.playback.exclusive_mode,
+                            theme,
+                        ))
+                        .child(toggle(
+                            "settings-skip-silence",
+                            "Skip silence",
+                            "Skip silence at the start and end of tracks.",
+                            settings.playback.skip_silence,
+                            |s| &mut s.playback.skip_silence,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-silence-threshold",
+                            "Treat as silence below",
+                            &[(-70.0, "-70 dB"), (-60.0, "-60 dB"), (-50.0, "-50 dB")],
+                            settings.playback.silence_threshold_db,
+                            |s, v| s.playback.silence_threshold_db = v,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-silence-max-trim",
+                            "Skip at most",
+                            &[(1000, "1 s"), (3000, "3 s"), (5000, "5 s"), (10000, "10 s")],
+                            settings.playback.silence_max_trim_ms,
+                            |s, v| s.playback.silence_max_trim_ms = v,
+                            theme,
+                        ))
+                        .child(toggle(
+                            "settings-auto-gapless",
+                            "Gapless albums",
+                            "Play tracks that run into each other on an album without a gap.",
+                            settings.playback.auto_gapless_albums,
+                            |s| &mut s.playback.auto_gapless_albums,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-default-volume",
+                            "Volume on startup",
+                            &[(0.25, "25%"), (0.5, "50%"), (0.75, "75%"), (1.0, "100%")],
+                            settings.playback.default_volume,
+                            |s, v| s.playback.default_volume = v,
+                            theme,
+                        ))
+                        .child(balance(theme, cx))
+                        .child(toggle(
+                            "settings-crossfeed",
+                            "Crossfeed",
+                            "Mix part of each channel into the other, for more natural stereo on \
+                            headphones.",
+                            settings.playback.crossfeed,
+                            |s| &mut s.playback.crossfeed,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-crossfeed-intensity",
+                            "Crossfeed intensity",
+                            &[
+                                (CrossfeedIntensity::Low, "Low"),
+                                (CrossfeedIntensity::Medium, "Medium"),
+                                (CrossfeedIntensity::High, "High"),
+                            ],
+                            settings.playback.crossfeed_intensity,
+                            |s, v| s.playback.crossfeed_intensity = v,
+                            theme,
+                        ))
+                        .child(output_device(theme, cx))
+                        .child(toggle(
+                            "settings-follow-default-device",
+                            "Follow the default output device",
+                            "Switch to the new default device when it's changed in your system \
+                            settings.",
+                            settings.playback.follow_default_device,
+                            |s| &mut s.playback.follow_default_device,
+                            theme,
+                        ))
+                        .child(toggle(
+                            "settings-pause-on-disconnect",
+                            "Pause when the output device is disconnected",
+                            "Pause playback when headphones or other devices are unplugged.",
+                            settings.playback.pause_on_output_disconnect,
+                            |s| &mut s.playback.pause_on_output_disconnect,
+                            theme,
+                        )),
+                )
+                .child(
+                    section("Scrobbling", theme)
+                        .child(choice(
+                            "settings-scrobble-percent",
+                            "Scrobble after playing",
+                            &[(25, "25%"), (50, "50%"), (75, "75%"), (90, "90%")],
+                            settings.scrobbling.threshold_percent,
+                            |s, v| s.scrobbling.threshold_percent = v,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-scrobble-seconds",
+                            "Or after playing for",
+                            &[
+                                (60, "1 min"),
+                                (120, "2 min"),
+                                (240, "4 min"),
+                                (480, "8 min"),
+                            ],
+                            settings.scrobbling.threshold_seconds,
+                            |s, v| s.scrobbling.threshold_seconds = v,
+                            theme,
+                        ))
+                        .child(toggle(
+                            "settings-listenbrainz",
+                            "Submit listens to ListenBrainz",
+                            "Listens are also scrobbled to Last.fm if you're signed in.",
+                            settings.scrobbling.listenbrainz_enabled,
+                            |s| &mut s.scrobbling.listenbrainz_enabled,
+                            theme,
+                        ))
+                        .child(listenbrainz_token(theme, cx)),
+                )
+                .child(
+                    section("Remote control", theme)
+                        .child(toggle(
+                            "settings-remote-control",
+                            "Control server",
+                            "Let scripts and other programs on this computer control playback \
+                            over HTTP. Requests must send the token from remote_token in the data \
+                            folder as an X-MrChat-Token header. Takes effect after restarting \
+                            MrChat.",
+                            settings.remote_control.enabled,
+                            |s| &mut s.remote_control.enabled,
+                            theme,
+                        ))
+                        .child(toggle(
+                            "settings-now-playing-file",
+                            "Write now playing file",
+                            format!(
+                                "Keep {} up to date, for stream overlays. Takes effect after \
+                                restarting MrChat.",
+                                now_playing_path(&settings.remote_control).display()
+                            ),
+                            settings.remote_control.write_now_playing_file,
+                            |s| &mut s.remote_control.write_now_playing_file,
+                            theme,
+                        )),
+                )
+                .child(
+                    section("Interface", theme)
+                        .child(choice(
+                            "settings-theme",
+                            "Theme",
+                            &[
+                                (ThemeMode::Dark, "Dark"),
+                                (ThemeMode::Light, "Light"),
+                                (ThemeMode::System, "Match system"),
+                            ],
+                            settings.interface.theme,
+                            |s, v| s.interface.theme = v,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-album-art-size",
+                            "Album art size",
+                            &[
+                                (AlbumArtSize::Small, "Small"),
+                                (AlbumArtSize::Medium, "Medium"),
+                                (AlbumArtSize::Large, "Large"),
+                            ],
+                            settings.interface.album_art_size,
+                            |s, v| s.interface.album_art_size = v,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-locale",
+                            "Dates and numbers",
+                            &[
+                                (FormatLocale::Language, "Same as language"),
+                                (FormatLocale::System, "System"),
+                                (FormatLocale::English, "English"),
+                                (FormatLocale::Chinese, "中文"),
+                                (FormatLocale::German, "Deutsch"),
+                            ],
+                            settings.interface.locale,
+                            |s, v| s.interface.locale = v,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-language",
+                            "Language",
+                            &[
+                                (Language::System, "System"),
+                                (Language::English, "English"),
+                                (Language::Chinese, "中文"),
+                            ],
+                            settings.interface.language,
+                            |s, v| s.interface.language = v,
+                            theme,
+                        ))
+                        .child(accent_color(
+                            settings.interface.accent(),
+                            accent_input,
+                            theme,
+                        ))
+                        .child(toggle(
+                            "settings-notify-on-track-change",
+                            "Track notifications",
+                            "Show a notification when a new track starts while MrChat is in the \
+                            background.",
+                            settings.interface.notify_on_track_change,
+                            |s| &mut s.interface.notify_on_track_change,
+                            theme,
+                        ))
+                        .child(choice(
+                            "settings-close-action",
+                            "Closing the window",
+                            &[
+                                (CloseAction::Quit, "Quits"),
+                                (CloseAction::KeepPlaying, "Keeps playing"),
+                            ],
+                            settings.interface.close_action,
+                            |s, v| s.interface.close_action = v,
+                            theme,
+                        ))
+                        .child(toggle(
+                            "settings-always-on-top",
+                            "Always on top",
+                            "Keep the window above other windows.",
+                            settings.interface.always_on_top,
+                            |s| &mut s.interface.always_on_top,
+                            theme,
+                        )),
+                ),
+        )
+    }
+}
+
+fn section(title: &'static str, theme: &Theme) -> Div {
+    div().flex().flex_col().gap(px(10.0)).child(
+        div()
+            .text_sm()
+            .font_weight(FontWeight::SEMIBOLD)
+            .text_color(theme.text_secondary)
+            .pb(px(4.0))
+            .border_b_1()
+            .border_color(theme.border_color)
+            .child(title),
+    )
+}
+
+fn label(title: &'static str, description: Option<SharedString>, theme: &Theme) -> Div {
+    div()
+        .flex()
+        .flex_col()
+        .flex_grow()
+        .gap(px(2.0))
+        .child(div().text_sm().child(title))
+        .when_some(description, |this, description| {
+            this.child(
+                div()
+                    .text_xs()
+                    .text_color(theme.text_secondary)
+                    .child(description),
+            )
+        })
+}
+
+fn toggle(
+    id: &'static str,
+    title: &'static str,
+    description: impl Into<SharedString>,
+    value: bool,
+    field: fn(&mut Settings) -> &mut bool,
+    theme: &Theme,
+) -> impl IntoElement {
+    div()
+        .flex()
+        .items_center()
+        .gap(px(12.0))
+        .child(label(title, Some(description.into()), theme))
+        .child(
+            button()
+                .size(ButtonSize::Regular)
+                .intent(if value {
+                    ButtonIntent::Primary
+                } else {
+                    ButtonIntent::Secondary
+                })
+                .child(if value { "On" } else { "Off" })
+                .id(id)
+                .on_click(move |_, _, cx| {
+                    update_settings(cx, |settings| {
+                        let value = field(settings);
+                        *value = !*value;
+                    });
+                }),
+        )
+}
+
+fn choice<T>(
+    id: &'static str,
+    title: &'static str,
+    options: &[(T, &'static str)],
+    current: T,
+    set: fn(&mut Settings, T),
+    theme: &Theme,
+) -> impl IntoElement
+where
+    T: Copy + PartialEq + 'static,
+{
+    div()
+        .flex()
+        .items_center()
+        .gap(px(12.0))
+        .child(label(title, None, theme))
+        .child(
+            div()
+                .flex()
+                .gap(px(6.0))
+                .children(options.iter().enumerate().map(|(idx, (value, name))| {
+                    let value = *value;
+
+                    button()
+                        .size(ButtonSize::Regular)
+                        .intent(if value == current {
+                            ButtonIntent::Primary
+                        } else {
+                            ButtonIntent::Secondary
+                        })
+                        .child(*name)
+                        .id((id, idx))
+                        .on_click(move |_, _, cx| {
+                            update_settings(cx, |settings| set(settings, value));
+                        })
+                })),
+        )
+}
+
+/// Accent colors that can be picked with a click. Any other color can be typed in.
+const ACCENT_COLORS: &[u32] = &[0x0667B2, 0x7C3AED, 0xDB2777, 0xDC2626, 0xEA580C, 0x16A34A];
+
+fn accent_color(
+    current: Option<u32>,
+    input: Entity<AccentColorInput>,
+    theme: &Theme,
+) -> impl IntoElement {
+    div()
+        .flex()
+        .items_center()
+        .gap(px(12.0))
+        .child(label(
+            "Accent color",
+            Some("Used for primary buttons, toggled controls and selections.".into()),
+            theme,
+        ))
+        .child(
+            div()
+                .flex()
+                .items_center()
+                .gap(px(6.0))
+                .children(ACCENT_COLORS.iter().enumerate().map(|(idx, color)| {
+                    let color = *color;
+
+                    div()
+                        .id(("settings-accent-color", idx))
+                        .size(px(22.0))
+                        .rounded(px(11.0))
+                        .border_2()
+                        .border_color(if current == Some(color) {
+                            theme.text
+                        } else {
+                            theme.border_color
+                        })
+                        .bg(rgb(color))
+                        .cursor_pointer()
+                        .on_click(move |_, _, cx| set_accent_color(cx, Some(color)))
+                }))
+                .child(
+                    button()
+                        .size(ButtonSize::Regular)
+                        .intent(if current.is_none() {
+                            ButtonIntent::Primary
+                        } else {
+                            ButtonIntent::Secondary
+                        })
+                        .child("Theme")
+                        .id("settings-accent-color-theme")
+                        .on_click(|_, _, cx| set_accent_color(cx, None)),
+                ),
+        )
+        .child(input)
+}
+
+/// A field for entering an accent color that isn't one of the presets, as `#RRGGBB`. The color is
+/// set when Enter is pressed, or an error is shown under the field if it isn't a valid color.
+struct AccentColorInput {
+    input: Entity<TextInput>,
+    text: String,
+    invalid: bool,
+}
+
+impl AccentColorInput {
+    fn new(cx: &mut Context<Self>) -> Self {
+        let this = cx.weak_entity();
+        let focus = cx.focus_handle();
+        let input = TextInput::new(
+            cx,
+            focus,
+            None,
+            Some(SharedString::from("#RRGGBB")),
+            Some(Box::new(move |action, _, cx| {
+                if let EnrichedInputAction::Accept = action {
+                    this.update(cx, |this, cx| this.apply(cx)).ok();
+                }
+            })),
+        );
+
+        cx.subscribe(&input, |this: &mut Self, _, text: &String, cx| {
+            this.text = text.clone();
+            // the error is for the color that was entered, not the one being typed
+            if this.invalid {
+                this.invalid = false;
+                cx.notify();
+            }
+        })
+        .detach();
+
+        AccentColorInput {
+            input,
+            text: String::new(),
+            invalid: false,
+        }
+    }
+
+    fn apply(&mut self, cx: &mut Context<Self>) {
+        match parse_hex_color(&self.text) {
+            Some(color) => {
+                set_accent_color(cx, Some(color));
+                self.input.update(cx, |input, _| input.reset());
+                self.text.clear();
+            }
+            None => self.invalid = !self.text.trim().is_empty(),
+        }
+
+        cx.notify();
+    }
+}
+
+impl Render for AccentColorInput {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+
+        div()
+            .flex()
+            .flex_col()
+            .gap(px(2.0))
+            .child(
+                div()
+                    .w(px(84.0))
+                    .px(px(8.0))
+                    .py(px(5.0))
+                    .text_sm()
+                    .line_height(px(14.0))
+                    .rounded(px(4.0))
+                    .border_1()
+                    .border_color(if self.invalid {
+                        theme.button_danger
+                    } else {
+                        theme.border_color
+                    })
+                    .overflow_hidden()
+                    .child(self.input.clone()),
+            )
+            .when(self.invalid, |this| {
+                this.child(
+                    div()
+                        .text_xs()
+                        .text_color(theme.button_danger)
+                        .child("Not a #RRGGBB color"),
+                )
+            })
+    }
+}
+
+/// Saves the accent color. The theme is reapplied by the settings observer in `setup_theme`.
+fn set_accent_color(cx: &mut App, color: Option<u32>) {
+    update_settings(cx, |settings| {
+        settings.interface.accent_color = color.map(|color| format!("#{color:06X}"));
+    });
+}
+
+/// The stereo balance is applied live, rather than the next time MrChat is started, so it's sent to
+/// the playback thread as well as being saved.
+fn balance(theme: &Theme, cx: &App) -> impl IntoElement {
+    let current = *cx.global::<PlaybackInfo>().balance.read(cx);
+
+    div()
+        .flex()
+        .items_center()
+        .gap(px(12.0))
+        .child(label("Balance", None, theme))
+        .child(
+            div().flex().gap(px(6.0)).children(
+                [
+                    (-1.0, "L"),
+                    (-0.5, "L 50%"),
+                    (0.0, "Center"),
+                    (0.5, "R 50%"),
+                    (1.0, "R"),
+                ]
+                .into_iter()
+                .enumerate()
+                .map(|(idx, (value, name))| {
+                    button()
+                        .size(ButtonSize::Regular)
+                        .intent(if value == current {
+                            ButtonIntent::Primary
+                        } else {
+                            ButtonIntent::Secondary
+                        })
+                        .child(name)
+                        .id(("settings-balance", idx))
+                        .on_click(move |_, _, cx| {
+                            cx.global::<GPUIPlaybackInterface>().set_balance(value);
+                            update_settings(cx, |settings| settings.playback.balance = value);
+                        })
+                }),
+            ),
+        )
+}
+
+fn output_device(theme: &Theme, cx: &App) -> impl IntoElement {
+    let device = cx.global::<PlaybackInfo>().device.read(cx).clone();
+
+    div()
+        .flex()
+        .items_center()
+        .gap(px(12.0))
+        .child(label("Output device", None, theme))
+        .child(
+            div()
+                .text_sm()
+                .text_color(theme.text_secondary)
+                .child(device.map_or_else(|| "None".to_string(), |device| device.name)),
+        )
+}
+
+fn listenbrainz_token(theme: &Theme, cx: &App) -> impl IntoElement {
+    let has_token = cx.global::<Models>().listenbrainz_token.read(cx).is_some();
+
+    div()
+        .flex()
+        .items_center()
+        .gap(px(12.0))
+        .child(label(
+            "ListenBrainz user token",
+            Some(
+                if has_token {
+                    "Your token has been saved."
+                } else {
+                    "Copy your token from listenbrainz.org/settings, then paste it here."
+                }
+                .into(),
+            ),
+            theme,
+        ))
+        .child(if has_token {
+            button()
+                .size(ButtonSize::Regular)
+                .intent(ButtonIntent::Secondary)
+                .child("Forget")
+                .id("settings-listenbrainz-token")
+                .on_click(|_, _, cx| set_listenbrainz_token(cx, None))
+        } else {
+            button()
+                .size(ButtonSize::Regular)
+                .intent(ButtonIntent::Secondary)
+                .child("Paste")
+                .id("settings-listenbrainz-token")
+                .on_click(|_, _, cx| {
+                    let token = cx
+                        .read_from_clipboard()
+                        .and_then(|item| item.text())
+                        .map(|text| text.trim().to_string())
+                        .filter(|text| !text.is_empty());
+
+                    if token.is_some() {
+                        set_listenbrainz_token(cx, token);
+                    } else {
+                        warn!("Not saving the ListenBrainz token: the clipboard is empty");
+                    }
+                })
+        })
+}
+
+fn scan_paths(paths: &[PathBuf], theme: &Theme, cx: &App) -> impl IntoElement {
+    let counts = cx.global::<Models>().folder_track_counts.read(cx);
+
+    div()
+        .flex()
+        .flex_col()
+        .gap(px(6.0))
+        .child(label(
+            "Music folders",
+            Some("New folders are scanned as soon as they're added.".into()),
+            theme,
+        ))
+        .children(paths.iter().enumerate().map(|(idx, path)| {
+            let count = counts.get(path).copied();
+            let path = path.clone();
+
+            div()
+                .flex()
+                .items_center()
+                .gap(px(8.0))
+                .px(px(10.0))
+                .py(px(6.0))
+                .rounded(px(4.0))
+                .bg(theme.background_tertiary)
+                .child(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .flex_grow()
+                        .overflow_hidden()
+                        .child(
+                            div()
+                                .text_sm()
+                                .overflow_hidden()
+                                .text_ellipsis()
+                                .child(SharedString::from(path.display().to_string())),
+                        )
+                        .child(
+                            div()
+                                .text_xs()
+                                .text_color(theme.text_secondary)
+                                .when(!path.is_dir(), |this| this.child("Folder not found"))
+                                .when_some(count.filter(|_| path.is_dir()), |this, count| {
+                                    this.child(track_count(count))
+                                }),
+                        ),
+                )
+                .child(
+                    button()
+                        .size(ButtonSize::Regular)
+                        .intent(ButtonIntent::Danger)
+                        .child("Remove")
+                        .id(("settings-remove-path", idx))
+                        .on_click(move |_, window, cx| {
+                            confirm_remove_scan_path(path.clone(), window, cx);
+                        }),
+                )
+        }))
+        .child(
+            div().flex().child(
+                button()
+                    .size(ButtonSize::Regular)
+                    .intent(ButtonIntent::Secondary)
+                    .child("Add folder…")
+                    .id("settings-add-path")
+                    .on_click(|_, _, cx| prompt_for_scan_paths(cx)),
+            ),
+        )
+}
+
+fn prompt_for_scan_paths(cx: &mut App) {
+    let paths = cx.prompt_for_paths(PathPromptOptions {
+        files: false,
+        directories: true,
+        multiple: true,
+        prompt: Some("Add".into()),
+    });
+
+    cx.spawn(async move |cx: &mut AsyncApp| {
+        let Ok(Ok(Some(paths))) = paths.await else {
+            return;
+        };
+
+        cx.update(|cx| add_scan_paths(cx, paths)).ok();
+    })
+    .detach();
+}
+
+/// Adds folders to the scan paths and scans them, skipping any that aren't folders or are
+/// already present.
+pub fn add_scan_paths(cx: &mut App, paths: Vec<PathBuf>) {
+    let existing = &cx.global::<SettingsGlobal>().model.read(cx).scanning.paths;
+    let mut added = Vec::new();
+
+    for path in paths {
+        if !path.is_dir() {
+            warn!("Not adding {:?} to the scan paths: not a folder", path);
+        } else if !existing.contains(&path) && !added.contains(&path) {
+            added.push(path);
+        }
+    }
+
+    if added.is_empty() {
+        return;
+    }
+
+    update_settings(cx, |settings| {
+        settings.scanning.paths.extend(added.iter().cloned());
+    });
+
+    cx.global::<ScanInterface>().add_paths(added);
+}
+
+/// Asks whether the tracks in a folder should be kept before removing it from the scan paths.
+fn confirm_remove_scan_path(path: PathBuf, window: &mut Window, cx: &mut App) {
+    // tracks are stored under their canonical path
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+    let count = cx.count_tracks_in_folder(&canonical).unwrap_or_default();
+
+    let message = format!("Remove {} from your library?", path.display());
+    let detail = format!(
+        "The folder contains {} in your library. They can be removed now, or kept until their \
+        files are deleted.",
+        track_count(count)
+    );
+    let answer = window.prompt(
+        PromptLevel::Warning,
+        &message,
+        Some(&detail),
+        &["Remove Tracks", "Keep Tracks", "Cancel"],
+        cx,
+    );
+
+    cx.spawn(async move |cx: &mut AsyncApp| {
+        let purge = match answer.await {
+            Ok(0) => true,
+            Ok(1) => false,
+            _ => return,
+        };
+
+        cx.update(|cx| {
+            update_settings(cx, |settings| {
+                settings.scanning.paths.retain(|v| *v != path);
+            });
+
+            cx.global::<ScanInterface>().remove_path(path, purge);
+        })
+        .ok();
+    })
+    .detach();
+}
+
+/// Asks whether to empty the library and scan every folder again, for when the library database
+/// has been damaged.
+pub fn confirm_rebuild_library(window: &mut Window, cx: &mut App) {
+    let answer = window.prompt(
+        PromptLevel::Warning,
+        "Rebuild your library?",
+        Some(
+            "Every track, album and artist is removed, then your folders are scanned again. \
+            Playlists are emptied and your listening history is deleted. Playlist names, radio \
+            stations and settings are kept.",
+        ),
+        &["Rebuild", "Cancel"],
+        cx,
+    );
+
+    cx.spawn(async move |cx: &mut AsyncApp| {
+        if let Ok(0) = answer.await {
+            cx.update(|cx| cx.global::<ScanInterface>().rebuild()).ok();
+        }
+    })
+    .detach();
+}
+
+/// Asks where to save the settings, and exports them there.
+pub fn prompt_to_export_settings(cx: &mut App) {
+    let directory = directories::UserDirs::new()
+        .map(|dirs| dirs.home_dir().to_path_buf())
+        .unwrap_or_default();
+    let path = cx.prompt_for_new_path(&directory, Some(EXPORT_FILE_NAME));
+
+    cx.spawn(async move |cx: &mut AsyncApp| {
+        let Ok(Ok(Some(path))) = path.await else {
+            return;
+        };
+
+        cx.update(|cx| {
+            let settings = cx.global::<SettingsGlobal>().model.read(cx);
+            let result = export_settings(settings)
+                .and_then(|text| fs::write(&path, text).map_err(Into::into));
+
+            match result {
+                Ok(()) => info!("Exported settings to {:?}", path),
+                Err(e) => {
+                    error!("Failed to export settings: {:?}", e);
+                    show_transfer_error(cx, "Couldn't export settings", &e);
+                }
+            }
+        })
+        .ok();
+    })
+    .detach();
+}
+
+/// Asks for an exported settings file, and applies the settings in it.
+pub fn prompt_to_import_settings(cx: &mut App) {
+    let paths = cx.prompt_for_paths(PathPromptOptions {
+        files: true,
+        directories: false,
+        multiple: false,
+        prompt: Some("Import".into()),
+    });
+
+    cx.spawn(async move |cx: &mut AsyncApp| {
+        let Ok(Ok(Some(paths))) = paths.await else {
+            return;
+        };
+        let Some(path) = paths.into_iter().next() else {
+            return;
+        };
+
+        cx.update(|cx| {
+            let current = cx.global::<SettingsGlobal>().model.read(cx).clone();
+            let result = fs::read_to_string(&path)
+                .map_err(Into::into)
+                .and_then(|text| import_settings(&current, &text));
+
+            let imported = match result {
+                Ok(imported) => imported,
+                Err(e) => {
+                    error!("Failed to import settings from {:?}: {:?}", path, e);
+                    show_transfer_error(cx, "Couldn't import settings", &e);
+                    return;
+                }
+            };
+
+            let added: Vec<PathBuf> = imported
+                .scanning
+                .paths
+                .iter()
+                .filter(|path| !current.scanning.paths.contains(path))
+                .cloned()
+                .collect();
+
+            // observers of the settings (such as the theme) pick up the changes right away
+            update_settings(cx, |settings| *settings = imported);
+            info!("Imported settings from {:?}", path);
+
+            if !added.is_empty() {
+                cx.global::<ScanInterface>().add_paths(added);
+            }
+        })
+        .ok();
+    })
+    .detach();
+}
+
+fn show_transfer_error(cx: &mut App, message: &str, error: &anyhow::Error) {
+    let Some(window) = cx.active_window() else {
+        return;
+    };
+    let detail = format!("{error:#}");
+
+    window
+        .update(cx, |_, window, cx| {
+            // nothing needs to happen once the prompt is dismissed
+            drop(window.prompt(PromptLevel::Critical, message, Some(&detail), &["OK"], cx));
+        })
+        .ok();
+}
+
+fn track_count(count: i64) -> String {
+    match count {
+        1 => "1 track".to_string(),
+        count => format!("{count} tracks"),
+    }
+}
+
+/// Counts the library tracks in each music folder for the settings dialog, which shows them
+/// without querying the library on every render.
+pub fn refresh_folder_track_counts(cx: &mut App) {
+    let paths = cx
+        .global::<SettingsGlobal>()
+        .model
+        .read(cx)
+        .scanning
+        .paths
+        .clone();
+
+    let counts: AHashMap<PathBuf, i64> = paths
+        .into_iter()
+        .map(|path| {
+            // tracks are stored under their canonical path
+            let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+            let count = cx
+                .count_tracks_in_folder(&canonical)
+                .inspect_err(|e| warn!("Could not count tracks in {:?}: {:?}", path, e))
+                .unwrap_or_default();
+
+            (path, count)
+        })
+        .collect();
+
+    let folder_track_counts = cx.global::<Models>().folder_track_counts.clone();
+    folder_track_counts.write(cx, counts);
+}
+
+pub fn settings_dialog(on_exit: &'static OnExitHandler) -> SettingsDialog {
+    SettingsDialog { on_exit }
+}
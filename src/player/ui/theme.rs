@@ -1,10 +1,12 @@
 use std::{fs::File, io::BufReader, path::PathBuf, sync::mpsc::channel, time::Duration};
 
-use gpui::{App, AppContext, AsyncApp, EventEmitter, Global, Rgba, rgb, rgba};
+use gpui::{App, AppContext, AsyncApp, EventEmitter, Global, Rgba, WindowAppearance, rgb, rgba};
 use notify::{Event, RecursiveMode, Watcher};
 use serde::Deserialize;
 use tracing::{error, info, warn};
 
+use crate::shared::settings::{SettingsGlobal, interface::ThemeMode};
+
 #[derive(Deserialize, Clone)]
 #[serde(default)]
 pub struct Theme {
@@ -13,6 +15,7 @@ pub struct Theme {
     pub background_tertiary: Rgba,
 
     pub border_color: Rgba,
+    pub window_border: Rgba,
 
     pub album_art_background: Rgba,
 
@@ -42,6 +45,8 @@ pub struct Theme {
     pub queue_item_active: Rgba,
     pub queue_item_current: Rgba,
 
+    pub track_item_selected: Rgba,
+
     pub button_primary: Rgba,
     pub button_primary_hover: Rgba,
     pub button_primary_active: Rgba,
@@ -79,6 +84,12 @@ pub struct Theme {
 
     pub palette_item_hover: Rgba,
     pub palette_item_active: Rgba,
+
+    pub chat_user_message: Rgba,
+    pub chat_user_message_text: Rgba,
+    pub chat_assistant_message: Rgba,
+    pub chat_system_message: Rgba,
+    pub chat_system_message_border: Rgba,
 }
 
 impl Default for Theme {
@@ -89,6 +100,7 @@ impl Default for Theme {
             background_tertiary: rgb(0x222831),
 
             border_color: rgb(0x272D37),
+            window_border: rgba(0x64748B33),
 
             album_art_background: rgb(0x4C5974),
 
@@ -114,6 +126,8 @@ impl Default for Theme {
             queue_item_active: rgb(0x0C1116),
             queue_item_current: rgb(0x272D37),
 
+            track_item_selected: rgba(0x0673C640),
+
             close_button: rgba(0x282F3D00),
             close_button_hover: rgb(0xAE0909),
             close_button_active: rgb(0x7A0606),
@@ -155,13 +169,156 @@ impl Default for Theme {
 
             palette_item_hover: rgb(0x282F3D),
             palette_item_active: rgb(0x0D1014),
+
+            chat_user_message: rgb(0x0A4F86),
+            chat_user_message_text: rgb(0xE0F1FE),
+            chat_assistant_message: rgb(0x161A22),
+            chat_system_message: rgba(0xEDB40714),
+            chat_system_message_border: rgba(0xEDB40766),
+        }
+    }
+}
+
+impl Theme {
+    /// The built-in light theme.
+    pub fn light() -> Self {
+        Self {
+            background_primary: rgb(0xFFFFFF),
+            background_secondary: rgb(0xF3F4F6),
+            background_tertiary: rgb(0xE5E7EB),
+
+            border_color: rgb(0xD9DDE3),
+            window_border: rgba(0x0F172A26),
+
+            album_art_background: rgb(0xB8C2D4),
+
+            text: rgb(0x111827),
+            text_secondary: rgb(0x4B5563),
+            text_link: rgb(0x2F5BC4),
+
+            nav_button_hover: rgb(0xE9ECF0),
+            nav_button_active: rgb(0xDCE0E6),
+
+            playback_button: rgba(0xE5E7EB00),
+            playback_button_hover: rgb(0xE5E7EB),
+            playback_button_active: rgb(0xD1D5DB),
+            playback_button_border: rgba(0xD1D5DB00),
+            playback_button_toggled: rgb(0x0667B2),
+
+            window_button: rgba(0xE5E7EB00),
+            window_button_hover: rgb(0xE5E7EB),
+            window_button_active: rgb(0xD1D5DB),
+
+            queue_item: rgba(0xF3F4F600),
+            queue_item_hover: rgb(0xF3F4F6),
+            queue_item_active: rgb(0xE5E7EB),
+            queue_item_current: rgb(0xE2E6EC),
+
+            track_item_selected: rgba(0x2F5BC426),
+
+            close_button: rgba(0xE5E7EB00),
+            close_button_hover: rgb(0xE81123),
+            close_button_active: rgb(0xBF0F1D),
+
+            button_primary: rgb(0x0667B2),
+            button_primary_hover: rgb(0x087AD1),
+            button_primary_active: rgb(0x065D9F),
+            button_primary_text: rgb(0xFFFFFF),
+
+            button_secondary: rgb(0xE5E7EB),
+            button_secondary_hover: rgb(0xD9DCE1),
+            button_secondary_active: rgb(0xC9CDD4),
+            button_secondary_text: rgb(0x374151),
+
+            button_warning: rgb(0xEDB407),
+            button_warning_hover: rgb(0xF8C017),
+            button_warning_active: rgb(0xD6A207),
+            button_warning_text: rgb(0x3D2E02),
+
+            button_danger: rgb(0xCD0B0B),
+            button_danger_hover: rgb(0xE80C0C),
+            button_danger_active: rgb(0xB70A0A),
+            button_danger_text: rgb(0xFFFFFF),
+
+            slider_foreground: rgb(0x0673C6),
+            slider_background: rgb(0xD1D5DB),
+
+            elevated_background: rgb(0xFFFFFF),
+            elevated_border_color: rgb(0xD9DDE3),
+
+            menu_item: rgba(0xE5E7EB00),
+            menu_item_hover: rgb(0xE5E7EB),
+            menu_item_active: rgb(0xD1D5DB),
+
+            modal_overlay_bg: rgba(0x0F172A33),
+
+            text_input_selection: rgba(0x0673C644),
+            caret_color: rgb(0x111827),
+
+            palette_item_hover: rgb(0xE5E7EB),
+            palette_item_active: rgb(0xD1D5DB),
+
+            chat_user_message: rgb(0x0667B2),
+            chat_user_message_text: rgb(0xFFFFFF),
+            chat_assistant_message: rgb(0xF3F4F6),
+            chat_system_message: rgba(0xEDB4071F),
+            chat_system_message_border: rgba(0xD6A20799),
+        }
+    }
+
+    /// Replaces the colors derived from the accent color, such as primary buttons, toggled
+    /// controls and selections, with ones derived from `accent`, given as `0xRRGGBB`.
+    pub fn with_accent(self, accent: u32) -> Self {
+        let message = shade(accent, 0.8);
+
+        Self {
+            playback_button_toggled: rgb(accent),
+            track_item_selected: rgba((accent << 8) | 0x40),
+            button_primary: rgb(accent),
+            button_primary_hover: rgb(shade(accent, 1.15)),
+            button_primary_active: rgb(shade(accent, 0.9)),
+            button_primary_text: rgb(text_on(accent)),
+            slider_foreground: rgb(accent),
+            text_input_selection: rgba((accent << 8) | 0x88),
+            chat_user_message: rgb(message),
+            chat_user_message_text: rgb(text_on(message)),
+            ..self
         }
     }
 }
 
 impl Global for Theme {}
 
-pub fn create_theme(path: &PathBuf) -> Theme {
+/// Scales each channel of `color` by `factor`, making it lighter above 1 and darker below.
+fn shade(color: u32, factor: f32) -> u32 {
+    [16, 8, 0].into_iter().fold(0, |result, offset| {
+        let channel = ((color >> offset) & 0xFF) as f32;
+        result | (((channel * factor).round().min(255.0) as u32) << offset)
+    })
+}
+
+/// Text that can be read on `background`: near-black on light colors, near-white on dark ones.
+fn text_on(background: u32) -> u32 {
+    let [r, g, b] = [16, 8, 0].map(|offset| ((background >> offset) & 0xFF) as f32);
+
+    if 0.299 * r + 0.587 * g + 0.114 * b > 160.0 {
+        0x0C1116
+    } else {
+        0xF4F5F6
+    }
+}
+
+/// Loads the theme from `theme.json` if it exists, otherwise returns the built-in theme matching
+/// the appearance.
+pub fn create_theme(path: &PathBuf, light: bool) -> Theme {
+    let builtin = || {
+        if light {
+            Theme::light()
+        } else {
+            Theme::default()
+        }
+    };
+
     if let Ok(file) = File::open(path) {
         let reader = BufReader::new(file);
 
@@ -169,30 +326,73 @@ pub fn create_theme(path: &PathBuf) -> Theme {
             theme
         } else {
             warn!("Theme file exists but it could not be loaded, using default");
-            Theme::default()
+            builtin()
         }
     } else {
-        Theme::default()
+        builtin()
     }
 }
 
+/// The path to `theme.json`.
+pub struct ThemePath(pub PathBuf);
+
+impl Global for ThemePath {}
+
+/// Reloads the theme, picking the built-in theme from the settings and the system appearance, and
+/// applying the accent color from the settings over it.
+pub fn apply_theme(cx: &mut App) {
+    let path = cx.global::<ThemePath>().0.clone();
+    let interface = &cx.global::<SettingsGlobal>().model.read(cx).interface;
+    let mode = interface.theme;
+    let accent = interface.accent();
+
+    let light = match mode {
+        ThemeMode::Dark => false,
+        ThemeMode::Light => true,
+        ThemeMode::System => matches!(
+            cx.window_appearance(),
+            WindowAppearance::Light | WindowAppearance::VibrantLight
+        ),
+    };
+
+    let theme = create_theme(&path, light);
+    cx.set_global(match accent {
+        Some(accent) => theme.with_accent(accent),
+        None => theme,
+    });
+    cx.refresh_windows();
+}
+
 #[derive(PartialEq, Clone)]
 pub struct ThemeEvTransmitter;
 
-impl EventEmitter<Theme> for ThemeEvTransmitter {}
+/// Sent when `theme.json` is created, changed or removed.
+pub struct ThemeFileChanged;
+
+impl EventEmitter<ThemeFileChanged> for ThemeEvTransmitter {}
 
 #[allow(dead_code)]
 pub struct ThemeWatcher(pub Box<dyn Watcher>);
 
 impl Global for ThemeWatcher {}
 
+/// Sets up the theme. The settings must be set up first, as they determine which built-in theme
+/// is used.
 pub fn setup_theme(cx: &mut App, path: PathBuf) {
-    cx.set_global(create_theme(&path));
+    cx.set_global(ThemePath(path.clone()));
+    apply_theme(cx);
+
     let theme_transmitter = cx.new(|_| ThemeEvTransmitter);
 
-    cx.subscribe(&theme_transmitter, |_, theme, cx| {
-        cx.set_global(theme.clone());
-        cx.refresh_windows();
+    cx.subscribe(&theme_transmitter, |_, _: &ThemeFileChanged, cx| {
+        apply_theme(cx);
+    })
+    .detach();
+
+    let settings = cx.global::<SettingsGlobal>().model.clone();
+
+    cx.observe(&settings, |_, cx| {
+        apply_theme(cx);
     })
     .detach();
 
@@ -214,10 +414,9 @@ pub fn setup_theme(cx: &mut App, path: PathBuf) {
                                 match v.kind {
                                     notify::EventKind::Create(_) | notify::EventKind::Modify(_) => {
                                         info!("Theme changed, updating...");
-                                        let theme = create_theme(&path);
                                         theme_transmitter
-                                            .update(cx, move |_, m| {
-                                                m.emit(theme);
+                                            .update(cx, |_, m| {
+                                                m.emit(ThemeFileChanged);
                                             })
                                             .expect("could not send theme to main thread");
                                     }
@@ -225,7 +424,7 @@ pub fn setup_theme(cx: &mut App, path: PathBuf) {
                                         info!("Theme file removed, resetting to default...");
                                         theme_transmitter
                                             .update(cx, |_, m| {
-                                                m.emit(Theme::default());
+                                                m.emit(ThemeFileChanged);
                                             })
                                             .expect("could not send theme to main thread");
                                     }
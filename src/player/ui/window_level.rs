@@ -0,0 +1,163 @@
+//! Keeps the main window above other windows. GPUI only sets the window level when a window is
+//! opened, so changes to the always on top setting are applied through the platform's own window
+//! APIs.
+//!
+//! Wayland has no protocol for clients to keep their windows on top, so changes can't be applied
+//! there while the window is open.
+
+use gpui::Window;
+use tracing::warn;
+
+/// Puts the window above other windows, or back among them.
+pub fn set_always_on_top(window: &Window, on_top: bool) {
+    if let Err(err) = platform::set_always_on_top(window, on_top) {
+        warn!("Failed to change the window level: {:?}", err);
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use anyhow::{Context, bail};
+    use gpui::Window;
+    use objc2_app_kit::{NSFloatingWindowLevel, NSNormalWindowLevel, NSView};
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+
+    pub fn set_always_on_top(window: &Window, on_top: bool) -> anyhow::Result<()> {
+        let handle = HasWindowHandle::window_handle(window)?.as_raw();
+        let RawWindowHandle::AppKit(handle) = handle else {
+            bail!("unexpected window handle: {:?}", handle);
+        };
+
+        // SAFETY: the view belongs to the open window, and this is called on the main thread
+        let view: &NSView = unsafe { handle.ns_view.cast().as_ref() };
+        let ns_window = view.window().context("the view has no window")?;
+
+        let level = if on_top {
+            NSFloatingWindowLevel
+        } else {
+            NSNormalWindowLevel
+        };
+        ns_window.setLevel(level);
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::ffi::c_void;
+
+    use anyhow::bail;
+    use gpui::Window;
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+    use windows::Win32::{
+        Foundation::HWND,
+        UI::WindowsAndMessaging::{
+            HWND_NOTOPMOST, HWND_TOPMOST, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SetWindowPos,
+        },
+    };
+
+    pub fn set_always_on_top(window: &Window, on_top: bool) -> anyhow::Result<()> {
+        let handle = HasWindowHandle::window_handle(window)?.as_raw();
+        let RawWindowHandle::Win32(handle) = handle else {
+            bail!("unexpected window handle: {:?}", handle);
+        };
+
+        let hwnd = HWND(handle.hwnd.get() as *mut c_void);
+        let insert_after = if on_top { HWND_TOPMOST } else { HWND_NOTOPMOST };
+
+        // SAFETY: the handle belongs to the open window
+        unsafe {
+            SetWindowPos(
+                hwnd,
+                Some(insert_after),
+                0,
+                0,
+                0,
+                0,
+                SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::env;
+
+    use anyhow::bail;
+    use gpui::Window;
+    use x11rb::{
+        connection::Connection,
+        protocol::xproto::{AtomEnum, ClientMessageEvent, ConnectionExt, EventMask},
+        rust_connection::RustConnection,
+    };
+
+    /// Asks the window manager to add or remove `_NET_WM_STATE_ABOVE` on this process's windows.
+    ///
+    /// GPUI doesn't give out X11 window handles, so the window is found through the window
+    /// manager's client list instead, by its `_NET_WM_PID`.
+    pub fn set_always_on_top(_: &Window, on_top: bool) -> anyhow::Result<()> {
+        if env::var_os("WAYLAND_DISPLAY").is_some_and(|display| !display.is_empty()) {
+            bail!("Wayland windows can't be kept on top");
+        }
+
+        let (conn, screen) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen].root;
+
+        let client_list = atom(&conn, "_NET_CLIENT_LIST")?;
+        let wm_pid = atom(&conn, "_NET_WM_PID")?;
+        let wm_state = atom(&conn, "_NET_WM_STATE")?;
+        let above = atom(&conn, "_NET_WM_STATE_ABOVE")?;
+
+        let clients = conn
+            .get_property(false, root, client_list, AtomEnum::WINDOW, 0, u32::MAX)?
+            .reply()?;
+        let pid = std::process::id();
+        let mut found = false;
+
+        for client in clients.value32().into_iter().flatten() {
+            let client_pid = conn
+                .get_property(false, client, wm_pid, AtomEnum::CARDINAL, 0, 1)?
+                .reply()?;
+            if client_pid.value32().and_then(|mut value| value.next()) != Some(pid) {
+                continue;
+            }
+
+            // the first value is the action: 1 adds the state, 0 removes it, and the fourth says
+            // that the request comes from an application
+            let event =
+                ClientMessageEvent::new(32, client, wm_state, [u32::from(on_top), above, 0, 1, 0]);
+            conn.send_event(
+                false,
+                root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                event,
+            )?;
+            found = true;
+        }
+
+        conn.flush()?;
+
+        if !found {
+            bail!("the window manager doesn't list the window");
+        }
+
+        Ok(())
+    }
+
+    fn atom(conn: &RustConnection, name: &str) -> anyhow::Result<u32> {
+        Ok(conn.intern_atom(false, name.as_bytes())?.reply()?.atom)
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+mod platform {
+    use gpui::Window;
+
+    pub fn set_always_on_top(_: &Window, _: bool) -> anyhow::Result<()> {
+        anyhow::bail!("keeping the window on top isn't supported on this platform")
+    }
+}
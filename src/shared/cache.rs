@@ -0,0 +1,258 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use tracing::{info, warn};
+
+use crate::player::ui::app;
+
+/// A category of data stored in the cache directory. Every category is stored in its own
+/// subdirectory, but all categories share the size budget set in
+/// [`CacheSettings`](super::settings::cache::CacheSettings).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheCategory {
+    /// Album art and thumbnails that are not stored in the library database.
+    Artwork,
+    /// The scan record, used to skip files that haven't changed since the last scan.
+    Scan,
+}
+
+impl CacheCategory {
+    pub const ALL: [CacheCategory; 2] = [CacheCategory::Artwork, CacheCategory::Scan];
+
+    fn dir_name(&self) -> &'static str {
+        match self {
+            CacheCategory::Artwork => "artwork",
+            CacheCategory::Scan => "scan",
+        }
+    }
+
+    /// Returns the directory for this category, creating it if it doesn't exist.
+    pub fn dir(&self) -> PathBuf {
+        let dir = cache_dir().join(self.dir_name());
+
+        if !dir.exists() {
+            if let Err(e) = fs::create_dir_all(&dir) {
+                warn!("couldn't create cache directory {:?}: {:?}", dir, e);
+            }
+        }
+
+        dir
+    }
+}
+
+/// Returns the platform-specific cache directory.
+pub fn cache_dir() -> PathBuf {
+    app::cache_dir()
+}
+
+struct CachedFile {
+    path: PathBuf,
+    size: u64,
+    last_used: SystemTime,
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<CachedFile>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            collect_files(&entry.path(), files);
+        } else if metadata.is_file() {
+            // access times are frequently disabled, so fall back to the modification time
+            let last_used = metadata
+                .accessed()
+                .or_else(|_| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+
+            files.push(CachedFile {
+                path: entry.path(),
+                size: metadata.len(),
+                last_used,
+            });
+        }
+    }
+}
+
+fn cached_files(root: &Path) -> Vec<CachedFile> {
+    let mut files = Vec::new();
+
+    for category in CacheCategory::ALL {
+        collect_files(&root.join(category.dir_name()), &mut files);
+    }
+
+    files
+}
+
+/// Returns the total size of every cached file, in bytes.
+pub fn cache_size() -> u64 {
+    cache_size_in(&cache_dir())
+}
+
+fn cache_size_in(root: &Path) -> u64 {
+    cached_files(root).iter().map(|file| file.size).sum()
+}
+
+/// Evicts the least recently used files across all categories until the total size of the cache
+/// is at or below `max_bytes`. Returns the new size of the cache.
+pub fn enforce_size_limit(max_bytes: u64) -> u64 {
+    enforce_size_limit_in(&cache_dir(), max_bytes)
+}
+
+fn enforce_size_limit_in(root: &Path, max_bytes: u64) -> u64 {
+    let mut files = cached_files(root);
+    let mut total: u64 = files.iter().map(|file| file.size).sum();
+
+    if total <= max_bytes {
+        return total;
+    }
+
+    files.sort_by_key(|file| file.last_used);
+
+    for file in files {
+        if total <= max_bytes {
+            break;
+        }
+
+        match fs::remove_file(&file.path) {
+            Ok(_) => total -= file.size,
+            Err(e) => warn!("couldn't evict cached file {:?}: {:?}", file.path, e),
+        }
+    }
+
+    info!("Cache over budget, evicted files down to {} bytes", total);
+
+    total
+}
+
+/// Removes every cached file.
+pub fn clear_cache() -> io::Result<()> {
+    clear_cache_in(&cache_dir())
+}
+
+fn clear_cache_in(root: &Path) -> io::Result<()> {
+    for category in CacheCategory::ALL {
+        let dir = root.join(category.dir_name());
+
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+    }
+
+    info!("Cache cleared");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{File, FileTimes},
+        time::Duration,
+    };
+
+    use super::*;
+
+    fn cache_root(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("mrchat-cache-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&root);
+
+        root
+    }
+
+    /// Writes a cached file of `size` bytes, last used `age` seconds ago.
+    fn cached(root: &Path, category: CacheCategory, name: &str, size: usize, age: u64) -> PathBuf {
+        let dir = root.join(category.dir_name());
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join(name);
+        fs::write(&path, vec![0u8; size]).unwrap();
+
+        let used = SystemTime::now() - Duration::from_secs(age);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_times(FileTimes::new().set_accessed(used).set_modified(used))
+            .unwrap();
+
+        path
+    }
+
+    #[test]
+    fn caches_within_the_limit_are_left_alone() {
+        let root = cache_root("within");
+        let art = cached(&root, CacheCategory::Artwork, "art", 100, 60);
+        let record = cached(&root, CacheCategory::Scan, "record", 50, 10);
+
+        assert_eq!(enforce_size_limit_in(&root, 150), 150);
+        assert!(art.exists());
+        assert!(record.exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn least_recently_used_files_are_evicted_first() {
+        let root = cache_root("eviction");
+        let oldest = cached(&root, CacheCategory::Artwork, "oldest", 100, 300);
+        let older = cached(&root, CacheCategory::Scan, "older", 100, 200);
+        let newest = cached(&root, CacheCategory::Artwork, "newest", 100, 100);
+
+        // the categories share the budget, so the scan record is evicted for artwork too
+        assert_eq!(enforce_size_limit_in(&root, 150), 100);
+        assert!(!oldest.exists());
+        assert!(!older.exists());
+        assert!(newest.exists());
+        assert_eq!(cache_size_in(&root), 100);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn files_in_nested_directories_are_counted() {
+        let root = cache_root("nested");
+        cached(&root, CacheCategory::Artwork, "top", 10, 0);
+
+        let nested = root
+            .join(CacheCategory::Artwork.dir_name())
+            .join("thumbnails");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("thumb"), [0u8; 20]).unwrap();
+
+        assert_eq!(cache_size_in(&root), 30);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn clearing_removes_every_category() {
+        let root = cache_root("clear");
+        cached(&root, CacheCategory::Artwork, "art", 100, 0);
+        cached(&root, CacheCategory::Scan, "record", 100, 0);
+        // files outside the categories aren't the cache's to remove
+        fs::write(root.join("other"), [0u8; 10]).unwrap();
+
+        clear_cache_in(&root).unwrap();
+
+        assert_eq!(cache_size_in(&root), 0);
+        for category in CacheCategory::ALL {
+            assert!(!root.join(category.dir_name()).exists());
+        }
+        assert!(root.join("other").exists());
+
+        // clearing an empty cache isn't an error
+        clear_cache_in(&root).unwrap();
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
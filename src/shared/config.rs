@@ -15,12 +15,29 @@ pub struct AppSection {
 pub struct ChatSection {
     #[serde(default = "default_chat_model")]
     pub default_model: String,
+    /// Other models offered when regenerating a reply, in addition to `default_model`.
+    #[serde(default)]
+    pub available_models: Vec<String>,
     #[serde(default = "default_chat_endpoint")]
     pub api_endpoint: String,
     #[serde(default)]
     pub api_key: Option<String>,
     #[serde(default = "default_max_context")]
     pub max_context_tokens: u32,
+    /// The most earlier messages sent with each completion request, or 0 to send all of them.
+    /// System messages are always sent and don't count towards the limit.
+    #[serde(default = "default_history_limit")]
+    pub history_limit: usize,
+    /// The sampling temperature used by conversations that don't set their own. Left to the
+    /// server if unset.
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// The nucleus sampling probability used by conversations that don't set their own.
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    /// The most tokens generated per reply by conversations that don't set their own.
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -101,9 +118,14 @@ impl Default for ChatSection {
     fn default() -> Self {
         Self {
             default_model: default_chat_model(),
+            available_models: Vec::new(),
             api_endpoint: default_chat_endpoint(),
             api_key: None,
             max_context_tokens: default_max_context(),
+            history_limit: default_history_limit(),
+            temperature: None,
+            top_p: None,
+            max_tokens: None,
         }
     }
 }
@@ -156,6 +178,10 @@ fn default_max_context() -> u32 {
     8192
 }
 
+fn default_history_limit() -> usize {
+    50
+}
+
 fn default_scan_directories() -> Vec<String> {
     Vec::new()
 }
@@ -0,0 +1,39 @@
+use tracing::warn;
+
+/// The service name secrets are stored under in the system's credential store.
+const SERVICE: &str = "org.v1cc0.mrchat";
+
+fn entry(name: &str) -> Option<keyring::Entry> {
+    keyring::Entry::new(SERVICE, name)
+        .inspect_err(|e| warn!("Could not access the credential store: {}", e))
+        .ok()
+}
+
+/// Stores a secret in the system's credential store (the Keychain on macOS, the Credential
+/// Manager on Windows and the Secret Service on Linux). Returns false if there is no credential
+/// store available, in which case the caller has to keep the secret itself.
+pub fn store_secret(name: &str, secret: &str) -> bool {
+    let Some(entry) = entry(name) else {
+        return false;
+    };
+
+    match entry.set_password(secret) {
+        Ok(()) => true,
+        Err(e) => {
+            warn!("Could not store {} in the credential store: {}", name, e);
+            false
+        }
+    }
+}
+
+/// Reads a secret stored with [`store_secret`], if there is one.
+pub fn load_secret(name: &str) -> Option<String> {
+    match entry(name)?.get_password() {
+        Ok(secret) => Some(secret),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            warn!("Could not read {} from the credential store: {}", name, e);
+            None
+        }
+    }
+}
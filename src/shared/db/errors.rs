@@ -0,0 +1,201 @@
+use thiserror::Error;
+use turso_core::LimboError;
+
+/// A database error, classified by what the caller can do about it.
+#[derive(PartialEq, Eq, Debug, Clone, Error)]
+pub enum DbError {
+    /// Another connection holds a lock on the database or one of its tables. Safe to retry.
+    #[error("Database is locked: `{0}`")]
+    Locked(String),
+    /// The database stayed busy for longer than the busy timeout. Safe to retry.
+    #[error("Database is busy: `{0}`")]
+    Busy(String),
+    /// A constraint (unique, foreign key, not null, etc.) was violated.
+    #[error("Constraint violated: `{0}`")]
+    Constraint(String),
+    /// A table, index or column being created already exists.
+    #[error("Schema object already exists: `{0}`")]
+    AlreadyExists(String),
+    #[error("Unknown database error: `{0}`")]
+    Other(String),
+}
+
+impl DbError {
+    /// Classifies an error by walking its chain and downcasting each cause to the underlying
+    /// Turso error. The first cause that can be classified wins; if none can, the outermost
+    /// error is returned as [`DbError::Other`].
+    pub fn classify(err: &anyhow::Error) -> DbError {
+        err.chain()
+            .find_map(|cause| {
+                if let Some(err) = cause.downcast_ref::<LimboError>() {
+                    Self::from_core(err)
+                } else if let Some(err) = cause.downcast_ref::<turso::Error>() {
+                    Self::from_turso(err)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_else(|| DbError::Other(err.to_string()))
+    }
+
+    fn from_core(err: &LimboError) -> Option<DbError> {
+        let msg = err.to_string();
+
+        match err {
+            LimboError::Busy => Some(DbError::Busy(msg)),
+            LimboError::LockingError(_) | LimboError::TableLocked | LimboError::SchemaLocked => {
+                Some(DbError::Locked(msg))
+            }
+            LimboError::Constraint(_) => Some(DbError::Constraint(msg)),
+            // turso_core has no variant of its own for existing schema objects, they're reported
+            // as parse errors
+            LimboError::ParseError(detail)
+                if detail.contains("already exists")
+                    || detail.contains("duplicate column name") =>
+            {
+                Some(DbError::AlreadyExists(msg))
+            }
+            _ => None,
+        }
+    }
+
+    /// turso passes engine errors on as `SqlExecutionFailure`, holding only the `LimboError`'s
+    /// message. The message is matched against the ones the same `LimboError` variants produce,
+    /// so this follows turso_core's wording rather than looking for words in it.
+    fn from_turso(err: &turso::Error) -> Option<DbError> {
+        let turso::Error::SqlExecutionFailure(msg) = err else {
+            return None;
+        };
+
+        let is = |variant: LimboError| msg == &variant.to_string();
+        let rest = |variant: fn(String) -> LimboError| {
+            variant_prefix(variant).and_then(|prefix| msg.strip_prefix(prefix.as_str()))
+        };
+
+        if is(LimboError::Busy) {
+            Some(DbError::Busy(msg.clone()))
+        } else if is(LimboError::TableLocked)
+            || is(LimboError::SchemaLocked)
+            || rest(LimboError::LockingError).is_some()
+        {
+            Some(DbError::Locked(msg.clone()))
+        } else if rest(LimboError::Constraint).is_some() {
+            Some(DbError::Constraint(msg.clone()))
+        } else if let Some(detail) = rest(LimboError::ParseError) {
+            Self::from_core(&LimboError::ParseError(detail.to_string()))
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if the operation failed because of contention and can be retried.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, DbError::Locked(_) | DbError::Busy(_))
+    }
+}
+
+/// The fixed start of the message of a `LimboError` variant that holds a detail, or None if the
+/// message starts with the detail itself and so can't be recognized.
+fn variant_prefix(variant: fn(String) -> LimboError) -> Option<String> {
+    const MARKER: &str = "\u{0}";
+
+    let msg = variant(MARKER.to_string()).to_string();
+    let prefix = msg.split(MARKER).next().unwrap_or_default();
+
+    (!prefix.is_empty() && prefix.len() < msg.len()).then(|| prefix.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flattened(err: LimboError) -> anyhow::Error {
+        turso::Error::SqlExecutionFailure(err.to_string()).into()
+    }
+
+    #[test]
+    fn busy_errors_are_retryable() {
+        for err in [LimboError::Busy.into(), flattened(LimboError::Busy)] {
+            let classified = DbError::classify(&err);
+
+            assert!(matches!(classified, DbError::Busy(_)));
+            assert!(classified.is_retryable());
+        }
+    }
+
+    #[test]
+    fn lock_errors_are_retryable() {
+        for err in [
+            LimboError::TableLocked,
+            LimboError::SchemaLocked,
+            LimboError::LockingError("write lock held".to_string()),
+        ] {
+            let core = err.to_string();
+            let classified = DbError::classify(&flattened(err));
+
+            assert_eq!(classified, DbError::Locked(core));
+            assert!(classified.is_retryable());
+        }
+
+        assert!(matches!(
+            DbError::classify(&LimboError::TableLocked.into()),
+            DbError::Locked(_)
+        ));
+    }
+
+    #[test]
+    fn constraint_errors_are_classified() {
+        let err = || LimboError::Constraint("UNIQUE constraint failed: artist.name".to_string());
+
+        for err in [err().into(), flattened(err())] {
+            let classified = DbError::classify(&err);
+
+            assert!(matches!(classified, DbError::Constraint(_)));
+            assert!(!classified.is_retryable());
+        }
+    }
+
+    #[test]
+    fn existing_schema_objects_are_classified() {
+        for detail in [
+            "table artist already exists",
+            "duplicate column name: folder",
+        ] {
+            let err = || LimboError::ParseError(detail.to_string());
+
+            assert!(matches!(
+                DbError::classify(&err().into()),
+                DbError::AlreadyExists(_)
+            ));
+            assert!(matches!(
+                DbError::classify(&flattened(err())),
+                DbError::AlreadyExists(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn other_parse_errors_are_not_classified() {
+        let err = flattened(LimboError::ParseError("no such table: busy".to_string()));
+
+        assert!(matches!(DbError::classify(&err), DbError::Other(_)));
+    }
+
+    #[test]
+    fn messages_are_not_searched_for_words() {
+        for err in [
+            anyhow::anyhow!("database is locked"),
+            anyhow::anyhow!("the device is busy"),
+            turso::Error::SqlExecutionFailure("busy".to_string()).into(),
+        ] {
+            assert!(matches!(DbError::classify(&err), DbError::Other(_)));
+        }
+    }
+
+    #[test]
+    fn the_chain_is_searched() {
+        let err = anyhow::Error::from(LimboError::Busy).context("failed to insert track");
+
+        assert!(matches!(DbError::classify(&err), DbError::Busy(_)));
+    }
+}
@@ -0,0 +1,170 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+
+/// In debug builds, the environment variable naming a directory of `.sql` files to apply instead
+/// of the embedded migrations, so that migrations can be edited without rebuilding.
+const MIGRATIONS_DIR_VAR: &str = "MRCHAT_MIGRATIONS_DIR";
+
+/// The migrations for the music library database, in the order they're applied.
+///
+/// Migrations are embedded in the binary, so they work regardless of the working directory, and
+/// tests can apply them to an in-memory database.
+pub const LIBRARY_MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "01_create_artist_table.sql",
+        include_str!("../../../migrations/01_create_artist_table.sql"),
+    ),
+    (
+        "02_create_album_table.sql",
+        include_str!("../../../migrations/02_create_album_table.sql"),
+    ),
+    (
+        "03_create_track_table.sql",
+        include_str!("../../../migrations/03_create_track_table.sql"),
+    ),
+    (
+        "04_add_album_release_info.sql",
+        include_str!("../../../migrations/04_add_album_release_info.sql"),
+    ),
+    (
+        "05_add_track_artist.sql",
+        include_str!("../../../migrations/05_add_track_artist.sql"),
+    ),
+    (
+        "06_add_album_mbid_and_path.sql",
+        include_str!("../../../migrations/06_add_album_mbid_and_path.sql"),
+    ),
+    (
+        "07_add_track_folder.sql",
+        include_str!("../../../migrations/07_add_track_folder.sql"),
+    ),
+    (
+        "08_create_playlist_table.sql",
+        include_str!("../../../migrations/08_create_playlist_table.sql"),
+    ),
+    (
+        "09_create_playlist_item_table.sql",
+        include_str!("../../../migrations/09_create_playlist_item_table.sql"),
+    ),
+    (
+        "10_insert_default_playlist.sql",
+        include_str!("../../../migrations/10_insert_default_playlist.sql"),
+    ),
+    (
+        "11_create_radio_station_table.sql",
+        include_str!("../../../migrations/11_create_radio_station_table.sql"),
+    ),
+    (
+        "12_create_play_history_table.sql",
+        include_str!("../../../migrations/12_create_play_history_table.sql"),
+    ),
+    (
+        "13_add_track_segments.sql",
+        include_str!("../../../migrations/13_add_track_segments.sql"),
+    ),
+    (
+        "15_delete_orphaned_plays.sql",
+        include_str!("../../../migrations/15_delete_orphaned_plays.sql"),
+    ),
+];
+
+/// The migrations for the chat database, in the order they're applied. They're recorded in the
+/// same way as the library migrations, but in the chat database's own `mrchat_migrations` table.
+pub const CHAT_MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "01_create_conversations_table.sql",
+        include_str!("../../../migrations/chat/01_create_conversations_table.sql"),
+    ),
+    (
+        "02_create_messages_table.sql",
+        include_str!("../../../migrations/chat/02_create_messages_table.sql"),
+    ),
+    (
+        "03_add_message_pending.sql",
+        include_str!("../../../migrations/chat/03_add_message_pending.sql"),
+    ),
+];
+
+/// The migrations to apply to the music library database. These are the embedded migrations,
+/// unless this is a debug build and `MRCHAT_MIGRATIONS_DIR` is set, in which case they're read
+/// from that directory. If the directory can't be read, the embedded migrations are used.
+pub fn library_migrations() -> &'static [(&'static str, &'static str)] {
+    let dir = match std::env::var_os(MIGRATIONS_DIR_VAR) {
+        Some(dir) if cfg!(debug_assertions) => dir,
+        _ => return LIBRARY_MIGRATIONS,
+    };
+
+    match read_migrations(Path::new(&dir)) {
+        Ok(migrations) => {
+            info!("Using {} migrations from {:?}", migrations.len(), dir);
+
+            // only read once at startup, so leaking them is harmless
+            migrations
+                .into_iter()
+                .map(|(filename, sql)| -> (&'static str, &'static str) {
+                    (filename.leak(), sql.leak())
+                })
+                .collect::<Vec<_>>()
+                .leak()
+        }
+        Err(err) => {
+            warn!("Using the embedded migrations: {:?}", err);
+            LIBRARY_MIGRATIONS
+        }
+    }
+}
+
+/// Reads every `.sql` file in the directory, ordered by filename.
+fn read_migrations(dir: &Path) -> Result<Vec<(String, String)>> {
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("unable to read migrations directory {:?}", dir))?;
+
+    let mut migrations = Vec::new();
+
+    for entry in entries {
+        let path = entry?.path();
+
+        if path.extension().is_none_or(|ext| ext != "sql") {
+            continue;
+        }
+
+        let Some(filename) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        let sql = std::fs::read_to_string(&path)
+            .with_context(|| format!("unable to read migration {:?}", path))?;
+
+        migrations.push((filename.to_string(), sql));
+    }
+
+    migrations.sort();
+
+    Ok(migrations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn on_disk_migrations_match_the_embedded_ones() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("migrations");
+        let migrations = read_migrations(&dir).unwrap();
+
+        let names: Vec<&str> = migrations.iter().map(|(name, _)| name.as_str()).collect();
+        let embedded: Vec<&str> = LIBRARY_MIGRATIONS.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, embedded);
+
+        for ((_, sql), (_, embedded)) in migrations.iter().zip(LIBRARY_MIGRATIONS) {
+            assert_eq!(sql, embedded);
+        }
+    }
+
+    #[test]
+    fn missing_directory_is_an_error() {
+        assert!(read_migrations(Path::new("/no/such/migrations")).is_err());
+    }
+}
@@ -1,15 +1,24 @@
-use std::{future::Future, path::Path, time::Duration};
+pub mod errors;
+pub mod migrations;
+
+use std::{future::Future, path::Path, sync::Arc, time::Duration};
 
 use anyhow::{Context, Error, Result};
 
+use async_lock::{Mutex, MutexGuardArc};
 use smol::{Timer, block_on};
-use tracing::warn;
+use tracing::{debug, info, warn};
 use turso::{Connection, Database, params::IntoParams};
 use turso_core::types::FromValue;
 
+use self::errors::DbError;
+
 #[derive(Clone)]
 pub struct TursoDatabase {
     inner: Database,
+    /// Serializes writers within this process. Shared between clones, so every handle to the
+    /// same database uses the same lock.
+    write_lock: Arc<Mutex<()>>,
 }
 
 impl TursoDatabase {
@@ -38,7 +47,37 @@ impl TursoDatabase {
             .map_err(|err| Error::msg(err.to_string()))
             .context("Failed to set busy timeout")?;
 
-        Ok(Self { inner: db })
+        Ok(Self {
+            inner: db,
+            write_lock: Arc::new(Mutex::new(())),
+        })
+    }
+
+    /// Opens a new database that only exists in memory, with the library migrations applied. Each
+    /// call opens a separate database, so tests using it can't interfere with each other.
+    #[cfg(test)]
+    pub async fn open_in_memory() -> Result<Self> {
+        let db = turso::Builder::new_local(":memory:")
+            .build()
+            .await
+            .context("failed to open in-memory Turso database")?;
+
+        let db = Self {
+            inner: db,
+            write_lock: Arc::new(Mutex::new(())),
+        };
+
+        db.run_migrations(migrations::LIBRARY_MIGRATIONS).await?;
+
+        Ok(db)
+    }
+
+    /// Acquires the in-process write lock for this database. The guard should be held for the
+    /// duration of a write (or a group of related writes), so that writers in this process wait
+    /// for each other instead of colliding and retrying on `database is locked`. Reads don't need
+    /// to take the lock, since WAL mode allows them to run concurrently with a writer.
+    pub async fn lock_writes(&self) -> MutexGuardArc<()> {
+        self.write_lock.lock_arc().await
     }
 
     pub fn connect(&self) -> Result<TursoConnection> {
@@ -56,6 +95,7 @@ impl TursoDatabase {
     }
 
     pub async fn run_migrations(&self, migrations: &[(&str, &str)]) -> Result<()> {
+        let _guard = self.lock_writes().await;
         let conn = self.connect()?;
 
         conn.execute(
@@ -85,32 +125,18 @@ impl TursoDatabase {
 
             match execution {
                 Ok(()) => {}
-                Err(err) => {
-                    let mut benign = false;
-                    let mut cause_message = String::new();
-
-                    for cause in err.chain() {
-                        let msg = cause.to_string();
-                        if msg.contains("duplicate column name") || msg.contains("already exists") {
-                            benign = true;
-                            cause_message = msg;
-                            break;
-                        }
-
-                        // Keep the deepest cause for diagnostics if nothing matches.
-                        cause_message = msg;
-                    }
-
-                    if benign {
+                Err(err) => match DbError::classify(&err) {
+                    DbError::AlreadyExists(cause) => {
                         warn!(
                             "skipping migration {:?} because it appears already applied: {}",
-                            filename, cause_message
+                            filename, cause
                         );
-                    } else {
+                    }
+                    _ => {
                         return Err(err)
                             .context(format!("failed to execute migration {:?}", filename));
                     }
-                }
+                },
             }
 
             conn.execute(
@@ -123,6 +149,103 @@ impl TursoDatabase {
 
         Ok(())
     }
+
+    /// Like `migration_status`, for the database file at `path`. A file that doesn't exist isn't
+    /// created: every migration is pending for it. An existing file is opened without the setup
+    /// `open_local` does, so its journal mode is left alone.
+    pub async fn migration_status_at(
+        path: &Path,
+        migrations: &[(&str, &str)],
+    ) -> Result<MigrationStatus> {
+        if !path.exists() {
+            return Ok(MigrationStatus {
+                pending: migrations
+                    .iter()
+                    .map(|(name, _)| name.to_string())
+                    .collect(),
+                ..Default::default()
+            });
+        }
+
+        let db = turso::Builder::new_local(path.to_string_lossy().as_ref())
+            .build()
+            .await
+            .context("failed to open local Turso database")?;
+
+        let db = Self {
+            inner: db,
+            write_lock: Arc::new(Mutex::new(())),
+        };
+
+        db.migration_status(migrations).await
+    }
+
+    /// Compares the migrations recorded in the `mrchat_migrations` table against the given list,
+    /// without applying anything. Safe to call on a database that has never been migrated, in
+    /// which case every migration is pending.
+    pub async fn migration_status(&self, migrations: &[(&str, &str)]) -> Result<MigrationStatus> {
+        let conn = self.connect()?;
+
+        let has_table = conn
+            .query_scalar_optional::<i64>(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'mrchat_migrations'",
+                (),
+            )
+            .await
+            .context("failed to check for mrchat_migrations bookkeeping table")?
+            .is_some();
+
+        let recorded = if has_table {
+            conn.query_map(
+                "SELECT filename FROM mrchat_migrations ORDER BY filename",
+                (),
+                |row| {
+                    row.get::<String>(0)
+                        .context("failed to read migration filename")
+                },
+            )
+            .await
+            .context("failed to read migration history")?
+        } else {
+            Vec::new()
+        };
+
+        let mut status = MigrationStatus::default();
+
+        for (filename, _) in migrations {
+            if recorded.iter().any(|applied| applied == filename) {
+                status.applied.push(filename.to_string());
+            } else {
+                status.pending.push(filename.to_string());
+            }
+        }
+
+        status.missing = recorded
+            .into_iter()
+            .filter(|applied| !migrations.iter().any(|(filename, _)| filename == applied))
+            .collect();
+
+        Ok(status)
+    }
+}
+
+/// The state of the database's migrations relative to the migrations known to this build. See
+/// [`TursoDatabase::migration_status`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationStatus {
+    /// Migrations that have been applied to the database.
+    pub applied: Vec<String>,
+    /// Migrations that would be applied by the next call to `run_migrations`.
+    pub pending: Vec<String>,
+    /// Migrations recorded as applied that are not known to this build, usually because the
+    /// database was last opened by a newer (or modified) version of the application.
+    pub missing: Vec<String>,
+}
+
+impl MigrationStatus {
+    pub fn is_up_to_date(&self) -> bool {
+        self.pending.is_empty() && self.missing.is_empty()
+    }
 }
 
 pub struct TursoConnection {
@@ -246,11 +369,20 @@ where
 
     for attempt in 0..=MAX_RETRIES {
         match op().await {
-            Ok(value) => return Ok(value),
-            Err(err) if is_locked(&err) && attempt < MAX_RETRIES => {
+            Ok(value) => {
+                if attempt > 0 {
+                    info!(
+                        "database operation succeeded after {} lock retries",
+                        attempt
+                    );
+                }
+                return Ok(value);
+            }
+            Err(err) if DbError::classify(&err).is_retryable() && attempt < MAX_RETRIES => {
                 let exponential = BASE_DELAY_MS * (1_u64 << attempt);
                 let delay_ms = exponential.min(MAX_DELAY_MS);
                 let delay = Duration::from_millis(delay_ms);
+                debug!("database is locked, retrying in {:?}", delay);
                 Timer::after(delay).await;
             }
             Err(err) => return Err(err),
@@ -260,7 +392,85 @@ where
     unreachable!("retry loop should return on success or error")
 }
 
-fn is_locked(err: &anyhow::Error) -> bool {
-    err.chain()
-        .any(|cause| cause.to_string().contains("database is locked"))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::db::migrations::LIBRARY_MIGRATIONS;
+
+    #[test]
+    fn in_memory_database_is_migrated() {
+        block_on(async {
+            let db = TursoDatabase::open_in_memory().await.unwrap();
+            let status = db.migration_status(LIBRARY_MIGRATIONS).await.unwrap();
+
+            assert!(status.is_up_to_date(), "{status:?}");
+            assert_eq!(status.applied.len(), LIBRARY_MIGRATIONS.len());
+        });
+    }
+
+    #[test]
+    fn migrations_are_only_applied_once() {
+        block_on(async {
+            let db = TursoDatabase::open_in_memory().await.unwrap();
+            db.run_migrations(LIBRARY_MIGRATIONS).await.unwrap();
+
+            // the default playlist would be inserted twice if its migration ran again
+            let conn = db.connect().unwrap();
+            let playlists = conn
+                .query_scalar::<i64>("SELECT COUNT(*) FROM playlist", ())
+                .await
+                .unwrap();
+            assert_eq!(playlists, 1);
+        });
+    }
+
+    #[test]
+    fn in_memory_databases_are_separate() {
+        block_on(async {
+            let first = TursoDatabase::open_in_memory().await.unwrap();
+            let second = TursoDatabase::open_in_memory().await.unwrap();
+
+            first
+                .connect()
+                .unwrap()
+                .execute("INSERT INTO playlist (name) VALUES ('Only here')", ())
+                .await
+                .unwrap();
+
+            let count = second
+                .connect()
+                .unwrap()
+                .query_scalar::<i64>("SELECT COUNT(*) FROM playlist", ())
+                .await
+                .unwrap();
+            assert_eq!(count, 1);
+        });
+    }
+
+    #[test]
+    fn missing_databases_arent_created_for_their_status() {
+        block_on(async {
+            let path =
+                std::env::temp_dir().join(format!("mrchat-missing-{}.db", std::process::id()));
+
+            let status = TursoDatabase::migration_status_at(&path, LIBRARY_MIGRATIONS)
+                .await
+                .unwrap();
+
+            assert!(!path.exists());
+            assert!(status.applied.is_empty());
+            assert_eq!(status.pending.len(), LIBRARY_MIGRATIONS.len());
+        });
+    }
+
+    #[test]
+    fn unknown_migrations_are_reported_as_missing() {
+        block_on(async {
+            let db = TursoDatabase::open_in_memory().await.unwrap();
+            let status = db.migration_status(&LIBRARY_MIGRATIONS[1..]).await.unwrap();
+
+            assert_eq!(status.missing, vec![LIBRARY_MIGRATIONS[0].0.to_string()]);
+            assert!(status.pending.is_empty());
+        });
+    }
 }
@@ -0,0 +1,111 @@
+use chrono::{DateTime, Datelike, Utc};
+use gpui::App;
+
+use crate::shared::settings::{SettingsGlobal, interface::FormatLocale};
+
+const GERMAN_MONTHS: [&str; 12] = [
+    "Januar",
+    "Februar",
+    "März",
+    "April",
+    "Mai",
+    "Juni",
+    "Juli",
+    "August",
+    "September",
+    "Oktober",
+    "November",
+    "Dezember",
+];
+
+/// The locale dates and numbers are formatted for, as set in the interface settings. The helpers
+/// below expect a locale resolved this way, and format for English otherwise.
+pub fn locale(cx: &App) -> FormatLocale {
+    let interface = &cx.global::<SettingsGlobal>().model.read(cx).interface;
+    interface.locale.resolve(interface.language)
+}
+
+/// A date written out in full, such as "March 5, 2021".
+pub fn long_date(date: &DateTime<Utc>, locale: FormatLocale) -> String {
+    match locale {
+        FormatLocale::Chinese => date.format("%Y年%-m月%-d日").to_string(),
+        FormatLocale::German => format!(
+            "{}. {} {}",
+            date.day(),
+            GERMAN_MONTHS[date.month0() as usize],
+            date.year()
+        ),
+        FormatLocale::English | FormatLocale::Language | FormatLocale::System => {
+            date.format("%B %-e, %Y").to_string()
+        }
+    }
+}
+
+/// A date in its short numeric form, such as "03/05/21".
+pub fn short_date(date: &DateTime<Utc>, locale: FormatLocale) -> String {
+    match locale {
+        FormatLocale::Chinese => date.format("%Y/%m/%d").to_string(),
+        FormatLocale::German => date.format("%d.%m.%Y").to_string(),
+        FormatLocale::English | FormatLocale::Language | FormatLocale::System => {
+            date.format("%x").to_string()
+        }
+    }
+}
+
+/// A whole number with its digits grouped by thousands, such as "12,345".
+pub fn number(value: i64, locale: FormatLocale) -> String {
+    let separator = match locale {
+        FormatLocale::German => '.',
+        FormatLocale::English
+        | FormatLocale::Chinese
+        | FormatLocale::Language
+        | FormatLocale::System => ',',
+    };
+
+    let digits = value.unsigned_abs().to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+
+    if value < 0 {
+        grouped.push('-');
+    }
+
+    for (idx, digit) in digits.chars().enumerate() {
+        if idx > 0 && (digits.len() - idx) % 3 == 0 {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn date() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2021, 3, 5, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn dates_follow_the_locale() {
+        assert_eq!(long_date(&date(), FormatLocale::English), "March 5, 2021");
+        assert_eq!(long_date(&date(), FormatLocale::Chinese), "2021年3月5日");
+        assert_eq!(long_date(&date(), FormatLocale::German), "5. März 2021");
+
+        assert_eq!(short_date(&date(), FormatLocale::English), "03/05/21");
+        assert_eq!(short_date(&date(), FormatLocale::Chinese), "2021/03/05");
+        assert_eq!(short_date(&date(), FormatLocale::German), "05.03.2021");
+    }
+
+    #[test]
+    fn numbers_are_grouped_by_thousands() {
+        assert_eq!(number(7, FormatLocale::English), "7");
+        assert_eq!(number(999, FormatLocale::English), "999");
+        assert_eq!(number(1000, FormatLocale::English), "1,000");
+        assert_eq!(number(1234567, FormatLocale::Chinese), "1,234,567");
+        assert_eq!(number(-12345, FormatLocale::German), "-12.345");
+    }
+}
@@ -0,0 +1,363 @@
+//! Interface text in the user's language.
+//!
+//! The main window is translated: the chat, the playback controls, the queue and the library. The
+//! settings, the app menu, the command palette, the about and metadata lookup windows and most
+//! prompts are still only in English, and their text is written inline until they're translated.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use gpui::App;
+
+use crate::shared::settings::{SettingsGlobal, interface::Language};
+
+/// The resolved interface language, kept outside the settings model so that strings can be looked
+/// up from code that doesn't have the app context at hand.
+static LANGUAGE: AtomicU8 = AtomicU8::new(0);
+
+const ENGLISH: &[(&str, &str)] = &[
+    ("chat.new_conversation", "New conversation"),
+    ("chat.role.system", "System prompt"),
+    ("chat.role.user", "You"),
+    ("chat.role.assistant", "Assistant"),
+    ("chat.role.tool", "Tool"),
+    ("chat.parameter.default", "{} (default)"),
+    ("chat.parameter.server_default", "Server default"),
+    ("chat.composer.placeholder", "Send a message…"),
+    (
+        "chat.unavailable",
+        "Chat is unavailable: the Turso configuration or chat service is missing",
+    ),
+    ("chat.conversations", "Conversations"),
+    ("chat.new", "+ New"),
+    ("chat.no_conversations", "No conversations yet"),
+    ("chat.model", "Model: {}"),
+    ("chat.current", "Current conversation: {}"),
+    ("chat.current.none", "Current conversation: none"),
+    ("chat.parameters", "Parameters"),
+    ("chat.parameter.max_tokens", "Max tokens"),
+    ("chat.parameters.reset", "Reset to defaults"),
+    ("chat.no_messages", "This conversation has no messages yet"),
+    ("chat.history_truncated", "Earlier messages were truncated"),
+    ("chat.system_prompt.expanded", "▾ System prompt"),
+    ("chat.system_prompt.collapsed", "▸ System prompt"),
+    (
+        "chat.waiting_for_network",
+        "{} · waiting for the network to send",
+    ),
+    ("chat.generating", "Generating…"),
+    ("chat.regenerate", "Regenerate"),
+    ("chat.other_model", "Use another model ▾"),
+    ("chat.send", "Send"),
+    ("config.title", "MrChat needs to be configured"),
+    (
+        "config.missing",
+        "No config.toml was found in the data directory. Create one at: {}",
+    ),
+    (
+        "config.hint",
+        "Click \"Create default config\" for a template, then fill in the API and other settings \
+         for your setup.",
+    ),
+    (
+        "config.restart",
+        "Restart or refresh after saving the config to turn on chat.",
+    ),
+    ("config.create", "Create default config"),
+    ("config.later", "Configure later"),
+    ("player.unknown_track", "Unknown Track"),
+    ("tray.play", "Play"),
+    ("tray.pause", "Pause"),
+    ("tray.previous", "Previous"),
+    ("tray.next", "Next"),
+    ("tray.show", "Show MrChat"),
+    ("tray.quit", "Quit MrChat"),
+    ("player.unknown_artist", "Unknown Artist"),
+    ("player.queue_empty", "The queue is empty"),
+    ("player.copy_now_playing", "Copy Now Playing"),
+    (
+        "player.copy_now_playing_with_time",
+        "Copy Now Playing with Time",
+    ),
+    ("player.queue_folder", "Queue Other Tracks in Folder"),
+    ("player.repeat_off", "Off"),
+    ("player.repeat", "Repeat"),
+    ("player.repeat_one", "Repeat One"),
+    ("player.live", "LIVE"),
+    ("player.default_device", "Default device"),
+    ("queue.cleared", "Queue cleared"),
+    ("queue.undo", "Undo"),
+    ("queue.title", "Queue"),
+    ("queue.clear", "Clear"),
+    ("queue.shuffle", "Shuffle"),
+    ("queue.shuffling", "Shuffling"),
+    ("radio.save", "Save"),
+    ("radio.no_stations", "No saved stations"),
+    ("radio.remove", "Remove"),
+    ("library.albums", "Albums"),
+    ("library.radio", "Radio"),
+    ("library.shuffle_all", "Shuffle All"),
+    ("library.play", "Play"),
+    ("library.pause", "Pause"),
+    ("library.play_next", "Play next"),
+    ("library.play_from_here", "Play from here"),
+    ("library.play_to_end_of_album", "Play to end of album"),
+    ("library.play_tracks", "Play {} tracks"),
+    ("library.released", "Released {}"),
+    ("library.disc", "DISC {}"),
+    ("library.filter_tracks", "Filter tracks"),
+    ("library.no_matching_tracks", "No tracks match the filter."),
+    ("library.recently_played", "Recently played"),
+    ("library.add_to_queue", "Add to queue"),
+    ("library.queue_to_end_of_album", "Queue to end of album"),
+    ("library.queue_folder", "Queue other tracks in folder"),
+    ("library.queue_tracks", "Add {} tracks to queue"),
+    ("library.refresh_metadata", "Refresh metadata"),
+    ("library.look_up", "Look up on MusicBrainz"),
+    ("library.like", "Add to Liked Songs"),
+    ("library.unlike", "Remove from Liked Songs"),
+    ("library.add_to_playlist", "Add to {}"),
+    ("library.clear_selection", "Clear selection"),
+    ("library.copy_album_art", "Copy Album Art"),
+    ("library.save_album_art", "Save Album Art…"),
+    ("library.fetch_cover_art", "Fetch Cover Art"),
+    ("library.fetching_cover_art", "Fetching Cover Art…"),
+    ("library.replace_queue.title", "Replace the queue?"),
+    (
+        "library.replace_queue.detail",
+        "The album can replace the tracks in the queue, or be added after them.",
+    ),
+    ("library.replace_queue", "Replace Queue"),
+    ("common.cancel", "Cancel"),
+];
+
+const CHINESE: &[(&str, &str)] = &[
+    ("chat.new_conversation", "新会话"),
+    ("chat.role.system", "系统提示"),
+    ("chat.role.user", "你"),
+    ("chat.role.assistant", "助手"),
+    ("chat.role.tool", "工具"),
+    ("chat.parameter.default", "{}（默认）"),
+    ("chat.parameter.server_default", "服务器默认"),
+    ("chat.composer.placeholder", "发送消息…"),
+    ("chat.unavailable", "聊天不可用：缺少 Turso 配置或聊天服务"),
+    ("chat.conversations", "会话列表"),
+    ("chat.new", "+ 新建"),
+    ("chat.no_conversations", "暂无会话"),
+    ("chat.model", "模型: {}"),
+    ("chat.current", "当前会话: {}"),
+    ("chat.current.none", "当前会话: 无"),
+    ("chat.parameters", "参数"),
+    ("chat.parameter.max_tokens", "最大 Token 数"),
+    ("chat.parameters.reset", "恢复默认"),
+    ("chat.no_messages", "该会话暂无消息"),
+    ("chat.history_truncated", "上文已截断"),
+    ("chat.system_prompt.expanded", "▾ 系统提示"),
+    ("chat.system_prompt.collapsed", "▸ 系统提示"),
+    ("chat.waiting_for_network", "{} · 等待网络恢复后发送"),
+    ("chat.generating", "正在生成…"),
+    ("chat.regenerate", "重新生成"),
+    ("chat.other_model", "使用其他模型 ▾"),
+    ("chat.send", "发送"),
+    ("config.title", "需要配置 MrChat"),
+    (
+        "config.missing",
+        "未在数据目录找到 config.toml。请在下列路径创建配置文件：{}",
+    ),
+    (
+        "config.hint",
+        "可以点击“生成默认配置”按钮获取一份模版文件，再根据实际环境填写 API 等参数。",
+    ),
+    (
+        "config.restart",
+        "配置保存后请重新启动或刷新以启用聊天功能。",
+    ),
+    ("config.create", "生成默认配置"),
+    ("config.later", "稍后配置"),
+    ("player.unknown_track", "未知曲目"),
+    ("tray.play", "播放"),
+    ("tray.pause", "暂停"),
+    ("tray.previous", "上一首"),
+    ("tray.next", "下一首"),
+    ("tray.show", "显示 MrChat"),
+    ("tray.quit", "退出 MrChat"),
+    ("player.unknown_artist", "未知艺术家"),
+    ("player.queue_empty", "队列为空"),
+    ("player.copy_now_playing", "复制正在播放"),
+    ("player.copy_now_playing_with_time", "复制正在播放及时间"),
+    ("player.queue_folder", "将文件夹中的其他曲目加入队列"),
+    ("player.repeat_off", "关闭"),
+    ("player.repeat", "循环"),
+    ("player.repeat_one", "单曲循环"),
+    ("player.live", "直播"),
+    ("player.default_device", "默认设备"),
+    ("queue.cleared", "队列已清空"),
+    ("queue.undo", "撤销"),
+    ("queue.title", "队列"),
+    ("queue.clear", "清空"),
+    ("queue.shuffle", "随机播放"),
+    ("queue.shuffling", "随机播放中"),
+    ("radio.save", "保存"),
+    ("radio.no_stations", "没有保存的电台"),
+    ("radio.remove", "删除"),
+    ("library.albums", "专辑"),
+    ("library.radio", "电台"),
+    ("library.shuffle_all", "全部随机播放"),
+    ("library.play", "播放"),
+    ("library.pause", "暂停"),
+    ("library.play_next", "下一首播放"),
+    ("library.play_from_here", "从此处播放"),
+    ("library.play_to_end_of_album", "播放至专辑结尾"),
+    ("library.play_tracks", "播放 {} 首曲目"),
+    ("library.released", "发行于 {}"),
+    ("library.disc", "碟 {}"),
+    ("library.filter_tracks", "筛选曲目"),
+    ("library.no_matching_tracks", "没有符合筛选条件的曲目。"),
+    ("library.recently_played", "最近播放"),
+    ("library.add_to_queue", "加入队列"),
+    (
+        "library.queue_to_end_of_album",
+        "将至专辑结尾的曲目加入队列",
+    ),
+    ("library.queue_folder", "将文件夹中的其他曲目加入队列"),
+    ("library.queue_tracks", "将 {} 首曲目加入队列"),
+    ("library.refresh_metadata", "刷新元数据"),
+    ("library.look_up", "在 MusicBrainz 上查询"),
+    ("library.like", "添加到“喜欢的歌曲”"),
+    ("library.unlike", "从“喜欢的歌曲”中移除"),
+    ("library.add_to_playlist", "添加到 {}"),
+    ("library.clear_selection", "取消选择"),
+    ("library.copy_album_art", "复制专辑封面"),
+    ("library.save_album_art", "保存专辑封面…"),
+    ("library.fetch_cover_art", "获取专辑封面"),
+    ("library.fetching_cover_art", "正在获取专辑封面…"),
+    ("library.replace_queue.title", "替换队列？"),
+    (
+        "library.replace_queue.detail",
+        "专辑可以替换队列中的曲目，也可以添加到它们之后。",
+    ),
+    ("library.replace_queue", "替换队列"),
+    ("common.cancel", "取消"),
+];
+
+fn table(language: Language) -> &'static [(&'static str, &'static str)] {
+    match language {
+        Language::Chinese => CHINESE,
+        Language::English | Language::System => ENGLISH,
+    }
+}
+
+fn current() -> Language {
+    match LANGUAGE.load(Ordering::Relaxed) {
+        1 => Language::Chinese,
+        _ => Language::English,
+    }
+}
+
+fn set_language(language: Language) {
+    let value = match language.resolve() {
+        Language::Chinese => 1,
+        Language::English | Language::System => 0,
+    };
+    LANGUAGE.store(value, Ordering::Relaxed);
+}
+
+fn lookup(language: Language, key: &'static str) -> &'static str {
+    let find = |table: &'static [(&'static str, &'static str)]| {
+        table
+            .iter()
+            .find(|(candidate, _)| *candidate == key)
+            .map(|(_, text)| *text)
+    };
+
+    find(table(language))
+        .or_else(|| find(ENGLISH))
+        .unwrap_or(key)
+}
+
+/// Looks up the interface text for `key` in the current language. Keys missing from the current
+/// language fall back to English, and keys missing from English are shown as-is.
+pub fn t(key: &'static str) -> &'static str {
+    lookup(current(), key)
+}
+
+/// Looks up the text for `key` and fills in its `{}` placeholders in order.
+pub fn t_args(key: &'static str, args: &[&dyn std::fmt::Display]) -> String {
+    fill(t(key), args)
+}
+
+fn fill(text: &str, args: &[&dyn std::fmt::Display]) -> String {
+    let mut parts = text.split("{}");
+    let mut result = parts.next().unwrap_or_default().to_string();
+
+    for (index, part) in parts.enumerate() {
+        if let Some(arg) = args.get(index) {
+            result.push_str(&arg.to_string());
+        }
+        result.push_str(part);
+    }
+
+    result
+}
+
+/// Sets the interface language from the settings, and keeps it in step with them.
+pub fn setup_language(cx: &mut App) {
+    let settings = cx.global::<SettingsGlobal>().model.clone();
+    set_language(settings.read(cx).interface.language);
+
+    cx.observe(&settings, |settings, cx| {
+        let language = settings.read(cx).interface.language.resolve();
+        if language != current() {
+            set_language(language);
+            cx.refresh_windows();
+        }
+    })
+    .detach();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_language_has_the_same_keys() {
+        let keys = |table: &[(&'static str, &str)]| {
+            let mut keys: Vec<_> = table.iter().map(|(key, _)| *key).collect();
+            keys.sort_unstable();
+            keys
+        };
+
+        assert_eq!(keys(ENGLISH), keys(CHINESE));
+    }
+
+    #[test]
+    fn keys_are_unique() {
+        let mut keys: Vec<_> = ENGLISH.iter().map(|(key, _)| *key).collect();
+        keys.sort_unstable();
+        keys.dedup();
+
+        assert_eq!(keys.len(), ENGLISH.len());
+    }
+
+    #[test]
+    fn placeholders_match_across_languages() {
+        for (key, text) in CHINESE {
+            assert_eq!(
+                text.matches("{}").count(),
+                lookup(Language::English, key).matches("{}").count(),
+                "{key}"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_keys_are_shown_as_is() {
+        assert_eq!(lookup(Language::Chinese, "missing.key"), "missing.key");
+        assert_eq!(lookup(Language::Chinese, "library.play"), "播放");
+    }
+
+    #[test]
+    fn placeholders_are_filled_in_order() {
+        assert_eq!(fill("{} of {}", &[&1, &"two"]), "1 of two");
+        assert_eq!(fill("Released {}", &[]), "Released ");
+    }
+}
@@ -0,0 +1,109 @@
+use std::sync::OnceLock;
+
+use tracing_subscriber::{
+    filter::Targets, fmt::time::LocalTime, layer::SubscriberExt, util::SubscriberInitExt,
+};
+
+/// The target used for logs written for every frame of audio. They're only written when this
+/// target is named in the log filter, since they'd otherwise drown out everything else.
+pub const AUDIO_FRAMES_TARGET: &str = "mrchat::audio_frames";
+
+/// The environment variable that the log filter is read from, such as `debug` or
+/// `info,mrchat::audio_frames=trace`.
+const LOG_FILTER_VAR: &str = "MRCHAT_LOG";
+
+const DEFAULT_LOG_FILTER: &str = "info";
+
+static EFFECTIVE_FILTER: OnceLock<String> = OnceLock::new();
+
+/// Builds the filter directives from the user's filter, if any. The audio frame target is turned
+/// off unless the user's filter mentions it.
+fn filter_directives(requested: Option<&str>) -> String {
+    let requested = requested
+        .map(str::trim)
+        .filter(|filter| !filter.is_empty())
+        .unwrap_or(DEFAULT_LOG_FILTER);
+
+    if requested.contains(AUDIO_FRAMES_TARGET) {
+        requested.to_string()
+    } else {
+        format!("{requested},{AUDIO_FRAMES_TARGET}=off")
+    }
+}
+
+/// Sets up logging to stderr, with the filter from the `MRCHAT_LOG` environment variable.
+pub fn init() {
+    let requested = std::env::var(LOG_FILTER_VAR).ok();
+    let directives = filter_directives(requested.as_deref());
+
+    let (directives, targets, invalid) = match directives.parse::<Targets>() {
+        Ok(targets) => (directives, targets, None),
+        Err(err) => {
+            let directives = filter_directives(None);
+            let targets = directives
+                .parse::<Targets>()
+                .expect("default log filter is invalid");
+            (directives, targets, Some(err))
+        }
+    };
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_timer(LocalTime::rfc_3339()))
+        .with(targets)
+        .init();
+
+    if let Some(err) = invalid {
+        tracing::warn!(
+            "Invalid {} filter, using the default: {}",
+            LOG_FILTER_VAR,
+            err
+        );
+    }
+
+    tracing::info!("Logging with filter {:?}", directives);
+    EFFECTIVE_FILTER.get_or_init(|| directives);
+}
+
+/// The log filter in use, for display. Empty if logging hasn't been set up.
+pub fn effective_filter() -> &'static str {
+    EFFECTIVE_FILTER
+        .get()
+        .map(String::as_str)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_to_info_without_audio_frames() {
+        assert_eq!(filter_directives(None), "info,mrchat::audio_frames=off");
+        assert_eq!(
+            filter_directives(Some("  ")),
+            "info,mrchat::audio_frames=off"
+        );
+    }
+
+    #[test]
+    fn audio_frames_stay_off_unless_requested() {
+        assert_eq!(
+            filter_directives(Some("debug")),
+            "debug,mrchat::audio_frames=off"
+        );
+        assert_eq!(
+            filter_directives(Some("info,mrchat::audio_frames=trace")),
+            "info,mrchat::audio_frames=trace"
+        );
+    }
+
+    #[test]
+    fn directives_parse() {
+        assert!(filter_directives(None).parse::<Targets>().is_ok());
+        assert!(
+            filter_directives(Some("warn,mrchat::player=debug"))
+                .parse::<Targets>()
+                .is_ok()
+        );
+    }
+}
@@ -1,7 +1,12 @@
 // Shared components used by both chat and player modules
 
+pub mod cache;
 pub mod config;
+pub mod credentials;
 pub mod db;
+pub mod format;
+pub mod i18n;
+pub mod logging;
 pub mod settings;
 pub mod ui;
 pub mod util;
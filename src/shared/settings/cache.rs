@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// User-set cache settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheSettings {
+    /// The maximum total size of the cache directory, in megabytes. When the cache grows beyond
+    /// this size, the least recently used files are evicted until it fits again.
+    ///
+    /// Defaults to 512.
+    #[serde(default = "default_max_size_mb")]
+    pub max_size_mb: u64,
+}
+
+impl CacheSettings {
+    pub fn max_size_bytes(&self) -> u64 {
+        self.max_size_mb.saturating_mul(1024 * 1024)
+    }
+}
+
+fn default_max_size_mb() -> u64 {
+    512
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            max_size_mb: default_max_size_mb(),
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use serde::{Deserialize, Serialize};
+
+/// User-set interface settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InterfaceSettings {
+    /// Which of the built-in themes to use. A `theme.json` file in the data directory overrides
+    /// the built-in themes entirely.
+    ///
+    /// Defaults to the dark theme.
+    #[serde(default)]
+    pub theme: ThemeMode,
+    /// How large album art is shown in the album list. Larger art is easier to browse by, while
+    /// smaller art fits more albums on screen.
+    #[serde(default)]
+    pub album_art_size: AlbumArtSize,
+    /// The conventions dates and numbers are written in, such as release dates and play counts.
+    ///
+    /// Defaults to following the interface language.
+    #[serde(default)]
+    pub locale: FormatLocale,
+    /// The language the interface is shown in.
+    ///
+    /// Defaults to following the system language.
+    #[serde(default)]
+    pub language: Language,
+    /// Keeps the main window above other windows.
+    #[serde(default)]
+    pub always_on_top: bool,
+    /// The accent color, written as `#RRGGBB`. When set, it replaces the theme's colors for
+    /// primary buttons, toggled controls and selections.
+    #[serde(default)]
+    pub accent_color: Option<String>,
+    /// Shows a desktop notification when a new track starts playing, while the window is in the
+    /// background.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub notify_on_track_change: bool,
+    /// What closing the main window does.
+    ///
+    /// Defaults to quitting.
+    #[serde(default)]
+    pub close_action: CloseAction,
+}
+
+impl InterfaceSettings {
+    /// The accent color as `0xRRGGBB`, or None if it isn't set or isn't a valid color.
+    pub fn accent(&self) -> Option<u32> {
+        self.accent_color.as_deref().and_then(parse_hex_color)
+    }
+}
+
+/// Parses a color written as `#RRGGBB` or `RRGGBB` into `0xRRGGBB`.
+pub fn parse_hex_color(value: &str) -> Option<u32> {
+    let value = value.trim();
+    let digits = value.strip_prefix('#').unwrap_or(value);
+
+    if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    u32::from_str_radix(digits, 16).ok()
+}
+
+/// The built-in theme to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeMode {
+    #[default]
+    Dark,
+    Light,
+    /// Follows the system appearance, switching between the dark and light themes as it changes.
+    System,
+}
+
+/// What closing the main window does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CloseAction {
+    /// Quits MrChat, stopping playback.
+    #[default]
+    Quit,
+    /// Hides the window and keeps playing, with a tray icon to control playback and bring the
+    /// window back.
+    KeepPlaying,
+}
+
+/// The conventions used to format dates and numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormatLocale {
+    /// Follows the interface language, so that dates and numbers match the text around them.
+    #[default]
+    Language,
+    /// Follows the system's locale, falling back to English.
+    System,
+    English,
+    Chinese,
+    German,
+}
+
+impl FormatLocale {
+    /// Replaces `Language` and `System` with the locale they stand for. `language` is the
+    /// interface language setting.
+    pub fn resolve(self, language: Language) -> Self {
+        match self {
+            FormatLocale::Language => match language.resolve() {
+                Language::Chinese => FormatLocale::Chinese,
+                Language::English | Language::System => FormatLocale::English,
+            },
+            FormatLocale::System => FormatLocale::from_tag(&system_locale()),
+            locale => locale,
+        }
+    }
+
+    /// The locale for a language tag such as "de-AT", or English if it isn't one of the locales
+    /// dates and numbers can be formatted for.
+    fn from_tag(tag: &str) -> Self {
+        match primary_language(tag).as_str() {
+            "zh" => FormatLocale::Chinese,
+            "de" => FormatLocale::German,
+            _ => FormatLocale::English,
+        }
+    }
+}
+
+/// The language interface text is shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+    /// Follows the system's language, falling back to English.
+    #[default]
+    System,
+    English,
+    Chinese,
+}
+
+impl Language {
+    /// Replaces `System` with the language it stands for.
+    pub fn resolve(self) -> Self {
+        if self != Language::System {
+            return self;
+        }
+
+        Language::from_tag(&system_locale())
+    }
+
+    /// The language for a language tag such as "zh-Hans-CN", or English if the interface hasn't
+    /// been translated into it.
+    fn from_tag(tag: &str) -> Self {
+        match primary_language(tag).as_str() {
+            "zh" => Language::Chinese,
+            _ => Language::English,
+        }
+    }
+}
+
+/// The language tag of the system's locale, such as "en-US". This is read from the OS, since the
+/// POSIX locale variables aren't set for apps on Windows, or on macOS when they're opened from the
+/// Finder. Empty if the locale can't be read.
+fn system_locale() -> String {
+    sys_locale::get_locale().unwrap_or_default()
+}
+
+/// The language part of a language tag, in lowercase. Tags are written as "en-US" by most
+/// systems, and as POSIX locales like "en_US.UTF-8" by some.
+fn primary_language(tag: &str) -> String {
+    tag.split(['-', '_', '.'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// The size of the album art in the album list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlbumArtSize {
+    #[default]
+    Small,
+    Medium,
+    Large,
+}
+
+impl AlbumArtSize {
+    /// The width and height of the art, in pixels. Every size fits within the stored thumbnails,
+    /// so the full size art never has to be loaded for the list.
+    pub fn art_size(self) -> f32 {
+        match self {
+            AlbumArtSize::Small => 22.0,
+            AlbumArtSize::Medium => 40.0,
+            AlbumArtSize::Large => 64.0,
+        }
+    }
+
+    /// The height of a row in the album list.
+    pub fn row_height(self) -> f32 {
+        self.art_size() + 14.0
+    }
+
+    /// The next size up, or the largest size if it's already the largest.
+    pub fn larger(self) -> Self {
+        match self {
+            AlbumArtSize::Small => AlbumArtSize::Medium,
+            AlbumArtSize::Medium | AlbumArtSize::Large => AlbumArtSize::Large,
+        }
+    }
+
+    /// The next size down, or the smallest size if it's already the smallest.
+    pub fn smaller(self) -> Self {
+        match self {
+            AlbumArtSize::Large => AlbumArtSize::Medium,
+            AlbumArtSize::Medium | AlbumArtSize::Small => AlbumArtSize::Small,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_step_and_stop_at_the_ends() {
+        assert_eq!(AlbumArtSize::Small.larger(), AlbumArtSize::Medium);
+        assert_eq!(AlbumArtSize::Large.larger(), AlbumArtSize::Large);
+        assert_eq!(AlbumArtSize::Large.smaller(), AlbumArtSize::Medium);
+        assert_eq!(AlbumArtSize::Small.smaller(), AlbumArtSize::Small);
+    }
+
+    #[test]
+    fn missing_size_defaults_to_small() {
+        let settings: InterfaceSettings = serde_json::from_str(r#"{ "theme": "light" }"#).unwrap();
+
+        assert_eq!(settings.album_art_size, AlbumArtSize::Small);
+        // the small size keeps the list as it was before the setting existed
+        assert_eq!(settings.album_art_size.row_height(), 36.0);
+    }
+
+    #[test]
+    fn closing_quits_by_default() {
+        let settings: InterfaceSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings.close_action, CloseAction::Quit);
+
+        let settings: InterfaceSettings =
+            serde_json::from_str(r#"{ "close_action": "keep_playing" }"#).unwrap();
+        assert_eq!(settings.close_action, CloseAction::KeepPlaying);
+    }
+
+    #[test]
+    fn languages_are_read_from_tags() {
+        assert_eq!(Language::from_tag("zh-Hans-CN"), Language::Chinese);
+        assert_eq!(Language::from_tag("zh_TW.UTF-8"), Language::Chinese);
+        assert_eq!(Language::from_tag("de-DE"), Language::English);
+        assert_eq!(Language::from_tag(""), Language::English);
+
+        assert_eq!(FormatLocale::from_tag("de_AT"), FormatLocale::German);
+        assert_eq!(FormatLocale::from_tag("ZH-cn"), FormatLocale::Chinese);
+        assert_eq!(FormatLocale::from_tag("fr-FR"), FormatLocale::English);
+    }
+
+    #[test]
+    fn formatting_follows_the_language_by_default() {
+        let settings: InterfaceSettings =
+            serde_json::from_str(r#"{ "language": "chinese" }"#).unwrap();
+
+        assert_eq!(
+            settings.locale.resolve(settings.language),
+            FormatLocale::Chinese
+        );
+        assert_eq!(
+            FormatLocale::German.resolve(Language::Chinese),
+            FormatLocale::German
+        );
+    }
+
+    #[test]
+    fn hex_colors_are_validated() {
+        assert_eq!(parse_hex_color("#0667B2"), Some(0x0667B2));
+        assert_eq!(parse_hex_color(" e5484d "), Some(0xE5484D));
+        assert_eq!(parse_hex_color("#fff"), None);
+        assert_eq!(parse_hex_color("#12345G"), None);
+        assert_eq!(parse_hex_color("+12345"), None);
+        assert_eq!(parse_hex_color("##0667B2"), None);
+    }
+
+    #[test]
+    fn invalid_accent_is_ignored() {
+        let settings: InterfaceSettings =
+            serde_json::from_str(r#"{ "accent_color": "blue" }"#).unwrap();
+
+        assert_eq!(settings.accent(), None);
+    }
+}
@@ -1,13 +1,20 @@
+pub mod cache;
+pub mod interface;
 pub mod playback;
+pub mod remote;
 pub mod scan;
+pub mod scrobbling;
 pub mod storage;
+pub mod transfer;
 
 use std::{fs::File, path::PathBuf, sync::mpsc::channel, time::Duration};
 
 use gpui::{App, AppContext, AsyncApp, Entity, Global};
 use notify::{Event, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
-use tracing::{info, warn};
+use tracing::{error, info, warn};
+
+use crate::shared::util::write_file_atomically;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Settings {
@@ -15,6 +22,14 @@ pub struct Settings {
     pub scanning: scan::ScanSettings,
     #[serde(default)]
     pub playback: playback::PlaybackSettings,
+    #[serde(default)]
+    pub cache: cache::CacheSettings,
+    #[serde(default)]
+    pub interface: interface::InterfaceSettings,
+    #[serde(default)]
+    pub scrobbling: scrobbling::ScrobblingSettings,
+    #[serde(default)]
+    pub remote_control: remote::RemoteControlSettings,
 }
 
 pub fn create_settings(path: &PathBuf) -> Settings {
@@ -35,6 +50,8 @@ pub struct SettingsGlobal {
     pub model: Entity<Settings>,
     #[allow(dead_code)]
     pub watcher: Option<Box<dyn Watcher>>,
+    /// The path to `settings.json`.
+    pub path: PathBuf,
 }
 
 impl Global for SettingsGlobal {}
@@ -42,6 +59,7 @@ impl Global for SettingsGlobal {}
 pub fn setup_settings(cx: &mut App, path: PathBuf) {
     let settings = cx.new(|_| create_settings(&path));
     let settings_model = settings.clone(); // for the closure
+    let settings_path = path.clone();
 
     // create and setup file watcher
     let (tx, rx) = channel::<notify::Result<Event>>();
@@ -54,6 +72,7 @@ pub fn setup_settings(cx: &mut App, path: PathBuf) {
         let global = SettingsGlobal {
             model: settings,
             watcher: None,
+            path,
         };
 
         cx.set_global(global);
@@ -68,24 +87,28 @@ pub fn setup_settings(cx: &mut App, path: PathBuf) {
             while let Ok(event) = rx.try_recv() {
                 match event {
                     Ok(v) => {
+                        // other files in the directory, including the temporary file settings
+                        // are written through, don't concern the watcher
                         if !v.paths.iter().any(|t| t.ends_with("settings.json")) {
-                            return;
+                            continue;
                         };
                         match v.kind {
                             notify::EventKind::Create(_) | notify::EventKind::Modify(_) => {
                                 info!("Settings changed, updating...");
                                 let settings = create_settings(&path);
                                 settings_model
-                                    .update(app, |v, _| {
+                                    .update(app, |v, cx| {
                                         *v = settings;
+                                        cx.notify();
                                     })
                                     .expect("settings model could not be updated");
                             }
                             notify::EventKind::Remove(_) => {
                                 info!("Settings file removed, using default settings");
                                 settings_model
-                                    .update(app, |v, _| {
+                                    .update(app, |v, cx| {
                                         *v = Settings::default();
+                                        cx.notify();
                                     })
                                     .expect("settings model could not be updated");
                             }
@@ -106,7 +129,31 @@ pub fn setup_settings(cx: &mut App, path: PathBuf) {
     let global = SettingsGlobal {
         model: settings,
         watcher: Some(Box::new(watcher)),
+        path: settings_path,
     };
 
     cx.set_global(global);
 }
+
+/// Applies a change to the settings and writes them back to `settings.json`, so that the change
+/// persists across launches.
+pub fn update_settings(cx: &mut App, f: impl FnOnce(&mut Settings)) {
+    let global = cx.global::<SettingsGlobal>();
+    let model = global.model.clone();
+    let path = global.path.clone();
+
+    model.update(cx, |settings, cx| {
+        f(settings);
+        cx.notify();
+    });
+
+    let settings = model.read(cx).clone();
+
+    let result = serde_json::to_vec_pretty(&settings)
+        .map_err(anyhow::Error::from)
+        .and_then(|data| write_file_atomically(&path, data).map_err(Into::into));
+
+    if let Err(e) = result {
+        error!("Failed to save settings: {:?}", e);
+    }
+}
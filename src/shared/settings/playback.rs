@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 /// User-set playback settings, to be passed to the playback thread.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PlaybackSettings {
     /// Whether or not the playback thread should allow for repeating to be disabled.
     ///
@@ -17,7 +17,7 @@ pub struct PlaybackSettings {
 
     /// Determines whether or not the playback thread should handle previous track requests by
     /// jumping to the beginning of the track if the current track has been played for more than
-    /// 5 seconds.
+    /// `prev_restart_threshold_secs` seconds.
     ///
     /// If the option is false, requests to go to the previous track always result in the previous
     /// track in the queue being played. If the option is true, requests to go to the previous
@@ -28,14 +28,397 @@ pub struct PlaybackSettings {
     /// prefer this behavior)
     #[serde(default)]
     pub prev_track_jump_first: bool,
+
+    /// How long the current track has to have played for, in seconds, before previous track
+    /// requests restart it instead of going to the previous track. Only used if
+    /// `prev_track_jump_first` is enabled.
+    ///
+    /// Defaults to 5.
+    #[serde(default = "default_prev_restart_threshold_secs")]
+    pub prev_restart_threshold_secs: u64,
+
+    /// Determines what happens when a track in a track listing (such as an album or playlist) is
+    /// double-clicked.
+    ///
+    /// Defaults to playing the album (or playlist) from the clicked track.
+    #[serde(default)]
+    pub double_click_action: TrackDoubleClickAction,
+
+    /// The sample rate to open the output device at, in Hz.
+    ///
+    /// If the device doesn't support the rate, or the option is unset (the default), the device's
+    /// default format is used.
+    #[serde(default)]
+    pub preferred_sample_rate: Option<u32>,
+
+    /// The bit depth to open the output device with.
+    ///
+    /// If the device doesn't support the bit depth, or the option is unset (the default), the
+    /// device's default format is used.
+    #[serde(default)]
+    pub preferred_bit_depth: Option<u16>,
+
+    /// Whether or not the output device should be opened in exclusive mode.
+    ///
+    /// In exclusive mode, the device is reopened at the sample rate of each track when the device
+    /// supports it, so that audio is never resampled. Exclusive access to the device is also
+    /// requested, which stops other applications from playing through it or changing its format.
+    ///
+    /// Exclusive access is only available on macOS, where CoreAudio hog mode is used. Neither
+    /// AudioGraph nor cpal's WASAPI and ALSA hosts can open a device exclusively, so on Windows
+    /// and Linux (or if another application holds the device) the device is opened in shared
+    /// mode, still at the track's sample rate when it supports it.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub exclusive_mode: bool,
+
+    /// Whether or not silence at the start and end of tracks should be skipped.
+    ///
+    /// Only audio that stays below `silence_threshold_db` is skipped, and never more than
+    /// `silence_max_trim_ms` from either end of a track, so that quiet intros and outros aren't
+    /// cut off.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub skip_silence: bool,
+
+    /// The level below which audio counts as silence when skipping silence, in dBFS.
+    ///
+    /// Defaults to -60.
+    #[serde(default = "default_silence_threshold_db")]
+    pub silence_threshold_db: f64,
+
+    /// The most silence skipped from the start or end of a track, in milliseconds.
+    ///
+    /// Defaults to 3000.
+    #[serde(default = "default_silence_max_trim_ms")]
+    pub silence_max_trim_ms: u32,
+
+    /// The stereo balance, from -1.0 (left only) to 1.0 (right only). The opposite channel is
+    /// attenuated, so the level of the favored channel never changes. Only affects stereo output.
+    ///
+    /// Defaults to 0.0 (centered).
+    #[serde(default)]
+    pub balance: f64,
+
+    /// Whether or not playback should move to the new default output device when the default
+    /// device is changed in the operating system.
+    ///
+    /// If the option is false, playback stays on the device it's currently using for as long as
+    /// that device is present, and only moves to the default device once it's removed.
+    ///
+    /// Defaults to true.
+    #[serde(default = "default_follow_default_device")]
+    pub follow_default_device: bool,
+
+    /// Whether or not playback should be paused when the output device it's playing on is
+    /// removed, such as when headphones are unplugged. Playback moves to another device either
+    /// way, but stays paused until it's resumed.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub pause_on_output_disconnect: bool,
+
+    /// Whether or not part of each stereo channel should be mixed into the other, for more natural
+    /// stereo imaging on headphones. Only affects stereo output.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub crossfeed: bool,
+
+    /// How much of each channel is mixed into the other when `crossfeed` is enabled.
+    ///
+    /// Defaults to low.
+    #[serde(default)]
+    pub crossfeed_intensity: CrossfeedIntensity,
+
+    /// Whether or not tracks that run into each other on an album (such as live albums, DJ mixes
+    /// and classical works) should be played without any gap between them.
+    ///
+    /// A track is treated as running into the next one when both are from the same album in the
+    /// library, the next track is the following track number (or the first track of the next
+    /// disc), and the track doesn't end in silence. Across such a transition, silence is never
+    /// skipped and audio that's being resampled carries straight on. Other transitions, including
+    /// those between albums, are played as normal. Turning this off plays every transition as
+    /// normal.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub auto_gapless_albums: bool,
+
+    /// The volume playback starts at when MrChat is opened, from 0.0 to 1.0. Values outside of
+    /// that range are clamped.
+    ///
+    /// Defaults to 1.0.
+    #[serde(default = "default_volume")]
+    pub default_volume: f64,
+
+    /// The most tracks queued when shuffling the whole library. Larger libraries are sampled at
+    /// random, so the queue doesn't grow to tens of thousands of tracks.
+    ///
+    /// Defaults to 1000.
+    #[serde(default = "default_shuffle_library_limit")]
+    pub shuffle_library_limit: u32,
+
+    /// What happens when the last track in the queue finishes and the queue isn't repeating.
+    ///
+    /// Defaults to stopping.
+    #[serde(default)]
+    pub end_of_queue_action: EndOfQueueAction,
+
+    /// What happens when a track that's already in the queue is queued again.
+    ///
+    /// Defaults to allowing the duplicate.
+    #[serde(default)]
+    pub duplicate_queue_items: DuplicateQueueItems,
+
+    /// What happens to the queue when an album is played from its page.
+    ///
+    /// Defaults to replacing the queue.
+    #[serde(default)]
+    pub album_play_action: AlbumPlayAction,
+
+    /// What the shuffle button on an album's page does.
+    ///
+    /// Defaults to shuffling the album's tracks without turning on shuffle.
+    #[serde(default)]
+    pub album_shuffle_action: AlbumShuffleAction,
+}
+
+fn default_prev_restart_threshold_secs() -> u64 {
+    5
+}
+
+fn default_silence_threshold_db() -> f64 {
+    -60.0
+}
+
+fn default_silence_max_trim_ms() -> u32 {
+    3000
+}
+
+fn default_follow_default_device() -> bool {
+    true
+}
+
+fn default_volume() -> f64 {
+    1.0
+}
+
+fn default_shuffle_library_limit() -> u32 {
+    1000
+}
+
+/// The action taken when a track is double-clicked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackDoubleClickAction {
+    /// Replaces the queue with only the clicked track.
+    PlayTrack,
+    /// Replaces the queue with the clicked track and every track after it in the album, or with
+    /// the playlist if the track was clicked in a playlist.
+    #[default]
+    PlayAlbumFromHere,
+    /// Adds the clicked track to the end of the queue.
+    AddToQueue,
+}
+
+/// The action taken when the end of the queue is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndOfQueueAction {
+    /// Stops playback, which unloads the last track.
+    #[default]
+    Stop,
+    /// Keeps the last track loaded, paused at its start, so that it can be played again.
+    Pause,
+}
+
+/// The action taken when a track that's already in the queue is queued again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateQueueItems {
+    /// Queues the track again, so it's in the queue more than once.
+    #[default]
+    Allow,
+    /// Leaves the track where it is in the queue, without queueing it again.
+    Skip,
+    /// Removes the track from where it is in the queue and queues it at the new position. The
+    /// current track is never removed.
+    Move,
+}
+
+/// The action taken on the queue when an album is played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlbumPlayAction {
+    /// Replaces the queue with the album.
+    #[default]
+    Replace,
+    /// Adds the album to the end of the queue and starts playing it, keeping the tracks that were
+    /// already queued.
+    Append,
+    /// Asks whether to replace the queue or add to it, unless the queue is empty.
+    Ask,
+}
+
+/// The action taken when an album is shuffled from its page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlbumShuffleAction {
+    /// Replaces the queue with the album's tracks in a random order, leaving shuffle as it was.
+    #[default]
+    ShuffleTracks,
+    /// Turns on shuffle, then replaces the queue with the album. Shuffle stays on for whatever is
+    /// played next.
+    TurnOnShuffle,
+}
+
+/// How strongly the channels are mixed together by crossfeed. The presets are the ones commonly
+/// used with the Bauer stereophonic-to-binaural filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrossfeedIntensity {
+    /// 650 Hz, 9.5 dB, as in Jan Meier's crossfeed circuit: a subtle effect.
+    #[default]
+    Low,
+    /// 700 Hz, 6 dB, as in Chu Moy's crossfeed circuit.
+    Medium,
+    /// 700 Hz, 4.5 dB: the strongest effect, and the closest to listening to speakers.
+    High,
+}
+
+impl CrossfeedIntensity {
+    /// The cutoff frequency of the filter applied to the crossfed signal, in Hz.
+    pub fn cutoff(self) -> f64 {
+        match self {
+            CrossfeedIntensity::Low => 650.0,
+            CrossfeedIntensity::Medium | CrossfeedIntensity::High => 700.0,
+        }
+    }
+
+    /// How much quieter the crossfed signal is than the direct signal at low frequencies, in dB.
+    pub fn feed_db(self) -> f64 {
+        match self {
+            CrossfeedIntensity::Low => 9.5,
+            CrossfeedIntensity::Medium => 6.0,
+            CrossfeedIntensity::High => 4.5,
+        }
+    }
+}
+
+impl PlaybackSettings {
+    /// Whether any of the enabled options raise the level of the decoded audio. If so, samples
+    /// can exceed full scale, and are passed through a limiter before they're played.
+    pub fn modifies_gain(&self) -> bool {
+        // volume is applied by the output device, but the crossfeed filters can overshoot full
+        // scale on some transients
+        self.crossfeed
+    }
+
+    /// Whether a previous track request should restart the current track, which has been played
+    /// up to `position` (in seconds), rather than going to the previous track.
+    pub fn restarts_on_previous(&self, position: u64) -> bool {
+        self.prev_track_jump_first && position > self.prev_restart_threshold_secs
+    }
+
+    /// The volume to start playback at, limited to the valid range. A hand-edited settings file
+    /// can hold anything, including NaN, which is treated as the default.
+    pub fn initial_volume(&self) -> f64 {
+        if self.default_volume.is_nan() {
+            default_volume()
+        } else {
+            self.default_volume.clamp(0.0, 1.0)
+        }
+    }
 }
 
-#[allow(clippy::derivable_impls)]
 impl Default for PlaybackSettings {
     fn default() -> Self {
         Self {
             always_repeat: false,
             prev_track_jump_first: false,
+            prev_restart_threshold_secs: default_prev_restart_threshold_secs(),
+            double_click_action: TrackDoubleClickAction::default(),
+            preferred_sample_rate: None,
+            preferred_bit_depth: None,
+            exclusive_mode: false,
+            skip_silence: false,
+            silence_threshold_db: default_silence_threshold_db(),
+            silence_max_trim_ms: default_silence_max_trim_ms(),
+            balance: 0.0,
+            follow_default_device: default_follow_default_device(),
+            pause_on_output_disconnect: false,
+            crossfeed: false,
+            crossfeed_intensity: CrossfeedIntensity::default(),
+            auto_gapless_albums: false,
+            default_volume: default_volume(),
+            shuffle_library_limit: default_shuffle_library_limit(),
+            end_of_queue_action: EndOfQueueAction::default(),
+            duplicate_queue_items: DuplicateQueueItems::default(),
+            album_play_action: AlbumPlayAction::default(),
+            album_shuffle_action: AlbumShuffleAction::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(jump_first: bool, threshold: u64) -> PlaybackSettings {
+        PlaybackSettings {
+            prev_track_jump_first: jump_first,
+            prev_restart_threshold_secs: threshold,
+            ..PlaybackSettings::default()
         }
     }
+
+    #[test]
+    fn restarts_only_after_the_threshold() {
+        let settings = settings(true, 5);
+
+        assert!(!settings.restarts_on_previous(4));
+        assert!(!settings.restarts_on_previous(5));
+        assert!(settings.restarts_on_previous(6));
+    }
+
+    #[test]
+    fn threshold_is_configurable() {
+        assert!(settings(true, 2).restarts_on_previous(3));
+        assert!(!settings(true, 10).restarts_on_previous(9));
+        assert!(settings(true, 10).restarts_on_previous(11));
+    }
+
+    #[test]
+    fn never_restarts_unless_enabled() {
+        let settings = settings(false, 5);
+
+        assert!(!settings.restarts_on_previous(6));
+        assert!(!settings.restarts_on_previous(600));
+    }
+
+    #[test]
+    fn missing_threshold_defaults_to_five_seconds() {
+        let settings: PlaybackSettings =
+            serde_json::from_str(r#"{ "prev_track_jump_first": true }"#).unwrap();
+
+        assert_eq!(settings.prev_restart_threshold_secs, 5);
+        assert!(settings.restarts_on_previous(6));
+    }
+
+    #[test]
+    fn initial_volume_is_clamped() {
+        let volume = |default_volume| PlaybackSettings {
+            default_volume,
+            ..PlaybackSettings::default()
+        };
+
+        assert_eq!(volume(0.4).initial_volume(), 0.4);
+        assert_eq!(volume(-0.5).initial_volume(), 0.0);
+        assert_eq!(volume(3.0).initial_volume(), 1.0);
+        assert_eq!(volume(f64::NAN).initial_volume(), 1.0);
+        assert_eq!(PlaybackSettings::default().initial_volume(), 1.0);
+    }
 }
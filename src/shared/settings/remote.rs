@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// User-set settings for the local control server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteControlSettings {
+    /// Whether or not the control server is started. The server allows playback to be controlled
+    /// over HTTP, see `player::services::controllers::remote` for the endpoints.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// The address the control server listens on. Anything that can reach this address can
+    /// control playback, so only change this if every device on the network is trusted.
+    ///
+    /// Defaults to 127.0.0.1, so only programs on this computer can connect.
+    #[serde(default = "default_address")]
+    pub address: String,
+
+    /// The port the control server listens on.
+    ///
+    /// Defaults to 47630.
+    #[serde(default = "default_port")]
+    pub port: u16,
+
+    /// Whether or not the current track and playback state are written to a JSON file, for use
+    /// in stream overlays.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub write_now_playing_file: bool,
+
+    /// Where the now playing file is written. If unset, `now_playing.json` in the data directory
+    /// is used.
+    #[serde(default)]
+    pub now_playing_file: Option<PathBuf>,
+}
+
+fn default_address() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_port() -> u16 {
+    47630
+}
+
+impl Default for RemoteControlSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: default_address(),
+            port: default_port(),
+            write_now_playing_file: false,
+            now_playing_file: None,
+        }
+    }
+}
@@ -7,16 +7,52 @@ use tracing::{error, warn};
 pub struct ScanSettings {
     #[serde(default = "retrieve_default_paths")]
     pub paths: Vec<PathBuf>,
+    /// When an album or artist has no sort name tag, sort it as if leading articles ("The", "A",
+    /// "An") weren't there, so "The Beatles" sorts under B.
+    #[serde(default = "default_ignore_articles")]
+    pub ignore_articles: bool,
+    /// Whether or not the library should be scanned when the application starts. Scans can still
+    /// be started manually when this is disabled.
+    #[serde(default = "default_scan_on_startup")]
+    pub scan_on_startup: bool,
+    /// Glob patterns for files and folders that shouldn't be scanned, matched against the full
+    /// path (for example `**/Music/Podcasts/**`).
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
+    /// Whether or not folders starting with a `.` should be skipped.
+    #[serde(default)]
+    pub skip_hidden_dirs: bool,
+    /// Whether or not symlinked folders should be scanned. Folders that are reachable through
+    /// more than one path are only scanned once either way.
+    #[serde(default = "default_follow_symlinks")]
+    pub follow_symlinks: bool,
 }
 
 impl Default for ScanSettings {
     fn default() -> Self {
         Self {
             paths: retrieve_default_paths(),
+            ignore_articles: default_ignore_articles(),
+            scan_on_startup: default_scan_on_startup(),
+            exclude_patterns: Vec::new(),
+            skip_hidden_dirs: false,
+            follow_symlinks: default_follow_symlinks(),
         }
     }
 }
 
+fn default_ignore_articles() -> bool {
+    true
+}
+
+fn default_scan_on_startup() -> bool {
+    true
+}
+
+fn default_follow_symlinks() -> bool {
+    true
+}
+
 fn retrieve_default_paths() -> Vec<PathBuf> {
     #[cfg(target_os = "windows")]
     {
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+/// User-set scrobbling settings, shared by every scrobbling service.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScrobblingSettings {
+    /// The percentage of a track that has to be played before it is scrobbled.
+    ///
+    /// Defaults to 50.
+    #[serde(default = "default_threshold_percent")]
+    pub threshold_percent: u8,
+
+    /// The number of seconds of a track that have to be played before it is scrobbled, even if
+    /// less than `threshold_percent` of it has been played. This keeps long tracks (such as DJ
+    /// mixes) from having to be played for an hour before they count.
+    ///
+    /// Defaults to 240 (4 minutes), as recommended by Last.fm.
+    #[serde(default = "default_threshold_seconds")]
+    pub threshold_seconds: u64,
+
+    /// Whether listens are submitted to ListenBrainz. Listens are only submitted once a user
+    /// token has been provided.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub listenbrainz_enabled: bool,
+}
+
+fn default_threshold_percent() -> u8 {
+    50
+}
+
+fn default_threshold_seconds() -> u64 {
+    240
+}
+
+impl Default for ScrobblingSettings {
+    fn default() -> Self {
+        Self {
+            threshold_percent: default_threshold_percent(),
+            threshold_seconds: default_threshold_seconds(),
+            listenbrainz_enabled: false,
+        }
+    }
+}
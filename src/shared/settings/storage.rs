@@ -1,13 +1,86 @@
+use gpui::{App, Bounds, Pixels, Size, WindowBounds, point, px, size};
 use serde::{Deserialize, Serialize};
 
-use crate::player::ui::models::CurrentTrack;
+use crate::player::{library::types::TrackSegment, ui::models::CurrentTrack};
 
-use std::{fs, path::PathBuf};
+use std::{collections::BTreeMap, fs, path::PathBuf};
 
 /// Data to store while quitting the app
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct StorageData {
     pub current_track: Option<CurrentTrack>,
+    /// The part of a larger file the current track plays, if it's a track from a cue sheet.
+    #[serde(default)]
+    pub current_segment: Option<TrackSegment>,
+    #[serde(default)]
+    pub window: Option<WindowState>,
+    /// The UID of the output device picked by the user, or None to use the default device.
+    #[serde(default)]
+    pub output_device: Option<String>,
+    /// The volume last used with each output device, by UID.
+    #[serde(default)]
+    pub device_volumes: BTreeMap<String, f64>,
+}
+
+/// The position and size of the main window when the app was last closed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowState {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub maximized: bool,
+}
+
+impl WindowState {
+    pub fn new(bounds: WindowBounds) -> Self {
+        let rect = bounds.get_bounds();
+
+        Self {
+            x: rect.origin.x.into(),
+            y: rect.origin.y.into(),
+            width: rect.size.width.into(),
+            height: rect.size.height.into(),
+            maximized: matches!(bounds, WindowBounds::Maximized(_)),
+        }
+    }
+
+    /// Converts the stored state back into window bounds, making sure the window is at least
+    /// `min_size` and fits on a connected display. If the window would no longer be visible (for
+    /// example, because the monitor it was on was unplugged), it's centered on the primary
+    /// display instead.
+    pub fn to_window_bounds(&self, min_size: Size<Pixels>, cx: &App) -> WindowBounds {
+        let width = self.width.max(min_size.width.into());
+        let height = self.height.max(min_size.height.into());
+        let bounds = Bounds::new(point(px(self.x), px(self.y)), size(px(width), px(height)));
+
+        let display = cx
+            .displays()
+            .into_iter()
+            .find(|display| display.bounds().intersects(&bounds));
+
+        let bounds = match display {
+            Some(display) => {
+                let area = display.bounds();
+                let (left, top): (f32, f32) = (area.origin.x.into(), area.origin.y.into());
+                let width = width.min(area.size.width.into());
+                let height = height.min(area.size.height.into());
+
+                // pull the window back inside the display if it hangs off an edge
+                let x = self.x.clamp(left, f32::from(area.right()) - width);
+                let y = self.y.clamp(top, f32::from(area.bottom()) - height);
+
+                Bounds::new(point(px(x), px(y)), size(px(width), px(height)))
+            }
+            None => Bounds::centered(None, bounds.size, cx),
+        };
+
+        if self.maximized {
+            WindowBounds::Maximized(bounds)
+        } else {
+            WindowBounds::Windowed(bounds)
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -39,13 +112,92 @@ impl Storage {
                 serde_json::from_reader(file)
                     .map_err(|e| e.into())
                     .map(|data: StorageData| match &data.current_track {
-                        // validate whether path still exists
-                        Some(current_track) if !current_track.get_path().exists() => StorageData {
-                            current_track: None,
-                        },
+                        // validate whether the file still exists, which for a track from a cue
+                        // sheet is the file the sheet refers to
+                        Some(current_track)
+                            if !data
+                                .current_segment
+                                .as_ref()
+                                .map_or(current_track.get_path(), |segment| &segment.file)
+                                .exists() =>
+                        {
+                            StorageData {
+                                current_track: None,
+                                current_segment: None,
+                                ..data
+                            }
+                        }
                         _ => data,
                     })
             })
             .unwrap_or_default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(name: &str, data: &StorageData) -> StorageData {
+        let path = std::env::temp_dir().join(format!(
+            "mrchat-storage-{}-{}.json",
+            std::process::id(),
+            name
+        ));
+        let storage = Storage::new(path.clone());
+
+        storage.save(data);
+        let loaded = storage.load_or_default();
+        let _ = fs::remove_file(path);
+
+        loaded
+    }
+
+    fn cue_track(file: PathBuf) -> StorageData {
+        StorageData {
+            current_track: Some(CurrentTrack::new(PathBuf::from("/music/album.cue#02"))),
+            current_segment: Some(TrackSegment {
+                file,
+                start_ms: 1000,
+                end_ms: Some(2000),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn cue_tracks_are_kept_while_their_file_exists() {
+        // any file that exists will do
+        let file = std::env::current_exe().unwrap();
+
+        let loaded = load("cue-kept", &cue_track(file.clone()));
+
+        assert_eq!(
+            loaded.current_track,
+            Some(CurrentTrack::new(PathBuf::from("/music/album.cue#02")))
+        );
+        assert_eq!(
+            loaded.current_segment.map(|segment| segment.file),
+            Some(file)
+        );
+    }
+
+    #[test]
+    fn tracks_whose_file_is_gone_are_dropped() {
+        let loaded = load(
+            "cue-dropped",
+            &cue_track(PathBuf::from("/nonexistent/album.flac")),
+        );
+        assert_eq!(loaded.current_track, None);
+        assert_eq!(loaded.current_segment, None);
+
+        let loaded = load(
+            "file-dropped",
+            &StorageData {
+                current_track: Some(CurrentTrack::new(PathBuf::from("/nonexistent/track.flac"))),
+                ..Default::default()
+            },
+        );
+        assert_eq!(loaded.current_track, None);
+    }
+}
@@ -0,0 +1,185 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::Settings;
+
+/// Identifies a file as exported settings, so that other JSON files aren't imported by mistake.
+const EXPORT_FORMAT: &str = "mrchat-settings";
+
+/// The version of the export format. Files from newer versions are refused, since they could hold
+/// settings that this version would misread.
+const EXPORT_VERSION: u32 = 1;
+
+/// The name suggested when exporting settings.
+pub const EXPORT_FILE_NAME: &str = "mrchat-settings.json";
+
+#[derive(Serialize, Deserialize)]
+struct SettingsExport {
+    format: String,
+    version: u32,
+    settings: Value,
+}
+
+/// Writes the settings out as a portable file, to be imported on another machine.
+pub fn export_settings(settings: &Settings) -> anyhow::Result<String> {
+    let export = SettingsExport {
+        format: EXPORT_FORMAT.to_string(),
+        version: EXPORT_VERSION,
+        settings: serde_json::to_value(settings)?,
+    };
+
+    Ok(serde_json::to_string_pretty(&export)?)
+}
+
+/// Reads exported settings on top of the current ones. Settings missing from the file (such as
+/// ones added after it was exported) keep their current values.
+///
+/// Library folders are specific to each machine, so the imported folders are added to the
+/// current ones, and only if they exist here.
+pub fn import_settings(current: &Settings, text: &str) -> anyhow::Result<Settings> {
+    let export: SettingsExport =
+        serde_json::from_str(text).context("the file isn't an exported settings file")?;
+
+    if export.format != EXPORT_FORMAT {
+        bail!("the file isn't an exported settings file");
+    }
+
+    if export.version > EXPORT_VERSION {
+        bail!(
+            "the file was exported by a newer version of MrChat (format version {})",
+            export.version
+        );
+    }
+
+    if !export.settings.is_object() {
+        bail!("the file doesn't contain any settings");
+    }
+
+    let mut merged = serde_json::to_value(current)?;
+    merge(&mut merged, export.settings);
+
+    let mut settings: Settings =
+        serde_json::from_value(merged).context("the file's settings are invalid")?;
+
+    let imported_paths = std::mem::take(&mut settings.scanning.paths);
+    settings.scanning.paths = merge_paths(&current.scanning.paths, imported_paths);
+
+    Ok(settings)
+}
+
+/// Merges `incoming` into `base`, key by key for objects, replacing anything else.
+fn merge(base: &mut Value, incoming: Value) {
+    match (base, incoming) {
+        (Value::Object(base), Value::Object(incoming)) => {
+            for (key, value) in incoming {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, incoming) => *base = incoming,
+    }
+}
+
+fn merge_paths(current: &[PathBuf], imported: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut paths = current.to_vec();
+
+    for path in imported {
+        if path.is_dir() && !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shared::settings::interface::ThemeMode;
+
+    use super::*;
+
+    #[test]
+    fn settings_survive_a_round_trip() {
+        let mut settings = Settings::default();
+        settings.interface.theme = ThemeMode::Light;
+        settings.playback.crossfeed = true;
+
+        let text = export_settings(&settings).unwrap();
+        let imported = import_settings(&Settings::default(), &text).unwrap();
+
+        assert_eq!(imported.interface.theme, ThemeMode::Light);
+        assert!(imported.playback.crossfeed);
+    }
+
+    #[test]
+    fn missing_settings_keep_their_current_values() {
+        let mut current = Settings::default();
+        current.playback.crossfeed = true;
+        current.scrobbling.threshold_percent = 80;
+
+        let text = r#"{
+            "format": "mrchat-settings",
+            "version": 1,
+            "settings": { "interface": { "theme": "system" }, "playback": { "balance": 0.5 } }
+        }"#;
+        let imported = import_settings(&current, text).unwrap();
+
+        assert_eq!(imported.interface.theme, ThemeMode::System);
+        assert_eq!(imported.playback.balance, 0.5);
+        assert!(imported.playback.crossfeed);
+        assert_eq!(imported.scrobbling.threshold_percent, 80);
+    }
+
+    #[test]
+    fn missing_library_folders_are_skipped() {
+        let mut current = Settings::default();
+        current.scanning.paths = vec![PathBuf::from("/music")];
+
+        let here = std::env::temp_dir();
+        let text = serde_json::json!({
+            "format": "mrchat-settings",
+            "version": 1,
+            "settings": { "scanning": { "paths": ["/no/such/folder", here] } }
+        })
+        .to_string();
+        let imported = import_settings(&current, &text).unwrap();
+
+        assert_eq!(imported.scanning.paths, vec![PathBuf::from("/music"), here]);
+    }
+
+    #[test]
+    fn other_files_are_refused() {
+        let current = Settings::default();
+
+        assert!(import_settings(&current, "not json").is_err());
+        assert!(import_settings(&current, r#"{ "interface": {} }"#).is_err());
+        assert!(
+            import_settings(
+                &current,
+                r#"{ "format": "something-else", "version": 1, "settings": {} }"#
+            )
+            .is_err()
+        );
+        assert!(
+            import_settings(
+                &current,
+                r#"{ "format": "mrchat-settings", "version": 2, "settings": {} }"#
+            )
+            .is_err()
+        );
+        assert!(
+            import_settings(
+                &current,
+                r#"{ "format": "mrchat-settings", "version": 1, "settings": [] }"#
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use gpui::{
+    Animation, AnimationExt, App, Img, IntoElement, ParentElement, Pixels, Rgba, SharedString,
+    Styled, StyledImage, div, img, pulsating_between,
+};
+
+use crate::player::ui::{
+    components::icons::{DISC, icon},
+    theme::Theme,
+};
+
+/// Album art at `path`, drawn `size` wide and high. A pulsing placeholder is shown while the image
+/// loads, and a disc in its place if it can't be loaded, as happens for albums without art.
+pub fn album_art(path: impl Into<SharedString>, size: Pixels, cx: &App) -> Img {
+    let theme = cx.global::<Theme>();
+    let background = theme.album_art_background;
+    let foreground = theme.text_secondary;
+
+    img(path.into())
+        .w(size)
+        .h(size)
+        .with_loading(move || {
+            div()
+                .size_full()
+                .bg(background)
+                .with_animation(
+                    "album-art-loading",
+                    Animation::new(Duration::from_millis(1200))
+                        .repeat()
+                        .with_easing(pulsating_between(0.4, 1.0)),
+                    |this, delta| this.opacity(delta),
+                )
+                .into_any_element()
+        })
+        .with_fallback(move || no_art(size, background, foreground).into_any_element())
+}
+
+/// The placeholder shown for albums without art.
+fn no_art(size: Pixels, background: Rgba, foreground: Rgba) -> impl IntoElement {
+    div()
+        .size_full()
+        .flex()
+        .items_center()
+        .justify_center()
+        .bg(background)
+        .child(icon(DISC).size(size * 0.45).text_color(foreground))
+}
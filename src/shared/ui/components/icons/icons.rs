@@ -29,3 +29,6 @@ pub const STAR_FILLED: &str = "!bundled:icons/star-filled.svg";
 pub const SIDEBAR: &str = "!bundled:icons/layout-sidebar.svg";
 pub const SIDEBAR_INACTIVE: &str = "!bundled:icons/layout-sidebar-inactive.svg";
 pub const SEARCH: &str = "!bundled:icons/search.svg";
+pub const RADIO: &str = "!bundled:icons/radio.svg";
+pub const SPEAKER: &str = "!bundled:icons/device-speaker.svg";
+pub const CHECK: &str = "!bundled:icons/check.svg";
@@ -309,6 +309,13 @@ impl TextInput {
         self.is_selecting = false;
     }
 
+    /// Replaces the text in the input, placing the cursor at the end. No event is emitted.
+    pub fn set_content(&mut self, content: impl Into<SharedString>) {
+        self.reset();
+        self.content = content.into();
+        self.selected_range = self.content.len()..self.content.len();
+    }
+
     pub fn next(&mut self, _: &Next, window: &mut Window, cx: &mut Context<Self>) {
         let Some(handler) = self.enriched_input_handler.as_mut() else {
             return;
@@ -1,5 +1,6 @@
 // Shared UI components
 
+pub mod album_art;
 pub mod button;
 pub mod context;
 pub mod icons;
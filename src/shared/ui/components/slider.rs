@@ -5,13 +5,18 @@ use gpui::*;
 use crate::player::ui::theme::Theme;
 
 type ClickHandler = dyn FnMut(f32, &mut Window, &mut App);
+type HoverHandler = dyn FnMut(Option<f32>, &mut Window, &mut App);
 
 pub struct Slider {
     pub(self) id: Option<ElementId>,
     pub(self) style: StyleRefinement,
     pub(self) value: f32,
     pub(self) on_change: Option<Rc<RefCell<ClickHandler>>>,
+    pub(self) on_hover: Option<Rc<RefCell<HoverHandler>>>,
     pub(self) hitbox: Option<Hitbox>,
+    pub(self) focus_handle: Option<FocusHandle>,
+    pub(self) step: f32,
+    pub(self) page_step: f32,
 }
 
 impl Slider {
@@ -29,6 +34,63 @@ impl Slider {
         self.on_change = Some(Rc::new(RefCell::new(func)));
         self
     }
+
+    /// Called with the value under the cursor whenever the mouse moves, or with `None` once the
+    /// cursor is no longer over the slider.
+    pub fn on_hover(
+        mut self,
+        func: impl FnMut(Option<f32>, &mut Window, &mut App) + 'static,
+    ) -> Self {
+        self.on_hover = Some(Rc::new(RefCell::new(func)));
+        self
+    }
+
+    /// Makes the slider focusable. While focused, the arrow keys move the value by `step`, Page Up
+    /// and Page Down move it by `page_step`, and Home and End move it to either end.
+    pub fn track_focus(mut self, handle: &FocusHandle) -> Self {
+        self.focus_handle = Some(handle.clone());
+        self
+    }
+
+    /// Sets how far the arrow keys move the value. Defaults to 0.05.
+    pub fn step(mut self, step: f32) -> Self {
+        self.step = step;
+        self
+    }
+
+    /// Sets how far Page Up and Page Down move the value. Defaults to 0.2.
+    pub fn page_step(mut self, page_step: f32) -> Self {
+        self.page_step = page_step;
+        self
+    }
+}
+
+/// How far above and below the slider clicks are still counted, since the track is usually only a
+/// few pixels tall.
+const HIT_SLOP: Pixels = px(6.0);
+
+/// Returns the value at the given horizontal position on the slider.
+fn value_at(position: Point<Pixels>, bounds: Bounds<Pixels>) -> f32 {
+    let relative_x: f32 = (position.x - bounds.origin.x).into();
+    let width: f32 = bounds.size.width.into();
+
+    (relative_x / width).clamp(0.0, 1.0)
+}
+
+/// Returns the value the slider should move to when the given key is pressed, if the key adjusts
+/// the slider.
+fn value_for_key(key: &str, value: f32, step: f32, page_step: f32) -> Option<f32> {
+    let value = match key {
+        "left" | "down" => value - step,
+        "right" | "up" => value + step,
+        "pagedown" => value - page_step,
+        "pageup" => value + page_step,
+        "home" => 0.0,
+        "end" => 1.0,
+        _ => return None,
+    };
+
+    Some(value.clamp(0.0, 1.0))
 }
 
 impl Styled for Slider {
@@ -77,9 +139,17 @@ impl Element for Slider {
         bounds: Bounds<Pixels>,
         _: &mut Self::RequestLayoutState,
         window: &mut Window,
-        _: &mut App,
+        cx: &mut App,
     ) -> Self::PrepaintState {
-        self.hitbox = Some(window.insert_hitbox(bounds, HitboxBehavior::Normal));
+        let hit_bounds = Bounds::from_corners(
+            point(bounds.left(), bounds.top() - HIT_SLOP),
+            point(bounds.right(), bounds.bottom() + HIT_SLOP),
+        );
+        self.hitbox = Some(window.insert_hitbox(hit_bounds, HitboxBehavior::Normal));
+
+        if let Some(handle) = self.focus_handle.as_ref() {
+            window.set_focus_handle(handle, cx);
+        }
     }
 
     fn paint(
@@ -95,6 +165,10 @@ impl Element for Slider {
         let theme = cx.global::<Theme>();
         let default_background = theme.slider_background;
         let default_foreground = theme.slider_foreground;
+        let focused = self
+            .focus_handle
+            .as_ref()
+            .is_some_and(|handle| handle.is_focused(window));
 
         let mut inner_bounds = bounds;
         inner_bounds.size.width = bounds.size.width * self.value;
@@ -133,7 +207,61 @@ impl Element for Slider {
             BorderStyle::Solid,
         ));
 
+        if focused {
+            window.paint_quad(quad(
+                bounds.dilate(px(2.0)),
+                corners.to_pixels(window.rem_size()),
+                transparent_black(),
+                Edges::all(px(1.0)),
+                default_foreground,
+                BorderStyle::Solid,
+            ));
+        }
+
+        if let (Some(func), Some(handle)) = (self.on_change.as_ref(), self.focus_handle.as_ref()) {
+            let func = func.clone();
+            let handle = handle.clone();
+            let (value, step, page_step) = (self.value, self.step, self.page_step);
+
+            window.on_key_event(move |ev: &KeyDownEvent, phase, window, cx| {
+                if phase != DispatchPhase::Bubble || !handle.is_focused(window) {
+                    return;
+                }
+
+                if let Some(value) = value_for_key(&ev.keystroke.key, value, step, page_step) {
+                    cx.stop_propagation();
+                    (func.borrow_mut())(value, window, cx);
+                }
+            });
+        }
+
+        if let Some(func) = self.on_hover.as_ref() {
+            let func_copy = func.clone();
+            let func = func.clone();
+            let hitbox = self.hitbox.clone().unwrap();
+
+            window.on_mouse_event(move |ev: &MouseMoveEvent, phase, window, cx| {
+                if phase != DispatchPhase::Bubble {
+                    return;
+                }
+
+                let value = hitbox
+                    .is_hovered(window)
+                    .then(|| value_at(ev.position, bounds));
+                (func.borrow_mut())(value, window, cx);
+            });
+
+            window.on_mouse_event(move |_: &MouseExitEvent, phase, window, cx| {
+                if phase == DispatchPhase::Bubble {
+                    (func_copy.borrow_mut())(None, window, cx);
+                }
+            });
+        }
+
         if let Some(func) = self.on_change.as_ref() {
+            let focus_handle = self.focus_handle.clone();
+            let hitbox = self.hitbox.clone().unwrap();
+
             window.with_optional_element_state(
                 id,
                 move |v: Option<Option<Rc<RefCell<bool>>>>, cx| {
@@ -143,34 +271,42 @@ impl Element for Slider {
 
                     let mouse_in_1 = mouse_in.clone();
 
-                    cx.on_mouse_event(move |ev: &MouseDownEvent, _, window, cx| {
-                        if !bounds.contains(&ev.position) {
+                    // a click anywhere on the track jumps straight to that position, and holding
+                    // the button down drags from there
+                    cx.on_mouse_event(move |ev: &MouseDownEvent, phase, window, cx| {
+                        if phase != DispatchPhase::Bubble
+                            || ev.button != MouseButton::Left
+                            || !hitbox.is_hovered(window)
+                        {
                             return;
                         }
 
                         window.prevent_default();
                         cx.stop_propagation();
 
-                        let relative = ev.position - bounds.origin;
-                        let relative_x: f32 = relative.x.into();
-                        let width: f32 = bounds.size.width.into();
-                        let value = (relative_x / width).clamp(0.0, 1.0);
+                        if let Some(handle) = focus_handle.as_ref() {
+                            window.focus(handle);
+                        }
 
-                        (func.borrow_mut())(value, window, cx);
+                        (func.borrow_mut())(value_at(ev.position, bounds), window, cx);
                         (*mouse_in_1.borrow_mut()) = true;
                     });
 
                     let mouse_in_2 = mouse_in.clone();
 
-                    cx.on_mouse_event(move |ev: &MouseMoveEvent, _, window, cx| {
-                        if *mouse_in_2.borrow() {
-                            let relative = ev.position - bounds.origin;
-                            let relative_x: f32 = relative.x.into();
-                            let width: f32 = bounds.size.width.into();
-                            let value = (relative_x / width).clamp(0.0, 1.0);
+                    cx.on_mouse_event(move |ev: &MouseMoveEvent, phase, window, cx| {
+                        if phase != DispatchPhase::Bubble || !*mouse_in_2.borrow() {
+                            return;
+                        }
 
-                            (func_copy.borrow_mut())(value, window, cx);
+                        // the button may have been released outside of the window, in which case
+                        // no mouse up event is received
+                        if ev.pressed_button != Some(MouseButton::Left) {
+                            (*mouse_in_2.borrow_mut()) = false;
+                            return;
                         }
+
+                        (func_copy.borrow_mut())(value_at(ev.position, bounds), window, cx);
                     });
 
                     let mouse_in_3 = mouse_in.clone();
@@ -192,6 +328,10 @@ pub fn slider() -> Slider {
         style: StyleRefinement::default(),
         value: 0.0,
         on_change: None,
+        on_hover: None,
         hitbox: None,
+        focus_handle: None,
+        step: 0.05,
+        page_step: 0.2,
     }
 }
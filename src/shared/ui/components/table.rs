@@ -10,11 +10,17 @@ use rustc_hash::FxBuildHasher;
 use table_data::{Column, TableData, TableSort};
 use table_item::TableItem;
 
-use crate::player::ui::{
-    caching::hummingbird_cache,
-    components::icons::{CHEVRON_DOWN, CHEVRON_UP, icon},
-    theme::Theme,
-    util::{create_or_retrieve_view, prune_views},
+use crate::{
+    player::ui::{
+        caching::hummingbird_cache,
+        components::icons::{CHEVRON_DOWN, CHEVRON_UP, icon},
+        theme::Theme,
+        util::{create_or_retrieve_view, prune_views},
+    },
+    shared::{
+        format,
+        settings::{SettingsGlobal, interface::AlbumArtSize},
+    },
 };
 
 type RowMap<T, C> = AHashMap<usize, Entity<TableItem<T, C>>>;
@@ -45,6 +51,15 @@ pub enum TableEvent {
     NewRows,
 }
 
+/// The size of the images shown in rows, from the user's settings.
+fn image_size(cx: &App) -> AlbumArtSize {
+    cx.global::<SettingsGlobal>()
+        .model
+        .read(cx)
+        .interface
+        .album_art_size
+}
+
 impl<T, C> EventEmitter<TableEvent> for Table<T, C>
 where
     T: TableData<C>,
@@ -101,6 +116,24 @@ where
             })
             .detach();
 
+            let settings = cx.global::<SettingsGlobal>().model.clone();
+            let mut appearance = (image_size(cx), format::locale(cx));
+
+            // the rows are recreated when their size or formatting changes, since the list needs
+            // them all the same
+            cx.observe(&settings, move |this: &mut Table<T, C>, _, cx| {
+                let new_appearance = (image_size(cx), format::locale(cx));
+
+                if new_appearance != appearance {
+                    appearance = new_appearance;
+                    this.views = cx.new(|_| AHashMap::new());
+                    this.render_counter = cx.new(|_| 0);
+
+                    cx.notify();
+                }
+            })
+            .detach();
+
             Self {
                 columns,
                 views,
@@ -167,11 +200,12 @@ where
         let render_counter = self.render_counter.clone();
         let columns = self.columns.clone();
         let handler = self.on_select.clone();
+        let size = image_size(cx);
 
         if T::has_images() {
             header = header.child(
                 div()
-                    .w(px(47.0))
+                    .w(px(size.art_size() + 25.0))
                     .h(px(36.0))
                     .pl(px(21.0))
                     .pr(px(10.0))
@@ -1,6 +1,8 @@
 use std::{fmt::Debug, hash::Hash, sync::Arc};
 
-use gpui::{App, ElementId, SharedString};
+use gpui::{AnyElement, App, ElementId, SharedString};
+
+use crate::player::ui::drag::DraggedTracks;
 use indexmap::IndexMap;
 use rustc_hash::FxBuildHasher;
 
@@ -64,4 +66,16 @@ where
 
     /// Retrieves the table ID for the row.
     fn get_table_id(&self) -> Self::Identifier;
+
+    /// Retrieves the menu shown when the row is right-clicked. Rows have no context menu by
+    /// default.
+    fn get_context_menu(&self, _cx: &mut App) -> Option<AnyElement> {
+        None
+    }
+
+    /// Retrieves the tracks carried when the row is dragged, e.g. onto the queue. Rows can't be
+    /// dragged by default.
+    fn get_drag_data(&self, _cx: &mut App) -> Option<DraggedTracks> {
+        None
+    }
 }
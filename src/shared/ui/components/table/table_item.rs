@@ -4,10 +4,14 @@ use gpui::{prelude::FluentBuilder, *};
 use indexmap::IndexMap;
 use rustc_hash::FxBuildHasher;
 
-use crate::player::ui::theme::Theme;
+use crate::player::ui::{
+    components::{album_art::album_art, context::context},
+    drag::DraggedTracks,
+    theme::Theme,
+};
 
 use super::{
-    OnSelectHandler,
+    OnSelectHandler, image_size,
     table_data::{Column, TableData},
 };
 
@@ -23,6 +27,7 @@ where
     row: Option<Arc<T>>,
     id: Option<ElementId>,
     image_path: Option<SharedString>,
+    drag_data: Option<DraggedTracks>,
 }
 
 impl<T, C> TableItem<T, C>
@@ -49,6 +54,7 @@ where
         });
 
         let image_path = row.as_ref().and_then(|row| row.get_image_path());
+        let drag_data = row.as_ref().and_then(|row| row.get_drag_data(cx));
 
         cx.new(|cx| {
             cx.observe(columns, |this: &mut TableItem<T, C>, m, cx| {
@@ -67,6 +73,7 @@ where
             Self {
                 data,
                 image_path,
+                drag_data,
                 columns: columns_read,
                 on_select,
                 id,
@@ -82,8 +89,12 @@ where
     C: Column + 'static,
 {
     fn render(&mut self, _: &mut Window, cx: &mut Context<'_, Self>) -> impl IntoElement {
+        let context_menu = self.row.as_ref().and_then(|row| row.get_context_menu(cx));
+        let drag_data = self.drag_data.clone();
         let theme = cx.global::<Theme>();
         let row_data = self.row.clone();
+        let size = image_size(cx);
+        let art_size = px(size.art_size());
         let mut row = div()
             .w_full()
             .flex()
@@ -96,13 +107,16 @@ where
                 .cursor_pointer()
                 .hover(|this| this.bg(theme.nav_button_hover))
                 .active(|this| this.bg(theme.nav_button_active))
+            })
+            .when_some(drag_data, |div, drag_data| {
+                div.on_drag(drag_data, |data, _, _, cx| cx.new(|_| data.clone()))
             });
 
         if T::has_images() {
             row = row.child(
                 div()
-                    .w(px(47.0))
-                    .h(px(36.0))
+                    .w(art_size + px(25.0))
+                    .h(px(size.row_height()))
                     .text_sm()
                     .pl(px(11.0))
                     .flex_shrink_0()
@@ -115,12 +129,12 @@ where
                     .child(
                         div()
                             .m_auto()
-                            .w(px(22.0))
-                            .h(px(22.0))
+                            .w(art_size)
+                            .h(art_size)
                             .rounded(px(3.0))
                             .bg(theme.album_art_background)
                             .when_some(self.image_path.clone(), |div, image| {
-                                div.child(img(image).w(px(22.0)).h(px(22.0)).rounded(px(3.0)))
+                                div.child(album_art(image, art_size, cx).rounded(px(3.0)))
                             }),
                     ),
             );
@@ -137,7 +151,10 @@ where
                 let column = div()
                     .w(px(width))
                     .when(T::has_images(), |div| {
-                        div.h(px(36.0)).px(px(12.0)).py(px(6.0))
+                        // centres the text alongside the art
+                        div.h(px(size.row_height()))
+                            .px(px(12.0))
+                            .py(px((size.row_height() - 24.0) / 2.0))
                     })
                     .when(!T::has_images(), |div| {
                         div.h(px(30.0))
@@ -161,6 +178,12 @@ where
             }
         }
 
-        row
+        match context_menu {
+            Some(menu) => context("table-item-context")
+                .with(row)
+                .child(div().bg(theme.elevated_background).child(menu))
+                .into_any_element(),
+            None => row.into_any_element(),
+        }
     }
 }
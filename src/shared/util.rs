@@ -1,3 +1,9 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
 use image::{Pixel, RgbaImage};
 
 pub fn rgb_to_bgr(image: &mut RgbaImage) {
@@ -7,6 +13,25 @@ pub fn rgb_to_bgr(image: &mut RgbaImage) {
     });
 }
 
+/// Writes `data` to a temporary file next to `path`, then moves it into place, so that the file is
+/// never left half-written if the application is interrupted.
+pub fn write_file_atomically(path: &Path, data: impl AsRef<[u8]>) -> std::io::Result<()> {
+    // the directory may have been removed (e.g. the cache cleared) since the file was last written
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut file = File::create(&temp_path)?;
+    file.write_all(data.as_ref())?;
+    file.sync_all()?;
+
+    fs::rename(&temp_path, path)
+}
+
 macro_rules! make_unknown_error {
     ($from:ty, $to:ty) => {
         impl From<$from> for $to {